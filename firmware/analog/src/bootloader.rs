@@ -0,0 +1,62 @@
+//! Reset into the STM32 system bootloader for a firmware update over the link.
+//!
+//! The digital board reflashes this MCU through the ROM bootloader (AN3155),
+//! which also listens on USART3 (PC10/PC11), so no probe or BOOT0 strap is
+//! needed. The option bytes pin BOOT0 low (see `ensure_bor_level`), so the
+//! ROM can only be reached by jumping to it from software.
+//!
+//! Jumping straight from the running app is not safe: the IWDG cannot be
+//! stopped once armed and the ROM never refreshes it, and the ROM expects
+//! reset-state clocks. Instead [`request_and_reset`] leaves a marker in
+//! `.uninit` RAM (kept across a system reset) and resets; [`enter_if_requested`]
+//! runs first thing in `main`, before `embassy_stm32::init`, and jumps into the
+//! ROM with the watchdog off and the clock tree untouched.
+//!
+//! The marker is single-shot: the ROM's `Go` command (or any later reset)
+//! boots the application normally.
+
+use core::mem::MaybeUninit;
+use core::ptr::{addr_of_mut, read_volatile, write_volatile};
+
+use loadlynx_protocol::ENTER_BOOTLOADER_MAGIC;
+
+/// STM32G4 system memory (ROM bootloader) vector table.
+const SYSTEM_MEMORY: u32 = 0x1FFF_0000;
+const RCC_APB2ENR_ADDR: *mut u32 = 0x4002_1060 as *mut u32;
+const RCC_APB2ENR_SYSCFGEN: u32 = 1 << 0;
+const SYSCFG_MEMRMP_ADDR: *mut u32 = 0x4001_0000 as *mut u32;
+/// MEM_MODE = 0b001: system flash aliased at 0x0000_0000, as with BOOT0 high.
+const SYSCFG_MEMRMP_SYSTEM_FLASH: u32 = 0b001;
+
+#[unsafe(link_section = ".uninit.BOOTLOADER_REQUEST")]
+static mut BOOTLOADER_REQUEST: MaybeUninit<u32> = MaybeUninit::uninit();
+
+fn request_marker() -> *mut u32 {
+    addr_of_mut!(BOOTLOADER_REQUEST).cast::<u32>()
+}
+
+/// Arm the marker and reset; the next boot lands in the ROM bootloader.
+pub fn request_and_reset() -> ! {
+    unsafe { write_volatile(request_marker(), ENTER_BOOTLOADER_MAGIC) };
+    cortex_m::peripheral::SCB::sys_reset()
+}
+
+/// Jump into the ROM bootloader if the previous run asked for it.
+///
+/// Must run before any clock, interrupt or peripheral setup.
+pub fn enter_if_requested() {
+    let marker = request_marker();
+    if unsafe { read_volatile(marker) } != ENTER_BOOTLOADER_MAGIC {
+        return;
+    }
+    unsafe {
+        write_volatile(marker, 0);
+        write_volatile(
+            RCC_APB2ENR_ADDR,
+            read_volatile(RCC_APB2ENR_ADDR) | RCC_APB2ENR_SYSCFGEN,
+        );
+        write_volatile(SYSCFG_MEMRMP_ADDR, SYSCFG_MEMRMP_SYSTEM_FLASH);
+        (*cortex_m::peripheral::SCB::PTR).vtor.write(SYSTEM_MEMORY);
+        cortex_m::asm::bootload(SYSTEM_MEMORY as *const u32)
+    }
+}
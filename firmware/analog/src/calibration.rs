@@ -3,11 +3,18 @@
 //! This module is `no_std` and designed to be host-testable via the package
 //! library target (`src/lib.rs`).
 
+use loadlynx_calibration_format::TEMP_COMP_RECORD_LEN;
+pub use loadlynx_calibration_format::{TempComp, TempSensor};
 use loadlynx_protocol::crc16_ccitt_false;
 
 pub const MAX_POINTS: usize = 24;
 pub const POINTS_PER_CHUNK: usize = 3;
-pub const MAX_CHUNKS: usize = 8;
+// 8 point chunks + the fmt v4 temperature-compensation chunk.
+pub const MAX_CHUNKS: usize = 9;
+
+/// First calibration format carrying a per-curve temperature-compensation
+/// chunk (see `libs/calibration-format` for the wire layout).
+pub const FMT_VERSION_TEMP_COMP: u8 = 4;
 
 // NOTE: The firmware no longer uses a fixed 0.5 A reference point for output
 // mapping; DAC codes are derived either from user calibration samples
@@ -21,10 +28,29 @@ pub struct CalPoint {
     pub meas_physical: i32,
 }
 
+/// Temperatures available to the compensation, in m°C (same values as the
+/// `FastStatus` fields of the same name).
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Temps {
+    pub sink_core_mc: i32,
+    pub mcu_mc: i32,
+}
+
+impl Temps {
+    /// Reading of the sensor a curve's [`TempComp`] is referenced to.
+    pub const fn of(&self, sensor: TempSensor) -> i32 {
+        match sensor {
+            TempSensor::SinkCore => self.sink_core_mc,
+            TempSensor::Mcu => self.mcu_mc,
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug, Default)]
 pub struct CalCurve {
     pub points: [CalPoint; MAX_POINTS],
     pub len: u8,
+    pub temp_comp: TempComp,
 }
 
 impl CalCurve {
@@ -36,6 +62,7 @@ impl CalCurve {
                 meas_physical: 0,
             }; MAX_POINTS],
             len: 0,
+            temp_comp: TempComp::NONE,
         }
     }
 
@@ -86,6 +113,7 @@ pub enum CalError {
     InconsistentHeader,
     TooManyPoints,
     TooManyChunks,
+    InvalidTempComp,
 }
 
 /// Prepare a curve in-place: sort by raw, dedup by raw, and validate meas monotonicity.
@@ -176,6 +204,16 @@ pub fn piecewise_linear(points: &[CalPoint], raw: i16) -> Result<i32, CalError>
     Err(CalError::InvalidCurve)
 }
 
+/// [`piecewise_linear`] followed by the curve's temperature compensation.
+pub fn piecewise_linear_compensated(
+    curve: &CalCurve,
+    raw: i16,
+    temps: Temps,
+) -> Result<i32, CalError> {
+    let tc = &curve.temp_comp;
+    piecewise_linear(curve.as_slice(), raw).map(|m| tc.correct(m, temps.of(tc.sensor)))
+}
+
 pub fn preserve_nonzero_uncalibrated(
     calibrated: i32,
     uncalibrated: i32,
@@ -252,6 +290,20 @@ pub fn inverse_piecewise(points: &[CalPoint], meas_des: i32) -> Result<i16, CalE
     Err(CalError::InvalidCurve)
 }
 
+/// Temperature-aware [`inverse_piecewise`]: undo the compensation first so
+/// the returned raw target yields `meas_des` at the current temperature.
+pub fn inverse_piecewise_compensated(
+    curve: &CalCurve,
+    meas_des: i32,
+    temps: Temps,
+) -> Result<i16, CalError> {
+    let tc = &curve.temp_comp;
+    inverse_piecewise(
+        curve.as_slice(),
+        tc.uncorrect(meas_des, temps.of(tc.sensor)),
+    )
+}
+
 fn inverse_segment(a: CalPoint, b: CalPoint, meas_des: i64) -> Result<i16, CalError> {
     let meas_a = a.meas_physical as i64;
     let meas_b = b.meas_physical as i64;
@@ -389,9 +441,10 @@ struct PendingCurve {
     hw_rev: u8,
     total_chunks: u8,
     total_points: u8,
-    received_chunks_mask: u8,
+    received_chunks_mask: u16,
     filled: [bool; MAX_POINTS],
     points: [CalPoint; MAX_POINTS],
+    temp_comp: TempComp,
     active: bool,
 }
 
@@ -409,6 +462,7 @@ impl PendingCurve {
                 raw_dac_code: 0,
                 meas_physical: 0,
             }; MAX_POINTS],
+            temp_comp: TempComp::NONE,
             active: false,
         }
     }
@@ -475,7 +529,7 @@ impl CalibrationState {
         let total_chunks = payload[4];
         let total_points = payload[5];
 
-        if fmt_version != 1 && fmt_version != 2 && fmt_version != 3 {
+        if !(1..=FMT_VERSION_TEMP_COMP).contains(&fmt_version) {
             return Err(CalError::VersionMismatch);
         }
         if total_points as usize > MAX_POINTS {
@@ -484,6 +538,13 @@ impl CalibrationState {
         if total_chunks == 0 || total_chunks as usize > MAX_CHUNKS {
             return Err(CalError::TooManyChunks);
         }
+        // v4 sends the temperature-compensation record as one extra chunk
+        // after the point chunks.
+        let point_chunks = (total_points as usize).div_ceil(POINTS_PER_CHUNK).max(1);
+        let has_temp_comp = fmt_version >= FMT_VERSION_TEMP_COMP;
+        if has_temp_comp && total_chunks as usize != point_chunks + 1 {
+            return Err(CalError::InconsistentHeader);
+        }
         if chunk_index >= total_chunks {
             return Err(CalError::InvalidChunk);
         }
@@ -510,8 +571,21 @@ impl CalibrationState {
             return Err(CalError::InconsistentHeader);
         }
 
+        if has_temp_comp && chunk_index as usize == point_chunks {
+            match TempComp::read_record(&payload[8..8 + TEMP_COMP_RECORD_LEN]) {
+                Some(tc) => pending.temp_comp = tc,
+                None => {
+                    pending.reset();
+                    return Err(CalError::InvalidTempComp);
+                }
+            }
+        }
+
         // Unpack up to 3 points from this chunk.
         for point_off in 0..POINTS_PER_CHUNK {
+            if has_temp_comp && chunk_index as usize == point_chunks {
+                break;
+            }
             let overall = (chunk_index as usize) * POINTS_PER_CHUNK + point_off;
             if overall >= total_points as usize {
                 continue;
@@ -533,7 +607,7 @@ impl CalibrationState {
             pending.filled[overall] = true;
         }
 
-        pending.received_chunks_mask |= 1u8 << chunk_index;
+        pending.received_chunks_mask |= 1u16 << chunk_index;
 
         // Check completion.
         let all_chunks = pending.received_chunks_mask.count_ones() as u8 == total_chunks;
@@ -546,6 +620,7 @@ impl CalibrationState {
                     let mut curve = CalCurve::empty();
                     curve.len = new_len as u8;
                     curve.points[..new_len].copy_from_slice(&pts[..new_len]);
+                    curve.temp_comp = pending.temp_comp;
                     self.active_curves[pending_idx] = curve;
                     self.active_valid[pending_idx] = true;
                     pending.reset();
@@ -674,6 +749,126 @@ mod tests {
         assert_eq!(inverse_piecewise(&points, 3000).unwrap(), 3000);
     }
 
+    const TEMPS_REF: Temps = Temps {
+        sink_core_mc: 25_000,
+        mcu_mc: 25_000,
+    };
+
+    fn curve_with(points: &[CalPoint], tc: TempComp) -> CalCurve {
+        let mut curve = CalCurve::empty();
+        curve.points[..points.len()].copy_from_slice(points);
+        curve.len = points.len() as u8;
+        curve.temp_comp = tc;
+        curve
+    }
+
+    #[test]
+    fn temp_comp_none_is_identity() {
+        let curve = curve_with(&[pt(1000, 1000), pt(3000, 5000)], TempComp::NONE);
+        let hot = Temps {
+            sink_core_mc: 80_000,
+            mcu_mc: 60_000,
+        };
+        assert_eq!(
+            piecewise_linear_compensated(&curve, 2000, hot).unwrap(),
+            3000
+        );
+        assert_eq!(
+            inverse_piecewise_compensated(&curve, 3000, hot).unwrap(),
+            2000
+        );
+    }
+
+    #[test]
+    fn temp_comp_gain_and_offset_correct_reading() {
+        // +1000 ppm/°C and +100 µA/°C referenced to 25 °C on the sink sensor.
+        let tc = TempComp {
+            sensor: TempSensor::SinkCore,
+            t_ref_mc: 25_000,
+            gain_ppm_per_c: 1_000,
+            offset_micro_per_c: 100,
+        };
+        let curve = curve_with(&[pt(0, 0), pt(10_000, 10_000)], tc);
+        // At t_ref the curve is used as-is.
+        assert_eq!(
+            piecewise_linear_compensated(&curve, 5_000, TEMPS_REF).unwrap(),
+            5_000
+        );
+        // At 35 °C: reading = true * 1.01 + 1 mA -> true = (5051 - 1) / 1.01 = 5000.
+        let warm = Temps {
+            sink_core_mc: 35_000,
+            mcu_mc: 0,
+        };
+        assert_eq!(
+            piecewise_linear_compensated(&curve, 5_051, warm).unwrap(),
+            5_000
+        );
+        // The inverse targets the drifted reading.
+        assert_eq!(
+            inverse_piecewise_compensated(&curve, 5_000, warm).unwrap(),
+            5_051
+        );
+    }
+
+    fn encode_chunk(index: u8, payload: [u8; 32]) -> ([u8; 32], u16) {
+        let mut buf = [0u8; 33];
+        buf[0] = index;
+        buf[1..].copy_from_slice(&payload);
+        (payload, crc16_ccitt_false(&buf))
+    }
+
+    #[test]
+    fn ingest_v4_applies_temp_comp_chunk() {
+        let mut state = CalibrationState::new();
+        let kind = CurveKind::CurrentCh1 as u8;
+
+        let mut p0 = [0u8; 32];
+        p0[..6].copy_from_slice(&[4, 42, kind, 0, 2, 2]);
+        p0[8..10].copy_from_slice(&100i16.to_le_bytes());
+        p0[12..16].copy_from_slice(&10i32.to_le_bytes());
+        p0[16..18].copy_from_slice(&2000i16.to_le_bytes());
+        p0[20..24].copy_from_slice(&2000i32.to_le_bytes());
+        let (payload, crc) = encode_chunk(0, p0);
+        assert_eq!(state.ingest_cal_write(0, &payload, crc), Ok(None));
+
+        let mut p1 = [0u8; 32];
+        p1[..6].copy_from_slice(&[4, 42, kind, 1, 2, 2]);
+        p1[8] = TempSensor::Mcu as u8;
+        p1[12..16].copy_from_slice(&30_000i32.to_le_bytes());
+        p1[16..20].copy_from_slice(&(-20i32).to_le_bytes());
+        p1[20..24].copy_from_slice(&500i32.to_le_bytes());
+        let (payload, crc) = encode_chunk(1, p1);
+        assert_eq!(
+            state.ingest_cal_write(1, &payload, crc),
+            Ok(Some(CurveKind::CurrentCh1))
+        );
+
+        let curve = state.snapshot()[CurveKind::CurrentCh1.index()];
+        assert_eq!(curve.len, 2);
+        assert_eq!(
+            curve.temp_comp,
+            TempComp {
+                sensor: TempSensor::Mcu,
+                t_ref_mc: 30_000,
+                gain_ppm_per_c: -20,
+                offset_micro_per_c: 500,
+            }
+        );
+    }
+
+    #[test]
+    fn ingest_v4_rejects_bad_temp_comp() {
+        let mut state = CalibrationState::new();
+        let mut p = [0u8; 32];
+        p[..6].copy_from_slice(&[4, 42, CurveKind::VLocal as u8, 1, 2, 1]);
+        p[8] = 9; // unknown sensor
+        let (payload, crc) = encode_chunk(1, p);
+        assert_eq!(
+            state.ingest_cal_write(1, &payload, crc),
+            Err(CalError::InvalidTempComp)
+        );
+    }
+
     #[test]
     fn prepare_rejects_non_monotonic_meas() {
         let mut pts = [CalPoint::default(); MAX_POINTS];
@@ -0,0 +1,253 @@
+//! CH1/CH2 current sharing: target split, FET temperature estimate and
+//! sharing-health warnings.
+//!
+//! The split follows [`ProtectionConfig::share_policy`](loadlynx_protocol::ProtectionConfig):
+//!
+//! - **auto**: CH1 alone below `i_share_threshold_ma`, both channels from
+//!   there on (the historical behaviour);
+//! - **dual**: both channels at every target;
+//! - **ch1_only / ch2_only**: one channel carries everything, for debugging a
+//!   single FET. The SOA clamp must not move current onto the idle channel
+//!   then ([`policy_allows_rebalance`]).
+//!
+//! Only the heatsink core NTC exists, so the per-FET temperature is an
+//! estimate: sink core plus the channel's dissipation (`V_local × I_ch`) over
+//! [`FET_RTH_JS_MC_PER_W`].
+//!
+//! Warnings (`CHANNEL_WARN_*` in `FastStatus.channel_warnings`) are evaluated
+//! once per status window and debounced like the sense diagnostics:
+//!
+//! - **imbalance**: both channels are driven but their tracking errors
+//!   (measured − target) differ by more than [`IMBALANCE_MIN_MA`] or
+//!   [`IMBALANCE_PERMILLE`] of the mean channel target;
+//! - **stray**: a channel with a zero target conducts more than
+//!   [`STRAY_MIN_MA`] (shorted FET, DAC/op-amp offset), output on or off.
+//!
+//! Advisory only: nothing here changes the output. Host-testable via the
+//! package library target.
+
+use crate::sense_diag::Debounce;
+use loadlynx_protocol::{
+    CHANNEL_WARN_CH1_STRAY, CHANNEL_WARN_CH2_STRAY, CHANNEL_WARN_IMBALANCE, SHARE_POLICY_CH1_ONLY,
+    SHARE_POLICY_CH2_ONLY, SHARE_POLICY_DUAL,
+};
+
+/// IRFP4468 R_thJC (0.29 °C/W max) + greased case-to-sink (0.24 °C/W).
+pub const FET_RTH_JS_MC_PER_W: i64 = 530;
+
+/// Minimum per-channel target for the imbalance check.
+pub const IMBALANCE_MIN_TARGET_MA: i32 = 200;
+/// Tracking-error difference that always counts as imbalance.
+pub const IMBALANCE_MIN_MA: i32 = 150;
+/// Relative imbalance allowance (of the mean channel target).
+pub const IMBALANCE_PERMILLE: i32 = 150;
+/// Current on a zero-target channel that counts as stray.
+pub const STRAY_MIN_MA: i32 = 150;
+
+/// Imbalance must persist longer than a setpoint step takes to settle.
+pub const IMBALANCE_ENTER_WINDOWS: u8 = 10;
+pub const STRAY_ENTER_WINDOWS: u8 = 5;
+pub const EXIT_WINDOWS: u8 = 5;
+
+/// Split a total current target into `(ch1, ch2)` per `SHARE_POLICY_*`.
+/// Unknown policies fall back to auto. Odd mA go to CH1.
+pub fn split_target(total_ma: i32, policy: u8, threshold_ma: i32) -> (i32, i32) {
+    let dual = |total: i32| {
+        let half = total / 2;
+        (total - half, half)
+    };
+    match policy {
+        SHARE_POLICY_DUAL => dual(total_ma),
+        SHARE_POLICY_CH1_ONLY => (total_ma, 0),
+        SHARE_POLICY_CH2_ONLY => (0, total_ma),
+        _ if total_ma < threshold_ma => (total_ma, 0),
+        _ => dual(total_ma),
+    }
+}
+
+/// Whether the SOA clamp may move excess current to the other channel.
+pub fn policy_allows_rebalance(policy: u8) -> bool {
+    !matches!(policy, SHARE_POLICY_CH1_ONLY | SHARE_POLICY_CH2_ONLY)
+}
+
+/// Estimated FET junction temperature (m°C) for one channel.
+pub fn fet_temp_mc(sink_core_mc: i32, vds_mv: i32, i_ch_ma: i32) -> i32 {
+    let p_mw = vds_mv.max(0) as i64 * i_ch_ma.max(0) as i64 / 1_000;
+    (sink_core_mc as i64 + p_mw * FET_RTH_JS_MC_PER_W / 1_000)
+        .clamp(i32::MIN as i64, i32::MAX as i64) as i32
+}
+
+/// Status-window inputs: per-channel targets after the SOA clamp and the
+/// smoothed measured currents.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Sample {
+    pub target_ch1_ma: i32,
+    pub target_ch2_ma: i32,
+    pub i_ch1_ma: i32,
+    pub i_ch2_ma: i32,
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ChannelMonitor {
+    imbalance: Debounce,
+    stray_ch1: Debounce,
+    stray_ch2: Debounce,
+}
+
+impl ChannelMonitor {
+    pub const fn new() -> Self {
+        Self {
+            imbalance: Debounce::new(),
+            stray_ch1: Debounce::new(),
+            stray_ch2: Debounce::new(),
+        }
+    }
+
+    /// Evaluate one status window; returns the `CHANNEL_WARN_*` bitmask.
+    pub fn update(&mut self, s: Sample) -> u32 {
+        let both_driven = s.target_ch1_ma >= IMBALANCE_MIN_TARGET_MA
+            && s.target_ch2_ma >= IMBALANCE_MIN_TARGET_MA;
+        let mean_target_ma = (s.target_ch1_ma + s.target_ch2_ma) / 2;
+        let allowed_ma = IMBALANCE_MIN_MA.max(mean_target_ma * IMBALANCE_PERMILLE / 1_000);
+        let skew_ma = ((s.i_ch1_ma - s.target_ch1_ma) - (s.i_ch2_ma - s.target_ch2_ma)).abs();
+        self.imbalance.update_windows(
+            both_driven && skew_ma > allowed_ma,
+            IMBALANCE_ENTER_WINDOWS,
+            EXIT_WINDOWS,
+        );
+
+        self.stray_ch1.update_windows(
+            s.target_ch1_ma == 0 && s.i_ch1_ma > STRAY_MIN_MA,
+            STRAY_ENTER_WINDOWS,
+            EXIT_WINDOWS,
+        );
+        self.stray_ch2.update_windows(
+            s.target_ch2_ma == 0 && s.i_ch2_ma > STRAY_MIN_MA,
+            STRAY_ENTER_WINDOWS,
+            EXIT_WINDOWS,
+        );
+
+        self.warnings()
+    }
+
+    pub fn warnings(&self) -> u32 {
+        let mut flags = 0;
+        if self.imbalance.active {
+            flags |= CHANNEL_WARN_IMBALANCE;
+        }
+        if self.stray_ch1.active {
+            flags |= CHANNEL_WARN_CH1_STRAY;
+        }
+        if self.stray_ch2.active {
+            flags |= CHANNEL_WARN_CH2_STRAY;
+        }
+        flags
+    }
+
+    /// Drop all warnings, e.g. while calibration drives the channels directly.
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use loadlynx_protocol::SHARE_POLICY_AUTO;
+
+    fn run(monitor: &mut ChannelMonitor, sample: Sample, windows: u8) -> u32 {
+        let mut flags = 0;
+        for _ in 0..windows {
+            flags = monitor.update(sample);
+        }
+        flags
+    }
+
+    #[test]
+    fn split_follows_policy() {
+        assert_eq!(split_target(1_500, SHARE_POLICY_AUTO, 2_000), (1_500, 0));
+        assert_eq!(
+            split_target(3_001, SHARE_POLICY_AUTO, 2_000),
+            (1_501, 1_500)
+        );
+        assert_eq!(split_target(1_500, SHARE_POLICY_DUAL, 2_000), (750, 750));
+        assert_eq!(
+            split_target(4_000, SHARE_POLICY_CH1_ONLY, 2_000),
+            (4_000, 0)
+        );
+        assert_eq!(
+            split_target(4_000, SHARE_POLICY_CH2_ONLY, 2_000),
+            (0, 4_000)
+        );
+        // Unknown values behave like auto.
+        assert_eq!(split_target(1_000, 9, 2_000), (1_000, 0));
+        assert!(policy_allows_rebalance(SHARE_POLICY_DUAL));
+        assert!(!policy_allows_rebalance(SHARE_POLICY_CH2_ONLY));
+    }
+
+    #[test]
+    fn fet_temperature_adds_dissipation_over_rth() {
+        // 12 V × 5 A = 60 W → +31.8 °C over a 40 °C sink.
+        assert_eq!(fet_temp_mc(40_000, 12_000, 5_000), 71_800);
+        assert_eq!(fet_temp_mc(40_000, 12_000, 0), 40_000);
+        assert_eq!(fet_temp_mc(40_000, -500, 1_000), 40_000);
+    }
+
+    #[test]
+    fn balanced_sharing_reports_nothing() {
+        let mut monitor = ChannelMonitor::new();
+        let sample = Sample {
+            target_ch1_ma: 2_000,
+            target_ch2_ma: 2_000,
+            i_ch1_ma: 2_060,
+            i_ch2_ma: 1_950,
+        };
+        assert_eq!(run(&mut monitor, sample, 30), 0);
+    }
+
+    #[test]
+    fn imbalance_needs_persistence_and_clears() {
+        let mut monitor = ChannelMonitor::new();
+        let skewed = Sample {
+            target_ch1_ma: 2_000,
+            target_ch2_ma: 2_000,
+            i_ch1_ma: 2_400,
+            i_ch2_ma: 1_600,
+        };
+        assert_eq!(run(&mut monitor, skewed, IMBALANCE_ENTER_WINDOWS - 1), 0);
+        assert_eq!(monitor.update(skewed), CHANNEL_WARN_IMBALANCE);
+
+        let balanced = Sample {
+            i_ch1_ma: 2_000,
+            i_ch2_ma: 2_000,
+            ..skewed
+        };
+        assert_eq!(run(&mut monitor, balanced, EXIT_WINDOWS), 0);
+
+        // Single-channel operation never counts as imbalance.
+        let single = Sample {
+            target_ch1_ma: 1_000,
+            target_ch2_ma: 0,
+            i_ch1_ma: 1_000,
+            i_ch2_ma: 0,
+        };
+        assert_eq!(run(&mut monitor, single, 30), 0);
+    }
+
+    #[test]
+    fn stray_current_on_idle_channel() {
+        let mut monitor = ChannelMonitor::new();
+        let shorted_ch2 = Sample {
+            target_ch1_ma: 0,
+            target_ch2_ma: 0,
+            i_ch1_ma: 3,
+            i_ch2_ma: 900,
+        };
+        assert_eq!(
+            run(&mut monitor, shorted_ch2, STRAY_ENTER_WINDOWS),
+            CHANNEL_WARN_CH2_STRAY
+        );
+        monitor.reset();
+        assert_eq!(monitor.warnings(), 0);
+    }
+}
@@ -0,0 +1,145 @@
+//! Charge / energy counters integrated at control-loop rate.
+//!
+//! Every control tick with the output enabled adds the instantaneous total
+//! current and V_main power times the measured loop period, so pulsed loads
+//! are counted exactly rather than sampled at the 20 Hz status rate, and an
+//! overrunning loop (the scheduler drops missed ticks) does not lose time.
+//! Sums are kept in `mA·µs` / `mW·µs` and converted to µAh / µWh when a
+//! status frame is built; at full-scale power the `u64` energy sum lasts
+//! about a year of continuous operation.
+//!
+//! Counters run from analog boot or the last `ResetCounters` request, which
+//! selects them with `COUNTER_*` bits; nothing is persisted. Host-testable via
+//! the package library target.
+
+use loadlynx_protocol::{COUNTER_CHARGE, COUNTER_ENERGY, COUNTER_TIME};
+
+/// `mA·µs` per µAh (equally `mW·µs` per µWh).
+const US_PER_MICRO_HOUR: u64 = 3_600_000_000 / 1_000;
+/// Longest single step credited; guards against a stale loop timestamp.
+pub const MAX_STEP_US: u32 = 100_000;
+
+/// Running counters since the last reset.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct EnergyCounters {
+    charge_ma_us: u64,
+    energy_mw_us: u64,
+    on_us: u64,
+    reset_at_ms: u32,
+}
+
+impl EnergyCounters {
+    pub const fn new(now_ms: u32) -> Self {
+        Self {
+            charge_ma_us: 0,
+            energy_mw_us: 0,
+            on_us: 0,
+            reset_at_ms: now_ms,
+        }
+    }
+
+    /// Account one control tick of `dt_us`. Nothing passes through the load
+    /// while the output is off, so sense offsets are not integrated then.
+    pub fn tick(&mut self, output_enabled: bool, dt_us: u32, i_total_ma: i32, p_mw: u32) {
+        if !output_enabled {
+            return;
+        }
+        let dt_us = dt_us.min(MAX_STEP_US) as u64;
+        self.charge_ma_us = self
+            .charge_ma_us
+            .wrapping_add(i_total_ma.max(0) as u64 * dt_us);
+        self.energy_mw_us = self.energy_mw_us.wrapping_add(p_mw as u64 * dt_us);
+        self.on_us = self.on_us.wrapping_add(dt_us);
+    }
+
+    /// Zero the totals selected by `mask` (`COUNTER_*`).
+    pub fn reset(&mut self, mask: u8, now_ms: u32) {
+        if mask & COUNTER_CHARGE != 0 {
+            self.charge_ma_us = 0;
+        }
+        if mask & COUNTER_ENERGY != 0 {
+            self.energy_mw_us = 0;
+        }
+        if mask & COUNTER_TIME != 0 {
+            self.on_us = 0;
+            self.reset_at_ms = now_ms;
+        }
+    }
+
+    /// Charge since reset in µAh.
+    pub fn charge_uah(&self) -> u64 {
+        self.charge_ma_us / US_PER_MICRO_HOUR
+    }
+
+    /// Energy since reset in µWh.
+    pub fn energy_uwh(&self) -> u64 {
+        self.energy_mw_us / US_PER_MICRO_HOUR
+    }
+
+    /// Time since the timers were reset (ms), output on or off.
+    pub fn elapsed_ms(&self, now_ms: u32) -> u32 {
+        now_ms.wrapping_sub(self.reset_at_ms)
+    }
+
+    /// Time with the output enabled since the timers were reset (ms).
+    pub fn on_ms(&self) -> u32 {
+        (self.on_us / 1_000).min(u32::MAX as u64) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use loadlynx_protocol::COUNTER_ALL;
+
+    #[test]
+    fn integrates_charge_energy_and_on_time() {
+        let mut counters = EnergyCounters::new(1_000);
+        // 2 A at 12 V for 3.6 s → 2 mAh, 24 mWh.
+        for _ in 0..36_000 {
+            counters.tick(true, 100, 2_000, 24_000);
+        }
+        // Output off: offsets are not counted, but wall time still runs.
+        for _ in 0..10_000 {
+            counters.tick(false, 100, 7, 80);
+        }
+        assert_eq!(counters.charge_uah(), 2_000);
+        assert_eq!(counters.energy_uwh(), 24_000);
+        assert_eq!(counters.on_ms(), 3_600);
+        assert_eq!(counters.elapsed_ms(5_600), 4_600);
+    }
+
+    #[test]
+    fn pulsed_load_and_loop_overruns_keep_exact_totals() {
+        let mut counters = EnergyCounters::new(0);
+        // 10 % duty 5 A pulses: average 500 mA over 7.2 s → 1 mAh.
+        for tick in 0..72_000u32 {
+            let i_ma = if tick % 10 == 0 { 5_000 } else { 0 };
+            counters.tick(true, 100, i_ma, 0);
+        }
+        assert_eq!(counters.charge_uah(), 1_000);
+
+        // An overrun tick credits its real length, capped at MAX_STEP_US.
+        counters.tick(true, 3_600_000, 1_000, 0);
+        assert_eq!(counters.charge_uah(), 1_000 + 27);
+        counters.tick(true, 360, -300, 0);
+        assert_eq!(counters.charge_uah(), 1_000 + 27);
+    }
+
+    #[test]
+    fn reset_follows_selectors() {
+        let mut counters = EnergyCounters::new(0);
+        for _ in 0..36_000 {
+            counters.tick(true, 100, 1_000, 5_000);
+        }
+        counters.reset(COUNTER_ENERGY, 100);
+        assert_eq!(counters.charge_uah(), 1_000);
+        assert_eq!(counters.energy_uwh(), 0);
+        assert_eq!(counters.elapsed_ms(200), 200);
+
+        counters.reset(COUNTER_ALL, u32::MAX - 9);
+        assert_eq!(counters.charge_uah(), 0);
+        assert_eq!(counters.on_ms(), 0);
+        assert_eq!(counters.elapsed_ms(20), 30);
+    }
+}
@@ -1,6 +1,10 @@
 #![no_std]
 
 pub mod calibration;
+pub mod channel_share;
+pub mod counters;
+pub mod sense_diag;
+pub mod soa;
 
 #[cfg(test)]
 extern crate std;
@@ -23,26 +23,41 @@ use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, channel::Channe
 use embassy_time::{Duration, Instant, Timer};
 use libm::logf;
 use loadlynx_protocol::{
-    CRC_LEN, CalKind, Error as ProtocolError, FAST_STATUS_MODE_CC, FAST_STATUS_MODE_CP,
-    FAST_STATUS_MODE_CV, FAULT_MCU_OVER_TEMP, FAULT_OVERCURRENT, FAULT_OVERVOLTAGE,
-    FAULT_SINK_OVER_TEMP, FLAG_IS_ACK, FastStatus, FrameHeader, HEADER_LEN, Hello, LoadMode,
-    MSG_CAL_MODE, MSG_SET_MODE, MSG_SET_POINT, PD_MAX_FIXED_PDOS, PdStatus,
-    STATE_FLAG_CURRENT_LIMITED, STATE_FLAG_ENABLED, STATE_FLAG_LINK_GOOD, STATE_FLAG_POWER_LIMITED,
-    STATE_FLAG_REMOTE_ACTIVE, STATE_FLAG_UV_LATCHED, SlipDecoder, SoftReset, SoftResetReason,
-    decode_cal_mode_frame, decode_cal_write_frame, decode_frame, decode_limit_profile_frame,
-    decode_pd_sink_request_frame, decode_set_enable_frame, decode_set_mode_frame,
-    decode_set_point_frame, decode_soft_reset_frame, encode_ack_only_frame,
-    encode_fast_status_frame, encode_hello_frame, encode_pd_status_frame, encode_soft_reset_frame,
-    slip_encode,
+    CRC_LEN, CalKind, ENTER_BOOTLOADER_MAGIC, Error as ProtocolError, FAST_STATUS_MODE_CC,
+    FAST_STATUS_MODE_CP, FAST_STATUS_MODE_CV, FAULT_ALL, FAULT_MCU_OVER_TEMP, FAULT_OVERCURRENT,
+    FAULT_OVERVOLTAGE, FAULT_POLICY_ALL, FAULT_POLICY_UV_LATCH, FAULT_SINK_OVER_TEMP, FLAG_IS_ACK,
+    FastStatus, FrameHeader, HEADER_LEN, Hello, LoadMode, MSG_CAL_MODE, MSG_CLEAR_FAULTS,
+    MSG_ENTER_BOOTLOADER, MSG_PROTECTION_CONFIG, MSG_RESET_COUNTERS, MSG_SET_MODE, MSG_SET_POINT,
+    PD_MAX_FIXED_PDOS, PdStatus, ProtectionConfig, STATE_FLAG_CURRENT_LIMITED, STATE_FLAG_ENABLED,
+    STATE_FLAG_LINK_GOOD, STATE_FLAG_POWER_LIMITED, STATE_FLAG_REMOTE_ACTIVE,
+    STATE_FLAG_SOA_LIMITED, STATE_FLAG_UV_LATCHED, SlipDecoder, SoftReset, SoftResetReason,
+    decode_cal_mode_frame, decode_cal_write_frame, decode_clear_faults_frame,
+    decode_enter_bootloader_frame, decode_frame, decode_limit_profile_frame,
+    decode_pd_sink_request_frame, decode_protection_config_frame, decode_reset_counters_frame,
+    decode_set_enable_frame, decode_set_mode_frame, decode_set_point_frame,
+    decode_soft_reset_frame, encode_ack_only_frame, encode_fast_status_frame, encode_hello_frame,
+    encode_pd_status_frame, encode_soft_reset_frame, slip_encode,
 };
 use static_cell::StaticCell;
 
+mod bootloader;
 mod calibration;
+mod channel_share;
+mod counters;
 mod pd;
+mod sense_diag;
+mod soa;
 use calibration::{
-    CalCurve, CalibrationState, CurveKind, inverse_piecewise, mv_to_raw_100uv, piecewise_linear,
-    preserve_nonzero_uncalibrated, raw_100uv_to_dac_code_calibrated, raw_100uv_to_dac_code_vref,
+    CalCurve, CalibrationState, CurveKind, Temps, inverse_piecewise_compensated, mv_to_raw_100uv,
+    piecewise_linear, piecewise_linear_compensated, preserve_nonzero_uncalibrated,
+    raw_100uv_to_dac_code_calibrated, raw_100uv_to_dac_code_vref,
 };
+use channel_share::{ChannelMonitor, Sample as ChannelSample};
+use counters::EnergyCounters;
+use loadlynx_protocol::link_safe::{
+    self, Event as LinkSafeEvent, LinkSafing, Reason as LinkSafeReason, State as LinkSafeState,
+};
+use sense_diag::{Sample as SenseSample, SenseDiag};
 
 // STM32G431 VREFBUF 基址/寄存器地址（同 pd-sink-stm32g431cbu6-rs 工程）
 const VREFBUF_BASE: u32 = 0x4001_0030;
@@ -56,6 +71,9 @@ const FLASH_OPTR_ADDR: *mut u32 = (FLASH_BASE + 0x20) as *mut u32;
 const IWDG_KR_ADDR: *mut u32 = 0x4000_3000 as *mut u32;
 const IWDG_PR_ADDR: *mut u32 = 0x4000_3004 as *mut u32;
 const IWDG_RLR_ADDR: *mut u32 = 0x4000_3008 as *mut u32;
+const RCC_CSR_ADDR: *mut u32 = 0x4002_1094 as *mut u32;
+const RCC_CSR_RMVF: u32 = 1 << 23;
+const RCC_CSR_IWDGRSTF: u32 = 1 << 29;
 
 bind_interrupts!(struct Irqs {
     USART3 => stm32::usart::InterruptHandler<stm32::peripherals::USART3>;
@@ -78,10 +96,12 @@ const LINK_DEAD_TIMEOUT_MS: u32 = 300;
 const COMM_BOOT_PROBE_WINDOW_MS: u32 = 2_500;
 // 启动探测窗口内重发 HELLO 的频率，用于让数字侧更快确认模拟板已可通信。
 const COMM_BOOT_HELLO_RETRY_MS: u32 = 150;
-// IWDG recovery is intentionally disabled in the normal build. Cold-boot failures
-// seen here happen before analog firmware can participate, while an early IWDG
-// can slow healthy boots if digital is still starting.
-const ENABLE_COMM_IWDG_RECOVERY: bool = false;
+// IWDG recovery: armed lazily on the first valid control frame (an early IWDG
+// would slow healthy boots while digital is still starting), then refreshed by
+// the control loop while the link is alive or the output is not yet safed. A
+// wedged loop or a link that stays dead past the safing window resets the MCU,
+// which boots with the output latched off (see `loadlynx_protocol::link_safe`).
+const ENABLE_COMM_IWDG_RECOVERY: bool = true;
 const COMM_IWDG_TIMEOUT_RELOAD: u32 = 499;
 const IWDG_REFRESH_LINK_MAX_AGE_MS: u32 = 2_000;
 // STM32G4 BOR level 4 is the highest normal brown-out threshold, around 2.8 V.
@@ -168,6 +188,8 @@ const TARGET_I_MAX_MA: i32 = 10_000;
 const TARGET_I_CH_MAX_MA: i32 = 5_000;
 
 // Basic protection thresholds (units: mA, mV, m°C).
+// These are the power-on defaults; the digital side may override them at
+// runtime via MSG_PROTECTION_CONFIG (see `PROT_*` atomics below).
 const OC_LIMIT_CH_MA: i32 = 5_500; // 过流阈值（略高于 TARGET_I_CH_MAX_MA）
 const OC_LIMIT_TOTAL_MA: i32 = 11_000; // 略高于 10A 总目标（双通道同时略超时保护）
 const OV_LIMIT_MV: i32 = 55_000; // 过压阈值（与文档 55V 对齐）
@@ -181,6 +203,31 @@ const HARD_MAX_P_MW: u32 = 200_000;
 // 通道调度阈值：总目标电流 < 2 A 时仅驱动通道 1；≥ 2 A 时两通道近似均分。
 const I_SHARE_THRESHOLD_MA: i32 = 2_000;
 
+// Active protection thresholds. Initialised from the constants above and
+// updated by validated ProtectionConfig frames from the digital side.
+static PROT_OC_LIMIT_CH_MA: AtomicI32 = AtomicI32::new(OC_LIMIT_CH_MA);
+static PROT_OV_LIMIT_MV: AtomicI32 = AtomicI32::new(OV_LIMIT_MV);
+static PROT_MCU_TEMP_LIMIT_MC: AtomicI32 = AtomicI32::new(MCU_TEMP_LIMIT_MC);
+static PROT_SINK_TEMP_LIMIT_MC: AtomicI32 = AtomicI32::new(SINK_TEMP_LIMIT_MC);
+static PROT_I_SHARE_THRESHOLD_MA: AtomicI32 = AtomicI32::new(I_SHARE_THRESHOLD_MA);
+// CH1/CH2 split (`SHARE_POLICY_*`); auto uses the threshold above.
+static PROT_SHARE_POLICY: AtomicU8 = AtomicU8::new(ProtectionConfig::DEFAULT.share_policy);
+// Fault policy: selectors set here latch until ClearFaults/SoftReset, the
+// others auto-recover once their condition is gone.
+static PROT_FAULT_LATCH_MASK: AtomicU32 = AtomicU32::new(FAULT_POLICY_ALL);
+// Link-loss safing window (ms without a valid control frame).
+static PROT_LINK_LOSS_TIMEOUT_MS: AtomicU32 =
+    AtomicU32::new(ProtectionConfig::DEFAULT.link_loss_timeout_ms as u32);
+// Set by the RX task on a SetMode output-enable rising edge; consumed by the
+// control loop to leave the link-safed latch.
+static LINK_SAFE_REARM_REQUESTED: AtomicBool = AtomicBool::new(false);
+// Pending ResetCounters selectors (`COUNTER_*`); consumed by the control loop.
+static COUNTER_RESET_MASK: AtomicU8 = AtomicU8::new(0);
+
+// UV auto-recover hysteresis: V_main must climb this far above min_v before an
+// auto-recover UV latch releases, so a sagging source does not chatter.
+const UV_AUTO_RECOVER_HYST_MV: i32 = 500;
+
 // CV loop tuning (legacy constants were historically tuned at FAST_STATUS_PERIOD_US cadence).
 //
 // Control model: integrate on conductance `G` so current demand scales with voltage:
@@ -1066,7 +1113,8 @@ static QUIET_UNTIL_MS: AtomicU32 = AtomicU32::new(0);
 static ACTIVE_MODE_SEEN: AtomicBool = AtomicBool::new(false);
 static LAST_SETPOINT_IGNORED_LOG_MS: AtomicU32 = AtomicU32::new(0);
 
-// Latched protection faults reported via FastStatus and used to gate output.
+// Protection faults reported via FastStatus and used to gate output. Latching
+// follows PROT_FAULT_LATCH_MASK.
 static FAULT_FLAGS: AtomicU32 = AtomicU32::new(0);
 
 static UART_TX_SHARED: StaticCell<Mutex<CriticalSectionRawMutex, UartTx<'static, UartAsync>>> =
@@ -1218,6 +1266,15 @@ fn comm_iwdg_arm() {
     }
 }
 
+/// Read and clear the RCC reset flags; true if the last reset came from the IWDG.
+fn take_iwdg_reset_flag() -> bool {
+    unsafe {
+        let csr = core::ptr::read_volatile(RCC_CSR_ADDR);
+        core::ptr::write_volatile(RCC_CSR_ADDR, csr | RCC_CSR_RMVF);
+        csr & RCC_CSR_IWDGRSTF != 0
+    }
+}
+
 fn comm_iwdg_refresh() {
     unsafe {
         core::ptr::write_volatile(IWDG_KR_ADDR, 0xAAAA);
@@ -1326,6 +1383,9 @@ static LIMIT_PROFILE: Mutex<CriticalSectionRawMutex, LimitProfileLocal> =
         temp_trip_mc: SINK_TEMP_LIMIT_MC,
         thermal_derate_pct: 100,
     });
+// Lock-free copy of `LimitProfile.thermal_derate_pct` for the SetMode (v1)
+// control path, which scales the hard power budget by it.
+static THERMAL_DERATE_PCT: AtomicU8 = AtomicU8::new(100);
 
 fn timestamp_ms() -> u64 {
     Instant::now().as_millis()
@@ -1385,6 +1445,9 @@ fn g4_internal_mcu_temp_to_mc(adc_code: u16) -> i32 {
 
 #[embassy_executor::main]
 async fn main(_spawner: Spawner) -> ! {
+    // Before any clock setup: a pending EnterBootloader request jumps to ROM.
+    bootloader::enter_if_requested();
+
     // Clock config:
     // Align with the reference PD sink bring-up (pd-sink-stm32g431cbu6-rs):
     // - SYSCLK = 170MHz (PLL1_R)
@@ -1418,12 +1481,9 @@ async fn main(_spawner: Spawner) -> ! {
 
     ensure_bor_level();
 
-    if ENABLE_COMM_IWDG_RECOVERY {
-        comm_iwdg_arm();
-        warn!(
-            "communication IWDG recovery armed early (timeout≈4s, refresh_link_age_ms={})",
-            IWDG_REFRESH_LINK_MAX_AGE_MS
-        );
+    let iwdg_reset = take_iwdg_reset_flag();
+    if iwdg_reset {
+        warn!("boot after IWDG reset: output latched off until re-enabled");
     }
 
     info!("LoadLynx analog alive; init VREFBUF/ADC/DAC/UART (CC 0.5A, real telemetry)");
@@ -1638,13 +1698,11 @@ async fn main(_spawner: Spawner) -> ! {
         (mv1, mv2)
     };
     let init_total_i_ma = DEFAULT_TARGET_I_LOCAL_MA;
-    let (init_ch1_ma, init_ch2_ma) = if init_total_i_ma < I_SHARE_THRESHOLD_MA {
-        (init_total_i_ma, 0)
-    } else {
-        let half = init_total_i_ma / 2;
-        let rem = init_total_i_ma - 2 * half;
-        (half + rem, half)
-    };
+    let (init_ch1_ma, init_ch2_ma) = channel_share::split_target(
+        init_total_i_ma,
+        ProtectionConfig::DEFAULT.share_policy,
+        I_SHARE_THRESHOLD_MA,
+    );
     let init_raw_ch1_100uv = init_ch1_ma.saturating_mul(5).clamp(0, i16::MAX as i32) as i16;
     let init_raw_ch2_100uv = init_ch2_ma.saturating_mul(5).clamp(0, i16::MAX as i32) as i16;
     let init_dac_code_ch1 = raw_100uv_to_dac_code_vref(init_raw_ch1_100uv, vref_mv);
@@ -1667,10 +1725,28 @@ async fn main(_spawner: Spawner) -> ! {
     let mut last_link_fault = false;
     let mut next_boot_hello_ms = COMM_BOOT_HELLO_RETRY_MS;
 
+    // Link-loss safing: ramp the output down and latch it off when the digital
+    // side goes silent. An IWDG reset means the previous session lost the link
+    // (or wedged), so start latched.
+    let mut link_safing = if iwdg_reset {
+        LinkSafing::new_safed(LinkSafeReason::WatchdogReset)
+    } else {
+        LinkSafing::new()
+    };
+    let mut comm_iwdg_armed = false;
+
     // 远端 sense 判定状态（3 帧进入 / 2 帧退出）。
     let mut remote_active: bool = false;
     let mut remote_good_streak: u8 = 0;
     let mut remote_bad_streak: u8 = 0;
+    // Sense-wiring diagnostics (reversed/open leads, lead drop, oscillation).
+    let mut sense_diag = SenseDiag::new();
+    let mut sense_warnings_prev: u32 = 0;
+    // CH1/CH2 sharing health (imbalance, stray current on an idle channel).
+    let mut channel_monitor = ChannelMonitor::new();
+    let mut channel_warnings_prev: u32 = 0;
+    // Charge/energy through the load, integrated every control tick.
+    let mut energy_counters = EnergyCounters::new(timestamp_ms() as u32);
 
     // Calibration-only UI/RAW smoothing (see CAL_SMOOTH_WINDOW_FRAMES).
     let mut cal_smoother: CalSmoother<CAL_SMOOTH_WINDOW_FRAMES> = CalSmoother::new();
@@ -1827,11 +1903,55 @@ async fn main(_spawner: Spawner) -> ! {
         //   未再看到任何控制帧，则认为当前处于“通信异常”状态，让 LED1 闪烁；
         // - 一旦重新收到有效控制帧，则视作恢复正常，LED1 熄灭。
         let last_rx = LAST_RX_GOOD_MS.load(Ordering::Relaxed);
-        if ENABLE_COMM_IWDG_RECOVERY
-            && last_rx != 0
-            && now_ms.wrapping_sub(last_rx) <= IWDG_REFRESH_LINK_MAX_AGE_MS
+        let link_ever_good = LINK_EVER_GOOD.load(Ordering::Relaxed);
+
+        // 链路丢失安全态：超过 link_loss_timeout_ms 未收到有效控制帧时，将输出斜坡降至 0
+        // 并锁存，直到数字侧显式重新使能输出（SetMode 上升沿）。
+        if LINK_SAFE_REARM_REQUESTED.swap(false, Ordering::Relaxed)
+            && link_safing.state() != LinkSafeState::Normal
         {
-            comm_iwdg_refresh();
+            info!("link safing re-armed by output enable");
+            link_safing.rearm();
+        }
+        let (link_safe_scale_permille, link_safe_event) = link_safing.tick(
+            now_ms,
+            link_ever_good.then_some(last_rx),
+            PROT_LINK_LOSS_TIMEOUT_MS.load(Ordering::Relaxed),
+        );
+        match link_safe_event {
+            Some(LinkSafeEvent::RampStarted { silent_ms }) => warn!(
+                "link loss: no control frames for {} ms; ramping output to zero",
+                silent_ms
+            ),
+            Some(LinkSafeEvent::Safed) => {
+                warn!("link loss: output safed (latched until re-enabled)")
+            }
+            Some(LinkSafeEvent::LinkRestored { reason }) => warn!(
+                "link restored while safed (reason={}); output stays off until re-enabled",
+                match reason {
+                    LinkSafeReason::LinkTimeout => "link_timeout",
+                    LinkSafeReason::WatchdogReset => "iwdg_reset",
+                }
+            ),
+            None => {}
+        }
+
+        if ENABLE_COMM_IWDG_RECOVERY && link_ever_good {
+            if !comm_iwdg_armed {
+                comm_iwdg_arm();
+                comm_iwdg_armed = true;
+                warn!(
+                    "communication IWDG recovery armed (timeout≈4s, refresh_link_age_ms={})",
+                    IWDG_REFRESH_LINK_MAX_AGE_MS
+                );
+            }
+            // Keep feeding while the output may still be live; once safed, only
+            // a live link keeps the MCU from resetting.
+            if !link_safing.is_safed()
+                || now_ms.wrapping_sub(last_rx) <= IWDG_REFRESH_LINK_MAX_AGE_MS
+            {
+                comm_iwdg_refresh();
+            }
         }
         let link_fault = if last_rx == 0 {
             now_ms > LINK_DEAD_TIMEOUT_MS
@@ -2056,19 +2176,38 @@ async fn main(_spawner: Spawner) -> ! {
         let i_ch1_ma_uncal = (2 * cur1_sns_mv_eff) as i32;
         let i_ch2_ma_uncal = (2 * cur2_sns_mv_eff) as i32;
 
+        // 实物板确认：TS2 (R40) 靠近 MOSFET / 散热片热点；TS1 (R39) 更靠近出风口/侧壁。
+        // 约定：
+        // - sink_core_temp_mc 始终表示“靠 MOS 的 NTC”（CORE，TS2/R40）
+        // - sink_exhaust_temp_mc 表示“靠外壳/出风口一侧的 NTC”（SINK/EXHAUST，TS1/R39）
+        let sink_core_temp_mc: i32 = ntc_mv_to_mc(ts2_mv);
+        let sink_exhaust_temp_mc: i32 = ntc_mv_to_mc(ts1_mv);
+        let mcu_temp_mc: i32 = g4_internal_mcu_temp_to_mc(mcu_temp_code);
+
+        // Temperatures feeding the calibration fmt v4 per-curve compensation.
+        let cal_temps = Temps {
+            sink_core_mc: sink_core_temp_mc,
+            mcu_mc: mcu_temp_mc,
+        };
+
         // --- Active-calibrated physical values ---
         let v_local_mv = if curves[CurveKind::VLocal.index()].is_empty() {
             v_local_mv_uncal
         } else {
-            piecewise_linear(curves[CurveKind::VLocal.index()].as_slice(), raw_v_nr_100uv)
-                .unwrap_or(v_local_mv_uncal)
+            piecewise_linear_compensated(
+                &curves[CurveKind::VLocal.index()],
+                raw_v_nr_100uv,
+                cal_temps,
+            )
+            .unwrap_or(v_local_mv_uncal)
         };
         let v_remote_mv = if curves[CurveKind::VRemote.index()].is_empty() {
             v_remote_mv_uncal
         } else {
-            piecewise_linear(
-                curves[CurveKind::VRemote.index()].as_slice(),
+            piecewise_linear_compensated(
+                &curves[CurveKind::VRemote.index()],
                 raw_v_rmt_100uv,
+                cal_temps,
             )
             .unwrap_or(v_remote_mv_uncal)
         };
@@ -2127,18 +2266,20 @@ async fn main(_spawner: Spawner) -> ! {
         let i_ch1_ma = if curves[CurveKind::CurrentCh1.index()].is_empty() {
             i_ch1_ma_uncal
         } else {
-            piecewise_linear(
-                curves[CurveKind::CurrentCh1.index()].as_slice(),
+            piecewise_linear_compensated(
+                &curves[CurveKind::CurrentCh1.index()],
                 raw_cur1_eff_100uv,
+                cal_temps,
             )
             .unwrap_or(i_ch1_ma_uncal)
         };
         let i_ch2_ma = if curves[CurveKind::CurrentCh2.index()].is_empty() {
             i_ch2_ma_uncal
         } else {
-            piecewise_linear(
-                curves[CurveKind::CurrentCh2.index()].as_slice(),
+            piecewise_linear_compensated(
+                &curves[CurveKind::CurrentCh2.index()],
                 raw_cur2_eff_100uv,
+                cal_temps,
             )
             .unwrap_or(i_ch2_ma_uncal)
         };
@@ -2152,6 +2293,7 @@ async fn main(_spawner: Spawner) -> ! {
         } else {
             v_local_mv
         };
+        sense_diag.sample_ripple(v_main_mv);
 
         // Power estimate used for UI/diagnostics and CP control feedback.
         //
@@ -2160,28 +2302,24 @@ async fn main(_spawner: Spawner) -> ! {
         let calc_p_mw = ((i_total_ma as i64 * (v_main_mv.max(0) as i64)) / 1_000)
             .clamp(0, u32::MAX as i64) as u32;
 
-        // 实物板确认：TS2 (R40) 靠近 MOSFET / 散热片热点；TS1 (R39) 更靠近出风口/侧壁。
-        // 约定：
-        // - sink_core_temp_mc 始终表示“靠 MOS 的 NTC”（CORE，TS2/R40）
-        // - sink_exhaust_temp_mc 表示“靠外壳/出风口一侧的 NTC”（SINK/EXHAUST，TS1/R39）
-        let sink_core_temp_mc: i32 = ntc_mv_to_mc(ts2_mv);
-        let sink_exhaust_temp_mc: i32 = ntc_mv_to_mc(ts1_mv);
-        let mcu_temp_mc: i32 = g4_internal_mcu_temp_to_mc(mcu_temp_code);
-
         // --- Fault detection ---
         let mut new_faults: u32 = 0;
+        let oc_limit_ch_ma = PROT_OC_LIMIT_CH_MA.load(Ordering::Relaxed);
+        // The total trip scales with the per-channel limit but never exceeds
+        // the hardware total.
+        let oc_limit_total_ma = oc_limit_ch_ma.saturating_mul(2).min(OC_LIMIT_TOTAL_MA);
 
-        if i_ch1_ma > OC_LIMIT_CH_MA || i_ch2_ma > OC_LIMIT_CH_MA || i_total_ma > OC_LIMIT_TOTAL_MA
+        if i_ch1_ma > oc_limit_ch_ma || i_ch2_ma > oc_limit_ch_ma || i_total_ma > oc_limit_total_ma
         {
             new_faults |= FAULT_OVERCURRENT;
         }
-        if v_local_mv > OV_LIMIT_MV {
+        if v_local_mv > PROT_OV_LIMIT_MV.load(Ordering::Relaxed) {
             new_faults |= FAULT_OVERVOLTAGE;
         }
-        if mcu_temp_mc > MCU_TEMP_LIMIT_MC {
+        if mcu_temp_mc > PROT_MCU_TEMP_LIMIT_MC.load(Ordering::Relaxed) {
             new_faults |= FAULT_MCU_OVER_TEMP;
         }
-        if sink_core_temp_mc > SINK_TEMP_LIMIT_MC {
+        if sink_core_temp_mc > PROT_SINK_TEMP_LIMIT_MC.load(Ordering::Relaxed) {
             new_faults |= FAULT_SINK_OVER_TEMP;
         }
 
@@ -2196,6 +2334,15 @@ async fn main(_spawner: Spawner) -> ! {
             }
         }
 
+        // Auto-recover faults drop out as soon as their condition is gone.
+        let fault_latch_mask = PROT_FAULT_LATCH_MASK.load(Ordering::Relaxed);
+        let recovered =
+            FAULT_FLAGS.load(Ordering::Relaxed) & FAULT_ALL & !fault_latch_mask & !new_faults;
+        if recovered != 0 {
+            FAULT_FLAGS.fetch_and(!recovered, Ordering::Relaxed);
+            info!("protection fault auto-recovered: 0x{:08x}", recovered);
+        }
+
         let fault_flags = FAULT_FLAGS.load(Ordering::Relaxed);
         let has_fault = fault_flags != 0;
 
@@ -2213,8 +2360,25 @@ async fn main(_spawner: Spawner) -> ! {
 
         // Undervoltage latch (non-fault):
         // - Trigger when output_enabled=true and V_main <= min_v.
-        // - Clears ONLY on output enable rising edge (handled in SetMode RX path).
+        // - Latched policy: clears on output enable rising edge (SetMode RX path)
+        //   or ClearFaults.
+        // - Auto-recover policy: additionally clears once V_main recovers above
+        //   min_v + UV_AUTO_RECOVER_HYST_MV.
         let mut uv_latched = active_mode_seen && ctrl_snapshot.uv_latched;
+        if uv_latched
+            && fault_latch_mask & FAULT_POLICY_UV_LATCH == 0
+            && v_main_mv
+                > ctrl_snapshot
+                    .min_v_mv
+                    .saturating_add(UV_AUTO_RECOVER_HYST_MV)
+        {
+            uv_latched = false;
+            active_control_set_uv_latched(false);
+            info!(
+                "uv_latched auto-recovered: preset_id={} v_main={}mV",
+                ctrl_snapshot.preset_id, v_main_mv
+            );
+        }
         if active_mode_seen
             && ctrl_snapshot.output_enabled
             && ctrl_snapshot.min_v_mv > 0
@@ -2234,11 +2398,13 @@ async fn main(_spawner: Spawner) -> ! {
         // - CAL_READY == false
         // - any FAULT_FLAGS != 0
         // - uv_latched == true
+        // - link-loss safing latched
         let enable_requested = ENABLE_REQUESTED.load(Ordering::Relaxed);
+        let link_safed = link_safing.is_safed();
         let effective_output_enable = if active_mode_seen {
-            ctrl_snapshot.output_enabled && cal_ready && !has_fault && !uv_latched
+            ctrl_snapshot.output_enabled && cal_ready && !has_fault && !uv_latched && !link_safed
         } else {
-            enable_requested && cal_ready && !has_fault
+            enable_requested && cal_ready && !has_fault && !link_safed
         };
 
         // CP 1ms acceptance run reset:
@@ -2253,6 +2419,20 @@ async fn main(_spawner: Spawner) -> ! {
             cp_accept_last_enable = false;
         }
 
+        // Charge/energy counters: integrate this tick's current and power over
+        // the measured loop period.
+        let counter_reset_mask = COUNTER_RESET_MASK.swap(0, Ordering::Relaxed);
+        if counter_reset_mask != 0 {
+            energy_counters.reset(counter_reset_mask, now_ms);
+            info!("counters reset: mask=0x{:02x}", counter_reset_mask);
+        }
+        energy_counters.tick(
+            effective_output_enable,
+            dt_us.min(u32::MAX as u64) as u32,
+            i_total_ma,
+            calc_p_mw,
+        );
+
         // Physically gate the TPS22810 load switch based on the effective enable state.
         if effective_output_enable {
             load_en_ctl.set_high();
@@ -2281,11 +2461,15 @@ async fn main(_spawner: Spawner) -> ! {
             let current_limit_ma = ctrl_snapshot
                 .max_i_ma_total
                 .clamp(TARGET_I_MIN_MA, TARGET_I_MAX_MA);
+            // Thermal derate (digital-side model) shrinks the hard power budget;
+            // the preset power limit still applies when it is tighter.
+            let thermal_derate_pct = THERMAL_DERATE_PCT.load(Ordering::Relaxed).min(100);
+            let derated_max_p_mw = (HARD_MAX_P_MW as u64 * thermal_derate_pct as u64 / 100) as u32;
+            let max_p_mw = ctrl_snapshot.max_p_mw.min(derated_max_p_mw);
             let power_limit_ma: i32 = if v_main_mv <= 0 {
                 0
             } else {
-                let i_by_power_ma =
-                    (ctrl_snapshot.max_p_mw as i64).saturating_mul(1_000) / (v_main_mv as i64);
+                let i_by_power_ma = (max_p_mw as i64).saturating_mul(1_000) / (v_main_mv as i64);
                 i_by_power_ma.clamp(TARGET_I_MIN_MA as i64, TARGET_I_MAX_MA as i64) as i32
             };
 
@@ -2622,9 +2806,10 @@ async fn main(_spawner: Spawner) -> ! {
             desired_i_total_ma
         };
 
-        // Effective total current target after all gating/limits.
+        // Effective total current target after all gating/limits (scaled down
+        // while the link-loss ramp is running).
         let target_i_total_ma = if effective_output_enable {
-            desired_i_total_ma
+            link_safe::scale_target(desired_i_total_ma, link_safe_scale_permille)
         } else {
             0
         };
@@ -2636,31 +2821,59 @@ async fn main(_spawner: Spawner) -> ! {
         // NOTE: we publish the latest signals after the DAC update below, so that the
         // captured samples include the actual command + sense values for that tick.
 
-        // 按总目标电流拆分两路通道：
+        // 按总目标电流拆分两路通道（share_policy，见 channel_share）：
         //
-        // - I_total < I_SHARE_THRESHOLD_MA：仅 CH1 承担全部电流，CH2=0；
-        // - I_total ≥ I_SHARE_THRESHOLD_MA：CH1/CH2 近似均分（奇数 mA 由 CH1 多承担 1 mA）。
+        // - auto：I_total < I_SHARE_THRESHOLD_MA 时仅 CH1 承担全部电流，否则 CH1/CH2 近似均分
+        //   （奇数 mA 由 CH1 多承担 1 mA）；
+        // - dual：始终均分；ch1_only / ch2_only：单通道承担全部电流（调试单颗 FET）。
+        let share_policy = PROT_SHARE_POLICY.load(Ordering::Relaxed);
         let (mut target_ch1_ma, mut target_ch2_ma) = if !effective_output_enable {
             (0, 0)
         } else {
             match cal_kind {
                 CalKind::CurrentCh1 => (target_i_total_ma, 0),
                 CalKind::CurrentCh2 => (0, target_i_total_ma),
-                _ => {
-                    if target_i_total_ma < I_SHARE_THRESHOLD_MA {
-                        (target_i_total_ma, 0)
-                    } else {
-                        let half = target_i_total_ma / 2;
-                        let rem = target_i_total_ma - 2 * half;
-                        (half + rem, half)
-                    }
-                }
+                _ => channel_share::split_target(
+                    target_i_total_ma,
+                    share_policy,
+                    PROT_I_SHARE_THRESHOLD_MA.load(Ordering::Relaxed),
+                ),
             }
         };
         // Enforce per-channel clamp after split.
         target_ch1_ma = target_ch1_ma.clamp(0, TARGET_I_CH_MAX_MA);
         target_ch2_ma = target_ch2_ma.clamp(0, TARGET_I_CH_MAX_MA);
 
+        // MOSFET SOA: per-channel V_DS → max power, derated by sink temperature.
+        // V_DS is taken from the local (terminal) voltage, which is what the FETs
+        // actually see. In normal operation excess current is moved to the other
+        // channel first; single-channel calibration and the single-channel
+        // share policies never rebalance.
+        let soa_limit_ch1_ma = soa::max_channel_current_ma(
+            &soa::SOA_TABLE_CH1,
+            v_local_mv,
+            sink_core_temp_mc,
+            TARGET_I_CH_MAX_MA,
+        );
+        let soa_limit_ch2_ma = soa::max_channel_current_ma(
+            &soa::SOA_TABLE_CH2,
+            v_local_mv,
+            sink_core_temp_mc,
+            TARGET_I_CH_MAX_MA,
+        );
+        let soa_rebalance = !matches!(cal_kind, CalKind::CurrentCh1 | CalKind::CurrentCh2)
+            && channel_share::policy_allows_rebalance(share_policy);
+        let (soa_ch1_ma, soa_ch2_ma, soa_limited) = soa::clamp_channels(
+            target_ch1_ma,
+            target_ch2_ma,
+            soa_limit_ch1_ma,
+            soa_limit_ch2_ma,
+            soa_rebalance,
+        );
+        target_ch1_ma = soa_ch1_ma;
+        target_ch2_ma = soa_ch2_ma;
+        let soa_limited = effective_output_enable && soa_limited;
+
         // Inverse mapping: physical target → raw 100 µV target.
         let ideal_raw_ch1_des_100uv =
             target_ch1_ma.saturating_mul(5).clamp(0, i16::MAX as i32) as i16;
@@ -2672,9 +2885,10 @@ async fn main(_spawner: Spawner) -> ! {
         } else if curves[CurveKind::CurrentCh1.index()].is_empty() {
             ideal_raw_ch1_des_100uv
         } else {
-            inverse_piecewise(
-                curves[CurveKind::CurrentCh1.index()].as_slice(),
+            inverse_piecewise_compensated(
+                &curves[CurveKind::CurrentCh1.index()],
                 target_ch1_ma,
+                cal_temps,
             )
             .unwrap_or(ideal_raw_ch1_des_100uv)
         };
@@ -2683,9 +2897,10 @@ async fn main(_spawner: Spawner) -> ! {
         } else if curves[CurveKind::CurrentCh2.index()].is_empty() {
             ideal_raw_ch2_des_100uv
         } else {
-            inverse_piecewise(
-                curves[CurveKind::CurrentCh2.index()].as_slice(),
+            inverse_piecewise_compensated(
+                &curves[CurveKind::CurrentCh2.index()],
                 target_ch2_ma,
+                cal_temps,
             )
             .unwrap_or(ideal_raw_ch2_des_100uv)
         };
@@ -3088,18 +3303,20 @@ async fn main(_spawner: Spawner) -> ! {
                     let v_local_sm = if curves[CurveKind::VLocal.index()].is_empty() {
                         v_local_mv_uncal_sm
                     } else {
-                        piecewise_linear(
-                            curves[CurveKind::VLocal.index()].as_slice(),
+                        piecewise_linear_compensated(
+                            &curves[CurveKind::VLocal.index()],
                             raw_v_nr_100uv_sm,
+                            cal_temps,
                         )
                         .unwrap_or(v_local_mv_uncal_sm)
                     };
                     let v_remote_sm = if curves[CurveKind::VRemote.index()].is_empty() {
                         v_remote_mv_uncal_sm
                     } else {
-                        piecewise_linear(
-                            curves[CurveKind::VRemote.index()].as_slice(),
+                        piecewise_linear_compensated(
+                            &curves[CurveKind::VRemote.index()],
                             raw_v_rmt_100uv_sm,
+                            cal_temps,
                         )
                         .unwrap_or(v_remote_mv_uncal_sm)
                     };
@@ -3107,18 +3324,20 @@ async fn main(_spawner: Spawner) -> ! {
                     let i_ch1_sm = if curves[CurveKind::CurrentCh1.index()].is_empty() {
                         i_ch1_ma_uncal_sm
                     } else {
-                        piecewise_linear(
-                            curves[CurveKind::CurrentCh1.index()].as_slice(),
+                        piecewise_linear_compensated(
+                            &curves[CurveKind::CurrentCh1.index()],
                             raw_cur1_100uv_sm,
+                            cal_temps,
                         )
                         .unwrap_or(i_ch1_ma_uncal_sm)
                     };
                     let i_ch2_sm = if curves[CurveKind::CurrentCh2.index()].is_empty() {
                         i_ch2_ma_uncal_sm
                     } else {
-                        piecewise_linear(
-                            curves[CurveKind::CurrentCh2.index()].as_slice(),
+                        piecewise_linear_compensated(
+                            &curves[CurveKind::CurrentCh2.index()],
                             raw_cur2_100uv_sm,
+                            cal_temps,
                         )
                         .unwrap_or(i_ch2_ma_uncal_sm)
                     };
@@ -3180,6 +3399,64 @@ async fn main(_spawner: Spawner) -> ! {
             if current_limited {
                 state_flags |= STATE_FLAG_CURRENT_LIMITED;
             }
+            if soa_limited {
+                state_flags |= STATE_FLAG_SOA_LIMITED;
+            }
+            state_flags |= link_safing.state_flags();
+
+            // Sense-path diagnostics are advisory. Calibration deliberately
+            // rewires the sense inputs, so report nothing while it runs.
+            let (sense_warnings, lead_resistance_mohm) = if cal_kind == CalKind::Off {
+                let warnings = sense_diag.update(SenseSample {
+                    v_local_mv: status_v_local_mv,
+                    v_remote_mv: status_v_remote_mv,
+                    i_total_ma: status_i_total_ma,
+                    remote_active,
+                    output_enabled: effective_output_enable,
+                });
+                (warnings, sense_diag.lead_resistance_mohm())
+            } else {
+                (0, None)
+            };
+            // Channel sharing health; calibration drives one channel on purpose.
+            let channel_warnings = if cal_kind == CalKind::Off {
+                channel_monitor.update(ChannelSample {
+                    target_ch1_ma,
+                    target_ch2_ma,
+                    i_ch1_ma: status_i_ch1_ma,
+                    i_ch2_ma: status_i_ch2_ma,
+                })
+            } else {
+                channel_monitor.reset();
+                0
+            };
+            if channel_warnings != channel_warnings_prev {
+                info!(
+                    "channel sharing: warnings=0x{:02x} (was 0x{:02x}) target={}/{}mA meas={}/{}mA",
+                    channel_warnings,
+                    channel_warnings_prev,
+                    target_ch1_ma,
+                    target_ch2_ma,
+                    status_i_ch1_ma,
+                    status_i_ch2_ma
+                );
+                channel_warnings_prev = channel_warnings;
+            }
+            let (dac_ch1_opt, dac_ch2_opt) = if cal_kind == CalKind::Off {
+                (Some(dac_code_ch1), Some(dac_code_ch2))
+            } else {
+                (None, None)
+            };
+
+            if sense_warnings != sense_warnings_prev {
+                info!(
+                    "sense diagnostics: warnings=0x{:02x} (was 0x{:02x}) lead={}mOhm",
+                    sense_warnings,
+                    sense_warnings_prev,
+                    lead_resistance_mohm.unwrap_or(0)
+                );
+                sense_warnings_prev = sense_warnings;
+            }
 
             // Optional Raw telemetry fields during calibration.
             let (status_cal_kind, raw_v_nr_opt, raw_v_rmt_opt, raw_cur_opt, raw_dac_opt) =
@@ -3231,6 +3508,26 @@ async fn main(_spawner: Spawner) -> ! {
                 raw_v_rmt_100uv: raw_v_rmt_opt,
                 raw_cur_100uv: raw_cur_opt,
                 raw_dac_code: raw_dac_opt,
+                sense_warnings: (sense_warnings != 0).then_some(sense_warnings),
+                lead_resistance_mohm,
+                dac_code_ch1: dac_ch1_opt,
+                dac_code_ch2: dac_ch2_opt,
+                fet_temp_ch1_mc: Some(channel_share::fet_temp_mc(
+                    sink_core_temp_mc,
+                    status_v_local_mv,
+                    status_i_ch1_ma,
+                )),
+                fet_temp_ch2_mc: Some(channel_share::fet_temp_mc(
+                    sink_core_temp_mc,
+                    status_v_local_mv,
+                    status_i_ch2_ma,
+                )),
+                share_policy: Some(share_policy),
+                channel_warnings: (channel_warnings != 0).then_some(channel_warnings),
+                charge_uah: Some(energy_counters.charge_uah()),
+                energy_uwh: Some(energy_counters.energy_uwh()),
+                counter_elapsed_ms: Some(energy_counters.elapsed_ms(now_ms)),
+                counter_on_ms: Some(energy_counters.on_ms()),
             };
 
             if ENABLE_FAST_STATUS_TX {
@@ -3414,6 +3711,280 @@ async fn handle_soft_reset_request(
     }
 }
 
+/// Validate and apply a ProtectionConfig frame, then ACK (or NACK when the
+/// payload is malformed or out of the hardware-safe range; the previous
+/// thresholds stay active in that case).
+async fn handle_protection_config_request(
+    uart_tx: &'static Mutex<CriticalSectionRawMutex, UartTx<'static, UartAsync>>,
+    ack_raw: &mut [u8],
+    ack_slip: &mut [u8],
+    frame: &[u8],
+    header: FrameHeader,
+) {
+    if header.flags & FLAG_IS_ACK != 0 {
+        info!(
+            "ProtectionConfig ACK received on analog side (ignored) seq={}",
+            header.seq
+        );
+        return;
+    }
+
+    let is_nack = match decode_protection_config_frame(frame) {
+        Ok((_hdr, cfg)) => match cfg.validate() {
+            Ok(()) => {
+                PROT_OC_LIMIT_CH_MA.store(cfg.oc_limit_ch_ma, Ordering::Relaxed);
+                PROT_OV_LIMIT_MV.store(cfg.ov_limit_mv, Ordering::Relaxed);
+                PROT_MCU_TEMP_LIMIT_MC.store(cfg.mcu_temp_limit_mc, Ordering::Relaxed);
+                PROT_SINK_TEMP_LIMIT_MC.store(cfg.sink_temp_limit_mc, Ordering::Relaxed);
+                PROT_I_SHARE_THRESHOLD_MA.store(cfg.i_share_threshold_ma, Ordering::Relaxed);
+                PROT_FAULT_LATCH_MASK.store(cfg.fault_latch_mask, Ordering::Relaxed);
+                PROT_LINK_LOSS_TIMEOUT_MS.store(cfg.link_loss_timeout_ms as u32, Ordering::Relaxed);
+                PROT_SHARE_POLICY.store(cfg.share_policy, Ordering::Relaxed);
+                info!(
+                    "ProtectionConfig applied: oc_ch={}mA ov={}mV mcu_temp={}mC sink_temp={}mC i_share={}mA share_policy={} latch_mask=0x{:08x} link_loss={}ms seq={}",
+                    cfg.oc_limit_ch_ma,
+                    cfg.ov_limit_mv,
+                    cfg.mcu_temp_limit_mc,
+                    cfg.sink_temp_limit_mc,
+                    cfg.i_share_threshold_ma,
+                    cfg.share_policy,
+                    cfg.fault_latch_mask,
+                    cfg.link_loss_timeout_ms,
+                    header.seq
+                );
+                false
+            }
+            Err(field) => {
+                warn!(
+                    "ProtectionConfig rejected: {} out of range (seq={})",
+                    field.as_str(),
+                    header.seq
+                );
+                true
+            }
+        },
+        Err(err) => {
+            warn!(
+                "ProtectionConfig decode error: {:?} (seq={})",
+                err, header.seq
+            );
+            true
+        }
+    };
+
+    LAST_RX_GOOD_MS.store(timestamp_ms() as u32, Ordering::Relaxed);
+    LINK_EVER_GOOD.store(true, Ordering::Relaxed);
+
+    let ack_len = match encode_ack_only_frame(header.seq, MSG_PROTECTION_CONFIG, is_nack, ack_raw) {
+        Ok(len) => len,
+        Err(err) => {
+            warn!("ProtectionConfig ack encode error: {:?}", err);
+            return;
+        }
+    };
+    let slip_len = match slip_encode(&ack_raw[..ack_len], ack_slip) {
+        Ok(len) => len,
+        Err(err) => {
+            warn!("ProtectionConfig ack slip encode error: {:?}", err);
+            return;
+        }
+    };
+
+    let mut tx = uart_tx.lock().await;
+    match tx.write(&ack_slip[..slip_len]).await {
+        Ok(_) => info!(
+            "ProtectionConfig {} sent: seq={}",
+            if is_nack { "NACK" } else { "ACK" },
+            header.seq
+        ),
+        Err(err) => warn!("ProtectionConfig ack write error: {:?}", err),
+    }
+}
+
+/// Clear the faults / UV latch selected by a ClearFaults frame, then ACK (or
+/// NACK when the payload cannot be decoded).
+async fn handle_clear_faults_request(
+    uart_tx: &'static Mutex<CriticalSectionRawMutex, UartTx<'static, UartAsync>>,
+    ack_raw: &mut [u8],
+    ack_slip: &mut [u8],
+    frame: &[u8],
+    header: FrameHeader,
+) {
+    if header.flags & FLAG_IS_ACK != 0 {
+        info!(
+            "ClearFaults ACK received on analog side (ignored) seq={}",
+            header.seq
+        );
+        return;
+    }
+
+    let is_nack = match decode_clear_faults_frame(frame) {
+        Ok((_hdr, req)) => {
+            let fault_mask = req.mask & FAULT_ALL;
+            let prev = FAULT_FLAGS.fetch_and(!fault_mask, Ordering::Relaxed);
+            let uv_cleared = req.mask & FAULT_POLICY_UV_LATCH != 0
+                && ACTIVE_CTRL_UV_LATCHED.load(Ordering::Relaxed);
+            if uv_cleared {
+                active_control_set_uv_latched(false);
+            }
+            info!(
+                "ClearFaults applied: mask=0x{:08x} cleared=0x{:08x} uv_cleared={} seq={}",
+                req.mask,
+                prev & fault_mask,
+                uv_cleared,
+                header.seq
+            );
+            false
+        }
+        Err(err) => {
+            warn!("ClearFaults decode error: {:?} (seq={})", err, header.seq);
+            true
+        }
+    };
+
+    LAST_RX_GOOD_MS.store(timestamp_ms() as u32, Ordering::Relaxed);
+    LINK_EVER_GOOD.store(true, Ordering::Relaxed);
+
+    let ack_len = match encode_ack_only_frame(header.seq, MSG_CLEAR_FAULTS, is_nack, ack_raw) {
+        Ok(len) => len,
+        Err(err) => {
+            warn!("ClearFaults ack encode error: {:?}", err);
+            return;
+        }
+    };
+    let slip_len = match slip_encode(&ack_raw[..ack_len], ack_slip) {
+        Ok(len) => len,
+        Err(err) => {
+            warn!("ClearFaults ack slip encode error: {:?}", err);
+            return;
+        }
+    };
+
+    let mut tx = uart_tx.lock().await;
+    match tx.write(&ack_slip[..slip_len]).await {
+        Ok(_) => info!(
+            "ClearFaults {} sent: seq={}",
+            if is_nack { "NACK" } else { "ACK" },
+            header.seq
+        ),
+        Err(err) => warn!("ClearFaults ack write error: {:?}", err),
+    }
+}
+
+/// Queue the counter reset selected by a ResetCounters frame for the control
+/// loop, then ACK (or NACK when the payload cannot be decoded).
+async fn handle_reset_counters_request(
+    uart_tx: &'static Mutex<CriticalSectionRawMutex, UartTx<'static, UartAsync>>,
+    ack_raw: &mut [u8],
+    ack_slip: &mut [u8],
+    frame: &[u8],
+    header: FrameHeader,
+) {
+    if header.flags & FLAG_IS_ACK != 0 {
+        info!(
+            "ResetCounters ACK received on analog side (ignored) seq={}",
+            header.seq
+        );
+        return;
+    }
+
+    let is_nack = match decode_reset_counters_frame(frame) {
+        Ok((_hdr, req)) => {
+            COUNTER_RESET_MASK.fetch_or(req.mask, Ordering::Relaxed);
+            info!(
+                "ResetCounters accepted: mask=0x{:02x} seq={}",
+                req.mask, header.seq
+            );
+            false
+        }
+        Err(err) => {
+            warn!("ResetCounters decode error: {:?} (seq={})", err, header.seq);
+            true
+        }
+    };
+
+    LAST_RX_GOOD_MS.store(timestamp_ms() as u32, Ordering::Relaxed);
+    LINK_EVER_GOOD.store(true, Ordering::Relaxed);
+
+    let ack_len = match encode_ack_only_frame(header.seq, MSG_RESET_COUNTERS, is_nack, ack_raw) {
+        Ok(len) => len,
+        Err(err) => {
+            warn!("ResetCounters ack encode error: {:?}", err);
+            return;
+        }
+    };
+    let slip_len = match slip_encode(&ack_raw[..ack_len], ack_slip) {
+        Ok(len) => len,
+        Err(err) => {
+            warn!("ResetCounters ack slip encode error: {:?}", err);
+            return;
+        }
+    };
+
+    let mut tx = uart_tx.lock().await;
+    match tx.write(&ack_slip[..slip_len]).await {
+        Ok(_) => info!(
+            "ResetCounters {} sent: seq={}",
+            if is_nack { "NACK" } else { "ACK" },
+            header.seq
+        ),
+        Err(err) => warn!("ResetCounters ack write error: {:?}", err),
+    }
+}
+
+/// ACK a valid EnterBootloader frame, drop the output and reset into the ROM
+/// bootloader (see `bootloader.rs`); NACK anything else and keep running.
+async fn handle_enter_bootloader_request(
+    uart_tx: &'static Mutex<CriticalSectionRawMutex, UartTx<'static, UartAsync>>,
+    ack_raw: &mut [u8],
+    ack_slip: &mut [u8],
+    frame: &[u8],
+    header: FrameHeader,
+) {
+    if header.flags & FLAG_IS_ACK != 0 {
+        return;
+    }
+
+    let accepted = match decode_enter_bootloader_frame(frame) {
+        Ok((_hdr, req)) if req.magic == ENTER_BOOTLOADER_MAGIC => true,
+        Ok((_hdr, req)) => {
+            warn!(
+                "EnterBootloader rejected: magic=0x{:08x} seq={}",
+                req.magic, header.seq
+            );
+            false
+        }
+        Err(err) => {
+            warn!(
+                "EnterBootloader decode error: {:?} (seq={})",
+                err, header.seq
+            );
+            false
+        }
+    };
+
+    if let Ok(ack_len) = encode_ack_only_frame(header.seq, MSG_ENTER_BOOTLOADER, !accepted, ack_raw)
+        && let Ok(slip_len) = slip_encode(&ack_raw[..ack_len], ack_slip)
+    {
+        let mut tx = uart_tx.lock().await;
+        if let Err(err) = tx.write(&ack_slip[..slip_len]).await {
+            warn!("EnterBootloader ack write error: {:?}", err);
+        }
+    }
+    if !accepted {
+        return;
+    }
+
+    warn!(
+        "EnterBootloader accepted (seq={}); resetting into system bootloader",
+        header.seq
+    );
+    active_control_reset();
+    // Let the ACK drain and the control loop apply the zeroed setpoint.
+    Timer::after_millis(20).await;
+    bootloader::request_and_reset();
+}
+
 /// UART RX 任务：从数字板接收控制帧（SetMode/SetPoint/SoftReset/SetEnable/...）。
 #[embassy_executor::task]
 async fn uart_setpoint_rx_task(
@@ -3469,6 +4040,58 @@ async fn uart_setpoint_rx_task(
                                 frame.len(),
                                 &frame[..frame.len().min(16)]
                             );
+                            if let Ok((hdr, _payload)) = decode_frame(&frame)
+                                && hdr.msg == MSG_PROTECTION_CONFIG
+                            {
+                                handle_protection_config_request(
+                                    uart_tx,
+                                    &mut ack_raw,
+                                    &mut ack_slip,
+                                    &frame,
+                                    hdr,
+                                )
+                                .await;
+                                continue;
+                            }
+                            if let Ok((hdr, _payload)) = decode_frame(&frame)
+                                && hdr.msg == MSG_CLEAR_FAULTS
+                            {
+                                handle_clear_faults_request(
+                                    uart_tx,
+                                    &mut ack_raw,
+                                    &mut ack_slip,
+                                    &frame,
+                                    hdr,
+                                )
+                                .await;
+                                continue;
+                            }
+                            if let Ok((hdr, _payload)) = decode_frame(&frame)
+                                && hdr.msg == MSG_ENTER_BOOTLOADER
+                            {
+                                handle_enter_bootloader_request(
+                                    uart_tx,
+                                    &mut ack_raw,
+                                    &mut ack_slip,
+                                    &frame,
+                                    hdr,
+                                )
+                                .await;
+                                continue;
+                            }
+                            if let Ok((hdr, _payload)) = decode_frame(&frame)
+                                && hdr.msg == MSG_RESET_COUNTERS
+                            {
+                                handle_reset_counters_request(
+                                    uart_tx,
+                                    &mut ack_raw,
+                                    &mut ack_slip,
+                                    &frame,
+                                    hdr,
+                                )
+                                .await;
+                                continue;
+                            }
                             match decode_set_mode_frame(&frame) {
                                 Ok((hdr, cmd)) => {
                                     if hdr.flags & FLAG_IS_ACK != 0 {
@@ -3491,6 +4114,11 @@ async fn uart_setpoint_rx_task(
                                                 ACTIVE_CTRL_OUTPUT_ENABLED.load(Ordering::Relaxed);
                                             let prev_uv_latched =
                                                 ACTIVE_CTRL_UV_LATCHED.load(Ordering::Relaxed);
+                                            // The first SetMode after boot / SoftReset replays the
+                                            // digital-side state; only an edge within a session is an
+                                            // operator re-enable.
+                                            let prev_mode_seen =
+                                                ACTIVE_MODE_SEEN.load(Ordering::Relaxed);
 
                                             let new_target_p_mw = cmd.target_p_mw.unwrap_or(0);
 
@@ -3523,6 +4151,10 @@ async fn uart_setpoint_rx_task(
                                                 }
                                                 ACTIVE_CTRL_UV_LATCHED
                                                     .store(false, Ordering::Relaxed);
+                                                if prev_mode_seen {
+                                                    LINK_SAFE_REARM_REQUESTED
+                                                        .store(true, Ordering::Relaxed);
+                                                }
                                             }
                                             ACTIVE_CTRL_SEQ.fetch_add(1, Ordering::Release);
 
@@ -3834,6 +4466,10 @@ async fn uart_setpoint_rx_task(
                                                                 limits.thermal_derate_pct =
                                                                     profile.thermal_derate_pct;
                                                             }
+                                                            THERMAL_DERATE_PCT.store(
+                                                                profile.thermal_derate_pct.min(100),
+                                                                Ordering::Relaxed,
+                                                            );
                                                             info!(
                                                                 "LimitProfile received: max_i={}mA max_p={}mW ovp={}mV temp_trip={}mC derate={}%",
                                                                 profile.max_i_ma,
@@ -0,0 +1,357 @@
+//! Remote-sense wiring diagnostics.
+//!
+//! `main.rs` already decides whether remote sense is usable
+//! (`STATE_FLAG_REMOTE_ACTIVE`, 3 frames in / 2 frames out). This module looks
+//! at the same readings to explain *why* the sense path looks wrong, reported
+//! as `FastStatus.sense_warnings` (`SENSE_WARN_*`):
+//!
+//! - **reversed**: the remote reading goes below `-REVERSED_MIN_MV` while the
+//!   local terminals see a DUT. An unconnected remote input already reads
+//!   around -0.9 V through the calibration intercept, hence the margin; on the
+//!   single-supply front end a hard-reversed pair clamps at that same floor and
+//!   can only show up as **open**.
+//! - **open**: remote sense was active for the current DUT connection, then
+//!   fell below [`REMOTE_LOST_MV`] while the local voltage stayed up, i.e. a
+//!   lead came off. A setup that never used remote sense does not warn.
+//!   Cleared once remote sense returns or the DUT is disconnected.
+//! - **high lead drop**: with remote sense active and at least
+//!   [`DROP_MIN_CURRENT_MA`] flowing, `|v_local - v_remote|` over the load
+//!   current gives the force-lead (cable) resistance; warn above
+//!   [`DROP_WARN_MV`] or [`LEAD_WARN_MOHM`].
+//! - **oscillation**: peak-to-peak ripple of the regulated voltage within one
+//!   status window, fed from every control tick, exceeds
+//!   `OSC_MIN_PP_MV + v * OSC_PP_PERMILLE / 1000` for several windows while the
+//!   output is enabled.
+//!
+//! Each condition is debounced over status windows ([`ENTER_WINDOWS`] to set,
+//! [`EXIT_WINDOWS`] to clear). Advisory only: nothing here touches the output.
+//!
+//! Pure state machine like `loadlynx_protocol::link_safe`; host-testable via
+//! the package library target.
+
+use loadlynx_protocol::{
+    SENSE_WARN_HIGH_LEAD_DROP, SENSE_WARN_OSCILLATION, SENSE_WARN_REMOTE_OPEN,
+    SENSE_WARN_REMOTE_REVERSED,
+};
+
+/// Local voltage above which a DUT is considered connected.
+pub const DUT_PRESENT_MV: i32 = 1_000;
+/// Remote reading below `-REVERSED_MIN_MV` counts as reversed.
+pub const REVERSED_MIN_MV: i32 = 2_000;
+/// Remote reading below this (including the negative floor of an
+/// unconnected input) counts as no remote signal.
+pub const REMOTE_LOST_MV: i32 = 500;
+
+/// Minimum load current for a lead-resistance sample.
+pub const DROP_MIN_CURRENT_MA: i32 = 500;
+/// Absolute local-vs-remote drop that warns regardless of current.
+pub const DROP_WARN_MV: i32 = 1_000;
+/// Estimated cable resistance (both force leads) that warns.
+pub const LEAD_WARN_MOHM: u32 = 200;
+
+/// Fixed ripple allowance covering ADC noise on the ×12.4 sense chain.
+pub const OSC_MIN_PP_MV: i32 = 300;
+/// Ripple allowance proportional to the regulated voltage.
+pub const OSC_PP_PERMILLE: i32 = 20;
+
+pub const ENTER_WINDOWS: u8 = 3;
+pub const EXIT_WINDOWS: u8 = 5;
+
+/// Status-window inputs (smoothed values, as sent in `FastStatus`).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Sample {
+    pub v_local_mv: i32,
+    pub v_remote_mv: i32,
+    pub i_total_ma: i32,
+    pub remote_active: bool,
+    pub output_enabled: bool,
+}
+
+/// Window-count hysteresis for one advisory condition; also used by
+/// `channel_share`.
+#[derive(Copy, Clone, Debug, Default)]
+pub(crate) struct Debounce {
+    pub(crate) active: bool,
+    count: u8,
+}
+
+impl Debounce {
+    pub(crate) const fn new() -> Self {
+        Self {
+            active: false,
+            count: 0,
+        }
+    }
+
+    fn update(&mut self, condition: bool) -> bool {
+        self.update_windows(condition, ENTER_WINDOWS, EXIT_WINDOWS)
+    }
+
+    /// Flip after `enter` (set) or `exit` (clear) consecutive disagreeing windows.
+    pub(crate) fn update_windows(&mut self, condition: bool, enter: u8, exit: u8) -> bool {
+        if condition == self.active {
+            self.count = 0;
+        } else {
+            self.count = self.count.saturating_add(1);
+            let needed = if self.active { exit } else { enter };
+            if self.count >= needed {
+                self.active = condition;
+                self.count = 0;
+            }
+        }
+        self.active
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct SenseDiag {
+    reversed: Debounce,
+    open: Debounce,
+    high_drop: Debounce,
+    oscillation: Debounce,
+    /// Remote sense was active since the DUT was connected.
+    remote_seen: bool,
+    /// Cable resistance estimate (mΩ, ×8 fixed point for the 1/8 EMA).
+    lead_mohm_x8: Option<u32>,
+    ripple_min_mv: i32,
+    ripple_max_mv: i32,
+}
+
+impl SenseDiag {
+    pub const fn new() -> Self {
+        Self {
+            reversed: Debounce::new(),
+            open: Debounce::new(),
+            high_drop: Debounce::new(),
+            oscillation: Debounce::new(),
+            remote_seen: false,
+            lead_mohm_x8: None,
+            ripple_min_mv: i32::MAX,
+            ripple_max_mv: i32::MIN,
+        }
+    }
+
+    /// Record one control-tick value of the regulated voltage (remote when
+    /// active, else local) for the ripple check.
+    pub fn sample_ripple(&mut self, v_main_mv: i32) {
+        self.ripple_min_mv = self.ripple_min_mv.min(v_main_mv);
+        self.ripple_max_mv = self.ripple_max_mv.max(v_main_mv);
+    }
+
+    /// Evaluate one status window; returns the `SENSE_WARN_*` bitmask.
+    pub fn update(&mut self, s: Sample) -> u32 {
+        let dut_present = s.v_local_mv >= DUT_PRESENT_MV;
+        if !dut_present {
+            self.remote_seen = false;
+        } else if s.remote_active {
+            self.remote_seen = true;
+        }
+
+        let reversed = dut_present && s.v_remote_mv <= -REVERSED_MIN_MV;
+        self.reversed.update(reversed);
+        self.open.update(
+            dut_present
+                && self.remote_seen
+                && !s.remote_active
+                && !reversed
+                && s.v_remote_mv < REMOTE_LOST_MV,
+        );
+
+        let drop_mv = (s.v_local_mv - s.v_remote_mv).abs();
+        if !s.remote_active {
+            self.lead_mohm_x8 = None;
+        } else if s.i_total_ma >= DROP_MIN_CURRENT_MA {
+            // mV / mA = Ω; ×1000 for mΩ.
+            let sample_x8 = (drop_mv as u64 * 1_000 * 8 / s.i_total_ma as u64) as u32;
+            self.lead_mohm_x8 = Some(match self.lead_mohm_x8 {
+                Some(prev) => prev - prev / 8 + sample_x8 / 8,
+                None => sample_x8,
+            });
+        }
+        self.high_drop.update(
+            s.remote_active
+                && s.i_total_ma >= DROP_MIN_CURRENT_MA
+                && (drop_mv >= DROP_WARN_MV
+                    || self
+                        .lead_resistance_mohm()
+                        .is_some_and(|mohm| mohm >= LEAD_WARN_MOHM)),
+        );
+
+        let ripple_pp = if self.ripple_max_mv >= self.ripple_min_mv {
+            self.ripple_max_mv - self.ripple_min_mv
+        } else {
+            0
+        };
+        let v_main = if s.remote_active {
+            s.v_remote_mv
+        } else {
+            s.v_local_mv
+        };
+        let allowed_pp = OSC_MIN_PP_MV + v_main.max(0) / 1_000 * OSC_PP_PERMILLE;
+        self.oscillation
+            .update(s.output_enabled && dut_present && ripple_pp > allowed_pp);
+        self.ripple_min_mv = i32::MAX;
+        self.ripple_max_mv = i32::MIN;
+
+        self.warnings()
+    }
+
+    pub fn warnings(&self) -> u32 {
+        let mut flags = 0;
+        if self.reversed.active {
+            flags |= SENSE_WARN_REMOTE_REVERSED;
+        }
+        if self.open.active {
+            flags |= SENSE_WARN_REMOTE_OPEN;
+        }
+        if self.high_drop.active {
+            flags |= SENSE_WARN_HIGH_LEAD_DROP;
+        }
+        if self.oscillation.active {
+            flags |= SENSE_WARN_OSCILLATION;
+        }
+        flags
+    }
+
+    /// Estimated force-lead resistance while remote sense is active.
+    pub fn lead_resistance_mohm(&self) -> Option<u32> {
+        self.lead_mohm_x8.map(|x8| x8 / 8)
+    }
+}
+
+impl Default for SenseDiag {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IDLE: Sample = Sample {
+        v_local_mv: 12_000,
+        v_remote_mv: 12_000,
+        i_total_ma: 0,
+        remote_active: true,
+        output_enabled: false,
+    };
+
+    fn run(diag: &mut SenseDiag, sample: Sample, windows: u8) -> u32 {
+        let mut flags = 0;
+        for _ in 0..windows {
+            flags = diag.update(sample);
+        }
+        flags
+    }
+
+    #[test]
+    fn healthy_wiring_reports_nothing() {
+        let mut diag = SenseDiag::new();
+        let loaded = Sample {
+            v_local_mv: 11_950,
+            i_total_ma: 5_000,
+            output_enabled: true,
+            ..IDLE
+        };
+        for _ in 0..10 {
+            diag.sample_ripple(12_000);
+            diag.sample_ripple(12_050);
+            assert_eq!(diag.update(loaded), 0);
+        }
+        // 50 mV over 5 A = 10 mΩ.
+        assert_eq!(diag.lead_resistance_mohm(), Some(10));
+    }
+
+    #[test]
+    fn lead_drop_is_debounced_and_estimated() {
+        let mut diag = SenseDiag::new();
+        let thin_leads = Sample {
+            v_local_mv: 10_500,
+            i_total_ma: 5_000,
+            ..IDLE
+        };
+        assert_eq!(run(&mut diag, thin_leads, ENTER_WINDOWS - 1), 0);
+        assert_eq!(diag.update(thin_leads), SENSE_WARN_HIGH_LEAD_DROP);
+        assert_eq!(diag.lead_resistance_mohm(), Some(300));
+
+        // Load off: the estimate is held, the warning clears after the exit window.
+        assert_eq!(
+            run(&mut diag, IDLE, EXIT_WINDOWS - 1),
+            SENSE_WARN_HIGH_LEAD_DROP
+        );
+        assert_eq!(diag.update(IDLE), 0);
+        assert_eq!(diag.lead_resistance_mohm(), Some(300));
+
+        let local_only = Sample {
+            remote_active: false,
+            ..IDLE
+        };
+        diag.update(local_only);
+        assert_eq!(diag.lead_resistance_mohm(), None);
+    }
+
+    #[test]
+    fn lead_coming_off_is_open_but_local_only_is_not() {
+        // Captured from hardware: an unconnected remote input reads slightly
+        // negative through the calibration intercept.
+        let lost = Sample {
+            v_remote_mv: -876,
+            remote_active: false,
+            ..IDLE
+        };
+
+        let mut never_used = SenseDiag::new();
+        assert_eq!(run(&mut never_used, lost, 10), 0);
+
+        let mut diag = SenseDiag::new();
+        diag.update(IDLE);
+        assert_eq!(run(&mut diag, lost, ENTER_WINDOWS), SENSE_WARN_REMOTE_OPEN);
+
+        // Unplugging the DUT forgets the previous connection.
+        let unplugged = Sample {
+            v_local_mv: 0,
+            ..lost
+        };
+        assert_eq!(run(&mut diag, unplugged, EXIT_WINDOWS), 0);
+        assert_eq!(run(&mut diag, lost, 10), 0);
+    }
+
+    #[test]
+    fn negative_remote_is_reversed() {
+        let mut diag = SenseDiag::new();
+        let reversed = Sample {
+            v_remote_mv: -11_900,
+            remote_active: false,
+            ..IDLE
+        };
+        assert_eq!(
+            run(&mut diag, reversed, ENTER_WINDOWS),
+            SENSE_WARN_REMOTE_REVERSED
+        );
+    }
+
+    #[test]
+    fn ripple_beyond_allowance_is_oscillation() {
+        let mut diag = SenseDiag::new();
+        let enabled = Sample {
+            output_enabled: true,
+            ..IDLE
+        };
+        // Allowance at 12 V: 300 + 12 * 20 = 540 mV.
+        let mut flags = 0;
+        for _ in 0..ENTER_WINDOWS {
+            diag.sample_ripple(11_700);
+            diag.sample_ripple(12_300);
+            flags = diag.update(enabled);
+        }
+        assert_eq!(flags, SENSE_WARN_OSCILLATION);
+
+        let mut disabled = SenseDiag::new();
+        for _ in 0..ENTER_WINDOWS {
+            disabled.sample_ripple(11_700);
+            disabled.sample_ripple(12_300);
+            assert_eq!(disabled.update(IDLE), 0);
+        }
+    }
+}
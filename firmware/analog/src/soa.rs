@@ -0,0 +1,206 @@
+//! MOSFET safe-operating-area (SOA) limits for the two load channels.
+//!
+//! The flat limits in `main.rs` (`OC_LIMIT_CH_MA`, `HARD_MAX_P_MW`) are only
+//! safe at low V_DS. In linear mode the FETs (IRFP4468 class, see
+//! `docs/components/mosfets`) lose DC power capability quickly as V_DS rises
+//! (hot-spotting), and further as the heatsink warms up. This module turns a
+//! per-channel V_DS → max-power table plus the sink temperature into a
+//! per-channel current ceiling that the 10 kHz loop applies right before the
+//! DAC update.
+//!
+//! Integer-only on purpose: it runs every control tick. Host-testable via the
+//! package library target (`src/lib.rs`).
+
+/// One SOA table entry: maximum continuous dissipation for one channel at the
+/// given drain-source voltage (sink at or below [`DERATE_START_MC`]).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SoaPoint {
+    pub vds_mv: i32,
+    pub max_p_mw: u32,
+}
+
+/// CH1 DC SOA (per FET, heatsink-mounted). Entries must be sorted by `vds_mv`.
+pub const SOA_TABLE_CH1: [SoaPoint; 6] = [
+    SoaPoint {
+        vds_mv: 0,
+        max_p_mw: 100_000,
+    },
+    SoaPoint {
+        vds_mv: 12_000,
+        max_p_mw: 100_000,
+    },
+    SoaPoint {
+        vds_mv: 24_000,
+        max_p_mw: 80_000,
+    },
+    SoaPoint {
+        vds_mv: 36_000,
+        max_p_mw: 60_000,
+    },
+    SoaPoint {
+        vds_mv: 48_000,
+        max_p_mw: 45_000,
+    },
+    SoaPoint {
+        vds_mv: 55_000,
+        max_p_mw: 40_000,
+    },
+];
+
+/// CH2 uses the same FET and heatsink today; kept separate so a channel with a
+/// different part or airflow can get its own curve.
+pub const SOA_TABLE_CH2: [SoaPoint; 6] = SOA_TABLE_CH1;
+
+/// Below this V_DS the channel is current-limited only (P/V would blow up).
+pub const VDS_MIN_MV: i32 = 500;
+
+/// Sink temperature where SOA temperature derating starts (m°C).
+pub const DERATE_START_MC: i32 = 50_000;
+/// Sink temperature where the derating reaches [`DERATE_END_PERMILLE`] (m°C).
+/// Matches the hard `SINK_TEMP_LIMIT_MC` trip.
+pub const DERATE_END_MC: i32 = 100_000;
+pub const DERATE_END_PERMILLE: u32 = 250;
+
+/// Piecewise-linear max power for `vds_mv`, clamped to the table ends.
+pub fn max_power_mw(table: &[SoaPoint], vds_mv: i32) -> u32 {
+    let Some(first) = table.first() else {
+        return 0;
+    };
+    if vds_mv <= first.vds_mv {
+        return first.max_p_mw;
+    }
+    for pair in table.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        if vds_mv <= b.vds_mv {
+            let span = (b.vds_mv - a.vds_mv).max(1) as i64;
+            let dx = (vds_mv - a.vds_mv) as i64;
+            let dp = b.max_p_mw as i64 - a.max_p_mw as i64;
+            return (a.max_p_mw as i64 + dp * dx / span).max(0) as u32;
+        }
+    }
+    table[table.len() - 1].max_p_mw
+}
+
+/// Sink-temperature derate factor in permille (1000 = no derating).
+pub fn temp_derate_permille(sink_temp_mc: i32) -> u32 {
+    if sink_temp_mc <= DERATE_START_MC {
+        return 1_000;
+    }
+    if sink_temp_mc >= DERATE_END_MC {
+        return DERATE_END_PERMILLE;
+    }
+    let span = (DERATE_END_MC - DERATE_START_MC) as u32;
+    let dx = (sink_temp_mc - DERATE_START_MC) as u32;
+    1_000 - (1_000 - DERATE_END_PERMILLE) * dx / span
+}
+
+/// Maximum channel current (mA) allowed by the SOA at `vds_mv` and
+/// `sink_temp_mc`, never above `i_ch_max_ma`.
+pub fn max_channel_current_ma(
+    table: &[SoaPoint],
+    vds_mv: i32,
+    sink_temp_mc: i32,
+    i_ch_max_ma: i32,
+) -> i32 {
+    if vds_mv <= VDS_MIN_MV {
+        return i_ch_max_ma;
+    }
+    let p_mw =
+        max_power_mw(table, vds_mv) as i64 * temp_derate_permille(sink_temp_mc) as i64 / 1_000;
+    let i_ma = p_mw * 1_000 / vds_mv as i64;
+    i_ma.clamp(0, i_ch_max_ma as i64) as i32
+}
+
+/// Clamp the split channel targets to their SOA ceilings.
+///
+/// With `rebalance` set (normal two-channel operation), current that one
+/// channel cannot carry is moved to the other channel's remaining headroom
+/// before anything is dropped. Returns the new targets and whether the total
+/// was reduced (i.e. the load is SOA-limited).
+pub fn clamp_channels(
+    ch1_ma: i32,
+    ch2_ma: i32,
+    limit_ch1_ma: i32,
+    limit_ch2_ma: i32,
+    rebalance: bool,
+) -> (i32, i32, bool) {
+    let requested = ch1_ma.max(0) + ch2_ma.max(0);
+    let mut ch1 = ch1_ma.max(0);
+    let mut ch2 = ch2_ma.max(0);
+
+    if rebalance {
+        if ch1 > limit_ch1_ma {
+            ch2 += ch1 - limit_ch1_ma;
+            ch1 = limit_ch1_ma;
+        }
+        if ch2 > limit_ch2_ma {
+            let excess = ch2 - limit_ch2_ma;
+            ch2 = limit_ch2_ma;
+            ch1 = (ch1 + excess).min(limit_ch1_ma);
+        }
+    }
+
+    ch1 = ch1.clamp(0, limit_ch1_ma.max(0));
+    ch2 = ch2.clamp(0, limit_ch2_ma.max(0));
+    (ch1, ch2, ch1 + ch2 < requested)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_interpolates_and_clamps() {
+        assert_eq!(max_power_mw(&SOA_TABLE_CH1, -1), 100_000);
+        assert_eq!(max_power_mw(&SOA_TABLE_CH1, 6_000), 100_000);
+        assert_eq!(max_power_mw(&SOA_TABLE_CH1, 18_000), 90_000);
+        assert_eq!(max_power_mw(&SOA_TABLE_CH1, 55_000), 40_000);
+        assert_eq!(max_power_mw(&SOA_TABLE_CH1, 80_000), 40_000);
+        assert_eq!(max_power_mw(&[], 10_000), 0);
+    }
+
+    #[test]
+    fn temperature_derates_linearly() {
+        assert_eq!(temp_derate_permille(25_000), 1_000);
+        assert_eq!(temp_derate_permille(75_000), 625);
+        assert_eq!(temp_derate_permille(120_000), DERATE_END_PERMILLE);
+    }
+
+    #[test]
+    fn high_vds_limits_channel_current() {
+        // 55 V: 40 W / 55 V ≈ 727 mA per channel.
+        assert_eq!(
+            max_channel_current_ma(&SOA_TABLE_CH1, 55_000, 30_000, 5_000),
+            727
+        );
+        // Low V_DS stays on the flat per-channel clamp.
+        assert_eq!(
+            max_channel_current_ma(&SOA_TABLE_CH1, 5_000, 30_000, 5_000),
+            5_000
+        );
+        assert_eq!(
+            max_channel_current_ma(&SOA_TABLE_CH1, 300, 90_000, 5_000),
+            5_000
+        );
+    }
+
+    #[test]
+    fn rebalance_moves_excess_before_limiting() {
+        assert_eq!(
+            clamp_channels(1_500, 0, 1_000, 1_000, true),
+            (1_000, 500, false)
+        );
+        assert_eq!(
+            clamp_channels(1_500, 1_500, 1_000, 1_000, true),
+            (1_000, 1_000, true)
+        );
+        assert_eq!(
+            clamp_channels(1_500, 0, 1_000, 1_000, false),
+            (1_000, 0, true)
+        );
+        assert_eq!(
+            clamp_channels(800, 800, 1_000, 1_000, true),
+            (800, 800, false)
+        );
+    }
+}
@@ -0,0 +1,564 @@
+//! Reflash the analog STM32G431 through its ROM bootloader over the
+//! board-to-board UART.
+//!
+//! There is no BOOT0 or NRST wire between the boards, so the update rides on
+//! the link itself:
+//!
+//! - [`begin`] asks `setmode_tx_task` to open the bridge. It sends
+//!   `MSG_ENTER_BOOTLOADER`; the analog firmware ACKs, drops its outputs and
+//!   resets into the ROM bootloader (AN3155), which listens on the same USART3
+//!   pins. The TX task then switches UART1 to 8E1 and from then on only
+//!   forwards [`BRIDGE_TX`]; `uart_link_task_dma` diverts received bytes into
+//!   [`BRIDGE_RX`] instead of the SLIP decoder.
+//! - The session syncs with the ROM, checks the product ID and erases only the
+//!   pages the image covers.
+//! - [`write`] programs 256-byte blocks and reads each one back. A short last
+//!   block is padded with `0xFF` to the double-word programming size.
+//! - [`finish`] compares the streamed SHA-256 with the catalog digest, sends
+//!   `Go 0x0800_0000`, restores 8N1 and lets the link watchdog re-handshake
+//!   with the new image.
+//!
+//! Every step is short enough to fit one USB JSONL request. A bridge left
+//! without traffic for [`BRIDGE_IDLE_TIMEOUT`] is closed and the session
+//! fails. The ROM stays active until the next power cycle, so an interrupted
+//! update can simply be retried; once the board has been power cycled with a
+//! half-written flash, only a probe can recover it. Read-out protection
+//! (RDP level 1) makes the ROM NACK the erase and is reported as such.
+
+use core::cell::Cell;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use defmt::*;
+use embassy_futures::select::{Either3, select3};
+use embassy_sync::blocking_mutex::{Mutex as BlockingMutex, raw::CriticalSectionRawMutex};
+use embassy_sync::mutex::Mutex;
+use embassy_sync::pipe::Pipe;
+use embassy_sync::signal::Signal;
+use embassy_time::{Duration, Timer, with_timeout};
+use esp_hal::Async;
+use esp_hal::uart::{Parity, uhci};
+use loadlynx_protocol::{
+    ENTER_BOOTLOADER_MAGIC, EnterBootloader, encode_enter_bootloader_frame, slip_encode,
+};
+use loadlynx_stm32_boot as boot;
+use sha2::{Digest, Sha256};
+
+/// Largest `data` chunk of one `analog_flash_write` USB request.
+pub const MAX_WRITE_CHUNK: usize = 1024;
+/// Close the bridge when the host stops talking to it.
+pub const BRIDGE_IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Time for the analog side to ACK, reset and start the ROM.
+const ENTER_SETTLE: Duration = Duration::from_millis(200);
+const BRIDGE_OPEN_TIMEOUT: Duration = Duration::from_secs(1);
+const SYNC_ATTEMPTS: u8 = 5;
+const SYNC_TIMEOUT: Duration = Duration::from_millis(100);
+const REPLY_TIMEOUT: Duration = Duration::from_millis(500);
+/// Page erase is 40 ms worst case; 64 pages plus margin.
+const ERASE_TIMEOUT: Duration = Duration::from_millis(3_000);
+
+static BRIDGE_REQUESTED: AtomicBool = AtomicBool::new(false);
+static BRIDGE_ACTIVE: AtomicBool = AtomicBool::new(false);
+static BRIDGE_OPENED: Signal<CriticalSectionRawMutex, bool> = Signal::new();
+static BRIDGE_CLOSE: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+static BRIDGE_CLOSED: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+static BRIDGE_TX: Pipe<CriticalSectionRawMutex, 512> = Pipe::new();
+static BRIDGE_RX: Pipe<CriticalSectionRawMutex, 1024> = Pipe::new();
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FlashState {
+    Idle,
+    Erasing,
+    Writing,
+    Verifying,
+    Done,
+    Error,
+}
+
+impl FlashState {
+    pub const fn name(self) -> &'static str {
+        match self {
+            FlashState::Idle => "idle",
+            FlashState::Erasing => "erasing",
+            FlashState::Writing => "writing",
+            FlashState::Verifying => "verifying",
+            FlashState::Done => "done",
+            FlashState::Error => "error",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct FlashStatus {
+    pub state: FlashState,
+    pub received: u32,
+    pub size: u32,
+    pub last_error: Option<&'static str>,
+}
+
+static STATUS: BlockingMutex<CriticalSectionRawMutex, Cell<FlashStatus>> =
+    BlockingMutex::new(Cell::new(FlashStatus {
+        state: FlashState::Idle,
+        received: 0,
+        size: 0,
+        last_error: None,
+    }));
+
+pub fn status() -> FlashStatus {
+    STATUS.lock(|cell| cell.get())
+}
+
+fn update_status(f: impl FnOnce(&mut FlashStatus)) {
+    STATUS.lock(|cell| {
+        let mut status = cell.get();
+        f(&mut status);
+        cell.set(status);
+    });
+}
+
+fn fail(message: &'static str) {
+    update_status(|status| {
+        status.state = FlashState::Error;
+        status.last_error = Some(message);
+    });
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FlashError {
+    Busy,
+    NoSession,
+    /// The bridge could not be opened (no UHCI TX task, UART reconfig failed).
+    Unavailable(&'static str),
+    BadImageSize,
+    /// `offset` is not where the previous write stopped.
+    OutOfOrder,
+    /// The ROM bootloader NACKed, timed out or answered unexpectedly.
+    Bootloader(&'static str),
+    DigestMismatch,
+}
+
+impl FlashError {
+    pub const fn message(self) -> &'static str {
+        match self {
+            FlashError::Busy => "another analog firmware update is in progress",
+            FlashError::NoSession => "no analog firmware update in progress",
+            FlashError::Unavailable(message) | FlashError::Bootloader(message) => message,
+            FlashError::BadImageSize => "image is empty or larger than the analog flash",
+            FlashError::OutOfOrder => "write offset does not continue the image",
+            FlashError::DigestMismatch => "image SHA-256 does not match",
+        }
+    }
+}
+
+struct Session {
+    size: u32,
+    expected_sha256: [u8; 32],
+    hasher: Sha256,
+    block: [u8; boot::MAX_BLOCK_LEN],
+    block_len: usize,
+    /// Bytes already programmed; always a multiple of the block size.
+    flushed: u32,
+}
+
+impl Session {
+    fn received(&self) -> u32 {
+        self.flushed + self.block_len as u32
+    }
+}
+
+static SESSION: Mutex<CriticalSectionRawMutex, Option<Session>> = Mutex::new(None);
+
+/// Polled by `setmode_tx_task` once per loop.
+pub(crate) fn bridge_requested() -> bool {
+    BRIDGE_REQUESTED.load(Ordering::Acquire)
+}
+
+/// Called by the UART RX task for every received chunk. Returns `true` when
+/// the bytes belong to the bootloader session and must not reach the SLIP
+/// decoder.
+pub(crate) fn bridge_rx(chunk: &[u8]) -> bool {
+    if !BRIDGE_ACTIVE.load(Ordering::Acquire) {
+        return false;
+    }
+    let mut rest = chunk;
+    while !rest.is_empty() {
+        match BRIDGE_RX.try_write(rest) {
+            Ok(n) => rest = &rest[n..],
+            Err(_) => {
+                warn!(
+                    "analog flash: bridge RX overflow, {} bytes dropped",
+                    rest.len()
+                );
+                break;
+            }
+        }
+    }
+    true
+}
+
+/// Run the bridge until the session closes it or it goes idle. Called by
+/// `setmode_tx_task`, which owns the UART TX half, when
+/// [`bridge_requested`] is set.
+pub(crate) async fn serve_bridge(
+    uhci_tx: &mut uhci::UhciTx<'static, Async>,
+    seq: u8,
+    raw: &mut [u8; 64],
+    slip: &mut [u8; 192],
+) {
+    BRIDGE_REQUESTED.store(false, Ordering::Release);
+    let enter = EnterBootloader {
+        magic: ENTER_BOOTLOADER_MAGIC,
+    };
+    let sent = match encode_enter_bootloader_frame(seq, &enter, raw) {
+        Ok(frame_len) => match slip_encode(&raw[..frame_len], slip) {
+            Ok(slip_len) => uhci_tx.uart_tx.write_async(&slip[..slip_len]).await.is_ok(),
+            Err(_) => false,
+        },
+        Err(_) => false,
+    };
+    let _ = uhci_tx.uart_tx.flush_async().await;
+    if !sent {
+        warn!("analog flash: EnterBootloader frame not sent");
+    }
+    // Even without an ACK the analog side may already sit in the ROM from an
+    // earlier, interrupted update; the sync step tells.
+    Timer::after(ENTER_SETTLE).await;
+    if uhci_tx
+        .uart_tx
+        .apply_config(&crate::link_uart_config(Parity::Even))
+        .is_err()
+    {
+        warn!("analog flash: switching UART1 to 8E1 failed");
+        BRIDGE_OPENED.signal(false);
+        return;
+    }
+    BRIDGE_CLOSE.reset();
+    BRIDGE_RX.clear();
+    BRIDGE_ACTIVE.store(true, Ordering::Release);
+    BRIDGE_OPENED.signal(true);
+    info!("analog flash: bridge open (seq={})", seq);
+
+    let mut buf = [0u8; 64];
+    loop {
+        match select3(
+            BRIDGE_TX.read(&mut buf),
+            BRIDGE_CLOSE.wait(),
+            Timer::after(BRIDGE_IDLE_TIMEOUT),
+        )
+        .await
+        {
+            Either3::First(n) => {
+                if uhci_tx.uart_tx.write_async(&buf[..n]).await.is_err() {
+                    warn!("analog flash: bridge write error");
+                }
+                let _ = uhci_tx.uart_tx.flush_async().await;
+            }
+            Either3::Second(()) => break,
+            Either3::Third(()) => {
+                warn!("analog flash: bridge idle; closing");
+                fail("bridge idle timeout");
+                break;
+            }
+        }
+    }
+
+    BRIDGE_ACTIVE.store(false, Ordering::Release);
+    if uhci_tx
+        .uart_tx
+        .apply_config(&crate::link_uart_config(Parity::None))
+        .is_err()
+    {
+        warn!("analog flash: restoring UART1 8N1 failed");
+    }
+    BRIDGE_TX.clear();
+    BRIDGE_RX.clear();
+    BRIDGE_CLOSED.signal(());
+    info!("analog flash: bridge closed");
+}
+
+async fn open_bridge() -> Result<(), FlashError> {
+    BRIDGE_OPENED.reset();
+    BRIDGE_REQUESTED.store(true, Ordering::Release);
+    // Enter frame, settle time and the UART reconfiguration.
+    match with_timeout(ENTER_SETTLE + BRIDGE_OPEN_TIMEOUT, BRIDGE_OPENED.wait()).await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(FlashError::Unavailable("UART1 could not switch to 8E1")),
+        Err(_) => {
+            BRIDGE_REQUESTED.store(false, Ordering::Release);
+            Err(FlashError::Unavailable(
+                "UART bridge unavailable (UHCI TX task not running)",
+            ))
+        }
+    }
+}
+
+async fn close_bridge() {
+    if !BRIDGE_ACTIVE.load(Ordering::Acquire) {
+        return;
+    }
+    BRIDGE_CLOSED.reset();
+    BRIDGE_CLOSE.signal(());
+    let _ = with_timeout(BRIDGE_OPEN_TIMEOUT, BRIDGE_CLOSED.wait()).await;
+}
+
+async fn send(bytes: &[u8]) {
+    BRIDGE_TX.write_all(bytes).await;
+}
+
+async fn recv(buf: &mut [u8], timeout: Duration) -> Result<(), FlashError> {
+    with_timeout(timeout, async {
+        let mut at = 0;
+        while at < buf.len() {
+            at += BRIDGE_RX.read(&mut buf[at..]).await;
+        }
+    })
+    .await
+    .map_err(|_| FlashError::Bootloader("bootloader reply timeout"))
+}
+
+async fn expect_ack(timeout: Duration, what: &'static str) -> Result<(), FlashError> {
+    let mut reply = [0u8; 1];
+    recv(&mut reply, timeout).await?;
+    boot::check_reply(reply[0]).map_err(|_| FlashError::Bootloader(what))
+}
+
+async fn send_command(cmd: u8, what: &'static str) -> Result<(), FlashError> {
+    send(&boot::command(cmd)).await;
+    expect_ack(REPLY_TIMEOUT, what).await
+}
+
+async fn sync() -> Result<(), FlashError> {
+    for _ in 0..SYNC_ATTEMPTS {
+        BRIDGE_RX.clear();
+        send(&[boot::SYNC]).await;
+        let mut reply = [0u8; 1];
+        if recv(&mut reply, SYNC_TIMEOUT).await.is_ok() {
+            // NACK: the ROM was already synced by an earlier attempt and took
+            // 0x7F as an unknown command.
+            if matches!(reply[0], boot::ACK | boot::NACK) {
+                BRIDGE_RX.clear();
+                return Ok(());
+            }
+        }
+    }
+    Err(FlashError::Bootloader(
+        "no answer from the STM32 bootloader",
+    ))
+}
+
+async fn check_product_id() -> Result<(), FlashError> {
+    send_command(boot::CMD_GET_ID, "bootloader rejected GET_ID").await?;
+    let mut reply = [0u8; 4];
+    recv(&mut reply, REPLY_TIMEOUT).await?;
+    let pid = boot::parse_product_id(&reply[..3])
+        .map_err(|_| FlashError::Bootloader("malformed GET_ID reply"))?;
+    boot::check_reply(reply[3]).map_err(|_| FlashError::Bootloader("malformed GET_ID reply"))?;
+    if pid != boot::G431_PRODUCT_ID {
+        warn!("analog flash: unexpected product id 0x{:03x}", pid);
+        return Err(FlashError::Bootloader("analog MCU is not an STM32G431"));
+    }
+    Ok(())
+}
+
+async fn erase(pages: u16) -> Result<(), FlashError> {
+    let mut frame = [0u8; 2 + 2 * (boot::G431_FLASH_LEN / boot::G431_PAGE_LEN) as usize + 1];
+    let len = boot::erase_pages(0, pages, &mut frame).map_err(|_| FlashError::BadImageSize)?;
+    send_command(
+        boot::CMD_EXTENDED_ERASE,
+        "bootloader rejected erase (read-out protection?)",
+    )
+    .await?;
+    send(&frame[..len]).await;
+    expect_ack(ERASE_TIMEOUT, "flash erase failed").await
+}
+
+async fn write_block(addr: u32, data: &[u8]) -> Result<(), FlashError> {
+    let mut frame = [0u8; boot::MAX_FRAME_LEN];
+    let len = boot::write_data(data, &mut frame).map_err(|_| FlashError::BadImageSize)?;
+    send_command(boot::CMD_WRITE_MEMORY, "bootloader rejected write").await?;
+    send(&boot::address(addr)).await;
+    expect_ack(REPLY_TIMEOUT, "bootloader rejected write address").await?;
+    send(&frame[..len]).await;
+    expect_ack(REPLY_TIMEOUT, "flash write failed").await
+}
+
+async fn read_block(addr: u32, out: &mut [u8]) -> Result<(), FlashError> {
+    let length = boot::read_length(out.len()).map_err(|_| FlashError::BadImageSize)?;
+    send_command(boot::CMD_READ_MEMORY, "bootloader rejected read").await?;
+    send(&boot::address(addr)).await;
+    expect_ack(REPLY_TIMEOUT, "bootloader rejected read address").await?;
+    send(&length).await;
+    expect_ack(REPLY_TIMEOUT, "bootloader rejected read length").await?;
+    recv(out, REPLY_TIMEOUT).await
+}
+
+/// Program one (padded) block and compare it against a read-back.
+async fn program_block(addr: u32, data: &[u8]) -> Result<(), FlashError> {
+    write_block(addr, data).await?;
+    let mut readback = [0u8; boot::MAX_BLOCK_LEN];
+    read_block(addr, &mut readback[..data.len()]).await?;
+    if readback[..data.len()] != *data {
+        return Err(FlashError::Bootloader("flash read-back mismatch"));
+    }
+    Ok(())
+}
+
+async fn go() -> Result<(), FlashError> {
+    send_command(boot::CMD_GO, "bootloader rejected GO").await?;
+    send(&boot::address(boot::G431_FLASH_BASE)).await;
+    expect_ack(REPLY_TIMEOUT, "bootloader rejected GO address").await
+}
+
+/// Open the bridge, sync with the ROM and erase the pages `size` bytes need.
+pub async fn begin(size: u32, expected_sha256: [u8; 32]) -> Result<(), FlashError> {
+    let pages = boot::page_span(size).map_err(|_| FlashError::BadImageSize)?;
+    let mut session = SESSION.lock().await;
+    if session.is_some() {
+        if BRIDGE_ACTIVE.load(Ordering::Acquire) {
+            return Err(FlashError::Busy);
+        }
+        // The bridge went idle under an abandoned session.
+        *session = None;
+    }
+    STATUS.lock(|cell| {
+        cell.set(FlashStatus {
+            state: FlashState::Erasing,
+            received: 0,
+            size,
+            last_error: None,
+        })
+    });
+    info!("analog flash: begin size={} pages={}", size, pages);
+
+    let opened = async {
+        open_bridge().await?;
+        sync().await?;
+        check_product_id().await?;
+        erase(pages).await
+    }
+    .await;
+    if let Err(err) = opened {
+        fail(err.message());
+        close_bridge().await;
+        return Err(err);
+    }
+
+    *session = Some(Session {
+        size,
+        expected_sha256,
+        hasher: Sha256::new(),
+        block: [0xFF; boot::MAX_BLOCK_LEN],
+        block_len: 0,
+        flushed: 0,
+    });
+    update_status(|status| status.state = FlashState::Writing);
+    Ok(())
+}
+
+/// Append `data` at `offset`, which must continue the previous write.
+/// Returns the number of image bytes received so far.
+pub async fn write(offset: u32, mut data: &[u8]) -> Result<u32, FlashError> {
+    let mut guard = SESSION.lock().await;
+    let session = guard.as_mut().ok_or(FlashError::NoSession)?;
+    if !BRIDGE_ACTIVE.load(Ordering::Acquire) {
+        *guard = None;
+        return Err(FlashError::NoSession);
+    }
+    if offset != session.received() {
+        return Err(FlashError::OutOfOrder);
+    }
+    if session.received() + data.len() as u32 > session.size {
+        return Err(abort(&mut guard, FlashError::BadImageSize).await);
+    }
+    session.hasher.update(data);
+    while !data.is_empty() {
+        let take = (boot::MAX_BLOCK_LEN - session.block_len).min(data.len());
+        session.block[session.block_len..session.block_len + take].copy_from_slice(&data[..take]);
+        session.block_len += take;
+        data = &data[take..];
+        if session.block_len == boot::MAX_BLOCK_LEN {
+            let addr = boot::G431_FLASH_BASE + session.flushed;
+            if let Err(err) = program_block(addr, &session.block).await {
+                return Err(abort(&mut guard, err).await);
+            }
+            session.flushed += boot::MAX_BLOCK_LEN as u32;
+            session.block_len = 0;
+        }
+    }
+    let received = session.received();
+    update_status(|status| status.received = received);
+    Ok(received)
+}
+
+/// Program the padded tail, check the digest and start the new image.
+pub async fn finish() -> Result<(), FlashError> {
+    let mut guard = SESSION.lock().await;
+    let session = guard.as_mut().ok_or(FlashError::NoSession)?;
+    if !BRIDGE_ACTIVE.load(Ordering::Acquire) {
+        *guard = None;
+        return Err(FlashError::NoSession);
+    }
+    if session.received() != session.size {
+        return Err(abort(&mut guard, FlashError::BadImageSize).await);
+    }
+    update_status(|status| status.state = FlashState::Verifying);
+    let streamed: [u8; 32] = session.hasher.finalize_reset().into();
+    if streamed != session.expected_sha256 {
+        // The tail stays unprogrammed; the ROM keeps running, so a retry
+        // starts over with `begin`.
+        return Err(abort(&mut guard, FlashError::DigestMismatch).await);
+    }
+    if session.block_len > 0 {
+        let len = boot::padded_len(session.block_len);
+        session.block[session.block_len..len].fill(0xFF);
+        let addr = boot::G431_FLASH_BASE + session.flushed;
+        if let Err(err) = program_block(addr, &session.block[..len]).await {
+            return Err(abort(&mut guard, err).await);
+        }
+    }
+    if let Err(err) = go().await {
+        return Err(abort(&mut guard, err).await);
+    }
+    let size = session.size;
+    *guard = None;
+    close_bridge().await;
+    update_status(|status| status.state = FlashState::Done);
+    info!(
+        "analog flash: {} bytes written; analog firmware restarting",
+        size
+    );
+    Ok(())
+}
+
+/// Drop the current session (e.g. the upload connection ended early).
+pub async fn cancel(message: &'static str) {
+    let mut guard = SESSION.lock().await;
+    if guard.take().is_some() {
+        fail(message);
+        close_bridge().await;
+    }
+}
+
+async fn abort(session: &mut Option<Session>, err: FlashError) -> FlashError {
+    *session = None;
+    fail(err.message());
+    close_bridge().await;
+    err
+}
+
+/// Decode an even-length hex string into `out`; returns the byte count.
+pub fn parse_hex_into(text: &str, out: &mut [u8]) -> Option<usize> {
+    let text = text.as_bytes();
+    if !text.len().is_multiple_of(2) || text.len() / 2 > out.len() {
+        return None;
+    }
+    let nibble = |c: u8| match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'a'..=b'f' => Some(c - b'a' + 10),
+        b'A'..=b'F' => Some(c - b'A' + 10),
+        _ => None,
+    };
+    for (byte, pair) in out.iter_mut().zip(text.chunks(2)) {
+        *byte = nibble(pair[0])? << 4 | nibble(pair[1])?;
+    }
+    Some(text.len() / 2)
+}
@@ -0,0 +1,619 @@
+//! Bearer-token authentication for the HTTP API.
+//!
+//! Every route except discovery (`/api/v1/ping`, `/health`,
+//! `/api/v1/identity`) and pairing needs `Authorization: Bearer <token>`.
+//! The SSE (`GET /api/v1/status`) and WebSocket (`GET /api/v1/ws`) upgrades
+//! also take `?access_token=<token>`, since EventSource and WebSocket clients
+//! cannot set headers; no other route reads the query. Each token carries a
+//! [`Role`] and the route table in [`required_role`] decides what it may do.
+//!
+//! Tokens are issued in two ways:
+//!
+//! - touchscreen pairing: `POST /api/v1/auth/pair/start` shows a six-digit
+//!   code on the dashboard for `PAIRING_WINDOW`. Someone at the device taps
+//!   the code line to pick the role (read → control → admin); only then does
+//!   `POST /api/v1/auth/pair` exchange the code for a token, with the role
+//!   chosen on screen. `PAIRING_ATTEMPTS` wrong codes end the window and lock
+//!   pairing out for `PAIRING_LOCKOUT_BASE`, doubling with every exhausted
+//!   window up to `PAIRING_LOCKOUT_MAX` until a pairing succeeds;
+//! - USB (`create_api_token`), which is trusted like `get_ota_token`.
+//!
+//! Up to `AUTH_MAX_TOKENS` tokens live in the EEPROM auth blob, keyed by
+//! label; issuing a token for an existing label replaces it. Firmware uploads
+//! keep using the separate OTA token (`ota.rs`).
+
+use core::cell::Cell;
+use core::fmt::Write as _;
+
+use alloc::string::String;
+use embassy_sync::blocking_mutex::{Mutex as BlockingMutex, raw::CriticalSectionRawMutex};
+use embassy_sync::mutex::Mutex;
+use embassy_time::{Duration, Instant};
+use esp_hal::rng::Rng;
+use heapless::Vec;
+
+use crate::EepromMutex;
+use crate::eeprom::{self, AUTH_MAX_TOKENS, AUTH_TOKEN_LEN, AuthTokenEntry};
+use crate::net::write_json_string_escaped;
+
+/// How long a pairing code stays on screen.
+const PAIRING_WINDOW: Duration = Duration::from_secs(120);
+/// Wrong codes before the pairing window closes early.
+const PAIRING_ATTEMPTS: u8 = 5;
+/// Lockout after the first exhausted window; doubles per further one.
+const PAIRING_LOCKOUT_BASE: Duration = Duration::from_secs(60);
+const PAIRING_LOCKOUT_MAX: Duration = Duration::from_secs(3600);
+const PAIRING_CODE_SPACE: u32 = 1_000_000;
+pub const TOKEN_HEX_LEN: usize = 2 * AUTH_TOKEN_LEN;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    /// Telemetry and settings reads.
+    Read,
+    /// Everything `Read` may do, plus output, setpoints, presets, PD,
+    /// protection, measurements and fault/counter resets.
+    Control,
+    /// Everything, including calibration, Wi-Fi, MQTT, the clock and token
+    /// management.
+    Admin,
+}
+
+impl Role {
+    pub const fn name(self) -> &'static str {
+        match self {
+            Role::Read => "read",
+            Role::Control => "control",
+            Role::Admin => "admin",
+        }
+    }
+
+    /// Four-letter form for the dashboard's pairing line.
+    pub const fn abbrev(self) -> &'static str {
+        match self {
+            Role::Read => "READ",
+            Role::Control => "CTRL",
+            Role::Admin => "ADMN",
+        }
+    }
+
+    pub fn parse(text: &str) -> Option<Self> {
+        match text {
+            "read" => Some(Role::Read),
+            "control" => Some(Role::Control),
+            "admin" => Some(Role::Admin),
+            _ => None,
+        }
+    }
+
+    const fn as_u8(self) -> u8 {
+        self as u8
+    }
+
+    const fn from_u8(raw: u8) -> Option<Self> {
+        match raw {
+            0 => Some(Role::Read),
+            1 => Some(Role::Control),
+            2 => Some(Role::Admin),
+            _ => None,
+        }
+    }
+}
+
+/// Minimum role for `method path` (query already stripped); `None` for the
+/// routes that stay open.
+pub fn required_role(method: &str, path: &str) -> Option<Role> {
+    match (method, path) {
+        ("OPTIONS", _)
+        | ("GET", "/api/v1/ping" | "/health" | "/api/v1/identity")
+        | ("POST", "/api/v1/auth/pair/start" | "/api/v1/auth/pair") => None,
+        ("GET", "/api/v1/wifi/credentials" | "/api/v1/auth/tokens") => Some(Role::Admin),
+        ("GET", _) => Some(Role::Read),
+        (_, "/api/v1/wifi" | "/api/v1/mqtt" | "/api/v1/time") => Some(Role::Admin),
+        _ if path.starts_with("/api/v1/calibration/")
+            || path.starts_with("/api/v1/wifi/")
+            || path.starts_with("/api/v1/auth/") =>
+        {
+            Some(Role::Admin)
+        }
+        _ => Some(Role::Control),
+    }
+}
+
+/// Routes whose clients cannot send headers (EventSource, WebSocket), so
+/// the token may ride in the `access_token` query parameter instead. Tokens
+/// in URLs end up in logs and history; keep this list to the upgrades.
+fn accepts_query_token(method: &str, path: &str) -> bool {
+    matches!((method, path), ("GET", "/api/v1/status" | "/api/v1/ws"))
+}
+
+/// The token presented with `method path`: the bearer header, or on the
+/// stream upgrades the `access_token` query parameter.
+pub fn presented_token<'a>(
+    method: &str,
+    path: &str,
+    authorization: Option<&'a str>,
+    query: &'a str,
+) -> Option<&'a str> {
+    authorization
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+        .or_else(|| {
+            query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("access_token="))
+                .filter(|_| accepts_query_token(method, path))
+        })
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuthError {
+    NotPairing,
+    /// Nobody has picked a role on the device screen yet.
+    NotConfirmed,
+    BadCode,
+    LockedOut {
+        retry_after_s: u64,
+    },
+    Full,
+    UnknownLabel,
+    InvalidLabel(&'static str),
+    Storage,
+}
+
+impl AuthError {
+    pub const fn message(self) -> &'static str {
+        match self {
+            AuthError::NotPairing => "no pairing code is active; start pairing first",
+            AuthError::NotConfirmed => "tap the pairing line on the device to choose a role first",
+            AuthError::BadCode => "wrong pairing code",
+            AuthError::LockedOut { .. } => "too many wrong pairing codes; try again later",
+            AuthError::Full => "all token slots are in use; revoke one first",
+            AuthError::UnknownLabel => "no token with that label",
+            AuthError::InvalidLabel(msg) => msg,
+            AuthError::Storage => "EEPROM access failed",
+        }
+    }
+}
+
+type Entries = Vec<AuthTokenEntry, AUTH_MAX_TOKENS>;
+
+/// EEPROM copy of the token list, loaded on first use so every request does
+/// not cost an I2C read.
+static TOKENS: Mutex<CriticalSectionRawMutex, Option<Entries>> = Mutex::new(None);
+
+#[derive(Clone, Copy)]
+struct Pairing {
+    code: u32,
+    expires_at: Instant,
+    attempts_left: u8,
+    /// Picked on the touchscreen; `None` until someone taps.
+    role: Option<Role>,
+}
+
+/// The open window plus the lockout, which outlives any single window so
+/// restarting pairing does not reset it.
+#[derive(Clone, Copy)]
+struct PairingState {
+    window: Option<Pairing>,
+    /// Windows exhausted by wrong codes since the last successful pairing.
+    strikes: u8,
+    locked_until: Option<Instant>,
+}
+
+impl PairingState {
+    /// Seconds left on the lockout, if any.
+    fn locked_for(&self, now: Instant) -> Option<u64> {
+        self.locked_until
+            .filter(|until| *until > now)
+            .map(|until| (until - now).as_secs().max(1))
+    }
+
+    fn strike(&mut self, now: Instant) {
+        self.window = None;
+        self.strikes = self.strikes.saturating_add(1);
+        self.locked_until = Some(now + lockout_for(self.strikes));
+    }
+}
+
+static PAIRING: BlockingMutex<CriticalSectionRawMutex, Cell<PairingState>> =
+    BlockingMutex::new(Cell::new(PairingState {
+        window: None,
+        strikes: 0,
+        locked_until: None,
+    }));
+
+async fn with_tokens<R>(
+    eeprom: &'static EepromMutex,
+    f: impl FnOnce(&Entries) -> R,
+) -> Result<R, AuthError> {
+    let mut guard = TOKENS.lock().await;
+    if guard.is_none() {
+        let blob = eeprom
+            .lock()
+            .await
+            .read_auth_blob()
+            .await
+            .map_err(|_| AuthError::Storage)?;
+        *guard = Some(eeprom::decode_auth_blob(&blob).unwrap_or_default());
+    }
+    Ok(f(guard.as_ref().expect("token list loaded")))
+}
+
+async fn store_tokens(eeprom: &'static EepromMutex, entries: &Entries) -> Result<(), AuthError> {
+    eeprom
+        .lock()
+        .await
+        .write_auth_blob(&eeprom::encode_auth_blob(entries))
+        .await
+        .map_err(|_| AuthError::Storage)
+}
+
+/// Role of a presented hex token, comparing against every stored token
+/// without an early exit.
+pub async fn authorize(eeprom: &'static EepromMutex, presented: &str) -> Option<Role> {
+    let presented = crate::ota::parse_hex::<AUTH_TOKEN_LEN>(presented)?;
+    with_tokens(eeprom, |entries| {
+        let mut role = None;
+        for entry in entries.iter() {
+            let diff = presented
+                .iter()
+                .zip(entry.token.iter())
+                .fold(0u8, |diff, (a, b)| diff | (a ^ b));
+            if diff == 0 {
+                role = Role::from_u8(entry.role);
+            }
+        }
+        role
+    })
+    .await
+    .ok()
+    .flatten()
+}
+
+/// Issue a token for `label`, replacing any token that label already had.
+pub async fn issue(
+    eeprom: &'static EepromMutex,
+    role: Role,
+    label: &str,
+) -> Result<heapless::String<TOKEN_HEX_LEN>, AuthError> {
+    let entry = AuthTokenEntry::new(random_token(), role.as_u8(), label)
+        .map_err(AuthError::InvalidLabel)?;
+    let updated = with_tokens(eeprom, |entries| {
+        let mut updated = entries.clone();
+        if let Some(existing) = updated.iter_mut().find(|e| e.label() == label) {
+            *existing = entry;
+        } else {
+            updated.push(entry).map_err(|_| AuthError::Full)?;
+        }
+        Ok(updated)
+    })
+    .await??;
+    store_tokens(eeprom, &updated).await?;
+    *TOKENS.lock().await = Some(updated);
+
+    let mut out = heapless::String::new();
+    for byte in entry.token {
+        let _ = write!(out, "{:02x}", byte);
+    }
+    Ok(out)
+}
+
+pub async fn revoke(eeprom: &'static EepromMutex, label: &str) -> Result<(), AuthError> {
+    let updated = with_tokens(eeprom, |entries| {
+        let mut updated = entries.clone();
+        let idx = updated
+            .iter()
+            .position(|e| e.label() == label)
+            .ok_or(AuthError::UnknownLabel)?;
+        updated.remove(idx);
+        Ok(updated)
+    })
+    .await??;
+    store_tokens(eeprom, &updated).await?;
+    *TOKENS.lock().await = Some(updated);
+    Ok(())
+}
+
+/// `{"tokens":[{"label":..,"role":..}],"max_tokens":4}`; the tokens
+/// themselves are never listed.
+pub async fn render_tokens_json(
+    buf: &mut String,
+    eeprom: &'static EepromMutex,
+) -> Result<(), AuthError> {
+    let entries = with_tokens(eeprom, |entries| entries.clone()).await?;
+    buf.clear();
+    buf.push_str("{\"tokens\":[");
+    for (idx, entry) in entries.iter().enumerate() {
+        if idx > 0 {
+            buf.push(',');
+        }
+        buf.push_str("{\"label\":\"");
+        write_json_string_escaped(buf, entry.label());
+        buf.push_str("\",\"role\":\"");
+        buf.push_str(Role::from_u8(entry.role).map_or("unknown", Role::name));
+        buf.push_str("\"}");
+    }
+    let _ = write!(buf, "],\"max_tokens\":{}}}", AUTH_MAX_TOKENS);
+    Ok(())
+}
+
+/// Show a pairing code on the dashboard, or keep the one already showing so
+/// repeated requests cannot reroll it. Returns the seconds left.
+pub fn start_pairing() -> Result<u64, AuthError> {
+    let now = Instant::now();
+    PAIRING.lock(|cell| {
+        let mut state = cell.get();
+        if let Some(retry_after_s) = state.locked_for(now) {
+            return Err(AuthError::LockedOut { retry_after_s });
+        }
+        let pairing = match state.window {
+            Some(pairing) if pairing.expires_at > now => pairing,
+            _ => Pairing {
+                code: random_code(|| Rng::new().random()),
+                expires_at: now + PAIRING_WINDOW,
+                attempts_left: PAIRING_ATTEMPTS,
+                role: None,
+            },
+        };
+        state.window = Some(pairing);
+        cell.set(state);
+        Ok((pairing.expires_at - now).as_secs())
+    })
+}
+
+/// The code the dashboard should show and the role picked for it so far, if
+/// pairing is open.
+pub fn pairing_prompt() -> Option<(u32, Option<Role>)> {
+    let now = Instant::now();
+    PAIRING.lock(|cell| {
+        cell.get()
+            .window
+            .filter(|pairing| pairing.expires_at > now)
+            .map(|pairing| (pairing.code, pairing.role))
+    })
+}
+
+/// Touchscreen tap on the pairing line: step the granted role
+/// read → control → admin → read. Returns `false` when no window is open.
+pub fn cycle_pairing_role() -> bool {
+    let now = Instant::now();
+    PAIRING.lock(|cell| {
+        let mut state = cell.get();
+        let Some(pairing) = state.window.as_mut().filter(|p| p.expires_at > now) else {
+            return false;
+        };
+        pairing.role = Some(match pairing.role {
+            None | Some(Role::Admin) => Role::Read,
+            Some(Role::Read) => Role::Control,
+            Some(Role::Control) => Role::Admin,
+        });
+        cell.set(state);
+        true
+    })
+}
+
+/// Exchange the on-screen code for a token carrying the role picked on the
+/// screen. Each wrong code uses up one of `PAIRING_ATTEMPTS`; running out
+/// starts the lockout. A correct code closes the window and clears it.
+pub async fn pair(
+    eeprom: &'static EepromMutex,
+    code: u32,
+    label: &str,
+) -> Result<(heapless::String<TOKEN_HEX_LEN>, Role), AuthError> {
+    eeprom::validate_auth_label(label).map_err(AuthError::InvalidLabel)?;
+    let now = Instant::now();
+    let role = PAIRING.lock(|cell| {
+        let mut state = cell.get();
+        let result = check_pairing_code(&mut state, code, now);
+        cell.set(state);
+        result
+    })?;
+    Ok((issue(eeprom, role, label).await?, role))
+}
+
+fn check_pairing_code(
+    state: &mut PairingState,
+    code: u32,
+    now: Instant,
+) -> Result<Role, AuthError> {
+    if let Some(retry_after_s) = state.locked_for(now) {
+        return Err(AuthError::LockedOut { retry_after_s });
+    }
+    let Some(pairing) = state.window.filter(|pairing| pairing.expires_at > now) else {
+        state.window = None;
+        return Err(AuthError::NotPairing);
+    };
+    if pairing.code != code {
+        if pairing.attempts_left > 1 {
+            state.window = Some(Pairing {
+                attempts_left: pairing.attempts_left - 1,
+                ..pairing
+            });
+        } else {
+            state.strike(now);
+        }
+        return Err(AuthError::BadCode);
+    }
+    let role = pairing.role.ok_or(AuthError::NotConfirmed)?;
+    *state = PairingState {
+        window: None,
+        strikes: 0,
+        locked_until: None,
+    };
+    Ok(role)
+}
+
+/// `PAIRING_LOCKOUT_BASE · 2^(strikes-1)`, capped at `PAIRING_LOCKOUT_MAX`.
+fn lockout_for(strikes: u8) -> Duration {
+    let doublings = u32::from(strikes.saturating_sub(1)).min(16);
+    let secs = PAIRING_LOCKOUT_BASE.as_secs() << doublings;
+    Duration::from_secs(secs.min(PAIRING_LOCKOUT_MAX.as_secs()))
+}
+
+/// Uniform six-digit code: draws falling in the partial block above the
+/// largest multiple of the code space are rejected, so `% 1_000_000` has no
+/// bias towards low codes.
+fn random_code(mut next: impl FnMut() -> u32) -> u32 {
+    const ZONE: u32 = u32::MAX - u32::MAX % PAIRING_CODE_SPACE;
+    loop {
+        let draw = next();
+        if draw < ZONE {
+            return draw % PAIRING_CODE_SPACE;
+        }
+    }
+}
+
+fn random_token() -> [u8; AUTH_TOKEN_LEN] {
+    let rng = Rng::new();
+    let mut token = [0u8; AUTH_TOKEN_LEN];
+    for chunk in token.chunks_mut(4) {
+        chunk.copy_from_slice(&rng.random().to_le_bytes());
+    }
+    token
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn discovery_and_pairing_stay_open() {
+        assert_eq!(required_role("GET", "/api/v1/identity"), None);
+        assert_eq!(required_role("GET", "/health"), None);
+        assert_eq!(required_role("OPTIONS", "/api/v1/control"), None);
+        assert_eq!(required_role("POST", "/api/v1/auth/pair"), None);
+        assert_eq!(required_role("POST", "/api/v1/auth/pair/start"), None);
+    }
+
+    #[test]
+    fn routes_map_to_roles() {
+        assert_eq!(required_role("GET", "/api/v1/status"), Some(Role::Read));
+        assert_eq!(required_role("GET", "/api/v1/ws"), Some(Role::Read));
+        assert_eq!(
+            required_role("GET", "/api/v1/calibration/profile"),
+            Some(Role::Read)
+        );
+        assert_eq!(
+            required_role("POST", "/api/v1/control"),
+            Some(Role::Control)
+        );
+        assert_eq!(required_role("PUT", "/api/v1/cc"), Some(Role::Control));
+        assert_eq!(
+            required_role("POST", "/api/v1/soft-reset"),
+            Some(Role::Control)
+        );
+        assert_eq!(
+            required_role("POST", "/api/v1/calibration/apply"),
+            Some(Role::Admin)
+        );
+        assert_eq!(required_role("DELETE", "/api/v1/wifi"), Some(Role::Admin));
+        assert_eq!(
+            required_role("POST", "/api/v1/wifi/networks/add"),
+            Some(Role::Admin)
+        );
+        assert_eq!(
+            required_role("GET", "/api/v1/wifi/credentials"),
+            Some(Role::Admin)
+        );
+        assert_eq!(required_role("POST", "/api/v1/mqtt"), Some(Role::Admin));
+        assert_eq!(required_role("POST", "/api/v1/time"), Some(Role::Admin));
+        assert_eq!(
+            required_role("POST", "/api/v1/auth/tokens/revoke"),
+            Some(Role::Admin)
+        );
+    }
+
+    #[test]
+    fn pairing_code_is_drawn_without_modulo_bias() {
+        let zone = u32::MAX - u32::MAX % PAIRING_CODE_SPACE;
+        let mut draws = [u32::MAX, zone, zone - 1].into_iter();
+        assert_eq!(
+            random_code(|| draws.next().unwrap()),
+            (zone - 1) % 1_000_000
+        );
+        assert_eq!(random_code(|| 1_042_017), 42_017);
+    }
+
+    #[test]
+    fn pairing_needs_on_screen_role_and_locks_out_brute_force() {
+        let now = Instant::from_secs(1_000);
+        let window = Pairing {
+            code: 42_017,
+            expires_at: now + PAIRING_WINDOW,
+            attempts_left: PAIRING_ATTEMPTS,
+            role: None,
+        };
+        let mut state = PairingState {
+            window: Some(window),
+            strikes: 0,
+            locked_until: None,
+        };
+        assert_eq!(
+            check_pairing_code(&mut state, 42_017, now),
+            Err(AuthError::NotConfirmed)
+        );
+        state.window = Some(Pairing {
+            role: Some(Role::Read),
+            ..window
+        });
+        assert_eq!(check_pairing_code(&mut state, 42_017, now), Ok(Role::Read));
+        assert!(state.window.is_none());
+
+        // Exhaust a window, wait out the lockout, exhaust another: the second
+        // lockout is twice the first and outlives the window that caused it.
+        let mut at = now;
+        for strike in 1..=2u8 {
+            state.window = Some(window);
+            for _ in 0..PAIRING_ATTEMPTS {
+                assert_eq!(
+                    check_pairing_code(&mut state, 1, at),
+                    Err(AuthError::BadCode)
+                );
+            }
+            assert_eq!(state.strikes, strike);
+            assert!(state.window.is_none());
+            assert_eq!(state.locked_until, Some(at + lockout_for(strike)));
+            at += lockout_for(strike);
+        }
+        assert_eq!(lockout_for(2), PAIRING_LOCKOUT_BASE * 2);
+        assert_eq!(lockout_for(30), PAIRING_LOCKOUT_MAX);
+        state.window = Some(window);
+        assert_eq!(
+            check_pairing_code(&mut state, 42_017, now + lockout_for(1)),
+            Err(AuthError::LockedOut { retry_after_s: 120 })
+        );
+    }
+
+    #[test]
+    fn header_token_wins_over_query() {
+        assert_eq!(
+            presented_token(
+                "GET",
+                "/api/v1/status",
+                Some("Bearer abc "),
+                "access_token=def"
+            ),
+            Some("abc")
+        );
+        assert_eq!(
+            presented_token("GET", "/api/v1/ws", None, "x=1&access_token=def"),
+            Some("def")
+        );
+        assert_eq!(
+            presented_token("GET", "/api/v1/status", Some("Basic abc"), ""),
+            None
+        );
+    }
+
+    #[test]
+    fn query_token_only_on_stream_upgrades() {
+        let query = "access_token=def";
+        assert_eq!(presented_token("GET", "/api/v1/control", None, query), None);
+        assert_eq!(presented_token("PUT", "/api/v1/cc", None, query), None);
+        assert_eq!(presented_token("POST", "/api/v1/status", None, query), None);
+        assert_eq!(
+            presented_token("PUT", "/api/v1/cc", Some("Bearer abc"), query),
+            Some("abc")
+        );
+    }
+}
@@ -1,8 +1,9 @@
 use core::sync::atomic::Ordering;
 
 use loadlynx_calibration_format as calfmt;
-use loadlynx_protocol::{CalKind, LoadMode, PdStatus};
+use loadlynx_protocol::{CalKind, FAULT_POLICY_ALL, LoadMode, PdStatus, ProtectionConfig};
 
+use crate::thermal::ThermalModelConfig;
 use crate::ui::preset_panel::{PresetPanelDigit, PresetPanelField};
 
 pub const PRESET_COUNT: usize = 5;
@@ -393,6 +394,10 @@ pub struct ControlState {
     pub output_enabled: bool,
     pub calibration_cc_override: Option<CalibrationCcOverride>,
     calibration_cc_restore_output_enabled: Option<bool>,
+    /// CC target (mA) of a running IR measurement; replaces the active
+    /// preset's mode/target outside calibration (see `ir_measure`).
+    pub measure_cc_override: Option<i32>,
+    measure_restore_output_enabled: Option<bool>,
     pub adjust_digit: AdjustDigit,
     pub ui_view: UiView,
     pub panel_selected_field: PresetPanelField,
@@ -405,6 +410,9 @@ pub struct ControlState {
     pub pd_draft: PdConfig,
     pub pd_settings_focus: PdSettingsFocus,
     pub pd_settings_digit: AdjustDigit,
+    /// Persisted analog protection thresholds (EEPROM-backed); pushed to the
+    /// analog side by the SetMode TX task.
+    pub protection: ProtectionConfig,
 }
 
 impl ControlState {
@@ -422,6 +430,8 @@ impl ControlState {
             output_enabled: false,
             calibration_cc_override: None,
             calibration_cc_restore_output_enabled: None,
+            measure_cc_override: None,
+            measure_restore_output_enabled: None,
             adjust_digit: AdjustDigit::DEFAULT,
             ui_view: UiView::Main,
             panel_selected_field: PresetPanelField::Target,
@@ -431,6 +441,7 @@ impl ControlState {
             pd_draft: pd,
             pd_settings_focus: PdSettingsFocus::DEFAULT,
             pd_settings_digit: AdjustDigit::Tenths,
+            protection: ProtectionConfig::DEFAULT,
         }
     }
 
@@ -449,6 +460,10 @@ impl ControlState {
             self.calibration_cc_restore_output_enabled = Some(output_enabled);
             return;
         }
+        // A manual toggle ends a running IR measurement.
+        if self.measure_cc_override.take().is_some() {
+            self.measure_restore_output_enabled = None;
+        }
         self.set_live_output_enabled(output_enabled);
     }
 
@@ -477,9 +492,44 @@ impl ControlState {
             self.calibration_cc_override = Some(override_state);
         }
         self.calibration_cc_restore_output_enabled = None;
+        self.measure_cc_override = None;
+        self.measure_restore_output_enabled = None;
         self.set_live_output_enabled(false);
     }
 
+    /// Start (or retarget) the IR-measurement CC override and switch the
+    /// output on; the pre-measurement output state is restored by
+    /// `end_measure_override`.
+    pub fn begin_measure_override(&mut self, target_i_ma: i32) {
+        self.measure_restore_output_enabled
+            .get_or_insert(self.output_enabled);
+        self.measure_cc_override = Some(target_i_ma.clamp(0, HARD_MAX_I_MA_TOTAL));
+        self.set_live_output_enabled(true);
+    }
+
+    /// Retarget a running measurement; `false` once it was aborted by a
+    /// forced output-off or a manual toggle.
+    pub fn set_measure_target(&mut self, target_i_ma: i32) -> bool {
+        match self.measure_cc_override.as_mut() {
+            Some(target) => {
+                *target = target_i_ma.clamp(0, HARD_MAX_I_MA_TOTAL);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drop the measurement override and restore the output state it found.
+    /// Returns whether an override was still active.
+    pub fn end_measure_override(&mut self) -> bool {
+        let restore_output_enabled = self.measure_restore_output_enabled.take();
+        if self.measure_cc_override.take().is_none() {
+            return false;
+        }
+        self.set_live_output_enabled(restore_output_enabled.unwrap_or(false));
+        true
+    }
+
     pub fn set_calibration_cc_override(&mut self, target_i_ma: i32, output_enabled: bool) {
         let target_i_ma = target_i_ma
             .clamp(0, crate::LIMIT_PROFILE_DEFAULT.max_i_ma)
@@ -625,6 +675,18 @@ impl ControlState {
             };
         }
 
+        if let Some(target_i_ma) = self.measure_cc_override {
+            let mut preset = self.active_preset();
+            let max_i_ma_total = preset.max_i_ma_total.min(HARD_MAX_I_MA_TOTAL);
+            preset.mode = LoadMode::Cc;
+            preset.target_p_mw = 0;
+            preset.target_i_ma = target_i_ma.min(max_i_ma_total);
+            return EffectiveOutputCommand {
+                preset,
+                output_enabled: self.output_enabled,
+            };
+        }
+
         EffectiveOutputCommand {
             preset: self.active_preset(),
             output_enabled: self.output_enabled,
@@ -986,10 +1048,266 @@ pub fn decode_pd_blob(
     ))
 }
 
+// ---- EEPROM protection config blob -----------------------------------------
+
+const PROTECTION_MAGIC: [u8; 4] = *b"LLPR";
+// v1: thresholds only, CRC at offset 28 (32-byte slot).
+// v2: adds fault_latch_mask at offset 28, CRC at the end of the 64-byte slot.
+// v3: adds link_loss_timeout_ms at offset 32.
+// v4: adds share_policy at offset 36.
+const PROTECTION_FMT_VERSION: u8 = 4;
+const PROTECTION_V1_CRC_OFFSET: usize = 28;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProtectionBlobError {
+    InvalidMagic,
+    UnsupportedVersion(u8),
+    CrcMismatch { stored: u32, computed: u32 },
+    OutOfRange(&'static str),
+}
+
+pub fn encode_protection_blob(
+    cfg: &ProtectionConfig,
+) -> [u8; crate::eeprom::EEPROM_PROTECTION_LEN] {
+    let mut out = [0u8; crate::eeprom::EEPROM_PROTECTION_LEN];
+    out[0..4].copy_from_slice(&PROTECTION_MAGIC);
+    out[4] = PROTECTION_FMT_VERSION;
+    // out[5..8] reserved = 0
+    put_u32_le(&mut out, 8, cfg.oc_limit_ch_ma as u32);
+    put_u32_le(&mut out, 12, cfg.ov_limit_mv as u32);
+    put_u32_le(&mut out, 16, cfg.mcu_temp_limit_mc as u32);
+    put_u32_le(&mut out, 20, cfg.sink_temp_limit_mc as u32);
+    put_u32_le(&mut out, 24, cfg.i_share_threshold_ma as u32);
+    put_u32_le(&mut out, 28, cfg.fault_latch_mask);
+    put_u32_le(&mut out, 32, cfg.link_loss_timeout_ms as u32);
+    put_u32_le(&mut out, 36, cfg.share_policy as u32);
+
+    let crc_offset = crate::eeprom::EEPROM_PROTECTION_LEN - 4;
+    let crc = calfmt::crc32_ieee(&out[..crc_offset]);
+    put_u32_le(&mut out, crc_offset, crc);
+    out
+}
+
+pub fn decode_protection_blob(
+    bytes: &[u8; crate::eeprom::EEPROM_PROTECTION_LEN],
+) -> Result<ProtectionConfig, ProtectionBlobError> {
+    if bytes[0..4] != PROTECTION_MAGIC {
+        return Err(ProtectionBlobError::InvalidMagic);
+    }
+    let ver = bytes[4];
+    let crc_offset = match ver {
+        1 => PROTECTION_V1_CRC_OFFSET,
+        2 | 3 | PROTECTION_FMT_VERSION => crate::eeprom::EEPROM_PROTECTION_LEN - 4,
+        _ => return Err(ProtectionBlobError::UnsupportedVersion(ver)),
+    };
+    let stored_crc = get_u32_le(bytes, crc_offset);
+    let computed_crc = calfmt::crc32_ieee(&bytes[..crc_offset]);
+    if stored_crc != computed_crc {
+        return Err(ProtectionBlobError::CrcMismatch {
+            stored: stored_crc,
+            computed: computed_crc,
+        });
+    }
+
+    let cfg = ProtectionConfig {
+        oc_limit_ch_ma: get_u32_le(bytes, 8) as i32,
+        ov_limit_mv: get_u32_le(bytes, 12) as i32,
+        mcu_temp_limit_mc: get_u32_le(bytes, 16) as i32,
+        sink_temp_limit_mc: get_u32_le(bytes, 20) as i32,
+        i_share_threshold_ma: get_u32_le(bytes, 24) as i32,
+        // v1 predates the fault policy: keep the historical latch-everything behaviour.
+        fault_latch_mask: if ver == 1 {
+            FAULT_POLICY_ALL
+        } else {
+            get_u32_le(bytes, 28)
+        },
+        link_loss_timeout_ms: if ver < 3 {
+            ProtectionConfig::DEFAULT.link_loss_timeout_ms
+        } else {
+            get_u32_le(bytes, 32) as i32
+        },
+        share_policy: if ver < 4 {
+            ProtectionConfig::DEFAULT.share_policy
+        } else {
+            get_u32_le(bytes, 36) as u8
+        },
+    };
+    // Bounds may tighten between firmware versions; never hand an
+    // out-of-range config to the analog side.
+    cfg.validate()
+        .map_err(|field| ProtectionBlobError::OutOfRange(field.as_str()))?;
+    Ok(cfg)
+}
+
+// ---- EEPROM thermal model blob ---------------------------------------------
+
+const THERMAL_MAGIC: [u8; 4] = *b"LLTH";
+const THERMAL_FMT_VERSION: u8 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ThermalBlobError {
+    InvalidMagic,
+    UnsupportedVersion(u8),
+    CrcMismatch { stored: u32, computed: u32 },
+    OutOfRange(&'static str),
+}
+
+pub fn encode_thermal_blob(cfg: &ThermalModelConfig) -> [u8; crate::eeprom::EEPROM_THERMAL_LEN] {
+    let mut out = [0u8; crate::eeprom::EEPROM_THERMAL_LEN];
+    out[0..4].copy_from_slice(&THERMAL_MAGIC);
+    out[4] = THERMAL_FMT_VERSION;
+    out[5] = cfg.min_derate_pct;
+    out[6] = cfg.step_pct;
+    // out[7] reserved = 0
+    put_u32_le(&mut out, 8, cfg.rth_mc_per_w);
+    put_u32_le(&mut out, 12, cfg.tau_s);
+    put_u32_le(&mut out, 16, cfg.margin_mc);
+    put_u32_le(&mut out, 20, cfg.recover_step_ms);
+
+    let crc_offset = crate::eeprom::EEPROM_THERMAL_LEN - 4;
+    let crc = calfmt::crc32_ieee(&out[..crc_offset]);
+    put_u32_le(&mut out, crc_offset, crc);
+    out
+}
+
+/// An out-of-range field (bounds may tighten between firmware versions) names
+/// the field, so the caller falls back to the built-in model.
+pub fn decode_thermal_blob(
+    bytes: &[u8; crate::eeprom::EEPROM_THERMAL_LEN],
+) -> Result<ThermalModelConfig, ThermalBlobError> {
+    if bytes[0..4] != THERMAL_MAGIC {
+        return Err(ThermalBlobError::InvalidMagic);
+    }
+    if bytes[4] != THERMAL_FMT_VERSION {
+        return Err(ThermalBlobError::UnsupportedVersion(bytes[4]));
+    }
+    let crc_offset = crate::eeprom::EEPROM_THERMAL_LEN - 4;
+    let stored_crc = get_u32_le(bytes, crc_offset);
+    let computed_crc = calfmt::crc32_ieee(&bytes[..crc_offset]);
+    if stored_crc != computed_crc {
+        return Err(ThermalBlobError::CrcMismatch {
+            stored: stored_crc,
+            computed: computed_crc,
+        });
+    }
+
+    let cfg = ThermalModelConfig {
+        rth_mc_per_w: get_u32_le(bytes, 8),
+        tau_s: get_u32_le(bytes, 12),
+        margin_mc: get_u32_le(bytes, 16),
+        min_derate_pct: bytes[5],
+        step_pct: bytes[6],
+        recover_step_ms: get_u32_le(bytes, 20),
+    };
+    cfg.validate().map_err(ThermalBlobError::OutOfRange)?;
+    Ok(cfg)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn protection_blob_roundtrip_and_rejects_out_of_range() {
+        let cfg = ProtectionConfig {
+            oc_limit_ch_ma: 2_500,
+            ov_limit_mv: 24_000,
+            mcu_temp_limit_mc: 95_000,
+            sink_temp_limit_mc: 80_000,
+            i_share_threshold_ma: 1_000,
+            fault_latch_mask: loadlynx_protocol::FAULT_OVERCURRENT,
+            link_loss_timeout_ms: 3_000,
+            share_policy: loadlynx_protocol::SHARE_POLICY_DUAL,
+        };
+        let blob = encode_protection_blob(&cfg);
+        assert_eq!(decode_protection_blob(&blob), Ok(cfg));
+
+        // v1 blobs (no fault policy) still load and default to latching.
+        let mut v1 = [0u8; crate::eeprom::EEPROM_PROTECTION_LEN];
+        v1[..PROTECTION_V1_CRC_OFFSET].copy_from_slice(&blob[..PROTECTION_V1_CRC_OFFSET]);
+        v1[4] = 1;
+        let crc = calfmt::crc32_ieee(&v1[..PROTECTION_V1_CRC_OFFSET]);
+        put_u32_le(&mut v1, PROTECTION_V1_CRC_OFFSET, crc);
+        assert_eq!(
+            decode_protection_blob(&v1),
+            Ok(ProtectionConfig {
+                fault_latch_mask: FAULT_POLICY_ALL,
+                link_loss_timeout_ms: ProtectionConfig::DEFAULT.link_loss_timeout_ms,
+                share_policy: ProtectionConfig::DEFAULT.share_policy,
+                ..cfg
+            })
+        );
+
+        // v2 blobs (no link-loss window) keep their policy and use the default window.
+        let mut v2 = blob;
+        v2[4] = 2;
+        put_u32_le(&mut v2, 32, 0);
+        put_u32_le(&mut v2, 36, 0);
+        let crc_offset = crate::eeprom::EEPROM_PROTECTION_LEN - 4;
+        let crc = calfmt::crc32_ieee(&v2[..crc_offset]);
+        put_u32_le(&mut v2, crc_offset, crc);
+        assert_eq!(
+            decode_protection_blob(&v2),
+            Ok(ProtectionConfig {
+                link_loss_timeout_ms: ProtectionConfig::DEFAULT.link_loss_timeout_ms,
+                share_policy: ProtectionConfig::DEFAULT.share_policy,
+                ..cfg
+            })
+        );
+
+        // v3 blobs (no sharing policy) keep their window and share in auto mode.
+        let mut v3 = blob;
+        v3[4] = 3;
+        put_u32_le(&mut v3, 36, 0);
+        let crc = calfmt::crc32_ieee(&v3[..crc_offset]);
+        put_u32_le(&mut v3, crc_offset, crc);
+        assert_eq!(
+            decode_protection_blob(&v3),
+            Ok(ProtectionConfig {
+                share_policy: ProtectionConfig::DEFAULT.share_policy,
+                ..cfg
+            })
+        );
+
+        let erased = [0xFFu8; crate::eeprom::EEPROM_PROTECTION_LEN];
+        assert_eq!(
+            decode_protection_blob(&erased),
+            Err(ProtectionBlobError::InvalidMagic)
+        );
+
+        let hot = encode_protection_blob(&ProtectionConfig {
+            sink_temp_limit_mc: 150_000,
+            ..cfg
+        });
+        assert_eq!(
+            decode_protection_blob(&hot),
+            Err(ProtectionBlobError::OutOfRange("sink_temp_limit_mc"))
+        );
+    }
+
+    #[test]
+    fn thermal_blob_roundtrip_and_rejects_out_of_range() {
+        let cfg = ThermalModelConfig {
+            rth_mc_per_w: 450,
+            tau_s: 120,
+            ..crate::thermal::THERMAL_MODEL_DEFAULT
+        };
+        let blob = encode_thermal_blob(&cfg);
+        assert_eq!(decode_thermal_blob(&blob), Ok(cfg));
+
+        let erased = [0xFFu8; crate::eeprom::EEPROM_THERMAL_LEN];
+        assert_eq!(
+            decode_thermal_blob(&erased),
+            Err(ThermalBlobError::InvalidMagic)
+        );
+
+        let slow = encode_thermal_blob(&ThermalModelConfig { tau_s: 0, ..cfg });
+        assert_eq!(
+            decode_thermal_blob(&slow),
+            Err(ThermalBlobError::OutOfRange("tau_s"))
+        );
+    }
+
     fn encode_v3_blob(cfg: &PdConfig) -> [u8; crate::eeprom::EEPROM_PD_LEN] {
         let mut out = [0u8; crate::eeprom::EEPROM_PD_LEN];
         out[0..4].copy_from_slice(&PD_MAGIC);
@@ -1272,6 +1590,46 @@ mod tests {
         crate::DESIRED_OUTPUT_ENABLED.store(false, Ordering::Relaxed);
     }
 
+    #[test]
+    fn measure_override_replaces_target_and_restores_output() {
+        let mut state = ControlState::new(default_presets(), PdConfig::default(), false);
+        state.presets[0].mode = LoadMode::Cv;
+        state.presets[0].max_i_ma_total = 1_500;
+        state.output_enabled = false;
+
+        state.begin_measure_override(200);
+        assert!(state.set_measure_target(2_000));
+        let cmd = state.effective_output_command(CalKind::Off);
+        assert_eq!(cmd.preset.mode, LoadMode::Cc);
+        assert_eq!(cmd.preset.target_i_ma, 1_500);
+        assert!(cmd.output_enabled);
+        // Calibration still owns the output while its mode is active.
+        let cal = state.effective_output_command(CalKind::CurrentCh1);
+        assert!(!cal.output_enabled);
+
+        assert!(state.end_measure_override());
+        assert!(!state.output_enabled);
+        assert_eq!(
+            state.effective_output_command(CalKind::Off).preset.mode,
+            LoadMode::Cv
+        );
+
+        // A forced output-off aborts the run; nothing is left to restore.
+        state.begin_measure_override(200);
+        state.force_output_off();
+        assert!(!state.set_measure_target(1_000));
+        assert!(!state.end_measure_override());
+
+        // So does a manual toggle, which keeps the requested state.
+        state.begin_measure_override(200);
+        state.set_normal_output_enabled(true);
+        assert_eq!(state.measure_cc_override, None);
+        assert!(state.output_enabled);
+        assert!(!state.end_measure_override());
+
+        crate::DESIRED_OUTPUT_ENABLED.store(false, Ordering::Relaxed);
+    }
+
     #[test]
     fn clearing_calibration_override_restores_normal_control() {
         let mut state = ControlState::new(default_presets(), PdConfig::default(), false);
@@ -3,25 +3,114 @@ use embassy_time::{Duration, Timer};
 use embedded_hal_async::i2c::I2c as EhI2c;
 use heapless::Vec;
 
+use loadlynx_calibration_format::slots::{
+    CAL_SLOT_COUNT, EEPROM_SLOT_ADDRS, EEPROM_SLOT_DIR_ADDR, EEPROM_SLOT_DIR_LEN,
+};
+use loadlynx_calibration_format::verify::{
+    EEPROM_VERIFY_BASE_ADDR, EEPROM_VERIFY_LEN, EEPROM_VERIFY_RECORD_LEN,
+};
 use loadlynx_calibration_format::{
-    EEPROM_I2C_ADDR_7BIT, EEPROM_PAGE_SIZE_BYTES, EEPROM_PROFILE_BASE_ADDR, EEPROM_PROFILE_LEN,
+    CurveKind, EEPROM_I2C_ADDR_7BIT, EEPROM_PAGE_SIZE_BYTES, EEPROM_PROFILE_BASE_ADDR,
+    EEPROM_PROFILE_LEN,
 };
 
 use crate::i2c0::I2c0Bus;
 
-// Calibration blob is fixed at base=0x0000 len=EEPROM_PROFILE_LEN (v3=1024).
-// Presets are stored in the next non-overlapping region.
+// Calibration slot 0 is fixed at base=0x0000 len=EEPROM_PROFILE_LEN (v3=1024);
+// the slot directory and slots 1.. live at the addresses in
+// `calfmt::slots`, verification records at `calfmt::verify`. Presets are stored in the next non-overlapping region.
 pub const EEPROM_PRESETS_BASE_ADDR: u16 = EEPROM_PROFILE_BASE_ADDR + (EEPROM_PROFILE_LEN as u16);
 pub const EEPROM_PRESETS_LEN: usize = 256;
 pub const EEPROM_PD_BASE_ADDR: u16 = EEPROM_PRESETS_BASE_ADDR + (EEPROM_PRESETS_LEN as u16);
 pub const EEPROM_PD_LEN: usize = 32;
 pub const EEPROM_WIFI_BASE_ADDR: u16 = EEPROM_PD_BASE_ADDR + (EEPROM_PD_LEN as u16);
 pub const EEPROM_WIFI_LEN: usize = 192;
+// Thermal derating model parameters.
+pub const EEPROM_THERMAL_BASE_ADDR: u16 = EEPROM_WIFI_BASE_ADDR + (EEPROM_WIFI_LEN as u16);
+pub const EEPROM_THERMAL_LEN: usize = 32;
+pub const EEPROM_PROTECTION_BASE_ADDR: u16 = EEPROM_THERMAL_BASE_ADDR + (EEPROM_THERMAL_LEN as u16);
+pub const EEPROM_PROTECTION_LEN: usize = 64;
+const _: () = assert!(
+    EEPROM_PROTECTION_BASE_ADDR as usize + EEPROM_PROTECTION_LEN <= EEPROM_SLOT_DIR_ADDR as usize
+);
 pub const WIFI_BLOB_MAGIC: &[u8; 8] = b"LLWIFI1\0";
 pub const WIFI_MAX_SSID_LEN: usize = 32;
 pub const WIFI_MAX_PSK_LEN: usize = 64;
 const WIFI_HEADER_LEN: usize = 10;
 const WIFI_STORED_LEN: usize = WIFI_HEADER_LEN + WIFI_MAX_SSID_LEN + WIFI_MAX_PSK_LEN;
+// MQTT broker settings follow the calibration verification records.
+pub const EEPROM_MQTT_BASE_ADDR: u16 = EEPROM_VERIFY_BASE_ADDR + (EEPROM_VERIFY_LEN as u16);
+pub const EEPROM_MQTT_LEN: usize = 256;
+pub const MQTT_BLOB_MAGIC: &[u8; 8] = b"LLMQTT1\0";
+pub const MQTT_MAX_HOST_LEN: usize = 64;
+pub const MQTT_MAX_USERNAME_LEN: usize = 32;
+pub const MQTT_MAX_PASSWORD_LEN: usize = 64;
+pub const MQTT_MAX_TOPIC_PREFIX_LEN: usize = 64;
+pub const MQTT_PUBLISH_INTERVAL_MS_RANGE: (u16, u16) = (200, 60_000);
+const MQTT_HEADER_LEN: usize = 24;
+const MQTT_HOST_OFFSET: usize = MQTT_HEADER_LEN;
+const MQTT_USERNAME_OFFSET: usize = MQTT_HOST_OFFSET + MQTT_MAX_HOST_LEN;
+const MQTT_PASSWORD_OFFSET: usize = MQTT_USERNAME_OFFSET + MQTT_MAX_USERNAME_LEN;
+const MQTT_TOPIC_PREFIX_OFFSET: usize = MQTT_PASSWORD_OFFSET + MQTT_MAX_PASSWORD_LEN;
+const MQTT_STORED_LEN: usize = MQTT_TOPIC_PREFIX_OFFSET + MQTT_MAX_TOPIC_PREFIX_LEN;
+const MQTT_FLAG_ENABLED: u8 = 0x01;
+/// Execute `<prefix>/cmd/*`; blobs written before the flag existed leave it
+/// clear, so commands stay off until explicitly turned on.
+const MQTT_FLAG_COMMANDS: u8 = 0x02;
+
+const _: () = assert!(MQTT_STORED_LEN <= EEPROM_MQTT_LEN);
+const _: () = assert!(EEPROM_MQTT_BASE_ADDR as usize + EEPROM_MQTT_LEN <= 0x1000);
+
+// The stored Wi-Fi network list follows the MQTT settings and supersedes the
+// single-network blob at `EEPROM_WIFI_BASE_ADDR`, which is only read to
+// migrate devices provisioned before the list existed.
+pub const EEPROM_WIFI_NETWORKS_BASE_ADDR: u16 = EEPROM_MQTT_BASE_ADDR + (EEPROM_MQTT_LEN as u16);
+pub const EEPROM_WIFI_NETWORKS_LEN: usize = 640;
+pub const WIFI_NETWORKS_MAGIC: &[u8; 8] = b"LLWIFI2\0";
+pub const WIFI_MAX_NETWORKS: usize = 6;
+const WIFI_NETWORKS_HEADER_LEN: usize = 16;
+const WIFI_NETWORK_ENTRY_LEN: usize = 2 + WIFI_MAX_SSID_LEN + WIFI_MAX_PSK_LEN;
+
+const _: () = assert!(
+    WIFI_NETWORKS_HEADER_LEN + WIFI_MAX_NETWORKS * WIFI_NETWORK_ENTRY_LEN
+        <= EEPROM_WIFI_NETWORKS_LEN
+);
+const _: () = assert!(EEPROM_WIFI_NETWORKS_BASE_ADDR as usize + EEPROM_WIFI_NETWORKS_LEN <= 0x1000);
+
+// SNTP server override; absent means the build-time default server.
+pub const EEPROM_NTP_BASE_ADDR: u16 =
+    EEPROM_WIFI_NETWORKS_BASE_ADDR + (EEPROM_WIFI_NETWORKS_LEN as u16);
+pub const EEPROM_NTP_LEN: usize = 64;
+pub const NTP_BLOB_MAGIC: &[u8; 8] = b"LLNTP1\0\0";
+pub const NTP_MAX_HOST_LEN: usize = 48;
+const NTP_HEADER_LEN: usize = 16;
+
+const _: () = assert!(NTP_HEADER_LEN + NTP_MAX_HOST_LEN <= EEPROM_NTP_LEN);
+const _: () = assert!(EEPROM_NTP_BASE_ADDR as usize + EEPROM_NTP_LEN <= 0x1000);
+
+// OTA upload token and the unconfirmed-boot counter used for rollback.
+pub const EEPROM_OTA_BASE_ADDR: u16 = EEPROM_NTP_BASE_ADDR + (EEPROM_NTP_LEN as u16);
+pub const EEPROM_OTA_LEN: usize = 64;
+pub const OTA_BLOB_MAGIC: &[u8; 8] = b"LLOTA1\0\0";
+pub const OTA_TOKEN_LEN: usize = 32;
+const OTA_HEADER_LEN: usize = 16;
+const OTA_FLAG_ROLLED_BACK: u8 = 0x01;
+
+const _: () = assert!(OTA_HEADER_LEN + OTA_TOKEN_LEN <= EEPROM_OTA_LEN);
+const _: () = assert!(EEPROM_OTA_BASE_ADDR as usize + EEPROM_OTA_LEN <= 0x1000);
+
+// HTTP API tokens issued by pairing; this region ends the EEPROM.
+pub const EEPROM_AUTH_BASE_ADDR: u16 = EEPROM_OTA_BASE_ADDR + (EEPROM_OTA_LEN as u16);
+pub const EEPROM_AUTH_LEN: usize = 128;
+pub const AUTH_BLOB_MAGIC: &[u8; 8] = b"LLAUTH1\0";
+pub const AUTH_MAX_TOKENS: usize = 4;
+pub const AUTH_TOKEN_LEN: usize = 16;
+pub const AUTH_MAX_LABEL_LEN: usize = 10;
+const AUTH_HEADER_LEN: usize = 16;
+const AUTH_ENTRY_LEN: usize = 2 + AUTH_MAX_LABEL_LEN + AUTH_TOKEN_LEN;
+
+const _: () = assert!(AUTH_HEADER_LEN + AUTH_MAX_TOKENS * AUTH_ENTRY_LEN <= EEPROM_AUTH_LEN);
+const _: () = assert!(EEPROM_AUTH_BASE_ADDR as usize + EEPROM_AUTH_LEN <= 0x1000);
 
 pub struct WifiBlobParts<'a> {
     pub ssid: &'a str,
@@ -29,14 +118,9 @@ pub struct WifiBlobParts<'a> {
 }
 
 pub fn encode_wifi_blob(ssid: &str, psk: &str) -> Result<[u8; EEPROM_WIFI_LEN], &'static str> {
+    validate_wifi_network(ssid, psk)?;
     let ssid_bytes = ssid.as_bytes();
     let psk_bytes = psk.as_bytes();
-    if ssid_bytes.is_empty() || ssid_bytes.len() > WIFI_MAX_SSID_LEN {
-        return Err("ssid length must be 1..32 bytes");
-    }
-    if psk_bytes.len() < 8 || psk_bytes.len() > WIFI_MAX_PSK_LEN {
-        return Err("psk length must be 8..64 bytes");
-    }
     let mut blob = [0xFFu8; EEPROM_WIFI_LEN];
     blob[..WIFI_BLOB_MAGIC.len()].copy_from_slice(WIFI_BLOB_MAGIC);
     blob[8] = ssid_bytes.len() as u8;
@@ -65,6 +149,336 @@ pub fn decode_wifi_blob(blob: &[u8; EEPROM_WIFI_LEN]) -> Option<WifiBlobParts<'_
     })
 }
 
+pub fn validate_wifi_network(ssid: &str, psk: &str) -> Result<(), &'static str> {
+    if ssid.is_empty() || ssid.len() > WIFI_MAX_SSID_LEN {
+        return Err("ssid length must be 1..32 bytes");
+    }
+    if psk.len() < 8 || psk.len() > WIFI_MAX_PSK_LEN {
+        return Err("psk length must be 8..64 bytes");
+    }
+    Ok(())
+}
+
+/// Layout: magic (8), count, reserved up to 16, then `count` entries of
+/// ssid length, psk length, ssid (32), psk (64). List order is priority
+/// order (index 0 is tried first).
+pub fn encode_wifi_networks_blob(
+    networks: &[WifiBlobParts<'_>],
+) -> Result<[u8; EEPROM_WIFI_NETWORKS_LEN], &'static str> {
+    if networks.len() > WIFI_MAX_NETWORKS {
+        return Err("too many Wi-Fi networks");
+    }
+    let mut blob = [0xFFu8; EEPROM_WIFI_NETWORKS_LEN];
+    blob[..WIFI_NETWORKS_MAGIC.len()].copy_from_slice(WIFI_NETWORKS_MAGIC);
+    blob[8] = networks.len() as u8;
+    for (idx, network) in networks.iter().enumerate() {
+        validate_wifi_network(network.ssid, network.psk)?;
+        let entry = WIFI_NETWORKS_HEADER_LEN + idx * WIFI_NETWORK_ENTRY_LEN;
+        let ssid_start = entry + 2;
+        let psk_start = ssid_start + WIFI_MAX_SSID_LEN;
+        blob[entry] = network.ssid.len() as u8;
+        blob[entry + 1] = network.psk.len() as u8;
+        blob[ssid_start..ssid_start + network.ssid.len()].copy_from_slice(network.ssid.as_bytes());
+        blob[psk_start..psk_start + network.psk.len()].copy_from_slice(network.psk.as_bytes());
+    }
+    Ok(blob)
+}
+
+/// `None` when no list has been written yet; `Some` with an empty list once
+/// the user has removed every network.
+pub fn decode_wifi_networks_blob(
+    blob: &[u8; EEPROM_WIFI_NETWORKS_LEN],
+) -> Option<Vec<WifiBlobParts<'_>, WIFI_MAX_NETWORKS>> {
+    if &blob[..WIFI_NETWORKS_MAGIC.len()] != WIFI_NETWORKS_MAGIC {
+        return None;
+    }
+    let count = blob[8] as usize;
+    if count > WIFI_MAX_NETWORKS {
+        return None;
+    }
+    let mut networks = Vec::new();
+    for idx in 0..count {
+        let entry = WIFI_NETWORKS_HEADER_LEN + idx * WIFI_NETWORK_ENTRY_LEN;
+        let ssid_len = blob[entry] as usize;
+        let psk_len = blob[entry + 1] as usize;
+        if ssid_len == 0
+            || ssid_len > WIFI_MAX_SSID_LEN
+            || !(8..=WIFI_MAX_PSK_LEN).contains(&psk_len)
+        {
+            return None;
+        }
+        let ssid_start = entry + 2;
+        let psk_start = ssid_start + WIFI_MAX_SSID_LEN;
+        let _ = networks.push(WifiBlobParts {
+            ssid: str::from_utf8(&blob[ssid_start..ssid_start + ssid_len]).ok()?,
+            psk: str::from_utf8(&blob[psk_start..psk_start + psk_len]).ok()?,
+        });
+    }
+    Some(networks)
+}
+
+/// Broker settings; empty `username`/`password` mean anonymous.
+pub struct MqttBlobParts<'a> {
+    pub enabled: bool,
+    pub commands_enabled: bool,
+    pub host: &'a str,
+    pub port: u16,
+    pub username: &'a str,
+    pub password: &'a str,
+    pub topic_prefix: &'a str,
+    pub publish_interval_ms: u16,
+}
+
+/// Layout: magic (8), flags, host/username/password/prefix lengths, reserved,
+/// port u16 LE, publish interval u16 LE, reserved up to 24, then the
+/// fixed-size string fields.
+pub fn encode_mqtt_blob(parts: &MqttBlobParts<'_>) -> Result<[u8; EEPROM_MQTT_LEN], &'static str> {
+    if parts.host.is_empty() || parts.host.len() > MQTT_MAX_HOST_LEN {
+        return Err("host length must be 1..64 bytes");
+    }
+    if parts.port == 0 {
+        return Err("port must be 1..65535");
+    }
+    if parts.username.len() > MQTT_MAX_USERNAME_LEN {
+        return Err("username length must be 0..32 bytes");
+    }
+    if parts.password.len() > MQTT_MAX_PASSWORD_LEN {
+        return Err("password length must be 0..64 bytes");
+    }
+    if parts.topic_prefix.len() > MQTT_MAX_TOPIC_PREFIX_LEN
+        || !loadlynx_mqtt::is_valid_topic(parts.topic_prefix)
+    {
+        return Err("topic_prefix must be 1..64 bytes without wildcards");
+    }
+    let (min_ms, max_ms) = MQTT_PUBLISH_INTERVAL_MS_RANGE;
+    if !(min_ms..=max_ms).contains(&parts.publish_interval_ms) {
+        return Err("publish_interval_ms must be 200..60000");
+    }
+    let mut blob = [0xFFu8; EEPROM_MQTT_LEN];
+    blob[..MQTT_BLOB_MAGIC.len()].copy_from_slice(MQTT_BLOB_MAGIC);
+    let mut flags = 0;
+    if parts.enabled {
+        flags |= MQTT_FLAG_ENABLED;
+    }
+    if parts.commands_enabled {
+        flags |= MQTT_FLAG_COMMANDS;
+    }
+    blob[8] = flags;
+    blob[9] = parts.host.len() as u8;
+    blob[10] = parts.username.len() as u8;
+    blob[11] = parts.password.len() as u8;
+    blob[12] = parts.topic_prefix.len() as u8;
+    blob[14..16].copy_from_slice(&parts.port.to_le_bytes());
+    blob[16..18].copy_from_slice(&parts.publish_interval_ms.to_le_bytes());
+    for (offset, value) in [
+        (MQTT_HOST_OFFSET, parts.host),
+        (MQTT_USERNAME_OFFSET, parts.username),
+        (MQTT_PASSWORD_OFFSET, parts.password),
+        (MQTT_TOPIC_PREFIX_OFFSET, parts.topic_prefix),
+    ] {
+        blob[offset..offset + value.len()].copy_from_slice(value.as_bytes());
+    }
+    Ok(blob)
+}
+
+pub fn decode_mqtt_blob(blob: &[u8; EEPROM_MQTT_LEN]) -> Option<MqttBlobParts<'_>> {
+    if &blob[..MQTT_BLOB_MAGIC.len()] != MQTT_BLOB_MAGIC {
+        return None;
+    }
+    let parts = MqttBlobParts {
+        enabled: blob[8] & MQTT_FLAG_ENABLED != 0,
+        commands_enabled: blob[8] & MQTT_FLAG_COMMANDS != 0,
+        host: mqtt_field(blob, MQTT_HOST_OFFSET, blob[9], MQTT_MAX_HOST_LEN)?,
+        port: u16::from_le_bytes([blob[14], blob[15]]),
+        username: mqtt_field(blob, MQTT_USERNAME_OFFSET, blob[10], MQTT_MAX_USERNAME_LEN)?,
+        password: mqtt_field(blob, MQTT_PASSWORD_OFFSET, blob[11], MQTT_MAX_PASSWORD_LEN)?,
+        topic_prefix: mqtt_field(
+            blob,
+            MQTT_TOPIC_PREFIX_OFFSET,
+            blob[12],
+            MQTT_MAX_TOPIC_PREFIX_LEN,
+        )?,
+        publish_interval_ms: u16::from_le_bytes([blob[16], blob[17]]),
+    };
+    // Re-run the write-side validation so a corrupted blob reads as unset.
+    encode_mqtt_blob(&parts).ok()?;
+    Some(parts)
+}
+
+pub struct NtpBlobParts<'a> {
+    pub host: &'a str,
+    pub port: u16,
+}
+
+/// Layout: magic (8), host length, reserved, port u16 LE, reserved up to 16,
+/// then the host.
+pub fn encode_ntp_blob(parts: &NtpBlobParts<'_>) -> Result<[u8; EEPROM_NTP_LEN], &'static str> {
+    if parts.host.is_empty()
+        || parts.host.len() > NTP_MAX_HOST_LEN
+        || !parts
+            .host
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'.' || b == b'-')
+    {
+        return Err("server host must be 1..48 bytes of [A-Za-z0-9.-]");
+    }
+    if parts.port == 0 {
+        return Err("server port must be 1..65535");
+    }
+    let mut blob = [0xFFu8; EEPROM_NTP_LEN];
+    blob[..NTP_BLOB_MAGIC.len()].copy_from_slice(NTP_BLOB_MAGIC);
+    blob[8] = parts.host.len() as u8;
+    blob[10..12].copy_from_slice(&parts.port.to_le_bytes());
+    blob[NTP_HEADER_LEN..NTP_HEADER_LEN + parts.host.len()].copy_from_slice(parts.host.as_bytes());
+    Ok(blob)
+}
+
+pub fn decode_ntp_blob(blob: &[u8; EEPROM_NTP_LEN]) -> Option<NtpBlobParts<'_>> {
+    if &blob[..NTP_BLOB_MAGIC.len()] != NTP_BLOB_MAGIC {
+        return None;
+    }
+    let host_len = blob[8] as usize;
+    if host_len > NTP_MAX_HOST_LEN {
+        return None;
+    }
+    let parts = NtpBlobParts {
+        host: str::from_utf8(&blob[NTP_HEADER_LEN..NTP_HEADER_LEN + host_len]).ok()?,
+        port: u16::from_le_bytes([blob[10], blob[11]]),
+    };
+    encode_ntp_blob(&parts).ok()?;
+    Some(parts)
+}
+
+#[derive(Clone, Copy)]
+pub struct OtaBlobParts {
+    pub token: [u8; OTA_TOKEN_LEN],
+    /// Boots of a freshly written image that have not been confirmed yet.
+    pub boot_attempts: u8,
+    /// The last update was rolled back after too many unconfirmed boots.
+    pub rolled_back: bool,
+}
+
+/// Layout: magic (8), boot attempts, flags, reserved up to 16, then the token.
+pub fn encode_ota_blob(parts: &OtaBlobParts) -> [u8; EEPROM_OTA_LEN] {
+    let mut blob = [0xFFu8; EEPROM_OTA_LEN];
+    blob[..OTA_BLOB_MAGIC.len()].copy_from_slice(OTA_BLOB_MAGIC);
+    blob[8] = parts.boot_attempts;
+    blob[9] = if parts.rolled_back {
+        OTA_FLAG_ROLLED_BACK
+    } else {
+        0
+    };
+    blob[OTA_HEADER_LEN..OTA_HEADER_LEN + OTA_TOKEN_LEN].copy_from_slice(&parts.token);
+    blob
+}
+
+pub fn decode_ota_blob(blob: &[u8; EEPROM_OTA_LEN]) -> Option<OtaBlobParts> {
+    if &blob[..OTA_BLOB_MAGIC.len()] != OTA_BLOB_MAGIC {
+        return None;
+    }
+    let mut token = [0u8; OTA_TOKEN_LEN];
+    token.copy_from_slice(&blob[OTA_HEADER_LEN..OTA_HEADER_LEN + OTA_TOKEN_LEN]);
+    Some(OtaBlobParts {
+        token,
+        boot_attempts: blob[8],
+        rolled_back: blob[9] & OTA_FLAG_ROLLED_BACK != 0,
+    })
+}
+
+/// One paired client: its bearer token, permission level (`auth::Role`) and
+/// a short label naming the client (`web`, `devd`, ...).
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct AuthTokenEntry {
+    pub token: [u8; AUTH_TOKEN_LEN],
+    pub role: u8,
+    label_len: u8,
+    label: [u8; AUTH_MAX_LABEL_LEN],
+}
+
+impl AuthTokenEntry {
+    pub fn new(token: [u8; AUTH_TOKEN_LEN], role: u8, label: &str) -> Result<Self, &'static str> {
+        validate_auth_label(label)?;
+        let mut stored = [0u8; AUTH_MAX_LABEL_LEN];
+        stored[..label.len()].copy_from_slice(label.as_bytes());
+        Ok(Self {
+            token,
+            role,
+            label_len: label.len() as u8,
+            label: stored,
+        })
+    }
+
+    pub fn label(&self) -> &str {
+        str::from_utf8(&self.label[..self.label_len as usize]).unwrap_or("")
+    }
+}
+
+pub fn validate_auth_label(label: &str) -> Result<(), &'static str> {
+    if label.is_empty()
+        || label.len() > AUTH_MAX_LABEL_LEN
+        || !label
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_' || b == b'.')
+    {
+        return Err("label must be 1..10 bytes of [A-Za-z0-9._-]");
+    }
+    Ok(())
+}
+
+/// Layout: magic (8), count, reserved up to 16, then `count` entries of
+/// role, label length, label (10), token (16).
+pub fn encode_auth_blob(entries: &[AuthTokenEntry]) -> [u8; EEPROM_AUTH_LEN] {
+    let entries = &entries[..entries.len().min(AUTH_MAX_TOKENS)];
+    let mut blob = [0xFFu8; EEPROM_AUTH_LEN];
+    blob[..AUTH_BLOB_MAGIC.len()].copy_from_slice(AUTH_BLOB_MAGIC);
+    blob[8] = entries.len() as u8;
+    for (entry, chunk) in entries
+        .iter()
+        .zip(blob[AUTH_HEADER_LEN..].chunks_exact_mut(AUTH_ENTRY_LEN))
+    {
+        chunk[0] = entry.role;
+        chunk[1] = entry.label_len;
+        chunk[2..2 + AUTH_MAX_LABEL_LEN].copy_from_slice(&entry.label);
+        chunk[2 + AUTH_MAX_LABEL_LEN..].copy_from_slice(&entry.token);
+    }
+    blob
+}
+
+/// `None` when no token was ever issued; entries with a corrupt label are
+/// dropped.
+pub fn decode_auth_blob(
+    blob: &[u8; EEPROM_AUTH_LEN],
+) -> Option<Vec<AuthTokenEntry, AUTH_MAX_TOKENS>> {
+    if &blob[..AUTH_BLOB_MAGIC.len()] != AUTH_BLOB_MAGIC {
+        return None;
+    }
+    let count = (blob[8] as usize).min(AUTH_MAX_TOKENS);
+    let mut entries = Vec::new();
+    for chunk in blob[AUTH_HEADER_LEN..]
+        .chunks_exact(AUTH_ENTRY_LEN)
+        .take(count)
+    {
+        let label_len = (chunk[1] as usize).min(AUTH_MAX_LABEL_LEN);
+        let Ok(label) = str::from_utf8(&chunk[2..2 + label_len]) else {
+            continue;
+        };
+        let mut token = [0u8; AUTH_TOKEN_LEN];
+        token.copy_from_slice(&chunk[2 + AUTH_MAX_LABEL_LEN..]);
+        if let Ok(entry) = AuthTokenEntry::new(token, chunk[0], label) {
+            let _ = entries.push(entry);
+        }
+    }
+    Some(entries)
+}
+
+fn mqtt_field(blob: &[u8; EEPROM_MQTT_LEN], offset: usize, len: u8, max: usize) -> Option<&str> {
+    let len = len as usize;
+    if len > max {
+        return None;
+    }
+    str::from_utf8(&blob[offset..offset + len]).ok()
+}
+
 #[derive(Debug, Clone, Copy, defmt::Format)]
 pub enum EepromError {
     I2c,
@@ -72,6 +486,18 @@ pub enum EepromError {
     InvalidLength,
 }
 
+fn slot_addr(slot: usize) -> Result<u16, EepromError> {
+    if slot < CAL_SLOT_COUNT {
+        Ok(EEPROM_SLOT_ADDRS[slot])
+    } else {
+        Err(EepromError::InvalidLength)
+    }
+}
+
+fn verify_addr(kind: CurveKind) -> u16 {
+    EEPROM_VERIFY_BASE_ADDR + (kind.as_u8() as u16) * EEPROM_VERIFY_RECORD_LEN as u16
+}
+
 /// Minimal M24C64 (64 Kbit) EEPROM driver over embedded-hal-async I2C.
 ///
 /// - 7-bit address: 0x50 (A0/A1/A2 strapped to GND).
@@ -179,42 +605,36 @@ impl SharedM24c64 {
 
     pub async fn write_profile_blob(
         &mut self,
+        slot: usize,
         blob: &[u8; EEPROM_PROFILE_LEN],
     ) -> Result<(), EepromError> {
-        self.write(EEPROM_PROFILE_BASE_ADDR, blob).await
+        self.write(slot_addr(slot)?, blob).await
     }
 
-    pub async fn read_profile_blob(&mut self) -> Result<[u8; EEPROM_PROFILE_LEN], EepromError> {
+    pub async fn read_profile_blob(
+        &mut self,
+        slot: usize,
+    ) -> Result<[u8; EEPROM_PROFILE_LEN], EepromError> {
         let mut buf = [0u8; EEPROM_PROFILE_LEN];
-        self.read(EEPROM_PROFILE_BASE_ADDR, &mut buf).await?;
+        self.read(slot_addr(slot)?, &mut buf).await?;
         Ok(buf)
     }
 
     pub async fn verify_profile_blob(
         &mut self,
+        slot: usize,
         expected: &[u8; EEPROM_PROFILE_LEN],
     ) -> Result<bool, EepromError> {
-        let mut offset = 0usize;
-        let mut page = [0u8; EEPROM_PAGE_SIZE_BYTES];
-        while offset < expected.len() {
-            let len = (expected.len() - offset).min(page.len());
-            self.read(EEPROM_PROFILE_BASE_ADDR + offset as u16, &mut page[..len])
-                .await?;
-            if page[..len] != expected[offset..offset + len] {
-                return Ok(false);
-            }
-            offset += len;
-        }
-        Ok(true)
+        self.verify_region(slot_addr(slot)?, expected).await
     }
 
-    pub async fn profile_blob_is_cleared(&mut self) -> Result<bool, EepromError> {
+    pub async fn profile_blob_is_cleared(&mut self, slot: usize) -> Result<bool, EepromError> {
+        let base = slot_addr(slot)?;
         let mut offset = 0usize;
         let mut page = [0u8; EEPROM_PAGE_SIZE_BYTES];
         while offset < EEPROM_PROFILE_LEN {
             let len = (EEPROM_PROFILE_LEN - offset).min(page.len());
-            self.read(EEPROM_PROFILE_BASE_ADDR + offset as u16, &mut page[..len])
-                .await?;
+            self.read(base + offset as u16, &mut page[..len]).await?;
             if page[..len].iter().any(|byte| *byte != 0xFF) {
                 return Ok(false);
             }
@@ -223,11 +643,61 @@ impl SharedM24c64 {
         Ok(true)
     }
 
-    pub async fn clear_profile_blob(&mut self) -> Result<(), EepromError> {
+    pub async fn clear_profile_blob(&mut self, slot: usize) -> Result<(), EepromError> {
         // Invalidate by filling with 0xFF; CRC will fail and we will fall back
         // to firmware factory defaults on next boot.
         let buf = [0xFFu8; EEPROM_PROFILE_LEN];
-        self.write_profile_blob(&buf).await
+        self.write_profile_blob(slot, &buf).await
+    }
+
+    pub async fn read_slot_dir_blob(&mut self) -> Result<[u8; EEPROM_SLOT_DIR_LEN], EepromError> {
+        let mut buf = [0u8; EEPROM_SLOT_DIR_LEN];
+        self.read(EEPROM_SLOT_DIR_ADDR, &mut buf).await?;
+        Ok(buf)
+    }
+
+    /// Write the slot directory and read it back.
+    pub async fn write_slot_dir_blob(
+        &mut self,
+        blob: &[u8; EEPROM_SLOT_DIR_LEN],
+    ) -> Result<bool, EepromError> {
+        self.write(EEPROM_SLOT_DIR_ADDR, blob).await?;
+        self.verify_region(EEPROM_SLOT_DIR_ADDR, blob).await
+    }
+
+    /// Latest calibration verification record for `kind`.
+    pub async fn read_verify_blob(
+        &mut self,
+        kind: CurveKind,
+    ) -> Result<[u8; EEPROM_VERIFY_RECORD_LEN], EepromError> {
+        let mut buf = [0u8; EEPROM_VERIFY_RECORD_LEN];
+        self.read(verify_addr(kind), &mut buf).await?;
+        Ok(buf)
+    }
+
+    /// Write a verification record and read it back.
+    pub async fn write_verify_blob(
+        &mut self,
+        kind: CurveKind,
+        blob: &[u8; EEPROM_VERIFY_RECORD_LEN],
+    ) -> Result<bool, EepromError> {
+        let addr = verify_addr(kind);
+        self.write(addr, blob).await?;
+        self.verify_region(addr, blob).await
+    }
+
+    async fn verify_region(&mut self, base: u16, expected: &[u8]) -> Result<bool, EepromError> {
+        let mut offset = 0usize;
+        let mut page = [0u8; EEPROM_PAGE_SIZE_BYTES];
+        while offset < expected.len() {
+            let len = (expected.len() - offset).min(page.len());
+            self.read(base + offset as u16, &mut page[..len]).await?;
+            if page[..len] != expected[offset..offset + len] {
+                return Ok(false);
+            }
+            offset += len;
+        }
+        Ok(true)
     }
 
     pub async fn write_presets_blob(
@@ -295,6 +765,151 @@ impl SharedM24c64 {
             .await
     }
 
+    pub async fn write_wifi_networks_blob(
+        &mut self,
+        blob: &[u8; EEPROM_WIFI_NETWORKS_LEN],
+    ) -> Result<(), EepromError> {
+        // Only the used entries are written; the magic goes last as for the
+        // other blobs.
+        let used = WIFI_NETWORKS_HEADER_LEN + blob[8] as usize * WIFI_NETWORK_ENTRY_LEN;
+        if used > EEPROM_WIFI_NETWORKS_LEN {
+            return Err(EepromError::InvalidLength);
+        }
+        self.write(
+            EEPROM_WIFI_NETWORKS_BASE_ADDR,
+            &[0xFF; WIFI_NETWORKS_MAGIC.len()],
+        )
+        .await?;
+        self.write(
+            EEPROM_WIFI_NETWORKS_BASE_ADDR + WIFI_NETWORKS_MAGIC.len() as u16,
+            &blob[WIFI_NETWORKS_MAGIC.len()..used],
+        )
+        .await?;
+        self.write(EEPROM_WIFI_NETWORKS_BASE_ADDR, WIFI_NETWORKS_MAGIC)
+            .await
+    }
+
+    pub async fn read_wifi_networks_blob(
+        &mut self,
+    ) -> Result<[u8; EEPROM_WIFI_NETWORKS_LEN], EepromError> {
+        let mut buf = [0u8; EEPROM_WIFI_NETWORKS_LEN];
+        self.read(EEPROM_WIFI_NETWORKS_BASE_ADDR, &mut buf).await?;
+        Ok(buf)
+    }
+
+    pub async fn write_mqtt_blob(
+        &mut self,
+        blob: &[u8; EEPROM_MQTT_LEN],
+    ) -> Result<(), EepromError> {
+        // Same commit order as the Wi-Fi blob: the magic is written last.
+        self.write(EEPROM_MQTT_BASE_ADDR, &[0xFF; MQTT_BLOB_MAGIC.len()])
+            .await?;
+        self.write(
+            EEPROM_MQTT_BASE_ADDR + MQTT_BLOB_MAGIC.len() as u16,
+            &blob[MQTT_BLOB_MAGIC.len()..MQTT_STORED_LEN],
+        )
+        .await?;
+        self.write(EEPROM_MQTT_BASE_ADDR, MQTT_BLOB_MAGIC).await
+    }
+
+    pub async fn read_mqtt_blob(&mut self) -> Result<[u8; EEPROM_MQTT_LEN], EepromError> {
+        let mut buf = [0u8; EEPROM_MQTT_LEN];
+        self.read(EEPROM_MQTT_BASE_ADDR, &mut buf).await?;
+        Ok(buf)
+    }
+
+    pub async fn clear_mqtt_blob(&mut self) -> Result<(), EepromError> {
+        self.write(EEPROM_MQTT_BASE_ADDR, &[0xFF; MQTT_HEADER_LEN])
+            .await
+    }
+
+    pub async fn write_ntp_blob(&mut self, blob: &[u8; EEPROM_NTP_LEN]) -> Result<(), EepromError> {
+        self.write(EEPROM_NTP_BASE_ADDR, &[0xFF; NTP_BLOB_MAGIC.len()])
+            .await?;
+        self.write(
+            EEPROM_NTP_BASE_ADDR + NTP_BLOB_MAGIC.len() as u16,
+            &blob[NTP_BLOB_MAGIC.len()..],
+        )
+        .await?;
+        self.write(EEPROM_NTP_BASE_ADDR, NTP_BLOB_MAGIC).await
+    }
+
+    pub async fn read_ntp_blob(&mut self) -> Result<[u8; EEPROM_NTP_LEN], EepromError> {
+        let mut buf = [0u8; EEPROM_NTP_LEN];
+        self.read(EEPROM_NTP_BASE_ADDR, &mut buf).await?;
+        Ok(buf)
+    }
+
+    pub async fn clear_ntp_blob(&mut self) -> Result<(), EepromError> {
+        self.write(EEPROM_NTP_BASE_ADDR, &[0xFF; NTP_HEADER_LEN])
+            .await
+    }
+
+    pub async fn write_ota_blob(&mut self, blob: &[u8; EEPROM_OTA_LEN]) -> Result<(), EepromError> {
+        self.write(EEPROM_OTA_BASE_ADDR, &[0xFF; OTA_BLOB_MAGIC.len()])
+            .await?;
+        self.write(
+            EEPROM_OTA_BASE_ADDR + OTA_BLOB_MAGIC.len() as u16,
+            &blob[OTA_BLOB_MAGIC.len()..],
+        )
+        .await?;
+        self.write(EEPROM_OTA_BASE_ADDR, OTA_BLOB_MAGIC).await
+    }
+
+    pub async fn read_ota_blob(&mut self) -> Result<[u8; EEPROM_OTA_LEN], EepromError> {
+        let mut buf = [0u8; EEPROM_OTA_LEN];
+        self.read(EEPROM_OTA_BASE_ADDR, &mut buf).await?;
+        Ok(buf)
+    }
+
+    pub async fn write_auth_blob(
+        &mut self,
+        blob: &[u8; EEPROM_AUTH_LEN],
+    ) -> Result<(), EepromError> {
+        self.write(EEPROM_AUTH_BASE_ADDR, &[0xFF; AUTH_BLOB_MAGIC.len()])
+            .await?;
+        self.write(
+            EEPROM_AUTH_BASE_ADDR + AUTH_BLOB_MAGIC.len() as u16,
+            &blob[AUTH_BLOB_MAGIC.len()..],
+        )
+        .await?;
+        self.write(EEPROM_AUTH_BASE_ADDR, AUTH_BLOB_MAGIC).await
+    }
+
+    pub async fn read_auth_blob(&mut self) -> Result<[u8; EEPROM_AUTH_LEN], EepromError> {
+        let mut buf = [0u8; EEPROM_AUTH_LEN];
+        self.read(EEPROM_AUTH_BASE_ADDR, &mut buf).await?;
+        Ok(buf)
+    }
+
+    pub async fn write_protection_blob(
+        &mut self,
+        blob: &[u8; EEPROM_PROTECTION_LEN],
+    ) -> Result<(), EepromError> {
+        self.write(EEPROM_PROTECTION_BASE_ADDR, blob).await
+    }
+
+    pub async fn read_protection_blob(
+        &mut self,
+    ) -> Result<[u8; EEPROM_PROTECTION_LEN], EepromError> {
+        let mut buf = [0u8; EEPROM_PROTECTION_LEN];
+        self.read(EEPROM_PROTECTION_BASE_ADDR, &mut buf).await?;
+        Ok(buf)
+    }
+
+    pub async fn write_thermal_blob(
+        &mut self,
+        blob: &[u8; EEPROM_THERMAL_LEN],
+    ) -> Result<(), EepromError> {
+        self.write(EEPROM_THERMAL_BASE_ADDR, blob).await
+    }
+
+    pub async fn read_thermal_blob(&mut self) -> Result<[u8; EEPROM_THERMAL_LEN], EepromError> {
+        let mut buf = [0u8; EEPROM_THERMAL_LEN];
+        self.read(EEPROM_THERMAL_BASE_ADDR, &mut buf).await?;
+        Ok(buf)
+    }
+
     async fn wait_ready(&mut self) -> Result<(), EepromError> {
         // Typical tWR is a few ms; keep a generous timeout.
         const POLL_TIMEOUT_MS: u32 = 20;
@@ -0,0 +1,548 @@
+//! DUT internal-resistance (DC IR / ESR) measurement (digital side).
+//!
+//! A run borrows the control loop the same way current calibration does:
+//! while it is active `ControlState` carries a CC override that replaces the
+//! active preset's mode/target (limits and `min_v_mv` still come from the
+//! preset). Each cycle steps the load from `low_ma` to `high_ma`:
+//!
+//! - after every step the task waits `settle_ms`, then averages the FastStatus
+//!   frames that arrive during `sample_ms`;
+//! - the cycle's resistance is `(V_low - V_high) / (I_high - I_low)` using the
+//!   *measured* currents, so CC setpoint error cancels out;
+//! - voltage comes from `v_remote_mv` when remote sense is active at the start
+//!   (4-wire, excludes the load leads), otherwise from `v_local_mv` (2-wire,
+//!   includes the lead resistance; reported as `sense="local"`).
+//!
+//! The result is the mean over all cycles, with the per-cycle min/max as a
+//! spread indicator. Anything that forces the output off (load guard, a user
+//! toggle, a cancel request) aborts the run and the previous output state is
+//! restored on success.
+
+// Runs are only started through the HTTP / USB API handlers.
+#![cfg_attr(not(feature = "net_http"), allow(dead_code))]
+
+use core::cell::Cell;
+use core::ops::RangeInclusive;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use defmt::{info, warn};
+use embassy_sync::{
+    blocking_mutex::{Mutex as BlockingMutex, raw::CriticalSectionRawMutex},
+    signal::Signal,
+};
+use embassy_time::Timer;
+use loadlynx_protocol::{CalKind, FastStatus, STATE_FLAG_REMOTE_ACTIVE};
+
+use crate::{CalibrationMutex, ControlMutex, TelemetryMutex};
+
+/// Parameters of one run. Currents are total load current (both channels).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IrConfig {
+    pub low_ma: i32,
+    pub high_ma: i32,
+    pub cycles: u8,
+    pub settle_ms: u32,
+    pub sample_ms: u32,
+}
+
+pub const IR_CONFIG_DEFAULT: IrConfig = IrConfig {
+    low_ma: 100,
+    high_ma: 1_000,
+    cycles: 5,
+    settle_ms: 300,
+    sample_ms: 200,
+};
+
+pub const IR_CYCLES_RANGE: RangeInclusive<u8> = 1..=20;
+pub const IR_SETTLE_MS_RANGE: RangeInclusive<u32> = 50..=5_000;
+/// FastStatus runs at 20 Hz; 100 ms guarantees at least two frames per phase.
+pub const IR_SAMPLE_MS_RANGE: RangeInclusive<u32> = 100..=5_000;
+/// Smallest commanded current step.
+pub const IR_MIN_STEP_MA: i32 = 100;
+/// Smallest *measured* current step accepted for a cycle; below this the
+/// source could not deliver the high level (or CC is not tracking).
+const IR_MIN_MEASURED_STEP_MA: i64 = 50;
+/// Local voltage required at start; below this there is no DUT to measure.
+const IR_DUT_PRESENT_MV: i32 = 500;
+/// Telemetry/abort polling period while a phase is running.
+const IR_POLL_MS: u64 = 10;
+
+impl IrConfig {
+    /// Check the config against the hardware ranges and the active preset's
+    /// `max_i_ma_total`. Returns the offending field name.
+    pub fn validate(&self, max_i_ma: i32) -> Result<(), &'static str> {
+        if self.low_ma < 0 || self.low_ma > max_i_ma {
+            return Err("low_ma");
+        }
+        if self.high_ma > max_i_ma || self.high_ma - self.low_ma < IR_MIN_STEP_MA {
+            return Err("high_ma");
+        }
+        if !IR_CYCLES_RANGE.contains(&self.cycles) {
+            return Err("cycles");
+        }
+        if !IR_SETTLE_MS_RANGE.contains(&self.settle_ms) {
+            return Err("settle_ms");
+        }
+        if !IR_SAMPLE_MS_RANGE.contains(&self.sample_ms) {
+            return Err("sample_ms");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IrPhase {
+    Low,
+    High,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IrError {
+    /// Another run is in progress.
+    Busy,
+    /// A calibration mode owns the CC override.
+    CalibrationActive,
+    LinkDown,
+    /// Local voltage below [`IR_DUT_PRESENT_MV`] at start.
+    NoDut,
+    /// Remote sense dropped out during a 4-wire run.
+    RemoteLost,
+    /// A phase finished without any fresh FastStatus frame.
+    NoSamples,
+    /// Measured current step below [`IR_MIN_MEASURED_STEP_MA`].
+    NoCurrentStep,
+    /// The output was forced off (load guard or user toggle).
+    OutputOff,
+    Cancelled,
+}
+
+impl IrError {
+    pub fn code(self) -> &'static str {
+        match self {
+            IrError::Busy => "BUSY",
+            IrError::CalibrationActive => "CALIBRATION_ACTIVE",
+            IrError::LinkDown => "LINK_DOWN",
+            IrError::NoDut => "NO_DUT",
+            IrError::RemoteLost => "REMOTE_LOST",
+            IrError::NoSamples => "NO_SAMPLES",
+            IrError::NoCurrentStep => "NO_CURRENT_STEP",
+            IrError::OutputOff => "OUTPUT_OFF",
+            IrError::Cancelled => "CANCELLED",
+        }
+    }
+
+    pub fn message(self) -> &'static str {
+        match self {
+            IrError::Busy => "an IR measurement is already running",
+            IrError::CalibrationActive => "calibration mode is active",
+            IrError::LinkDown => "no analog telemetry",
+            IrError::NoDut => "no DUT voltage at the load terminals",
+            IrError::RemoteLost => "remote sense dropped out during the run",
+            IrError::NoSamples => "no telemetry received during a sample window",
+            IrError::NoCurrentStep => "DUT did not deliver the requested current step",
+            IrError::OutputOff => "output was switched off during the run",
+            IrError::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// Averaged outcome of a completed run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IrResult {
+    /// Mean resistance over all cycles (µΩ).
+    pub r_uohm: i32,
+    pub r_min_uohm: i32,
+    pub r_max_uohm: i32,
+    pub cycles: u8,
+    /// Mean voltage / measured current at the two levels.
+    pub v_low_mv: i32,
+    pub v_high_mv: i32,
+    pub i_low_ma: i32,
+    pub i_high_ma: i32,
+    /// Voltage came from `v_remote_mv` (4-wire) rather than `v_local_mv`.
+    pub remote_sense: bool,
+}
+
+impl IrResult {
+    /// Mean resistance rounded to whole milliohms.
+    pub fn r_mohm(&self) -> i32 {
+        let half = if self.r_uohm < 0 { -500 } else { 500 };
+        (self.r_uohm + half) / 1_000
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct PhaseAcc {
+    v_sum: i64,
+    i_sum: i64,
+    n: u32,
+}
+
+impl PhaseAcc {
+    fn mean(&self) -> Option<(i64, i64)> {
+        (self.n != 0).then(|| (self.v_sum / self.n as i64, self.i_sum / self.n as i64))
+    }
+}
+
+/// Per-cycle ΔV/ΔI accumulator (pure; fed by the task or by tests).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IrEstimator {
+    low: PhaseAcc,
+    high: PhaseAcc,
+    cycles: u8,
+    r_sum_uohm: i64,
+    r_min_uohm: i64,
+    r_max_uohm: i64,
+    v_low_sum: i64,
+    v_high_sum: i64,
+    i_low_sum: i64,
+    i_high_sum: i64,
+}
+
+impl IrEstimator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, phase: IrPhase, v_mv: i32, i_ma: i32) {
+        let acc = match phase {
+            IrPhase::Low => &mut self.low,
+            IrPhase::High => &mut self.high,
+        };
+        acc.v_sum += v_mv as i64;
+        acc.i_sum += i_ma as i64;
+        acc.n += 1;
+    }
+
+    /// Close the current low/high pair and return its resistance (µΩ).
+    pub fn finish_cycle(&mut self) -> Result<i64, IrError> {
+        let low = self.low.mean();
+        let high = self.high.mean();
+        self.low = PhaseAcc::default();
+        self.high = PhaseAcc::default();
+        let ((v_low, i_low), (v_high, i_high)) = low.zip(high).ok_or(IrError::NoSamples)?;
+        let di_ma = i_high - i_low;
+        if di_ma < IR_MIN_MEASURED_STEP_MA {
+            return Err(IrError::NoCurrentStep);
+        }
+        // mV / mA = Ω; scale to µΩ.
+        let r_uohm = (v_low - v_high) * 1_000_000 / di_ma;
+        if self.cycles == 0 {
+            self.r_min_uohm = r_uohm;
+            self.r_max_uohm = r_uohm;
+        } else {
+            self.r_min_uohm = self.r_min_uohm.min(r_uohm);
+            self.r_max_uohm = self.r_max_uohm.max(r_uohm);
+        }
+        self.cycles = self.cycles.saturating_add(1);
+        self.r_sum_uohm += r_uohm;
+        self.v_low_sum += v_low;
+        self.v_high_sum += v_high;
+        self.i_low_sum += i_low;
+        self.i_high_sum += i_high;
+        Ok(r_uohm)
+    }
+
+    /// Mean over the finished cycles; `None` before the first one.
+    pub fn result(&self, remote_sense: bool) -> Option<IrResult> {
+        if self.cycles == 0 {
+            return None;
+        }
+        let n = self.cycles as i64;
+        let clamp = |v: i64| v.clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+        Some(IrResult {
+            r_uohm: clamp(self.r_sum_uohm / n),
+            r_min_uohm: clamp(self.r_min_uohm),
+            r_max_uohm: clamp(self.r_max_uohm),
+            cycles: self.cycles,
+            v_low_mv: clamp(self.v_low_sum / n),
+            v_high_mv: clamp(self.v_high_sum / n),
+            i_low_ma: clamp(self.i_low_sum / n),
+            i_high_ma: clamp(self.i_high_sum / n),
+            remote_sense,
+        })
+    }
+}
+
+/// State reported by `GET /api/v1/measure/ir`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IrStatus {
+    Idle,
+    Running { config: IrConfig, cycle: u8 },
+    Done { config: IrConfig, result: IrResult },
+    Failed { config: IrConfig, error: IrError },
+}
+
+static STATUS: BlockingMutex<CriticalSectionRawMutex, Cell<IrStatus>> =
+    BlockingMutex::new(Cell::new(IrStatus::Idle));
+static START: Signal<CriticalSectionRawMutex, IrConfig> = Signal::new();
+static CANCEL: AtomicBool = AtomicBool::new(false);
+
+fn set_status(status: IrStatus) {
+    STATUS.lock(|cell| cell.set(status));
+}
+
+pub fn status() -> IrStatus {
+    STATUS.lock(|cell| cell.get())
+}
+
+/// Queue a run. The caller has already validated `config` and the output
+/// gating; this only rejects overlapping runs.
+pub fn start(config: IrConfig) -> Result<(), IrError> {
+    STATUS.lock(|cell| {
+        if matches!(cell.get(), IrStatus::Running { .. }) {
+            return Err(IrError::Busy);
+        }
+        cell.set(IrStatus::Running { config, cycle: 0 });
+        Ok(())
+    })?;
+    CANCEL.store(false, Ordering::Relaxed);
+    START.signal(config);
+    Ok(())
+}
+
+/// Request the running measurement to stop; returns whether one was running.
+pub fn cancel() -> bool {
+    let running = matches!(status(), IrStatus::Running { .. });
+    if running {
+        CANCEL.store(true, Ordering::Relaxed);
+    }
+    running
+}
+
+#[embassy_executor::task]
+pub async fn ir_measure_task(
+    control: &'static ControlMutex,
+    calibration: &'static CalibrationMutex,
+    telemetry: &'static TelemetryMutex,
+) {
+    info!("IR measure task starting");
+    loop {
+        let config = START.wait().await;
+        info!(
+            "IR measure start (low={}mA high={}mA cycles={} settle={}ms sample={}ms)",
+            config.low_ma, config.high_ma, config.cycles, config.settle_ms, config.sample_ms
+        );
+        let outcome = run(config, control, calibration, telemetry).await;
+        {
+            let mut guard = control.lock().await;
+            if guard.end_measure_override() {
+                crate::bump_control_rev();
+            }
+        }
+        match outcome {
+            Ok(result) => {
+                info!(
+                    "IR measure done: {} uOhm (min={} max={} cycles={} remote={})",
+                    result.r_uohm,
+                    result.r_min_uohm,
+                    result.r_max_uohm,
+                    result.cycles,
+                    result.remote_sense
+                );
+                set_status(IrStatus::Done { config, result });
+            }
+            Err(error) => {
+                warn!("IR measure failed: {}", error.code());
+                set_status(IrStatus::Failed { config, error });
+            }
+        }
+    }
+}
+
+async fn run(
+    config: IrConfig,
+    control: &'static ControlMutex,
+    calibration: &'static CalibrationMutex,
+    telemetry: &'static TelemetryMutex,
+) -> Result<IrResult, IrError> {
+    if calibration.lock().await.cal_mode != CalKind::Off {
+        return Err(IrError::CalibrationActive);
+    }
+    if !crate::LINK_UP.load(Ordering::Relaxed) {
+        return Err(IrError::LinkDown);
+    }
+    let first = telemetry
+        .lock()
+        .await
+        .last_status
+        .ok_or(IrError::LinkDown)?;
+    if first.v_local_mv < IR_DUT_PRESENT_MV {
+        return Err(IrError::NoDut);
+    }
+    let remote_sense = first.state_flags & STATE_FLAG_REMOTE_ACTIVE != 0;
+
+    {
+        let mut guard = control.lock().await;
+        guard.begin_measure_override(config.low_ma);
+        crate::bump_control_rev();
+    }
+
+    let mut estimator = IrEstimator::new();
+    for cycle in 0..config.cycles {
+        set_status(IrStatus::Running { config, cycle });
+        for (phase, target_ma) in [
+            (IrPhase::Low, config.low_ma),
+            (IrPhase::High, config.high_ma),
+        ] {
+            {
+                let mut guard = control.lock().await;
+                if !guard.set_measure_target(target_ma) {
+                    return Err(IrError::OutputOff);
+                }
+                crate::bump_control_rev();
+            }
+            let mut settle_left = config.settle_ms as u64;
+            while settle_left > 0 {
+                let step = settle_left.min(IR_POLL_MS);
+                Timer::after_millis(step).await;
+                settle_left -= step;
+                check_abort(control, calibration).await?;
+            }
+            sample_phase(
+                phase,
+                config.sample_ms,
+                remote_sense,
+                &mut estimator,
+                control,
+                calibration,
+                telemetry,
+            )
+            .await?;
+        }
+        estimator.finish_cycle()?;
+    }
+    estimator.result(remote_sense).ok_or(IrError::NoSamples)
+}
+
+async fn check_abort(
+    control: &'static ControlMutex,
+    calibration: &'static CalibrationMutex,
+) -> Result<(), IrError> {
+    if CANCEL.load(Ordering::Relaxed) {
+        return Err(IrError::Cancelled);
+    }
+    if control.lock().await.measure_cc_override.is_none() {
+        return Err(IrError::OutputOff);
+    }
+    if calibration.lock().await.cal_mode != CalKind::Off {
+        return Err(IrError::CalibrationActive);
+    }
+    Ok(())
+}
+
+async fn sample_phase(
+    phase: IrPhase,
+    sample_ms: u32,
+    remote_sense: bool,
+    estimator: &mut IrEstimator,
+    control: &'static ControlMutex,
+    calibration: &'static CalibrationMutex,
+    telemetry: &'static TelemetryMutex,
+) -> Result<(), IrError> {
+    let mut last_uptime_ms: Option<u32> = None;
+    let mut elapsed_ms: u64 = 0;
+    while elapsed_ms < sample_ms as u64 {
+        Timer::after_millis(IR_POLL_MS).await;
+        elapsed_ms += IR_POLL_MS;
+        check_abort(control, calibration).await?;
+        let status: Option<FastStatus> = telemetry.lock().await.last_status;
+        let Some(status) = status else {
+            continue;
+        };
+        if last_uptime_ms == Some(status.uptime_ms) {
+            continue;
+        }
+        // The first frame seen may predate the window; only count frames that
+        // arrived after it.
+        let fresh = last_uptime_ms.is_some();
+        last_uptime_ms = Some(status.uptime_ms);
+        if !fresh {
+            continue;
+        }
+        let v_mv = if remote_sense {
+            if status.state_flags & STATE_FLAG_REMOTE_ACTIVE == 0 {
+                return Err(IrError::RemoteLost);
+            }
+            status.v_remote_mv
+        } else {
+            status.v_local_mv
+        };
+        estimator.push(
+            phase,
+            v_mv,
+            status.i_local_ma.saturating_add(status.i_remote_ma),
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed_cycle(est: &mut IrEstimator, v_low: i32, i_low: i32, v_high: i32, i_high: i32) {
+        for _ in 0..4 {
+            est.push(IrPhase::Low, v_low, i_low);
+            est.push(IrPhase::High, v_high, i_high);
+        }
+    }
+
+    #[test]
+    fn estimator_uses_measured_current_step_and_averages_cycles() {
+        let mut est = IrEstimator::new();
+        // 12.000 V -> 11.950 V over a measured 95..1000 mA step: ~55.25 mΩ.
+        feed_cycle(&mut est, 12_000, 95, 11_950, 1_000);
+        assert_eq!(est.finish_cycle(), Ok(55_248));
+        // Second cycle reads 60 mΩ.
+        feed_cycle(&mut est, 12_000, 100, 11_946, 1_000);
+        assert_eq!(est.finish_cycle(), Ok(60_000));
+
+        let result = est.result(true).unwrap();
+        assert_eq!(result.cycles, 2);
+        assert_eq!(result.r_uohm, 57_624);
+        assert_eq!(result.r_mohm(), 58);
+        assert_eq!(result.r_min_uohm, 55_248);
+        assert_eq!(result.r_max_uohm, 60_000);
+        assert_eq!(result.v_low_mv, 12_000);
+        assert_eq!(result.i_high_ma, 1_000);
+        assert!(result.remote_sense);
+    }
+
+    #[test]
+    fn estimator_rejects_missing_samples_and_flat_current() {
+        let mut est = IrEstimator::new();
+        est.push(IrPhase::Low, 12_000, 100);
+        assert_eq!(est.finish_cycle(), Err(IrError::NoSamples));
+
+        // Source current-limited: high level never materialises.
+        feed_cycle(&mut est, 5_000, 100, 4_200, 120);
+        assert_eq!(est.finish_cycle(), Err(IrError::NoCurrentStep));
+        assert_eq!(est.result(false), None);
+    }
+
+    #[test]
+    fn config_validation_reports_offending_field() {
+        assert_eq!(IR_CONFIG_DEFAULT.validate(5_000), Ok(()));
+        let cfg = IrConfig {
+            high_ma: 6_000,
+            ..IR_CONFIG_DEFAULT
+        };
+        assert_eq!(cfg.validate(5_000), Err("high_ma"));
+        let cfg = IrConfig {
+            low_ma: 950,
+            ..IR_CONFIG_DEFAULT
+        };
+        assert_eq!(cfg.validate(5_000), Err("high_ma"));
+        let cfg = IrConfig {
+            cycles: 0,
+            ..IR_CONFIG_DEFAULT
+        };
+        assert_eq!(cfg.validate(5_000), Err("cycles"));
+        let cfg = IrConfig {
+            sample_ms: 20,
+            ..IR_CONFIG_DEFAULT
+        };
+        assert_eq!(cfg.validate(5_000), Err("sample_ms"));
+    }
+}
@@ -65,16 +65,18 @@ use lcd_async::{
     raw_framebuf::RawFrameBuf,
 };
 use loadlynx_protocol::{
-    CRC_LEN, CalKind, CalMode, FAULT_MCU_OVER_TEMP, FAULT_OVERCURRENT, FAULT_OVERVOLTAGE,
-    FAULT_SINK_OVER_TEMP, FLAG_ACK_REQ, FLAG_IS_ACK, FLAG_IS_NACK, FastStatus, FrameHeader,
-    HEADER_LEN, LimitProfile, LoadMode, MSG_CAL_MODE, MSG_CAL_WRITE, MSG_FAST_STATUS, MSG_HELLO,
-    MSG_LIMIT_PROFILE, MSG_PD_SINK_REQUEST, MSG_PD_STATUS, MSG_SET_MODE, MSG_SET_POINT,
-    MSG_SOFT_RESET, PdSinkMode, PdSinkRequest, PdStatus, STATE_FLAG_UV_LATCHED, SetEnable, SetMode,
-    SlipDecoder, SoftReset, SoftResetReason, decode_cal_mode_frame, decode_fast_status_frame,
-    decode_frame, decode_hello_frame, decode_pd_status_frame, decode_soft_reset_frame,
-    encode_cal_mode_frame, encode_cal_write_frame, encode_limit_profile_frame,
-    encode_pd_sink_request_frame, encode_set_enable_frame, encode_set_mode_frame,
-    encode_soft_reset_frame, slip_encode,
+    COUNTER_ALL, CRC_LEN, CalKind, CalMode, ClearFaults, FAULT_MCU_OVER_TEMP, FAULT_OVERCURRENT,
+    FAULT_OVERVOLTAGE, FAULT_POLICY_ALL, FAULT_SINK_OVER_TEMP, FLAG_ACK_REQ, FLAG_IS_ACK,
+    FLAG_IS_NACK, FastStatus, FrameHeader, HEADER_LEN, LimitProfile, LoadMode, MSG_CAL_MODE,
+    MSG_CAL_WRITE, MSG_CLEAR_FAULTS, MSG_FAST_STATUS, MSG_HELLO, MSG_LIMIT_PROFILE,
+    MSG_PD_SINK_REQUEST, MSG_PD_STATUS, MSG_PROTECTION_CONFIG, MSG_RESET_COUNTERS, MSG_SET_MODE,
+    MSG_SET_POINT, MSG_SOFT_RESET, PdSinkMode, PdSinkRequest, PdStatus, ProtectionConfig,
+    ResetCounters, STATE_FLAG_LINK_SAFED, STATE_FLAG_UV_LATCHED, SetEnable, SetMode, SlipDecoder,
+    SoftReset, SoftResetReason, decode_cal_mode_frame, decode_fast_status_frame, decode_frame,
+    decode_hello_frame, decode_pd_status_frame, decode_soft_reset_frame, encode_cal_mode_frame,
+    encode_cal_write_frame, encode_clear_faults_frame, encode_limit_profile_frame,
+    encode_pd_sink_request_frame, encode_protection_config_frame, encode_reset_counters_frame,
+    encode_set_enable_frame, encode_set_mode_frame, encode_soft_reset_frame, slip_encode,
 };
 use loadlynx_screen_power::{ScreenPowerConfig, ScreenPowerModel, ScreenPowerState};
 use static_cell::StaticCell;
@@ -195,15 +197,33 @@ use ui::{AnalogState, UiSnapshot};
 
 mod eeprom;
 mod i2c0;
+mod ir_measure;
+mod ota;
 mod prompt_tone;
 mod speaker;
+mod thermal;
 mod touch;
+mod wall_clock;
 
 // Optional Wi‑Fi + HTTP support; compiled only when `net_http` feature is set.
 #[cfg(feature = "net_http")]
+mod analog_flash;
+#[cfg(feature = "net_http")]
+mod auth;
+#[cfg(feature = "net_http")]
 mod mdns;
 #[cfg(feature = "net_http")]
+mod mqtt;
+#[cfg(feature = "net_http")]
 mod net;
+#[cfg(feature = "net_http")]
+mod provision;
+#[cfg(feature = "net_http")]
+mod scpi;
+#[cfg(feature = "net_http")]
+mod sntp;
+#[cfg(feature = "net_http")]
+mod ws;
 
 // Optional compile-time Wi‑Fi fallback injected by firmware/digital/build.rs.
 // EEPROM user credentials are preferred at runtime; these values are only a
@@ -284,6 +304,16 @@ pub(crate) const LIMIT_PROFILE_DEFAULT: LimitProfile = LimitProfile {
 const ENABLE_UART_UHCI_DMA: bool = true;
 // SetMode 可靠传输：与 SetPoint 类似的 ACK 等待与退避重传（最新值优先）。
 const SETMODE_ACK_TIMEOUT_MS: u32 = 40;
+// ProtectionConfig is rare and not latency critical; use a generous ACK window
+// (like PD requests) and a bounded number of attempts per round.
+const PROTECTION_ACK_TIMEOUT_MS: u32 = 500;
+const PROTECTION_MAX_ATTEMPTS: u8 = 3;
+// ClearFaults is user-initiated; retry quickly but give up after a few tries.
+const CLEAR_FAULTS_ACK_TIMEOUT_MS: u32 = 200;
+const CLEAR_FAULTS_MAX_ATTEMPTS: u8 = 3;
+// ResetCounters follows the same user-initiated policy as ClearFaults.
+const RESET_COUNTERS_ACK_TIMEOUT_MS: u32 = 200;
+const RESET_COUNTERS_MAX_ATTEMPTS: u8 = 3;
 const SETMODE_RETRY_BACKOFF_MS: [u32; 3] = [40, 80, 160];
 const SETMODE_TX_PERIOD_MS: u32 = 250;
 const BOOT_LINK_RECOVERY_GRACE_MS: u32 = 1_500;
@@ -294,6 +324,10 @@ const LINK_RECOVERY_POST_RESET_QUIET_MS: u32 = 300;
 const MEASUREMENT_SIGNAL_MIN_MV: i32 = 100;
 const MEASUREMENT_SIGNAL_MIN_MA: i32 = 20;
 const MEASUREMENT_SIGNAL_MIN_MW: u32 = 500;
+// Thermal derating LimitProfile pushes: minimum spacing between derate changes,
+// and periodic refresh while derating is active.
+const THERMAL_LIMIT_PUSH_MIN_INTERVAL_MS: u32 = 500;
+const THERMAL_LIMIT_REFRESH_MS: u32 = 5_000;
 
 // Fan PWM control (ESP32‑S3 本地，根据 G431 上报的 sink_core_temp + 功率驱动风扇占空比)。
 // 数值集中在此处，便于后续调参。
@@ -350,6 +384,9 @@ use loadlynx_calibration_format::{self as calfmt, ActiveProfile, CurveKind};
 pub struct CalibrationState {
     pub profile: ActiveProfile,
     pub persistence_status: CalibrationPersistenceStatus,
+    /// Named profile slots; `slots.active` is the slot `profile` came from and
+    /// the one commit/reset write to.
+    pub slots: calfmt::slots::SlotDirectory,
     pub cal_mode: CalKind,
     pub pending_cal_mode: Option<CalKind>,
 }
@@ -389,7 +426,8 @@ impl CalibrationPersistenceStatus {
     pub const fn from_load_error(error: calfmt::ProfileLoadError) -> Self {
         match error {
             calfmt::ProfileLoadError::InvalidLength
-            | calfmt::ProfileLoadError::UnsupportedFmtVersion(_) => Self::InvalidFormat,
+            | calfmt::ProfileLoadError::UnsupportedFmtVersion(_)
+            | calfmt::ProfileLoadError::InvalidTempComp => Self::InvalidFormat,
             calfmt::ProfileLoadError::HwRevMismatch { .. } => Self::HwRevMismatch,
             calfmt::ProfileLoadError::InvalidCounts => Self::InvalidCounts,
             calfmt::ProfileLoadError::CrcMismatch { .. } => Self::CrcMismatch,
@@ -398,16 +436,85 @@ impl CalibrationPersistenceStatus {
 }
 
 impl CalibrationState {
-    pub fn new(profile: ActiveProfile, persistence_status: CalibrationPersistenceStatus) -> Self {
+    pub fn new(
+        profile: ActiveProfile,
+        persistence_status: CalibrationPersistenceStatus,
+        slots: calfmt::slots::SlotDirectory,
+    ) -> Self {
         Self {
             profile,
             persistence_status,
+            slots,
             cal_mode: CalKind::Off,
             pending_cal_mode: None,
         }
     }
 }
 
+/// Read and validate one calibration slot. Falls back to factory defaults on a
+/// cleared, invalid or unreadable blob; the CRC32 of the blob is returned only
+/// when it held a valid profile.
+pub(crate) async fn load_calibration_slot(
+    eeprom: &mut eeprom::SharedM24c64,
+    slot: usize,
+) -> (ActiveProfile, CalibrationPersistenceStatus, Option<u32>) {
+    match eeprom.read_profile_blob(slot).await {
+        Ok(blob) if blob.iter().all(|byte| *byte == 0xFF) => {
+            info!(
+                "EEPROM calibration slot {} is cleared; using factory-default",
+                slot
+            );
+            (
+                ActiveProfile::factory_default(calfmt::DIGITAL_HW_REV),
+                CalibrationPersistenceStatus::FactoryDefault,
+                None,
+            )
+        }
+        Ok(blob) => match calfmt::deserialize_profile(&blob, calfmt::DIGITAL_HW_REV) {
+            Ok(mut profile) => {
+                info!(
+                    "EEPROM calibration slot {} loaded (fmt_version={}, hw_rev={})",
+                    slot, profile.fmt_version, profile.hw_rev
+                );
+                // Older formats run as v4 with temperature compensation
+                // disabled; the EEPROM copy is upgraded on the next commit.
+                if profile.migrate_to_latest() {
+                    info!(
+                        "calibration profile migrated to fmt_version={} in RAM",
+                        profile.fmt_version
+                    );
+                }
+                (
+                    profile,
+                    CalibrationPersistenceStatus::UserProfileLoaded,
+                    Some(calfmt::slots::profile_blob_crc32(&blob)),
+                )
+            }
+            Err(err) => {
+                let status = CalibrationPersistenceStatus::from_load_error(err);
+                warn!(
+                    "EEPROM calibration slot {} invalid; using factory-default (err={})",
+                    slot,
+                    status.as_str()
+                );
+                (
+                    ActiveProfile::factory_default(calfmt::DIGITAL_HW_REV),
+                    status,
+                    None,
+                )
+            }
+        },
+        Err(err) => {
+            warn!("EEPROM read failed; using factory-default (err={:?})", err);
+            (
+                ActiveProfile::factory_default(calfmt::DIGITAL_HW_REV),
+                CalibrationPersistenceStatus::ReadFailed,
+                None,
+            )
+        }
+    }
+}
+
 pub type CalibrationMutex = Mutex<CriticalSectionRawMutex, CalibrationState>;
 static CALIBRATION: StaticCell<CalibrationMutex> = StaticCell::new();
 
@@ -525,6 +632,20 @@ static PD_LAST_RESULT_MS: AtomicU32 = AtomicU32::new(0);
 static PD_UI_APPLY_MS: AtomicU32 = AtomicU32::new(0);
 static PD_EXTENDED_FAILURE_LATCH: AtomicBool = AtomicBool::new(false);
 static SOFT_RESET_ACK_TOTAL: AtomicU32 = AtomicU32::new(0);
+static PROTECTION_ACK_TOTAL: AtomicU32 = AtomicU32::new(0);
+static PROTECTION_LAST_ACK_SEQ: AtomicU8 = AtomicU8::new(0);
+static PROTECTION_LAST_ACK_FLAGS: AtomicU8 = AtomicU8::new(0);
+static PROTECTION_FORCE_SEND: AtomicBool = AtomicBool::new(false);
+/// Analog-side state of the saved protection config (see `ProtectionSync`).
+pub(crate) static PROTECTION_SYNC: AtomicU8 = AtomicU8::new(ProtectionSync::Pending as u8);
+static CLEAR_FAULTS_ACK_TOTAL: AtomicU32 = AtomicU32::new(0);
+static CLEAR_FAULTS_LAST_ACK_SEQ: AtomicU8 = AtomicU8::new(0);
+// Pending ClearFaults selectors (FAULT_* | FAULT_POLICY_UV_LATCH); 0 = none.
+static CLEAR_FAULTS_REQUEST_MASK: AtomicU32 = AtomicU32::new(0);
+static RESET_COUNTERS_ACK_TOTAL: AtomicU32 = AtomicU32::new(0);
+static RESET_COUNTERS_LAST_ACK_SEQ: AtomicU8 = AtomicU8::new(0);
+// Pending ResetCounters selectors (COUNTER_*); 0 = none.
+static RESET_COUNTERS_REQUEST_MASK: AtomicU8 = AtomicU8::new(0);
 static SOFT_RESET_LAST_ACK_SEQ: AtomicU8 = AtomicU8::new(0);
 static CAL_MODE_ACK_TOTAL: AtomicU32 = AtomicU32::new(0);
 static SETMODE_TX_TOTAL: AtomicU32 = AtomicU32::new(0);
@@ -538,6 +659,7 @@ pub(crate) static LAST_CALC_P_MW: AtomicU32 = AtomicU32::new(0);
 pub(crate) static LAST_V_MAIN_MV: AtomicI32 = AtomicI32::new(0);
 pub(crate) static LAST_FAULT_FLAGS: AtomicU32 = AtomicU32::new(0);
 pub(crate) static UV_LATCHED: AtomicBool = AtomicBool::new(false);
+static LINK_SAFED: AtomicBool = AtomicBool::new(false);
 static LAST_ENABLE_BLOCK_MS: AtomicU32 = AtomicU32::new(0);
 static LAST_ENABLE_BLOCK_CODE: AtomicU8 = AtomicU8::new(0);
 pub(crate) static LINK_UP: AtomicBool = AtomicBool::new(false);
@@ -829,7 +951,7 @@ fn usb_find_pps_pdo(status: &PdStatus, object_pos: u8) -> Option<loadlynx_protoc
         .map(|(_idx, pdo)| *pdo)
 }
 
-async fn usb_cdc_write_line(tx: &mut UsbSerialJtagTx<'static, Async>, line: &UsbJsonLine) {
+async fn usb_cdc_write_line(tx: &mut UsbSerialJtagTx<'static, Async>, line: &str) {
     let _ = tx.write_all(line.as_bytes()).await;
     let _ = tx.write_all(b"\n").await;
     let _ = tx.flush().await;
@@ -970,7 +1092,31 @@ async fn write_usb_calibration_profile_response(
     write_usb_compact_voltage_curve(out, &profile.v_local);
     out.push_str(",\"vr\":").ok();
     write_usb_compact_voltage_curve(out, &profile.v_remote);
-    out.push_str("}}").ok();
+    // Temperature compensation, c1/c2/vl/vr order: [sensor, t_ref_mc, gain_ppm_per_c, offset_micro_per_c].
+    out.push_str(",\"tc\":[").ok();
+    for (idx, kind) in [
+        CurveKind::CurrentCh1,
+        CurveKind::CurrentCh2,
+        CurveKind::VLocal,
+        CurveKind::VRemote,
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        if idx != 0 {
+            out.push(',').ok();
+        }
+        let tc = profile.temp_comp_for(kind);
+        let _ = core::write!(
+            out,
+            "[{},{},{},{}]",
+            tc.sensor as u8,
+            tc.t_ref_mc,
+            tc.gain_ppm_per_c,
+            tc.offset_micro_per_c
+        );
+    }
+    out.push_str("]}}").ok();
 }
 
 fn usb_short_id_from_mac(mac: [u8; 6]) -> heapless::String<6> {
@@ -1118,11 +1264,12 @@ async fn write_usb_status_response(
             effective.preset.min_v_mv,
         )
     };
-    let (fast_status, analog_state) = {
+    let (fast_status, analog_state, thermal) = {
         let guard = telemetry.lock().await;
         (
             guard.last_status.unwrap_or(FastStatus::default()),
             AnalogState::from_u8(ANALOG_STATE.load(Ordering::Relaxed)),
+            guard.last_thermal,
         )
     };
     let mode_str = match mode {
@@ -1145,10 +1292,11 @@ async fn write_usb_status_response(
         write_json_string_escaped(out, id);
         out.push('"').ok();
     }
-    out.push_str(",\"ok\":true,\"data\":{").ok();
+    out.push_str(",\"ok\":true,\"data\":{\"wall_time\":").ok();
+    wall_clock::write_json_wall_time(out);
     let _ = core::write!(
         out,
-        "\"uptime_ms\":{},\"link_up\":{},\"hello_seen\":{},\"analog_state\":\"{}\",\"control\":{{\"active_preset_id\":{},\"output_enabled\":{},\"mode\":\"{}\",\"target_i_ma\":{},\"target_v_mv\":{},\"target_p_mw\":{},\"min_v_mv\":{}}},\"status\":{{\"state_flags\":{},\"fault_flags\":{},\"enable\":{},\"i_local_ma\":{},\"i_remote_ma\":{},\"v_local_mv\":{},\"v_remote_mv\":{},\"calc_p_mw\":{}}}}}",
+        ",\"uptime_ms\":{},\"link_up\":{},\"hello_seen\":{},\"analog_state\":\"{}\",\"control\":{{\"active_preset_id\":{},\"output_enabled\":{},\"mode\":\"{}\",\"target_i_ma\":{},\"target_v_mv\":{},\"target_p_mw\":{},\"min_v_mv\":{}}},\"status\":{{\"state_flags\":{},\"fault_flags\":{},\"enable\":{},\"i_local_ma\":{},\"i_remote_ma\":{},\"v_local_mv\":{},\"v_remote_mv\":{},\"calc_p_mw\":{}",
         now_ms32(),
         if LINK_UP.load(Ordering::Relaxed) {
             "true"
@@ -1177,6 +1325,51 @@ async fn write_usb_status_response(
         fast_status.v_remote_mv,
         fast_status.calc_p_mw,
     );
+    if let Some(v) = fast_status.sense_warnings {
+        let _ = core::write!(out, ",\"sense_warnings\":{}", v);
+    }
+    if let Some(v) = fast_status.lead_resistance_mohm {
+        let _ = core::write!(out, ",\"lead_resistance_mohm\":{}", v);
+    }
+    if let Some(v) = fast_status.channel_warnings {
+        let _ = core::write!(out, ",\"channel_warnings\":{}", v);
+    }
+    if let (Some(charge), Some(energy)) = (fast_status.charge_uah, fast_status.energy_uwh) {
+        let _ = core::write!(out, ",\"charge_uah\":{},\"energy_uwh\":{}", charge, energy);
+    }
+    out.push('}').ok();
+    out.push_str(",\"thermal\":").ok();
+    write_thermal_json(out, thermal.as_ref());
+    out.push_str("}}").ok();
+}
+
+/// `{"derate_pct":..,"time_to_trip_ms":..|null,"steady_core_mc":..|null,"temp_trip_mc":..}`
+pub(crate) fn write_thermal_json<W: core::fmt::Write>(
+    out: &mut W,
+    estimate: Option<&thermal::ThermalEstimate>,
+) {
+    let derate_pct = estimate
+        .map(|t| t.derate_pct)
+        .unwrap_or(thermal::derate_pct());
+    let _ = core::write!(out, "{{\"derate_pct\":{},\"time_to_trip_ms\":", derate_pct);
+    match estimate.and_then(|t| t.time_to_trip_ms) {
+        Some(ms) => {
+            let _ = core::write!(out, "{}", ms);
+        }
+        None => {
+            let _ = out.write_str("null");
+        }
+    }
+    let _ = out.write_str(",\"steady_core_mc\":");
+    match estimate {
+        Some(t) => {
+            let _ = core::write!(out, "{}", t.steady_core_mc);
+        }
+        None => {
+            let _ = out.write_str("null");
+        }
+    }
+    let _ = core::write!(out, ",\"temp_trip_mc\":{}}}", thermal::sink_trip_mc());
 }
 
 async fn write_usb_pd_response(
@@ -1922,6 +2115,71 @@ async fn write_usb_calibration_response(
                 ),
             }
         }
+        "get_calibration_slots" => {
+            net::render_calibration_slots_json(&mut body, calibration).await;
+            write_usb_net_body_response(
+                out,
+                request_id,
+                Ok(()),
+                &body,
+                "CALIBRATION_FAILED",
+                "calibration slots failed",
+            );
+        }
+        "calibration_slot_activate" => {
+            match net::handle_calibration_slot_activate(
+                line.unwrap_or("{}"),
+                &mut body,
+                calibration,
+                eeprom,
+            )
+            .await
+            {
+                Ok(()) => {
+                    if enqueue_cal_uart(CalUartCommand::SendAllCurves).is_err() {
+                        write_usb_error_response(
+                            out,
+                            request_id,
+                            "UNAVAILABLE",
+                            "calibration UART queue is full",
+                        );
+                        return;
+                    }
+                    write_usb_net_body_response(
+                        out,
+                        request_id,
+                        Ok(()),
+                        &body,
+                        "CALIBRATION_FAILED",
+                        "calibration slot activate failed",
+                    );
+                }
+                Err(err) => write_usb_net_body_response(
+                    out,
+                    request_id,
+                    Err(err),
+                    &body,
+                    "CALIBRATION_FAILED",
+                    "calibration slot activate failed",
+                ),
+            }
+        }
+        "calibration_slot_rename" | "calibration_slot_delete" => {
+            let line = line.unwrap_or("{}");
+            let result = if op == "calibration_slot_rename" {
+                net::handle_calibration_slot_rename(line, &mut body, calibration, eeprom).await
+            } else {
+                net::handle_calibration_slot_delete(line, &mut body, calibration, eeprom).await
+            };
+            write_usb_net_body_response(
+                out,
+                request_id,
+                result,
+                &body,
+                "CALIBRATION_FAILED",
+                "calibration slot update failed",
+            );
+        }
         "calibration_mode" => {
             match net::parse_calibration_mode_request(line.unwrap_or("{}"), &mut body) {
                 Ok(kind) => {
@@ -1958,6 +2216,91 @@ async fn write_usb_calibration_response(
     }
 }
 
+#[cfg(feature = "net_http")]
+async fn write_usb_calibration_verify_response(
+    out: &mut UsbJsonLine,
+    request_id: Option<&str>,
+    op: &str,
+    line: Option<&str>,
+    calibration: &'static CalibrationMutex,
+    telemetry: &'static TelemetryMutex,
+    eeprom: &'static EepromMutex,
+) {
+    let mut body = String::new();
+    let result = if op == "calibration_verify" {
+        net::handle_calibration_verify(
+            line.unwrap_or("{}"),
+            &mut body,
+            calibration,
+            telemetry,
+            eeprom,
+        )
+        .await
+    } else {
+        net::render_calibration_verify_json(&mut body, calibration, eeprom).await;
+        Ok(())
+    };
+    write_usb_net_body_response(
+        out,
+        request_id,
+        result,
+        &body,
+        "CALIBRATION_FAILED",
+        "calibration verify failed",
+    );
+}
+
+#[cfg(feature = "net_http")]
+async fn write_usb_protection_response(
+    out: &mut UsbJsonLine,
+    request_id: Option<&str>,
+    line: Option<&str>,
+    control: &'static ControlMutex,
+    eeprom: &'static EepromMutex,
+) {
+    let mut body = String::new();
+    let result = match line {
+        Some(line) => net::handle_protection_update(line, &mut body, control, eeprom).await,
+        None => {
+            net::render_protection_json(&mut body, control).await;
+            Ok(())
+        }
+    };
+    write_usb_net_body_response(
+        out,
+        request_id,
+        result,
+        &body,
+        "PROTECTION_FAILED",
+        "protection request failed",
+    );
+}
+
+#[cfg(feature = "net_http")]
+async fn write_usb_thermal_response(
+    out: &mut UsbJsonLine,
+    request_id: Option<&str>,
+    line: Option<&str>,
+    eeprom: &'static EepromMutex,
+) {
+    let mut body = String::new();
+    let result = match line {
+        Some(line) => net::handle_thermal_update(line, &mut body, eeprom).await,
+        None => {
+            net::render_thermal_json(&mut body);
+            Ok(())
+        }
+    };
+    write_usb_net_body_response(
+        out,
+        request_id,
+        result,
+        &body,
+        "THERMAL_FAILED",
+        "thermal request failed",
+    );
+}
+
 #[cfg(feature = "net_http")]
 fn write_usb_soft_reset_response(out: &mut UsbJsonLine, request_id: Option<&str>, line: &str) {
     let mut body = String::new();
@@ -1973,58 +2316,144 @@ fn write_usb_soft_reset_response(out: &mut UsbJsonLine, request_id: Option<&str>
 }
 
 #[cfg(feature = "net_http")]
-async fn read_usb_wifi_blob_bounded(
+fn write_usb_clear_faults_response(out: &mut UsbJsonLine, request_id: Option<&str>, line: &str) {
+    let mut body = String::new();
+    let result = net::handle_faults_clear_http(line, &mut body);
+    write_usb_net_body_response(
+        out,
+        request_id,
+        result,
+        &body,
+        "CLEAR_FAULTS_FAILED",
+        "clear faults failed",
+    );
+}
+
+#[cfg(feature = "net_http")]
+fn write_usb_reset_counters_response(out: &mut UsbJsonLine, request_id: Option<&str>, line: &str) {
+    let mut body = String::new();
+    let result = net::handle_counters_reset_http(line, &mut body);
+    write_usb_net_body_response(
+        out,
+        request_id,
+        result,
+        &body,
+        "RESET_COUNTERS_FAILED",
+        "reset counters failed",
+    );
+}
+
+#[cfg(feature = "net_http")]
+fn usb_scpi_context(
+    control: &'static ControlMutex,
+    calibration: &'static CalibrationMutex,
+    telemetry: &'static TelemetryMutex,
     eeprom: &'static EepromMutex,
-) -> Result<[u8; eeprom::EEPROM_WIFI_LEN], eeprom::EepromError> {
-    const USB_WIFI_EEPROM_TIMEOUT: Duration = Duration::from_millis(250);
-    let read = async {
-        let mut guard = eeprom.lock().await;
-        guard.read_wifi_blob().await
-    };
-    match select(read, Timer::after(USB_WIFI_EEPROM_TIMEOUT)).await {
-        Either::First(result) => result,
-        Either::Second(_) => Err(eeprom::EepromError::Timeout),
+) -> scpi::ScpiContext {
+    let short_id = usb_short_id_from_mac(hal::efuse::Efuse::mac_address());
+    scpi::ScpiContext {
+        control,
+        calibration,
+        telemetry,
+        eeprom,
+        serial: usb_hostname_from_short_id(short_id.as_str()),
     }
 }
 
+/// `{"op":"scpi","line":"MEAS:VOLT?;CURR?"}` -> `{"response":"12.000;1.000"}`.
+/// Used by devd to multiplex SCPI with other JSONL traffic; the error queue is
+/// shared with plain-text SCPI lines on the same link.
 #[cfg(feature = "net_http")]
-async fn write_usb_wifi_blob_bounded(
+async fn write_usb_scpi_response(
+    out: &mut UsbJsonLine,
+    request_id: Option<&str>,
+    line: &str,
+    session: &mut scpi::ScpiSession,
+    control: &'static ControlMutex,
+    calibration: &'static CalibrationMutex,
+    telemetry: &'static TelemetryMutex,
     eeprom: &'static EepromMutex,
-    blob: &[u8; eeprom::EEPROM_WIFI_LEN],
-) -> Result<(), eeprom::EepromError> {
-    const USB_WIFI_EEPROM_TIMEOUT: Duration = Duration::from_millis(500);
-    let write = async {
-        let mut guard = eeprom.lock().await;
-        let mut last_error = eeprom::EepromError::I2c;
-        for attempt in 0..3 {
-            match guard.write_wifi_blob(blob).await {
-                Ok(()) => return Ok(()),
-                Err(error) => {
-                    last_error = error;
-                    if attempt < 2 {
-                        Timer::after(Duration::from_millis(12)).await;
-                    }
-                }
-            }
+) {
+    let Some(program) = json_string_decoded_value(line, "\"line\"") else {
+        write_usb_error_response(out, request_id, "BAD_REQUEST", "missing line");
+        return;
+    };
+    let ctx = usb_scpi_context(control, calibration, telemetry, eeprom);
+    let mut response = String::new();
+    scpi::execute_line(&ctx, session, program.trim(), &mut response).await;
+
+    out.clear();
+    out.push_str("{\"type\":\"response\"").ok();
+    if let Some(id) = request_id {
+        out.push_str(",\"request_id\":\"").ok();
+        write_json_string_escaped(out, id);
+        out.push('"').ok();
+    }
+    out.push_str(",\"ok\":true,\"data\":{\"response\":\"").ok();
+    write_json_string_escaped(out, &response);
+    out.push_str("\"}}").ok();
+}
+
+#[cfg(feature = "net_http")]
+async fn write_usb_ir_measure_response(
+    out: &mut UsbJsonLine,
+    request_id: Option<&str>,
+    op: &str,
+    line: &str,
+    control: &'static ControlMutex,
+    calibration: &'static CalibrationMutex,
+) {
+    let mut body = String::new();
+    let result = match op {
+        "start_ir_measure" => {
+            net::handle_ir_measure_start(line, &mut body, control, calibration).await
+        }
+        "cancel_ir_measure" => {
+            net::handle_ir_measure_cancel(&mut body);
+            Ok(())
+        }
+        _ => {
+            net::render_ir_measure_json(&mut body);
+            Ok(())
         }
-        Err(last_error)
     };
-    match select(write, Timer::after(USB_WIFI_EEPROM_TIMEOUT)).await {
+    write_usb_net_body_response(
+        out,
+        request_id,
+        result,
+        &body,
+        "IR_MEASURE_FAILED",
+        "IR measurement request failed",
+    );
+}
+
+#[cfg(feature = "net_http")]
+async fn read_usb_wifi_networks_bounded(
+    eeprom: &'static EepromMutex,
+) -> Result<net::WifiNetworks, eeprom::EepromError> {
+    const USB_WIFI_EEPROM_TIMEOUT: Duration = Duration::from_millis(250);
+    match select(
+        net::read_wifi_networks(eeprom),
+        Timer::after(USB_WIFI_EEPROM_TIMEOUT),
+    )
+    .await
+    {
         Either::First(result) => result,
         Either::Second(_) => Err(eeprom::EepromError::Timeout),
     }
 }
 
 #[cfg(feature = "net_http")]
-async fn clear_usb_wifi_blob_bounded(
+async fn store_usb_wifi_networks_bounded(
     eeprom: &'static EepromMutex,
+    networks: &[net::WifiNetwork],
 ) -> Result<(), eeprom::EepromError> {
-    const USB_WIFI_EEPROM_TIMEOUT: Duration = Duration::from_millis(500);
-    let clear = async {
-        let mut guard = eeprom.lock().await;
+    // A full list spans ~20 EEPROM pages plus the legacy mirror.
+    const USB_WIFI_EEPROM_TIMEOUT: Duration = Duration::from_millis(1500);
+    let write = async {
         let mut last_error = eeprom::EepromError::I2c;
         for attempt in 0..3 {
-            match guard.clear_wifi_blob().await {
+            match net::store_wifi_networks(eeprom, networks).await {
                 Ok(()) => return Ok(()),
                 Err(error) => {
                     last_error = error;
@@ -2036,7 +2465,7 @@ async fn clear_usb_wifi_blob_bounded(
         }
         Err(last_error)
     };
-    match select(clear, Timer::after(USB_WIFI_EEPROM_TIMEOUT)).await {
+    match select(write, Timer::after(USB_WIFI_EEPROM_TIMEOUT)).await {
         Either::First(result) => result,
         Either::Second(_) => Err(eeprom::EepromError::Timeout),
     }
@@ -2060,51 +2489,55 @@ async fn write_usb_wifi_response(
     }
     match op {
         "get_wifi_credentials" => {
-            let blob = read_usb_wifi_blob_bounded(eeprom).await;
-            let Ok(blob) = blob else {
+            let Ok(user) = read_usb_wifi_networks_bounded(eeprom).await else {
                 out.push_str(",\"ok\":false,\"error\":{\"code\":\"UNAVAILABLE\",\"message\":\"EEPROM read failed\"}}").ok();
                 return;
             };
-            let credentials = eeprom::decode_wifi_blob(&blob)
-                .map(|parts| (String::from(parts.ssid), String::from(parts.psk)));
-            let (ssid, psk, source) = match credentials {
-                Some((ssid, psk)) => (ssid, psk, "user"),
-                None => match (WIFI_SSID, WIFI_PSK) {
-                    (Some(ssid), Some(psk)) => (String::from(ssid), String::from(psk), "factory"),
-                    _ => (String::new(), String::new(), "none"),
-                },
-            };
+            let (networks, source) = usb_wifi_effective_networks(user);
+            let top = networks.first();
             out.push_str(",\"ok\":true,\"data\":{\"ssid\":\"").ok();
-            write_json_string_escaped(out, &ssid);
+            write_json_string_escaped(out, top.map_or("", |n| n.ssid.as_str()));
             out.push_str("\",\"psk\":\"").ok();
-            write_json_string_escaped(out, &psk);
+            write_json_string_escaped(out, top.map_or("", |n| n.psk.as_str()));
             out.push_str("\",\"source\":\"").ok();
             out.push_str(source).ok();
-            out.push_str("\"}}").ok();
+            out.push_str("\",\"networks\":[").ok();
+            for (idx, network) in networks.iter().enumerate() {
+                if idx > 0 {
+                    out.push(',').ok();
+                }
+                out.push_str("{\"ssid\":\"").ok();
+                write_json_string_escaped(out, &network.ssid);
+                out.push_str("\",\"psk\":\"").ok();
+                write_json_string_escaped(out, &network.psk);
+                out.push_str("\"}").ok();
+            }
+            out.push_str("]}}").ok();
         }
         "get_wifi_status" => {
-            let user_ssid = read_usb_wifi_blob_bounded(eeprom)
+            let user = read_usb_wifi_networks_bounded(eeprom)
                 .await
-                .ok()
-                .and_then(|blob| {
-                    eeprom::decode_wifi_blob(&blob).map(|parts| String::from(parts.ssid))
-                });
+                .unwrap_or_default();
+            let network_count = user.len();
+            let (networks, source) = usb_wifi_effective_networks(user);
             let mut status = { *wifi_state.lock().await };
-            let source = match (user_ssid.is_some(), WIFI_SSID.is_some()) {
-                (true, _) => "user",
-                (false, true) => "factory",
-                (false, false) => "none",
-            };
-            if source == "none" {
+            if source == "none" && !matches!(status.state, net::WifiConnectionState::Provisioning) {
                 status.state = net::WifiConnectionState::Idle;
                 status.ipv4 = None;
                 status.gateway = None;
                 status.last_error = None;
             }
+            let ssid = match status.active_ssid {
+                Some(active) if networks.iter().any(|n| n.ssid == active.as_str()) => {
+                    String::from(active.as_str())
+                }
+                _ => networks.first().map(|n| n.ssid.clone()).unwrap_or_default(),
+            };
             out.push_str(",\"ok\":true,\"data\":{\"ssid\":\"").ok();
-            write_json_string_escaped(out, user_ssid.as_deref().or(WIFI_SSID).unwrap_or(""));
+            write_json_string_escaped(out, &ssid);
             out.push_str("\",\"source\":\"").ok();
             out.push_str(source).ok();
+            let _ = core::write!(out, "\",\"network_count\":{}", network_count);
             write_usb_wifi_status_tail(out, status);
         }
         "set_wifi_config" => {
@@ -2120,19 +2553,29 @@ async fn write_usb_wifi_response(
                 out.push_str(",\"ok\":false,\"error\":{\"code\":\"INVALID_REQUEST\",\"message\":\"missing psk\"}}").ok();
                 return;
             };
-            let blob = match eeprom::encode_wifi_blob(&ssid, &psk) {
-                Ok(blob) => blob,
-                Err(message) => {
-                    out.push_str(
-                        ",\"ok\":false,\"error\":{\"code\":\"INVALID_REQUEST\",\"message\":\"",
-                    )
-                    .ok();
-                    write_json_string_escaped(out, message);
-                    out.push_str("\"}}").ok();
-                    return;
-                }
+            let Ok(mut networks) = read_usb_wifi_networks_bounded(eeprom).await else {
+                out.push_str(",\"ok\":false,\"error\":{\"code\":\"UNAVAILABLE\",\"message\":\"EEPROM read failed\"}}").ok();
+                return;
             };
-            if write_usb_wifi_blob_bounded(eeprom, &blob).await.is_err() {
+            // Same semantics as `POST /api/v1/wifi`: the network moves to the
+            // top of the stored list and the station reconnects to it.
+            if let Err(message) = net::upsert_wifi_network(&mut networks, &ssid, &psk, 0) {
+                let code = if message == net::WIFI_NETWORKS_FULL {
+                    "INVALID_STATE"
+                } else {
+                    "INVALID_REQUEST"
+                };
+                out.push_str(",\"ok\":false,\"error\":{\"code\":\"").ok();
+                out.push_str(code).ok();
+                out.push_str("\",\"message\":\"").ok();
+                write_json_string_escaped(out, message);
+                out.push_str("\"}}").ok();
+                return;
+            }
+            if store_usb_wifi_networks_bounded(eeprom, &networks)
+                .await
+                .is_err()
+            {
                 out.push_str(",\"ok\":false,\"error\":{\"code\":\"UNAVAILABLE\",\"message\":\"EEPROM write failed\"}}").ok();
                 return;
             }
@@ -2144,10 +2587,11 @@ async fn write_usb_wifi_response(
             out.push_str(",\"ok\":true,\"data\":{\"ssid\":\"").ok();
             write_json_string_escaped(out, &ssid);
             out.push_str("\",\"source\":\"user").ok();
+            let _ = core::write!(out, "\",\"network_count\":{}", networks.len());
             write_usb_wifi_status_tail(out, status);
         }
         "clear_wifi_config" => {
-            if clear_usb_wifi_blob_bounded(eeprom).await.is_err() {
+            if store_usb_wifi_networks_bounded(eeprom, &[]).await.is_err() {
                 out.push_str(",\"ok\":false,\"error\":{\"code\":\"UNAVAILABLE\",\"message\":\"EEPROM clear failed\"}}").ok();
                 return;
             }
@@ -2162,6 +2606,7 @@ async fn write_usb_wifi_response(
                 "none"
             })
             .ok();
+            out.push_str("\",\"network_count\":0").ok();
             write_usb_wifi_status_tail(out, status);
         }
         _ => {
@@ -2171,6 +2616,57 @@ async fn write_usb_wifi_response(
     }
 }
 
+/// Stored networks, or the factory network when none are stored.
+#[cfg(feature = "net_http")]
+fn usb_wifi_effective_networks(user: net::WifiNetworks) -> (net::WifiNetworks, &'static str) {
+    if !user.is_empty() {
+        return (user, "user");
+    }
+    let mut networks = net::WifiNetworks::new();
+    match (WIFI_SSID, WIFI_PSK) {
+        (Some(ssid), Some(psk)) => {
+            let _ = networks.push(net::WifiNetwork {
+                ssid: String::from(ssid),
+                psk: String::from(psk),
+            });
+            (networks, "factory")
+        }
+        _ => (networks, "none"),
+    }
+}
+
+#[cfg(feature = "net_http")]
+async fn write_usb_wifi_networks_response(
+    out: &mut UsbJsonLine,
+    request_id: Option<&str>,
+    op: &str,
+    line: &str,
+    eeprom: &'static EepromMutex,
+    wifi_state: &'static net::WifiStateMutex,
+) {
+    let mut body = String::new();
+    let result = match op {
+        "add_wifi_network" => {
+            net::handle_wifi_network_add_http(line, &mut body, eeprom, wifi_state).await
+        }
+        "remove_wifi_network" => {
+            net::handle_wifi_network_remove_http(line, &mut body, eeprom, wifi_state).await
+        }
+        "move_wifi_network" => {
+            net::handle_wifi_network_move_http(line, &mut body, eeprom, wifi_state).await
+        }
+        _ => net::render_wifi_networks_json(&mut body, eeprom, wifi_state).await,
+    };
+    write_usb_net_body_response(
+        out,
+        request_id,
+        result,
+        &body,
+        "WIFI_NETWORKS_FAILED",
+        "Wi-Fi network list request failed",
+    );
+}
+
 #[cfg(feature = "net_http")]
 fn write_usb_wifi_status_tail(out: &mut UsbJsonLine, status: net::WifiState) {
     out.push_str("\",\"state\":\"").ok();
@@ -2205,6 +2701,7 @@ fn wifi_state_name(state: net::WifiConnectionState) -> &'static str {
         net::WifiConnectionState::Idle => "idle",
         net::WifiConnectionState::Connecting => "connecting",
         net::WifiConnectionState::Connected => "connected",
+        net::WifiConnectionState::Provisioning => "provisioning",
         net::WifiConnectionState::Error => "error",
     }
 }
@@ -2266,15 +2763,206 @@ async fn write_usb_diagnostics_response(
     calibration: &'static CalibrationMutex,
 ) {
     let mut body = String::new();
-    let result =
-        net::render_diagnostics_json(&mut body, eeprom, wifi_state, telemetry, calibration).await;
+    let result =
+        net::render_diagnostics_json(&mut body, eeprom, wifi_state, telemetry, calibration).await;
+    write_usb_net_body_response(
+        out,
+        request_id,
+        result,
+        &body,
+        "DIAGNOSTICS_FAILED",
+        "diagnostics export failed",
+    );
+}
+
+/// `get_time` / `set_time`: the set path lets the host seed the wall clock
+/// when the device has no Wi-Fi (and hence no SNTP).
+#[cfg(feature = "net_http")]
+async fn write_usb_time_response(
+    out: &mut UsbJsonLine,
+    request_id: Option<&str>,
+    line: Option<&str>,
+    eeprom: &'static EepromMutex,
+) {
+    let mut body = String::new();
+    let result = match line {
+        None => net::render_time_json(&mut body, eeprom).await,
+        Some(line) => net::handle_time_set_http(line, &mut body, eeprom).await,
+    };
+    write_usb_net_body_response(
+        out,
+        request_id,
+        result,
+        &body,
+        "TIME_FAILED",
+        "time request failed",
+    );
+}
+
+/// `get_firmware` reports the OTA slots; `get_ota_token` / `rotate_ota_token`
+/// hand out the upload token, which is deliberately not readable over HTTP.
+#[cfg(feature = "net_http")]
+async fn write_usb_firmware_response(
+    out: &mut UsbJsonLine,
+    request_id: Option<&str>,
+    op: &str,
+    eeprom: &'static EepromMutex,
+) {
+    let mut body = String::new();
+    let result = match op {
+        "get_firmware" => net::render_firmware_json(&mut body, eeprom).await,
+        _ => match ota::token_hex(eeprom, op == "rotate_ota_token").await {
+            Ok(token) => {
+                body.push_str("{\"token\":\"");
+                body.push_str(&token);
+                body.push_str("\"}");
+                Ok(())
+            }
+            Err(message) => {
+                net::write_error_body(&mut body, "UNAVAILABLE", message, true, None);
+                Err("503 Service Unavailable")
+            }
+        },
+    };
+    write_usb_net_body_response(
+        out,
+        request_id,
+        result,
+        &body,
+        "FIRMWARE_FAILED",
+        "firmware request failed",
+    );
+}
+
+/// HTTP API tokens (`auth.rs`). USB is trusted like `get_ota_token`, so
+/// `create_api_token {role?, label?}` issues a token without the on-screen
+/// pairing code; `role` defaults to `control`, `label` to `usb`.
+#[cfg(feature = "net_http")]
+async fn write_usb_auth_response(
+    out: &mut UsbJsonLine,
+    request_id: Option<&str>,
+    op: &str,
+    line: &str,
+    eeprom: &'static EepromMutex,
+) {
+    let mut body = String::new();
+    let result = match op {
+        "create_api_token" => {
+            let label = json_string_value(line, "\"label\"").unwrap_or("usb");
+            let Some(role) = json_string_value(line, "\"role\"")
+                .map_or(Some(auth::Role::Control), auth::Role::parse)
+            else {
+                write_usb_error_response(
+                    out,
+                    request_id,
+                    "INVALID_REQUEST",
+                    "role must be \"read\", \"control\" or \"admin\"",
+                );
+                return;
+            };
+            match auth::issue(eeprom, role, label).await {
+                Ok(token) => {
+                    net::write_issued_token_json(&mut body, &token, role, label);
+                    Ok(())
+                }
+                Err(err) => Err(net::write_auth_error(&mut body, err)),
+            }
+        }
+        "revoke_api_token" => {
+            let Some(label) = json_string_value(line, "\"label\"") else {
+                write_usb_error_response(out, request_id, "INVALID_REQUEST", "label is required");
+                return;
+            };
+            let revoked = match auth::revoke(eeprom, label).await {
+                Ok(()) => auth::render_tokens_json(&mut body, eeprom).await,
+                Err(err) => Err(err),
+            };
+            revoked.map_err(|err| net::write_auth_error(&mut body, err))
+        }
+        _ => auth::render_tokens_json(&mut body, eeprom)
+            .await
+            .map_err(|err| net::write_auth_error(&mut body, err)),
+    };
+    write_usb_net_body_response(
+        out,
+        request_id,
+        result,
+        &body,
+        "AUTH_FAILED",
+        "API token request failed",
+    );
+}
+
+/// Analog STM32 update over the UART bootloader bridge (`analog_flash.rs`),
+/// one step per request so each stays within the host's request timeout:
+/// `analog_flash_begin {size, sha256}`, `analog_flash_write {offset, data}`
+/// with up to 1 KiB of hex data, `analog_flash_finish`.
+#[cfg(feature = "net_http")]
+async fn write_usb_analog_flash_response(
+    out: &mut UsbJsonLine,
+    request_id: Option<&str>,
+    op: &str,
+    line: &str,
+    control: &'static ControlMutex,
+) {
+    let step = match op {
+        "get_analog_firmware" => Ok(()),
+        "analog_flash_begin" => {
+            let size = json_u32_value(line, "\"size\"");
+            let sha256 = json_string_value(line, "\"sha256\"").and_then(ota::parse_hex::<32>);
+            let (Some(size), Some(sha256)) = (size, sha256) else {
+                write_usb_error_response(
+                    out,
+                    request_id,
+                    "INVALID_REQUEST",
+                    "analog_flash_begin requires size and sha256",
+                );
+                return;
+            };
+            if control.lock().await.output_enabled {
+                write_usb_error_response(
+                    out,
+                    request_id,
+                    "INVALID_STATE",
+                    "disable the output before a firmware update",
+                );
+                return;
+            }
+            analog_flash::begin(size, sha256).await
+        }
+        "analog_flash_write" => {
+            let mut data = [0u8; analog_flash::MAX_WRITE_CHUNK];
+            let offset = json_u32_value(line, "\"offset\"");
+            let len = json_string_value(line, "\"data\"")
+                .and_then(|hex| analog_flash::parse_hex_into(hex, &mut data));
+            let (Some(offset), Some(len @ 1..)) = (offset, len) else {
+                write_usb_error_response(
+                    out,
+                    request_id,
+                    "INVALID_REQUEST",
+                    "analog_flash_write requires offset and 1..=1024 bytes of hex data",
+                );
+                return;
+            };
+            analog_flash::write(offset, &data[..len]).await.map(|_| ())
+        }
+        _ => analog_flash::finish().await,
+    };
+    let mut body = String::new();
+    let result = match step {
+        Ok(()) => {
+            net::render_analog_firmware_json(&mut body);
+            Ok(())
+        }
+        Err(err) => Err(net::write_analog_flash_error(&mut body, err)),
+    };
     write_usb_net_body_response(
         out,
         request_id,
         result,
         &body,
-        "DIAGNOSTICS_FAILED",
-        "diagnostics export failed",
+        "ANALOG_FLASH_FAILED",
+        "analog firmware update failed",
     );
 }
 
@@ -2286,6 +2974,7 @@ async fn handle_usb_jsonl_request(
     telemetry: &'static TelemetryMutex,
     eeprom: &'static EepromMutex,
     #[cfg(feature = "net_http")] wifi_state: &'static net::WifiStateMutex,
+    #[cfg(feature = "net_http")] scpi_session: &mut scpi::ScpiSession,
 ) {
     let request_id = json_string_value(line, "\"request_id\"");
     let Some(op) = json_string_value(line, "\"op\"") else {
@@ -2359,7 +3048,11 @@ async fn handle_usb_jsonl_request(
         | "calibration_apply"
         | "calibration_commit"
         | "calibration_reset"
-        | "calibration_mode" => {
+        | "calibration_mode"
+        | "get_calibration_slots"
+        | "calibration_slot_activate"
+        | "calibration_slot_rename"
+        | "calibration_slot_delete" => {
             write_usb_calibration_response(
                 out,
                 request_id,
@@ -2372,12 +3065,63 @@ async fn handle_usb_jsonl_request(
             .await
         }
         #[cfg(feature = "net_http")]
+        "get_calibration_verify" | "calibration_verify" => {
+            write_usb_calibration_verify_response(
+                out,
+                request_id,
+                op,
+                Some(line),
+                calibration,
+                telemetry,
+                eeprom,
+            )
+            .await
+        }
+        #[cfg(feature = "net_http")]
         "get_wifi_status" | "get_wifi_credentials" | "set_wifi_config" | "clear_wifi_config" => {
             write_usb_wifi_response(out, request_id, op, Some(line), eeprom, wifi_state).await
         }
         #[cfg(feature = "net_http")]
+        "get_wifi_networks" | "add_wifi_network" | "remove_wifi_network" | "move_wifi_network" => {
+            write_usb_wifi_networks_response(out, request_id, op, line, eeprom, wifi_state).await
+        }
+        #[cfg(feature = "net_http")]
+        "get_protection" => {
+            write_usb_protection_response(out, request_id, None, control, eeprom).await
+        }
+        #[cfg(feature = "net_http")]
+        "set_protection" => {
+            write_usb_protection_response(out, request_id, Some(line), control, eeprom).await
+        }
+        #[cfg(feature = "net_http")]
+        "get_thermal" => write_usb_thermal_response(out, request_id, None, eeprom).await,
+        #[cfg(feature = "net_http")]
+        "set_thermal" => write_usb_thermal_response(out, request_id, Some(line), eeprom).await,
+        #[cfg(feature = "net_http")]
         "soft_reset" => write_usb_soft_reset_response(out, request_id, line),
         #[cfg(feature = "net_http")]
+        "clear_faults" => write_usb_clear_faults_response(out, request_id, line),
+        #[cfg(feature = "net_http")]
+        "reset_counters" => write_usb_reset_counters_response(out, request_id, line),
+        #[cfg(feature = "net_http")]
+        "scpi" => {
+            write_usb_scpi_response(
+                out,
+                request_id,
+                line,
+                scpi_session,
+                control,
+                calibration,
+                telemetry,
+                eeprom,
+            )
+            .await
+        }
+        #[cfg(feature = "net_http")]
+        "get_ir_measure" | "start_ir_measure" | "cancel_ir_measure" => {
+            write_usb_ir_measure_response(out, request_id, op, line, control, calibration).await
+        }
+        #[cfg(feature = "net_http")]
         "get_diagnostics" => {
             write_usb_diagnostics_response(
                 out,
@@ -2389,6 +3133,25 @@ async fn handle_usb_jsonl_request(
             )
             .await
         }
+        #[cfg(feature = "net_http")]
+        "get_time" => write_usb_time_response(out, request_id, None, eeprom).await,
+        #[cfg(feature = "net_http")]
+        "set_time" => write_usb_time_response(out, request_id, Some(line), eeprom).await,
+        #[cfg(feature = "net_http")]
+        "get_firmware" | "get_ota_token" | "rotate_ota_token" => {
+            write_usb_firmware_response(out, request_id, op, eeprom).await
+        }
+        #[cfg(feature = "net_http")]
+        "get_api_tokens" | "create_api_token" | "revoke_api_token" => {
+            write_usb_auth_response(out, request_id, op, line, eeprom).await
+        }
+        #[cfg(feature = "net_http")]
+        "get_analog_firmware"
+        | "analog_flash_begin"
+        | "analog_flash_write"
+        | "analog_flash_finish" => {
+            write_usb_analog_flash_response(out, request_id, op, line, control).await
+        }
         _ => write_usb_error_response(out, request_id, "UNSUPPORTED_OPERATION", "unsupported op"),
     }
 }
@@ -2410,7 +3173,7 @@ async fn usb_cdc_jsonl_task(
     out.push_str("\",\"features\":[\"get_identity\",\"get_status\",\"get_pd\",\"set_pd_policy\",\"set_output_enabled\",\"set_cc_target\"")
         .ok();
     #[cfg(feature = "net_http")]
-    out.push_str(",\"get_control\",\"set_control\",\"get_presets\",\"set_preset\",\"apply_preset\",\"get_calibration_profile\",\"calibration_apply\",\"calibration_commit\",\"calibration_reset\",\"calibration_mode\",\"get_wifi_status\",\"get_wifi_credentials\",\"set_wifi_config\",\"clear_wifi_config\",\"soft_reset\",\"get_diagnostics\"")
+    out.push_str(",\"get_control\",\"set_control\",\"get_presets\",\"set_preset\",\"apply_preset\",\"get_calibration_profile\",\"calibration_apply\",\"calibration_commit\",\"calibration_reset\",\"calibration_mode\",\"get_calibration_slots\",\"calibration_slot_activate\",\"calibration_slot_rename\",\"calibration_slot_delete\",\"get_calibration_verify\",\"calibration_verify\",\"get_wifi_status\",\"get_wifi_credentials\",\"set_wifi_config\",\"clear_wifi_config\",\"get_wifi_networks\",\"add_wifi_network\",\"remove_wifi_network\",\"move_wifi_network\",\"get_protection\",\"set_protection\",\"get_thermal\",\"set_thermal\",\"soft_reset\",\"clear_faults\",\"reset_counters\",\"get_ir_measure\",\"start_ir_measure\",\"cancel_ir_measure\",\"get_diagnostics\",\"get_time\",\"set_time\",\"get_firmware\",\"get_ota_token\",\"rotate_ota_token\",\"get_analog_firmware\",\"analog_flash_begin\",\"analog_flash_write\",\"analog_flash_finish\",\"get_api_tokens\",\"create_api_token\",\"revoke_api_token\",\"scpi\"")
         .ok();
     out.push_str("]}").ok();
     usb_cdc_write_line(&mut tx, &out).await;
@@ -2418,6 +3181,10 @@ async fn usb_cdc_jsonl_task(
     let mut read_buf = [0_u8; 64];
     let mut line_buf = [0_u8; 4096];
     let mut line_len = 0usize;
+    #[cfg(feature = "net_http")]
+    let scpi_ctx = usb_scpi_context(control, calibration, telemetry, eeprom);
+    #[cfg(feature = "net_http")]
+    let mut scpi_session = scpi::ScpiSession::trusted();
 
     loop {
         let n = match rx.read(&mut read_buf).await {
@@ -2437,6 +3204,18 @@ async fn usb_cdc_jsonl_task(
                 b'\n' => {
                     let line = core::str::from_utf8(&line_buf[..line_len]).unwrap_or("");
                     let line = line.trim();
+                    // Lines that are not JSON objects are SCPI text, sharing
+                    // the command set and error queue with the `scpi` op.
+                    #[cfg(feature = "net_http")]
+                    if !line.is_empty() && !line.starts_with('{') {
+                        let mut response = String::new();
+                        scpi::execute_line(&scpi_ctx, &mut scpi_session, line, &mut response).await;
+                        if !response.is_empty() {
+                            usb_cdc_write_line(&mut tx, &response).await;
+                        }
+                        line_len = 0;
+                        continue;
+                    }
                     if !line.is_empty() {
                         handle_usb_jsonl_request(
                             line,
@@ -2447,6 +3226,8 @@ async fn usb_cdc_jsonl_task(
                             eeprom,
                             #[cfg(feature = "net_http")]
                             wifi_state,
+                            #[cfg(feature = "net_http")]
+                            &mut scpi_session,
                         )
                         .await;
                         usb_cdc_write_line(&mut tx, &out).await;
@@ -3777,6 +4558,9 @@ async fn touch_ui_task(
     enum TapAction {
         DashboardSettingsOpen,
         DashboardPdToggle,
+        DashboardClearFaults,
+        #[cfg(feature = "net_http")]
+        DashboardPairingRole,
         PdSettingsBack,
         PdSettingsApply,
         PdSettingsModeFixed,
@@ -4479,6 +5263,51 @@ async fn touch_ui_task(
                         continue;
                     }
 
+                    #[cfg(feature = "net_http")]
+                    if view == control::UiView::Main
+                        && ui::hit_test_dashboard_run_line(marker.x, marker.y)
+                        && auth::pairing_prompt().is_some()
+                    {
+                        if !is_duplicate_tap_action(
+                            &mut last_tap_action,
+                            TapAction::DashboardPairingRole,
+                            now_ms32(),
+                        ) && auth::cycle_pairing_role()
+                        {
+                            speaker::enqueue(speaker::SpeakerSound::UiTouch);
+                            info!("touch: pairing line tap -> cycle pairing role");
+                        }
+                        PRESET_PREVIEW_ID.store(0, Ordering::Relaxed);
+                        last_tab_tap = None;
+                        yield_now().await;
+                        continue;
+                    }
+
+                    if view == control::UiView::Main
+                        && ui::hit_test_dashboard_reason_line(marker.x, marker.y)
+                        && (LAST_FAULT_FLAGS.load(Ordering::Relaxed) != 0
+                            || UV_LATCHED.load(Ordering::Relaxed)
+                            || prompt_tone::is_trip_alarm_latched()
+                            || prompt_tone::is_link_alarm_latched())
+                    {
+                        if !is_duplicate_tap_action(
+                            &mut last_tap_action,
+                            TapAction::DashboardClearFaults,
+                            now_ms32(),
+                        ) {
+                            request_clear_faults(FAULT_POLICY_ALL);
+                            prompt_tone::enqueue_ui_ok();
+                            info!(
+                                "touch: reason line tap -> clear faults (fault_flags=0x{:08x})",
+                                LAST_FAULT_FLAGS.load(Ordering::Relaxed)
+                            );
+                        }
+                        PRESET_PREVIEW_ID.store(0, Ordering::Relaxed);
+                        last_tab_tap = None;
+                        yield_now().await;
+                        continue;
+                    }
+
                     if view == control::UiView::Main
                         && ui::hit_test_dashboard_pd_button(marker.x, marker.y)
                     {
@@ -6270,6 +7099,9 @@ pub struct TelemetryModel {
     /// by the optional HTTP API to expose a structured status view.
     pub last_status: Option<FastStatus>,
     pub last_pd_status: Option<PdStatus>,
+    /// Digital-side thermal derating model fed by every FastStatus frame.
+    thermal: thermal::ThermalModel,
+    pub last_thermal: Option<thermal::ThermalEstimate>,
     last_touch_marker_seq: u32,
 }
 
@@ -6281,6 +7113,8 @@ impl TelemetryModel {
             last_rendered: None,
             last_status: None,
             last_pd_status: None,
+            thermal: thermal::ThermalModel::new(thermal::THERMAL_MODEL_DEFAULT),
+            last_thermal: None,
             last_touch_marker_seq: 0,
         }
     }
@@ -6316,16 +7150,38 @@ impl TelemetryModel {
         self.snapshot.sink_exhaust_temp = status.sink_exhaust_temp_mc as f32 / 1000.0;
         self.snapshot.mcu_temp = status.mcu_temp_mc as f32 / 1000.0;
         self.snapshot.fault_flags = status.fault_flags;
+        self.snapshot.sense_warnings = status.sense_warnings.unwrap_or(0);
+        self.snapshot.lead_resistance_mohm = status.lead_resistance_mohm;
+        self.snapshot.channel_warnings = status.channel_warnings.unwrap_or(0);
+        self.thermal.set_config(thermal::config());
+        let thermal = self.thermal.update(status, &thermal::limit_profile());
+        self.snapshot.thermal_derate_pct = thermal.derate_pct;
+        self.snapshot.thermal_time_to_trip_s = thermal.time_to_trip_ms.map(|ms| ms / 1000);
+        self.last_thermal = Some(thermal);
         let analog_state = AnalogState::from_u8(ANALOG_STATE.load(Ordering::Relaxed));
         self.snapshot.analog_state = analog_state;
 
-        write_runtime(&mut self.snapshot.run_time, status.uptime_ms);
+        // Analog-side counters are integrated at control-loop rate and can be
+        // reset remotely; RUN then shows the time since that reset. Older
+        // analog firmware falls back to uptime and a local 20 Hz estimate.
+        let counters = status.charge_uah.zip(status.energy_uwh);
+        self.snapshot.counters_live = counters.is_some();
+        self.snapshot.run_line_page =
+            ((status.uptime_ms / ui::RUN_LINE_PAGE_MS) % ui::RUN_LINE_PAGE_COUNT as u32) as u8;
+        write_runtime(
+            &mut self.snapshot.run_time,
+            status.counter_elapsed_ms.unwrap_or(status.uptime_ms),
+        );
 
-        if let Some(prev) = self.last_uptime_ms {
+        if let Some((charge_uah, energy_uwh)) = counters {
+            self.snapshot.charge_ah = charge_uah as f32 / 1_000_000.0;
+            self.snapshot.energy_wh = energy_uwh as f32 / 1_000_000.0;
+        } else if let Some(prev) = self.last_uptime_ms {
             let delta_ms = status.uptime_ms.wrapping_sub(prev);
             if delta_ms < 60_000 {
                 let delta_hours = delta_ms as f32 / 3_600_000.0;
                 self.snapshot.energy_wh += power_w * delta_hours;
+                self.snapshot.charge_ah += i_total.max(0.0) * delta_hours;
             }
         }
         self.last_uptime_ms = Some(status.uptime_ms);
@@ -6346,6 +7202,12 @@ impl TelemetryModel {
         // intentionally called from the display task (UI context), not from the
         // UART link task, to avoid doing floating-point formatting work in the
         // UART path on ProCpu.
+        #[cfg(feature = "net_http")]
+        {
+            let prompt = auth::pairing_prompt();
+            self.snapshot.pairing_code = prompt.map(|(code, _)| code);
+            self.snapshot.pairing_role = prompt.and_then(|(_, role)| role).map(auth::Role::abbrev);
+        }
         self.snapshot.update_strings();
 
         let prev_snapshot = self.last_rendered.as_ref();
@@ -6568,7 +7430,9 @@ async fn wifi_ui_task(state: &'static net::WifiStateMutex, telemetry: &'static T
             let guard = state.lock().await;
             match guard.state {
                 net::WifiConnectionState::Connected => WifiUiStatus::Ok,
-                net::WifiConnectionState::Connecting => WifiUiStatus::Connecting,
+                net::WifiConnectionState::Connecting | net::WifiConnectionState::Provisioning => {
+                    WifiUiStatus::Connecting
+                }
                 net::WifiConnectionState::Idle => {
                     if guard.last_error.is_some() {
                         WifiUiStatus::Error
@@ -6621,6 +7485,15 @@ async fn apply_fast_status(
     if uv_latched && !prev_uv_latched {
         prompt_tone::latch_trip_alarm(prompt_tone::TripReason::Uvlo);
     }
+    // The analog side ramped the output off after losing the link (or came back
+    // from an IWDG reset). The link is already back by the time we see this, so
+    // latch the LNK alarm explicitly; output stays off until re-enabled.
+    let link_safed = (status.state_flags & STATE_FLAG_LINK_SAFED) != 0;
+    let prev_link_safed = LINK_SAFED.swap(link_safed, Ordering::Relaxed);
+    if link_safed && !prev_link_safed {
+        warn!("analog reports link-loss safing; output held off until re-enabled");
+        prompt_tone::latch_link_alarm();
+    }
     let enabled = status.enable;
     let desired_output_enabled = DESIRED_OUTPUT_ENABLED.load(Ordering::Relaxed);
     // Non-fatal warning class: while the load is still enabled, the analog side may report
@@ -7615,6 +8488,23 @@ fn log_framebuffer_samples(label: &'static str, framebuffer: &[u8]) {
     }
 }
 
+/// UART1 link settings. The link runs 8N1; the analog firmware update
+/// (`analog_flash.rs`) switches to even parity for the STM32 ROM bootloader.
+pub(crate) fn link_uart_config(parity: Parity) -> UartConfig {
+    UartConfig::default()
+        // Match analog MCU; 230400 baud is sufficient for 30 Hz FAST_STATUS traffic with SLIP overhead,
+        // and keeps headroom if we later move the sender back towards 60 Hz.
+        .with_baudrate(UART_BAUD)
+        .with_data_bits(DataBits::_8)
+        .with_parity(parity)
+        .with_stop_bits(StopBits::_1)
+        .with_rx(
+            RxConfig::default()
+                .with_fifo_full_threshold(UART_RX_FIFO_FULL_THRESHOLD)
+                .with_timeout(UART_RX_TIMEOUT_SYMS),
+        )
+}
+
 #[embassy_executor::task]
 async fn uart_link_task(
     uart: &'static mut Uart<'static, Async>,
@@ -7700,6 +8590,12 @@ async fn uart_link_task_dma(
                 // When chunk_limit < dma buffer len, received bytes may wrap across descriptors.
                 // Always consume via the provided iterator to preserve ordering.
                 for chunk in buf_back.received_data() {
+                    // Bytes from the STM32 ROM bootloader during an analog update.
+                    #[cfg(feature = "net_http")]
+                    if analog_flash::bridge_rx(chunk) {
+                        decoder.reset();
+                        continue;
+                    }
                     feed_decoder(chunk, decoder, calibration, control, telemetry).await;
                 }
                 dma_rx = buf_back;
@@ -7864,6 +8760,39 @@ async fn feed_decoder(
                                 );
                             }
                         }
+                        MSG_PROTECTION_CONFIG => {
+                            if header.flags & (FLAG_IS_ACK | FLAG_IS_NACK) != 0 {
+                                record_link_activity();
+                                handle_protection_config_ack(&header);
+                            } else {
+                                rate_limited_proto_warn(
+                                    "unexpected PROTECTION_CONFIG frame (not ack/nack)",
+                                    Some(frame.as_slice()),
+                                );
+                            }
+                        }
+                        MSG_CLEAR_FAULTS => {
+                            if header.flags & (FLAG_IS_ACK | FLAG_IS_NACK) != 0 {
+                                record_link_activity();
+                                handle_clear_faults_ack(&header);
+                            } else {
+                                rate_limited_proto_warn(
+                                    "unexpected CLEAR_FAULTS frame (not ack/nack)",
+                                    Some(frame.as_slice()),
+                                );
+                            }
+                        }
+                        MSG_RESET_COUNTERS => {
+                            if header.flags & (FLAG_IS_ACK | FLAG_IS_NACK) != 0 {
+                                record_link_activity();
+                                handle_reset_counters_ack(&header);
+                            } else {
+                                rate_limited_proto_warn(
+                                    "unexpected RESET_COUNTERS frame (not ack/nack)",
+                                    Some(frame.as_slice()),
+                                );
+                            }
+                        }
                         MSG_CAL_MODE => match decode_cal_mode_frame(&frame) {
                             Ok((hdr, mode)) => {
                                 record_link_activity();
@@ -8012,6 +8941,106 @@ fn handle_pd_sink_request_ack(header: &FrameHeader) {
     }
 }
 
+/// Whether the analog side is running the protection config saved on this
+/// board.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub(crate) enum ProtectionSync {
+    Pending = 0,
+    Applied = 1,
+    Rejected = 2,
+}
+
+#[cfg_attr(not(feature = "net_http"), allow(dead_code))]
+impl ProtectionSync {
+    pub(crate) fn load() -> Self {
+        match PROTECTION_SYNC.load(Ordering::Relaxed) {
+            1 => Self::Applied,
+            2 => Self::Rejected,
+            _ => Self::Pending,
+        }
+    }
+
+    pub(crate) const fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Applied => "applied",
+            Self::Rejected => "rejected",
+        }
+    }
+}
+
+/// Queue a (re-)send of the saved protection config to the analog side.
+#[cfg_attr(not(feature = "net_http"), allow(dead_code))]
+pub(crate) fn request_protection_sync() {
+    PROTECTION_SYNC.store(ProtectionSync::Pending as u8, Ordering::Relaxed);
+    PROTECTION_FORCE_SEND.store(true, Ordering::Release);
+}
+
+fn handle_protection_config_ack(header: &FrameHeader) {
+    PROTECTION_LAST_ACK_SEQ.store(header.seq, Ordering::Relaxed);
+    PROTECTION_LAST_ACK_FLAGS.store(header.flags, Ordering::Relaxed);
+    let total = PROTECTION_ACK_TOTAL.fetch_add(1, Ordering::Relaxed) + 1;
+    if header.flags & FLAG_IS_NACK != 0 {
+        warn!(
+            "protection_config NACK received: seq={} (ack_total={})",
+            header.seq, total
+        );
+    } else {
+        info!(
+            "protection_config ACK received: seq={} (ack_total={})",
+            header.seq, total
+        );
+    }
+}
+
+/// Queue a ClearFaults request for the analog side and silence latched
+/// prompt-tone alarms. Requests issued before the previous one is sent are
+/// merged.
+pub(crate) fn request_clear_faults(mask: u32) {
+    CLEAR_FAULTS_REQUEST_MASK.fetch_or(mask & FAULT_POLICY_ALL, Ordering::AcqRel);
+    prompt_tone::silence_alarms();
+}
+
+fn handle_clear_faults_ack(header: &FrameHeader) {
+    CLEAR_FAULTS_LAST_ACK_SEQ.store(header.seq, Ordering::Relaxed);
+    let total = CLEAR_FAULTS_ACK_TOTAL.fetch_add(1, Ordering::Relaxed) + 1;
+    if header.flags & FLAG_IS_NACK != 0 {
+        warn!(
+            "clear_faults NACK received: seq={} (ack_total={})",
+            header.seq, total
+        );
+    } else {
+        info!(
+            "clear_faults ACK received: seq={} (ack_total={})",
+            header.seq, total
+        );
+    }
+}
+
+/// Queue a ResetCounters request (`COUNTER_*` selectors) for the analog
+/// side. Requests issued before the previous one is sent are merged.
+#[cfg_attr(not(feature = "net_http"), allow(dead_code))]
+pub(crate) fn request_counters_reset(mask: u8) {
+    RESET_COUNTERS_REQUEST_MASK.fetch_or(mask & COUNTER_ALL, Ordering::AcqRel);
+}
+
+fn handle_reset_counters_ack(header: &FrameHeader) {
+    RESET_COUNTERS_LAST_ACK_SEQ.store(header.seq, Ordering::Relaxed);
+    let total = RESET_COUNTERS_ACK_TOTAL.fetch_add(1, Ordering::Relaxed) + 1;
+    if header.flags & FLAG_IS_NACK != 0 {
+        warn!(
+            "reset_counters NACK received: seq={} (ack_total={})",
+            header.seq, total
+        );
+    } else {
+        info!(
+            "reset_counters ACK received: seq={} (ack_total={})",
+            header.seq, total
+        );
+    }
+}
+
 fn handle_soft_reset_frame(header: &FrameHeader, reset: &SoftReset) {
     if header.flags & FLAG_IS_ACK != 0 {
         SOFT_RESET_LAST_ACK_SEQ.store(header.seq, Ordering::Relaxed);
@@ -8124,43 +9153,45 @@ async fn main(spawner: Spawner) {
     let i2c0_bus = i2c0::init(i2c0);
     let eeprom = EEPROM.init(Mutex::new(eeprom::SharedM24c64::new(i2c0_bus)));
 
-    // Load calibration profile from EEPROM; if invalid, fall back to firmware defaults.
-    let (initial_profile, calibration_persistence_status) = {
+    // Count this boot against an unconfirmed OTA image before anything else
+    // runs long enough to crash; an image that never confirms is rolled back.
+    ota::init(peripherals.FLASH).await;
+    ota::boot_check(eeprom).await;
+
+    // Load the calibration slot directory, then the active slot's profile; if
+    // invalid, fall back to firmware defaults. EEPROMs written before slots
+    // existed have no directory and run from slot 0.
+    let (initial_profile, calibration_persistence_status, initial_slots) = {
         let mut guard = eeprom.lock().await;
-        match guard.read_profile_blob().await {
-            Ok(blob) if blob.iter().all(|byte| *byte == 0xFF) => {
-                info!("EEPROM calibration profile is cleared; using factory-default");
-                (
-                    ActiveProfile::factory_default(calfmt::DIGITAL_HW_REV),
-                    CalibrationPersistenceStatus::FactoryDefault,
-                )
+        let dir = match guard.read_slot_dir_blob().await {
+            Ok(bytes) => calfmt::slots::SlotDirectory::decode(&bytes),
+            Err(err) => {
+                warn!("EEPROM slot directory read failed (err={:?})", err);
+                None
             }
-            Ok(blob) => match calfmt::deserialize_profile(&blob, calfmt::DIGITAL_HW_REV) {
-                Ok(profile) => {
-                    info!(
-                        "EEPROM calibration profile loaded (fmt_version={}, hw_rev={})",
-                        profile.fmt_version, profile.hw_rev
-                    );
-                    (profile, CalibrationPersistenceStatus::UserProfileLoaded)
-                }
-                Err(err) => {
-                    let status = CalibrationPersistenceStatus::from_load_error(err);
+        };
+        let slot = dir.as_ref().map_or(0, |dir| dir.active_slot());
+        let (profile, status, crc) = load_calibration_slot(&mut guard, slot).await;
+        match dir {
+            Some(dir) => {
+                let entry = &dir.slots[slot];
+                if entry.used && crc.is_some_and(|crc| crc != entry.crc32) {
                     warn!(
-                        "EEPROM calibration profile invalid; using factory-default (err={})",
-                        status.as_str()
+                        "calibration slot {} CRC does not match directory; using factory-default",
+                        slot
                     );
                     (
                         ActiveProfile::factory_default(calfmt::DIGITAL_HW_REV),
-                        status,
+                        CalibrationPersistenceStatus::CrcMismatch,
+                        dir,
                     )
+                } else {
+                    (profile, status, dir)
                 }
-            },
-            Err(err) => {
-                warn!("EEPROM read failed; using factory-default (err={:?})", err);
-                (
-                    ActiveProfile::factory_default(calfmt::DIGITAL_HW_REV),
-                    CalibrationPersistenceStatus::ReadFailed,
-                )
+            }
+            None => {
+                info!("calibration slot directory missing; using slot 0");
+                (profile, status, calfmt::slots::SlotDirectory::legacy(crc))
             }
         }
     };
@@ -8224,13 +9255,89 @@ async fn main(spawner: Spawner) {
             },
             Err(err) => {
                 warn!(
-                    "EEPROM PD config read failed; using defaults (err={:?})",
+                    "EEPROM PD config read failed; using defaults (err={:?})",
+                    err
+                );
+                (control::PdConfig::default(), false)
+            }
+        }
+    };
+
+    // Load protection thresholds; an erased/invalid blob keeps the analog
+    // firmware defaults (which match `ProtectionConfig::DEFAULT`).
+    let initial_protection = {
+        let mut guard = eeprom.lock().await;
+        match guard.read_protection_blob().await {
+            Ok(blob) => match control::decode_protection_blob(&blob) {
+                Ok(cfg) => {
+                    info!(
+                        "EEPROM protection config loaded (oc_ch={}mA ov={}mV mcu_temp={}mC sink_temp={}mC i_share={}mA)",
+                        cfg.oc_limit_ch_ma,
+                        cfg.ov_limit_mv,
+                        cfg.mcu_temp_limit_mc,
+                        cfg.sink_temp_limit_mc,
+                        cfg.i_share_threshold_ma
+                    );
+                    cfg
+                }
+                Err(err) => {
+                    let kind = match err {
+                        control::ProtectionBlobError::InvalidMagic => "magic",
+                        control::ProtectionBlobError::UnsupportedVersion(_) => "version",
+                        control::ProtectionBlobError::CrcMismatch { .. } => "crc32",
+                        control::ProtectionBlobError::OutOfRange(field) => field,
+                    };
+                    warn!(
+                        "EEPROM protection config invalid; using defaults (err={})",
+                        kind
+                    );
+                    ProtectionConfig::DEFAULT
+                }
+            },
+            Err(err) => {
+                warn!(
+                    "EEPROM protection config read failed; using defaults (err={:?})",
+                    err
+                );
+                ProtectionConfig::DEFAULT
+            }
+        }
+    };
+
+    // Thermal model parameters; an erased/invalid blob keeps the built-in model.
+    {
+        let mut guard = eeprom.lock().await;
+        match guard.read_thermal_blob().await {
+            Ok(blob) => match control::decode_thermal_blob(&blob) {
+                Ok(cfg) => {
+                    info!(
+                        "EEPROM thermal model loaded (rth={}mC/W tau={}s margin={}mC min_derate={}%)",
+                        cfg.rth_mc_per_w, cfg.tau_s, cfg.margin_mc, cfg.min_derate_pct
+                    );
+                    thermal::set_config(cfg);
+                }
+                Err(control::ThermalBlobError::InvalidMagic) => {}
+                Err(err) => {
+                    let kind = match err {
+                        control::ThermalBlobError::InvalidMagic => "magic",
+                        control::ThermalBlobError::UnsupportedVersion(_) => "version",
+                        control::ThermalBlobError::CrcMismatch { .. } => "crc32",
+                        control::ThermalBlobError::OutOfRange(field) => field,
+                    };
+                    warn!(
+                        "EEPROM thermal model invalid; using defaults (err={})",
+                        kind
+                    );
+                }
+            },
+            Err(err) => {
+                warn!(
+                    "EEPROM thermal model read failed; using defaults (err={:?})",
                     err
                 );
-                (control::PdConfig::default(), false)
             }
         }
-    };
+    }
 
     // SPI2 provides the high-speed channel for the TFT.
     let spi_peripheral = peripherals.SPI2;
@@ -8406,12 +9513,14 @@ async fn main(spawner: Spawner) {
     let calibration = CALIBRATION.init(Mutex::new(CalibrationState::new(
         initial_profile,
         calibration_persistence_status,
+        initial_slots,
     )));
-    let control = CONTROL.init(Mutex::new(ControlState::new(
-        initial_presets,
-        initial_pd,
-        initial_allow_extended_voltage,
-    )));
+    let control = CONTROL.init(Mutex::new({
+        let mut state =
+            ControlState::new(initial_presets, initial_pd, initial_allow_extended_voltage);
+        state.protection = initial_protection;
+        state
+    }));
     let usb_serial = UsbSerialJtag::new(peripherals.USB_DEVICE).into_async();
     let (usb_rx, usb_tx) = usb_serial.split();
     CONTROL_REV.store(1, Ordering::Relaxed);
@@ -8483,18 +9592,7 @@ async fn main(spawner: Spawner) {
     // NOTE: esp-hal 默认的 RxConfig 在大多数场景下更稳定：
     //   fifo_full_threshold ≈ 120, timeout ≈ 10 符号。
     // 之前我们调得太敏感（16 / 2），会放大中断压力；这里先回到接近默认的安全值。
-    let uart_cfg = UartConfig::default()
-        // Match analog MCU; 230400 baud is sufficient for 30 Hz FAST_STATUS traffic with SLIP overhead,
-        // and keeps headroom if we later move the sender back towards 60 Hz.
-        .with_baudrate(UART_BAUD)
-        .with_data_bits(DataBits::_8)
-        .with_parity(Parity::None)
-        .with_stop_bits(StopBits::_1)
-        .with_rx(
-            RxConfig::default()
-                .with_fifo_full_threshold(UART_RX_FIFO_FULL_THRESHOLD)
-                .with_timeout(UART_RX_TIMEOUT_SYMS),
-        );
+    let uart_cfg = link_uart_config(Parity::None);
 
     info!("UART1 cross-link: GPIO17=TX / GPIO18=RX");
 
@@ -8678,10 +9776,17 @@ async fn main(spawner: Spawner) {
     spawner
         .spawn(stats_task(telemetry))
         .expect("stats_task spawn");
+    spawner
+        .spawn(ota::ota_confirm_task(eeprom))
+        .expect("ota_confirm_task spawn");
     info!("spawning load guard task");
     spawner
         .spawn(load_guard_task(control))
         .expect("load_guard_task spawn");
+    info!("spawning IR measure task");
+    spawner
+        .spawn(ir_measure::ir_measure_task(control, calibration, telemetry))
+        .expect("ir_measure_task spawn");
     if let Some(uhci_tx) = uhci_tx_opt.take() {
         info!("spawning SetMode tx task (UHCI TX, active control)");
         spawner
@@ -8974,6 +10079,41 @@ async fn setmode_tx_task(
         user_initiated: bool,
     }
 
+    #[derive(Clone, Copy)]
+    struct ProtectionPending {
+        seq: u8,
+        cfg: ProtectionConfig,
+        attempts: u8, // includes initial send
+        ack_total_at_send: u32,
+        deadline_ms: u32,
+    }
+
+    #[derive(Clone, Copy)]
+    struct ClearFaultsPending {
+        seq: u8,
+        mask: u32,
+        attempts: u8, // includes initial send
+        ack_total_at_send: u32,
+        deadline_ms: u32,
+    }
+
+    let mut clear_faults_pending: Option<ClearFaultsPending> = None;
+
+    #[derive(Clone, Copy)]
+    struct ResetCountersPending {
+        seq: u8,
+        mask: u8,
+        attempts: u8, // includes initial send
+        ack_total_at_send: u32,
+        deadline_ms: u32,
+    }
+
+    let mut reset_counters_pending: Option<ResetCountersPending> = None;
+
+    let mut protection_pending: Option<ProtectionPending> = None;
+    let mut protection_last_sent: Option<ProtectionConfig> = None;
+    let mut protection_force_send: bool = true; // boot
+
     let mut pd_pending: Option<PdPending> = None;
     let mut pd_last_sent: Option<PdPolicyKey> = None;
     let mut pd_force_send: bool = false;
@@ -9099,6 +10239,10 @@ async fn setmode_tx_task(
     let mut last_calmissing_handshake_ms: u32 = 0;
     let task_start_ms = now_ms32();
     let mut last_boot_link_recovery_ms: u32 = task_start_ms.wrapping_sub(LINK_RECOVERY_RETRY_MS);
+    // Thermal derate last pushed to the analog side (boot sends the static v0
+    // profile, i.e. 100%).
+    let mut last_limit_derate_pct: u8 = LIMIT_PROFILE_DEFAULT.thermal_derate_pct;
+    let mut last_limit_sent_ms: u32 = task_start_ms;
 
     loop {
         yield_now().await;
@@ -9110,6 +10254,20 @@ async fn setmode_tx_task(
             send_soft_reset_one_shot(&mut uhci_tx, soft_seq, &mut raw, &mut slip, reason).await;
         }
 
+        // Analog firmware update: hand the UART to the bootloader session until
+        // it closes the bridge, then resync with whatever image runs now.
+        #[cfg(feature = "net_http")]
+        if analog_flash::bridge_requested() {
+            let bridge_seq = seq;
+            seq = seq.wrapping_add(1);
+            analog_flash::serve_bridge(&mut uhci_tx, bridge_seq, &mut raw, &mut slip).await;
+            pending = None;
+            force_send = true;
+            protection_pending = None;
+            protection_force_send = true;
+            continue;
+        }
+
         // Handle low-frequency calibration UART commands from the HTTP API.
         #[cfg(feature = "net_http")]
         if let Some(cmd) = crate::dequeue_cal_uart() {
@@ -9197,6 +10355,9 @@ async fn setmode_tx_task(
                 reason.as_str(),
             )
             .await;
+            last_limit_derate_pct = thermal::derate_pct();
+            last_limit_sent_ms = now;
+            protection_force_send = true;
             force_send = true;
         }
 
@@ -9214,6 +10375,8 @@ async fn setmode_tx_task(
                 "link-recover",
             )
             .await;
+            // The analog side may have rebooted with its default (100%) limits.
+            last_limit_derate_pct = LIMIT_PROFILE_DEFAULT.thermal_derate_pct;
             force_send = true;
         } else if !link_up_now && prev_link_up {
             prev_link_up = false;
@@ -9326,17 +10489,50 @@ async fn setmode_tx_task(
             calmissing_since_ms = None;
         }
 
+        // Thermal derating: push an updated LimitProfile when the model changes
+        // its derate, and refresh it periodically while derating is active since
+        // LimitProfile is fire-and-forget (no ACK).
+        let derate_pct = thermal::derate_pct();
+        let since_limit_ms = now.wrapping_sub(last_limit_sent_ms);
+        if LINK_UP.load(Ordering::Relaxed)
+            && pending.is_none()
+            && pd_pending.is_none()
+            && ((derate_pct != last_limit_derate_pct
+                && since_limit_ms >= THERMAL_LIMIT_PUSH_MIN_INTERVAL_MS)
+                || (derate_pct < 100 && since_limit_ms >= THERMAL_LIMIT_REFRESH_MS))
+        {
+            let limit_profile = thermal::limit_profile();
+            let limit_seq = seq;
+            seq = seq.wrapping_add(1);
+            if send_limit_profile_frame(
+                &mut uhci_tx,
+                limit_seq,
+                &limit_profile,
+                &mut raw,
+                &mut slip,
+                "thermal",
+            )
+            .await
+            {
+                last_limit_derate_pct = limit_profile.thermal_derate_pct;
+            }
+            last_limit_sent_ms = now;
+        }
+
         let link_up_now = LINK_UP.load(Ordering::Relaxed);
         if link_up_now && !prev_pd_link_up {
             prev_pd_link_up = true;
             start_pd_extended_voltage_retry_window(now);
             pd_force_send = true;
+            // The analog side boots with its compiled-in thresholds.
+            protection_force_send = true;
+            PROTECTION_SYNC.store(ProtectionSync::Pending as u8, Ordering::Relaxed);
         } else if !link_up_now && prev_pd_link_up {
             prev_pd_link_up = false;
         }
 
         let cal_mode = { calibration.lock().await.cal_mode };
-        let (rev_now, desired_cmd, mut pd_cfg, allow_extended_voltage) = {
+        let (rev_now, desired_cmd, mut pd_cfg, allow_extended_voltage, protection_cfg) = {
             let guard = control.lock().await;
             let effective = guard.effective_output_command(cal_mode);
             let p = effective.preset;
@@ -9365,6 +10561,7 @@ async fn setmode_tx_task(
                 sanitize_setmode(cmd),
                 control::PdConfig::effective(guard.pd_saved, guard.allow_extended_voltage),
                 guard.allow_extended_voltage,
+                guard.protection,
             )
         };
         if pd_cfg.target_mv < control::PdConfig::MIN_AUGMENTED_TARGET_MV
@@ -9556,6 +10753,194 @@ async fn setmode_tx_task(
             }
         }
 
+        // Protection config: ACK / NACK / timeout handling, then (re-)send when
+        // forced (boot, link edge, API change) or when the saved config differs
+        // from what the analog side last acknowledged.
+        if PROTECTION_FORCE_SEND.swap(false, Ordering::AcqRel) {
+            protection_force_send = true;
+        }
+        if let Some(p) = protection_pending {
+            let ack_total = PROTECTION_ACK_TOTAL.load(Ordering::Relaxed);
+            let ack_seq = PROTECTION_LAST_ACK_SEQ.load(Ordering::Relaxed);
+            if ack_total != p.ack_total_at_send && ack_seq == p.seq {
+                let flags = PROTECTION_LAST_ACK_FLAGS.load(Ordering::Relaxed);
+                let sync = if flags & FLAG_IS_NACK != 0 {
+                    ProtectionSync::Rejected
+                } else {
+                    ProtectionSync::Applied
+                };
+                if p.cfg == protection_cfg {
+                    PROTECTION_SYNC.store(sync as u8, Ordering::Relaxed);
+                }
+                if sync == ProtectionSync::Applied {
+                    thermal::set_sink_trip_mc(p.cfg.sink_temp_limit_mc);
+                }
+                protection_last_sent = Some(p.cfg);
+                protection_pending = None;
+            } else if p.cfg != protection_cfg {
+                protection_pending = None;
+            } else if now >= p.deadline_ms {
+                if p.attempts >= PROTECTION_MAX_ATTEMPTS {
+                    warn!(
+                        "protection_config ack timeout after {} attempts (seq={}); retrying on next link edge",
+                        p.attempts, p.seq
+                    );
+                    // Do not spin on a silent peer; the next link-up edge or
+                    // recovery handshake forces another round.
+                    protection_last_sent = Some(p.cfg);
+                    protection_pending = None;
+                } else {
+                    let seq_now = seq;
+                    seq = seq.wrapping_add(1);
+                    let ack_baseline = PROTECTION_ACK_TOTAL.load(Ordering::Relaxed);
+                    send_protection_config_frame(
+                        &mut uhci_tx,
+                        seq_now,
+                        &p.cfg,
+                        &mut raw,
+                        &mut slip,
+                    )
+                    .await;
+                    protection_pending = Some(ProtectionPending {
+                        seq: seq_now,
+                        attempts: p.attempts.saturating_add(1),
+                        ack_total_at_send: ack_baseline,
+                        deadline_ms: now.saturating_add(PROTECTION_ACK_TIMEOUT_MS),
+                        ..p
+                    });
+                }
+            }
+        }
+        if LINK_UP.load(Ordering::Relaxed)
+            && protection_pending.is_none()
+            && (protection_force_send || protection_last_sent != Some(protection_cfg))
+        {
+            let seq_now = seq;
+            seq = seq.wrapping_add(1);
+            let ack_baseline = PROTECTION_ACK_TOTAL.load(Ordering::Relaxed);
+            send_protection_config_frame(
+                &mut uhci_tx,
+                seq_now,
+                &protection_cfg,
+                &mut raw,
+                &mut slip,
+            )
+            .await;
+            protection_force_send = false;
+            protection_pending = Some(ProtectionPending {
+                seq: seq_now,
+                cfg: protection_cfg,
+                attempts: 1,
+                ack_total_at_send: ack_baseline,
+                deadline_ms: now.saturating_add(PROTECTION_ACK_TIMEOUT_MS),
+            });
+        }
+
+        // ClearFaults: one request in flight; later requests merge into
+        // CLEAR_FAULTS_REQUEST_MASK and go out once it is ACKed or abandoned.
+        if let Some(p) = clear_faults_pending {
+            let ack_total = CLEAR_FAULTS_ACK_TOTAL.load(Ordering::Relaxed);
+            let ack_seq = CLEAR_FAULTS_LAST_ACK_SEQ.load(Ordering::Relaxed);
+            if ack_total != p.ack_total_at_send && ack_seq == p.seq {
+                clear_faults_pending = None;
+            } else if now >= p.deadline_ms {
+                if p.attempts >= CLEAR_FAULTS_MAX_ATTEMPTS || !LINK_UP.load(Ordering::Relaxed) {
+                    warn!(
+                        "clear_faults ack timeout after {} attempts (seq={} mask=0x{:08x}); dropping",
+                        p.attempts, p.seq, p.mask
+                    );
+                    clear_faults_pending = None;
+                } else {
+                    let seq_now = seq;
+                    seq = seq.wrapping_add(1);
+                    let ack_baseline = CLEAR_FAULTS_ACK_TOTAL.load(Ordering::Relaxed);
+                    send_clear_faults_frame(&mut uhci_tx, seq_now, p.mask, &mut raw, &mut slip)
+                        .await;
+                    clear_faults_pending = Some(ClearFaultsPending {
+                        seq: seq_now,
+                        attempts: p.attempts.saturating_add(1),
+                        ack_total_at_send: ack_baseline,
+                        deadline_ms: now.saturating_add(CLEAR_FAULTS_ACK_TIMEOUT_MS),
+                        ..p
+                    });
+                }
+            }
+        }
+        if clear_faults_pending.is_none() {
+            let mask = CLEAR_FAULTS_REQUEST_MASK.swap(0, Ordering::AcqRel);
+            if mask != 0 && !LINK_UP.load(Ordering::Relaxed) {
+                // Never replay a stale clear after reconnecting: the operator
+                // has not seen whatever latched while the link was down.
+                warn!(
+                    "clear_faults dropped while link is down (mask=0x{:08x})",
+                    mask
+                );
+            } else if mask != 0 {
+                let seq_now = seq;
+                seq = seq.wrapping_add(1);
+                let ack_baseline = CLEAR_FAULTS_ACK_TOTAL.load(Ordering::Relaxed);
+                send_clear_faults_frame(&mut uhci_tx, seq_now, mask, &mut raw, &mut slip).await;
+                clear_faults_pending = Some(ClearFaultsPending {
+                    seq: seq_now,
+                    mask,
+                    attempts: 1,
+                    ack_total_at_send: ack_baseline,
+                    deadline_ms: now.saturating_add(CLEAR_FAULTS_ACK_TIMEOUT_MS),
+                });
+            }
+        }
+
+        // ResetCounters: same single-in-flight scheme as ClearFaults.
+        if let Some(p) = reset_counters_pending {
+            let ack_total = RESET_COUNTERS_ACK_TOTAL.load(Ordering::Relaxed);
+            let ack_seq = RESET_COUNTERS_LAST_ACK_SEQ.load(Ordering::Relaxed);
+            if ack_total != p.ack_total_at_send && ack_seq == p.seq {
+                reset_counters_pending = None;
+            } else if now >= p.deadline_ms {
+                if p.attempts >= RESET_COUNTERS_MAX_ATTEMPTS || !LINK_UP.load(Ordering::Relaxed) {
+                    warn!(
+                        "reset_counters ack timeout after {} attempts (seq={} mask=0x{:02x}); dropping",
+                        p.attempts, p.seq, p.mask
+                    );
+                    reset_counters_pending = None;
+                } else {
+                    let seq_now = seq;
+                    seq = seq.wrapping_add(1);
+                    let ack_baseline = RESET_COUNTERS_ACK_TOTAL.load(Ordering::Relaxed);
+                    send_reset_counters_frame(&mut uhci_tx, seq_now, p.mask, &mut raw, &mut slip)
+                        .await;
+                    reset_counters_pending = Some(ResetCountersPending {
+                        seq: seq_now,
+                        attempts: p.attempts.saturating_add(1),
+                        ack_total_at_send: ack_baseline,
+                        deadline_ms: now.saturating_add(RESET_COUNTERS_ACK_TIMEOUT_MS),
+                        ..p
+                    });
+                }
+            }
+        }
+        if reset_counters_pending.is_none() {
+            let mask = RESET_COUNTERS_REQUEST_MASK.swap(0, Ordering::AcqRel);
+            if mask != 0 && !LINK_UP.load(Ordering::Relaxed) {
+                warn!(
+                    "reset_counters dropped while link is down (mask=0x{:02x})",
+                    mask
+                );
+            } else if mask != 0 {
+                let seq_now = seq;
+                seq = seq.wrapping_add(1);
+                let ack_baseline = RESET_COUNTERS_ACK_TOTAL.load(Ordering::Relaxed);
+                send_reset_counters_frame(&mut uhci_tx, seq_now, mask, &mut raw, &mut slip).await;
+                reset_counters_pending = Some(ResetCountersPending {
+                    seq: seq_now,
+                    mask,
+                    attempts: 1,
+                    ack_total_at_send: ack_baseline,
+                    deadline_ms: now.saturating_add(RESET_COUNTERS_ACK_TIMEOUT_MS),
+                });
+            }
+        }
+
         // ACK arrival check for the current pending seq.
         let ack_hit = if let Some(p) = pending.as_ref() {
             let ack_total = SETMODE_ACK_TOTAL.load(Ordering::Relaxed);
@@ -9996,6 +11381,154 @@ async fn send_pd_sink_request_frame(
     }
 }
 
+async fn send_protection_config_frame(
+    uhci_tx: &mut uhci::UhciTx<'static, Async>,
+    seq: u8,
+    cfg: &ProtectionConfig,
+    raw: &mut [u8; 64],
+    slip: &mut [u8; 192],
+) -> bool {
+    let frame_len = match encode_protection_config_frame(seq, cfg, raw) {
+        Ok(len) => len,
+        Err(err) => {
+            warn!("protection_config: encode error: {:?}", err);
+            return false;
+        }
+    };
+
+    let slip_len = match slip_encode(&raw[..frame_len], slip) {
+        Ok(len) => len,
+        Err(err) => {
+            warn!("protection_config: slip_encode error: {:?}", err);
+            return false;
+        }
+    };
+
+    match uhci_tx.uart_tx.write_async(&slip[..slip_len]).await {
+        Ok(written) if written == slip_len => {
+            let _ = uhci_tx.uart_tx.flush_async().await;
+            info!(
+                "protection_config sent (msg=0x{:02x}): seq={} oc_ch={}mA ov={}mV mcu_temp={}mC sink_temp={}mC i_share={}mA share_policy={}",
+                MSG_PROTECTION_CONFIG,
+                seq,
+                cfg.oc_limit_ch_ma,
+                cfg.ov_limit_mv,
+                cfg.mcu_temp_limit_mc,
+                cfg.sink_temp_limit_mc,
+                cfg.i_share_threshold_ma,
+                cfg.share_policy
+            );
+            true
+        }
+        Ok(written) => {
+            warn!(
+                "protection_config short write {} < {} (seq={})",
+                written, slip_len, seq
+            );
+            false
+        }
+        Err(err) => {
+            warn!(
+                "protection_config uart write error for seq={}: {:?}",
+                seq, err
+            );
+            false
+        }
+    }
+}
+
+async fn send_clear_faults_frame(
+    uhci_tx: &mut uhci::UhciTx<'static, Async>,
+    seq: u8,
+    mask: u32,
+    raw: &mut [u8; 64],
+    slip: &mut [u8; 192],
+) -> bool {
+    let frame_len = match encode_clear_faults_frame(seq, &ClearFaults { mask }, raw) {
+        Ok(len) => len,
+        Err(err) => {
+            warn!("clear_faults: encode error: {:?}", err);
+            return false;
+        }
+    };
+
+    let slip_len = match slip_encode(&raw[..frame_len], slip) {
+        Ok(len) => len,
+        Err(err) => {
+            warn!("clear_faults: slip_encode error: {:?}", err);
+            return false;
+        }
+    };
+
+    match uhci_tx.uart_tx.write_async(&slip[..slip_len]).await {
+        Ok(written) if written == slip_len => {
+            let _ = uhci_tx.uart_tx.flush_async().await;
+            info!(
+                "clear_faults sent (msg=0x{:02x}): seq={} mask=0x{:08x}",
+                MSG_CLEAR_FAULTS, seq, mask
+            );
+            true
+        }
+        Ok(written) => {
+            warn!(
+                "clear_faults short write {} < {} (seq={})",
+                written, slip_len, seq
+            );
+            false
+        }
+        Err(err) => {
+            warn!("clear_faults uart write error for seq={}: {:?}", seq, err);
+            false
+        }
+    }
+}
+
+async fn send_reset_counters_frame(
+    uhci_tx: &mut uhci::UhciTx<'static, Async>,
+    seq: u8,
+    mask: u8,
+    raw: &mut [u8; 64],
+    slip: &mut [u8; 192],
+) -> bool {
+    let frame_len = match encode_reset_counters_frame(seq, &ResetCounters { mask }, raw) {
+        Ok(len) => len,
+        Err(err) => {
+            warn!("reset_counters: encode error: {:?}", err);
+            return false;
+        }
+    };
+
+    let slip_len = match slip_encode(&raw[..frame_len], slip) {
+        Ok(len) => len,
+        Err(err) => {
+            warn!("reset_counters: slip_encode error: {:?}", err);
+            return false;
+        }
+    };
+
+    match uhci_tx.uart_tx.write_async(&slip[..slip_len]).await {
+        Ok(written) if written == slip_len => {
+            let _ = uhci_tx.uart_tx.flush_async().await;
+            info!(
+                "reset_counters sent (msg=0x{:02x}): seq={} mask=0x{:02x}",
+                MSG_RESET_COUNTERS, seq, mask
+            );
+            true
+        }
+        Ok(written) => {
+            warn!(
+                "reset_counters short write {} < {} (seq={})",
+                written, slip_len, seq
+            );
+            false
+        }
+        Err(err) => {
+            warn!("reset_counters uart write error for seq={}: {:?}", seq, err);
+            false
+        }
+    }
+}
+
 async fn send_cal_mode_frame(
     uhci_tx: &mut uhci::UhciTx<'static, Async>,
     seq: u8,
@@ -10066,7 +11599,13 @@ async fn send_calibration_curve(
     }
 
     let points = profile.points_for(kind);
-    let chunks = calfmt::encode_calwrite_chunks(profile.fmt_version, profile.hw_rev, kind, points);
+    let chunks = calfmt::encode_calwrite_chunks(
+        profile.fmt_version,
+        profile.hw_rev,
+        kind,
+        points,
+        profile.temp_comp_for(kind),
+    );
 
     info!(
         "{}: sending CalWrite curve kind={} points={} chunks={}",
@@ -10198,21 +11737,22 @@ async fn send_set_enable_true_frame(
     }
 }
 
-async fn send_limit_profile_default_frame(
+async fn send_limit_profile_frame(
     uhci_tx: &mut uhci::UhciTx<'static, Async>,
     seq: u8,
+    profile: &LimitProfile,
     raw: &mut [u8; 64],
     slip: &mut [u8; 192],
     ctx: &str,
 ) -> bool {
-    match encode_limit_profile_frame(seq, &LIMIT_PROFILE_DEFAULT, raw) {
+    match encode_limit_profile_frame(seq, profile, raw) {
         Ok(frame_len) => match slip_encode(&raw[..frame_len], slip) {
             Ok(slip_len) => match uhci_tx.uart_tx.write_async(&slip[..slip_len]).await {
                 Ok(written) if written == slip_len => {
                     let _ = uhci_tx.uart_tx.flush_async().await;
                     info!(
-                        "{}: LimitProfile v0 sent seq={} len={} slip_len={}",
-                        ctx, seq, frame_len, slip_len
+                        "{}: LimitProfile v0 sent seq={} derate={}% len={} slip_len={}",
+                        ctx, seq, profile.thermal_derate_pct, frame_len, slip_len
                     );
                     true
                 }
@@ -10272,7 +11812,8 @@ async fn run_link_recovery_handshake(
 
     let limit_seq = *seq;
     *seq = (*seq).wrapping_add(1);
-    let _ = send_limit_profile_default_frame(uhci_tx, limit_seq, raw, slip, ctx).await;
+    let limit_profile = thermal::limit_profile();
+    let _ = send_limit_profile_frame(uhci_tx, limit_seq, &limit_profile, raw, slip, ctx).await;
 }
 
 async fn send_soft_reset_one_shot(
@@ -0,0 +1,544 @@
+//! MQTT 3.1.1 telemetry publisher and command subscriber.
+//!
+//! Packet encoding lives in `loadlynx-mqtt`; this module owns the broker
+//! connection. Broker settings come from the EEPROM MQTT blob (see
+//! `GET/POST/DELETE /api/v1/mqtt`). Topics hang off the configured prefix:
+//!
+//! - `<prefix>/online` (retained): `online`, or `offline` via the last will.
+//! - `<prefix>/status`: the `/api/v1/status` snapshot every publish interval.
+//! - `<prefix>/faults` (retained): fault flags, republished on change.
+//! - `<prefix>/pd` (retained): the `/api/v1/pd` view, republished on change.
+//! - `<prefix>/cmd/<name>` (subscribed): JSON bodies for `cc`, `control`,
+//!   `preset` and `preset_apply`, executed by the HTTP handlers; the handler's
+//!   response body is published to `<prefix>/result/<name>`. Only subscribed
+//!   when `commands_enabled` is set, which takes an admin token (`POST
+//!   /api/v1/mqtt`); anyone who can publish to the broker can drive the load.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use alloc::{format, string::String, vec, vec::Vec};
+use defmt::*;
+use embassy_futures::select::{Either, select};
+use embassy_net::{IpAddress, Ipv4Address, Stack, dns::DnsQueryType, tcp::TcpSocket};
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex};
+use embassy_time::{Duration, Instant, Timer};
+use heapless::String as HString;
+use loadlynx_mqtt::{self as mqtt, Connect, Packet, Will};
+
+use crate::{CalibrationMutex, ControlMutex, EepromMutex, TelemetryMutex, eeprom, net};
+
+pub const MQTT_DEFAULT_PORT: u16 = 1883;
+pub const MQTT_DEFAULT_PUBLISH_INTERVAL_MS: u16 = 1_000;
+const KEEP_ALIVE_S: u16 = 30;
+/// Send PINGREQ when nothing was written for this long.
+const PING_AFTER: Duration = Duration::from_secs(KEEP_ALIVE_S as u64 / 2);
+const PING_TIMEOUT: Duration = Duration::from_secs(10);
+const CONNACK_TIMEOUT: Duration = Duration::from_secs(10);
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+/// The PD view is comparatively expensive to render; poll it less often.
+const PD_POLL_INTERVAL: Duration = Duration::from_secs(5);
+const CONFIG_POLL_INTERVAL: Duration = Duration::from_secs(2);
+const SOCKET_RX_LEN: usize = 1024;
+const SOCKET_TX_LEN: usize = 2048;
+/// Largest incoming packet (command publish) accepted from the broker.
+const RX_PACKET_LEN: usize = 1024;
+/// Largest outgoing packet; status and PD views must fit.
+const TX_PACKET_LEN: usize = 2048;
+const SUBSCRIBE_PACKET_ID: u16 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum MqttConnectionState {
+    Disabled,
+    Connecting,
+    Connected,
+    Error,
+}
+
+impl MqttConnectionState {
+    pub(crate) const fn name(self) -> &'static str {
+        match self {
+            MqttConnectionState::Disabled => "disabled",
+            MqttConnectionState::Connecting => "connecting",
+            MqttConnectionState::Connected => "connected",
+            MqttConnectionState::Error => "error",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct MqttStatus {
+    pub state: MqttConnectionState,
+    pub last_error: Option<&'static str>,
+}
+
+static MQTT_STATUS: Mutex<CriticalSectionRawMutex, MqttStatus> = Mutex::new(MqttStatus {
+    state: MqttConnectionState::Disabled,
+    last_error: None,
+});
+static RECONFIGURE_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+pub(crate) async fn status() -> MqttStatus {
+    *MQTT_STATUS.lock().await
+}
+
+/// Drop the current broker session and reload the EEPROM settings.
+pub(crate) async fn request_reconfigure() {
+    RECONFIGURE_REQUESTED.store(true, Ordering::Relaxed);
+    let mut status = MQTT_STATUS.lock().await;
+    status.last_error = None;
+}
+
+async fn set_status(state: MqttConnectionState, last_error: Option<&'static str>) {
+    *MQTT_STATUS.lock().await = MqttStatus { state, last_error };
+}
+
+/// Owned copy of the EEPROM broker settings.
+pub(crate) struct MqttConfig {
+    pub enabled: bool,
+    pub commands_enabled: bool,
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    pub password: String,
+    pub topic_prefix: String,
+    pub publish_interval_ms: u16,
+}
+
+pub(crate) async fn read_config(eeprom: &'static EepromMutex) -> Option<MqttConfig> {
+    let blob = eeprom.lock().await.read_mqtt_blob().await.ok()?;
+    eeprom::decode_mqtt_blob(&blob).map(|parts| MqttConfig {
+        enabled: parts.enabled,
+        commands_enabled: parts.commands_enabled,
+        host: String::from(parts.host),
+        port: parts.port,
+        username: String::from(parts.username),
+        password: String::from(parts.password),
+        topic_prefix: String::from(parts.topic_prefix),
+        publish_interval_ms: parts.publish_interval_ms,
+    })
+}
+
+/// Default topic prefix, `loadlynx/<device_id>`.
+pub(crate) fn default_topic_prefix(device_id: &str) -> String {
+    format!("loadlynx/{device_id}")
+}
+
+pub(crate) struct MqttContext {
+    pub control: &'static ControlMutex,
+    pub calibration: &'static CalibrationMutex,
+    pub telemetry: &'static TelemetryMutex,
+    pub eeprom: &'static EepromMutex,
+    /// Client identifier (device_id, e.g. `loadlynx-a1b2c3`).
+    pub client_id: HString<32>,
+}
+
+#[derive(Debug, defmt::Format)]
+enum SessionError {
+    Tcp(embassy_net::tcp::Error),
+    Connect(embassy_net::tcp::ConnectError),
+    Closed,
+    Protocol(&'static str),
+}
+
+impl SessionError {
+    fn message(&self) -> &'static str {
+        match self {
+            SessionError::Tcp(_) => "connection lost",
+            SessionError::Connect(_) => "broker unreachable",
+            SessionError::Closed => "broker closed the connection",
+            SessionError::Protocol(message) => message,
+        }
+    }
+}
+
+impl From<embassy_net::tcp::Error> for SessionError {
+    fn from(err: embassy_net::tcp::Error) -> Self {
+        SessionError::Tcp(err)
+    }
+}
+
+impl From<mqtt::Error> for SessionError {
+    fn from(err: mqtt::Error) -> Self {
+        SessionError::Protocol(match err {
+            mqtt::Error::BufferTooSmall => "packet exceeds buffer",
+            mqtt::Error::TooLong => "field too long",
+            mqtt::Error::Malformed => "malformed packet from broker",
+        })
+    }
+}
+
+#[embassy_executor::task]
+pub async fn mqtt_task(stack: Stack<'static>, ctx: MqttContext) {
+    let mut rx_buf = [0u8; SOCKET_RX_LEN];
+    let mut tx_buf = [0u8; SOCKET_TX_LEN];
+
+    info!(
+        "MQTT client starting (client_id={})",
+        ctx.client_id.as_str()
+    );
+
+    loop {
+        RECONFIGURE_REQUESTED.store(false, Ordering::Relaxed);
+        let config = match read_config(ctx.eeprom).await {
+            Some(config) if config.enabled => config,
+            _ => {
+                set_status(MqttConnectionState::Disabled, None).await;
+                wait_for_reconfigure(CONFIG_POLL_INTERVAL).await;
+                continue;
+            }
+        };
+        stack.wait_config_up().await;
+        set_status(MqttConnectionState::Connecting, None).await;
+
+        let Some(address) = resolve_host(stack, &config.host).await else {
+            warn!("MQTT broker host lookup failed: {}", config.host.as_str());
+            set_status(
+                MqttConnectionState::Error,
+                Some("broker host lookup failed"),
+            )
+            .await;
+            wait_for_reconfigure(RETRY_DELAY).await;
+            continue;
+        };
+
+        let mut socket = TcpSocket::new(stack, &mut rx_buf, &mut tx_buf);
+        socket.set_timeout(Some(Duration::from_secs(KEEP_ALIVE_S as u64 * 2)));
+        let result = match socket.connect((address, config.port)).await {
+            Ok(()) => run_session(&mut socket, &ctx, &config).await,
+            Err(err) => Err(SessionError::Connect(err)),
+        };
+        socket.close();
+        let _ = socket.flush().await;
+        socket.abort();
+
+        match result {
+            Ok(()) => info!("MQTT session closed for reconfigure"),
+            Err(err) => {
+                warn!("MQTT session error: {:?}", err);
+                set_status(MqttConnectionState::Error, Some(err.message())).await;
+                wait_for_reconfigure(RETRY_DELAY).await;
+            }
+        }
+    }
+}
+
+async fn wait_for_reconfigure(timeout: Duration) {
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        if RECONFIGURE_REQUESTED.load(Ordering::Relaxed) {
+            return;
+        }
+        Timer::after(Duration::from_millis(250)).await;
+    }
+}
+
+pub(crate) async fn resolve_host(stack: Stack<'static>, host: &str) -> Option<IpAddress> {
+    if let Ok(ip) = host.parse::<Ipv4Address>() {
+        return Some(IpAddress::Ipv4(ip));
+    }
+    let addrs = stack.dns_query(host, DnsQueryType::A).await.ok()?;
+    addrs.first().copied()
+}
+
+/// Socket plus the scratch buffer used to encode outgoing packets.
+struct Session<'s, 'a> {
+    socket: &'s mut TcpSocket<'a>,
+    packet: Vec<u8>,
+    last_tx: Instant,
+}
+
+impl Session<'_, '_> {
+    async fn send(&mut self, len: usize) -> Result<(), SessionError> {
+        net::socket_write_all(self.socket, &self.packet[..len]).await?;
+        self.socket.flush().await?;
+        self.last_tx = Instant::now();
+        Ok(())
+    }
+
+    async fn publish(
+        &mut self,
+        topic: &str,
+        payload: &[u8],
+        retain: bool,
+    ) -> Result<(), SessionError> {
+        match mqtt::encode_publish(&mut self.packet, topic, payload, retain) {
+            Ok(len) => self.send(len).await,
+            // An oversized view is skipped rather than tearing down the session.
+            Err(mqtt::Error::BufferTooSmall) => {
+                warn!("MQTT publish to {} dropped: payload too large", topic);
+                Ok(())
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+async fn run_session(
+    socket: &mut TcpSocket<'_>,
+    ctx: &MqttContext,
+    config: &MqttConfig,
+) -> Result<(), SessionError> {
+    let prefix = config.topic_prefix.as_str();
+    let online_topic = format!("{prefix}/online");
+    let status_topic = format!("{prefix}/status");
+    let faults_topic = format!("{prefix}/faults");
+    let pd_topic = format!("{prefix}/pd");
+    let command_filter = format!("{prefix}/cmd/+");
+    let command_prefix = format!("{prefix}/cmd/");
+
+    let mut session = Session {
+        socket,
+        packet: vec![0u8; TX_PACKET_LEN],
+        last_tx: Instant::now(),
+    };
+    let len = mqtt::encode_connect(
+        &mut session.packet,
+        &Connect {
+            client_id: ctx.client_id.as_str(),
+            keep_alive_s: KEEP_ALIVE_S,
+            username: non_empty(&config.username),
+            password: non_empty(&config.password),
+            will: Some(Will {
+                topic: &online_topic,
+                payload: b"offline",
+                retain: true,
+            }),
+        },
+    )?;
+    session.send(len).await?;
+
+    let mut rx = vec![0u8; RX_PACKET_LEN];
+    let mut rx_len = 0usize;
+
+    // CONNACK must be the first packet from the broker.
+    let connack_deadline = Instant::now() + CONNACK_TIMEOUT;
+    loop {
+        let n = match select(
+            session.socket.read(&mut rx[rx_len..]),
+            Timer::at(connack_deadline),
+        )
+        .await
+        {
+            Either::First(read) => read?,
+            Either::Second(()) => return Err(SessionError::Protocol("no CONNACK from broker")),
+        };
+        if n == 0 {
+            return Err(SessionError::Closed);
+        }
+        rx_len += n;
+        if let Some((packet, used)) = mqtt::decode(&rx[..rx_len])? {
+            match packet {
+                Packet::ConnAck { return_code: 0, .. } => {}
+                Packet::ConnAck { return_code, .. } => {
+                    warn!(
+                        "MQTT broker refused connection: {}",
+                        mqtt::connack_reason(return_code)
+                    );
+                    return Err(SessionError::Protocol(mqtt::connack_reason(return_code)));
+                }
+                _ => return Err(SessionError::Protocol("expected CONNACK")),
+            }
+            rx.copy_within(used..rx_len, 0);
+            rx_len -= used;
+            break;
+        }
+    }
+
+    if config.commands_enabled {
+        let len =
+            mqtt::encode_subscribe(&mut session.packet, SUBSCRIBE_PACKET_ID, &command_filter, 0)?;
+        session.send(len).await?;
+    }
+    session.publish(&online_topic, b"online", true).await?;
+    info!(
+        "MQTT connected to {}:{} (prefix={})",
+        config.host.as_str(),
+        config.port,
+        prefix
+    );
+    set_status(MqttConnectionState::Connected, None).await;
+
+    let publish_interval = Duration::from_millis(config.publish_interval_ms as u64);
+    let mut next_publish = Instant::now();
+    let mut next_pd_poll = Instant::now();
+    let mut ping_sent_at: Option<Instant> = None;
+    let mut last_faults: Option<String> = None;
+    let mut last_pd: Option<String> = None;
+    let mut body = String::new();
+
+    loop {
+        if RECONFIGURE_REQUESTED.load(Ordering::Relaxed) {
+            let len = mqtt::encode_disconnect(&mut session.packet)?;
+            session.send(len).await?;
+            return Ok(());
+        }
+
+        let now = Instant::now();
+        if now >= next_publish {
+            next_publish = now + publish_interval;
+            body.clear();
+            if net::render_status_json_sse(&mut body, ctx.telemetry)
+                .await
+                .is_ok()
+            {
+                session
+                    .publish(&status_topic, body.as_bytes(), false)
+                    .await?;
+            }
+            let faults = render_faults_json(ctx.telemetry).await;
+            if last_faults.as_ref() != Some(&faults) {
+                session
+                    .publish(&faults_topic, faults.as_bytes(), true)
+                    .await?;
+                last_faults = Some(faults);
+            }
+        }
+        if now >= next_pd_poll {
+            next_pd_poll = now + PD_POLL_INTERVAL;
+            let mut pd = String::new();
+            if net::render_pd_view_json(&mut pd, ctx.control, ctx.telemetry)
+                .await
+                .is_ok()
+                && last_pd.as_ref() != Some(&pd)
+            {
+                session.publish(&pd_topic, pd.as_bytes(), true).await?;
+                last_pd = Some(pd);
+            }
+        }
+        match ping_sent_at {
+            Some(sent) if now.duration_since(sent) >= PING_TIMEOUT => {
+                return Err(SessionError::Protocol("PINGRESP timeout"));
+            }
+            None if now.duration_since(session.last_tx) >= PING_AFTER => {
+                let len = mqtt::encode_pingreq(&mut session.packet)?;
+                session.send(len).await?;
+                ping_sent_at = Some(Instant::now());
+            }
+            _ => {}
+        }
+
+        let wake_at = next_publish
+            .min(next_pd_poll)
+            .min(Instant::now() + Duration::from_millis(250));
+        let read = select(session.socket.read(&mut rx[rx_len..]), Timer::at(wake_at)).await;
+        let n = match read {
+            Either::First(read) => read?,
+            Either::Second(()) => continue,
+        };
+        if n == 0 {
+            return Err(SessionError::Closed);
+        }
+        rx_len += n;
+
+        loop {
+            let Some((packet, used)) = mqtt::decode(&rx[..rx_len])? else {
+                break;
+            };
+            match packet {
+                Packet::Publish {
+                    topic,
+                    payload,
+                    packet_id,
+                    ..
+                } => {
+                    let name = topic
+                        .strip_prefix(command_prefix.as_str())
+                        .map(String::from);
+                    let payload = core::str::from_utf8(payload).map(String::from);
+                    if let Some(packet_id) = packet_id {
+                        let len = mqtt::encode_puback(&mut session.packet, packet_id)?;
+                        session.send(len).await?;
+                    }
+                    // Brokers only deliver what we subscribed to; the check
+                    // guards against one that forwards anyway.
+                    if config.commands_enabled
+                        && let (Some(name), Ok(payload)) = (name, payload)
+                    {
+                        body.clear();
+                        execute_command(ctx, &name, &payload, &mut body).await;
+                        let result_topic = format!("{prefix}/result/{name}");
+                        session
+                            .publish(&result_topic, body.as_bytes(), false)
+                            .await?;
+                    }
+                }
+                Packet::PingResp => ping_sent_at = None,
+                Packet::SubAck { granted: 0x80, .. } => {
+                    warn!("MQTT broker rejected command subscription");
+                }
+                _ => {}
+            }
+            rx.copy_within(used..rx_len, 0);
+            rx_len -= used;
+        }
+        if rx_len == rx.len() {
+            return Err(SessionError::Protocol("incoming packet exceeds buffer"));
+        }
+    }
+}
+
+fn non_empty(value: &str) -> Option<&str> {
+    (!value.is_empty()).then_some(value)
+}
+
+/// Run one command through the matching HTTP handler; `out` receives the
+/// handler's response or error body.
+async fn execute_command(ctx: &MqttContext, name: &str, payload: &str, out: &mut String) {
+    let result = match name {
+        "cc" => {
+            net::handle_cc_update(payload, out, ctx.control, ctx.calibration, ctx.telemetry).await
+        }
+        "control" => {
+            net::handle_control_update(payload, out, ctx.control, ctx.calibration, ctx.telemetry)
+                .await
+        }
+        "preset" => net::handle_presets_update(payload, out, ctx.control, ctx.eeprom).await,
+        "preset_apply" => {
+            net::handle_presets_apply(payload, out, ctx.control, ctx.calibration, ctx.telemetry)
+                .await
+        }
+        _ => {
+            net::write_error_body(
+                out,
+                "UNSUPPORTED_OPERATION",
+                "unknown MQTT command",
+                false,
+                None,
+            );
+            Err("404 Not Found")
+        }
+    };
+    if let Err(status) = result {
+        info!("MQTT command {} failed: {}", name, status);
+    }
+}
+
+async fn render_faults_json(telemetry: &'static TelemetryMutex) -> String {
+    let status = { telemetry.lock().await.last_status };
+    let mut out = String::new();
+    let Some(status) = status else {
+        out.push_str("{\"fault_flags\":null,\"faults\":[]}");
+        return out;
+    };
+    out.push_str(&format!(
+        "{{\"fault_flags\":{},\"faults\":[",
+        status.fault_flags
+    ));
+    let mut first = true;
+    for (name, bit) in net::FAULT_POLICY_SELECTORS {
+        if status.fault_flags & bit != 0 {
+            if !first {
+                out.push(',');
+            }
+            first = false;
+            out.push('"');
+            out.push_str(name);
+            out.push('"');
+        }
+    }
+    out.push_str(&format!(
+        "],\"uv_latched\":{}}}",
+        status.state_flags & loadlynx_protocol::STATE_FLAG_UV_LATCHED != 0
+    ));
+    out
+}
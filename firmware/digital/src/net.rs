@@ -19,16 +19,25 @@ use embassy_time::{Duration, Timer};
 use esp_hal::{peripherals::WIFI, rng::Rng};
 use esp_radio::{
     Controller as RadioController, init as radio_init,
-    wifi::{self, AuthMethod, ClientConfig, ModeConfig, ScanMethod, WifiController, WifiDevice},
+    wifi::{
+        self, AccessPointConfig, AuthMethod, ClientConfig, ModeConfig, ScanConfig, ScanMethod,
+        WifiController, WifiDevice,
+    },
 };
 use heapless::{String as HString, Vec};
 use static_cell::StaticCell;
 
 use loadlynx_protocol::{
-    CalKind, FAULT_MCU_OVER_TEMP, FAULT_OVERCURRENT, FAULT_OVERVOLTAGE, FAULT_SINK_OVER_TEMP,
-    FastStatus, LimitProfile, LoadMode, PROTOCOL_VERSION, STATE_FLAG_CURRENT_LIMITED,
-    STATE_FLAG_ENABLED, STATE_FLAG_LINK_GOOD, STATE_FLAG_POWER_LIMITED, STATE_FLAG_UV_LATCHED,
-    SoftResetReason,
+    CHANNEL_WARN_CH1_STRAY, CHANNEL_WARN_CH2_STRAY, CHANNEL_WARN_IMBALANCE, COUNTER_ALL, CalKind,
+    FAULT_MCU_OVER_TEMP, FAULT_OVERCURRENT, FAULT_OVERVOLTAGE, FAULT_POLICY_ALL,
+    FAULT_POLICY_UV_LATCH, FAULT_SINK_OVER_TEMP, FastStatus, LimitProfile, LoadMode,
+    PROTECTION_I_SHARE_THRESHOLD_MA_RANGE, PROTECTION_LINK_LOSS_TIMEOUT_MS_RANGE,
+    PROTECTION_MCU_TEMP_LIMIT_MC_RANGE, PROTECTION_OC_LIMIT_CH_MA_RANGE,
+    PROTECTION_OV_LIMIT_MV_RANGE, PROTECTION_SINK_TEMP_LIMIT_MC_RANGE, PROTOCOL_VERSION,
+    ProtectionConfig, SENSE_WARN_HIGH_LEAD_DROP, SENSE_WARN_OSCILLATION, SENSE_WARN_REMOTE_OPEN,
+    SENSE_WARN_REMOTE_REVERSED, STATE_FLAG_CURRENT_LIMITED, STATE_FLAG_ENABLED,
+    STATE_FLAG_LINK_GOOD, STATE_FLAG_LINK_SAFED, STATE_FLAG_POWER_LIMITED, STATE_FLAG_SOA_LIMITED,
+    STATE_FLAG_UV_LATCHED, SoftResetReason, share_policy_from_name, share_policy_name,
 };
 
 use crate::mdns::MdnsConfig;
@@ -37,8 +46,9 @@ use crate::{
     ENCODER_VALUE, EepromMutex, FAST_STATUS_OK_COUNT, FW_VERSION, HELLO_SEEN, LAST_GOOD_FRAME_MS,
     LIMIT_PROFILE_DEFAULT, LINK_UP, LOAD_SWITCH_ENABLED, STATE_FLAG_REMOTE_ACTIVE, TARGET_I_MAX_MA,
     TARGET_I_MIN_MA, TelemetryMutex, WIFI_DNS, WIFI_GATEWAY, WIFI_HOSTNAME, WIFI_NETMASK, WIFI_PSK,
-    WIFI_SSID, WIFI_STATIC_IP, bump_control_rev, control, eeprom, enqueue_cal_uart, mdns, now_ms32,
-    timestamp_ms, ui::AnalogState,
+    WIFI_SSID, WIFI_STATIC_IP, analog_flash, auth, bump_control_rev, control, eeprom,
+    enqueue_cal_uart, ir_measure, mdns, mqtt, now_ms32, ota, provision, scpi, sntp, thermal,
+    timestamp_ms, ui::AnalogState, wall_clock, ws,
 };
 
 use loadlynx_calibration_format::{self as calfmt, CalPoint, CurveKind, ProfileSource};
@@ -53,6 +63,9 @@ pub enum WifiConnectionState {
     Idle,
     Connecting,
     Connected,
+    /// Setup access point is up (no credentials, or station mode kept
+    /// failing); see `provision.rs`.
+    Provisioning,
     Error,
 }
 
@@ -73,9 +86,34 @@ pub struct WifiState {
     pub is_static: bool,
     pub last_error: Option<WifiErrorKind>,
     pub mac: Option<[u8; 6]>,
+    /// Stored network the station is joining or connected to.
+    pub active_ssid: Option<WifiSsid>,
     pub reconfigure_requested: bool,
 }
 
+/// SSID kept inline so `WifiState` stays `Copy`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WifiSsid {
+    len: u8,
+    bytes: [u8; eeprom::WIFI_MAX_SSID_LEN],
+}
+
+impl WifiSsid {
+    fn new(ssid: &str) -> Self {
+        let len = ssid.len().min(eeprom::WIFI_MAX_SSID_LEN);
+        let mut bytes = [0u8; eeprom::WIFI_MAX_SSID_LEN];
+        bytes[..len].copy_from_slice(&ssid.as_bytes()[..len]);
+        Self {
+            len: len as u8,
+            bytes,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.bytes[..self.len as usize]).unwrap_or("")
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum WifiCredentialSource {
     Factory,
@@ -83,9 +121,28 @@ enum WifiCredentialSource {
     None,
 }
 
+impl WifiCredentialSource {
+    fn name(self) -> &'static str {
+        match self {
+            WifiCredentialSource::Factory => "factory",
+            WifiCredentialSource::User => "user",
+            WifiCredentialSource::None => "none",
+        }
+    }
+}
+
+/// One stored network; its position in the list is its priority (0 is
+/// tried first).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct WifiNetwork {
+    pub ssid: String,
+    pub psk: String,
+}
+
+pub(crate) type WifiNetworks = Vec<WifiNetwork, { eeprom::WIFI_MAX_NETWORKS }>;
+
 struct WifiCredentials {
-    ssid: Option<String>,
-    psk: Option<String>,
+    networks: WifiNetworks,
     source: WifiCredentialSource,
 }
 
@@ -98,6 +155,7 @@ impl WifiState {
             is_static: false,
             last_error: None,
             mac: None,
+            active_ssid: None,
             reconfigure_requested: false,
         }
     }
@@ -118,8 +176,10 @@ static WIFI_STATE_CELL: StaticCell<WifiStateMutex> = StaticCell::new();
 static RADIO_CONTROLLER: StaticCell<RadioController<'static>> = StaticCell::new();
 // Allow a modest number of simultaneous TCP sockets (HTTP fetches + SSE).
 // Value chosen to cover a typical browser's 4–6 parallel GETs without being
-// wasteful on RAM.
-static NET_RESOURCES: StaticCell<StackResources<10>> = StaticCell::new();
+// wasteful on RAM. Also covers the SCPI, MQTT, mDNS, SNTP, DHCP and DNS sockets.
+static NET_RESOURCES: StaticCell<StackResources<12>> = StaticCell::new();
+// Setup AP stack: DHCP + DNS + portal sockets only.
+static AP_NET_RESOURCES: StaticCell<StackResources<4>> = StaticCell::new();
 
 fn derive_device_names(mac: [u8; 6]) -> DeviceNames {
     let short_id = mdns::short_id_from_mac(mac);
@@ -272,6 +332,7 @@ pub fn spawn_wifi_and_http(
         };
 
     let wifi_device: WifiDevice<'static> = wifi_interfaces.sta;
+    let ap_device: WifiDevice<'static> = wifi_interfaces.ap;
     let wifi_mac = wifi_device.mac_address();
     let device_names = derive_device_names(wifi_mac);
 
@@ -280,9 +341,18 @@ pub fn spawn_wifi_and_http(
     let rng = Rng::new();
     let seed = (rng.random() as u64) << 32 | rng.random() as u64;
 
-    let resources = NET_RESOURCES.init(StackResources::<10>::new());
+    let resources = NET_RESOURCES.init(StackResources::<12>::new());
     let (stack, runner) = embassy_net::new(wifi_device, net_cfg, resources, seed);
 
+    let ap_cfg = NetConfig::ipv4_static(StaticConfigV4 {
+        address: Ipv4Cidr::new(provision::AP_ADDRESS, provision::AP_PREFIX_LEN),
+        gateway: None,
+        dns_servers: Vec::new(),
+    });
+    let ap_resources = AP_NET_RESOURCES.init(StackResources::<4>::new());
+    let (ap_stack, ap_runner) =
+        embassy_net::new(ap_device, ap_cfg, ap_resources, seed.rotate_left(32));
+
     info!("spawning Wi-Fi connection task");
     spawner
         .spawn(wifi_task(
@@ -292,9 +362,28 @@ pub fn spawn_wifi_and_http(
             is_static,
             wifi_mac,
             eeprom,
+            provision::ap_ssid(device_names.short_id.as_str()),
         ))
         .expect("wifi_task spawn");
 
+    info!("spawning setup AP services (DHCP, DNS, portal)");
+    spawner
+        .spawn(provision::dhcp_task(ap_stack))
+        .expect("provision dhcp_task spawn");
+    spawner
+        .spawn(provision::dns_task(ap_stack))
+        .expect("provision dns_task spawn");
+    spawner
+        .spawn(provision::portal_task(
+            ap_stack,
+            provision::PortalContext {
+                eeprom,
+                wifi_state,
+                hostname: device_names.hostname.clone(),
+            },
+        ))
+        .expect("provision portal_task spawn");
+
     info!("spawning HTTP workers (count={})", HTTP_WORKER_COUNT);
     for idx in 0..HTTP_WORKER_COUNT {
         spawner
@@ -310,6 +399,42 @@ pub fn spawn_wifi_and_http(
             .expect("http_worker spawn");
     }
 
+    info!("spawning SCPI server (port={})", scpi::SCPI_PORT);
+    spawner
+        .spawn(scpi::scpi_task(
+            stack,
+            scpi::ScpiContext {
+                control,
+                calibration,
+                telemetry,
+                eeprom,
+                serial: device_names.hostname.clone(),
+            },
+        ))
+        .expect("scpi_task spawn");
+
+    info!("spawning MQTT client");
+    spawner
+        .spawn(mqtt::mqtt_task(
+            stack,
+            mqtt::MqttContext {
+                control,
+                calibration,
+                telemetry,
+                eeprom,
+                client_id: device_names.hostname.clone(),
+            },
+        ))
+        .expect("mqtt_task spawn");
+
+    info!(
+        "spawning SNTP client (default server={})",
+        sntp::DEFAULT_NTP_SERVER
+    );
+    spawner
+        .spawn(sntp::sntp_task(stack, sntp::SntpContext { eeprom }))
+        .expect("sntp_task spawn");
+
     let mdns_cfg = MdnsConfig {
         hostname: device_names.hostname.clone(),
         hostname_fqdn: device_names.hostname_fqdn.clone(),
@@ -328,9 +453,13 @@ pub fn spawn_wifi_and_http(
     spawner
         .spawn(net_task(runner))
         .expect("net_task runner spawn");
+    spawner
+        .spawn(net_task(ap_runner))
+        .expect("net_task AP runner spawn");
 }
 
-#[embassy_executor::task]
+// One runner per interface: station and setup AP.
+#[embassy_executor::task(pool_size = 2)]
 async fn net_task(mut runner: embassy_net::Runner<'static, WifiDevice<'static>>) {
     runner.run().await;
 }
@@ -343,58 +472,54 @@ async fn wifi_task(
     is_static_ip: bool,
     mac: [u8; 6],
     eeprom: &'static EepromMutex,
+    ap_ssid: HString<32>,
 ) {
     info!(
         "Wi-Fi task starting (ssid=\"{}\", hostname={:?}, static_ip={})",
         WIFI_SSID, WIFI_HOSTNAME, is_static_ip,
     );
 
+    // Connection rounds (every stored network tried once) that failed since
+    // the last successful link.
+    let mut failures: u8 = 0;
+
     loop {
         let credentials = read_wifi_credentials(eeprom).await;
-        let source = match credentials.source {
-            WifiCredentialSource::Factory => "factory",
-            WifiCredentialSource::User => "user",
-            WifiCredentialSource::None => "none",
-        };
-        let Some(ssid) = credentials.ssid.clone() else {
-            info!("Wi-Fi credentials unavailable (source=none); waiting for EEPROM/user config");
-            if matches!(controller.is_started(), Ok(true)) {
-                let _ = controller.disconnect_async().await;
-                if let Err(err) = controller.stop_async().await {
-                    warn!(
-                        "Wi-Fi stop_async after credentials cleared error: {:?}",
-                        err
-                    );
-                }
-            }
+        let source = credentials.source.name();
+        if credentials.networks.is_empty() {
+            info!("Wi-Fi credentials unavailable (source=none); starting setup AP");
             {
                 let mut guard = state.lock().await;
-                guard.state = WifiConnectionState::Idle;
-                guard.ipv4 = None;
-                guard.gateway = None;
                 guard.last_error = None;
+                guard.active_ssid = None;
             }
-            Timer::after(Duration::from_secs(2)).await;
+            run_setup_ap(&mut controller, state, eeprom, ap_ssid.as_str(), None).await;
+            failures = 0;
             continue;
-        };
-        let Some(psk) = credentials.psk.clone() else {
-            warn!("Wi-Fi credentials missing PSK for source={}", source);
-            if matches!(controller.is_started(), Ok(true)) {
-                let _ = controller.disconnect_async().await;
-                if let Err(err) = controller.stop_async().await {
-                    warn!("Wi-Fi stop_async after missing PSK error: {:?}", err);
-                }
-            }
-            {
-                let mut guard = state.lock().await;
-                guard.state = WifiConnectionState::Idle;
-                guard.ipv4 = None;
-                guard.gateway = None;
-                guard.last_error = Some(WifiErrorKind::ConnectFailed);
-            }
-            Timer::after(Duration::from_secs(2)).await;
+        }
+        // The portal is open and `POST /save` adds a top-priority network, so
+        // it must never come up on a unit a user has provisioned: only build
+        // time (factory) credentials fall back to it. User networks are
+        // retried until they come back or are changed over USB / the API.
+        if failures >= WIFI_SETUP_AP_AFTER_FAILURES
+            && credentials.source != WifiCredentialSource::User
+        {
+            warn!(
+                "Wi-Fi failed {} times in a row; starting setup AP",
+                failures
+            );
+            state.lock().await.active_ssid = None;
+            run_setup_ap(
+                &mut controller,
+                state,
+                eeprom,
+                ap_ssid.as_str(),
+                Some(WIFI_SETUP_AP_IDLE_TIMEOUT),
+            )
+            .await;
+            failures = 0;
             continue;
-        };
+        }
         {
             let mut guard = state.lock().await;
             guard.state = WifiConnectionState::Connecting;
@@ -404,146 +529,127 @@ async fn wifi_task(
             guard.reconfigure_requested = false;
         }
 
-        let client_config = ModeConfig::Client(
-            ClientConfig::default()
-                .with_ssid(ssid.clone())
-                .with_password(psk.clone())
-                .with_auth_method(AuthMethod::WpaWpa2Personal)
-                .with_scan_method(ScanMethod::AllChannels)
-                .with_failure_retry_cnt(3),
-        );
+        let rssi = scan_wifi_networks(&mut controller, &credentials.networks).await;
+        let candidates = order_wifi_candidates(&rssi[..credentials.networks.len()]);
 
-        if matches!(controller.is_started(), Ok(true)) {
-            let _ = controller.disconnect_async().await;
-            if let Err(err) = controller.stop_async().await {
-                warn!("Wi-Fi stop_async before reconfigure error: {:?}", err);
+        let mut connected = None;
+        let mut last_error = WifiErrorKind::ConnectFailed;
+        for idx in candidates {
+            let network = &credentials.networks[idx];
+            state.lock().await.active_ssid = Some(WifiSsid::new(&network.ssid));
+            info!(
+                "Connecting to Wi-Fi SSID=\"{}\" (priority={}, source={})",
+                network.ssid.as_str(),
+                idx,
+                source
+            );
+            match connect_wifi_network(&mut controller, stack, network).await {
+                Ok(()) => {
+                    connected = Some(idx);
+                    break;
+                }
+                Err(error) => {
+                    warn!(
+                        "Wi-Fi SSID=\"{}\" failed ({}); trying next network",
+                        network.ssid.as_str(),
+                        wifi_error_name(error)
+                    );
+                    last_error = error;
+                    state.lock().await.last_error = Some(error);
+                }
+            }
+            if state.lock().await.reconfigure_requested {
+                break;
             }
         }
 
-        if let Err(err) = controller.set_config(&client_config) {
-            warn!("Wi-Fi set_config error: {:?}", err);
+        let Some(active_idx) = connected else {
+            if state.lock().await.reconfigure_requested {
+                // Networks changed mid-round; start over with the new list.
+                continue;
+            }
+            failures = failures.saturating_add(1);
             {
                 let mut guard = state.lock().await;
                 guard.state = WifiConnectionState::Error;
-                guard.last_error = Some(WifiErrorKind::ConnectFailed);
+                guard.ipv4 = None;
+                guard.gateway = None;
+                guard.last_error = Some(last_error);
             }
-            Timer::after(Duration::from_secs(10)).await;
+            Timer::after(Duration::from_secs(5)).await;
             continue;
-        }
-
-        info!("Starting Wi-Fi STA");
-        if let Err(err) = controller.start_async().await {
-            warn!("Wi-Fi start_async error: {:?}", err);
+        };
+        failures = 0;
+        let active_ssid = credentials.networks[active_idx].ssid.clone();
+
+        if let Some(cfg) = stack.config_v4() {
+            let ip = cfg.address.address();
+            let gw = cfg.gateway.unwrap_or(Ipv4Address::UNSPECIFIED);
+            info!(
+                "Wi-Fi link up: ssid=\"{}\" ip={} gw={}",
+                active_ssid.as_str(),
+                ip,
+                gw
+            );
             {
                 let mut guard = state.lock().await;
-                guard.state = WifiConnectionState::Error;
-                guard.last_error = Some(WifiErrorKind::ConnectFailed);
+                guard.state = WifiConnectionState::Connected;
+                guard.ipv4 = Some(ip);
+                guard.gateway = Some(gw);
+                guard.is_static = is_static_ip;
+                guard.last_error = None;
+                guard.mac = Some(mac);
             }
-            Timer::after(Duration::from_secs(10)).await;
-            continue;
         }
 
-        info!(
-            "Connecting to Wi-Fi SSID=\"{}\" (source={})",
-            ssid.as_str(),
-            source
-        );
-        const WIFI_CONNECT_TIMEOUT: Duration = Duration::from_secs(20);
-        let connect_result = select(
-            controller.connect_async(),
-            Timer::after(WIFI_CONNECT_TIMEOUT),
-        )
-        .await;
-        match connect_result {
-            Either::First(Ok(())) => {
-                info!("Wi-Fi connect_async returned Ok; waiting for IPv4 config");
-
-                let mut retries: u8 = 0;
-                loop {
-                    if stack.is_config_up() {
-                        break;
-                    }
-                    if retries >= 30 {
-                        warn!("Wi-Fi DHCP/static config not ready within timeout");
-                        {
-                            let mut guard = state.lock().await;
-                            guard.state = WifiConnectionState::Error;
-                            guard.last_error = Some(WifiErrorKind::DhcpTimeout);
-                        }
-                        break;
-                    }
-                    retries = retries.saturating_add(1);
-                    Timer::after(Duration::from_millis(500)).await;
-                }
-
-                if !stack.is_config_up() {
-                    Timer::after(Duration::from_secs(5)).await;
-                    continue;
-                }
-
-                if let Some(cfg) = stack.config_v4() {
-                    let ip = cfg.address.address();
-                    let gw = cfg.gateway.unwrap_or(Ipv4Address::UNSPECIFIED);
-                    info!("Wi-Fi link up: ip={} gw={}", ip, gw);
-                    {
-                        let mut guard = state.lock().await;
-                        guard.state = WifiConnectionState::Connected;
-                        guard.ipv4 = Some(ip);
-                        guard.gateway = Some(gw);
-                        guard.is_static = is_static_ip;
-                        guard.last_error = None;
-                        guard.mac = Some(mac);
-                    }
-                }
-
-                loop {
-                    Timer::after(Duration::from_secs(2)).await;
-                    if !matches!(controller.is_connected(), Ok(true)) {
-                        warn!("Wi-Fi STA disconnected; will retry");
-                        {
-                            let mut guard = state.lock().await;
-                            guard.state = WifiConnectionState::Error;
-                            guard.last_error = Some(WifiErrorKind::LinkLost);
-                        }
-                        break;
-                    }
-
-                    let next = read_wifi_credentials(eeprom).await;
-                    let reconfigure_requested = { state.lock().await.reconfigure_requested };
-                    if reconfigure_requested
-                        || next.source != credentials.source
-                        || next.ssid != Some(ssid.clone())
-                        || next.psk != Some(psk.clone())
-                    {
-                        info!("Wi-Fi credentials changed; reconnecting");
-                        let _ = controller.disconnect_async().await;
-                        break;
-                    }
-                }
-            }
-            Either::First(Err(err)) => {
-                warn!("Wi-Fi connect_async error: {:?}", err);
+        let mut last_roam_check_ms = now_ms32();
+        loop {
+            Timer::after(Duration::from_secs(2)).await;
+            if !matches!(controller.is_connected(), Ok(true)) {
+                // The next round rescans, so this fails over to another
+                // stored network when this one went away.
+                warn!("Wi-Fi STA disconnected; will rescan and retry");
                 {
                     let mut guard = state.lock().await;
                     guard.state = WifiConnectionState::Error;
-                    guard.last_error = Some(WifiErrorKind::ConnectFailed);
+                    guard.last_error = Some(WifiErrorKind::LinkLost);
                 }
-                Timer::after(Duration::from_secs(10)).await;
+                break;
             }
-            Either::Second(_) => {
-                warn!("Wi-Fi connect_async timed out; stopping STA before retry");
-                {
-                    let mut guard = state.lock().await;
-                    guard.state = WifiConnectionState::Error;
-                    guard.ipv4 = None;
-                    guard.gateway = None;
-                    guard.last_error = Some(WifiErrorKind::ConnectTimeout);
-                }
+
+            let reconfigure_requested = { state.lock().await.reconfigure_requested };
+            if reconfigure_requested {
+                info!("Wi-Fi networks changed; reconnecting");
                 let _ = controller.disconnect_async().await;
-                if let Err(err) = controller.stop_async().await {
-                    warn!("Wi-Fi stop_async after connect timeout error: {:?}", err);
-                }
-                Timer::after(Duration::from_secs(5)).await;
+                break;
+            }
+
+            if now_ms32().wrapping_sub(last_roam_check_ms) < WIFI_ROAM_CHECK_INTERVAL_MS {
+                continue;
+            }
+            last_roam_check_ms = now_ms32();
+            // Re-read the list: networks added or moved while connected only
+            // take effect through this check.
+            let networks = read_wifi_credentials(eeprom).await.networks;
+            let Some(current) = networks.iter().position(|n| n.ssid == active_ssid) else {
+                info!("Wi-Fi active network removed; reconnecting");
+                let _ = controller.disconnect_async().await;
+                break;
+            };
+            if current == 0 {
+                continue;
+            }
+            let rssi = scan_wifi_networks(&mut controller, &networks).await;
+            if let Some(&best) = order_wifi_candidates(&rssi[..networks.len()]).first()
+                && best < current
+                && rssi[best].is_some_and(|r| r >= WIFI_MIN_PREFERRED_RSSI)
+            {
+                info!(
+                    "Wi-Fi roaming to higher-priority SSID=\"{}\"",
+                    networks[best].ssid.as_str()
+                );
+                let _ = controller.disconnect_async().await;
+                break;
             }
         }
 
@@ -552,54 +658,366 @@ async fn wifi_task(
     }
 }
 
-async fn read_wifi_credentials(eeprom: &'static EepromMutex) -> WifiCredentials {
-    let user = {
-        let mut guard = eeprom.lock().await;
-        guard.read_wifi_blob().await.ok().and_then(|blob| {
-            eeprom::decode_wifi_blob(&blob)
-                .map(|parts| (String::from(parts.ssid), String::from(parts.psk)))
-        })
-    };
+/// Weakest signal at which a higher-priority network still wins over a
+/// stronger lower-priority one.
+const WIFI_MIN_PREFERRED_RSSI: i8 = -80;
+/// How often a station on a lower-priority network looks for a better one.
+const WIFI_ROAM_CHECK_INTERVAL_MS: u32 = 300_000;
+
+/// Connection order for the stored networks given the best RSSI the last
+/// scan saw for each: usable visible networks by priority, then weak
+/// visible ones strongest first, then the ones the scan missed (hidden
+/// SSIDs) by priority.
+fn order_wifi_candidates(rssi: &[Option<i8>]) -> Vec<usize, { eeprom::WIFI_MAX_NETWORKS }> {
+    let mut order: Vec<usize, { eeprom::WIFI_MAX_NETWORKS }> = Vec::new();
+    let mut weak: Vec<usize, { eeprom::WIFI_MAX_NETWORKS }> = Vec::new();
+    for (idx, seen) in rssi.iter().enumerate() {
+        match seen {
+            Some(r) if *r >= WIFI_MIN_PREFERRED_RSSI => {
+                let _ = order.push(idx);
+            }
+            Some(_) => {
+                let _ = weak.push(idx);
+            }
+            None => {}
+        }
+    }
+    weak.sort_unstable_by_key(|&idx| (core::cmp::Reverse(rssi[idx]), idx));
+    let _ = order.extend_from_slice(&weak);
+    for (idx, seen) in rssi.iter().enumerate() {
+        if seen.is_none() {
+            let _ = order.push(idx);
+        }
+    }
+    order
+}
+
+/// Best RSSI per stored network from a station scan. A failed scan reports
+/// nothing visible, which falls back to plain priority order.
+async fn scan_wifi_networks(
+    controller: &mut WifiController<'static>,
+    networks: &[WifiNetwork],
+) -> [Option<i8>; eeprom::WIFI_MAX_NETWORKS] {
+    let mut rssi = [None; eeprom::WIFI_MAX_NETWORKS];
+    if !matches!(controller.is_started(), Ok(true)) {
+        if let Err(err) = controller.set_config(&ModeConfig::Client(ClientConfig::default())) {
+            warn!("Wi-Fi scan set_config error: {:?}", err);
+            return rssi;
+        }
+        if let Err(err) = controller.start_async().await {
+            warn!("Wi-Fi scan start_async error: {:?}", err);
+            return rssi;
+        }
+    }
+    match controller
+        .scan_with_config_async(ScanConfig::default())
+        .await
+    {
+        Ok(found) => {
+            for ap in found.iter() {
+                if let Some(idx) = networks.iter().position(|n| n.ssid == ap.ssid.as_str()) {
+                    rssi[idx] = Some(
+                        rssi[idx].map_or(ap.signal_strength, |r: i8| r.max(ap.signal_strength)),
+                    );
+                }
+            }
+        }
+        Err(err) => warn!("Wi-Fi scan error: {:?}", err),
+    }
+    rssi
+}
+
+/// Join one stored network and wait for IPv4 config.
+async fn connect_wifi_network(
+    controller: &mut WifiController<'static>,
+    stack: Stack<'static>,
+    network: &WifiNetwork,
+) -> Result<(), WifiErrorKind> {
+    const WIFI_CONNECT_TIMEOUT: Duration = Duration::from_secs(20);
+
+    let client_config = ModeConfig::Client(
+        ClientConfig::default()
+            .with_ssid(network.ssid.clone())
+            .with_password(network.psk.clone())
+            .with_auth_method(AuthMethod::WpaWpa2Personal)
+            .with_scan_method(ScanMethod::AllChannels)
+            .with_failure_retry_cnt(3),
+    );
+
+    if matches!(controller.is_started(), Ok(true)) {
+        let _ = controller.disconnect_async().await;
+        if let Err(err) = controller.stop_async().await {
+            warn!("Wi-Fi stop_async before reconfigure error: {:?}", err);
+        }
+    }
+    if let Err(err) = controller.set_config(&client_config) {
+        warn!("Wi-Fi set_config error: {:?}", err);
+        return Err(WifiErrorKind::ConnectFailed);
+    }
+    info!("Starting Wi-Fi STA");
+    if let Err(err) = controller.start_async().await {
+        warn!("Wi-Fi start_async error: {:?}", err);
+        return Err(WifiErrorKind::ConnectFailed);
+    }
+
+    match select(
+        controller.connect_async(),
+        Timer::after(WIFI_CONNECT_TIMEOUT),
+    )
+    .await
+    {
+        Either::First(Ok(())) => {}
+        Either::First(Err(err)) => {
+            warn!("Wi-Fi connect_async error: {:?}", err);
+            return Err(WifiErrorKind::ConnectFailed);
+        }
+        Either::Second(_) => {
+            warn!("Wi-Fi connect_async timed out; stopping STA before retry");
+            let _ = controller.disconnect_async().await;
+            if let Err(err) = controller.stop_async().await {
+                warn!("Wi-Fi stop_async after connect timeout error: {:?}", err);
+            }
+            return Err(WifiErrorKind::ConnectTimeout);
+        }
+    }
+
+    info!("Wi-Fi connect_async returned Ok; waiting for IPv4 config");
+    for _ in 0..30 {
+        if stack.is_config_up() {
+            return Ok(());
+        }
+        Timer::after(Duration::from_millis(500)).await;
+    }
+    warn!("Wi-Fi DHCP/static config not ready within timeout");
+    let _ = controller.disconnect_async().await;
+    Err(WifiErrorKind::DhcpTimeout)
+}
+
+/// Consecutive station failures before the setup AP is brought up (factory
+/// credentials only).
+const WIFI_SETUP_AP_AFTER_FAILURES: u8 = 3;
+/// A failure-triggered setup AP goes back to retrying the stored credentials
+/// once the portal has been idle this long (router outage, not bad config).
+const WIFI_SETUP_AP_IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Run the setup access point (AP+STA so scans keep working) until new
+/// credentials are saved or, when `idle_timeout` is set, the portal has been
+/// unused for that long.
+async fn run_setup_ap(
+    controller: &mut WifiController<'static>,
+    state: &'static WifiStateMutex,
+    eeprom: &'static EepromMutex,
+    ap_ssid: &str,
+    idle_timeout: Option<Duration>,
+) {
+    if matches!(controller.is_started(), Ok(true)) {
+        let _ = controller.disconnect_async().await;
+        if let Err(err) = controller.stop_async().await {
+            warn!("Wi-Fi stop_async before setup AP error: {:?}", err);
+        }
+    }
+
+    let ap_config = ModeConfig::ApSta(
+        ClientConfig::default(),
+        AccessPointConfig::default()
+            .with_ssid(String::from(ap_ssid))
+            .with_auth_method(AuthMethod::None),
+    );
+    if let Err(err) = controller.set_config(&ap_config) {
+        warn!("Wi-Fi setup AP set_config error: {:?}", err);
+        Timer::after(Duration::from_secs(10)).await;
+        return;
+    }
+    if let Err(err) = controller.start_async().await {
+        warn!("Wi-Fi setup AP start_async error: {:?}", err);
+        Timer::after(Duration::from_secs(10)).await;
+        return;
+    }
+
+    info!(
+        "Wi-Fi setup AP up (ssid=\"{}\", portal=http://{}/)",
+        ap_ssid,
+        provision::AP_ADDRESS
+    );
+    {
+        let mut guard = state.lock().await;
+        guard.state = WifiConnectionState::Provisioning;
+        guard.ipv4 = None;
+        guard.gateway = None;
+        guard.reconfigure_requested = false;
+    }
+    provision::set_active(true);
+    provision::request_scan();
+
+    loop {
+        provision::service_scan(controller).await;
+
+        let reconfigure_requested = { state.lock().await.reconfigure_requested };
+        if reconfigure_requested {
+            if !read_wifi_credentials(eeprom).await.networks.is_empty() {
+                info!("Wi-Fi credentials saved; leaving setup AP");
+                // Let the portal deliver its confirmation page first.
+                Timer::after(Duration::from_secs(2)).await;
+                break;
+            }
+            // Credentials were cleared while the AP is up; stay here.
+            let mut guard = state.lock().await;
+            guard.state = WifiConnectionState::Provisioning;
+            guard.reconfigure_requested = false;
+        }
+        if let Some(timeout) = idle_timeout
+            && provision::idle_ms() as u64 >= timeout.as_millis()
+        {
+            info!("Wi-Fi setup AP idle; retrying stored credentials");
+            break;
+        }
+        Timer::after(Duration::from_millis(500)).await;
+    }
 
-    if let Some((ssid, psk)) = user {
+    provision::set_active(false);
+    if let Err(err) = controller.stop_async().await {
+        warn!("Wi-Fi stop_async after setup AP error: {:?}", err);
+    }
+    // `wifi_task` sets Connecting next; clear the flag consumed above.
+    state.lock().await.reconfigure_requested = false;
+}
+
+async fn read_wifi_credentials(eeprom: &'static EepromMutex) -> WifiCredentials {
+    let user = read_wifi_networks(eeprom).await.unwrap_or_default();
+    if !user.is_empty() {
         WifiCredentials {
-            ssid: Some(ssid),
-            psk: Some(psk),
+            networks: user,
             source: WifiCredentialSource::User,
         }
     } else if let (Some(ssid), Some(psk)) = (WIFI_SSID, WIFI_PSK) {
+        let mut networks = WifiNetworks::new();
+        let _ = networks.push(WifiNetwork {
+            ssid: String::from(ssid),
+            psk: String::from(psk),
+        });
         WifiCredentials {
-            ssid: Some(String::from(ssid)),
-            psk: Some(String::from(psk)),
+            networks,
             source: WifiCredentialSource::Factory,
         }
     } else {
         WifiCredentials {
-            ssid: None,
-            psk: None,
+            networks: WifiNetworks::new(),
             source: WifiCredentialSource::None,
         }
     }
 }
 
-// Keep at least one worker in accept() even if another is occupied by SSE, avoiding
-// browser-side ECONNREFUSED while reusing the same HTTP port and stack.
-//
-// Browsers often issue 4+ parallel HTTP requests on page load (identity, status,
-// cc, calibration mode, etc.). Keep enough workers to absorb that burst.
-const HTTP_WORKER_COUNT: usize = 4;
-const HTTP_PORT: u16 = 80;
+/// Stored network list. Devices provisioned before the list existed get
+/// their single legacy network back as a one-entry list.
+pub(crate) async fn read_wifi_networks(
+    eeprom: &'static EepromMutex,
+) -> Result<WifiNetworks, eeprom::EepromError> {
+    let mut guard = eeprom.lock().await;
+    let blob = guard.read_wifi_networks_blob().await?;
+    let mut networks = WifiNetworks::new();
+    if let Some(parts) = eeprom::decode_wifi_networks_blob(&blob) {
+        for part in parts.iter() {
+            let _ = networks.push(WifiNetwork {
+                ssid: String::from(part.ssid),
+                psk: String::from(part.psk),
+            });
+        }
+        return Ok(networks);
+    }
+    let legacy = guard.read_wifi_blob().await?;
+    if let Some(part) = eeprom::decode_wifi_blob(&legacy) {
+        let _ = networks.push(WifiNetwork {
+            ssid: String::from(part.ssid),
+            psk: String::from(part.psk),
+        });
+    }
+    Ok(networks)
+}
 
-#[embassy_executor::task(pool_size = HTTP_WORKER_COUNT)]
-async fn http_worker(
-    stack: Stack<'static>,
-    state: &'static WifiStateMutex,
-    telemetry: &'static TelemetryMutex,
-    calibration: &'static CalibrationMutex,
+/// Persist the network list. The legacy single-network blob mirrors the
+/// top entry so a firmware downgrade still joins the preferred network.
+pub(crate) async fn store_wifi_networks(
     eeprom: &'static EepromMutex,
-    control: &'static ControlMutex,
-    worker_id: usize,
-) {
+    networks: &[WifiNetwork],
+) -> Result<(), eeprom::EepromError> {
+    let mut parts: Vec<eeprom::WifiBlobParts<'_>, { eeprom::WIFI_MAX_NETWORKS }> = Vec::new();
+    for network in networks {
+        parts
+            .push(eeprom::WifiBlobParts {
+                ssid: &network.ssid,
+                psk: &network.psk,
+            })
+            .map_err(|_| eeprom::EepromError::InvalidLength)?;
+    }
+    let blob = eeprom::encode_wifi_networks_blob(&parts)
+        .map_err(|_| eeprom::EepromError::InvalidLength)?;
+    let mut guard = eeprom.lock().await;
+    guard.write_wifi_networks_blob(&blob).await?;
+    match networks.first() {
+        Some(top) => {
+            let legacy = eeprom::encode_wifi_blob(&top.ssid, &top.psk)
+                .map_err(|_| eeprom::EepromError::InvalidLength)?;
+            guard.write_wifi_blob(&legacy).await
+        }
+        None => guard.clear_wifi_blob().await,
+    }
+}
+
+pub(crate) const WIFI_NETWORKS_FULL: &str = "network list is full (max 6)";
+
+/// Insert or update `ssid` at `priority` (clamped to the end of the list).
+pub(crate) fn upsert_wifi_network(
+    networks: &mut WifiNetworks,
+    ssid: &str,
+    psk: &str,
+    priority: usize,
+) -> Result<(), &'static str> {
+    eeprom::validate_wifi_network(ssid, psk)?;
+    if let Some(idx) = networks.iter().position(|n| n.ssid == ssid) {
+        networks.remove(idx);
+    } else if networks.is_full() {
+        return Err(WIFI_NETWORKS_FULL);
+    }
+    let priority = priority.min(networks.len());
+    let _ = networks.insert(
+        priority,
+        WifiNetwork {
+            ssid: String::from(ssid),
+            psk: String::from(psk),
+        },
+    );
+    Ok(())
+}
+
+/// Move `ssid` to `priority` (clamped); `false` when it is not stored.
+pub(crate) fn move_wifi_network(networks: &mut WifiNetworks, ssid: &str, priority: usize) -> bool {
+    let Some(idx) = networks.iter().position(|n| n.ssid == ssid) else {
+        return false;
+    };
+    let network = networks.remove(idx);
+    let priority = priority.min(networks.len());
+    let _ = networks.insert(priority, network);
+    true
+}
+
+// Keep at least one worker in accept() even if another is occupied by SSE, avoiding
+// browser-side ECONNREFUSED while reusing the same HTTP port and stack.
+//
+// Browsers often issue 4+ parallel HTTP requests on page load (identity, status,
+// cc, calibration mode, etc.). Keep enough workers to absorb that burst.
+const HTTP_WORKER_COUNT: usize = 4;
+const HTTP_PORT: u16 = 80;
+
+#[embassy_executor::task(pool_size = HTTP_WORKER_COUNT)]
+async fn http_worker(
+    stack: Stack<'static>,
+    state: &'static WifiStateMutex,
+    telemetry: &'static TelemetryMutex,
+    calibration: &'static CalibrationMutex,
+    eeprom: &'static EepromMutex,
+    control: &'static ControlMutex,
+    worker_id: usize,
+) {
     let mut rx_buf = [0u8; 1024];
     let mut tx_buf = [0u8; 1024];
 
@@ -698,6 +1116,9 @@ async fn handle_http_connection(
         has_content_length,
         origin_s,
         accept_event_stream,
+        websocket_key,
+        authorization_s,
+        image_sha256_s,
     ) = {
         // Try to parse as UTF‑8; fall back to an error on failure.
         let req_str = match core::str::from_utf8(&buf[..total]) {
@@ -720,6 +1141,10 @@ async fn handle_http_connection(
         let mut has_content_length = false;
         let mut origin_s: Option<String> = None;
         let mut accept_event_stream = false;
+        let mut upgrade_websocket = false;
+        let mut websocket_key: Option<String> = None;
+        let mut authorization_s: Option<String> = None;
+        let mut image_sha256_s: Option<String> = None;
 
         let mut lines = req_str.lines();
         let request_line = lines.next().unwrap_or("");
@@ -769,6 +1194,19 @@ async fn handle_http_connection(
                 && rest.contains("text/event-stream")
             {
                 accept_event_stream = true;
+            } else if let Some(rest) = lower.strip_prefix("upgrade:") {
+                upgrade_websocket = rest.trim() == "websocket";
+            } else if lower.starts_with("sec-websocket-key:") {
+                // The key is case-sensitive base64; take it from the original line.
+                let rest = line.split_once(':').map(|x| x.1).unwrap_or("").trim();
+                if !rest.is_empty() {
+                    websocket_key = Some(String::from(rest));
+                }
+            } else if lower.starts_with("authorization:") {
+                let rest = line.split_once(':').map(|x| x.1).unwrap_or("").trim();
+                authorization_s = Some(String::from(rest));
+            } else if let Some(rest) = lower.strip_prefix("x-loadlynx-image-sha256:") {
+                image_sha256_s = Some(String::from(rest.trim()));
             }
         }
 
@@ -781,11 +1219,17 @@ async fn handle_http_connection(
             has_content_length,
             origin_s,
             accept_event_stream,
+            websocket_key.filter(|_| upgrade_websocket),
+            authorization_s,
+            image_sha256_s,
         )
     };
 
     let method = method_s.as_str();
-    let path = path_s.as_str();
+    // The query only carries `access_token` for clients that cannot send
+    // headers (the SSE and WebSocket upgrades, see `auth::presented_token`);
+    // routes match on the bare path.
+    let (path, query) = path_s.split_once('?').unwrap_or((path_s.as_str(), ""));
     let version = version_s.as_str();
     let cors_origin = origin_s.as_deref();
 
@@ -803,6 +1247,79 @@ async fn handle_http_connection(
         return Ok(());
     }
 
+    // Firmware images are streamed straight to flash instead of being buffered.
+    if method == "POST" && path == "/api/v1/analog/firmware" {
+        return handle_analog_firmware_upload(
+            socket,
+            version,
+            &mut buf,
+            header_end.min(total)..total,
+            has_content_length.then_some(content_length),
+            authorization_s.as_deref(),
+            image_sha256_s.as_deref(),
+            eeprom,
+            control,
+            cors_origin,
+        )
+        .await;
+    }
+    if method == "POST" && path == "/api/v1/firmware" {
+        return handle_firmware_upload(
+            socket,
+            version,
+            &mut buf,
+            header_end.min(total)..total,
+            has_content_length.then_some(content_length),
+            authorization_s.as_deref(),
+            image_sha256_s.as_deref(),
+            eeprom,
+            control,
+            cors_origin,
+        )
+        .await;
+    }
+
+    let mut role = None;
+    if let Some(required) = auth::required_role(method, path) {
+        let presented = auth::presented_token(method, path, authorization_s.as_deref(), query);
+        role = match presented {
+            Some(token) => auth::authorize(eeprom, token).await,
+            None => None,
+        };
+        let mut body = String::new();
+        match role {
+            None => {
+                write_error_body(
+                    &mut body,
+                    "UNAUTHORIZED",
+                    "API token required (pair via POST /api/v1/auth/pair or USB create_api_token)",
+                    false,
+                    None,
+                );
+                write_http_response(socket, version, "401 Unauthorized", &body, cors_origin)
+                    .await?;
+                return Ok(());
+            }
+            Some(role) if role < required => {
+                let details = format!(
+                    "{{\"required\":\"{}\",\"role\":\"{}\"}}",
+                    required.name(),
+                    role.name()
+                );
+                write_error_body(
+                    &mut body,
+                    "FORBIDDEN",
+                    "API token lacks the permission for this route",
+                    false,
+                    Some(&details),
+                );
+                write_http_response(socket, version, "403 Forbidden", &body, cors_origin).await?;
+                return Ok(());
+            }
+            Some(_) => {}
+        }
+    }
+
     // Ensure the full body has been read for PUT/POST requests that carry a JSON payload.
     let mut body_str: &str = "";
     if method == "PUT" || method == "POST" {
@@ -915,6 +1432,28 @@ async fn handle_http_connection(
                 }
             }
         }
+        ("GET", "/api/v1/ws") => {
+            let Some(key) = websocket_key.as_deref() else {
+                write_error_body(
+                    &mut body,
+                    "INVALID_REQUEST",
+                    "WebSocket upgrade required",
+                    false,
+                    None,
+                );
+                write_http_response(socket, version, "426 Upgrade Required", &body, cors_origin)
+                    .await?;
+                return Ok(());
+            };
+            let ctx = ws::WsContext {
+                telemetry,
+                calibration,
+                eeprom,
+                control,
+                role: role.unwrap_or(auth::Role::Read),
+            };
+            return ws::handle_websocket(socket, key, cors_origin, ctx).await;
+        }
         ("GET", "/api/v1/status") => {
             if accept_event_stream {
                 return handle_status_sse(socket, telemetry, cors_origin).await;
@@ -1035,6 +1574,61 @@ async fn handle_http_connection(
                 Err(err) => write_http_response(socket, version, err, &body, cors_origin).await?,
             }
         }
+        ("GET", "/api/v1/calibration/verify") => {
+            render_calibration_verify_json(&mut body, calibration, eeprom).await;
+            write_http_response(socket, version, "200 OK", &body, cors_origin).await?;
+        }
+        ("POST", "/api/v1/calibration/verify") => {
+            match handle_calibration_verify(body_str, &mut body, calibration, telemetry, eeprom)
+                .await
+            {
+                Ok(()) => {
+                    write_http_response(socket, version, "200 OK", &body, cors_origin).await?
+                }
+                Err(err) => write_http_response(socket, version, err, &body, cors_origin).await?,
+            }
+        }
+        ("GET", "/api/v1/calibration/slots") => {
+            render_calibration_slots_json(&mut body, calibration).await;
+            write_http_response(socket, version, "200 OK", &body, cors_origin).await?;
+        }
+        ("POST", "/api/v1/calibration/slots/activate") => {
+            match handle_calibration_slot_activate(body_str, &mut body, calibration, eeprom).await {
+                Ok(()) => {
+                    // The new active profile replaces all four curves on the analog side.
+                    if let Err(code) = enqueue_cal_uart(CalUartCommand::SendAllCurves) {
+                        write_error_body(&mut body, "UNAVAILABLE", code, true, None);
+                        write_http_response(
+                            socket,
+                            version,
+                            "503 Service Unavailable",
+                            &body,
+                            cors_origin,
+                        )
+                        .await?;
+                    } else {
+                        write_http_response(socket, version, "200 OK", &body, cors_origin).await?;
+                    }
+                }
+                Err(err) => write_http_response(socket, version, err, &body, cors_origin).await?,
+            }
+        }
+        ("POST", "/api/v1/calibration/slots/rename") => {
+            match handle_calibration_slot_rename(body_str, &mut body, calibration, eeprom).await {
+                Ok(()) => {
+                    write_http_response(socket, version, "200 OK", &body, cors_origin).await?
+                }
+                Err(err) => write_http_response(socket, version, err, &body, cors_origin).await?,
+            }
+        }
+        ("POST", "/api/v1/calibration/slots/delete") => {
+            match handle_calibration_slot_delete(body_str, &mut body, calibration, eeprom).await {
+                Ok(()) => {
+                    write_http_response(socket, version, "200 OK", &body, cors_origin).await?
+                }
+                Err(err) => write_http_response(socket, version, err, &body, cors_origin).await?,
+            }
+        }
         ("GET", "/api/v1/presets") => match render_presets_json(&mut body, control).await {
             Ok(()) => {
                 write_http_response(socket, version, "200 OK", &body, cors_origin).await?;
@@ -1122,6 +1716,34 @@ async fn handle_http_connection(
                 }
             }
         }
+        ("GET", "/api/v1/protection") => {
+            render_protection_json(&mut body, control).await;
+            write_http_response(socket, version, "200 OK", &body, cors_origin).await?;
+        }
+        ("PUT", "/api/v1/protection") | ("POST", "/api/v1/protection") => {
+            match handle_protection_update(body_str, &mut body, control, eeprom).await {
+                Ok(()) => {
+                    write_http_response(socket, version, "200 OK", &body, cors_origin).await?;
+                }
+                Err(err) => {
+                    write_http_response(socket, version, err, &body, cors_origin).await?;
+                }
+            }
+        }
+        ("GET", "/api/v1/thermal") => {
+            render_thermal_json(&mut body);
+            write_http_response(socket, version, "200 OK", &body, cors_origin).await?;
+        }
+        ("PUT", "/api/v1/thermal") | ("POST", "/api/v1/thermal") => {
+            match handle_thermal_update(body_str, &mut body, eeprom).await {
+                Ok(()) => {
+                    write_http_response(socket, version, "200 OK", &body, cors_origin).await?;
+                }
+                Err(err) => {
+                    write_http_response(socket, version, err, &body, cors_origin).await?;
+                }
+            }
+        }
         ("GET", "/api/v1/wifi") => {
             match render_wifi_status_json(&mut body, eeprom, wifi_state).await {
                 Ok(()) => {
@@ -1167,6 +1789,115 @@ async fn handle_http_connection(
                 Err(err) => write_http_response(socket, version, err, &body, cors_origin).await?,
             }
         }
+        ("GET", "/api/v1/wifi/networks") => {
+            match render_wifi_networks_json(&mut body, eeprom, wifi_state).await {
+                Ok(()) => {
+                    write_http_response(socket, version, "200 OK", &body, cors_origin).await?;
+                }
+                Err(err) => write_http_response(socket, version, err, &body, cors_origin).await?,
+            }
+        }
+        ("POST", "/api/v1/wifi/networks/add") => {
+            match handle_wifi_network_add_http(body_str, &mut body, eeprom, wifi_state).await {
+                Ok(()) => {
+                    write_http_response(socket, version, "200 OK", &body, cors_origin).await?;
+                }
+                Err(err) => write_http_response(socket, version, err, &body, cors_origin).await?,
+            }
+        }
+        ("POST", "/api/v1/wifi/networks/remove") => {
+            match handle_wifi_network_remove_http(body_str, &mut body, eeprom, wifi_state).await {
+                Ok(()) => {
+                    write_http_response(socket, version, "200 OK", &body, cors_origin).await?;
+                }
+                Err(err) => write_http_response(socket, version, err, &body, cors_origin).await?,
+            }
+        }
+        ("POST", "/api/v1/wifi/networks/move") => {
+            match handle_wifi_network_move_http(body_str, &mut body, eeprom, wifi_state).await {
+                Ok(()) => {
+                    write_http_response(socket, version, "200 OK", &body, cors_origin).await?;
+                }
+                Err(err) => write_http_response(socket, version, err, &body, cors_origin).await?,
+            }
+        }
+        ("GET", "/api/v1/mqtt") => match render_mqtt_json(&mut body, eeprom, wifi_state).await {
+            Ok(()) => {
+                write_http_response(socket, version, "200 OK", &body, cors_origin).await?;
+            }
+            Err(err) => write_http_response(socket, version, err, &body, cors_origin).await?,
+        },
+        ("POST", "/api/v1/mqtt") => {
+            match handle_mqtt_set_http(body_str, &mut body, eeprom, wifi_state).await {
+                Ok(()) => {
+                    write_http_response(socket, version, "200 OK", &body, cors_origin).await?;
+                }
+                Err(err) => write_http_response(socket, version, err, &body, cors_origin).await?,
+            }
+        }
+        ("DELETE", "/api/v1/mqtt") => {
+            match handle_mqtt_clear_http(&mut body, eeprom, wifi_state).await {
+                Ok(()) => {
+                    write_http_response(socket, version, "200 OK", &body, cors_origin).await?;
+                }
+                Err(err) => write_http_response(socket, version, err, &body, cors_origin).await?,
+            }
+        }
+        ("GET", "/api/v1/firmware") => match render_firmware_json(&mut body, eeprom).await {
+            Ok(()) => write_http_response(socket, version, "200 OK", &body, cors_origin).await?,
+            Err(err) => write_http_response(socket, version, err, &body, cors_origin).await?,
+        },
+        ("GET", "/api/v1/analog/firmware") => {
+            render_analog_firmware_json(&mut body);
+            write_http_response(socket, version, "200 OK", &body, cors_origin).await?;
+        }
+        ("GET", "/api/v1/time") => match render_time_json(&mut body, eeprom).await {
+            Ok(()) => {
+                write_http_response(socket, version, "200 OK", &body, cors_origin).await?;
+            }
+            Err(err) => write_http_response(socket, version, err, &body, cors_origin).await?,
+        },
+        ("POST", "/api/v1/time") => match handle_time_set_http(body_str, &mut body, eeprom).await {
+            Ok(()) => {
+                write_http_response(socket, version, "200 OK", &body, cors_origin).await?;
+            }
+            Err(err) => write_http_response(socket, version, err, &body, cors_origin).await?,
+        },
+        ("GET", "/api/v1/measure/ir") => {
+            render_ir_measure_json(&mut body);
+            write_http_response(socket, version, "200 OK", &body, cors_origin).await?;
+        }
+        ("POST", "/api/v1/measure/ir") => {
+            match handle_ir_measure_start(body_str, &mut body, control, calibration).await {
+                Ok(()) => {
+                    write_http_response(socket, version, "202 Accepted", &body, cors_origin)
+                        .await?;
+                }
+                Err(err) => write_http_response(socket, version, err, &body, cors_origin).await?,
+            }
+        }
+        ("POST", "/api/v1/measure/ir/cancel") => {
+            handle_ir_measure_cancel(&mut body);
+            write_http_response(socket, version, "200 OK", &body, cors_origin).await?;
+        }
+        ("POST", "/api/v1/faults/clear") => match handle_faults_clear_http(body_str, &mut body) {
+            Ok(()) => {
+                write_http_response(socket, version, "200 OK", &body, cors_origin).await?;
+            }
+            Err(status) => {
+                write_http_response(socket, version, status, &body, cors_origin).await?;
+            }
+        },
+        ("POST", "/api/v1/counters/reset") => {
+            match handle_counters_reset_http(body_str, &mut body) {
+                Ok(()) => {
+                    write_http_response(socket, version, "200 OK", &body, cors_origin).await?;
+                }
+                Err(status) => {
+                    write_http_response(socket, version, status, &body, cors_origin).await?;
+                }
+            }
+        }
         ("POST", "/api/v1/soft-reset") => match handle_soft_reset_http(body_str, &mut body) {
             Ok(()) => {
                 write_http_response(socket, version, "200 OK", &body, cors_origin).await?;
@@ -1175,6 +1906,52 @@ async fn handle_http_connection(
                 write_http_response(socket, version, status, &body, cors_origin).await?;
             }
         },
+        ("POST", "/api/v1/auth/pair/start") => match auth::start_pairing() {
+            Ok(expires_in_s) => {
+                let _ = core::write!(
+                    &mut body,
+                    "{{\"ok\":true,\"expires_in_s\":{}}}",
+                    expires_in_s
+                );
+                write_http_response(socket, version, "200 OK", &body, cors_origin).await?;
+            }
+            Err(err) => {
+                let status = write_auth_error(&mut body, err);
+                write_http_response(socket, version, status, &body, cors_origin).await?
+            }
+        },
+        ("POST", "/api/v1/auth/pair") => {
+            match handle_auth_pair(body_str, &mut body, eeprom).await {
+                Ok(()) => {
+                    write_http_response(socket, version, "200 OK", &body, cors_origin).await?
+                }
+                Err(err) => write_http_response(socket, version, err, &body, cors_origin).await?,
+            }
+        }
+        ("GET", "/api/v1/auth") => {
+            let _ = core::write!(
+                &mut body,
+                "{{\"role\":\"{}\",\"pairing\":{}}}",
+                role.map_or("read", auth::Role::name),
+                auth::pairing_prompt().is_some()
+            );
+            write_http_response(socket, version, "200 OK", &body, cors_origin).await?;
+        }
+        ("GET", "/api/v1/auth/tokens") => match auth::render_tokens_json(&mut body, eeprom).await {
+            Ok(()) => write_http_response(socket, version, "200 OK", &body, cors_origin).await?,
+            Err(err) => {
+                let status = write_auth_error(&mut body, err);
+                write_http_response(socket, version, status, &body, cors_origin).await?
+            }
+        },
+        ("POST", "/api/v1/auth/tokens/revoke") => {
+            match handle_auth_revoke(body_str, &mut body, eeprom).await {
+                Ok(()) => {
+                    write_http_response(socket, version, "200 OK", &body, cors_origin).await?
+                }
+                Err(err) => write_http_response(socket, version, err, &body, cors_origin).await?,
+            }
+        }
         ("GET", "/api/v1/diagnostics") | ("GET", "/api/v1/diagnostics/export") => {
             match render_diagnostics_json(&mut body, eeprom, wifi_state, telemetry, calibration)
                 .await
@@ -1205,7 +1982,7 @@ async fn handle_http_connection(
     Ok(())
 }
 
-fn write_json_string_escaped(buf: &mut String, s: &str) {
+pub(crate) fn write_json_string_escaped(buf: &mut String, s: &str) {
     for ch in s.chars() {
         match ch {
             '"' => buf.push_str("\\\""),
@@ -1235,7 +2012,7 @@ fn is_local_browser_origin(origin: &str) -> bool {
     matches!(host, "localhost" | "127.0.0.1" | "::1")
 }
 
-fn write_error_body(
+pub(crate) fn write_error_body(
     buf: &mut String,
     code: &str,
     message: &str,
@@ -1295,7 +2072,7 @@ fn parse_json_string_value(body: &str, key: &str) -> Option<String> {
     None
 }
 
-fn parse_json_bool_value(body: &str, key: &str) -> Option<bool> {
+pub(crate) fn parse_json_bool_value(body: &str, key: &str) -> Option<bool> {
     let idx = body.find(key)?;
     let colon = body[idx..].find(':')?;
     let rest = body[idx + colon + 1..].trim_start();
@@ -1313,6 +2090,7 @@ fn wifi_state_name(state: WifiConnectionState) -> &'static str {
         WifiConnectionState::Idle => "idle",
         WifiConnectionState::Connecting => "connecting",
         WifiConnectionState::Connected => "connected",
+        WifiConnectionState::Provisioning => "provisioning",
         WifiConnectionState::Error => "error",
     }
 }
@@ -1327,21 +2105,34 @@ fn wifi_error_name(error: WifiErrorKind) -> &'static str {
     }
 }
 
-async fn read_wifi_status_identity(eeprom: &'static EepromMutex) -> (String, &'static str) {
-    let user =
+/// SSID reported in status payloads (the network in use, else the top
+/// stored one), its source and how many networks are stored.
+async fn read_wifi_status_identity(
+    eeprom: &'static EepromMutex,
+    wifi_state: &'static WifiStateMutex,
+) -> (String, &'static str, usize) {
+    let credentials = read_wifi_credentials(eeprom).await;
+    let active = { wifi_state.lock().await.active_ssid };
+    let ssid = match active {
+        Some(active)
+            if credentials
+                .networks
+                .iter()
+                .any(|n| n.ssid == active.as_str()) =>
         {
-            let mut guard = eeprom.lock().await;
-            guard.read_wifi_blob().await.ok().and_then(|blob| {
-                eeprom::decode_wifi_blob(&blob).map(|parts| String::from(parts.ssid))
-            })
-        };
-    match user {
-        Some(ssid) => (ssid, "user"),
-        None => match WIFI_SSID {
-            Some(ssid) => (String::from(ssid), "factory"),
-            None => (String::new(), "none"),
-        },
-    }
+            String::from(active.as_str())
+        }
+        _ => credentials
+            .networks
+            .first()
+            .map(|n| n.ssid.clone())
+            .unwrap_or_default(),
+    };
+    let count = match credentials.source {
+        WifiCredentialSource::User => credentials.networks.len(),
+        _ => 0,
+    };
+    (ssid, credentials.source.name(), count)
 }
 
 fn append_wifi_status_fields(buf: &mut String, wifi: WifiState) {
@@ -1377,9 +2168,9 @@ pub(crate) async fn render_wifi_status_json(
     eeprom: &'static EepromMutex,
     wifi_state: &'static WifiStateMutex,
 ) -> Result<(), &'static str> {
-    let (ssid, source) = read_wifi_status_identity(eeprom).await;
+    let (ssid, source, network_count) = read_wifi_status_identity(eeprom, wifi_state).await;
     let mut wifi = { *wifi_state.lock().await };
-    if source == "none" {
+    if source == "none" && !matches!(wifi.state, WifiConnectionState::Provisioning) {
         wifi.state = WifiConnectionState::Idle;
         wifi.ipv4 = None;
         wifi.gateway = None;
@@ -1390,7 +2181,7 @@ pub(crate) async fn render_wifi_status_json(
     write_json_string_escaped(buf, &ssid);
     buf.push_str("\",\"source\":\"");
     buf.push_str(source);
-    buf.push('"');
+    let _ = core::write!(buf, "\",\"network_count\":{}", network_count);
     append_wifi_status_fields(buf, wifi);
     Ok(())
 }
@@ -1400,23 +2191,72 @@ pub(crate) async fn render_wifi_credentials_json(
     eeprom: &'static EepromMutex,
 ) -> Result<(), &'static str> {
     let credentials = read_wifi_credentials(eeprom).await;
-    let source = match credentials.source {
-        WifiCredentialSource::Factory => "factory",
-        WifiCredentialSource::User => "user",
-        WifiCredentialSource::None => "none",
-    };
+    let top = credentials.networks.first();
     buf.clear();
     buf.push_str("{\"ssid\":\"");
-    write_json_string_escaped(buf, credentials.ssid.as_deref().unwrap_or(""));
+    write_json_string_escaped(buf, top.map_or("", |n| n.ssid.as_str()));
     buf.push_str("\",\"psk\":\"");
-    write_json_string_escaped(buf, credentials.psk.as_deref().unwrap_or(""));
+    write_json_string_escaped(buf, top.map_or("", |n| n.psk.as_str()));
     buf.push_str("\",\"source\":\"");
-    buf.push_str(source);
-    buf.push_str("\"}");
+    buf.push_str(credentials.source.name());
+    buf.push_str("\",\"networks\":[");
+    for (idx, network) in credentials.networks.iter().enumerate() {
+        if idx > 0 {
+            buf.push(',');
+        }
+        buf.push_str("{\"ssid\":\"");
+        write_json_string_escaped(buf, &network.ssid);
+        buf.push_str("\",\"psk\":\"");
+        write_json_string_escaped(buf, &network.psk);
+        buf.push_str("\"}");
+    }
+    buf.push_str("]}");
+    Ok(())
+}
+
+/// Stored networks in priority order, without PSKs.
+pub(crate) async fn render_wifi_networks_json(
+    buf: &mut String,
+    eeprom: &'static EepromMutex,
+    wifi_state: &'static WifiStateMutex,
+) -> Result<(), &'static str> {
+    let Ok(networks) = read_wifi_networks(eeprom).await else {
+        write_error_body(buf, "UNAVAILABLE", "EEPROM read failed", true, None);
+        return Err("503 Service Unavailable");
+    };
+    let wifi = { *wifi_state.lock().await };
+    let active = match wifi.state {
+        WifiConnectionState::Connected => wifi.active_ssid,
+        _ => None,
+    };
+    buf.clear();
+    let _ = core::write!(
+        buf,
+        "{{\"max_networks\":{},\"active_ssid\":",
+        eeprom::WIFI_MAX_NETWORKS
+    );
+    if let Some(active) = active {
+        buf.push('"');
+        write_json_string_escaped(buf, active.as_str());
+        buf.push('"');
+    } else {
+        buf.push_str("null");
+    }
+    buf.push_str(",\"networks\":[");
+    for (idx, network) in networks.iter().enumerate() {
+        if idx > 0 {
+            buf.push(',');
+        }
+        buf.push_str("{\"ssid\":\"");
+        write_json_string_escaped(buf, &network.ssid);
+        let is_active = active.is_some_and(|a| a.as_str() == network.ssid);
+        let _ = core::write!(buf, "\",\"priority\":{},\"active\":{}}}", idx, is_active);
+    }
+    buf.push_str("]}");
     Ok(())
 }
 
-async fn mark_wifi_reconfigure_pending(wifi_state: &'static WifiStateMutex) {
+pub(crate) async fn mark_wifi_reconfigure_pending(wifi_state: &'static WifiStateMutex) {
     let mut status = wifi_state.lock().await;
     status.state = WifiConnectionState::Connecting;
     status.ipv4 = None;
@@ -1449,6 +2289,7 @@ async fn wait_for_wifi_connected(wifi_state: &'static WifiStateMutex) {
     }
 }
 
+/// Store `ssid` as the top-priority network and reconnect to it.
 pub(crate) async fn handle_wifi_set_http(
     body_in: &str,
     body_out: &mut String,
@@ -1463,17 +2304,11 @@ pub(crate) async fn handle_wifi_set_http(
         write_error_body(body_out, "INVALID_REQUEST", "missing psk", false, None);
         "400 Bad Request"
     })?;
-    let blob = match eeprom::encode_wifi_blob(&ssid, &psk) {
-        Ok(blob) => blob,
-        Err(message) => {
-            write_error_body(body_out, "INVALID_REQUEST", message, false, None);
-            return Err("400 Bad Request");
-        }
-    };
-    if eeprom.lock().await.write_wifi_blob(&blob).await.is_err() {
-        write_error_body(body_out, "UNAVAILABLE", "EEPROM write failed", true, None);
-        return Err("503 Service Unavailable");
+    let mut networks = read_wifi_networks_for_update(body_out, eeprom).await?;
+    if let Err(message) = upsert_wifi_network(&mut networks, &ssid, &psk, 0) {
+        return Err(write_wifi_network_error(body_out, message));
     }
+    store_wifi_networks_for_update(body_out, eeprom, &networks).await?;
     mark_wifi_reconfigure_pending(wifi_state).await;
     if parse_json_bool_value(body_in, "\"wait\"").unwrap_or(false) {
         wait_for_wifi_connected(wifi_state).await;
@@ -1481,12 +2316,13 @@ pub(crate) async fn handle_wifi_set_http(
     render_wifi_status_json(body_out, eeprom, wifi_state).await
 }
 
+/// Forget every stored network (factory credentials, if any, take over).
 pub(crate) async fn handle_wifi_clear_http(
     body_out: &mut String,
     eeprom: &'static EepromMutex,
     wifi_state: &'static WifiStateMutex,
 ) -> Result<(), &'static str> {
-    if eeprom.lock().await.clear_wifi_blob().await.is_err() {
+    if store_wifi_networks(eeprom, &[]).await.is_err() {
         write_error_body(body_out, "UNAVAILABLE", "EEPROM clear failed", true, None);
         return Err("503 Service Unavailable");
     }
@@ -1494,720 +2330,1700 @@ pub(crate) async fn handle_wifi_clear_http(
     render_wifi_status_json(body_out, eeprom, wifi_state).await
 }
 
-pub(crate) async fn render_diagnostics_json(
-    buf: &mut String,
+/// `POST /api/v1/wifi/networks/add`: `{ssid, psk, priority?}`; without a
+/// priority the network goes to the end of the list.
+pub(crate) async fn handle_wifi_network_add_http(
+    body_in: &str,
+    body_out: &mut String,
     eeprom: &'static EepromMutex,
     wifi_state: &'static WifiStateMutex,
-    telemetry: &'static TelemetryMutex,
-    calibration: &'static CalibrationMutex,
 ) -> Result<(), &'static str> {
-    let (ssid, source) = read_wifi_status_identity(eeprom).await;
-    let wifi = { *wifi_state.lock().await };
-    let status = telemetry.lock().await.last_status;
-    buf.clear();
-    buf.push_str("{\"schema_version\":1,\"redaction\":{\"psk\":true},\"firmware_version\":\"");
-    write_json_string_escaped(buf, FW_VERSION);
-    buf.push_str("\",\"wifi\":{\"ssid\":\"");
-    write_json_string_escaped(buf, &ssid);
-    buf.push_str("\",\"source\":\"");
-    buf.push_str(source);
-    buf.push_str("\",\"psk\":\"<redacted>\"");
-    append_wifi_status_fields(buf, wifi);
-    buf.push_str(",\"link_up\":");
-    buf.push_str(if LINK_UP.load(Ordering::Relaxed) {
-        "true"
-    } else {
-        "false"
-    });
-    let calibration_persistence_status = { calibration.lock().await.persistence_status };
-    buf.push_str(",\"calibration_persistence\":{\"status\":\"");
-    buf.push_str(calibration_persistence_status.as_str());
-    buf.push_str("\"}");
-    if let Some(status) = status {
-        let _ = core::write!(
-            buf,
-            ",\"last_status\":{{\"uptime_ms\":{},\"fault_flags\":{}}}",
-            status.uptime_ms,
-            status.fault_flags
+    let ssid = parse_json_string_value(body_in, "\"ssid\"").ok_or_else(|| {
+        write_error_body(body_out, "INVALID_REQUEST", "missing ssid", false, None);
+        "400 Bad Request"
+    })?;
+    let psk = parse_json_string_value(body_in, "\"psk\"").ok_or_else(|| {
+        write_error_body(body_out, "INVALID_REQUEST", "missing psk", false, None);
+        "400 Bad Request"
+    })?;
+    let priority = parse_wifi_priority(body_in, body_out, false)?;
+    let mut networks = read_wifi_networks_for_update(body_out, eeprom).await?;
+    let priority = priority.unwrap_or(networks.len());
+    if let Err(message) = upsert_wifi_network(&mut networks, &ssid, &psk, priority) {
+        return Err(write_wifi_network_error(body_out, message));
+    }
+    store_wifi_networks_for_update(body_out, eeprom, &networks).await?;
+    reconfigure_after_network_change(wifi_state, &ssid).await;
+    render_wifi_networks_json(body_out, eeprom, wifi_state).await
+}
+
+/// `POST /api/v1/wifi/networks/remove`: `{ssid}`.
+pub(crate) async fn handle_wifi_network_remove_http(
+    body_in: &str,
+    body_out: &mut String,
+    eeprom: &'static EepromMutex,
+    wifi_state: &'static WifiStateMutex,
+) -> Result<(), &'static str> {
+    let ssid = parse_json_string_value(body_in, "\"ssid\"").ok_or_else(|| {
+        write_error_body(body_out, "INVALID_REQUEST", "missing ssid", false, None);
+        "400 Bad Request"
+    })?;
+    let mut networks = read_wifi_networks_for_update(body_out, eeprom).await?;
+    let Some(idx) = networks.iter().position(|n| n.ssid == ssid) else {
+        write_error_body(
+            body_out,
+            "INVALID_REQUEST",
+            "ssid is not stored",
+            false,
+            None,
         );
+        return Err("400 Bad Request");
+    };
+    networks.remove(idx);
+    store_wifi_networks_for_update(body_out, eeprom, &networks).await?;
+    if networks.is_empty() {
+        mark_wifi_clear_pending(wifi_state).await;
     } else {
-        buf.push_str(",\"last_status\":null");
+        reconfigure_after_network_change(wifi_state, &ssid).await;
     }
-    buf.push('}');
-    Ok(())
+    render_wifi_networks_json(body_out, eeprom, wifi_state).await
 }
 
-async fn socket_write_all(
-    socket: &mut TcpSocket<'_>,
-    mut buf: &[u8],
-) -> Result<(), embassy_net::tcp::Error> {
-    while !buf.is_empty() {
-        let written = socket.write(buf).await?;
-        if written == 0 {
-            return Err(embassy_net::tcp::Error::ConnectionReset);
-        }
-        buf = &buf[written..];
+/// `POST /api/v1/wifi/networks/move`: `{ssid, priority}`. A connected
+/// station picks up the new order at its next roaming check.
+pub(crate) async fn handle_wifi_network_move_http(
+    body_in: &str,
+    body_out: &mut String,
+    eeprom: &'static EepromMutex,
+    wifi_state: &'static WifiStateMutex,
+) -> Result<(), &'static str> {
+    let ssid = parse_json_string_value(body_in, "\"ssid\"").ok_or_else(|| {
+        write_error_body(body_out, "INVALID_REQUEST", "missing ssid", false, None);
+        "400 Bad Request"
+    })?;
+    let priority = parse_wifi_priority(body_in, body_out, true)?.unwrap_or(0);
+    let mut networks = read_wifi_networks_for_update(body_out, eeprom).await?;
+    if !move_wifi_network(&mut networks, &ssid, priority) {
+        write_error_body(
+            body_out,
+            "INVALID_REQUEST",
+            "ssid is not stored",
+            false,
+            None,
+        );
+        return Err("400 Bad Request");
     }
-    Ok(())
+    store_wifi_networks_for_update(body_out, eeprom, &networks).await?;
+    if !matches!(
+        wifi_state.lock().await.state,
+        WifiConnectionState::Connected
+    ) {
+        mark_wifi_reconfigure_pending(wifi_state).await;
+    }
+    render_wifi_networks_json(body_out, eeprom, wifi_state).await
 }
 
-async fn write_http_response(
-    socket: &mut TcpSocket<'_>,
-    version: &str,
-    status_line: &str,
-    body: &str,
-    cors_origin: Option<&str>,
-) -> Result<(), embassy_net::tcp::Error> {
-    // Minimal CORS support to allow the LoadLynx web console (running on a
-    // separate origin during development) to access the HTTP API.
-    const CORS_ALLOW_METHODS: &str = "GET, PUT, POST, DELETE, OPTIONS";
-    const CORS_ALLOW_HEADERS: &str = "Content-Type";
-    const CORS_ALLOW_PRIVATE_NETWORK: &str = "true";
-
-    let allow_origin = cors_origin.unwrap_or("*");
-    let vary_origin = if cors_origin.is_some() {
-        "Vary: Origin\r\n"
-    } else {
-        ""
-    };
+fn parse_wifi_priority(
+    body_in: &str,
+    body_out: &mut String,
+    required: bool,
+) -> Result<Option<usize>, &'static str> {
+    match parse_json_i64_optional(body_in, "\"priority\"") {
+        Ok(Some(value)) if (0..eeprom::WIFI_MAX_NETWORKS as i64).contains(&value) => {
+            Ok(Some(value as usize))
+        }
+        Ok(None) if !required => Ok(None),
+        Ok(None) => {
+            write_error_body(body_out, "INVALID_REQUEST", "missing priority", false, None);
+            Err("400 Bad Request")
+        }
+        _ => {
+            write_error_body(
+                body_out,
+                "INVALID_REQUEST",
+                "priority must be 0..5",
+                false,
+                None,
+            );
+            Err("400 Bad Request")
+        }
+    }
+}
 
-    let mut head = String::new();
-    let _ = core::write!(
-        &mut head,
-        "{} {}\r\n\
-         Content-Type: application/json; charset=utf-8\r\n\
-         Access-Control-Allow-Origin: {}\r\n\
-         {}\
-         Access-Control-Allow-Methods: {}\r\n\
-         Access-Control-Allow-Headers: {}\r\n\
-         Access-Control-Allow-Private-Network: {}\r\n\
-         Connection: close\r\n\
-         Content-Length: {}\r\n\
-         \r\n",
-        version,
-        status_line,
-        allow_origin,
-        vary_origin,
-        CORS_ALLOW_METHODS,
-        CORS_ALLOW_HEADERS,
-        CORS_ALLOW_PRIVATE_NETWORK,
-        body.len()
-    );
-    socket_write_all(socket, head.as_bytes()).await?;
-    socket_write_all(socket, body.as_bytes()).await?;
-    Ok(())
+async fn read_wifi_networks_for_update(
+    body_out: &mut String,
+    eeprom: &'static EepromMutex,
+) -> Result<WifiNetworks, &'static str> {
+    read_wifi_networks(eeprom).await.map_err(|_| {
+        write_error_body(body_out, "UNAVAILABLE", "EEPROM read failed", true, None);
+        "503 Service Unavailable"
+    })
 }
 
-async fn write_sse_response_head(
-    socket: &mut TcpSocket<'_>,
-    cors_origin: Option<&str>,
-) -> Result<(), embassy_net::tcp::Error> {
-    const CORS_ALLOW_METHODS: &str = "GET, PUT, POST, DELETE, OPTIONS";
-    const CORS_ALLOW_HEADERS: &str = "Content-Type";
-    const CORS_ALLOW_PRIVATE_NETWORK: &str = "true";
+async fn store_wifi_networks_for_update(
+    body_out: &mut String,
+    eeprom: &'static EepromMutex,
+    networks: &[WifiNetwork],
+) -> Result<(), &'static str> {
+    store_wifi_networks(eeprom, networks).await.map_err(|_| {
+        write_error_body(body_out, "UNAVAILABLE", "EEPROM write failed", true, None);
+        "503 Service Unavailable"
+    })
+}
 
-    let allow_origin = cors_origin.unwrap_or("*");
-    let vary_origin = if cors_origin.is_some() {
-        "Vary: Origin\r\n"
+/// Error body and status for an `upsert_wifi_network` rejection.
+fn write_wifi_network_error(body_out: &mut String, message: &'static str) -> &'static str {
+    if message == WIFI_NETWORKS_FULL {
+        write_error_body(body_out, "INVALID_STATE", message, false, None);
+        "409 Conflict"
     } else {
-        ""
-    };
+        write_error_body(body_out, "INVALID_REQUEST", message, false, None);
+        "400 Bad Request"
+    }
+}
 
-    let mut head = String::new();
-    let _ = core::write!(
-        &mut head,
-        "HTTP/1.1 200 OK\r\n\
-         Content-Type: text/event-stream\r\n\
-         Cache-Control: no-cache\r\n\
-         Access-Control-Allow-Origin: {}\r\n\
-         {}\
-         Access-Control-Allow-Methods: {}\r\n\
-         Access-Control-Allow-Headers: {}\r\n\
-         Access-Control-Allow-Private-Network: {}\r\n\
-         Connection: keep-alive\r\n\
-         \r\n",
-        allow_origin,
-        vary_origin,
-        CORS_ALLOW_METHODS,
-        CORS_ALLOW_HEADERS,
-        CORS_ALLOW_PRIVATE_NETWORK,
-    );
+/// Reconnect only when `ssid` is the network in use or the station is not
+/// connected; otherwise the change waits for the next failover or roam.
+async fn reconfigure_after_network_change(wifi_state: &'static WifiStateMutex, ssid: &str) {
+    let wifi = { *wifi_state.lock().await };
+    let in_use = wifi.active_ssid.is_some_and(|a| a.as_str() == ssid);
+    if in_use || !matches!(wifi.state, WifiConnectionState::Connected) {
+        mark_wifi_reconfigure_pending(wifi_state).await;
+    }
+}
 
-    socket_write_all(socket, head.as_bytes()).await
+/// Device id used as the default MQTT topic prefix suffix.
+async fn mqtt_device_id(wifi_state: &'static WifiStateMutex) -> HString<32> {
+    let mac = { wifi_state.lock().await.mac };
+    match mac.map(derive_device_names) {
+        Some(names) => names.hostname,
+        None => {
+            let mut id = HString::new();
+            let _ = id.push_str("llx-digital-01");
+            id
+        }
+    }
 }
 
-/// Render the JSON body for `GET /api/v1/identity`.
-///
-/// On error this function writes an appropriate ErrorResponse into `buf` and
-/// returns the HTTP status line to use.
-async fn render_identity_json(
+/// Render the JSON body for `GET /api/v1/mqtt`. The password is never echoed.
+pub(crate) async fn render_mqtt_json(
     buf: &mut String,
+    eeprom: &'static EepromMutex,
     wifi_state: &'static WifiStateMutex,
 ) -> Result<(), &'static str> {
-    let wifi = {
-        let guard = wifi_state.lock().await;
-        *guard
-    };
-
-    // If we don't have a usable IPv4 config yet, treat the service as
-    // temporarily unavailable.
-    if !matches!(wifi.state, WifiConnectionState::Connected) || wifi.ipv4.is_none() {
-        write_error_body(buf, "UNAVAILABLE", "Wi-Fi is not connected", true, None);
-        return Err("503 Service Unavailable");
-    }
-
-    let ip = wifi.ipv4.unwrap();
-    let octets = ip.octets();
-    let names = wifi.mac.map(derive_device_names);
-
+    let config = mqtt::read_config(eeprom).await;
+    let status = mqtt::status().await;
+    let default_prefix = mqtt::default_topic_prefix(mqtt_device_id(wifi_state).await.as_str());
     buf.clear();
-    buf.push('{');
-
-    // device_id must be hardware-unique. A configured hostname is human-facing
-    // and may be reused across devices, so it must not become the registry key.
-    buf.push_str("\"device_id\":\"");
-    if let Some(ref names) = names {
-        write_json_string_escaped(buf, names.hostname.as_str());
+    match config {
+        Some(config) => {
+            let _ = core::write!(
+                buf,
+                "{{\"configured\":true,\"enabled\":{},\"commands_enabled\":{},\"host\":\"",
+                config.enabled,
+                config.commands_enabled
+            );
+            write_json_string_escaped(buf, &config.host);
+            let _ = core::write!(buf, "\",\"port\":{},\"username\":\"", config.port);
+            write_json_string_escaped(buf, &config.username);
+            let _ = core::write!(
+                buf,
+                "\",\"password_set\":{},\"topic_prefix\":\"",
+                !config.password.is_empty()
+            );
+            write_json_string_escaped(buf, &config.topic_prefix);
+            let _ = core::write!(
+                buf,
+                "\",\"publish_interval_ms\":{}",
+                config.publish_interval_ms
+            );
+        }
+        None => {
+            buf.push_str(
+                "{\"configured\":false,\"enabled\":false,\"commands_enabled\":false,\"host\":\"\",\"port\":",
+            );
+            let _ = core::write!(
+                buf,
+                "{},\"username\":\"\",\"password_set\":false,\"topic_prefix\":\"",
+                mqtt::MQTT_DEFAULT_PORT
+            );
+            write_json_string_escaped(buf, &default_prefix);
+            let _ = core::write!(
+                buf,
+                "\",\"publish_interval_ms\":{}",
+                mqtt::MQTT_DEFAULT_PUBLISH_INTERVAL_MS
+            );
+        }
+    }
+    buf.push_str(",\"default_topic_prefix\":\"");
+    write_json_string_escaped(buf, &default_prefix);
+    buf.push_str("\",\"state\":\"");
+    buf.push_str(status.state.name());
+    buf.push_str("\",\"last_error\":");
+    if let Some(error) = status.last_error {
+        buf.push('"');
+        write_json_string_escaped(buf, error);
+        buf.push('"');
     } else {
-        write_json_string_escaped(buf, "llx-digital-01");
+        buf.push_str("null");
     }
-    buf.push_str("\",");
+    buf.push('}');
+    Ok(())
+}
 
-    if let Some(ref names) = names {
-        buf.push_str("\"hostname\":\"");
-        write_json_string_escaped(buf, names.hostname_fqdn.as_str());
-        buf.push_str("\",\"short_id\":\"");
-        write_json_string_escaped(buf, names.short_id.as_str());
-        buf.push_str("\",");
+/// Handle `POST /api/v1/mqtt`. Omitted fields keep their stored value (or the
+/// default when nothing is stored); an omitted `password` keeps the stored one.
+pub(crate) async fn handle_mqtt_set_http(
+    body_in: &str,
+    body_out: &mut String,
+    eeprom: &'static EepromMutex,
+    wifi_state: &'static WifiStateMutex,
+) -> Result<(), &'static str> {
+    let stored = mqtt::read_config(eeprom).await;
+    let integer =
+        |key: &str, max: i64, body_out: &mut String| match parse_json_i64_optional(body_in, key) {
+            Ok(Some(value)) if (0..=max).contains(&value) => Ok(Some(value)),
+            Ok(Some(_)) | Err(_) => {
+                write_error_body(
+                    body_out,
+                    "INVALID_REQUEST",
+                    "invalid integer field",
+                    false,
+                    None,
+                );
+                Err("400 Bad Request")
+            }
+            Ok(None) => Ok(None),
+        };
+    let port = integer("\"port\"", u16::MAX as i64, body_out)?
+        .map(|v| v as u16)
+        .or(stored.as_ref().map(|c| c.port))
+        .unwrap_or(mqtt::MQTT_DEFAULT_PORT);
+    let publish_interval_ms = integer("\"publish_interval_ms\"", u16::MAX as i64, body_out)?
+        .map(|v| v as u16)
+        .or(stored.as_ref().map(|c| c.publish_interval_ms))
+        .unwrap_or(mqtt::MQTT_DEFAULT_PUBLISH_INTERVAL_MS);
+    let enabled = parse_json_bool_value(body_in, "\"enabled\"")
+        .or(stored.as_ref().map(|c| c.enabled))
+        .unwrap_or(true);
+    let commands_enabled = parse_json_bool_value(body_in, "\"commands_enabled\"")
+        .or(stored.as_ref().map(|c| c.commands_enabled))
+        .unwrap_or(false);
+    let pick = |key: &str, stored: Option<&String>| {
+        parse_json_string_value(body_in, key).or_else(|| stored.cloned())
+    };
+    let Some(host) = pick("\"host\"", stored.as_ref().map(|c| &c.host)) else {
+        write_error_body(body_out, "INVALID_REQUEST", "missing host", false, None);
+        return Err("400 Bad Request");
+    };
+    let username = pick("\"username\"", stored.as_ref().map(|c| &c.username)).unwrap_or_default();
+    let password = pick("\"password\"", stored.as_ref().map(|c| &c.password)).unwrap_or_default();
+    let topic_prefix = match pick("\"topic_prefix\"", stored.as_ref().map(|c| &c.topic_prefix)) {
+        Some(prefix) if !prefix.is_empty() => prefix,
+        _ => mqtt::default_topic_prefix(mqtt_device_id(wifi_state).await.as_str()),
+    };
+
+    let blob = match eeprom::encode_mqtt_blob(&eeprom::MqttBlobParts {
+        enabled,
+        commands_enabled,
+        host: &host,
+        port,
+        username: &username,
+        password: &password,
+        topic_prefix: &topic_prefix,
+        publish_interval_ms,
+    }) {
+        Ok(blob) => blob,
+        Err(message) => {
+            write_error_body(body_out, "INVALID_REQUEST", message, false, None);
+            return Err("400 Bad Request");
+        }
+    };
+    if eeprom.lock().await.write_mqtt_blob(&blob).await.is_err() {
+        write_error_body(body_out, "UNAVAILABLE", "EEPROM write failed", true, None);
+        return Err("503 Service Unavailable");
     }
+    mqtt::request_reconfigure().await;
+    render_mqtt_json(body_out, eeprom, wifi_state).await
+}
 
-    // digital_fw_version
-    buf.push_str("\"digital_fw_version\":\"");
-    write_json_string_escaped(buf, FW_VERSION);
-    buf.push_str("\",");
+/// Handle `DELETE /api/v1/mqtt`: forget the broker settings and disconnect.
+pub(crate) async fn handle_mqtt_clear_http(
+    body_out: &mut String,
+    eeprom: &'static EepromMutex,
+    wifi_state: &'static WifiStateMutex,
+) -> Result<(), &'static str> {
+    if eeprom.lock().await.clear_mqtt_blob().await.is_err() {
+        write_error_body(body_out, "UNAVAILABLE", "EEPROM clear failed", true, None);
+        return Err("503 Service Unavailable");
+    }
+    mqtt::request_reconfigure().await;
+    render_mqtt_json(body_out, eeprom, wifi_state).await
+}
 
-    // firmware contract used by devd artifact matching and USB/LAN record merge.
-    buf.push_str("\"firmware\":{");
-    buf.push_str("\"target\":\"digital_esp32s3\",");
-    buf.push_str("\"package_version\":\"");
-    write_json_string_escaped(buf, env!("LOADLYNX_PACKAGE_VERSION"));
-    buf.push_str("\",\"build_id\":\"");
-    write_json_string_escaped(buf, FW_VERSION);
-    buf.push_str("\",\"build_profile\":\"");
-    write_json_string_escaped(buf, option_env!("LOADLYNX_FW_PROFILE").unwrap_or("unknown"));
-    buf.push_str("\",\"target_triple\":\"");
-    write_json_string_escaped(buf, option_env!("LOADLYNX_FW_TARGET").unwrap_or("unknown"));
-    buf.push_str("\",\"source_digest\":\"");
-    write_json_string_escaped(
+pub(crate) async fn render_time_json(
+    buf: &mut String,
+    eeprom: &'static EepromMutex,
+) -> Result<(), &'static str> {
+    let clock = wall_clock::get();
+    let server = sntp::read_server(eeprom).await;
+    let status = sntp::status().await;
+    buf.clear();
+    let _ = core::write!(buf, "{{\"synced\":{},\"source\":", clock.is_some());
+    match clock {
+        Some(clock) => {
+            let _ = core::write!(
+                buf,
+                "\"{}\",\"unix_ms\":{},\"last_sync_uptime_ms\":{}",
+                clock.source.name(),
+                wall_clock::unix_ms_now().unwrap_or(0),
+                clock.synced_at_ms
+            );
+        }
+        None => buf.push_str("null,\"unix_ms\":null,\"last_sync_uptime_ms\":null"),
+    }
+    buf.push_str(",\"wall_time\":");
+    wall_clock::write_json_wall_time(buf);
+    let _ = core::write!(
         buf,
-        option_env!("LOADLYNX_FW_SRC_DIGEST").unwrap_or("src unknown"),
+        ",\"uptime_ms\":{},\"ntp\":{{\"server\":\"",
+        timestamp_ms()
     );
-    buf.push_str("\",\"features\":[\"net_http\",\"mdns_dns_sd\",\"usb_cdc_jsonl\"],");
-    buf.push_str("\"protocol\":\"loadlynx.cdc.v1\",");
-    buf.push_str("\"defmt\":{\"enabled\":true,\"encoding\":\"defmt-espflash\"}},");
-
-    buf.push_str("\"usb_bridge\":{");
-    buf.push_str("\"transport\":\"usb_cdc_jsonl\",");
-    buf.push_str("\"protocol\":\"loadlynx.cdc.v1\",");
-    buf.push_str("\"lease_required\":true,");
-    buf.push_str("\"framing\":\"lf_json\"},");
-
-    // analog_fw_version: for now expose the compact HELLO fw_version as
-    // 0xXXXXXXXX when available; otherwise "unknown".
-    buf.push_str("\"analog_fw_version\":\"");
-    let analog_raw = ANALOG_FW_VERSION_RAW.load(Ordering::Relaxed);
-    if analog_raw != 0 {
-        let s = format!("0x{:08x}", analog_raw);
-        write_json_string_escaped(buf, &s);
-    } else {
-        write_json_string_escaped(buf, "unknown");
-    }
-    buf.push_str("\",");
-
-    // protocol_version
-    buf.push_str("\"protocol_version\":");
-    let _ = core::write!(buf, "{}", PROTOCOL_VERSION);
-    buf.push(',');
-
-    // uptime_ms
-    buf.push_str("\"uptime_ms\":");
-    let _ = core::write!(buf, "{}", timestamp_ms());
-    buf.push(',');
-
-    // network block
-    buf.push_str("\"network\":{");
-    // ip
-    buf.push_str("\"ip\":\"");
+    write_json_string_escaped(buf, &server.host);
     let _ = core::write!(
         buf,
-        "{}.{}.{}.{}",
-        octets[0],
-        octets[1],
-        octets[2],
-        octets[3]
+        ":{}\",\"custom\":{},\"state\":\"{}\",\"last_error\":",
+        server.port,
+        server.custom,
+        status.state.name()
     );
-    buf.push_str("\",");
-    // mac
-    buf.push_str("\"mac\":\"");
-    if let Some(ref names) = names {
-        write_json_string_escaped(buf, names.mac_str.as_str());
-    } else {
-        write_json_string_escaped(buf, "unknown");
-    }
-    buf.push_str("\",");
-    // hostname
-    buf.push_str("\"hostname\":\"");
-    if let Some(ref names) = names {
-        write_json_string_escaped(buf, names.hostname_fqdn.as_str());
-    } else if let Some(host) = WIFI_HOSTNAME {
-        write_json_string_escaped(buf, host);
+    if let Some(error) = status.last_error {
+        buf.push('"');
+        write_json_string_escaped(buf, error);
+        buf.push('"');
     } else {
-        write_json_string_escaped(buf, "loadlynx-digital");
+        buf.push_str("null");
     }
-    buf.push_str("\"},");
-
-    // capabilities
-    buf.push_str("\"capabilities\":{");
-    buf.push_str("\"cc_supported\":true,");
-    buf.push_str("\"cv_supported\":true,");
-    buf.push_str("\"cp_supported\":true,");
-    buf.push_str("\"presets_supported\":true,");
-    buf.push_str("\"preset_count\":5,");
-    buf.push_str("\"api_version\":\"2.0.0\"}");
-
-    buf.push('}');
+    buf.push_str("}}");
     Ok(())
 }
 
-/// Render the JSON body for `GET /api/v1/status` (single-shot snapshot).
-async fn render_status_json(
-    buf: &mut String,
-    telemetry: &'static TelemetryMutex,
+/// Handle `POST /api/v1/time`: `unix_ms` sets the wall clock from the host
+/// (for devices without Wi-Fi), `ntp_server` stores the SNTP server
+/// (`host[:port]`; empty restores the default) and triggers a resync.
+pub(crate) async fn handle_time_set_http(
+    body_in: &str,
+    body_out: &mut String,
+    eeprom: &'static EepromMutex,
 ) -> Result<(), &'static str> {
-    render_status_json_inner(buf, telemetry, false).await
+    let unix_ms = match parse_json_i64_optional(body_in, "\"unix_ms\"") {
+        Ok(value) => value,
+        Err(_) => {
+            write_error_body(body_out, "INVALID_REQUEST", "invalid unix_ms", false, None);
+            return Err("400 Bad Request");
+        }
+    };
+    let ntp_server = parse_json_string_value(body_in, "\"ntp_server\"");
+    if unix_ms.is_none() && ntp_server.is_none() {
+        write_error_body(
+            body_out,
+            "INVALID_REQUEST",
+            "expected unix_ms and/or ntp_server",
+            false,
+            None,
+        );
+        return Err("400 Bad Request");
+    }
+    if let Some(unix_ms) = unix_ms
+        && unix_ms < loadlynx_sntp::PLAUSIBLE_UNIX_MS_MIN as i64
+    {
+        write_error_body(
+            body_out,
+            "LIMIT_VIOLATION",
+            "unix_ms must be at or after 2024-01-01T00:00:00Z",
+            false,
+            None,
+        );
+        return Err("422 Unprocessable Entity");
+    }
+
+    if let Some(server) = ntp_server {
+        let result = if server.is_empty() {
+            eeprom.lock().await.clear_ntp_blob().await
+        } else {
+            let Some((host, port)) = sntp::parse_server(&server) else {
+                write_error_body(
+                    body_out,
+                    "INVALID_REQUEST",
+                    "invalid ntp_server port",
+                    false,
+                    None,
+                );
+                return Err("400 Bad Request");
+            };
+            let blob = match eeprom::encode_ntp_blob(&eeprom::NtpBlobParts { host, port }) {
+                Ok(blob) => blob,
+                Err(message) => {
+                    write_error_body(body_out, "INVALID_REQUEST", message, false, None);
+                    return Err("400 Bad Request");
+                }
+            };
+            eeprom.lock().await.write_ntp_blob(&blob).await
+        };
+        if result.is_err() {
+            write_error_body(body_out, "UNAVAILABLE", "EEPROM write failed", true, None);
+            return Err("503 Service Unavailable");
+        }
+        sntp::request_reconfigure().await;
+    }
+    if let Some(unix_ms) = unix_ms {
+        wall_clock::set_unix_ms(unix_ms as u64, wall_clock::WallClockSource::Host);
+        info!("wall clock set by host (unix_ms={})", unix_ms);
+    }
+    render_time_json(body_out, eeprom).await
 }
 
-async fn render_status_json_sse(
+/// Render `GET /api/v1/firmware`: the running OTA slot, its confirmation
+/// state and the last upload.
+pub(crate) async fn render_firmware_json(
     buf: &mut String,
-    telemetry: &'static TelemetryMutex,
+    eeprom: &'static EepromMutex,
 ) -> Result<(), &'static str> {
-    // Allow offline snapshots to keep the SSE stream alive; the consumer can
-    // inspect link_up to decide how to render.
-    render_status_json_inner(buf, telemetry, true).await
+    let status = ota::status(eeprom).await;
+    buf.clear();
+    buf.push_str("{\"version\":\"");
+    write_json_string_escaped(buf, FW_VERSION);
+    buf.push_str("\",\"running_slot\":");
+    write_json_opt_str(buf, status.running_slot);
+    buf.push_str(",\"image_state\":");
+    write_json_opt_str(buf, status.image_state);
+    let _ = core::write!(
+        buf,
+        ",\"boot_attempts\":{},\"max_unconfirmed_boots\":{},\"rolled_back\":{},\"token_set\":{},\"slot_capacity\":",
+        status.boot_attempts,
+        ota::MAX_UNCONFIRMED_BOOTS,
+        status.rolled_back,
+        status.token_set
+    );
+    match status.slot_capacity {
+        Some(capacity) => {
+            let _ = core::write!(buf, "{}", capacity);
+        }
+        None => buf.push_str("null"),
+    }
+    let upload = status.upload;
+    let _ = core::write!(
+        buf,
+        ",\"upload\":{{\"state\":\"{}\",\"received\":{},\"size\":{},\"last_error\":",
+        upload.state.name(),
+        upload.received,
+        upload.size
+    );
+    write_json_opt_str(buf, upload.last_error);
+    buf.push_str("}}");
+    Ok(())
 }
 
-async fn render_status_json_inner(
-    buf: &mut String,
-    telemetry: &'static TelemetryMutex,
-    allow_offline: bool,
-) -> Result<(), &'static str> {
-    // Require the UART link to be up and at least one valid FastStatus frame.
-    let link_up = LINK_UP.load(Ordering::Relaxed);
-    let fast_ok = FAST_STATUS_OK_COUNT.load(Ordering::Relaxed);
-    let last_good = LAST_GOOD_FRAME_MS.load(Ordering::Relaxed);
-    let now = now_ms32();
-    let age_ms = if last_good == 0 {
-        u32::MAX
-    } else {
-        now.wrapping_sub(last_good)
-    };
+fn write_json_opt_str(buf: &mut String, value: Option<&str>) {
+    match value {
+        Some(value) => {
+            buf.push('"');
+            write_json_string_escaped(buf, value);
+            buf.push('"');
+        }
+        None => buf.push_str("null"),
+    }
+}
 
-    if (!link_up || fast_ok == 0) && !allow_offline {
-        let details = format!(r#"{{"last_frame_age_ms":{}}}"#, age_ms);
+/// Handle `POST /api/v1/firmware`: stream the raw app image in the body into
+/// the inactive OTA slot (`ota.rs`) and reboot into it. Requires the OTA
+/// bearer token, `X-LoadLynx-Image-SHA256` from the firmware catalog, and the
+/// output to be off, since flash erases stall the control tasks.
+#[allow(clippy::too_many_arguments)]
+async fn handle_firmware_upload(
+    socket: &mut TcpSocket<'_>,
+    version: &str,
+    buf: &mut [u8],
+    early_body: core::ops::Range<usize>,
+    content_length: Option<usize>,
+    authorization: Option<&str>,
+    image_sha256: Option<&str>,
+    eeprom: &'static EepromMutex,
+    control: &'static ControlMutex,
+    cors_origin: Option<&str>,
+) -> Result<(), embassy_net::tcp::Error> {
+    let mut body = String::new();
+    let authorized = match authorization.and_then(|value| value.strip_prefix("Bearer ")) {
+        Some(token) => ota::check_token(eeprom, token.trim()).await,
+        None => false,
+    };
+    if !authorized {
         write_error_body(
-            buf,
-            "LINK_DOWN",
-            "UART link is down or no FastStatus frames received",
-            true,
-            Some(&details),
+            &mut body,
+            "UNAUTHORIZED",
+            "firmware upload requires the OTA bearer token (USB get_ota_token)",
+            false,
+            None,
         );
-        return Err("503 Service Unavailable");
+        write_http_response(socket, version, "401 Unauthorized", &body, cors_origin).await?;
+        return Ok(());
     }
-
-    let (status, analog_state, ui_reason, ui_reason_blink) = {
-        let guard = telemetry.lock().await;
-        let status = guard.last_status.unwrap_or(FastStatus::default());
-        let analog_state = AnalogState::from_u8(crate::ANALOG_STATE.load(Ordering::Relaxed));
-        (
-            status,
-            analog_state,
-            guard.snapshot.status_lines[4].clone(),
-            guard.snapshot.blink_on,
-        )
-    };
-
-    let hello_seen = HELLO_SEEN.load(Ordering::Relaxed);
-
-    buf.clear();
-    buf.push('{');
-
-    // "status": { ... FastStatusJson ... }
-    buf.push_str("\"status\":");
-    write_fast_status_json(buf, &status);
-    buf.push(',');
-
-    // link_up / hello_seen
-    buf.push_str("\"link_up\":");
-    buf.push_str(if link_up { "true" } else { "false" });
-    buf.push_str(",\"hello_seen\":");
-    buf.push_str(if hello_seen { "true" } else { "false" });
-
-    // analog_state
-    buf.push_str(",\"analog_state\":\"");
-    let analog_state_str = match analog_state {
-        AnalogState::Offline => "offline",
-        AnalogState::CalMissing => "cal_missing",
-        AnalogState::Faulted => "faulted",
-        AnalogState::Ready => "ready",
-        AnalogState::MeasurementInvalid => "measurement_invalid",
+    let (Some(content_length), Some(expected_sha256)) =
+        (content_length, image_sha256.and_then(ota::parse_hex::<32>))
+    else {
+        write_error_body(
+            &mut body,
+            "INVALID_REQUEST",
+            "firmware upload requires Content-Length and X-LoadLynx-Image-SHA256",
+            false,
+            None,
+        );
+        write_http_response(socket, version, "400 Bad Request", &body, cors_origin).await?;
+        return Ok(());
     };
-    write_json_string_escaped(buf, analog_state_str);
-    buf.push_str("\",");
-
-    // ui_reason (status line #5) + blink phase (debug aid for UX validation).
-    buf.push_str("\"ui_reason\":\"");
-    write_json_string_escaped(buf, ui_reason.as_str());
-    buf.push_str("\",\"ui_reason_blink\":");
-    buf.push_str(if ui_reason_blink { "true" } else { "false" });
-    buf.push(',');
+    if control.lock().await.output_enabled {
+        write_error_body(
+            &mut body,
+            "INVALID_STATE",
+            "disable the output before a firmware update",
+            false,
+            None,
+        );
+        write_http_response(socket, version, "409 Conflict", &body, cors_origin).await?;
+        return Ok(());
+    }
 
-    // fault_flags_decoded
-    buf.push_str("\"fault_flags_decoded\":[");
-    let mut first = true;
-    let faults = status.fault_flags;
-    if faults & FAULT_OVERCURRENT != 0 {
-        if !first {
-            buf.push(',');
+    let mut upload = match ota::Upload::begin(content_length as u32, expected_sha256).await {
+        Ok(upload) => upload,
+        Err(err) => {
+            return write_firmware_upload_error(socket, version, err, cors_origin).await;
         }
-        buf.push('"');
-        write_json_string_escaped(buf, "OVERCURRENT");
-        buf.push('"');
-        first = false;
-    }
-    if faults & FAULT_OVERVOLTAGE != 0 {
-        if !first {
-            buf.push(',');
+    };
+    let mut received = 0usize;
+    let mut pending = early_body;
+    loop {
+        let end = pending.start + pending.len().min(content_length - received);
+        if end > pending.start {
+            if let Err(err) = upload.write(&buf[pending.start..end]).await {
+                upload.fail(err.message());
+                return write_firmware_upload_error(socket, version, err, cors_origin).await;
+            }
+            received += end - pending.start;
         }
-        buf.push('"');
-        write_json_string_escaped(buf, "OVERVOLTAGE");
-        buf.push('"');
-        first = false;
-    }
-    if faults & FAULT_MCU_OVER_TEMP != 0 {
-        if !first {
-            buf.push(',');
+        if received >= content_length {
+            break;
         }
-        buf.push('"');
-        write_json_string_escaped(buf, "MCU_OVER_TEMP");
-        buf.push('"');
-        first = false;
-    }
-    if faults & FAULT_SINK_OVER_TEMP != 0 {
-        if !first {
-            buf.push(',');
+        let n = socket.read(buf).await?;
+        if n == 0 {
+            upload.fail("truncated firmware image");
+            write_error_body(
+                &mut body,
+                "INVALID_REQUEST",
+                "truncated firmware image",
+                false,
+                None,
+            );
+            write_http_response(socket, version, "400 Bad Request", &body, cors_origin).await?;
+            return Ok(());
         }
-        buf.push('"');
-        write_json_string_escaped(buf, "SINK_OVER_TEMP");
-        buf.push('"');
+        pending = 0..n;
     }
-    buf.push(']');
 
-    // state_flags_decoded
-    buf.push_str(",\"state_flags_decoded\":[");
-    let mut first = true;
-    let flags = status.state_flags;
-    if flags & STATE_FLAG_REMOTE_ACTIVE != 0 {
-        if !first {
-            buf.push(',');
-        }
-        buf.push('"');
-        write_json_string_escaped(buf, "REMOTE_ACTIVE");
-        buf.push('"');
-        first = false;
-    }
-    if flags & STATE_FLAG_LINK_GOOD != 0 {
-        if !first {
-            buf.push(',');
-        }
-        buf.push('"');
-        write_json_string_escaped(buf, "LINK_GOOD");
-        buf.push('"');
-        first = false;
-    }
-    if flags & STATE_FLAG_ENABLED != 0 {
-        if !first {
-            buf.push(',');
-        }
-        buf.push('"');
-        write_json_string_escaped(buf, "ENABLED");
-        buf.push('"');
-        first = false;
-    }
-    if flags & STATE_FLAG_UV_LATCHED != 0 {
-        if !first {
-            buf.push(',');
+    match upload.finish(eeprom).await {
+        Ok(slot) => {
+            let _ = core::write!(
+                body,
+                "{{\"ok\":true,\"slot\":\"{}\",\"size\":{},\"rebooting\":true}}",
+                slot,
+                content_length
+            );
+            write_http_response(socket, version, "200 OK", &body, cors_origin).await?;
+            socket.flush().await?;
+            ota::reboot_into_update().await
         }
-        buf.push('"');
-        write_json_string_escaped(buf, "UV_LATCHED");
-        buf.push('"');
-        first = false;
-    }
-    if flags & STATE_FLAG_POWER_LIMITED != 0 {
-        if !first {
-            buf.push(',');
+        Err(err) => {
+            upload.fail(err.message());
+            write_firmware_upload_error(socket, version, err, cors_origin).await
         }
-        buf.push('"');
-        write_json_string_escaped(buf, "POWER_LIMITED");
-        buf.push('"');
-        first = false;
     }
-    if flags & STATE_FLAG_CURRENT_LIMITED != 0 {
-        if !first {
-            buf.push(',');
+}
+
+async fn write_firmware_upload_error(
+    socket: &mut TcpSocket<'_>,
+    version: &str,
+    err: ota::UploadError,
+    cors_origin: Option<&str>,
+) -> Result<(), embassy_net::tcp::Error> {
+    let (status, code, retryable) = match err {
+        ota::UploadError::Busy => ("409 Conflict", "INVALID_STATE", true),
+        ota::UploadError::Unavailable(_) | ota::UploadError::Flash(_) => {
+            ("503 Service Unavailable", "UNAVAILABLE", true)
         }
-        buf.push('"');
-        write_json_string_escaped(buf, "CURRENT_LIMITED");
-        buf.push('"');
-    }
-    buf.push(']');
+        ota::UploadError::TooLarge => ("413 Payload Too Large", "LIMIT_VIOLATION", false),
+        ota::UploadError::DigestMismatch => ("422 Unprocessable Entity", "INVALID_REQUEST", false),
+    };
+    let mut body = String::new();
+    write_error_body(&mut body, code, err.message(), retryable, None);
+    write_http_response(socket, version, status, &body, cors_origin).await
+}
 
+/// Render `GET /api/v1/analog/firmware`: progress of the last analog STM32
+/// update (`analog_flash.rs`).
+pub(crate) fn render_analog_firmware_json(buf: &mut String) {
+    let status = analog_flash::status();
+    buf.clear();
+    let _ = core::write!(
+        buf,
+        "{{\"state\":\"{}\",\"received\":{},\"size\":{},\"max_size\":{},\"last_error\":",
+        status.state.name(),
+        status.received,
+        status.size,
+        loadlynx_stm32_boot::G431_FLASH_LEN
+    );
+    write_json_opt_str(buf, status.last_error);
     buf.push('}');
-    Ok(())
 }
 
-async fn handle_status_sse(
+/// Error body for an analog update step; returns the HTTP status.
+pub(crate) fn write_analog_flash_error(
+    buf: &mut String,
+    err: analog_flash::FlashError,
+) -> &'static str {
+    let (status, code, retryable) = match err {
+        analog_flash::FlashError::Busy => ("409 Conflict", "INVALID_STATE", true),
+        analog_flash::FlashError::NoSession => ("409 Conflict", "INVALID_STATE", false),
+        analog_flash::FlashError::Unavailable(_) => {
+            ("503 Service Unavailable", "UNAVAILABLE", true)
+        }
+        analog_flash::FlashError::Bootloader(_) => ("503 Service Unavailable", "LINK_DOWN", true),
+        analog_flash::FlashError::BadImageSize => {
+            ("413 Payload Too Large", "LIMIT_VIOLATION", false)
+        }
+        analog_flash::FlashError::OutOfOrder => ("400 Bad Request", "INVALID_REQUEST", false),
+        analog_flash::FlashError::DigestMismatch => {
+            ("422 Unprocessable Entity", "INVALID_REQUEST", false)
+        }
+    };
+    write_error_body(buf, code, err.message(), retryable, None);
+    status
+}
+
+/// Handle `POST /api/v1/analog/firmware`: stream a raw analog image (`.bin`
+/// for 0x0800_0000) through the STM32 ROM bootloader bridge. Same gates as
+/// the digital upload: OTA bearer token, `X-LoadLynx-Image-SHA256`, output
+/// off.
+#[allow(clippy::too_many_arguments)]
+async fn handle_analog_firmware_upload(
     socket: &mut TcpSocket<'_>,
-    telemetry: &'static TelemetryMutex,
+    version: &str,
+    buf: &mut [u8],
+    early_body: core::ops::Range<usize>,
+    content_length: Option<usize>,
+    authorization: Option<&str>,
+    image_sha256: Option<&str>,
+    eeprom: &'static EepromMutex,
+    control: &'static ControlMutex,
     cors_origin: Option<&str>,
 ) -> Result<(), embassy_net::tcp::Error> {
-    // Conservative rate to keep CPU/stack usage low while still improving
-    // smoothness over 400 ms polling.
-    const SSE_INTERVAL_MS: u64 = 200;
-
-    if STATUS_SSE_ACTIVE
-        .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
-        .is_err()
-    {
-        let mut body = String::new();
+    let mut body = String::new();
+    let authorized = match authorization.and_then(|value| value.strip_prefix("Bearer ")) {
+        Some(token) => ota::check_token(eeprom, token.trim()).await,
+        None => false,
+    };
+    if !authorized {
         write_error_body(
             &mut body,
-            "UNAVAILABLE",
-            "status SSE stream already active",
-            true,
+            "UNAUTHORIZED",
+            "firmware upload requires the OTA bearer token (USB get_ota_token)",
+            false,
             None,
         );
-        write_http_response(
-            socket,
-            "HTTP/1.1",
-            "503 Service Unavailable",
-            &body,
-            cors_origin,
-        )
-        .await?;
+        write_http_response(socket, version, "401 Unauthorized", &body, cors_origin).await?;
         return Ok(());
     }
-
-    struct ClearSseActive;
-    impl Drop for ClearSseActive {
-        fn drop(&mut self) {
-            STATUS_SSE_ACTIVE.store(false, Ordering::Release);
-        }
-    }
-    let _guard = ClearSseActive;
-
-    write_sse_response_head(socket, cors_origin).await?;
-
-    let mut json_body = String::new();
-    let mut frame = String::new();
-
-    loop {
-        match render_status_json_sse(&mut json_body, telemetry).await {
-            Ok(()) => {
-                frame.clear();
-                frame.push_str("event: status\r\n");
-                frame.push_str("data: ");
-                frame.push_str(&json_body);
-                frame.push_str("\r\n\r\n");
-                socket_write_all(socket, frame.as_bytes()).await?;
-                socket.flush().await?;
-            }
-            Err(err_status) => {
-                frame.clear();
-                frame.push_str("event: error\r\n");
-                frame.push_str("data: \"");
-                write_json_string_escaped(&mut frame, err_status);
-                frame.push_str("\"\r\n\r\n");
-                socket_write_all(socket, frame.as_bytes()).await?;
-                socket.flush().await?;
-                return Ok(());
-            }
-        }
-
-        Timer::after(Duration::from_millis(SSE_INTERVAL_MS)).await;
+    let (Some(content_length), Some(expected_sha256)) =
+        (content_length, image_sha256.and_then(ota::parse_hex::<32>))
+    else {
+        write_error_body(
+            &mut body,
+            "INVALID_REQUEST",
+            "firmware upload requires Content-Length and X-LoadLynx-Image-SHA256",
+            false,
+            None,
+        );
+        write_http_response(socket, version, "400 Bad Request", &body, cors_origin).await?;
+        return Ok(());
+    };
+    if control.lock().await.output_enabled {
+        write_error_body(
+            &mut body,
+            "INVALID_STATE",
+            "disable the output before a firmware update",
+            false,
+            None,
+        );
+        write_http_response(socket, version, "409 Conflict", &body, cors_origin).await?;
+        return Ok(());
     }
-}
-
-fn write_fast_status_json(buf: &mut String, status: &FastStatus) {
-    buf.push('{');
-    let _ = core::write!(buf, "\"uptime_ms\":{}", status.uptime_ms);
-    let _ = core::write!(buf, ",\"mode\":{}", status.mode);
-    let _ = core::write!(buf, ",\"state_flags\":{}", status.state_flags);
-    let _ = core::write!(
-        buf,
-        ",\"enable\":{}",
-        if status.enable { "true" } else { "false" }
-    );
-    let _ = core::write!(buf, ",\"target_value\":{}", status.target_value);
-    let _ = core::write!(buf, ",\"i_local_ma\":{}", status.i_local_ma);
-    let _ = core::write!(buf, ",\"i_remote_ma\":{}", status.i_remote_ma);
-    let _ = core::write!(buf, ",\"v_local_mv\":{}", status.v_local_mv);
-    let _ = core::write!(buf, ",\"v_remote_mv\":{}", status.v_remote_mv);
-    let _ = core::write!(buf, ",\"calc_p_mw\":{}", status.calc_p_mw);
-    let _ = core::write!(buf, ",\"dac_headroom_mv\":{}", status.dac_headroom_mv);
-    let _ = core::write!(buf, ",\"loop_error\":{}", status.loop_error);
-    let _ = core::write!(buf, ",\"sink_core_temp_mc\":{}", status.sink_core_temp_mc);
-    let _ = core::write!(
-        buf,
-        ",\"sink_exhaust_temp_mc\":{}",
-        status.sink_exhaust_temp_mc
-    );
-    let _ = core::write!(buf, ",\"mcu_temp_mc\":{}", status.mcu_temp_mc);
-    let _ = core::write!(buf, ",\"fault_flags\":{}", status.fault_flags);
 
-    if let Some(v) = status.cal_kind {
-        let _ = core::write!(buf, ",\"cal_kind\":{}", v);
-    }
-    if let Some(v) = status.raw_v_nr_100uv {
-        let _ = core::write!(buf, ",\"raw_v_nr_100uv\":{}", v);
+    if let Err(err) = analog_flash::begin(content_length as u32, expected_sha256).await {
+        let status = write_analog_flash_error(&mut body, err);
+        return write_http_response(socket, version, status, &body, cors_origin).await;
     }
-    if let Some(v) = status.raw_v_rmt_100uv {
-        let _ = core::write!(buf, ",\"raw_v_rmt_100uv\":{}", v);
-    }
-    if let Some(v) = status.raw_cur_100uv {
-        let _ = core::write!(buf, ",\"raw_cur_100uv\":{}", v);
-    }
-    if let Some(v) = status.raw_dac_code {
-        let _ = core::write!(buf, ",\"raw_dac_code\":{}", v);
+    let mut received = 0usize;
+    let mut pending = early_body;
+    loop {
+        let end = pending.start + pending.len().min(content_length - received);
+        if end > pending.start {
+            if let Err(err) = analog_flash::write(received as u32, &buf[pending.start..end]).await {
+                let status = write_analog_flash_error(&mut body, err);
+                return write_http_response(socket, version, status, &body, cors_origin).await;
+            }
+            received += end - pending.start;
+        }
+        if received >= content_length {
+            break;
+        }
+        // The bridge closes itself after `BRIDGE_IDLE_TIMEOUT` if the client
+        // stalls, so a dropped connection does not leave the link down.
+        let n = socket.read(buf).await?;
+        if n == 0 {
+            analog_flash::cancel("truncated firmware image").await;
+            write_error_body(
+                &mut body,
+                "INVALID_REQUEST",
+                "truncated firmware image",
+                false,
+                None,
+            );
+            write_http_response(socket, version, "400 Bad Request", &body, cors_origin).await?;
+            return Ok(());
+        }
+        pending = 0..n;
     }
-    buf.push('}');
-}
-
-// ---- Presets / Control (v1 frozen) -----------------------------------------
 
-fn mode_to_json_str(mode: LoadMode) -> &'static str {
-    match mode {
-        LoadMode::Cc => "cc",
-        LoadMode::Cv => "cv",
-        LoadMode::Cp => "cp",
-        LoadMode::Reserved(_) => "cc",
+    match analog_flash::finish().await {
+        Ok(()) => {
+            render_analog_firmware_json(&mut body);
+            write_http_response(socket, version, "200 OK", &body, cors_origin).await
+        }
+        Err(err) => {
+            let status = write_analog_flash_error(&mut body, err);
+            write_http_response(socket, version, status, &body, cors_origin).await
+        }
     }
 }
 
-fn write_preset_json(buf: &mut String, preset: &control::Preset) {
-    buf.push('{');
-    let _ = core::write!(buf, "\"preset_id\":{}", preset.preset_id);
-    buf.push_str(",\"mode\":\"");
-    write_json_string_escaped(buf, mode_to_json_str(preset.mode));
-    buf.push('"');
-    let _ = core::write!(buf, ",\"target_p_mw\":{}", preset.target_p_mw);
-    let _ = core::write!(buf, ",\"target_i_ma\":{}", preset.target_i_ma);
-    let _ = core::write!(buf, ",\"target_v_mv\":{}", preset.target_v_mv);
-    let _ = core::write!(buf, ",\"min_v_mv\":{}", preset.min_v_mv);
-    let _ = core::write!(buf, ",\"max_i_ma_total\":{}", preset.max_i_ma_total);
-    let _ = core::write!(buf, ",\"max_p_mw\":{}", preset.max_p_mw);
-    buf.push('}');
-}
-
-pub(crate) async fn render_presets_json(
-    buf: &mut String,
-    control: &'static ControlMutex,
+/// `POST /api/v1/auth/pair` `{code, label?}`: trade the code shown on the
+/// dashboard for a token with the role picked on the device screen. `label`
+/// defaults to `web`.
+async fn handle_auth_pair(
+    body_str: &str,
+    out: &mut String,
+    eeprom: &'static EepromMutex,
 ) -> Result<(), &'static str> {
-    let presets = {
-        let guard = control.lock().await;
-        guard.presets
+    let code = parse_json_string_value(body_str, "\"code\"")
+        .filter(|code| code.len() == 6 && code.bytes().all(|b| b.is_ascii_digit()))
+        .and_then(|code| code.parse::<u32>().ok());
+    let Some(code) = code else {
+        write_error_body(
+            out,
+            "INVALID_REQUEST",
+            "code must be the six digits shown on the device",
+            false,
+            None,
+        );
+        return Err("400 Bad Request");
     };
-
-    buf.clear();
-    buf.push('{');
-    buf.push_str("\"presets\":[");
-    for (idx, p) in presets.iter().enumerate() {
-        if idx != 0 {
-            buf.push(',');
+    let label = parse_json_string_value(body_str, "\"label\"").unwrap_or_else(|| "web".into());
+    match auth::pair(eeprom, code, &label).await {
+        Ok((token, role)) => {
+            write_issued_token_json(out, &token, role, &label);
+            Ok(())
         }
-        write_preset_json(buf, p);
+        Err(err) => Err(write_auth_error(out, err)),
     }
-    buf.push_str("]}");
-    Ok(())
 }
 
-pub(crate) async fn render_control_view_json(
-    buf: &mut String,
-    control: &'static ControlMutex,
-    calibration: &'static CalibrationMutex,
-    telemetry: &'static TelemetryMutex,
+async fn handle_auth_revoke(
+    body_str: &str,
+    out: &mut String,
+    eeprom: &'static EepromMutex,
 ) -> Result<(), &'static str> {
-    let cal_mode = { calibration.lock().await.cal_mode };
-    let (active_preset_id, output_enabled, preset) = {
-        let guard = control.lock().await;
-        let effective = guard.effective_output_command(cal_mode);
-        (
-            guard.active_preset_id,
-            effective.output_enabled,
-            effective.preset,
-        )
+    let Some(label) = parse_json_string_value(body_str, "\"label\"") else {
+        write_error_body(out, "INVALID_REQUEST", "label is required", false, None);
+        return Err("400 Bad Request");
+    };
+    let result = match auth::revoke(eeprom, &label).await {
+        Ok(()) => auth::render_tokens_json(out, eeprom).await,
+        Err(err) => Err(err),
     };
+    result.map_err(|err| write_auth_error(out, err))
+}
 
-    let uv_latched = {
-        let guard = telemetry.lock().await;
-        guard
-            .last_status
-            .map(|s| (s.state_flags & STATE_FLAG_UV_LATCHED) != 0)
-            .unwrap_or(false)
+pub(crate) fn write_issued_token_json(
+    out: &mut String,
+    token: &str,
+    role: auth::Role,
+    label: &str,
+) {
+    out.clear();
+    out.push_str("{\"token\":\"");
+    out.push_str(token);
+    out.push_str("\",\"role\":\"");
+    out.push_str(role.name());
+    out.push_str("\",\"label\":\"");
+    write_json_string_escaped(out, label);
+    out.push_str("\"}");
+}
+
+/// Encode an auth error body and return the HTTP status line.
+pub(crate) fn write_auth_error(out: &mut String, err: auth::AuthError) -> &'static str {
+    let (code, status, retryable) = match err {
+        auth::AuthError::NotPairing => ("INVALID_STATE", "409 Conflict", false),
+        auth::AuthError::NotConfirmed => ("INVALID_STATE", "409 Conflict", true),
+        auth::AuthError::BadCode => ("UNAUTHORIZED", "401 Unauthorized", false),
+        auth::AuthError::LockedOut { retry_after_s } => {
+            let details = format!(r#"{{"retry_after_s":{}}}"#, retry_after_s);
+            write_error_body(out, "RATE_LIMITED", err.message(), true, Some(&details));
+            return "429 Too Many Requests";
+        }
+        auth::AuthError::Full => ("LIMIT_VIOLATION", "409 Conflict", false),
+        auth::AuthError::UnknownLabel => ("UNSUPPORTED_OPERATION", "404 Not Found", false),
+        auth::AuthError::InvalidLabel(_) => ("INVALID_REQUEST", "400 Bad Request", false),
+        auth::AuthError::Storage => ("UNAVAILABLE", "503 Service Unavailable", true),
     };
+    write_error_body(out, code, err.message(), retryable, None);
+    status
+}
 
+pub(crate) async fn render_diagnostics_json(
+    buf: &mut String,
+    eeprom: &'static EepromMutex,
+    wifi_state: &'static WifiStateMutex,
+    telemetry: &'static TelemetryMutex,
+    calibration: &'static CalibrationMutex,
+) -> Result<(), &'static str> {
+    let (ssid, source, network_count) = read_wifi_status_identity(eeprom, wifi_state).await;
+    let wifi = { *wifi_state.lock().await };
+    let status = telemetry.lock().await.last_status;
     buf.clear();
-    buf.push('{');
-    let _ = core::write!(buf, "\"active_preset_id\":{}", active_preset_id);
-    buf.push_str(",\"output_enabled\":");
-    buf.push_str(if output_enabled { "true" } else { "false" });
-    buf.push_str(",\"uv_latched\":");
-    buf.push_str(if uv_latched { "true" } else { "false" });
-    buf.push_str(",\"preset\":");
-    write_preset_json(buf, &preset);
+    buf.push_str("{\"schema_version\":1,\"redaction\":{\"psk\":true},\"firmware_version\":\"");
+    write_json_string_escaped(buf, FW_VERSION);
+    buf.push_str("\",\"wifi\":{\"ssid\":\"");
+    write_json_string_escaped(buf, &ssid);
+    buf.push_str("\",\"source\":\"");
+    let _ = core::write!(
+        buf,
+        "{}\",\"network_count\":{},\"psk\":\"<redacted>\"",
+        source,
+        network_count
+    );
+    append_wifi_status_fields(buf, wifi);
+    buf.push_str(",\"link_up\":");
+    buf.push_str(if LINK_UP.load(Ordering::Relaxed) {
+        "true"
+    } else {
+        "false"
+    });
+    buf.push_str(",\"wall_time\":");
+    wall_clock::write_json_wall_time(buf);
+    let _ = core::write!(buf, ",\"uptime_ms\":{}", timestamp_ms());
+    let calibration_persistence_status = { calibration.lock().await.persistence_status };
+    buf.push_str(",\"calibration_persistence\":{\"status\":\"");
+    buf.push_str(calibration_persistence_status.as_str());
+    buf.push_str("\"}");
+    if let Some(status) = status {
+        let _ = core::write!(
+            buf,
+            ",\"last_status\":{{\"uptime_ms\":{},\"fault_flags\":{}}}",
+            status.uptime_ms,
+            status.fault_flags
+        );
+    } else {
+        buf.push_str(",\"last_status\":null");
+    }
     buf.push('}');
     Ok(())
 }
 
-fn parse_json_bool(body: &str, key: &str) -> Result<bool, &'static str> {
-    let idx = body.find(key).ok_or("missing field")?;
-    let colon = body[idx..].find(':').ok_or("missing ':'")?;
-    let value_str = body[idx + colon + 1..].trim_start();
-    if value_str.starts_with("true") {
-        Ok(true)
-    } else if value_str.starts_with("false") {
-        Ok(false)
-    } else {
-        Err("expected boolean")
+pub(crate) async fn socket_write_all(
+    socket: &mut TcpSocket<'_>,
+    mut buf: &[u8],
+) -> Result<(), embassy_net::tcp::Error> {
+    while !buf.is_empty() {
+        let written = socket.write(buf).await?;
+        if written == 0 {
+            return Err(embassy_net::tcp::Error::ConnectionReset);
+        }
+        buf = &buf[written..];
     }
+    Ok(())
 }
 
-fn parse_json_i64(body: &str, key: &str) -> Result<i64, &'static str> {
-    let idx = body.find(key).ok_or("missing field")?;
-    let colon = body[idx..].find(':').ok_or("missing ':'")?;
-    let mut value_str = body[idx + colon + 1..].trim_start();
-    let mut end = 0usize;
-    for ch in value_str.chars() {
-        if ch == '-' || ch.is_ascii_digit() {
-            end += ch.len_utf8();
-        } else {
-            break;
-        }
-    }
-    value_str = &value_str[..end];
+pub(crate) async fn write_http_response(
+    socket: &mut TcpSocket<'_>,
+    version: &str,
+    status_line: &str,
+    body: &str,
+    cors_origin: Option<&str>,
+) -> Result<(), embassy_net::tcp::Error> {
+    // Minimal CORS support to allow the LoadLynx web console (running on a
+    // separate origin during development) to access the HTTP API.
+    const CORS_ALLOW_METHODS: &str = "GET, PUT, POST, DELETE, OPTIONS";
+    const CORS_ALLOW_HEADERS: &str = "Content-Type, Authorization";
+    const CORS_ALLOW_PRIVATE_NETWORK: &str = "true";
+
+    let allow_origin = cors_origin.unwrap_or("*");
+    let vary_origin = if cors_origin.is_some() {
+        "Vary: Origin\r\n"
+    } else {
+        ""
+    };
+
+    let mut head = String::new();
+    let _ = core::write!(
+        &mut head,
+        "{} {}\r\n\
+         Content-Type: application/json; charset=utf-8\r\n\
+         Access-Control-Allow-Origin: {}\r\n\
+         {}\
+         Access-Control-Allow-Methods: {}\r\n\
+         Access-Control-Allow-Headers: {}\r\n\
+         Access-Control-Allow-Private-Network: {}\r\n\
+         Connection: close\r\n\
+         Content-Length: {}\r\n\
+         \r\n",
+        version,
+        status_line,
+        allow_origin,
+        vary_origin,
+        CORS_ALLOW_METHODS,
+        CORS_ALLOW_HEADERS,
+        CORS_ALLOW_PRIVATE_NETWORK,
+        body.len()
+    );
+    socket_write_all(socket, head.as_bytes()).await?;
+    socket_write_all(socket, body.as_bytes()).await?;
+    Ok(())
+}
+
+async fn write_sse_response_head(
+    socket: &mut TcpSocket<'_>,
+    cors_origin: Option<&str>,
+) -> Result<(), embassy_net::tcp::Error> {
+    const CORS_ALLOW_METHODS: &str = "GET, PUT, POST, DELETE, OPTIONS";
+    const CORS_ALLOW_HEADERS: &str = "Content-Type, Authorization";
+    const CORS_ALLOW_PRIVATE_NETWORK: &str = "true";
+
+    let allow_origin = cors_origin.unwrap_or("*");
+    let vary_origin = if cors_origin.is_some() {
+        "Vary: Origin\r\n"
+    } else {
+        ""
+    };
+
+    let mut head = String::new();
+    let _ = core::write!(
+        &mut head,
+        "HTTP/1.1 200 OK\r\n\
+         Content-Type: text/event-stream\r\n\
+         Cache-Control: no-cache\r\n\
+         Access-Control-Allow-Origin: {}\r\n\
+         {}\
+         Access-Control-Allow-Methods: {}\r\n\
+         Access-Control-Allow-Headers: {}\r\n\
+         Access-Control-Allow-Private-Network: {}\r\n\
+         Connection: keep-alive\r\n\
+         \r\n",
+        allow_origin,
+        vary_origin,
+        CORS_ALLOW_METHODS,
+        CORS_ALLOW_HEADERS,
+        CORS_ALLOW_PRIVATE_NETWORK,
+    );
+
+    socket_write_all(socket, head.as_bytes()).await
+}
+
+/// Render the JSON body for `GET /api/v1/identity`.
+///
+/// On error this function writes an appropriate ErrorResponse into `buf` and
+/// returns the HTTP status line to use.
+async fn render_identity_json(
+    buf: &mut String,
+    wifi_state: &'static WifiStateMutex,
+) -> Result<(), &'static str> {
+    let wifi = {
+        let guard = wifi_state.lock().await;
+        *guard
+    };
+
+    // If we don't have a usable IPv4 config yet, treat the service as
+    // temporarily unavailable.
+    if !matches!(wifi.state, WifiConnectionState::Connected) || wifi.ipv4.is_none() {
+        write_error_body(buf, "UNAVAILABLE", "Wi-Fi is not connected", true, None);
+        return Err("503 Service Unavailable");
+    }
+
+    let ip = wifi.ipv4.unwrap();
+    let octets = ip.octets();
+    let names = wifi.mac.map(derive_device_names);
+
+    buf.clear();
+    buf.push('{');
+
+    // device_id must be hardware-unique. A configured hostname is human-facing
+    // and may be reused across devices, so it must not become the registry key.
+    buf.push_str("\"device_id\":\"");
+    if let Some(ref names) = names {
+        write_json_string_escaped(buf, names.hostname.as_str());
+    } else {
+        write_json_string_escaped(buf, "llx-digital-01");
+    }
+    buf.push_str("\",");
+
+    if let Some(ref names) = names {
+        buf.push_str("\"hostname\":\"");
+        write_json_string_escaped(buf, names.hostname_fqdn.as_str());
+        buf.push_str("\",\"short_id\":\"");
+        write_json_string_escaped(buf, names.short_id.as_str());
+        buf.push_str("\",");
+    }
+
+    // digital_fw_version
+    buf.push_str("\"digital_fw_version\":\"");
+    write_json_string_escaped(buf, FW_VERSION);
+    buf.push_str("\",");
+
+    // firmware contract used by devd artifact matching and USB/LAN record merge.
+    buf.push_str("\"firmware\":{");
+    buf.push_str("\"target\":\"digital_esp32s3\",");
+    buf.push_str("\"package_version\":\"");
+    write_json_string_escaped(buf, env!("LOADLYNX_PACKAGE_VERSION"));
+    buf.push_str("\",\"build_id\":\"");
+    write_json_string_escaped(buf, FW_VERSION);
+    buf.push_str("\",\"build_profile\":\"");
+    write_json_string_escaped(buf, option_env!("LOADLYNX_FW_PROFILE").unwrap_or("unknown"));
+    buf.push_str("\",\"target_triple\":\"");
+    write_json_string_escaped(buf, option_env!("LOADLYNX_FW_TARGET").unwrap_or("unknown"));
+    buf.push_str("\",\"source_digest\":\"");
+    write_json_string_escaped(
+        buf,
+        option_env!("LOADLYNX_FW_SRC_DIGEST").unwrap_or("src unknown"),
+    );
+    buf.push_str("\",\"features\":[\"net_http\",\"mdns_dns_sd\",\"usb_cdc_jsonl\"],");
+    buf.push_str("\"protocol\":\"loadlynx.cdc.v1\",");
+    buf.push_str("\"defmt\":{\"enabled\":true,\"encoding\":\"defmt-espflash\"}},");
+
+    buf.push_str("\"usb_bridge\":{");
+    buf.push_str("\"transport\":\"usb_cdc_jsonl\",");
+    buf.push_str("\"protocol\":\"loadlynx.cdc.v1\",");
+    buf.push_str("\"lease_required\":true,");
+    buf.push_str("\"framing\":\"lf_json\"},");
+
+    // analog_fw_version: for now expose the compact HELLO fw_version as
+    // 0xXXXXXXXX when available; otherwise "unknown".
+    buf.push_str("\"analog_fw_version\":\"");
+    let analog_raw = ANALOG_FW_VERSION_RAW.load(Ordering::Relaxed);
+    if analog_raw != 0 {
+        let s = format!("0x{:08x}", analog_raw);
+        write_json_string_escaped(buf, &s);
+    } else {
+        write_json_string_escaped(buf, "unknown");
+    }
+    buf.push_str("\",");
+
+    // protocol_version
+    buf.push_str("\"protocol_version\":");
+    let _ = core::write!(buf, "{}", PROTOCOL_VERSION);
+    buf.push(',');
+
+    // uptime_ms
+    buf.push_str("\"uptime_ms\":");
+    let _ = core::write!(buf, "{}", timestamp_ms());
+    buf.push(',');
+
+    // network block
+    buf.push_str("\"network\":{");
+    // ip
+    buf.push_str("\"ip\":\"");
+    let _ = core::write!(
+        buf,
+        "{}.{}.{}.{}",
+        octets[0],
+        octets[1],
+        octets[2],
+        octets[3]
+    );
+    buf.push_str("\",");
+    // mac
+    buf.push_str("\"mac\":\"");
+    if let Some(ref names) = names {
+        write_json_string_escaped(buf, names.mac_str.as_str());
+    } else {
+        write_json_string_escaped(buf, "unknown");
+    }
+    buf.push_str("\",");
+    // hostname
+    buf.push_str("\"hostname\":\"");
+    if let Some(ref names) = names {
+        write_json_string_escaped(buf, names.hostname_fqdn.as_str());
+    } else if let Some(host) = WIFI_HOSTNAME {
+        write_json_string_escaped(buf, host);
+    } else {
+        write_json_string_escaped(buf, "loadlynx-digital");
+    }
+    buf.push_str("\"},");
+
+    // capabilities
+    buf.push_str("\"capabilities\":{");
+    buf.push_str("\"cc_supported\":true,");
+    buf.push_str("\"cv_supported\":true,");
+    buf.push_str("\"cp_supported\":true,");
+    buf.push_str("\"presets_supported\":true,");
+    buf.push_str("\"preset_count\":5,");
+    buf.push_str("\"auth_required\":true,");
+    buf.push_str("\"api_version\":\"2.0.0\"}");
+
+    buf.push('}');
+    Ok(())
+}
+
+/// Render the JSON body for `GET /api/v1/status` (single-shot snapshot).
+pub(crate) async fn render_status_json(
+    buf: &mut String,
+    telemetry: &'static TelemetryMutex,
+) -> Result<(), &'static str> {
+    render_status_json_inner(buf, telemetry, false).await
+}
+
+pub(crate) async fn render_status_json_sse(
+    buf: &mut String,
+    telemetry: &'static TelemetryMutex,
+) -> Result<(), &'static str> {
+    // Allow offline snapshots to keep the SSE stream alive; the consumer can
+    // inspect link_up to decide how to render.
+    render_status_json_inner(buf, telemetry, true).await
+}
+
+async fn render_status_json_inner(
+    buf: &mut String,
+    telemetry: &'static TelemetryMutex,
+    allow_offline: bool,
+) -> Result<(), &'static str> {
+    // Require the UART link to be up and at least one valid FastStatus frame.
+    let link_up = LINK_UP.load(Ordering::Relaxed);
+    let fast_ok = FAST_STATUS_OK_COUNT.load(Ordering::Relaxed);
+    let last_good = LAST_GOOD_FRAME_MS.load(Ordering::Relaxed);
+    let now = now_ms32();
+    let age_ms = if last_good == 0 {
+        u32::MAX
+    } else {
+        now.wrapping_sub(last_good)
+    };
+
+    if (!link_up || fast_ok == 0) && !allow_offline {
+        let details = format!(r#"{{"last_frame_age_ms":{}}}"#, age_ms);
+        write_error_body(
+            buf,
+            "LINK_DOWN",
+            "UART link is down or no FastStatus frames received",
+            true,
+            Some(&details),
+        );
+        return Err("503 Service Unavailable");
+    }
+
+    let (status, analog_state, ui_reason, ui_reason_blink, thermal) = {
+        let guard = telemetry.lock().await;
+        let status = guard.last_status.unwrap_or(FastStatus::default());
+        let analog_state = AnalogState::from_u8(crate::ANALOG_STATE.load(Ordering::Relaxed));
+        (
+            status,
+            analog_state,
+            guard.snapshot.status_lines[4].clone(),
+            guard.snapshot.blink_on,
+            guard.last_thermal,
+        )
+    };
+
+    let hello_seen = HELLO_SEEN.load(Ordering::Relaxed);
+
+    buf.clear();
+    buf.push('{');
+
+    // "status": { ... FastStatusJson ... }
+    buf.push_str("\"status\":");
+    write_fast_status_json(buf, &status);
+    buf.push(',');
+
+    // link_up / hello_seen
+    buf.push_str("\"link_up\":");
+    buf.push_str(if link_up { "true" } else { "false" });
+    buf.push_str(",\"hello_seen\":");
+    buf.push_str(if hello_seen { "true" } else { "false" });
+    buf.push_str(",\"wall_time\":");
+    wall_clock::write_json_wall_time(buf);
+
+    // analog_state
+    buf.push_str(",\"analog_state\":\"");
+    let analog_state_str = match analog_state {
+        AnalogState::Offline => "offline",
+        AnalogState::CalMissing => "cal_missing",
+        AnalogState::Faulted => "faulted",
+        AnalogState::Ready => "ready",
+        AnalogState::MeasurementInvalid => "measurement_invalid",
+    };
+    write_json_string_escaped(buf, analog_state_str);
+    buf.push_str("\",");
+
+    // ui_reason (status line #5) + blink phase (debug aid for UX validation).
+    buf.push_str("\"ui_reason\":\"");
+    write_json_string_escaped(buf, ui_reason.as_str());
+    buf.push_str("\",\"ui_reason_blink\":");
+    buf.push_str(if ui_reason_blink { "true" } else { "false" });
+    buf.push(',');
+
+    // thermal derating model (digital side)
+    buf.push_str("\"thermal\":");
+    crate::write_thermal_json(buf, thermal.as_ref());
+    buf.push(',');
+
+    // fault_flags_decoded
+    buf.push_str("\"fault_flags_decoded\":[");
+    let mut first = true;
+    let faults = status.fault_flags;
+    if faults & FAULT_OVERCURRENT != 0 {
+        if !first {
+            buf.push(',');
+        }
+        buf.push('"');
+        write_json_string_escaped(buf, "OVERCURRENT");
+        buf.push('"');
+        first = false;
+    }
+    if faults & FAULT_OVERVOLTAGE != 0 {
+        if !first {
+            buf.push(',');
+        }
+        buf.push('"');
+        write_json_string_escaped(buf, "OVERVOLTAGE");
+        buf.push('"');
+        first = false;
+    }
+    if faults & FAULT_MCU_OVER_TEMP != 0 {
+        if !first {
+            buf.push(',');
+        }
+        buf.push('"');
+        write_json_string_escaped(buf, "MCU_OVER_TEMP");
+        buf.push('"');
+        first = false;
+    }
+    if faults & FAULT_SINK_OVER_TEMP != 0 {
+        if !first {
+            buf.push(',');
+        }
+        buf.push('"');
+        write_json_string_escaped(buf, "SINK_OVER_TEMP");
+        buf.push('"');
+    }
+    buf.push(']');
+
+    // state_flags_decoded
+    buf.push_str(",\"state_flags_decoded\":[");
+    let mut first = true;
+    let flags = status.state_flags;
+    if flags & STATE_FLAG_REMOTE_ACTIVE != 0 {
+        if !first {
+            buf.push(',');
+        }
+        buf.push('"');
+        write_json_string_escaped(buf, "REMOTE_ACTIVE");
+        buf.push('"');
+        first = false;
+    }
+    if flags & STATE_FLAG_LINK_GOOD != 0 {
+        if !first {
+            buf.push(',');
+        }
+        buf.push('"');
+        write_json_string_escaped(buf, "LINK_GOOD");
+        buf.push('"');
+        first = false;
+    }
+    if flags & STATE_FLAG_ENABLED != 0 {
+        if !first {
+            buf.push(',');
+        }
+        buf.push('"');
+        write_json_string_escaped(buf, "ENABLED");
+        buf.push('"');
+        first = false;
+    }
+    if flags & STATE_FLAG_UV_LATCHED != 0 {
+        if !first {
+            buf.push(',');
+        }
+        buf.push('"');
+        write_json_string_escaped(buf, "UV_LATCHED");
+        buf.push('"');
+        first = false;
+    }
+    if flags & STATE_FLAG_POWER_LIMITED != 0 {
+        if !first {
+            buf.push(',');
+        }
+        buf.push('"');
+        write_json_string_escaped(buf, "POWER_LIMITED");
+        buf.push('"');
+        first = false;
+    }
+    if flags & STATE_FLAG_CURRENT_LIMITED != 0 {
+        if !first {
+            buf.push(',');
+        }
+        buf.push('"');
+        write_json_string_escaped(buf, "CURRENT_LIMITED");
+        buf.push('"');
+        first = false;
+    }
+    if flags & STATE_FLAG_SOA_LIMITED != 0 {
+        if !first {
+            buf.push(',');
+        }
+        buf.push('"');
+        write_json_string_escaped(buf, "SOA_LIMITED");
+        buf.push('"');
+        first = false;
+    }
+    if flags & STATE_FLAG_LINK_SAFED != 0 {
+        if !first {
+            buf.push(',');
+        }
+        buf.push('"');
+        write_json_string_escaped(buf, "LINK_SAFED");
+        buf.push('"');
+    }
+    buf.push(']');
+
+    // sense_warnings_decoded (analog sense-path diagnostics, advisory)
+    buf.push_str(",\"sense_warnings_decoded\":[");
+    let mut first = true;
+    let warnings = status.sense_warnings.unwrap_or(0);
+    for (bit, name) in [
+        (SENSE_WARN_REMOTE_REVERSED, "REMOTE_REVERSED"),
+        (SENSE_WARN_REMOTE_OPEN, "REMOTE_OPEN"),
+        (SENSE_WARN_HIGH_LEAD_DROP, "HIGH_LEAD_DROP"),
+        (SENSE_WARN_OSCILLATION, "OSCILLATION"),
+    ] {
+        if warnings & bit != 0 {
+            if !first {
+                buf.push(',');
+            }
+            buf.push('"');
+            write_json_string_escaped(buf, name);
+            buf.push('"');
+            first = false;
+        }
+    }
+    buf.push(']');
+
+    // channel_warnings_decoded (CH1/CH2 current sharing health, advisory)
+    buf.push_str(",\"channel_warnings_decoded\":[");
+    let mut first = true;
+    let warnings = status.channel_warnings.unwrap_or(0);
+    for (bit, name) in [
+        (CHANNEL_WARN_IMBALANCE, "IMBALANCE"),
+        (CHANNEL_WARN_CH1_STRAY, "CH1_STRAY"),
+        (CHANNEL_WARN_CH2_STRAY, "CH2_STRAY"),
+    ] {
+        if warnings & bit != 0 {
+            if !first {
+                buf.push(',');
+            }
+            buf.push('"');
+            write_json_string_escaped(buf, name);
+            buf.push('"');
+            first = false;
+        }
+    }
+    buf.push(']');
+
+    buf.push('}');
+    Ok(())
+}
+
+async fn handle_status_sse(
+    socket: &mut TcpSocket<'_>,
+    telemetry: &'static TelemetryMutex,
+    cors_origin: Option<&str>,
+) -> Result<(), embassy_net::tcp::Error> {
+    // Conservative rate to keep CPU/stack usage low while still improving
+    // smoothness over 400 ms polling.
+    const SSE_INTERVAL_MS: u64 = 200;
+
+    if STATUS_SSE_ACTIVE
+        .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+        .is_err()
+    {
+        let mut body = String::new();
+        write_error_body(
+            &mut body,
+            "UNAVAILABLE",
+            "status SSE stream already active",
+            true,
+            None,
+        );
+        write_http_response(
+            socket,
+            "HTTP/1.1",
+            "503 Service Unavailable",
+            &body,
+            cors_origin,
+        )
+        .await?;
+        return Ok(());
+    }
+
+    struct ClearSseActive;
+    impl Drop for ClearSseActive {
+        fn drop(&mut self) {
+            STATUS_SSE_ACTIVE.store(false, Ordering::Release);
+        }
+    }
+    let _guard = ClearSseActive;
+
+    write_sse_response_head(socket, cors_origin).await?;
+
+    let mut json_body = String::new();
+    let mut frame = String::new();
+
+    loop {
+        match render_status_json_sse(&mut json_body, telemetry).await {
+            Ok(()) => {
+                frame.clear();
+                frame.push_str("event: status\r\n");
+                frame.push_str("data: ");
+                frame.push_str(&json_body);
+                frame.push_str("\r\n\r\n");
+                socket_write_all(socket, frame.as_bytes()).await?;
+                socket.flush().await?;
+            }
+            Err(err_status) => {
+                frame.clear();
+                frame.push_str("event: error\r\n");
+                frame.push_str("data: \"");
+                write_json_string_escaped(&mut frame, err_status);
+                frame.push_str("\"\r\n\r\n");
+                socket_write_all(socket, frame.as_bytes()).await?;
+                socket.flush().await?;
+                return Ok(());
+            }
+        }
+
+        Timer::after(Duration::from_millis(SSE_INTERVAL_MS)).await;
+    }
+}
+
+fn write_fast_status_json(buf: &mut String, status: &FastStatus) {
+    buf.push('{');
+    let _ = core::write!(buf, "\"uptime_ms\":{}", status.uptime_ms);
+    let _ = core::write!(buf, ",\"mode\":{}", status.mode);
+    let _ = core::write!(buf, ",\"state_flags\":{}", status.state_flags);
+    let _ = core::write!(
+        buf,
+        ",\"enable\":{}",
+        if status.enable { "true" } else { "false" }
+    );
+    let _ = core::write!(buf, ",\"target_value\":{}", status.target_value);
+    let _ = core::write!(buf, ",\"i_local_ma\":{}", status.i_local_ma);
+    let _ = core::write!(buf, ",\"i_remote_ma\":{}", status.i_remote_ma);
+    let _ = core::write!(buf, ",\"v_local_mv\":{}", status.v_local_mv);
+    let _ = core::write!(buf, ",\"v_remote_mv\":{}", status.v_remote_mv);
+    let _ = core::write!(buf, ",\"calc_p_mw\":{}", status.calc_p_mw);
+    let _ = core::write!(buf, ",\"dac_headroom_mv\":{}", status.dac_headroom_mv);
+    let _ = core::write!(buf, ",\"loop_error\":{}", status.loop_error);
+    let _ = core::write!(buf, ",\"sink_core_temp_mc\":{}", status.sink_core_temp_mc);
+    let _ = core::write!(
+        buf,
+        ",\"sink_exhaust_temp_mc\":{}",
+        status.sink_exhaust_temp_mc
+    );
+    let _ = core::write!(buf, ",\"mcu_temp_mc\":{}", status.mcu_temp_mc);
+    let _ = core::write!(buf, ",\"fault_flags\":{}", status.fault_flags);
+
+    if let Some(v) = status.cal_kind {
+        let _ = core::write!(buf, ",\"cal_kind\":{}", v);
+    }
+    if let Some(v) = status.raw_v_nr_100uv {
+        let _ = core::write!(buf, ",\"raw_v_nr_100uv\":{}", v);
+    }
+    if let Some(v) = status.raw_v_rmt_100uv {
+        let _ = core::write!(buf, ",\"raw_v_rmt_100uv\":{}", v);
+    }
+    if let Some(v) = status.raw_cur_100uv {
+        let _ = core::write!(buf, ",\"raw_cur_100uv\":{}", v);
+    }
+    if let Some(v) = status.raw_dac_code {
+        let _ = core::write!(buf, ",\"raw_dac_code\":{}", v);
+    }
+    if let Some(v) = status.sense_warnings {
+        let _ = core::write!(buf, ",\"sense_warnings\":{}", v);
+    }
+    if let Some(v) = status.lead_resistance_mohm {
+        let _ = core::write!(buf, ",\"lead_resistance_mohm\":{}", v);
+    }
+    if let Some(v) = status.dac_code_ch1 {
+        let _ = core::write!(buf, ",\"dac_code_ch1\":{}", v);
+    }
+    if let Some(v) = status.dac_code_ch2 {
+        let _ = core::write!(buf, ",\"dac_code_ch2\":{}", v);
+    }
+    if let Some(v) = status.fet_temp_ch1_mc {
+        let _ = core::write!(buf, ",\"fet_temp_ch1_mc\":{}", v);
+    }
+    if let Some(v) = status.fet_temp_ch2_mc {
+        let _ = core::write!(buf, ",\"fet_temp_ch2_mc\":{}", v);
+    }
+    if let Some(name) = status.share_policy.and_then(share_policy_name) {
+        let _ = core::write!(buf, ",\"share_policy\":\"{}\"", name);
+    }
+    if let Some(v) = status.channel_warnings {
+        let _ = core::write!(buf, ",\"channel_warnings\":{}", v);
+    }
+    if let Some(v) = status.charge_uah {
+        let _ = core::write!(buf, ",\"charge_uah\":{}", v);
+    }
+    if let Some(v) = status.energy_uwh {
+        let _ = core::write!(buf, ",\"energy_uwh\":{}", v);
+    }
+    if let Some(v) = status.counter_elapsed_ms {
+        let _ = core::write!(buf, ",\"counter_elapsed_ms\":{}", v);
+    }
+    if let Some(v) = status.counter_on_ms {
+        let _ = core::write!(buf, ",\"counter_on_ms\":{}", v);
+    }
+    buf.push('}');
+}
+
+// ---- Presets / Control (v1 frozen) -----------------------------------------
+
+fn mode_to_json_str(mode: LoadMode) -> &'static str {
+    match mode {
+        LoadMode::Cc => "cc",
+        LoadMode::Cv => "cv",
+        LoadMode::Cp => "cp",
+        LoadMode::Reserved(_) => "cc",
+    }
+}
+
+fn write_preset_json(buf: &mut String, preset: &control::Preset) {
+    buf.push('{');
+    let _ = core::write!(buf, "\"preset_id\":{}", preset.preset_id);
+    buf.push_str(",\"mode\":\"");
+    write_json_string_escaped(buf, mode_to_json_str(preset.mode));
+    buf.push('"');
+    let _ = core::write!(buf, ",\"target_p_mw\":{}", preset.target_p_mw);
+    let _ = core::write!(buf, ",\"target_i_ma\":{}", preset.target_i_ma);
+    let _ = core::write!(buf, ",\"target_v_mv\":{}", preset.target_v_mv);
+    let _ = core::write!(buf, ",\"min_v_mv\":{}", preset.min_v_mv);
+    let _ = core::write!(buf, ",\"max_i_ma_total\":{}", preset.max_i_ma_total);
+    let _ = core::write!(buf, ",\"max_p_mw\":{}", preset.max_p_mw);
+    buf.push('}');
+}
+
+pub(crate) async fn render_presets_json(
+    buf: &mut String,
+    control: &'static ControlMutex,
+) -> Result<(), &'static str> {
+    let presets = {
+        let guard = control.lock().await;
+        guard.presets
+    };
+
+    buf.clear();
+    buf.push('{');
+    buf.push_str("\"presets\":[");
+    for (idx, p) in presets.iter().enumerate() {
+        if idx != 0 {
+            buf.push(',');
+        }
+        write_preset_json(buf, p);
+    }
+    buf.push_str("]}");
+    Ok(())
+}
+
+pub(crate) async fn render_control_view_json(
+    buf: &mut String,
+    control: &'static ControlMutex,
+    calibration: &'static CalibrationMutex,
+    telemetry: &'static TelemetryMutex,
+) -> Result<(), &'static str> {
+    let cal_mode = { calibration.lock().await.cal_mode };
+    let (active_preset_id, output_enabled, preset) = {
+        let guard = control.lock().await;
+        let effective = guard.effective_output_command(cal_mode);
+        (
+            guard.active_preset_id,
+            effective.output_enabled,
+            effective.preset,
+        )
+    };
+
+    let uv_latched = {
+        let guard = telemetry.lock().await;
+        guard
+            .last_status
+            .map(|s| (s.state_flags & STATE_FLAG_UV_LATCHED) != 0)
+            .unwrap_or(false)
+    };
+
+    buf.clear();
+    buf.push('{');
+    let _ = core::write!(buf, "\"active_preset_id\":{}", active_preset_id);
+    buf.push_str(",\"output_enabled\":");
+    buf.push_str(if output_enabled { "true" } else { "false" });
+    buf.push_str(",\"uv_latched\":");
+    buf.push_str(if uv_latched { "true" } else { "false" });
+    buf.push_str(",\"preset\":");
+    write_preset_json(buf, &preset);
+    buf.push('}');
+    Ok(())
+}
+
+fn parse_json_bool(body: &str, key: &str) -> Result<bool, &'static str> {
+    let idx = body.find(key).ok_or("missing field")?;
+    let colon = body[idx..].find(':').ok_or("missing ':'")?;
+    let value_str = body[idx + colon + 1..].trim_start();
+    if value_str.starts_with("true") {
+        Ok(true)
+    } else if value_str.starts_with("false") {
+        Ok(false)
+    } else {
+        Err("expected boolean")
+    }
+}
+
+fn parse_json_i64(body: &str, key: &str) -> Result<i64, &'static str> {
+    let idx = body.find(key).ok_or("missing field")?;
+    let colon = body[idx..].find(':').ok_or("missing ':'")?;
+    let mut value_str = body[idx + colon + 1..].trim_start();
+    let mut end = 0usize;
+    for ch in value_str.chars() {
+        if ch == '-' || ch.is_ascii_digit() {
+            end += ch.len_utf8();
+        } else {
+            break;
+        }
+    }
+    value_str = &value_str[..end];
     value_str.parse::<i64>().map_err(|_| "expected integer")
 }
 
-fn parse_json_i64_optional(body: &str, key: &str) -> Result<Option<i64>, &'static str> {
+pub(crate) fn parse_json_i64_optional(body: &str, key: &str) -> Result<Option<i64>, &'static str> {
     let Some(idx) = body.find(key) else {
         return Ok(None);
     };
@@ -2226,7 +4042,7 @@ fn parse_json_i64_optional(body: &str, key: &str) -> Result<Option<i64>, &'stati
     Ok(Some(v))
 }
 
-fn parse_json_str<'a>(body: &'a str, key: &str) -> Result<&'a str, &'static str> {
+pub(crate) fn parse_json_str<'a>(body: &'a str, key: &str) -> Result<&'a str, &'static str> {
     let idx = body.find(key).ok_or("missing field")?;
     let colon = body[idx..].find(':').ok_or("missing ':'")?;
     let rest = body[idx + colon + 1..].trim_start();
@@ -2438,40 +4254,60 @@ fn parse_control_update_json(body: &str) -> Result<ControlUpdateRequest, &'stati
     Ok(ControlUpdateRequest { output_enabled })
 }
 
-async fn ensure_output_enable_allowed(
-    body_out: &mut String,
+/// Reason an output-enable request is refused; shared by the HTTP handlers and
+/// the SCPI `INPut ON` path.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum OutputEnableBlock {
+    AnalogFaulted,
+    LinkDown,
+    AnalogOffline,
+    Uvlo,
+}
+
+impl OutputEnableBlock {
+    pub(crate) fn message(self) -> &'static str {
+        match self {
+            Self::AnalogFaulted => "analog board is faulted",
+            Self::LinkDown => "UART link is down",
+            Self::AnalogOffline => "analog board is offline",
+            Self::Uvlo => "v_main below min_v; refusing enable",
+        }
+    }
+
+    fn write_http_error(self, body_out: &mut String) -> &'static str {
+        match self {
+            Self::AnalogFaulted => {
+                write_error_body(body_out, "ANALOG_FAULTED", self.message(), false, None);
+                "409 Conflict"
+            }
+            Self::LinkDown | Self::AnalogOffline => {
+                write_error_body(body_out, "LINK_DOWN", self.message(), true, None);
+                "503 Service Unavailable"
+            }
+            Self::Uvlo => {
+                write_error_body(body_out, "UVLO", self.message(), true, None);
+                "409 Conflict"
+            }
+        }
+    }
+}
+
+pub(crate) async fn output_enable_blocker(
     control: &'static ControlMutex,
     cal_mode: CalKind,
-) -> Result<(), &'static str> {
+) -> Option<OutputEnableBlock> {
     // Match the on-device LOAD gating semantics:
     // - fault_flags / link down => block enable
     // - UVLO latch does NOT block enable (it is cleared on an OFF->ON edge analog-side)
     if crate::LAST_FAULT_FLAGS.load(Ordering::Relaxed) != 0 {
-        write_error_body(
-            body_out,
-            "ANALOG_FAULTED",
-            "analog board is faulted",
-            false,
-            None,
-        );
-        return Err("409 Conflict");
+        return Some(OutputEnableBlock::AnalogFaulted);
     }
     if !LINK_UP.load(Ordering::Relaxed) {
-        write_error_body(body_out, "LINK_DOWN", "UART link is down", true, None);
-        return Err("503 Service Unavailable");
+        return Some(OutputEnableBlock::LinkDown);
     }
     let analog_state = AnalogState::from_u8(crate::ANALOG_STATE.load(Ordering::Relaxed));
     match analog_state {
-        AnalogState::Faulted => {
-            write_error_body(
-                body_out,
-                "ANALOG_FAULTED",
-                "analog board is faulted",
-                false,
-                None,
-            );
-            return Err("409 Conflict");
-        }
+        AnalogState::Faulted => return Some(OutputEnableBlock::AnalogFaulted),
         // NOTE: AnalogState::CalMissing currently means "not producing enable"
         // (FastStatus.enable == false). This is NOT a reliable indicator of
         // missing calibration, and blocking output ON here would create a
@@ -2481,10 +4317,7 @@ async fn ensure_output_enable_allowed(
         // True safety gating still happens on the analog side:
         // CAL_READY / fault flags / uv_latched => effective output=0.
         AnalogState::CalMissing => {}
-        AnalogState::Offline => {
-            write_error_body(body_out, "LINK_DOWN", "analog board is offline", true, None);
-            return Err("503 Service Unavailable");
-        }
+        AnalogState::Offline => return Some(OutputEnableBlock::AnalogOffline),
         AnalogState::Ready => {}
         AnalogState::MeasurementInvalid => {}
     }
@@ -2503,18 +4336,22 @@ async fn ensure_output_enable_allowed(
     if min_v_mv > 0 && crate::LAST_GOOD_FRAME_MS.load(Ordering::Relaxed) != 0 {
         let v_main_mv = crate::LAST_V_MAIN_MV.load(Ordering::Relaxed);
         if v_main_mv <= min_v_mv.max(0) {
-            write_error_body(
-                body_out,
-                "UVLO",
-                "v_main below min_v; refusing enable",
-                true,
-                None,
-            );
-            return Err("409 Conflict");
+            return Some(OutputEnableBlock::Uvlo);
         }
     }
-
-    Ok(())
+
+    None
+}
+
+async fn ensure_output_enable_allowed(
+    body_out: &mut String,
+    control: &'static ControlMutex,
+    cal_mode: CalKind,
+) -> Result<(), &'static str> {
+    match output_enable_blocker(control, cal_mode).await {
+        Some(block) => Err(block.write_http_error(body_out)),
+        None => Ok(()),
+    }
 }
 
 pub(crate) async fn handle_control_update(
@@ -2571,7 +4408,7 @@ pub(crate) async fn handle_control_update(
 }
 
 /// Render the JSON body for `GET /api/v1/cc`.
-async fn render_cc_view_json(
+pub(crate) async fn render_cc_view_json(
     buf: &mut String,
     control: &'static ControlMutex,
     calibration: &'static CalibrationMutex,
@@ -2609,7 +4446,7 @@ async fn render_cc_view_json(
         guard.last_status.unwrap_or(FastStatus::default())
     };
 
-    let limit: LimitProfile = LIMIT_PROFILE_DEFAULT;
+    let limit: LimitProfile = thermal::limit_profile();
     let i_total = status.i_local_ma + status.i_remote_ma;
     let v_main = if (status.state_flags & STATE_FLAG_REMOTE_ACTIVE) != 0 {
         status.v_remote_mv
@@ -2675,7 +4512,7 @@ async fn render_cc_view_json(
 /// Handle `PUT /api/v1/cc`: minimal v0 implementation that accepts `enable`
 /// and `target_i_ma` and maps them onto the existing encoder-driven CC
 /// control path. Limit fields and protection modes are currently ignored.
-async fn handle_cc_update(
+pub(crate) async fn handle_cc_update(
     body_in: &str,
     body_out: &mut String,
     control: &'static ControlMutex,
@@ -2770,7 +4607,7 @@ async fn handle_cc_update(
 }
 
 /// Render the JSON body for `GET /api/v1/pd`.
-async fn render_pd_view_json(
+pub(crate) async fn render_pd_view_json(
     buf: &mut String,
     control_mutex: &'static ControlMutex,
     telemetry: &'static TelemetryMutex,
@@ -3130,7 +4967,7 @@ fn parse_pd_update_json(body: &str) -> Result<PdUpdateRequest, &'static str> {
 }
 
 /// Handle `PUT/POST /api/v1/pd`: update saved PD config, persist to EEPROM, and trigger apply.
-async fn handle_pd_update(
+pub(crate) async fn handle_pd_update(
     body_in: &str,
     body_out: &mut String,
     control_mutex: &'static ControlMutex,
@@ -3469,295 +5306,859 @@ async fn handle_pd_update(
         }
     }
 
-    if let Some(value) = parsed.allow_extended_voltage {
-        allow_extended_voltage = value;
+    if let Some(value) = parsed.allow_extended_voltage {
+        allow_extended_voltage = value;
+    }
+
+    let saved_changed = cfg != prev_cfg;
+    let changed = saved_changed || allow_extended_voltage != prev_allow_extended_voltage;
+    if changed {
+        let blob = control::encode_pd_blob(&cfg, allow_extended_voltage);
+        let res = {
+            let mut ep = eeprom.lock().await;
+            ep.write_pd_blob(&blob).await
+        };
+        if let Err(_err) = res {
+            write_error_body(body_out, "UNAVAILABLE", "EEPROM write failed", true, None);
+            crate::PD_LAST_RESULT_CODE.store(4, Ordering::Relaxed);
+            crate::PD_LAST_RESULT_MS.store(now_ms32(), Ordering::Relaxed);
+            return Err("503 Service Unavailable");
+        }
+
+        ctrl.pd_saved = cfg;
+        ctrl.allow_extended_voltage = allow_extended_voltage;
+        if saved_changed && ctrl.ui_view == crate::control::UiView::PdSettings {
+            ctrl.pd_draft = cfg;
+        }
+        bump_control_rev();
+        drop(ctrl);
+    } else {
+        drop(ctrl);
+    }
+
+    if !allow_extended_voltage {
+        crate::clear_pd_extended_voltage_failure();
+    }
+
+    // Persisted PD updates apply immediately, but live renegotiation still requires a fresh
+    // PD session snapshot and an active digital<->analog link.
+    let attached_now = LINK_UP.load(Ordering::Relaxed) && {
+        let guard = telemetry.lock().await;
+        guard
+            .last_pd_status
+            .as_ref()
+            .map(|status| status.attached)
+            .unwrap_or(false)
+    };
+    if attached_now {
+        let now = now_ms32();
+        if !allow_extended_voltage {
+            crate::clear_pd_extended_voltage_failure();
+        }
+        crate::start_pd_extended_voltage_retry_window(now);
+        crate::PD_UI_APPLY_MS.store(now, Ordering::Relaxed);
+        let effective = control::PdConfig::effective(cfg, allow_extended_voltage);
+        let allows_non_safe5v = effective.allows_non_safe5v();
+        let analog_state = AnalogState::from_u8(crate::ANALOG_STATE.load(Ordering::Relaxed));
+        let may_force_send = !allow_extended_voltage
+            || !allows_non_safe5v
+            || !matches!(analog_state, AnalogState::Faulted | AnalogState::Offline);
+        if may_force_send {
+            crate::PD_FORCE_SEND.store(true, Ordering::Release);
+        }
+    }
+
+    match render_pd_view_json(body_out, control_mutex, telemetry).await {
+        Ok(()) => Ok(()),
+        Err(_err) if !updates_saved_cfg => {
+            // Best-effort: allow gate-only updates even when GET /api/v1/pd is currently
+            // unavailable (e.g. LINK_DOWN). Return a detached view so the caller gets a stable
+            // confirmation payload.
+            let (saved, allow_extended_voltage) = {
+                let guard = control_mutex.lock().await;
+                (guard.pd_saved, guard.allow_extended_voltage)
+            };
+            let ack_pending = crate::PD_REQ_ACK_PENDING.load(Ordering::Relaxed);
+            let last_code = crate::PD_LAST_RESULT_CODE.load(Ordering::Relaxed);
+            let last_ms = crate::PD_LAST_RESULT_MS.load(Ordering::Relaxed);
+
+            body_out.clear();
+            body_out.push('{');
+            body_out.push_str("\"attached\":false");
+            body_out.push_str(",\"contract_mv\":null");
+            body_out.push_str(",\"contract_ma\":null");
+            body_out.push_str(",\"fixed_pdos\":[]");
+            body_out.push_str(",\"pps_pdos\":[]");
+            body_out.push_str(",\"epr_active\":false");
+            body_out.push_str(",\"epr_avs_pdos\":[]");
+            body_out.push_str(",\"allow_extended_voltage\":");
+            body_out.push_str(if allow_extended_voltage {
+                "true"
+            } else {
+                "false"
+            });
+
+            body_out.push_str(",\"saved\":{");
+            body_out.push_str("\"mode\":\"");
+            body_out.push_str(match saved.mode {
+                crate::control::PdMode::Fixed => "fixed",
+                crate::control::PdMode::Pps => "pps",
+            });
+            body_out.push('"');
+            let _ = core::write!(
+                body_out,
+                ",\"fixed_object_pos\":{},\"pps_object_pos\":{},\"target_mv\":{},\"pps_target_mv\":{},\"i_req_ma\":{}",
+                saved.fixed_object_pos,
+                saved.pps_object_pos,
+                saved.target_mv,
+                saved.pps_target_mv,
+                saved.i_req_ma
+            );
+            body_out.push('}');
+
+            body_out.push_str(",\"apply\":{");
+            body_out.push_str("\"pending\":");
+            body_out.push_str(if ack_pending { "true" } else { "false" });
+            if last_code != 0 {
+                body_out.push_str(",\"last\":{");
+                body_out.push_str("\"code\":\"");
+                body_out.push_str(match last_code {
+                    1 => "ok",
+                    2 => "nack",
+                    3 => "timeout",
+                    4 => "persist_fail",
+                    _ => "failed",
+                });
+                body_out.push('"');
+                let _ = core::write!(body_out, ",\"at_ms\":{}", last_ms);
+                body_out.push('}');
+            }
+            body_out.push('}');
+
+            body_out.push('}');
+            Ok(())
+        }
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn live_fixed_lookup_requires_real_capability() {
+        let mut status = PdStatus {
+            attached: true,
+            epr_capable: true,
+            ..PdStatus::default()
+        };
+        let _ = status.fixed_pdos.push(loadlynx_protocol::FixedPdo {
+            pos: 1,
+            mv: 5_000,
+            max_ma: 3_000,
+        });
+
+        assert_eq!(
+            find_live_fixed_pdo(&status, control::EPR_FIXED_28V_OBJECT_POS),
+            None
+        );
+    }
+
+    #[test]
+    fn live_fixed_lookup_uses_index_when_protocol_pos_is_missing() {
+        let mut status = PdStatus {
+            attached: true,
+            ..PdStatus::default()
+        };
+        let _ = status.fixed_pdos.push(loadlynx_protocol::FixedPdo {
+            pos: 0,
+            mv: 5_000,
+            max_ma: 3_000,
+        });
+        let _ = status.fixed_pdos.push(loadlynx_protocol::FixedPdo {
+            pos: 0,
+            mv: 9_000,
+            max_ma: 3_000,
+        });
+
+        assert_eq!(
+            find_live_fixed_pdo(&status, 2).map(|pdo| pdo.mv),
+            Some(9_000)
+        );
+    }
+
+    #[test]
+    fn commit_verification_accepts_the_profile_applied_to_ram() {
+        let mut candidate = calfmt::ActiveProfile::factory_default(calfmt::DIGITAL_HW_REV);
+        candidate.source = ProfileSource::UserCalibrated;
+        candidate.current_ch1[1].meas_physical = 4_730;
+        let blob = calfmt::serialize_profile(&candidate);
+
+        assert!(serialized_profile_matches(&blob, &candidate));
+    }
+
+    #[test]
+    fn commit_verification_rejects_corrupted_eeprom_readback() {
+        let mut candidate = calfmt::ActiveProfile::factory_default(calfmt::DIGITAL_HW_REV);
+        candidate.source = ProfileSource::UserCalibrated;
+        let mut blob = calfmt::serialize_profile(&candidate);
+        blob[32] ^= 0x01;
+
+        assert!(!serialized_profile_matches(&blob, &candidate));
+    }
+
+    #[test]
+    fn wifi_candidates_prefer_priority_then_signal_then_unseen() {
+        // 0: not seen, 1: usable, 2: weak, 3: usable, 4: weaker.
+        let rssi = [None, Some(-70), Some(-88), Some(-50), Some(-90)];
+
+        assert_eq!(order_wifi_candidates(&rssi).as_slice(), &[1, 3, 2, 4, 0]);
+        assert_eq!(order_wifi_candidates(&[None, None]).as_slice(), &[0, 1]);
+    }
+
+    #[test]
+    fn wifi_network_upsert_and_move_keep_priority_order() {
+        let mut networks = WifiNetworks::new();
+        upsert_wifi_network(&mut networks, "lab", "lab-pass", 0).unwrap();
+        upsert_wifi_network(&mut networks, "home", "home-pass", 9).unwrap();
+        upsert_wifi_network(&mut networks, "office", "office-pass", 1).unwrap();
+        // Re-adding an existing SSID replaces it instead of duplicating it.
+        upsert_wifi_network(&mut networks, "lab", "new-pass!", 2).unwrap();
+        let order: Vec<&str, 6> = networks.iter().map(|n| n.ssid.as_str()).collect();
+        assert_eq!(order.as_slice(), &["office", "home", "lab"]);
+        assert_eq!(networks[2].psk, "new-pass!");
+
+        assert!(move_wifi_network(&mut networks, "lab", 0));
+        assert!(!move_wifi_network(&mut networks, "cafe", 0));
+        assert_eq!(networks[0].ssid, "lab");
+        assert_eq!(
+            upsert_wifi_network(&mut networks, "x", "short", 0),
+            Err("psk length must be 8..64 bytes")
+        );
+    }
+}
+
+struct CcUpdateRequest {
+    enable: bool,
+    target_i_ma: i32,
+}
+
+fn parse_cc_update_json(body: &str) -> Result<CcUpdateRequest, &'static str> {
+    let mut enable: Option<bool> = None;
+    let mut target_i_ma: Option<i32> = None;
+
+    // Very small hand-written JSON parser: looks for `"enable"` and
+    // `"target_i_ma"` keys and extracts their values. This keeps the firmware
+    // free from heavy JSON dependencies.
+    if let Some(idx) = body.find("\"enable\"")
+        && let Some(colon_idx) = body[idx..].find(':')
+    {
+        let value_str = body[idx + colon_idx + 1..].trim_start();
+        if value_str.starts_with("true") {
+            enable = Some(true);
+        } else if value_str.starts_with("false") {
+            enable = Some(false);
+        } else {
+            return Err("enable must be true or false");
+        }
+    }
+
+    if let Some(idx) = body.find("\"target_i_ma\"")
+        && let Some(colon_idx) = body[idx..].find(':')
+    {
+        let mut value_str = body[idx + colon_idx + 1..].trim_start();
+        // Strip leading sign/digits until we hit a delimiter.
+        let mut end = 0usize;
+        for ch in value_str.chars() {
+            if ch == '-' || ch.is_ascii_digit() {
+                end += ch.len_utf8();
+            } else {
+                break;
+            }
+        }
+        value_str = &value_str[..end];
+        match value_str.parse::<i32>() {
+            Ok(v) => target_i_ma = Some(v),
+            Err(_) => return Err("target_i_ma must be an integer"),
+        }
+    }
+
+    let enable = enable.ok_or("missing field enable")?;
+    let target_i_ma = target_i_ma.ok_or("missing field target_i_ma")?;
+
+    Ok(CcUpdateRequest {
+        enable,
+        target_i_ma,
+    })
+}
+
+struct SoftResetRequest<'a> {
+    reason_str: &'a str,
+    reason: SoftResetReason,
+}
+
+fn parse_soft_reset_json(body: &str) -> Result<SoftResetRequest<'_>, &'static str> {
+    // Very small hand-written JSON parser: looks for a `"reason"` string field
+    // and maps it onto the SoftResetReason enum. This keeps the firmware free
+    // from heavy JSON dependencies.
+    let idx = body.find("\"reason\"").ok_or("missing field reason")?;
+    let colon_idx = body[idx..].find(':').ok_or("malformed reason field")?;
+    let mut value_str = body[idx + colon_idx + 1..].trim_start();
+    if !value_str.starts_with('"') {
+        return Err("reason must be a string");
     }
+    value_str = &value_str[1..];
+    let end = value_str.find('"').ok_or("reason must be a string")?;
+    let reason_str = &value_str[..end];
 
-    let saved_changed = cfg != prev_cfg;
-    let changed = saved_changed || allow_extended_voltage != prev_allow_extended_voltage;
-    if changed {
-        let blob = control::encode_pd_blob(&cfg, allow_extended_voltage);
+    let reason = match reason_str {
+        "manual" => SoftResetReason::Manual,
+        "firmware_update" => SoftResetReason::FirmwareUpdate,
+        "ui_recover" => SoftResetReason::UiRecover,
+        "link_recover" => SoftResetReason::LinkRecover,
+        _ => {
+            return Err(
+                "reason must be one of \"manual\", \"firmware_update\", \"ui_recover\", \"link_recover\"",
+            );
+        }
+    };
+
+    Ok(SoftResetRequest { reason_str, reason })
+}
+
+/// Fault-policy selectors in the order they are reported.
+pub(crate) const FAULT_POLICY_SELECTORS: [(&str, u32); 5] = [
+    ("overcurrent", FAULT_OVERCURRENT),
+    ("overvoltage", FAULT_OVERVOLTAGE),
+    ("mcu_over_temp", FAULT_MCU_OVER_TEMP),
+    ("sink_over_temp", FAULT_SINK_OVER_TEMP),
+    ("uv_latch", FAULT_POLICY_UV_LATCH),
+];
+
+/// `ProtectionView`: saved thresholds, fault policy, analog sync state and the accepted
+/// range of every field.
+pub(crate) async fn render_protection_json(buf: &mut String, control_mutex: &'static ControlMutex) {
+    let cfg = { control_mutex.lock().await.protection };
+    buf.clear();
+    let _ = core::write!(
+        buf,
+        r#"{{"oc_limit_ch_ma":{},"ov_limit_mv":{},"mcu_temp_limit_mc":{},"sink_temp_limit_mc":{},"i_share_threshold_ma":{},"fault_latch_mask":{},"link_loss_timeout_ms":{},"share_policy":"{}","sync":"{}","fault_policy":{{"#,
+        cfg.oc_limit_ch_ma,
+        cfg.ov_limit_mv,
+        cfg.mcu_temp_limit_mc,
+        cfg.sink_temp_limit_mc,
+        cfg.i_share_threshold_ma,
+        cfg.fault_latch_mask,
+        cfg.link_loss_timeout_ms,
+        share_policy_name(cfg.share_policy).unwrap_or("auto"),
+        crate::ProtectionSync::load().as_str()
+    );
+    for (idx, (name, bit)) in FAULT_POLICY_SELECTORS.iter().enumerate() {
+        if idx != 0 {
+            buf.push(',');
+        }
+        let policy = if cfg.fault_latch_mask & bit != 0 {
+            "latch"
+        } else {
+            "auto"
+        };
+        let _ = core::write!(buf, r#""{}":"{}""#, name, policy);
+    }
+    buf.push_str(r#"},"bounds":{"#);
+    let bounds = [
+        ("oc_limit_ch_ma", PROTECTION_OC_LIMIT_CH_MA_RANGE),
+        ("ov_limit_mv", PROTECTION_OV_LIMIT_MV_RANGE),
+        ("mcu_temp_limit_mc", PROTECTION_MCU_TEMP_LIMIT_MC_RANGE),
+        ("sink_temp_limit_mc", PROTECTION_SINK_TEMP_LIMIT_MC_RANGE),
+        (
+            "i_share_threshold_ma",
+            PROTECTION_I_SHARE_THRESHOLD_MA_RANGE,
+        ),
+        (
+            "link_loss_timeout_ms",
+            PROTECTION_LINK_LOSS_TIMEOUT_MS_RANGE,
+        ),
+    ];
+    for (idx, (name, range)) in bounds.iter().enumerate() {
+        if idx != 0 {
+            buf.push(',');
+        }
+        let _ = core::write!(
+            buf,
+            r#""{}":{{"min":{},"max":{}}}"#,
+            name,
+            range.start(),
+            range.end()
+        );
+    }
+    buf.push_str("}}");
+}
+
+/// Merge the fields present in `body` over `base`. Absent fields keep their
+/// current value; `"reset":true` starts from the firmware defaults.
+fn parse_protection_update_json(
+    body: &str,
+    base: ProtectionConfig,
+) -> Result<ProtectionConfig, &'static str> {
+    let mut cfg = if parse_json_bool_value(body, "\"reset\"").unwrap_or(false) {
+        ProtectionConfig::DEFAULT
+    } else {
+        base
+    };
+    let fields: [(&str, &mut i32); 6] = [
+        ("\"oc_limit_ch_ma\"", &mut cfg.oc_limit_ch_ma),
+        ("\"ov_limit_mv\"", &mut cfg.ov_limit_mv),
+        ("\"mcu_temp_limit_mc\"", &mut cfg.mcu_temp_limit_mc),
+        ("\"sink_temp_limit_mc\"", &mut cfg.sink_temp_limit_mc),
+        ("\"i_share_threshold_ma\"", &mut cfg.i_share_threshold_ma),
+        ("\"link_loss_timeout_ms\"", &mut cfg.link_loss_timeout_ms),
+    ];
+    for (key, slot) in fields {
+        if let Some(v) = parse_json_i64_optional(body, key)? {
+            *slot = i32::try_from(v).map_err(|_| "integer out of range")?;
+        }
+    }
+    if let Some(v) = parse_json_i64_optional(body, "\"fault_latch_mask\"")? {
+        cfg.fault_latch_mask = u32::try_from(v).map_err(|_| "integer out of range")?;
+    }
+    if body.contains("\"share_policy\"") {
+        let name = parse_json_str(body, "\"share_policy\"")?;
+        cfg.share_policy = share_policy_from_name(name)
+            .ok_or("share_policy must be one of \"auto\", \"dual\", \"ch1_only\", \"ch2_only\"")?;
+    }
+    Ok(cfg)
+}
+
+pub(crate) async fn handle_protection_update(
+    body_in: &str,
+    body_out: &mut String,
+    control_mutex: &'static ControlMutex,
+    eeprom: &'static EepromMutex,
+) -> Result<(), &'static str> {
+    // Serialize under the control lock so concurrent HTTP/USB writers cannot
+    // persist a stale merge.
+    let mut ctrl = control_mutex.lock().await;
+    let cfg = match parse_protection_update_json(body_in, ctrl.protection) {
+        Ok(cfg) => cfg,
+        Err(msg) => {
+            write_error_body(body_out, "INVALID_REQUEST", msg, false, None);
+            return Err("400 Bad Request");
+        }
+    };
+    if let Err(field) = cfg.validate() {
+        let details = format!(r#"{{"field":"{}"}}"#, field.as_str());
+        write_error_body(
+            body_out,
+            "LIMIT_VIOLATION",
+            "protection threshold outside hardware-safe range",
+            false,
+            Some(&details),
+        );
+        return Err("422 Unprocessable Entity");
+    }
+
+    if cfg != ctrl.protection {
+        let blob = control::encode_protection_blob(&cfg);
         let res = {
             let mut ep = eeprom.lock().await;
-            ep.write_pd_blob(&blob).await
+            ep.write_protection_blob(&blob).await
         };
         if let Err(_err) = res {
             write_error_body(body_out, "UNAVAILABLE", "EEPROM write failed", true, None);
-            crate::PD_LAST_RESULT_CODE.store(4, Ordering::Relaxed);
-            crate::PD_LAST_RESULT_MS.store(now_ms32(), Ordering::Relaxed);
             return Err("503 Service Unavailable");
         }
-
-        ctrl.pd_saved = cfg;
-        ctrl.allow_extended_voltage = allow_extended_voltage;
-        if saved_changed && ctrl.ui_view == crate::control::UiView::PdSettings {
-            ctrl.pd_draft = cfg;
-        }
-        bump_control_rev();
-        drop(ctrl);
-    } else {
-        drop(ctrl);
+        ctrl.protection = cfg;
+        info!(
+            "protection config saved via API (oc_ch={}mA ov={}mV mcu_temp={}mC sink_temp={}mC i_share={}mA share_policy={} link_loss={}ms)",
+            cfg.oc_limit_ch_ma,
+            cfg.ov_limit_mv,
+            cfg.mcu_temp_limit_mc,
+            cfg.sink_temp_limit_mc,
+            cfg.i_share_threshold_ma,
+            cfg.share_policy,
+            cfg.link_loss_timeout_ms
+        );
     }
+    drop(ctrl);
 
-    if !allow_extended_voltage {
-        crate::clear_pd_extended_voltage_failure();
-    }
+    // Always re-push so callers can force a resync after an analog reset.
+    crate::request_protection_sync();
+    render_protection_json(body_out, control_mutex).await;
+    Ok(())
+}
 
-    // Persisted PD updates apply immediately, but live renegotiation still requires a fresh
-    // PD session snapshot and an active digital<->analog link.
-    let attached_now = LINK_UP.load(Ordering::Relaxed) && {
-        let guard = telemetry.lock().await;
-        guard
-            .last_pd_status
-            .as_ref()
-            .map(|status| status.attached)
-            .unwrap_or(false)
-    };
-    if attached_now {
-        let now = now_ms32();
-        if !allow_extended_voltage {
-            crate::clear_pd_extended_voltage_failure();
-        }
-        crate::start_pd_extended_voltage_retry_window(now);
-        crate::PD_UI_APPLY_MS.store(now, Ordering::Relaxed);
-        let effective = control::PdConfig::effective(cfg, allow_extended_voltage);
-        let allows_non_safe5v = effective.allows_non_safe5v();
-        let analog_state = AnalogState::from_u8(crate::ANALOG_STATE.load(Ordering::Relaxed));
-        let may_force_send = !allow_extended_voltage
-            || !allows_non_safe5v
-            || !matches!(analog_state, AnalogState::Faulted | AnalogState::Offline);
-        if may_force_send {
-            crate::PD_FORCE_SEND.store(true, Ordering::Release);
+/// `POST /api/v1/faults/clear`: queue a ClearFaults request (optional `mask`,
+/// default: every fault plus the UV latch) and silence latched alarms.
+pub(crate) fn handle_faults_clear_http(
+    body_in: &str,
+    body_out: &mut String,
+) -> Result<(), &'static str> {
+    let mask = match parse_json_i64_optional(body_in, "\"mask\"") {
+        Ok(None) => FAULT_POLICY_ALL,
+        Ok(Some(v)) => match u32::try_from(v) {
+            Ok(mask) if mask != 0 && mask & !FAULT_POLICY_ALL == 0 => mask,
+            _ => {
+                let details = format!(r#"{{"allowed_mask":{}}}"#, FAULT_POLICY_ALL);
+                write_error_body(
+                    body_out,
+                    "INVALID_REQUEST",
+                    "mask must be a non-empty subset of the fault policy selectors",
+                    false,
+                    Some(&details),
+                );
+                return Err("400 Bad Request");
+            }
+        },
+        Err(msg) => {
+            write_error_body(body_out, "INVALID_REQUEST", msg, false, None);
+            return Err("400 Bad Request");
         }
-    }
+    };
 
-    match render_pd_view_json(body_out, control_mutex, telemetry).await {
-        Ok(()) => Ok(()),
-        Err(_err) if !updates_saved_cfg => {
-            // Best-effort: allow gate-only updates even when GET /api/v1/pd is currently
-            // unavailable (e.g. LINK_DOWN). Return a detached view so the caller gets a stable
-            // confirmation payload.
-            let (saved, allow_extended_voltage) = {
-                let guard = control_mutex.lock().await;
-                (guard.pd_saved, guard.allow_extended_voltage)
-            };
-            let ack_pending = crate::PD_REQ_ACK_PENDING.load(Ordering::Relaxed);
-            let last_code = crate::PD_LAST_RESULT_CODE.load(Ordering::Relaxed);
-            let last_ms = crate::PD_LAST_RESULT_MS.load(Ordering::Relaxed);
+    if !LINK_UP.load(Ordering::Relaxed) {
+        write_error_body(body_out, "LINK_DOWN", "UART link is down", true, None);
+        return Err("503 Service Unavailable");
+    }
 
-            body_out.clear();
-            body_out.push('{');
-            body_out.push_str("\"attached\":false");
-            body_out.push_str(",\"contract_mv\":null");
-            body_out.push_str(",\"contract_ma\":null");
-            body_out.push_str(",\"fixed_pdos\":[]");
-            body_out.push_str(",\"pps_pdos\":[]");
-            body_out.push_str(",\"epr_active\":false");
-            body_out.push_str(",\"epr_avs_pdos\":[]");
-            body_out.push_str(",\"allow_extended_voltage\":");
-            body_out.push_str(if allow_extended_voltage {
-                "true"
-            } else {
-                "false"
-            });
+    crate::request_clear_faults(mask);
 
-            body_out.push_str(",\"saved\":{");
-            body_out.push_str("\"mode\":\"");
-            body_out.push_str(match saved.mode {
-                crate::control::PdMode::Fixed => "fixed",
-                crate::control::PdMode::Pps => "pps",
-            });
-            body_out.push('"');
-            let _ = core::write!(
-                body_out,
-                ",\"fixed_object_pos\":{},\"pps_object_pos\":{},\"target_mv\":{},\"pps_target_mv\":{},\"i_req_ma\":{}",
-                saved.fixed_object_pos,
-                saved.pps_object_pos,
-                saved.target_mv,
-                saved.pps_target_mv,
-                saved.i_req_ma
-            );
-            body_out.push('}');
+    body_out.clear();
+    let _ = core::write!(body_out, r#"{{"accepted":true,"mask":{}}}"#, mask);
+    Ok(())
+}
 
-            body_out.push_str(",\"apply\":{");
-            body_out.push_str("\"pending\":");
-            body_out.push_str(if ack_pending { "true" } else { "false" });
-            if last_code != 0 {
-                body_out.push_str(",\"last\":{");
-                body_out.push_str("\"code\":\"");
-                body_out.push_str(match last_code {
-                    1 => "ok",
-                    2 => "nack",
-                    3 => "timeout",
-                    4 => "persist_fail",
-                    _ => "failed",
-                });
-                body_out.push('"');
-                let _ = core::write!(body_out, ",\"at_ms\":{}", last_ms);
-                body_out.push('}');
+/// `POST /api/v1/counters/reset`: queue a ResetCounters request (optional
+/// `mask` of `COUNTER_*` selectors, default: charge, energy and timers).
+pub(crate) fn handle_counters_reset_http(
+    body_in: &str,
+    body_out: &mut String,
+) -> Result<(), &'static str> {
+    let mask = match parse_json_i64_optional(body_in, "\"mask\"") {
+        Ok(None) => COUNTER_ALL,
+        Ok(Some(v)) => match u8::try_from(v) {
+            Ok(mask) if mask != 0 && mask & !COUNTER_ALL == 0 => mask,
+            _ => {
+                let details = format!(r#"{{"allowed_mask":{}}}"#, COUNTER_ALL);
+                write_error_body(
+                    body_out,
+                    "INVALID_REQUEST",
+                    "mask must be a non-empty subset of the counter selectors",
+                    false,
+                    Some(&details),
+                );
+                return Err("400 Bad Request");
             }
-            body_out.push('}');
+        },
+        Err(msg) => {
+            write_error_body(body_out, "INVALID_REQUEST", msg, false, None);
+            return Err("400 Bad Request");
+        }
+    };
 
-            body_out.push('}');
-            Ok(())
+    if !LINK_UP.load(Ordering::Relaxed) {
+        write_error_body(body_out, "LINK_DOWN", "UART link is down", true, None);
+        return Err("503 Service Unavailable");
+    }
+
+    crate::request_counters_reset(mask);
+
+    body_out.clear();
+    let _ = core::write!(body_out, r#"{{"accepted":true,"mask":{}}}"#, mask);
+    Ok(())
+}
+
+/// `IrMeasureView`: state of the current (or last) internal-resistance run.
+pub(crate) fn render_ir_measure_json(buf: &mut String) {
+    let status = ir_measure::status();
+    let (state, config, cycle) = match status {
+        ir_measure::IrStatus::Idle => ("idle", None, None),
+        ir_measure::IrStatus::Running { config, cycle } => ("running", Some(config), Some(cycle)),
+        ir_measure::IrStatus::Done { config, .. } => ("done", Some(config), None),
+        ir_measure::IrStatus::Failed { config, .. } => ("failed", Some(config), None),
+    };
+    buf.clear();
+    let _ = core::write!(buf, r#"{{"state":"{}","config":"#, state);
+    match config {
+        Some(cfg) => {
+            let _ = core::write!(
+                buf,
+                r#"{{"low_ma":{},"high_ma":{},"cycles":{},"settle_ms":{},"sample_ms":{}}}"#,
+                cfg.low_ma,
+                cfg.high_ma,
+                cfg.cycles,
+                cfg.settle_ms,
+                cfg.sample_ms
+            );
         }
-        Err(err) => Err(err),
+        None => buf.push_str("null"),
+    }
+    buf.push_str(r#","cycle":"#);
+    match cycle {
+        Some(cycle) => {
+            let _ = core::write!(buf, "{}", cycle);
+        }
+        None => buf.push_str("null"),
+    }
+    buf.push_str(r#","result":"#);
+    match status {
+        ir_measure::IrStatus::Done { result, .. } => {
+            let _ = core::write!(
+                buf,
+                r#"{{"r_mohm":{},"r_uohm":{},"r_min_uohm":{},"r_max_uohm":{},"cycles":{},"v_low_mv":{},"v_high_mv":{},"i_low_ma":{},"i_high_ma":{},"sense":"{}"}}"#,
+                result.r_mohm(),
+                result.r_uohm,
+                result.r_min_uohm,
+                result.r_max_uohm,
+                result.cycles,
+                result.v_low_mv,
+                result.v_high_mv,
+                result.i_low_ma,
+                result.i_high_ma,
+                if result.remote_sense {
+                    "remote"
+                } else {
+                    "local"
+                }
+            );
+        }
+        _ => buf.push_str("null"),
+    }
+    buf.push_str(r#","error":"#);
+    match status {
+        ir_measure::IrStatus::Failed { error, .. } => {
+            let _ = core::write!(
+                buf,
+                r#"{{"code":"{}","message":"{}"}}"#,
+                error.code(),
+                error.message()
+            );
+        }
+        _ => buf.push_str("null"),
     }
+    buf.push('}');
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn live_fixed_lookup_requires_real_capability() {
-        let mut status = PdStatus {
-            attached: true,
-            epr_capable: true,
-            ..PdStatus::default()
-        };
-        let _ = status.fixed_pdos.push(loadlynx_protocol::FixedPdo {
-            pos: 1,
-            mv: 5_000,
-            max_ma: 3_000,
-        });
-
-        assert_eq!(
-            find_live_fixed_pdo(&status, control::EPR_FIXED_28V_OBJECT_POS),
-            None
-        );
+/// Merge the fields present in `body` over [`ir_measure::IR_CONFIG_DEFAULT`].
+fn parse_ir_measure_json(body: &str) -> Result<ir_measure::IrConfig, &'static str> {
+    let mut cfg = ir_measure::IR_CONFIG_DEFAULT;
+    if let Some(v) = parse_json_i64_optional(body, "\"low_ma\"")? {
+        cfg.low_ma = i32::try_from(v).map_err(|_| "integer out of range")?;
+    }
+    if let Some(v) = parse_json_i64_optional(body, "\"high_ma\"")? {
+        cfg.high_ma = i32::try_from(v).map_err(|_| "integer out of range")?;
+    }
+    if let Some(v) = parse_json_i64_optional(body, "\"cycles\"")? {
+        cfg.cycles = u8::try_from(v).map_err(|_| "integer out of range")?;
+    }
+    if let Some(v) = parse_json_i64_optional(body, "\"settle_ms\"")? {
+        cfg.settle_ms = u32::try_from(v).map_err(|_| "integer out of range")?;
     }
+    if let Some(v) = parse_json_i64_optional(body, "\"sample_ms\"")? {
+        cfg.sample_ms = u32::try_from(v).map_err(|_| "integer out of range")?;
+    }
+    Ok(cfg)
+}
 
-    #[test]
-    fn live_fixed_lookup_uses_index_when_protocol_pos_is_missing() {
-        let mut status = PdStatus {
-            attached: true,
-            ..PdStatus::default()
-        };
-        let _ = status.fixed_pdos.push(loadlynx_protocol::FixedPdo {
-            pos: 0,
-            mv: 5_000,
-            max_ma: 3_000,
-        });
-        let _ = status.fixed_pdos.push(loadlynx_protocol::FixedPdo {
-            pos: 0,
-            mv: 9_000,
-            max_ma: 3_000,
-        });
+/// `POST /api/v1/measure/ir`: validate against the active preset limits and
+/// queue a run; progress and the result are polled via `GET`.
+pub(crate) async fn handle_ir_measure_start(
+    body_in: &str,
+    body_out: &mut String,
+    control: &'static ControlMutex,
+    calibration: &'static CalibrationMutex,
+) -> Result<(), &'static str> {
+    let cfg = match parse_ir_measure_json(body_in) {
+        Ok(cfg) => cfg,
+        Err(msg) => {
+            write_error_body(body_out, "INVALID_REQUEST", msg, false, None);
+            return Err("400 Bad Request");
+        }
+    };
 
-        assert_eq!(
-            find_live_fixed_pdo(&status, 2).map(|pdo| pdo.mv),
-            Some(9_000)
+    let cal_mode = { calibration.lock().await.cal_mode };
+    if cal_mode != CalKind::Off {
+        write_error_body(
+            body_out,
+            "INVALID_STATE",
+            "exit calibration mode before measuring internal resistance",
+            false,
+            None,
         );
+        return Err("409 Conflict");
     }
 
-    #[test]
-    fn commit_verification_accepts_the_profile_applied_to_ram() {
-        let mut candidate = calfmt::ActiveProfile::factory_default(calfmt::DIGITAL_HW_REV);
-        candidate.source = ProfileSource::UserCalibrated;
-        candidate.current_ch1[1].meas_physical = 4_730;
-        let blob = calfmt::serialize_profile(&candidate);
-
-        assert!(serialized_profile_matches(&blob, &candidate));
+    let preset = { control.lock().await.active_preset() };
+    let max_i_ma = preset.max_i_ma_total.min(control::HARD_MAX_I_MA_TOTAL);
+    let v_main_mv = crate::LAST_V_MAIN_MV.load(Ordering::Relaxed).max(0) as i64;
+    let over_power =
+        preset.max_p_mw > 0 && v_main_mv * cfg.high_ma as i64 / 1_000 > preset.max_p_mw as i64;
+    let invalid_field = match cfg.validate(max_i_ma) {
+        Err(field) => Some(field),
+        Ok(()) if over_power => Some("high_ma"),
+        Ok(()) => None,
+    };
+    if let Some(field) = invalid_field {
+        let details = format!(
+            r#"{{"field":"{}","max_i_ma":{},"max_p_mw":{}}}"#,
+            field, max_i_ma, preset.max_p_mw
+        );
+        write_error_body(
+            body_out,
+            "LIMIT_VIOLATION",
+            "IR measurement parameters outside the active preset limits",
+            false,
+            Some(&details),
+        );
+        return Err("422 Unprocessable Entity");
     }
 
-    #[test]
-    fn commit_verification_rejects_corrupted_eeprom_readback() {
-        let mut candidate = calfmt::ActiveProfile::factory_default(calfmt::DIGITAL_HW_REV);
-        candidate.source = ProfileSource::UserCalibrated;
-        let mut blob = calfmt::serialize_profile(&candidate);
-        blob[32] ^= 0x01;
+    ensure_output_enable_allowed(body_out, control, cal_mode).await?;
 
-        assert!(!serialized_profile_matches(&blob, &candidate));
+    if let Err(err) = ir_measure::start(cfg) {
+        write_error_body(body_out, "CONFLICT", err.message(), true, None);
+        return Err("409 Conflict");
     }
+    render_ir_measure_json(body_out);
+    Ok(())
 }
 
-struct CcUpdateRequest {
-    enable: bool,
-    target_i_ma: i32,
+/// `POST /api/v1/measure/ir/cancel`: idempotent; reports the resulting view.
+pub(crate) fn handle_ir_measure_cancel(body_out: &mut String) {
+    let _ = ir_measure::cancel();
+    render_ir_measure_json(body_out);
 }
 
-fn parse_cc_update_json(body: &str) -> Result<CcUpdateRequest, &'static str> {
-    let mut enable: Option<bool> = None;
-    let mut target_i_ma: Option<i32> = None;
-
-    // Very small hand-written JSON parser: looks for `"enable"` and
-    // `"target_i_ma"` keys and extracts their values. This keeps the firmware
-    // free from heavy JSON dependencies.
-    if let Some(idx) = body.find("\"enable\"")
-        && let Some(colon_idx) = body[idx..].find(':')
-    {
-        let value_str = body[idx + colon_idx + 1..].trim_start();
-        if value_str.starts_with("true") {
-            enable = Some(true);
-        } else if value_str.starts_with("false") {
-            enable = Some(false);
-        } else {
-            return Err("enable must be true or false");
-        }
-    }
+/// `ThermalModelView`: saved derating-model parameters, the sink trip the analog
+/// side enforces, the live derate and the accepted range of every field.
+pub(crate) fn render_thermal_json(buf: &mut String) {
+    use thermal::{
+        THERMAL_MARGIN_MC_RANGE, THERMAL_MIN_DERATE_PCT_RANGE, THERMAL_RECOVER_STEP_MS_RANGE,
+        THERMAL_RTH_MC_PER_W_RANGE, THERMAL_STEP_PCT_RANGE, THERMAL_TAU_S_RANGE,
+    };
 
-    if let Some(idx) = body.find("\"target_i_ma\"")
-        && let Some(colon_idx) = body[idx..].find(':')
-    {
-        let mut value_str = body[idx + colon_idx + 1..].trim_start();
-        // Strip leading sign/digits until we hit a delimiter.
-        let mut end = 0usize;
-        for ch in value_str.chars() {
-            if ch == '-' || ch.is_ascii_digit() {
-                end += ch.len_utf8();
-            } else {
-                break;
-            }
-        }
-        value_str = &value_str[..end];
-        match value_str.parse::<i32>() {
-            Ok(v) => target_i_ma = Some(v),
-            Err(_) => return Err("target_i_ma must be an integer"),
+    let cfg = thermal::config();
+    buf.clear();
+    let _ = core::write!(
+        buf,
+        r#"{{"rth_mc_per_w":{},"tau_s":{},"margin_mc":{},"min_derate_pct":{},"step_pct":{},"recover_step_ms":{},"temp_trip_mc":{},"derate_pct":{},"bounds":{{"#,
+        cfg.rth_mc_per_w,
+        cfg.tau_s,
+        cfg.margin_mc,
+        cfg.min_derate_pct,
+        cfg.step_pct,
+        cfg.recover_step_ms,
+        thermal::sink_trip_mc(),
+        thermal::derate_pct()
+    );
+    let bounds = [
+        ("rth_mc_per_w", THERMAL_RTH_MC_PER_W_RANGE),
+        ("tau_s", THERMAL_TAU_S_RANGE),
+        ("margin_mc", THERMAL_MARGIN_MC_RANGE),
+        ("min_derate_pct", THERMAL_MIN_DERATE_PCT_RANGE),
+        ("step_pct", THERMAL_STEP_PCT_RANGE),
+        ("recover_step_ms", THERMAL_RECOVER_STEP_MS_RANGE),
+    ];
+    for (idx, (name, range)) in bounds.iter().enumerate() {
+        if idx != 0 {
+            buf.push(',');
         }
+        let _ = core::write!(
+            buf,
+            r#""{}":{{"min":{},"max":{}}}"#,
+            name,
+            range.start(),
+            range.end()
+        );
     }
-
-    let enable = enable.ok_or("missing field enable")?;
-    let target_i_ma = target_i_ma.ok_or("missing field target_i_ma")?;
-
-    Ok(CcUpdateRequest {
-        enable,
-        target_i_ma,
-    })
+    buf.push_str("}}");
 }
 
-struct SoftResetRequest<'a> {
-    reason_str: &'a str,
-    reason: SoftResetReason,
+/// Merge the fields present in `body` over `base`; `"reset":true` starts from
+/// the built-in model.
+fn parse_thermal_update_json(
+    body: &str,
+    base: thermal::ThermalModelConfig,
+) -> Result<thermal::ThermalModelConfig, &'static str> {
+    let mut cfg = if parse_json_bool_value(body, "\"reset\"").unwrap_or(false) {
+        thermal::THERMAL_MODEL_DEFAULT
+    } else {
+        base
+    };
+    let fields: [(&str, &mut u32); 4] = [
+        ("\"rth_mc_per_w\"", &mut cfg.rth_mc_per_w),
+        ("\"tau_s\"", &mut cfg.tau_s),
+        ("\"margin_mc\"", &mut cfg.margin_mc),
+        ("\"recover_step_ms\"", &mut cfg.recover_step_ms),
+    ];
+    for (key, slot) in fields {
+        if let Some(v) = parse_json_i64_optional(body, key)? {
+            *slot = u32::try_from(v).map_err(|_| "integer out of range")?;
+        }
+    }
+    let pct_fields: [(&str, &mut u8); 2] = [
+        ("\"min_derate_pct\"", &mut cfg.min_derate_pct),
+        ("\"step_pct\"", &mut cfg.step_pct),
+    ];
+    for (key, slot) in pct_fields {
+        if let Some(v) = parse_json_i64_optional(body, key)? {
+            *slot = u8::try_from(v).map_err(|_| "integer out of range")?;
+        }
+    }
+    Ok(cfg)
 }
 
-fn parse_soft_reset_json(body: &str) -> Result<SoftResetRequest<'_>, &'static str> {
-    // Very small hand-written JSON parser: looks for a `"reason"` string field
-    // and maps it onto the SoftResetReason enum. This keeps the firmware free
-    // from heavy JSON dependencies.
-    let idx = body.find("\"reason\"").ok_or("missing field reason")?;
-    let colon_idx = body[idx..].find(':').ok_or("malformed reason field")?;
-    let mut value_str = body[idx + colon_idx + 1..].trim_start();
-    if !value_str.starts_with('"') {
-        return Err("reason must be a string");
+pub(crate) async fn handle_thermal_update(
+    body_in: &str,
+    body_out: &mut String,
+    eeprom: &'static EepromMutex,
+) -> Result<(), &'static str> {
+    // Hold the EEPROM lock across the merge so concurrent HTTP/USB writers
+    // cannot persist a stale one.
+    let mut ep = eeprom.lock().await;
+    let current = thermal::config();
+    let cfg = match parse_thermal_update_json(body_in, current) {
+        Ok(cfg) => cfg,
+        Err(msg) => {
+            write_error_body(body_out, "INVALID_REQUEST", msg, false, None);
+            return Err("400 Bad Request");
+        }
+    };
+    if let Err(field) = cfg.validate() {
+        let details = format!(r#"{{"field":"{}"}}"#, field);
+        write_error_body(
+            body_out,
+            "LIMIT_VIOLATION",
+            "thermal model parameter outside the accepted range",
+            false,
+            Some(&details),
+        );
+        return Err("422 Unprocessable Entity");
     }
-    value_str = &value_str[1..];
-    let end = value_str.find('"').ok_or("reason must be a string")?;
-    let reason_str = &value_str[..end];
 
-    let reason = match reason_str {
-        "manual" => SoftResetReason::Manual,
-        "firmware_update" => SoftResetReason::FirmwareUpdate,
-        "ui_recover" => SoftResetReason::UiRecover,
-        "link_recover" => SoftResetReason::LinkRecover,
-        _ => {
-            return Err(
-                "reason must be one of \"manual\", \"firmware_update\", \"ui_recover\", \"link_recover\"",
-            );
+    if cfg != current {
+        if let Err(_err) = ep
+            .write_thermal_blob(&control::encode_thermal_blob(&cfg))
+            .await
+        {
+            write_error_body(body_out, "UNAVAILABLE", "EEPROM write failed", true, None);
+            return Err("503 Service Unavailable");
         }
-    };
+        thermal::set_config(cfg);
+        info!(
+            "thermal model saved via API (rth={}mC/W tau={}s margin={}mC min_derate={}% step={}% recover={}ms)",
+            cfg.rth_mc_per_w,
+            cfg.tau_s,
+            cfg.margin_mc,
+            cfg.min_derate_pct,
+            cfg.step_pct,
+            cfg.recover_step_ms
+        );
+    }
+    drop(ep);
 
-    Ok(SoftResetRequest { reason_str, reason })
+    render_thermal_json(body_out);
+    Ok(())
 }
 
 pub(crate) fn handle_soft_reset_http(
@@ -3959,11 +6360,100 @@ pub(crate) async fn render_calibration_profile_json(
         );
         body_out.push('}');
     }
-    body_out.push_str("]}");
+    body_out.push_str("],");
+
+    // temp_comp (fmt v4; all-zero coefficients = disabled)
+    body_out.push_str("\"temp_comp\":{");
+    for (idx, kind) in [
+        CurveKind::CurrentCh1,
+        CurveKind::CurrentCh2,
+        CurveKind::VLocal,
+        CurveKind::VRemote,
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        if idx != 0 {
+            body_out.push(',');
+        }
+        let tc = profile.temp_comp_for(kind);
+        let _ = core::write!(
+            body_out,
+            "\"{}\":{{\"sensor\":\"{}\",\"t_ref_mc\":{},\"gain_ppm_per_c\":{},\"offset_micro_per_c\":{}}}",
+            curve_kind_name(kind),
+            tc.sensor.as_str(),
+            tc.t_ref_mc,
+            tc.gain_ppm_per_c,
+            tc.offset_micro_per_c
+        );
+    }
+    body_out.push_str("}}");
 
     Ok(())
 }
 
+fn curve_kind_name(kind: CurveKind) -> &'static str {
+    match kind {
+        CurveKind::CurrentCh1 => "current_ch1",
+        CurveKind::CurrentCh2 => "current_ch2",
+        CurveKind::VLocal => "v_local",
+        CurveKind::VRemote => "v_remote",
+    }
+}
+
+/// Optional `"temp_comp":{"sensor":"sink_core"|"mcu","t_ref_mc":..,
+/// "gain_ppm_per_c":..,"offset_micro_per_c":..}` on apply/commit.
+/// `Ok(None)` keeps the curve's current coefficients.
+fn parse_temp_comp(body: &str) -> Result<Option<calfmt::TempComp>, &'static str> {
+    let Some(idx) = body.find("\"temp_comp\"") else {
+        return Ok(None);
+    };
+    let rest = &body[idx + "\"temp_comp\"".len()..];
+    let colon = rest.find(':').ok_or("malformed temp_comp field")?;
+    let rest = rest[colon + 1..].trim_start();
+    if !rest.starts_with('{') {
+        return Err("temp_comp must be an object");
+    }
+    let end = rest.find('}').ok_or("temp_comp must be an object")?;
+    let obj = &rest[1..end];
+
+    fn field<'a>(obj: &'a str, key: &str) -> Option<&'a str> {
+        let idx = obj.find(key)?;
+        let colon = obj[idx..].find(':')?;
+        Some(obj[idx + colon + 1..].trim_start())
+    }
+    let int_field = |key: &str, default: i32| -> Result<i32, &'static str> {
+        let Some(s) = field(obj, key) else {
+            return Ok(default);
+        };
+        let end = s
+            .char_indices()
+            .find(|(i, ch)| !(ch.is_ascii_digit() || (*i == 0 && *ch == '-')))
+            .map(|(i, _)| i)
+            .unwrap_or(s.len());
+        s[..end]
+            .parse::<i32>()
+            .map_err(|_| "temp_comp fields must be integers")
+    };
+
+    let sensor = match field(obj, "\"sensor\"") {
+        None => calfmt::TempSensor::SinkCore,
+        Some(s) if s.starts_with("\"sink_core\"") => calfmt::TempSensor::SinkCore,
+        Some(s) if s.starts_with("\"mcu\"") => calfmt::TempSensor::Mcu,
+        Some(_) => return Err("temp_comp.sensor must be \"sink_core\" or \"mcu\""),
+    };
+    let tc = calfmt::TempComp {
+        sensor,
+        t_ref_mc: int_field("\"t_ref_mc\"", calfmt::TempComp::NONE.t_ref_mc)?,
+        gain_ppm_per_c: int_field("\"gain_ppm_per_c\"", 0)?,
+        offset_micro_per_c: int_field("\"offset_micro_per_c\"", 0)?,
+    };
+    if !tc.is_valid() {
+        return Err("temp_comp out of range");
+    }
+    Ok(Some(tc))
+}
+
 fn parse_string_field<'a>(body: &'a str, key: &str) -> Result<&'a str, &'static str> {
     let needle = match key {
         "kind" => "\"kind\"",
@@ -4435,9 +6925,20 @@ pub(crate) async fn handle_calibration_apply(
         }
     };
 
+    let temp_comp = match parse_temp_comp(body_in) {
+        Ok(v) => v,
+        Err(msg) => {
+            write_error_body(body_out, "INVALID_REQUEST", msg, false, None);
+            return Err("400 Bad Request");
+        }
+    };
+
     {
         let mut guard = calibration.lock().await;
         *guard.profile.points_for_mut(kind) = points;
+        if let Some(tc) = temp_comp {
+            *guard.profile.temp_comp_for_mut(kind) = tc;
+        }
         // Apply is RAM-only (no EEPROM write), but the active profile is now user-supplied.
         guard.profile.source = ProfileSource::UserCalibrated;
         guard.profile.fmt_version = calfmt::CAL_FMT_VERSION_LATEST;
@@ -4472,7 +6973,15 @@ pub(crate) async fn handle_calibration_commit(
         }
     };
 
-    let points = match parse_points_for_kind(kind, body_in) {
+    let points = match parse_points_for_kind(kind, body_in) {
+        Ok(v) => v,
+        Err(msg) => {
+            write_error_body(body_out, "INVALID_REQUEST", msg, false, None);
+            return Err("400 Bad Request");
+        }
+    };
+
+    let temp_comp = match parse_temp_comp(body_in) {
         Ok(v) => v,
         Err(msg) => {
             write_error_body(body_out, "INVALID_REQUEST", msg, false, None);
@@ -4480,13 +6989,16 @@ pub(crate) async fn handle_calibration_commit(
         }
     };
 
-    let candidate = {
+    let (slot, candidate) = {
         let guard = calibration.lock().await;
         let mut candidate = guard.profile.clone();
         *candidate.points_for_mut(kind) = points;
+        if let Some(tc) = temp_comp {
+            *candidate.temp_comp_for_mut(kind) = tc;
+        }
         candidate.source = ProfileSource::UserCalibrated;
         candidate.fmt_version = calfmt::CAL_FMT_VERSION_LATEST;
-        candidate
+        (guard.slots.active_slot(), candidate)
     };
     let blob = calfmt::serialize_profile(&candidate);
     if !serialized_profile_matches(&blob, &candidate) {
@@ -4504,13 +7016,13 @@ pub(crate) async fn handle_calibration_commit(
 
     let verified = {
         let mut ep = eeprom.lock().await;
-        if ep.write_profile_blob(&blob).await.is_err() {
+        if ep.write_profile_blob(slot, &blob).await.is_err() {
             calibration.lock().await.persistence_status =
                 crate::CalibrationPersistenceStatus::WriteFailed;
             write_error_body(body_out, "UNAVAILABLE", "EEPROM write failed", true, None);
             return Err("503 Service Unavailable");
         }
-        ep.verify_profile_blob(&blob).await.unwrap_or(false)
+        ep.verify_profile_blob(slot, &blob).await.unwrap_or(false)
     };
     if !verified {
         calibration.lock().await.persistence_status =
@@ -4524,6 +7036,25 @@ pub(crate) async fn handle_calibration_commit(
         );
         return Err("503 Service Unavailable");
     }
+    if !store_slot_entry(
+        calibration,
+        eeprom,
+        slot,
+        Some(calfmt::slots::profile_blob_crc32(&blob)),
+    )
+    .await
+    {
+        calibration.lock().await.persistence_status =
+            crate::CalibrationPersistenceStatus::WriteFailed;
+        write_error_body(
+            body_out,
+            "UNAVAILABLE",
+            "EEPROM slot directory write failed",
+            true,
+            None,
+        );
+        return Err("503 Service Unavailable");
+    }
     {
         let mut guard = calibration.lock().await;
         guard.profile = candidate;
@@ -4556,6 +7087,7 @@ fn profile_equals_factory(profile: &calfmt::ActiveProfile) -> bool {
         && profile.current_ch2 == factory.current_ch2
         && profile.v_local == factory.v_local
         && profile.v_remote == factory.v_remote
+        && profile.temp_comp == factory.temp_comp
 }
 
 pub(crate) async fn handle_calibration_reset(
@@ -4619,6 +7151,7 @@ pub(crate) async fn handle_calibration_reset(
     };
 
     let factory = calfmt::ActiveProfile::factory_default(calfmt::DIGITAL_HW_REV);
+    let slot = calibration.lock().await.slots.active_slot();
     let (result_kind, candidate, should_clear) = if let Some(kind) = reset_kind {
         let guard = calibration.lock().await;
         let mut candidate = guard.profile.clone();
@@ -4629,6 +7162,7 @@ pub(crate) async fn handle_calibration_reset(
             CurveKind::VRemote => factory.v_remote.clone(),
         };
         *candidate.points_for_mut(kind) = factory_curve;
+        *candidate.temp_comp_for_mut(kind) = calfmt::TempComp::NONE;
         if profile_equals_factory(&candidate) {
             (None, factory.clone(), true)
         } else {
@@ -4639,18 +7173,21 @@ pub(crate) async fn handle_calibration_reset(
         (None, factory.clone(), true)
     };
 
-    let verified = {
+    let (verified, crc) = {
         let mut ep = eeprom.lock().await;
         if should_clear {
-            ep.clear_profile_blob().await.is_ok()
-                && ep.profile_blob_is_cleared().await.unwrap_or(false)
+            let cleared = ep.clear_profile_blob(slot).await.is_ok()
+                && ep.profile_blob_is_cleared(slot).await.unwrap_or(false);
+            (cleared, None)
         } else {
             let blob = calfmt::serialize_profile(&candidate);
-            serialized_profile_matches(&blob, &candidate)
-                && ep.write_profile_blob(&blob).await.is_ok()
-                && ep.verify_profile_blob(&blob).await.unwrap_or(false)
+            let written = serialized_profile_matches(&blob, &candidate)
+                && ep.write_profile_blob(slot, &blob).await.is_ok()
+                && ep.verify_profile_blob(slot, &blob).await.unwrap_or(false);
+            (written, Some(calfmt::slots::profile_blob_crc32(&blob)))
         }
     };
+    let verified = verified && store_slot_entry(calibration, eeprom, slot, crc).await;
     if !verified {
         calibration.lock().await.persistence_status = if should_clear {
             crate::CalibrationPersistenceStatus::WriteFailed
@@ -4681,6 +7218,490 @@ pub(crate) async fn handle_calibration_reset(
     Ok(result_kind)
 }
 
+/// Mark `slot` as holding a profile with blob CRC `crc` (or as empty) and
+/// persist the directory. The in-RAM directory only changes once the EEPROM
+/// copy has been read back.
+async fn store_slot_entry(
+    calibration: &'static CalibrationMutex,
+    eeprom: &'static EepromMutex,
+    slot: usize,
+    crc: Option<u32>,
+) -> bool {
+    let mut dir = calibration.lock().await.slots.clone();
+    dir.slots[slot].used = crc.is_some();
+    dir.slots[slot].crc32 = crc.unwrap_or(0);
+    persist_slot_dir(calibration, eeprom, dir).await
+}
+
+async fn persist_slot_dir(
+    calibration: &'static CalibrationMutex,
+    eeprom: &'static EepromMutex,
+    dir: calfmt::slots::SlotDirectory,
+) -> bool {
+    let blob = dir.encode();
+    let ok = eeprom
+        .lock()
+        .await
+        .write_slot_dir_blob(&blob)
+        .await
+        .unwrap_or(false);
+    if ok {
+        calibration.lock().await.slots = dir;
+    }
+    ok
+}
+
+fn parse_slot_index(body_in: &str, body_out: &mut String) -> Result<usize, &'static str> {
+    match parse_json_i64(body_in, "\"slot\"") {
+        Ok(slot) if (0..calfmt::slots::CAL_SLOT_COUNT as i64).contains(&slot) => Ok(slot as usize),
+        Ok(_) => {
+            write_error_body(
+                body_out,
+                "INVALID_REQUEST",
+                "slot is out of range",
+                false,
+                None,
+            );
+            Err("400 Bad Request")
+        }
+        Err(msg) => {
+            write_error_body(body_out, "INVALID_REQUEST", msg, false, None);
+            Err("400 Bad Request")
+        }
+    }
+}
+
+pub(crate) async fn render_calibration_slots_json(
+    body_out: &mut String,
+    calibration: &'static CalibrationMutex,
+) {
+    let guard = calibration.lock().await;
+    let dir = &guard.slots;
+    body_out.clear();
+    let _ = core::write!(body_out, "{{\"active\":{},\"slots\":[", dir.active);
+    for (idx, entry) in dir.slots.iter().enumerate() {
+        if idx != 0 {
+            body_out.push(',');
+        }
+        // Names are restricted to JSON-safe ASCII by `validate_slot_name`.
+        let _ = core::write!(
+            body_out,
+            "{{\"slot\":{},\"name\":\"{}\",\"used\":{},\"active\":{}}}",
+            idx,
+            entry.name.as_str(),
+            entry.used,
+            idx == dir.active_slot()
+        );
+    }
+    body_out.push_str("]}");
+}
+
+/// Switch the active profile to `slot`. An empty slot activates factory
+/// defaults; the caller re-sends all curves to the analog board.
+pub(crate) async fn handle_calibration_slot_activate(
+    body_in: &str,
+    body_out: &mut String,
+    calibration: &'static CalibrationMutex,
+    eeprom: &'static EepromMutex,
+) -> Result<(), &'static str> {
+    ensure_calibration_api_available(body_out)?;
+    let slot = parse_slot_index(body_in, body_out)?;
+    let (dir, cal_mode) = {
+        let guard = calibration.lock().await;
+        (guard.slots.clone(), guard.cal_mode)
+    };
+    if cal_mode != CalKind::Off {
+        write_error_body(
+            body_out,
+            "INVALID_STATE",
+            "leave calibration mode before switching slots",
+            false,
+            None,
+        );
+        return Err("409 Conflict");
+    }
+
+    let (profile, status, crc) = {
+        let mut ep = eeprom.lock().await;
+        crate::load_calibration_slot(&mut ep, slot).await
+    };
+    let entry = &dir.slots[slot];
+    let usable = match crc {
+        Some(crc) => entry.used && crc == entry.crc32,
+        None => !entry.used && status == crate::CalibrationPersistenceStatus::FactoryDefault,
+    };
+    if !usable {
+        write_error_body(
+            body_out,
+            "INVALID_STATE",
+            "slot profile is invalid",
+            false,
+            None,
+        );
+        return Err("409 Conflict");
+    }
+
+    let mut dir = dir;
+    dir.active = slot as u8;
+    if !persist_slot_dir(calibration, eeprom, dir).await {
+        write_error_body(
+            body_out,
+            "UNAVAILABLE",
+            "EEPROM slot directory write failed",
+            true,
+            None,
+        );
+        return Err("503 Service Unavailable");
+    }
+    {
+        let mut guard = calibration.lock().await;
+        guard.profile = profile;
+        guard.persistence_status = status;
+    }
+
+    body_out.clear();
+    body_out.push_str(r#"{"ok":true}"#);
+    Ok(())
+}
+
+pub(crate) async fn handle_calibration_slot_rename(
+    body_in: &str,
+    body_out: &mut String,
+    calibration: &'static CalibrationMutex,
+    eeprom: &'static EepromMutex,
+) -> Result<(), &'static str> {
+    let slot = parse_slot_index(body_in, body_out)?;
+    let name = match parse_json_str(body_in, "\"name\"")
+        .and_then(|name| calfmt::slots::validate_slot_name(name).map_err(|_| "invalid name"))
+    {
+        Ok(name) => name,
+        Err(_) => {
+            write_error_body(
+                body_out,
+                "INVALID_REQUEST",
+                "name must be 1..16 characters of A-Z a-z 0-9 space _ . -",
+                false,
+                None,
+            );
+            return Err("400 Bad Request");
+        }
+    };
+    let mut dir = calibration.lock().await.slots.clone();
+    dir.slots[slot].name = name;
+    if !persist_slot_dir(calibration, eeprom, dir).await {
+        write_error_body(
+            body_out,
+            "UNAVAILABLE",
+            "EEPROM slot directory write failed",
+            true,
+            None,
+        );
+        return Err("503 Service Unavailable");
+    }
+
+    body_out.clear();
+    body_out.push_str(r#"{"ok":true}"#);
+    Ok(())
+}
+
+/// Erase an inactive slot's profile and name.
+pub(crate) async fn handle_calibration_slot_delete(
+    body_in: &str,
+    body_out: &mut String,
+    calibration: &'static CalibrationMutex,
+    eeprom: &'static EepromMutex,
+) -> Result<(), &'static str> {
+    let slot = parse_slot_index(body_in, body_out)?;
+    let mut dir = calibration.lock().await.slots.clone();
+    if slot == dir.active_slot() {
+        write_error_body(
+            body_out,
+            "INVALID_STATE",
+            "cannot delete the active slot",
+            false,
+            None,
+        );
+        return Err("409 Conflict");
+    }
+    let cleared = {
+        let mut ep = eeprom.lock().await;
+        ep.clear_profile_blob(slot).await.is_ok()
+            && ep.profile_blob_is_cleared(slot).await.unwrap_or(false)
+    };
+    dir.slots[slot] = calfmt::slots::SlotEntry::default();
+    if !cleared || !persist_slot_dir(calibration, eeprom, dir).await {
+        write_error_body(
+            body_out,
+            "UNAVAILABLE",
+            "EEPROM slot erase failed",
+            true,
+            None,
+        );
+        return Err("503 Service Unavailable");
+    }
+
+    body_out.clear();
+    body_out.push_str(r#"{"ok":true}"#);
+    Ok(())
+}
+
+/// Parse `[[raw_100uv, reference], ...]` check points; an optional third
+/// element overrides the tolerance of that point.
+fn parse_verify_points(
+    body: &str,
+) -> Result<Vec<(i16, i32, Option<i32>), { calfmt::verify::MAX_VERIFY_POINTS }>, &'static str> {
+    let arr = parse_points_array(body)?;
+    let mut rest = arr.trim_start();
+    if rest.starts_with('{') {
+        return Err("points must be encoded as [[raw_100uv, reference], ...]");
+    }
+    let mut out = Vec::new();
+    while let Some(start) = rest.find('[') {
+        rest = &rest[start + 1..];
+        let end = rest.find(']').ok_or("malformed points array")?;
+        let tuple = &rest[..end];
+        rest = &rest[end + 1..];
+
+        let mut values = [0i32; 3];
+        let mut n = 0usize;
+        for item in tuple.split(',') {
+            if n >= values.len() {
+                return Err("point tuple is too long");
+            }
+            values[n] = item
+                .trim()
+                .parse::<i32>()
+                .map_err(|_| "point tuple must contain integers")?;
+            n += 1;
+        }
+        if n < 2 {
+            return Err(
+                "check point must be [raw_100uv, reference] or [raw_100uv, reference, tolerance]",
+            );
+        }
+        let raw = i16::try_from(values[0]).map_err(|_| "raw_100uv out of range for i16")?;
+        let tolerance = (n == 3).then_some(values[2]);
+        if tolerance.is_some_and(|t| t < 0) {
+            return Err("tolerance must not be negative");
+        }
+        out.push((raw, values[1], tolerance))
+            .map_err(|_| "too many check points (max 12)")?;
+    }
+    if out.is_empty() {
+        return Err("points must contain 1..12 items");
+    }
+    Ok(out)
+}
+
+struct VerifyRequest {
+    kind: CurveKind,
+    points: Vec<(i16, i32, Option<i32>), { calfmt::verify::MAX_VERIFY_POINTS }>,
+    abs: i32,
+    ppm: i32,
+    ts_unix_ms: u64,
+}
+
+/// `{"kind", "points", "tolerance_abs"?, "tolerance_ppm"?, "ts_unix_ms"?}`.
+fn parse_verify_request(body: &str) -> Result<VerifyRequest, &'static str> {
+    let kind = parse_curve_kind(parse_string_field(body, "kind")?)?;
+    let points = parse_verify_points(body)?;
+    let abs = parse_json_i64_optional(body, "\"tolerance_abs\"")?
+        .unwrap_or(calfmt::verify::DEFAULT_TOLERANCE_ABS as i64);
+    let ppm = parse_json_i64_optional(body, "\"tolerance_ppm\"")?
+        .unwrap_or(calfmt::verify::DEFAULT_TOLERANCE_PPM as i64);
+    if !(0..=i32::MAX as i64).contains(&abs) || !(0..=1_000_000).contains(&ppm) {
+        return Err("tolerance_abs/tolerance_ppm out of range");
+    }
+    let ts_unix_ms = parse_json_i64_optional(body, "\"ts_unix_ms\"")?.unwrap_or(0);
+    if ts_unix_ms < 0 {
+        return Err("ts_unix_ms must not be negative");
+    }
+    Ok(VerifyRequest {
+        kind,
+        points,
+        abs: abs as i32,
+        ppm: ppm as i32,
+        ts_unix_ms: ts_unix_ms as u64,
+    })
+}
+
+fn write_verify_record_json(
+    body_out: &mut String,
+    record: &calfmt::verify::VerifyRecord,
+    current: bool,
+) {
+    let _ = core::write!(
+        body_out,
+        "{{\"kind\":\"{}\",\"ts_unix_ms\":",
+        curve_kind_name(record.kind)
+    );
+    if record.ts_unix_ms == 0 {
+        body_out.push_str("null");
+    } else {
+        let _ = core::write!(body_out, "{}", record.ts_unix_ms);
+    }
+    let _ = core::write!(
+        body_out,
+        ",\"slot\":{},\"profile_crc32\":{},\"current\":{},\"passed\":{},\"worst_error\":",
+        record.slot,
+        record.profile_crc32,
+        current,
+        record.passed()
+    );
+    match record.worst_error() {
+        Some(err) => {
+            let _ = core::write!(body_out, "{}", err);
+        }
+        None => body_out.push_str("null"),
+    }
+    body_out.push_str(",\"points\":[");
+    for (idx, p) in record.points.iter().enumerate() {
+        if idx != 0 {
+            body_out.push(',');
+        }
+        let _ = core::write!(
+            body_out,
+            "{{\"raw_100uv\":{},\"reference\":{},\"reading\":{},\"error\":{},\"tolerance\":{},\"passed\":{}}}",
+            p.raw_100uv,
+            p.reference,
+            p.reading,
+            p.error(),
+            p.tolerance,
+            p.passed()
+        );
+    }
+    body_out.push_str("]}");
+}
+
+/// The record was taken against the profile that is active now.
+fn verify_record_is_current(
+    record: &calfmt::verify::VerifyRecord,
+    dir: &calfmt::slots::SlotDirectory,
+) -> bool {
+    let entry = &dir.slots[dir.active_slot()];
+    let crc = if entry.used { entry.crc32 } else { 0 };
+    record.slot == dir.active && record.profile_crc32 == crc
+}
+
+/// Compare check points against the active profile without changing it, and
+/// store the result as the latest verification record for the curve.
+pub(crate) async fn handle_calibration_verify(
+    body_in: &str,
+    body_out: &mut String,
+    calibration: &'static CalibrationMutex,
+    telemetry: &'static TelemetryMutex,
+    eeprom: &'static EepromMutex,
+) -> Result<(), &'static str> {
+    let request = match parse_verify_request(body_in) {
+        Ok(v) => v,
+        Err(msg) => {
+            write_error_body(body_out, "INVALID_REQUEST", msg, false, None);
+            return Err("400 Bad Request");
+        }
+    };
+    let kind = request.kind;
+
+    let temps = telemetry
+        .lock()
+        .await
+        .last_status
+        .map(|s| (s.sink_core_temp_mc, s.mcu_temp_mc));
+    let (record, dir) = {
+        let guard = calibration.lock().await;
+        let dir = guard.slots.clone();
+        let entry = &dir.slots[dir.active_slot()];
+        // Without telemetry, evaluate at the reference temperature.
+        let t_ref = guard.profile.temp_comp_for(kind).t_ref_mc;
+        let (sink_core_mc, mcu_mc) = temps.unwrap_or((t_ref, t_ref));
+        let mut record = calfmt::verify::VerifyRecord {
+            kind,
+            ts_unix_ms: request.ts_unix_ms,
+            slot: dir.active,
+            profile_crc32: if entry.used { entry.crc32 } else { 0 },
+            points: Vec::new(),
+        };
+        for (raw, reference, tolerance) in request.points {
+            let Some(reading) =
+                calfmt::verify::profile_reading(&guard.profile, kind, raw, sink_core_mc, mcu_mc)
+            else {
+                write_error_body(
+                    body_out,
+                    "INVALID_STATE",
+                    "active calibration curve cannot be evaluated",
+                    false,
+                    None,
+                );
+                return Err("409 Conflict");
+            };
+            let _ = record.points.push(calfmt::verify::VerifyPoint {
+                raw_100uv: raw,
+                reference,
+                reading,
+                tolerance: tolerance.unwrap_or_else(|| {
+                    calfmt::verify::tolerance_for(reference, request.abs, request.ppm)
+                }),
+            });
+        }
+        (record, dir)
+    };
+
+    let stored = eeprom
+        .lock()
+        .await
+        .write_verify_blob(kind, &record.encode())
+        .await
+        .unwrap_or(false);
+    if !stored {
+        warn!("calibration verify: EEPROM record write failed");
+    }
+
+    body_out.clear();
+    write_verify_record_json(body_out, &record, verify_record_is_current(&record, &dir));
+    body_out.pop();
+    let _ = core::write!(body_out, ",\"stored\":{}}}", stored);
+    Ok(())
+}
+
+/// Latest stored verification record per curve (`null` when none).
+pub(crate) async fn render_calibration_verify_json(
+    body_out: &mut String,
+    calibration: &'static CalibrationMutex,
+    eeprom: &'static EepromMutex,
+) {
+    let dir = calibration.lock().await.slots.clone();
+    body_out.clear();
+    body_out.push_str("{\"results\":{");
+    for (idx, kind) in [
+        CurveKind::CurrentCh1,
+        CurveKind::CurrentCh2,
+        CurveKind::VLocal,
+        CurveKind::VRemote,
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        if idx != 0 {
+            body_out.push(',');
+        }
+        let _ = core::write!(body_out, "\"{}\":", curve_kind_name(kind));
+        let record = eeprom
+            .lock()
+            .await
+            .read_verify_blob(kind)
+            .await
+            .ok()
+            .and_then(|blob| calfmt::verify::VerifyRecord::decode(&blob))
+            .filter(|record| record.kind == kind);
+        match record {
+            Some(record) => {
+                write_verify_record_json(body_out, &record, verify_record_is_current(&record, &dir))
+            }
+            None => body_out.push_str("null"),
+        }
+    }
+    body_out.push_str("}}");
+}
+
 pub(crate) fn parse_calibration_mode_request(
     body_in: &str,
     body_out: &mut String,
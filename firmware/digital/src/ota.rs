@@ -0,0 +1,557 @@
+//! Dual-slot over-the-air updates of the digital firmware.
+//!
+//! `partitions.csv` gives the app two OTA slots. `POST /api/v1/firmware`
+//! streams a bare app image (`espflash save-image` without `--merge`) into the
+//! inactive slot while hashing it, reads the slot back, and only activates it
+//! when both digests match the SHA-256 the client took from the firmware
+//! catalog. The new image boots in the `New` state.
+//!
+//! The ESP-IDF bootloader that espflash installs is built without app
+//! rollback, so rollback lives here: [`boot_check`] counts boots of an
+//! unconfirmed image in EEPROM and switches back to the other slot once
+//! `MAX_UNCONFIRMED_BOOTS` is exceeded, and [`ota_confirm_task`] marks the
+//! image valid after it has stayed up for `CONFIRM_AFTER`.
+//!
+//! Uploads carry `Authorization: Bearer <token>`. The token lives in the EEPROM
+//! OTA blob and is only readable over USB (`get_ota_token`, `rotate_ota_token`);
+//! until it has been read once, no token exists and uploads are refused.
+
+#![cfg_attr(not(feature = "net_http"), allow(dead_code))]
+
+use core::cell::Cell;
+use core::fmt::Write;
+
+use defmt::*;
+use embassy_sync::blocking_mutex::{Mutex as BlockingMutex, raw::CriticalSectionRawMutex};
+use embassy_sync::mutex::Mutex;
+use embassy_time::{Duration, Timer};
+use embedded_storage::{ReadStorage, Storage};
+use esp_bootloader_esp_idf::ota::OtaImageState;
+use esp_bootloader_esp_idf::ota_updater::OtaUpdater;
+use esp_bootloader_esp_idf::partitions::{self, AppPartitionSubType};
+use esp_hal::rng::Rng;
+use esp_storage::FlashStorage;
+
+use crate::EepromMutex;
+use crate::eeprom::{self, OTA_TOKEN_LEN, OtaBlobParts};
+
+/// Unconfirmed boots of a new image before falling back to the other slot.
+pub const MAX_UNCONFIRMED_BOOTS: u8 = 3;
+/// Uptime after which a new image counts as healthy.
+const CONFIRM_AFTER: Duration = Duration::from_secs(60);
+pub const TOKEN_HEX_LEN: usize = 2 * OTA_TOKEN_LEN;
+const FLASH_SECTOR_LEN: usize = 4096;
+
+static FLASH: Mutex<CriticalSectionRawMutex, Option<FlashStorage<'static>>> = Mutex::new(None);
+
+pub async fn init(flash: esp_hal::peripherals::FLASH<'static>) {
+    *FLASH.lock().await = Some(FlashStorage::new(flash));
+}
+
+/// Run `f` against a fresh `OtaUpdater`; the partition table is re-read on
+/// every call so no flash borrow outlives the lock.
+async fn with_updater<R>(
+    f: impl FnOnce(&mut OtaUpdater<'_, FlashStorage<'static>>) -> Result<R, &'static str>,
+) -> Result<R, &'static str> {
+    let mut guard = FLASH.lock().await;
+    let flash = guard.as_mut().ok_or("flash storage not initialized")?;
+    let mut buffer = [0u8; partitions::PARTITION_TABLE_MAX_LEN];
+    let mut ota =
+        OtaUpdater::new(flash, &mut buffer).map_err(|_| "partition table has no OTA slots")?;
+    f(&mut ota)
+}
+
+pub const fn slot_name(slot: AppPartitionSubType) -> &'static str {
+    match slot {
+        AppPartitionSubType::Factory => "factory",
+        AppPartitionSubType::Ota0 => "ota_0",
+        AppPartitionSubType::Ota1 => "ota_1",
+        _ => "other",
+    }
+}
+
+pub const fn image_state_name(state: OtaImageState) -> &'static str {
+    match state {
+        OtaImageState::New => "new",
+        OtaImageState::PendingVerify => "pending_verify",
+        OtaImageState::Valid => "valid",
+        OtaImageState::Invalid => "invalid",
+        OtaImageState::Aborted => "aborted",
+        OtaImageState::Undefined => "undefined",
+    }
+}
+
+const fn is_unconfirmed(state: OtaImageState) -> bool {
+    matches!(state, OtaImageState::New | OtaImageState::PendingVerify)
+}
+
+async fn read_blob(eeprom: &'static EepromMutex) -> Option<OtaBlobParts> {
+    let blob = eeprom.lock().await.read_ota_blob().await.ok()?;
+    eeprom::decode_ota_blob(&blob)
+}
+
+async fn write_blob(
+    eeprom: &'static EepromMutex,
+    parts: &OtaBlobParts,
+) -> Result<(), &'static str> {
+    eeprom
+        .lock()
+        .await
+        .write_ota_blob(&eeprom::encode_ota_blob(parts))
+        .await
+        .map_err(|_| "EEPROM write failed")
+}
+
+fn random_token() -> [u8; OTA_TOKEN_LEN] {
+    let rng = Rng::new();
+    let mut token = [0u8; OTA_TOKEN_LEN];
+    for chunk in token.chunks_mut(4) {
+        chunk.copy_from_slice(&rng.random().to_le_bytes());
+    }
+    token
+}
+
+/// Called once at boot, before any other task touches the OTA data. Returns
+/// only if this image should keep running.
+pub async fn boot_check(eeprom: &'static EepromMutex) {
+    let state = match with_updater(|ota| ota.current_ota_state().map_err(|_| "OTA data unreadable"))
+        .await
+    {
+        Ok(state) => state,
+        Err(message) => {
+            warn!("OTA: {}; over-the-air updates unavailable", message);
+            return;
+        }
+    };
+    if !is_unconfirmed(state) {
+        return;
+    }
+
+    let mut parts = read_blob(eeprom).await.unwrap_or(OtaBlobParts {
+        token: random_token(),
+        boot_attempts: 0,
+        rolled_back: false,
+    });
+    parts.boot_attempts = parts.boot_attempts.saturating_add(1);
+    if parts.boot_attempts <= MAX_UNCONFIRMED_BOOTS {
+        info!(
+            "OTA: unconfirmed image boot {}/{}",
+            parts.boot_attempts, MAX_UNCONFIRMED_BOOTS
+        );
+        if let Err(message) = write_blob(eeprom, &parts).await {
+            warn!("OTA: boot counter not saved: {}", message);
+        }
+        return;
+    }
+
+    warn!(
+        "OTA: image still unconfirmed after {} boots; rolling back",
+        MAX_UNCONFIRMED_BOOTS
+    );
+    parts.boot_attempts = 0;
+    parts.rolled_back = true;
+    if let Err(message) = write_blob(eeprom, &parts).await {
+        warn!("OTA: rollback flag not saved: {}", message);
+    }
+    let result = with_updater(|ota| {
+        ota.activate_next_partition()
+            .map_err(|_| "previous slot activation failed")?;
+        ota.set_current_ota_state(OtaImageState::Valid)
+            .map_err(|_| "previous slot state update failed")
+    })
+    .await;
+    match result {
+        Ok(()) => esp_hal::system::software_reset(),
+        Err(message) => warn!("OTA: rollback failed: {}", message),
+    }
+}
+
+/// Mark a freshly updated image as good once it has stayed up for
+/// `CONFIRM_AFTER`; no-op for images that are already confirmed.
+#[embassy_executor::task]
+pub async fn ota_confirm_task(eeprom: &'static EepromMutex) {
+    Timer::after(CONFIRM_AFTER).await;
+    let confirmed = with_updater(|ota| {
+        let state = ota.current_ota_state().map_err(|_| "OTA data unreadable")?;
+        if !is_unconfirmed(state) {
+            return Ok(false);
+        }
+        ota.set_current_ota_state(OtaImageState::Valid)
+            .map_err(|_| "OTA state update failed")?;
+        Ok(true)
+    })
+    .await;
+    match confirmed {
+        Ok(true) => {
+            info!("OTA: new image confirmed");
+            if let Some(mut parts) = read_blob(eeprom).await {
+                parts.boot_attempts = 0;
+                let _ = write_blob(eeprom, &parts).await;
+            }
+        }
+        Ok(false) => {}
+        Err(message) => warn!("OTA: confirm failed: {}", message),
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct OtaStatus {
+    pub running_slot: Option<&'static str>,
+    pub image_state: Option<&'static str>,
+    pub boot_attempts: u8,
+    pub rolled_back: bool,
+    pub token_set: bool,
+    /// Size of the slot the next upload goes to.
+    pub slot_capacity: Option<u32>,
+    pub upload: UploadStatus,
+}
+
+pub async fn status(eeprom: &'static EepromMutex) -> OtaStatus {
+    let slots = with_updater(|ota| {
+        let running = ota.selected_partition().ok().map(slot_name);
+        let state = ota.current_ota_state().ok().map(image_state_name);
+        let capacity = ota
+            .next_partition()
+            .ok()
+            .map(|(region, _)| region.capacity() as u32);
+        Ok((running, state, capacity))
+    })
+    .await
+    .unwrap_or((None, None, None));
+    let blob = read_blob(eeprom).await;
+    OtaStatus {
+        running_slot: slots.0,
+        image_state: slots.1,
+        boot_attempts: blob.map_or(0, |parts| parts.boot_attempts),
+        rolled_back: blob.is_some_and(|parts| parts.rolled_back),
+        token_set: blob.is_some(),
+        slot_capacity: slots.2,
+        upload: upload_status(),
+    }
+}
+
+/// The upload token as lowercase hex, created on first use. `rotate` replaces
+/// it, invalidating the copy any host still holds.
+pub async fn token_hex(
+    eeprom: &'static EepromMutex,
+    rotate: bool,
+) -> Result<heapless::String<TOKEN_HEX_LEN>, &'static str> {
+    let existing = read_blob(eeprom).await;
+    let parts = match existing {
+        Some(parts) if !rotate => parts,
+        _ => {
+            let parts = OtaBlobParts {
+                token: random_token(),
+                boot_attempts: existing.map_or(0, |parts| parts.boot_attempts),
+                rolled_back: existing.is_some_and(|parts| parts.rolled_back),
+            };
+            write_blob(eeprom, &parts).await?;
+            parts
+        }
+    };
+    let mut out = heapless::String::new();
+    for byte in parts.token {
+        let _ = write!(out, "{:02x}", byte);
+    }
+    Ok(out)
+}
+
+/// Compare a presented bearer token with the stored one without an early exit.
+pub async fn check_token(eeprom: &'static EepromMutex, presented: &str) -> bool {
+    let Some(parts) = read_blob(eeprom).await else {
+        return false;
+    };
+    let Some(presented) = parse_hex::<OTA_TOKEN_LEN>(presented) else {
+        return false;
+    };
+    presented
+        .iter()
+        .zip(parts.token.iter())
+        .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+        == 0
+}
+
+/// Decode exactly `N` bytes of hex (either case).
+pub fn parse_hex<const N: usize>(text: &str) -> Option<[u8; N]> {
+    let text = text.as_bytes();
+    if text.len() != 2 * N {
+        return None;
+    }
+    let nibble = |c: u8| match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'a'..=b'f' => Some(c - b'a' + 10),
+        b'A'..=b'F' => Some(c - b'A' + 10),
+        _ => None,
+    };
+    let mut out = [0u8; N];
+    for (byte, pair) in out.iter_mut().zip(text.chunks(2)) {
+        *byte = nibble(pair[0])? << 4 | nibble(pair[1])?;
+    }
+    Some(out)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UploadState {
+    Idle,
+    Receiving,
+    Verifying,
+    Rebooting,
+    Error,
+}
+
+impl UploadState {
+    pub const fn name(self) -> &'static str {
+        match self {
+            UploadState::Idle => "idle",
+            UploadState::Receiving => "receiving",
+            UploadState::Verifying => "verifying",
+            UploadState::Rebooting => "rebooting",
+            UploadState::Error => "error",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct UploadStatus {
+    pub state: UploadState,
+    pub received: u32,
+    pub size: u32,
+    pub last_error: Option<&'static str>,
+}
+
+// Updated from `Drop`, so a blocking mutex rather than the async one.
+static UPLOAD_STATUS: BlockingMutex<CriticalSectionRawMutex, Cell<UploadStatus>> =
+    BlockingMutex::new(Cell::new(UploadStatus {
+        state: UploadState::Idle,
+        received: 0,
+        size: 0,
+        last_error: None,
+    }));
+
+pub fn upload_status() -> UploadStatus {
+    UPLOAD_STATUS.lock(|status| status.get())
+}
+
+fn update_upload_status(f: impl FnOnce(&mut UploadStatus)) {
+    UPLOAD_STATUS.lock(|cell| {
+        let mut status = cell.get();
+        f(&mut status);
+        cell.set(status);
+    });
+}
+
+#[cfg(feature = "net_http")]
+pub use upload::{Upload, UploadError, reboot_into_update};
+
+#[cfg(feature = "net_http")]
+mod upload {
+    use alloc::vec::Vec;
+
+    use sha2::{Digest, Sha256};
+
+    use super::*;
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum UploadError {
+        Busy,
+        Unavailable(&'static str),
+        TooLarge,
+        Flash(&'static str),
+        DigestMismatch,
+    }
+
+    impl UploadError {
+        pub const fn message(self) -> &'static str {
+            match self {
+                UploadError::Busy => "another firmware upload is in progress",
+                UploadError::Unavailable(message) | UploadError::Flash(message) => message,
+                UploadError::TooLarge => "image is larger than the OTA slot",
+                UploadError::DigestMismatch => "image SHA-256 does not match",
+            }
+        }
+    }
+
+    /// One upload into the inactive slot. Bytes are buffered per flash sector
+    /// so every sector is erased once.
+    pub struct Upload {
+        size: u32,
+        expected_sha256: [u8; 32],
+        hasher: Sha256,
+        sector: Vec<u8>,
+        flushed: u32,
+        done: bool,
+    }
+
+    impl Upload {
+        pub async fn begin(size: u32, expected_sha256: [u8; 32]) -> Result<Self, UploadError> {
+            let busy = UPLOAD_STATUS.lock(|cell| {
+                let mut status = cell.get();
+                if matches!(
+                    status.state,
+                    UploadState::Receiving | UploadState::Verifying | UploadState::Rebooting
+                ) {
+                    return true;
+                }
+                status = UploadStatus {
+                    state: UploadState::Receiving,
+                    received: 0,
+                    size,
+                    last_error: None,
+                };
+                cell.set(status);
+                false
+            });
+            if busy {
+                return Err(UploadError::Busy);
+            }
+            // From here on `Drop` reports an unfinished upload as failed.
+            let mut upload = Self {
+                size,
+                expected_sha256,
+                hasher: Sha256::new(),
+                sector: Vec::with_capacity(FLASH_SECTOR_LEN),
+                flushed: 0,
+                done: false,
+            };
+            let capacity = with_updater(|ota| {
+                ota.next_partition()
+                    .map(|(region, _)| region.capacity() as u32)
+                    .map_err(|_| "no inactive OTA slot")
+            })
+            .await;
+            let checked = match capacity {
+                Ok(capacity) if size == 0 || size > capacity => Err(UploadError::TooLarge),
+                Ok(_) => Ok(()),
+                Err(message) => Err(UploadError::Unavailable(message)),
+            };
+            match checked {
+                Ok(()) => Ok(upload),
+                Err(err) => {
+                    upload.fail(err.message());
+                    Err(err)
+                }
+            }
+        }
+
+        pub async fn write(&mut self, mut data: &[u8]) -> Result<(), UploadError> {
+            let received = self.flushed + self.sector.len() as u32;
+            if received + data.len() as u32 > self.size {
+                return Err(UploadError::TooLarge);
+            }
+            self.hasher.update(data);
+            while !data.is_empty() {
+                let take = (FLASH_SECTOR_LEN - self.sector.len()).min(data.len());
+                self.sector.extend_from_slice(&data[..take]);
+                data = &data[take..];
+                if self.sector.len() == FLASH_SECTOR_LEN {
+                    self.flush().await?;
+                }
+            }
+            let received = self.flushed + self.sector.len() as u32;
+            update_upload_status(|status| status.received = received);
+            Ok(())
+        }
+
+        async fn flush(&mut self) -> Result<(), UploadError> {
+            let offset = self.flushed;
+            let sector = &self.sector;
+            with_updater(|ota| {
+                let (mut region, _) = ota.next_partition().map_err(|_| "no inactive OTA slot")?;
+                region
+                    .write(offset, sector)
+                    .map_err(|_| "flash write failed")
+            })
+            .await
+            .map_err(UploadError::Flash)?;
+            self.flushed += self.sector.len() as u32;
+            self.sector.clear();
+            Ok(())
+        }
+
+        /// Check the streamed and the read-back digest, then make the new slot
+        /// the boot slot. Returns the slot name. On error the caller reports
+        /// it through [`Upload::fail`].
+        pub async fn finish(
+            &mut self,
+            eeprom: &'static EepromMutex,
+        ) -> Result<&'static str, UploadError> {
+            if !self.sector.is_empty() {
+                self.flush().await?;
+            }
+            if self.flushed != self.size {
+                return Err(UploadError::TooLarge);
+            }
+            update_upload_status(|status| status.state = UploadState::Verifying);
+            let streamed: [u8; 32] = self.hasher.finalize_reset().into();
+            if streamed != self.expected_sha256 {
+                return Err(UploadError::DigestMismatch);
+            }
+
+            let size = self.size;
+            let readback = with_updater(|ota| {
+                let (mut region, _) = ota.next_partition().map_err(|_| "no inactive OTA slot")?;
+                let mut hasher = Sha256::new();
+                let mut chunk = [0u8; 512];
+                let mut offset = 0u32;
+                while offset < size {
+                    let len = (size - offset).min(chunk.len() as u32) as usize;
+                    region
+                        .read(offset, &mut chunk[..len])
+                        .map_err(|_| "flash read-back failed")?;
+                    hasher.update(&chunk[..len]);
+                    offset += len as u32;
+                }
+                Ok::<[u8; 32], &'static str>(hasher.finalize().into())
+            })
+            .await
+            .map_err(UploadError::Flash)?;
+            if readback != self.expected_sha256 {
+                return Err(UploadError::DigestMismatch);
+            }
+
+            let slot = with_updater(|ota| {
+                let (_, slot) = ota.next_partition().map_err(|_| "no inactive OTA slot")?;
+                ota.activate_next_partition()
+                    .map_err(|_| "slot activation failed")?;
+                ota.set_current_ota_state(OtaImageState::New)
+                    .map_err(|_| "OTA state update failed")?;
+                Ok(slot_name(slot))
+            })
+            .await
+            .map_err(UploadError::Flash)?;
+
+            // A fresh image starts with a clean boot counter.
+            if let Some(mut parts) = read_blob(eeprom).await {
+                parts.boot_attempts = 0;
+                parts.rolled_back = false;
+                let _ = write_blob(eeprom, &parts).await;
+            }
+            self.done = true;
+            update_upload_status(|status| status.state = UploadState::Rebooting);
+            info!("OTA: {} bytes written to {}; rebooting", size, slot);
+            Ok(slot)
+        }
+
+        pub fn fail(&mut self, message: &'static str) {
+            self.done = true;
+            update_upload_status(|status| {
+                status.state = UploadState::Error;
+                status.last_error = Some(message);
+            });
+        }
+    }
+
+    impl Drop for Upload {
+        fn drop(&mut self) {
+            if !self.done {
+                self.fail("upload aborted");
+            }
+        }
+    }
+
+    /// Give the HTTP response time to leave, then restart into the new slot.
+    pub async fn reboot_into_update() -> ! {
+        Timer::after(Duration::from_millis(500)).await;
+        esp_hal::system::software_reset()
+    }
+}
@@ -12,6 +12,9 @@
 //! - While any continuous alarm is active: it MUST keep playing and suppress other tones.
 //! - After an underlying alarm condition clears: that alarm MUST keep playing until
 //!   the *next* local interaction (touch / detent / button). Remote actions do not count.
+//! - An explicit "clear faults" request (`silence_alarms()`) acknowledges latched alarms
+//!   the same way a local interaction does; alarms whose condition is still active keep
+//!   playing.
 
 use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 
@@ -48,6 +51,8 @@ static TRIP_ALARM_LATCHED: AtomicBool = AtomicBool::new(false);
 static TRIP_REASON: AtomicU32 = AtomicU32::new(0);
 
 static LOCAL_ACTIVITY: AtomicU32 = AtomicU32::new(0);
+static SILENCE_ARMED: AtomicBool = AtomicBool::new(false);
+static SILENCE_UNTIL_MS: AtomicU32 = AtomicU32::new(0);
 static PENDING_TICKS: AtomicU32 = AtomicU32::new(0);
 pub static TICKS_ENQUEUE_TOTAL: AtomicU32 = AtomicU32::new(0);
 pub static TICKS_PLAY_TOTAL: AtomicU32 = AtomicU32::new(0);
@@ -164,6 +169,23 @@ pub fn trip_alarm_reason() -> Option<TripReason> {
     TripReason::from_u32(TRIP_REASON.load(Ordering::Relaxed))
 }
 
+/// Acknowledge and silence latched alarms as part of the ClearFaults workflow.
+///
+/// Stops the Trip alarm immediately, plus any Primary/Secondary alarm that is
+/// waiting for a local ack. The analog side clears `fault_flags` asynchronously,
+/// so a Primary condition that clears within `SILENCE_WINDOW_MS` of this call is
+/// acknowledged as well instead of re-arming the "needs local ack" alarm.
+pub fn silence_alarms() {
+    TRIP_ALARM_LATCHED.store(false, Ordering::Relaxed);
+    TRIP_REASON.store(0, Ordering::Relaxed);
+    SILENCE_UNTIL_MS.store(
+        now_ms32().wrapping_add(SILENCE_WINDOW_MS),
+        Ordering::Relaxed,
+    );
+    SILENCE_ARMED.store(true, Ordering::Relaxed);
+    WAKE.signal(());
+}
+
 /// Enqueue a single "UI ok" feedback sound (low volume).
 pub fn enqueue_ui_ok() {
     let _ = UI_SOUNDS.try_send(UiSound::Ok);
@@ -283,6 +305,10 @@ const UI_WARN_PLAY_MS: u32 = 180; // ~= warning beep clip + small margin
 // play a short warning beep every few seconds.
 const WARN_BEEP_INTERVAL_MS: u32 = 4_000;
 
+// ClearFaults acknowledgement window: long enough to cover the UART round trip
+// plus the next FastStatus that reports the cleared `fault_flags`.
+const SILENCE_WINDOW_MS: u32 = 2_000;
+
 // Primary alarm cadence (protection-class).
 const PRIMARY_ALARM_ON_MS: u32 = 300;
 const PRIMARY_ALARM_OFF_MS: u32 = 700;
@@ -576,6 +602,24 @@ pub async fn prompt_tone_task() {
     let mut warn_next_ms: u32 = now_ms32();
 
     loop {
+        // Explicit ClearFaults acknowledgement (see `silence_alarms()`).
+        let silence_window = SILENCE_ARMED.load(Ordering::Relaxed)
+            && (now_ms32().wrapping_sub(SILENCE_UNTIL_MS.load(Ordering::Relaxed)) as i32) < 0;
+        if silence_window {
+            if primary_cleared_wait_ack || secondary_cleared_wait_ack {
+                info!("prompt_tone: alarms acknowledged by clear-faults request");
+                primary_cleared_wait_ack = false;
+                secondary_cleared_wait_ack = false;
+                buzzer_apply(0);
+                player = None;
+            }
+            if LINK_UP.load(Ordering::Relaxed) {
+                LINK_ALARM_LATCHED.store(false, Ordering::Relaxed);
+            }
+        } else {
+            SILENCE_ARMED.store(false, Ordering::Relaxed);
+        }
+
         let fault_flags = FAULT_FLAGS.load(Ordering::Relaxed);
         let link_latched = LINK_ALARM_LATCHED.load(Ordering::Relaxed);
         let link_up = LINK_UP.load(Ordering::Relaxed);
@@ -619,12 +663,19 @@ pub async fn prompt_tone_task() {
 
             // Falling edge out of Primary condition.
             if primary_was_active && !primary_condition_active {
-                info!("prompt_tone: primary alarm cleared; waiting for local ack");
-                primary_cleared_wait_ack = true;
-                LOCAL_ACTIVITY.store(0, Ordering::Relaxed);
-                if player.is_none() || player.is_some_and(|p| p.sound != ActiveSound::PrimaryAlarm)
-                {
-                    player = Some(start_player(ActiveSound::PrimaryAlarm));
+                if silence_window {
+                    info!("prompt_tone: primary alarm cleared by clear-faults request");
+                    buzzer_apply(0);
+                    player = None;
+                } else {
+                    info!("prompt_tone: primary alarm cleared; waiting for local ack");
+                    primary_cleared_wait_ack = true;
+                    LOCAL_ACTIVITY.store(0, Ordering::Relaxed);
+                    if player.is_none()
+                        || player.is_some_and(|p| p.sound != ActiveSound::PrimaryAlarm)
+                    {
+                        player = Some(start_player(ActiveSound::PrimaryAlarm));
+                    }
                 }
             }
 
@@ -0,0 +1,507 @@
+//! SoftAP provisioning (captive setup portal).
+//!
+//! `wifi_task` switches the radio to AP+STA when no credentials are stored or
+//! station mode keeps failing. The access point has its own embassy-net
+//! stack on `192.168.4.1/24`; this module serves DHCP, a catch-all DNS and
+//! the setup page on it. Credentials saved from the page land in the same
+//! EEPROM blob as `POST /api/v1/wifi`, after which `wifi_task` returns to
+//! station mode. Packet formats live in `loadlynx-provisioning`.
+
+use core::fmt::Write as _;
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use alloc::string::String;
+use defmt::*;
+use embassy_net::{
+    IpAddress, IpEndpoint, Ipv4Address, Stack,
+    tcp::TcpSocket,
+    udp::{PacketMetadata, UdpSocket},
+};
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex};
+use embassy_time::{Duration, Timer};
+use esp_radio::wifi::{AuthMethod, ScanConfig, WifiController};
+use heapless::{String as HString, Vec};
+use loadlynx_provisioning::{
+    self as prov, DHCP_CLIENT_PORT, DHCP_REPLY_LEN, DHCP_SERVER_PORT, DNS_PORT, DhcpMessageType,
+    DhcpServerConfig, Leases,
+};
+
+use crate::{EepromMutex, net, now_ms32};
+
+pub const AP_ADDRESS: Ipv4Address = Ipv4Address::new(192, 168, 4, 1);
+pub const AP_PREFIX_LEN: u8 = 24;
+const AP_NETMASK: [u8; 4] = [255, 255, 255, 0];
+const AP_SSID_PREFIX: &str = "LoadLynx-Setup-";
+const PORTAL_PORT: u16 = 80;
+const PORTAL_URL: &str = "http://192.168.4.1/";
+/// Clients the setup AP hands addresses to (`.2` .. `.9`).
+const LEASE_COUNT: usize = 8;
+const LEASE_TIME_S: u32 = 600;
+/// Networks kept from the last scan (strongest first, one entry per SSID).
+const MAX_SCAN_RESULTS: usize = 16;
+/// Header + urlencoded `ssid`/`psk` form comfortably fit in 1 KiB.
+const MAX_REQUEST_SIZE: usize = 1024;
+
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+static SCAN_REQUESTED: AtomicBool = AtomicBool::new(false);
+static SCANNING: AtomicBool = AtomicBool::new(false);
+/// `now_ms32()` of the last portal request; keeps a failure-triggered AP up
+/// while someone is using the page.
+static LAST_ACTIVITY_MS: AtomicU32 = AtomicU32::new(0);
+
+#[derive(Clone)]
+struct ScanEntry {
+    ssid: HString<32>,
+    rssi: i8,
+}
+
+static SCAN_RESULTS: Mutex<CriticalSectionRawMutex, Vec<ScanEntry, MAX_SCAN_RESULTS>> =
+    Mutex::new(Vec::new());
+
+/// `LoadLynx-Setup-<short_id>`, where `short_id` is the mDNS hostname suffix.
+pub fn ap_ssid(short_id: &str) -> HString<32> {
+    let mut ssid = HString::new();
+    let _ = ssid.push_str(AP_SSID_PREFIX);
+    let _ = ssid.push_str(short_id);
+    ssid
+}
+
+pub fn set_active(active: bool) {
+    ACTIVE.store(active, Ordering::Relaxed);
+    if active {
+        LAST_ACTIVITY_MS.store(now_ms32(), Ordering::Relaxed);
+    }
+}
+
+pub fn is_active() -> bool {
+    ACTIVE.load(Ordering::Relaxed)
+}
+
+pub fn request_scan() {
+    SCAN_REQUESTED.store(true, Ordering::Relaxed);
+}
+
+/// Milliseconds since the portal last served a request.
+pub fn idle_ms() -> u32 {
+    now_ms32().wrapping_sub(LAST_ACTIVITY_MS.load(Ordering::Relaxed))
+}
+
+/// Run a pending scan request on the STA half of the AP+STA radio.
+pub async fn service_scan(controller: &mut WifiController<'static>) {
+    if !SCAN_REQUESTED.swap(false, Ordering::Relaxed) {
+        return;
+    }
+    SCANNING.store(true, Ordering::Relaxed);
+    match controller
+        .scan_with_config_async(ScanConfig::default())
+        .await
+    {
+        Ok(found) => {
+            let mut results: Vec<ScanEntry, MAX_SCAN_RESULTS> = Vec::new();
+            for ap in found.iter() {
+                // The EEPROM blob requires a PSK, so open networks cannot be
+                // provisioned; hidden networks can still be typed in.
+                if ap.ssid.is_empty() || matches!(ap.auth_method, Some(AuthMethod::None)) {
+                    continue;
+                }
+                if let Some(existing) = results.iter_mut().find(|e| e.ssid == ap.ssid.as_str()) {
+                    existing.rssi = existing.rssi.max(ap.signal_strength);
+                    continue;
+                }
+                let Ok(ssid) = HString::try_from(ap.ssid.as_str()) else {
+                    continue;
+                };
+                let entry = ScanEntry {
+                    ssid,
+                    rssi: ap.signal_strength,
+                };
+                if results.push(entry.clone()).is_err() {
+                    // Full: replace the weakest entry if this one is stronger.
+                    if let Some(weakest) = results.iter_mut().min_by_key(|e| e.rssi)
+                        && weakest.rssi < entry.rssi
+                    {
+                        *weakest = entry;
+                    }
+                }
+            }
+            results.sort_unstable_by(|a, b| b.rssi.cmp(&a.rssi));
+            info!("setup AP: scan found {} networks", results.len());
+            *SCAN_RESULTS.lock().await = results;
+        }
+        Err(err) => warn!("setup AP: Wi-Fi scan error: {:?}", err),
+    }
+    SCANNING.store(false, Ordering::Relaxed);
+}
+
+#[embassy_executor::task]
+pub async fn dhcp_task(stack: Stack<'static>) {
+    let mut rx_meta = [PacketMetadata::EMPTY; 4];
+    let mut tx_meta = [PacketMetadata::EMPTY; 4];
+    let mut rx_storage = [0u8; 1024];
+    let mut tx_storage = [0u8; 1024];
+    let mut socket = UdpSocket::new(
+        stack,
+        &mut rx_meta,
+        &mut rx_storage,
+        &mut tx_meta,
+        &mut tx_storage,
+    );
+    if let Err(err) = socket.bind(DHCP_SERVER_PORT) {
+        warn!("setup AP: DHCP bind failed: {:?}", err);
+        return;
+    }
+
+    let config = DhcpServerConfig {
+        server_ip: AP_ADDRESS.octets(),
+        netmask: AP_NETMASK,
+        lease_time_s: LEASE_TIME_S,
+    };
+    let mut leases = Leases::<LEASE_COUNT>::new(AP_ADDRESS.octets());
+    let mut buf = [0u8; 576];
+    let mut reply = [0u8; DHCP_REPLY_LEN];
+    let broadcast = IpEndpoint::new(IpAddress::Ipv4(Ipv4Address::BROADCAST), DHCP_CLIENT_PORT);
+
+    loop {
+        let Ok((len, _)) = socket.recv_from(&mut buf).await else {
+            continue;
+        };
+        let Some(request) = prov::parse_dhcp_request(&buf[..len]) else {
+            continue;
+        };
+        let (message_type, ip) = match request.message_type {
+            DhcpMessageType::Discover => {
+                (DhcpMessageType::Offer, leases.assign(request.client_mac))
+            }
+            DhcpMessageType::Request => {
+                if request.server_id.is_some_and(|id| id != config.server_ip) {
+                    // The client picked another server's offer.
+                    continue;
+                }
+                let ip = leases.assign(request.client_mac);
+                if request
+                    .requested_ip
+                    .is_some_and(|requested| requested != ip)
+                {
+                    (DhcpMessageType::Nak, ip)
+                } else {
+                    (DhcpMessageType::Ack, ip)
+                }
+            }
+            DhcpMessageType::Release | DhcpMessageType::Decline => {
+                leases.release(request.client_mac);
+                continue;
+            }
+            _ => continue,
+        };
+        let Ok(reply_len) =
+            prov::encode_dhcp_reply(&mut reply, &request, message_type, ip, &config)
+        else {
+            continue;
+        };
+        if let Err(err) = socket.send_to(&reply[..reply_len], broadcast).await {
+            warn!("setup AP: DHCP send failed: {:?}", err);
+        }
+    }
+}
+
+#[embassy_executor::task]
+pub async fn dns_task(stack: Stack<'static>) {
+    let mut rx_meta = [PacketMetadata::EMPTY; 4];
+    let mut tx_meta = [PacketMetadata::EMPTY; 4];
+    let mut rx_storage = [0u8; 512];
+    let mut tx_storage = [0u8; 512];
+    let mut socket = UdpSocket::new(
+        stack,
+        &mut rx_meta,
+        &mut rx_storage,
+        &mut tx_meta,
+        &mut tx_storage,
+    );
+    if let Err(err) = socket.bind(DNS_PORT) {
+        warn!("setup AP: DNS bind failed: {:?}", err);
+        return;
+    }
+
+    let mut query = [0u8; 256];
+    let mut answer = [0u8; 256];
+    loop {
+        let Ok((len, meta)) = socket.recv_from(&mut query).await else {
+            continue;
+        };
+        let Some(answer_len) =
+            prov::encode_dns_redirect(&query[..len], &mut answer, AP_ADDRESS.octets())
+        else {
+            continue;
+        };
+        let _ = socket.send_to(&answer[..answer_len], meta.endpoint).await;
+    }
+}
+
+/// State the setup page needs to store credentials and name the device.
+pub(crate) struct PortalContext {
+    pub eeprom: &'static EepromMutex,
+    pub wifi_state: &'static net::WifiStateMutex,
+    /// mDNS hostname shown after saving (e.g. `loadlynx-a1b2c3`).
+    pub hostname: HString<32>,
+}
+
+#[embassy_executor::task]
+pub async fn portal_task(stack: Stack<'static>, ctx: PortalContext) {
+    let mut rx_buf = [0u8; 1024];
+    let mut tx_buf = [0u8; 2048];
+
+    loop {
+        let mut socket = TcpSocket::new(stack, &mut rx_buf, &mut tx_buf);
+        socket.set_timeout(Some(Duration::from_secs(10)));
+
+        match socket.accept(PORTAL_PORT).await {
+            Ok(()) => {
+                if is_active() {
+                    LAST_ACTIVITY_MS.store(now_ms32(), Ordering::Relaxed);
+                    if let Err(err) = serve_connection(&mut socket, &ctx).await {
+                        warn!("setup AP: portal connection error: {:?}", err);
+                    }
+                }
+                socket.close();
+                let _ = socket.flush().await;
+            }
+            Err(err) => {
+                warn!("setup AP: portal accept error: {:?}", err);
+                Timer::after(Duration::from_millis(200)).await;
+            }
+        }
+    }
+}
+
+async fn serve_connection(
+    socket: &mut TcpSocket<'_>,
+    ctx: &PortalContext,
+) -> Result<(), embassy_net::tcp::Error> {
+    let mut buf = [0u8; MAX_REQUEST_SIZE];
+    let mut total = 0usize;
+    let header_end = loop {
+        if total == buf.len() {
+            return write_response(socket, "413 Payload Too Large", "text/plain", "", "").await;
+        }
+        let n = socket.read(&mut buf[total..]).await?;
+        if n == 0 {
+            return Ok(());
+        }
+        total += n;
+        if let Some(idx) = buf[..total].windows(4).position(|w| w == b"\r\n\r\n") {
+            break idx + 4;
+        }
+    };
+
+    let Ok(head) = core::str::from_utf8(&buf[..header_end]) else {
+        return write_response(socket, "400 Bad Request", "text/plain", "", "").await;
+    };
+    let mut parts = head.lines().next().unwrap_or("").split_whitespace();
+    let method: HString<8> = HString::try_from(parts.next().unwrap_or("")).unwrap_or_default();
+    let target = parts.next().unwrap_or("");
+    let path: HString<64> =
+        HString::try_from(target.split('?').next().unwrap_or("")).unwrap_or_default();
+    let content_length = head
+        .lines()
+        .skip(1)
+        .find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            if !name.trim().eq_ignore_ascii_case("content-length") {
+                return None;
+            }
+            value.trim().parse::<usize>().ok()
+        })
+        .unwrap_or(0);
+
+    let body_end = header_end + content_length;
+    if body_end > buf.len() {
+        return write_response(socket, "413 Payload Too Large", "text/plain", "", "").await;
+    }
+    while total < body_end {
+        let n = socket.read(&mut buf[total..body_end]).await?;
+        if n == 0 {
+            return Ok(());
+        }
+        total += n;
+    }
+    let body = core::str::from_utf8(&buf[header_end..body_end]).unwrap_or("");
+
+    match (method.as_str(), path.as_str()) {
+        ("GET", "/") => {
+            write_response(socket, "200 OK", "text/html; charset=utf-8", "", SETUP_PAGE).await
+        }
+        ("GET", "/scan") => {
+            let mut json = String::new();
+            render_scan_json(&mut json).await;
+            write_response(socket, "200 OK", "application/json", "", &json).await
+        }
+        ("POST", "/scan") => {
+            request_scan();
+            write_response(
+                socket,
+                "202 Accepted",
+                "application/json",
+                "",
+                "{\"scanning\":true}",
+            )
+            .await
+        }
+        ("POST", "/save") => {
+            let mut page = String::new();
+            let status = save_credentials(body, &mut page, ctx).await;
+            write_response(socket, status, "text/html; charset=utf-8", "", &page).await
+        }
+        // Everything else (OS connectivity probes, typed URLs) lands on the
+        // setup page so the captive-portal sheet opens.
+        _ => {
+            let mut location = String::new();
+            let _ = core::write!(location, "Location: {}\r\n", PORTAL_URL);
+            write_response(socket, "302 Found", "text/plain", &location, "").await
+        }
+    }
+}
+
+async fn render_scan_json(out: &mut String) {
+    out.push_str("{\"scanning\":");
+    out.push_str(
+        if SCANNING.load(Ordering::Relaxed) || SCAN_REQUESTED.load(Ordering::Relaxed) {
+            "true"
+        } else {
+            "false"
+        },
+    );
+    out.push_str(",\"networks\":[");
+    let results = SCAN_RESULTS.lock().await;
+    for (idx, entry) in results.iter().enumerate() {
+        if idx > 0 {
+            out.push(',');
+        }
+        out.push_str("{\"ssid\":\"");
+        net::write_json_string_escaped(out, entry.ssid.as_str());
+        let _ = core::write!(out, "\",\"rssi\":{}}}", entry.rssi);
+    }
+    out.push_str("]}");
+}
+
+async fn save_credentials(body: &str, page: &mut String, ctx: &PortalContext) -> &'static str {
+    let mut ssid_buf = [0u8; 64];
+    let mut psk_buf = [0u8; 128];
+    let ssid = prov::form_value(body, "ssid", &mut ssid_buf).ok().flatten();
+    let psk = prov::form_value(body, "psk", &mut psk_buf).ok().flatten();
+    let (Some(ssid), Some(psk)) = (ssid, psk) else {
+        write_result_page(page, "Missing network name or password.");
+        return "400 Bad Request";
+    };
+    // Saved networks go to the top of the stored list; the others stay as
+    // fallbacks.
+    let Ok(mut networks) = net::read_wifi_networks(ctx.eeprom).await else {
+        write_result_page(page, "Reading saved networks failed. Please try again.");
+        return "503 Service Unavailable";
+    };
+    if let Err(message) = net::upsert_wifi_network(&mut networks, ssid, psk, 0) {
+        write_result_page(page, message);
+        return "400 Bad Request";
+    }
+    if net::store_wifi_networks(ctx.eeprom, &networks)
+        .await
+        .is_err()
+    {
+        write_result_page(
+            page,
+            "Saving failed (EEPROM write error). Please try again.",
+        );
+        return "503 Service Unavailable";
+    }
+    info!("setup AP: credentials saved for SSID=\"{}\"", ssid);
+    net::mark_wifi_reconfigure_pending(ctx.wifi_state).await;
+
+    let mut message = String::new();
+    message.push_str("Saved. LoadLynx is joining ");
+    write_html_escaped(&mut message, ssid);
+    let _ = core::write!(
+        message,
+        " and this setup network will close. Reconnect to that network and open \
+         http://{}.local/.",
+        ctx.hostname.as_str()
+    );
+    write_result_page(page, &message);
+    "200 OK"
+}
+
+fn write_result_page(page: &mut String, message_html: &str) {
+    page.push_str(
+        "<!doctype html><html><head><meta charset=\"utf-8\">\
+         <meta name=\"viewport\" content=\"width=device-width,initial-scale=1\">\
+         <title>LoadLynx Wi-Fi setup</title></head>\
+         <body style=\"font-family:sans-serif;max-width:24em;margin:2em auto;padding:0 1em\">\
+         <h2>LoadLynx Wi-Fi setup</h2><p>",
+    );
+    page.push_str(message_html);
+    page.push_str("</p><p><a href=\"/\">Back</a></p></body></html>");
+}
+
+fn write_html_escaped(out: &mut String, s: &str) {
+    for ch in s.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(ch),
+        }
+    }
+}
+
+async fn write_response(
+    socket: &mut TcpSocket<'_>,
+    status_line: &str,
+    content_type: &str,
+    extra_headers: &str,
+    body: &str,
+) -> Result<(), embassy_net::tcp::Error> {
+    let mut head = String::new();
+    let _ = core::write!(
+        head,
+        "HTTP/1.1 {}\r\n\
+         Content-Type: {}\r\n\
+         Cache-Control: no-store\r\n\
+         {}\
+         Connection: close\r\n\
+         Content-Length: {}\r\n\
+         \r\n",
+        status_line,
+        content_type,
+        extra_headers,
+        body.len()
+    );
+    net::socket_write_all(socket, head.as_bytes()).await?;
+    net::socket_write_all(socket, body.as_bytes()).await
+}
+
+/// Setup page: plain form post (works without JavaScript) plus a scan list
+/// filled from `GET /scan`. Network names are inserted via `textContent`.
+const SETUP_PAGE: &str = "<!doctype html><html><head><meta charset=\"utf-8\">\
+<meta name=\"viewport\" content=\"width=device-width,initial-scale=1\">\
+<title>LoadLynx Wi-Fi setup</title><style>\
+body{font-family:sans-serif;max-width:24em;margin:2em auto;padding:0 1em}\
+input,button{width:100%;padding:.6em;margin:.3em 0;box-sizing:border-box}\
+li{padding:.4em 0;cursor:pointer}</style></head><body>\
+<h2>LoadLynx Wi-Fi setup</h2>\
+<form method=\"post\" action=\"/save\">\
+<label>Network<input name=\"ssid\" maxlength=\"32\" required autocapitalize=\"none\"></label>\
+<label>Password<input name=\"psk\" type=\"password\" minlength=\"8\" maxlength=\"64\" required></label>\
+<button>Save and connect</button></form>\
+<h3>Nearby networks</h3><ul id=\"nets\"><li>Scanning…</li></ul>\
+<button type=\"button\" onclick=\"rescan()\">Rescan</button>\
+<script>\
+function load(){fetch('/scan').then(function(r){return r.json()}).then(function(d){\
+var l=document.getElementById('nets');l.textContent='';\
+d.networks.forEach(function(n){var i=document.createElement('li');\
+i.textContent=n.ssid+' ('+n.rssi+' dBm)';\
+i.onclick=function(){document.forms[0].ssid.value=n.ssid;document.forms[0].psk.focus()};\
+l.appendChild(i)});\
+if(d.scanning){setTimeout(load,1500)}else if(!d.networks.length){l.textContent='No networks found.'}})}\
+function rescan(){fetch('/scan',{method:'POST'}).then(function(){setTimeout(load,1500)})}\
+load();\
+</script></body></html>";
@@ -0,0 +1,408 @@
+//! SCPI raw-socket server (TCP port 5025).
+//!
+//! Parsing lives in `loadlynx-scpi`; this module executes the parsed commands
+//! against the same control/telemetry state as the HTTP API. Setpoint
+//! commands edit the active preset's in-RAM working copy (like the on-device
+//! panel) and are not persisted; `*RST` reverts them to the saved snapshot.
+//!
+//! Network connections start unauthenticated: `SYST:AUTH <token>` presents an
+//! HTTP API token (see `auth`), after which the token's role gates commands
+//! exactly like the HTTP routes (queries need `read`, settings and output need
+//! `control`). Protected commands before that fail with `-203`. The USB link
+//! is trusted like USB token issuance and starts with `admin`.
+
+use core::fmt::Write as _;
+use core::sync::atomic::Ordering;
+
+use alloc::string::String;
+use defmt::*;
+use embassy_net::{Stack, tcp::TcpSocket};
+use embassy_time::{Duration, Timer};
+use heapless::String as HString;
+use loadlynx_protocol::{CalKind, FastStatus, LoadMode};
+use loadlynx_scpi::{self as scpi, Command, ErrorQueue, Function, Level, Quantity};
+
+use crate::{
+    CalibrationMutex, ControlMutex, EepromMutex, FW_VERSION, LINK_UP, STATE_FLAG_REMOTE_ACTIVE,
+    TelemetryMutex,
+    auth::{self, Role},
+    bump_control_rev, control, net,
+};
+
+pub const SCPI_PORT: u16 = 5025;
+/// Longest accepted program message; longer lines are discarded with -102.
+const MAX_LINE_LEN: usize = 256;
+/// Drop idle clients so a forgotten session does not hold the only slot.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Shared state the executor needs; one per transport.
+pub(crate) struct ScpiContext {
+    pub control: &'static ControlMutex,
+    pub calibration: &'static CalibrationMutex,
+    pub telemetry: &'static TelemetryMutex,
+    /// Token store checked by `SYST:AUTH`.
+    pub eeprom: &'static EepromMutex,
+    /// `*IDN?` serial field (device_id, e.g. `loadlynx-a1b2c3`).
+    pub serial: HString<32>,
+}
+
+/// Per-connection state (error queue, role granted by `SYST:AUTH`).
+#[derive(Default)]
+pub(crate) struct ScpiSession {
+    errors: ErrorQueue,
+    role: Option<Role>,
+}
+
+impl ScpiSession {
+    /// Session for the USB link, which needs no token.
+    pub(crate) fn trusted() -> Self {
+        Self {
+            errors: ErrorQueue::new(),
+            role: Some(Role::Admin),
+        }
+    }
+}
+
+#[embassy_executor::task]
+pub async fn scpi_task(stack: Stack<'static>, ctx: ScpiContext) {
+    let mut rx_buf = [0u8; 512];
+    let mut tx_buf = [0u8; 512];
+
+    info!("SCPI server starting (port={})", SCPI_PORT);
+
+    loop {
+        stack.wait_config_up().await;
+
+        let mut socket = TcpSocket::new(stack, &mut rx_buf, &mut tx_buf);
+        socket.set_timeout(Some(IDLE_TIMEOUT));
+
+        match socket.accept(SCPI_PORT).await {
+            Ok(()) => {
+                if let Err(err) = serve_connection(&mut socket, &ctx).await {
+                    warn!("SCPI connection error: {:?}", err);
+                }
+                socket.close();
+                let _ = socket.flush().await;
+            }
+            Err(err) => {
+                warn!("SCPI accept error: {:?}", err);
+                Timer::after(Duration::from_millis(200)).await;
+            }
+        }
+    }
+}
+
+async fn serve_connection(
+    socket: &mut TcpSocket<'_>,
+    ctx: &ScpiContext,
+) -> Result<(), embassy_net::tcp::Error> {
+    let mut session = ScpiSession::default();
+    let mut line = [0u8; MAX_LINE_LEN];
+    let mut len = 0usize;
+    let mut overflowed = false;
+    let mut chunk = [0u8; 128];
+    let mut response = String::new();
+
+    loop {
+        let n = socket.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(());
+        }
+        for &byte in &chunk[..n] {
+            if byte != b'\n' {
+                if len < MAX_LINE_LEN {
+                    line[len] = byte;
+                    len += 1;
+                } else {
+                    overflowed = true;
+                }
+                continue;
+            }
+
+            response.clear();
+            if overflowed {
+                session
+                    .errors
+                    .push_detail(scpi::Error::Syntax, "program message too long");
+            } else {
+                match core::str::from_utf8(&line[..len]) {
+                    Ok(text) => execute_line(ctx, &mut session, text, &mut response).await,
+                    Err(_) => session.errors.push(scpi::Error::Syntax),
+                }
+            }
+            len = 0;
+            overflowed = false;
+
+            if !response.is_empty() {
+                response.push('\n');
+                net::socket_write_all(socket, response.as_bytes()).await?;
+            }
+        }
+    }
+}
+
+/// Execute one program message, appending query responses (joined with `;`,
+/// no terminator) to `out`. Errors go to the session's error queue.
+pub(crate) async fn execute_line(
+    ctx: &ScpiContext,
+    session: &mut ScpiSession,
+    line: &str,
+    out: &mut String,
+) {
+    for parsed in scpi::parse_program(line) {
+        let result = match parsed {
+            Ok(command) => execute(ctx, session, command, out).await,
+            Err(err) => Err((err, None)),
+        };
+        if let Err((err, detail)) = result {
+            match detail {
+                Some(detail) => session.errors.push_detail(err, detail),
+                None => session.errors.push(err),
+            }
+        }
+    }
+}
+
+type ExecResult = Result<(), (scpi::Error, Option<&'static str>)>;
+
+/// Role a command needs; `None` for identification, the error queue and
+/// authentication itself.
+fn required_role(command: &Command<'_>) -> Option<Role> {
+    match command {
+        Command::Identify
+        | Command::Clear
+        | Command::OperationComplete
+        | Command::OperationCompleteQuery
+        | Command::ErrorQuery
+        | Command::VersionQuery
+        | Command::Authenticate(_)
+        | Command::AuthQuery => None,
+        Command::Measure(_)
+        | Command::LevelQuery(_)
+        | Command::FunctionQuery
+        | Command::InputQuery => Some(Role::Read),
+        Command::Reset
+        | Command::Recall(_)
+        | Command::SetLevel(..)
+        | Command::SetFunction(_)
+        | Command::SetInput(_) => Some(Role::Control),
+    }
+}
+
+fn begin_response(out: &mut String) {
+    if !out.is_empty() {
+        out.push(';');
+    }
+}
+
+async fn execute(
+    ctx: &ScpiContext,
+    session: &mut ScpiSession,
+    command: Command<'_>,
+    out: &mut String,
+) -> ExecResult {
+    if let Some(required) = required_role(&command)
+        && session.role.is_none_or(|role| role < required)
+    {
+        let detail = match session.role {
+            None => "SYST:AUTH <token> first",
+            Some(_) => "token role too low",
+        };
+        return Err((scpi::Error::CommandProtected, Some(detail)));
+    }
+    match command {
+        Command::Identify => {
+            begin_response(out);
+            let _ = write!(out, "LoadLynx,LoadLynx,{},{}", ctx.serial, FW_VERSION);
+        }
+        Command::Reset => {
+            let mut guard = ctx.control.lock().await;
+            guard.force_output_off();
+            let preset_id = guard.active_preset_id;
+            let idx = preset_index(preset_id);
+            guard.presets[idx] = guard.saved[idx];
+            guard.update_dirty_for_preset_id(preset_id);
+            bump_control_rev();
+        }
+        Command::Clear => session.errors.clear(),
+        Command::OperationComplete => {}
+        Command::OperationCompleteQuery => {
+            // Commands complete synchronously, so *OPC? answers immediately.
+            begin_response(out);
+            out.push('1');
+        }
+        Command::Recall(preset_id) => {
+            if preset_id == 0 || preset_id as usize > control::PRESET_COUNT {
+                return Err((scpi::Error::DataOutOfRange, Some("preset 1..5")));
+            }
+            let mut guard = ctx.control.lock().await;
+            guard.activate_preset(preset_id);
+            bump_control_rev();
+        }
+        Command::ErrorQuery => {
+            begin_response(out);
+            let _ = scpi::write_error(out, session.errors.pop());
+        }
+        Command::VersionQuery => {
+            begin_response(out);
+            out.push_str("1999.0");
+        }
+        Command::Authenticate(token) => {
+            // A rejected token also drops any role granted earlier.
+            session.role = auth::authorize(ctx.eeprom, token).await;
+            if session.role.is_none() {
+                return Err((scpi::Error::CommandProtected, Some("unknown token")));
+            }
+        }
+        Command::AuthQuery => {
+            begin_response(out);
+            out.push_str(match session.role {
+                None => "NONE",
+                Some(Role::Read) => "READ",
+                Some(Role::Control) => "CONTROL",
+                Some(Role::Admin) => "ADMIN",
+            });
+        }
+        Command::Measure(quantity) => {
+            let status = latest_status(ctx).await?;
+            let milli = match quantity {
+                Quantity::Voltage => {
+                    if status.state_flags & STATE_FLAG_REMOTE_ACTIVE != 0 {
+                        status.v_remote_mv as i64
+                    } else {
+                        status.v_local_mv as i64
+                    }
+                }
+                Quantity::Current => status.i_local_ma as i64 + status.i_remote_ma as i64,
+                Quantity::Power => status.calc_p_mw as i64,
+            };
+            begin_response(out);
+            let _ = scpi::write_milli(out, milli);
+        }
+        Command::SetLevel(quantity, level) => {
+            let mut guard = ctx.control.lock().await;
+            let preset_id = guard.active_preset_id;
+            let idx = preset_index(preset_id);
+            let mut preset = guard.presets[idx];
+            let saved = guard.saved[idx];
+            let (min, max, default) = level_bounds(&preset, &saved, quantity);
+            let value = match level {
+                Level::Milli(v) if (min..=max).contains(&v) => v,
+                Level::Milli(_) => return Err((scpi::Error::DataOutOfRange, None)),
+                Level::Min => min,
+                Level::Max => max,
+                Level::Default => default.clamp(min, max),
+            };
+            match quantity {
+                Quantity::Current => preset.target_i_ma = value as i32,
+                Quantity::Voltage => preset.target_v_mv = value as i32,
+                Quantity::Power => preset.target_p_mw = value as u32,
+            }
+            guard.presets[idx] = preset.clamp();
+            guard.update_dirty_for_preset_id(preset_id);
+            bump_control_rev();
+        }
+        Command::LevelQuery(quantity) => {
+            let preset = { ctx.control.lock().await.active_preset() };
+            let milli = match quantity {
+                Quantity::Current => preset.target_i_ma as i64,
+                Quantity::Voltage => preset.target_v_mv as i64,
+                Quantity::Power => preset.target_p_mw as i64,
+            };
+            begin_response(out);
+            let _ = scpi::write_milli(out, milli);
+        }
+        Command::SetFunction(function) => {
+            let mode = match function {
+                Function::Cc => LoadMode::Cc,
+                Function::Cv => LoadMode::Cv,
+                Function::Cp => LoadMode::Cp,
+            };
+            let mut guard = ctx.control.lock().await;
+            let preset_id = guard.active_preset_id;
+            let idx = preset_index(preset_id);
+            if guard.presets[idx].mode != mode {
+                guard.presets[idx].mode = mode;
+                guard.presets[idx] = guard.presets[idx].clamp();
+                guard.update_dirty_for_preset_id(preset_id);
+                // Any mode change on the active preset forces output OFF.
+                guard.force_output_off();
+                bump_control_rev();
+            }
+        }
+        Command::FunctionQuery => {
+            let mode = { ctx.control.lock().await.active_preset().mode };
+            let function = match mode {
+                LoadMode::Cc => Function::Cc,
+                LoadMode::Cv => Function::Cv,
+                LoadMode::Cp => Function::Cp,
+                LoadMode::Reserved(_) => return Err((scpi::Error::Execution, None)),
+            };
+            begin_response(out);
+            out.push_str(function.name());
+        }
+        Command::SetInput(enabled) => {
+            let cal_mode = { ctx.calibration.lock().await.cal_mode };
+            if cal_mode != CalKind::Off {
+                return Err((
+                    scpi::Error::SettingsConflict,
+                    Some("calibration mode active"),
+                ));
+            }
+            if enabled && let Some(block) = net::output_enable_blocker(ctx.control, cal_mode).await
+            {
+                return Err((scpi::Error::SettingsConflict, Some(block.message())));
+            }
+            ctx.control.lock().await.set_normal_output_enabled(enabled);
+            bump_control_rev();
+        }
+        Command::InputQuery => {
+            let cal_mode = { ctx.calibration.lock().await.cal_mode };
+            let enabled = {
+                ctx.control
+                    .lock()
+                    .await
+                    .effective_output_command(cal_mode)
+                    .output_enabled
+            };
+            begin_response(out);
+            out.push(if enabled { '1' } else { '0' });
+        }
+    }
+    Ok(())
+}
+
+fn preset_index(preset_id: u8) -> usize {
+    (preset_id.clamp(1, control::PRESET_COUNT as u8) - 1) as usize
+}
+
+/// `(min, max, default)` in milli-units for a setpoint of the active preset;
+/// `DEFault` is the saved (persisted) value.
+fn level_bounds(
+    preset: &control::Preset,
+    saved: &control::Preset,
+    quantity: Quantity,
+) -> (i64, i64, i64) {
+    match quantity {
+        Quantity::Current => (0, preset.max_i_ma_total as i64, saved.target_i_ma as i64),
+        Quantity::Voltage => (
+            preset.min_v_mv.max(0) as i64,
+            control::HARD_MAX_V_MV as i64,
+            saved.target_v_mv as i64,
+        ),
+        Quantity::Power => (0, preset.max_p_mw as i64, saved.target_p_mw as i64),
+    }
+}
+
+async fn latest_status(
+    ctx: &ScpiContext,
+) -> Result<FastStatus, (scpi::Error, Option<&'static str>)> {
+    if !LINK_UP.load(Ordering::Relaxed) {
+        return Err((scpi::Error::DataStale, Some("UART link is down")));
+    }
+    ctx.telemetry
+        .lock()
+        .await
+        .last_status
+        .ok_or((scpi::Error::DataStale, Some("no telemetry yet")))
+}
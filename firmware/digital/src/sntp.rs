@@ -0,0 +1,221 @@
+//! SNTP client that keeps the wall clock (`wall_clock.rs`) in sync.
+//!
+//! Packet handling lives in `loadlynx-sntp`. The server defaults to the
+//! build-time `LOADLYNX_NTP_SERVER` (else `pool.ntp.org`) and can be
+//! overridden through the EEPROM NTP blob (`POST /api/v1/time`), e.g. to point
+//! at the `loadlynx time serve-ntp` stand-in on a bench network. Servers are
+//! written as `host` or `host:port`.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use alloc::string::String;
+use defmt::*;
+use embassy_futures::select::{Either, select};
+use embassy_net::{
+    IpEndpoint, Stack,
+    udp::{PacketMetadata, UdpSocket},
+};
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex};
+use embassy_time::{Duration, Instant, Timer};
+use esp_hal::rng::Rng;
+use loadlynx_sntp::{self as sntp, PACKET_LEN, Sample};
+
+use crate::wall_clock::{self, WallClockSource};
+use crate::{EepromMutex, eeprom, mqtt, timestamp_ms};
+
+pub const DEFAULT_NTP_SERVER: &str = match option_env!("LOADLYNX_NTP_SERVER") {
+    Some(server) => server,
+    None => "pool.ntp.org",
+};
+const RESYNC_INTERVAL: Duration = Duration::from_secs(3_600);
+const RETRY_DELAY: Duration = Duration::from_secs(30);
+const REPLY_TIMEOUT: Duration = Duration::from_secs(3);
+/// A reply slower than this says more about the network than the time.
+const MAX_DELAY_MS: i64 = 2_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum SntpState {
+    WaitingForNetwork,
+    Syncing,
+    Synced,
+    Error,
+}
+
+impl SntpState {
+    pub(crate) const fn name(self) -> &'static str {
+        match self {
+            SntpState::WaitingForNetwork => "waiting_for_network",
+            SntpState::Syncing => "syncing",
+            SntpState::Synced => "synced",
+            SntpState::Error => "error",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct SntpStatus {
+    pub state: SntpState,
+    pub last_error: Option<&'static str>,
+}
+
+static SNTP_STATUS: Mutex<CriticalSectionRawMutex, SntpStatus> = Mutex::new(SntpStatus {
+    state: SntpState::WaitingForNetwork,
+    last_error: None,
+});
+static RECONFIGURE_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+pub(crate) async fn status() -> SntpStatus {
+    *SNTP_STATUS.lock().await
+}
+
+/// Reload the server setting and sync now.
+pub(crate) async fn request_reconfigure() {
+    RECONFIGURE_REQUESTED.store(true, Ordering::Relaxed);
+    SNTP_STATUS.lock().await.last_error = None;
+}
+
+async fn set_status(state: SntpState, last_error: Option<&'static str>) {
+    *SNTP_STATUS.lock().await = SntpStatus { state, last_error };
+}
+
+/// Split `host[:port]`; the port defaults to 123.
+pub(crate) fn parse_server(server: &str) -> Option<(&str, u16)> {
+    match server.rsplit_once(':') {
+        Some((host, port)) => Some((host, port.parse().ok().filter(|&port| port != 0)?)),
+        None => Some((server, sntp::NTP_PORT)),
+    }
+}
+
+pub(crate) struct NtpServer {
+    pub host: String,
+    pub port: u16,
+    /// False when the build-time default is in use.
+    pub custom: bool,
+}
+
+pub(crate) async fn read_server(eeprom: &'static EepromMutex) -> NtpServer {
+    let blob = eeprom.lock().await.read_ntp_blob().await.ok();
+    if let Some(parts) = blob.as_ref().and_then(eeprom::decode_ntp_blob) {
+        return NtpServer {
+            host: String::from(parts.host),
+            port: parts.port,
+            custom: true,
+        };
+    }
+    let (host, port) = parse_server(DEFAULT_NTP_SERVER).unwrap_or(("pool.ntp.org", sntp::NTP_PORT));
+    NtpServer {
+        host: String::from(host),
+        port,
+        custom: false,
+    }
+}
+
+pub(crate) struct SntpContext {
+    pub eeprom: &'static EepromMutex,
+}
+
+#[embassy_executor::task]
+pub async fn sntp_task(stack: Stack<'static>, ctx: SntpContext) {
+    let mut rx_meta = [PacketMetadata::EMPTY; 2];
+    let mut tx_meta = [PacketMetadata::EMPTY; 2];
+    let mut rx_buf = [0u8; 2 * PACKET_LEN];
+    let mut tx_buf = [0u8; 2 * PACKET_LEN];
+
+    loop {
+        RECONFIGURE_REQUESTED.store(false, Ordering::Relaxed);
+        if !stack.is_config_up() {
+            set_status(SntpState::WaitingForNetwork, None).await;
+            stack.wait_config_up().await;
+        }
+        let server = read_server(ctx.eeprom).await;
+        set_status(SntpState::Syncing, None).await;
+
+        let mut socket =
+            UdpSocket::new(stack, &mut rx_meta, &mut rx_buf, &mut tx_meta, &mut tx_buf);
+        let result = match socket.bind(0) {
+            Ok(()) => sync_once(stack, &mut socket, &server).await,
+            Err(_) => Err("UDP bind failed"),
+        };
+        socket.close();
+
+        match result {
+            Ok(sample) => {
+                wall_clock::set_offset(sample.offset_ms, WallClockSource::Sntp);
+                info!(
+                    "SNTP synced with {} (offset={}ms delay={}ms)",
+                    server.host.as_str(),
+                    sample.offset_ms,
+                    sample.delay_ms
+                );
+                set_status(SntpState::Synced, None).await;
+                wait_for_reconfigure(RESYNC_INTERVAL).await;
+            }
+            Err(message) => {
+                warn!(
+                    "SNTP sync with {} failed: {}",
+                    server.host.as_str(),
+                    message
+                );
+                set_status(SntpState::Error, Some(message)).await;
+                wait_for_reconfigure(RETRY_DELAY).await;
+            }
+        }
+    }
+}
+
+async fn sync_once(
+    stack: Stack<'static>,
+    socket: &mut UdpSocket<'_>,
+    server: &NtpServer,
+) -> Result<Sample, &'static str> {
+    let address = mqtt::resolve_host(stack, &server.host)
+        .await
+        .ok_or("server host lookup failed")?;
+    let endpoint = IpEndpoint::new(address, server.port);
+
+    let rng = Rng::new();
+    let nonce = (rng.random() as u64) << 32 | rng.random() as u64;
+    let mut request = [0u8; PACKET_LEN];
+    sntp::encode_request(&mut request, nonce);
+
+    let sent_ms = timestamp_ms();
+    socket
+        .send_to(&request, endpoint)
+        .await
+        .map_err(|_| "request send failed")?;
+    let deadline = Instant::now() + REPLY_TIMEOUT;
+    let mut reply = [0u8; PACKET_LEN];
+    loop {
+        let (len, meta) = match select(socket.recv_from(&mut reply), Timer::at(deadline)).await {
+            Either::First(Ok(received)) => received,
+            Either::First(Err(_)) => return Err("reply receive failed"),
+            Either::Second(()) => return Err("no reply from server"),
+        };
+        let received_ms = timestamp_ms();
+        // Stray datagrams (wrong peer, stale replies) are ignored until the deadline.
+        if meta.endpoint != endpoint {
+            continue;
+        }
+        let parsed = match sntp::parse_reply(&reply[..len], nonce) {
+            Ok(parsed) => parsed,
+            Err(sntp::Error::OriginMismatch) => continue,
+            Err(sntp::Error::Malformed) => return Err("malformed reply"),
+            Err(sntp::Error::Unsynchronized) => return Err("server not synchronized"),
+        };
+        let sample = Sample::new(sent_ms, &parsed, received_ms);
+        if sample.delay_ms > MAX_DELAY_MS {
+            return Err("round trip too slow");
+        }
+        return Ok(sample);
+    }
+}
+
+async fn wait_for_reconfigure(timeout: Duration) {
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        if RECONFIGURE_REQUESTED.load(Ordering::Relaxed) {
+            return;
+        }
+        Timer::after(Duration::from_millis(250)).await;
+    }
+}
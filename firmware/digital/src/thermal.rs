@@ -0,0 +1,349 @@
+//! Thermal derating model (digital side).
+//!
+//! The analog board only enforces hard trips (`SINK_OVER_TEMP` latches a
+//! fault). This module keeps the load away from that trip by predicting where
+//! the MOSFET heatsink is heading and shrinking the power budget before it gets
+//! there:
+//!
+//! - `sink_exhaust_temp_mc` is used as the air-side reference temperature.
+//! - The heatsink is modelled as a single thermal resistance (`rth_c_per_w`)
+//!   from the core NTC to that reference, plus a first-order time constant
+//!   (`tau_s`).
+//! - The steady-state core temperature for the current `calc_p_mw` decides the
+//!   derate; the first-order trajectory from the measured core temperature
+//!   gives the predicted time-to-trip.
+//!
+//! The resulting derate is also published through an atomic so that the
+//! SetMode TX task can push an updated `LimitProfile` without locking the
+//! telemetry model.
+//!
+//! The model parameters are user-configurable (`/api/v1/thermal`, USB
+//! `set_thermal`) and persisted in EEPROM; the trip temperature follows the
+//! sink limit the analog side last acknowledged in its `ProtectionConfig`.
+
+use core::cell::Cell;
+use core::ops::RangeInclusive;
+use core::sync::atomic::{AtomicI32, AtomicU8, Ordering};
+
+use embassy_sync::blocking_mutex::{Mutex as BlockingMutex, raw::CriticalSectionRawMutex};
+use loadlynx_protocol::{FastStatus, LimitProfile, ProtectionConfig};
+
+/// Thermal model parameters. Stored in integer milli-units so they round-trip
+/// through JSON and EEPROM exactly; the model converts to `f32` like the fan
+/// controller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ThermalModelConfig {
+    /// Core-NTC to exhaust-NTC thermal resistance (m°C/W).
+    pub rth_mc_per_w: u32,
+    /// First-order heatsink time constant (s).
+    pub tau_s: u32,
+    /// Derating starts once the predicted steady-state core temperature comes
+    /// within this margin of `temp_trip_mc` (m°C).
+    pub margin_mc: u32,
+    /// Lower bound for the published derate (%). Hard trips still apply below it.
+    pub min_derate_pct: u8,
+    /// Derate quantization step (%); keeps LimitProfile traffic low.
+    pub step_pct: u8,
+    /// Minimum interval between two upward derate steps (recovery), in ms.
+    pub recover_step_ms: u32,
+}
+
+pub const THERMAL_MODEL_DEFAULT: ThermalModelConfig = ThermalModelConfig {
+    rth_mc_per_w: 300,
+    tau_s: 90,
+    margin_mc: 10_000,
+    min_derate_pct: 20,
+    step_pct: 5,
+    recover_step_ms: 2_000,
+};
+
+pub const THERMAL_RTH_MC_PER_W_RANGE: RangeInclusive<u32> = 50..=5_000;
+pub const THERMAL_TAU_S_RANGE: RangeInclusive<u32> = 5..=3_600;
+pub const THERMAL_MARGIN_MC_RANGE: RangeInclusive<u32> = 0..=30_000;
+pub const THERMAL_MIN_DERATE_PCT_RANGE: RangeInclusive<u32> = 0..=100;
+pub const THERMAL_STEP_PCT_RANGE: RangeInclusive<u32> = 1..=25;
+pub const THERMAL_RECOVER_STEP_MS_RANGE: RangeInclusive<u32> = 100..=60_000;
+
+impl ThermalModelConfig {
+    /// Check every field against its accepted range; the error is the name of
+    /// the first offending field.
+    pub fn validate(&self) -> Result<(), &'static str> {
+        let fields = [
+            (
+                "rth_mc_per_w",
+                self.rth_mc_per_w,
+                THERMAL_RTH_MC_PER_W_RANGE,
+            ),
+            ("tau_s", self.tau_s, THERMAL_TAU_S_RANGE),
+            ("margin_mc", self.margin_mc, THERMAL_MARGIN_MC_RANGE),
+            (
+                "min_derate_pct",
+                self.min_derate_pct as u32,
+                THERMAL_MIN_DERATE_PCT_RANGE,
+            ),
+            ("step_pct", self.step_pct as u32, THERMAL_STEP_PCT_RANGE),
+            (
+                "recover_step_ms",
+                self.recover_step_ms,
+                THERMAL_RECOVER_STEP_MS_RANGE,
+            ),
+        ];
+        for (name, value, range) in fields {
+            if !range.contains(&value) {
+                return Err(name);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Time-to-trip horizon: predictions beyond this are reported as "none".
+const TIME_TO_TRIP_HORIZON_TAUS: u32 = 8;
+/// Forward-simulation substeps per time constant (no libm `exp`/`ln` on target).
+const TIME_TO_TRIP_STEPS_PER_TAU: u32 = 64;
+
+static DERATE_PCT: AtomicU8 = AtomicU8::new(100);
+/// Sink trip the analog side enforces (m°C); updated when it acknowledges a
+/// `ProtectionConfig`.
+static SINK_TRIP_MC: AtomicI32 = AtomicI32::new(ProtectionConfig::DEFAULT.sink_temp_limit_mc);
+/// Saved model parameters; picked up by `ThermalModel` on its next update.
+static CONFIG: BlockingMutex<CriticalSectionRawMutex, Cell<ThermalModelConfig>> =
+    BlockingMutex::new(Cell::new(THERMAL_MODEL_DEFAULT));
+
+/// Output of one model update.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ThermalEstimate {
+    pub derate_pct: u8,
+    /// Predicted time until `sink_core_temp_mc` reaches `temp_trip_mc` at the
+    /// current power; `None` when the steady state stays below the trip.
+    pub time_to_trip_ms: Option<u32>,
+    /// Predicted steady-state core temperature at the current power (m°C).
+    pub steady_core_mc: i32,
+}
+
+pub struct ThermalModel {
+    config: ThermalModelConfig,
+    derate_pct: u8,
+    last_raise_ms: Option<u32>,
+}
+
+impl ThermalModel {
+    pub const fn new(config: ThermalModelConfig) -> Self {
+        Self {
+            config,
+            derate_pct: 100,
+            last_raise_ms: None,
+        }
+    }
+
+    pub fn set_config(&mut self, config: ThermalModelConfig) {
+        self.config = config;
+    }
+
+    /// Feed one FastStatus frame. `uptime_ms` from the frame paces recovery so
+    /// the model does not depend on digital-side timers.
+    pub fn update(&mut self, status: &FastStatus, limits: &LimitProfile) -> ThermalEstimate {
+        let cfg = &self.config;
+        let core_c = status.sink_core_temp_mc as f32 / 1000.0;
+        let exhaust_c = status.sink_exhaust_temp_mc as f32 / 1000.0;
+        // The exhaust side cannot sit above the core in a heating sink; clamp so a
+        // disconnected/odd exhaust NTC does not make the model optimistic.
+        let ref_c = exhaust_c.min(core_c);
+        let trip_c = limits.temp_trip_mc as f32 / 1000.0;
+        let power_w = status.calc_p_mw as f32 / 1000.0;
+        let max_p_w = (limits.max_p_mw as f32 / 1000.0).max(1.0);
+        let rth = (cfg.rth_mc_per_w as f32 / 1000.0).max(0.001);
+        let margin_c = cfg.margin_mc as f32 / 1000.0;
+
+        let steady_c = ref_c + power_w * rth;
+
+        // Model-based budget: largest power whose steady state stays below
+        // (trip - margin).
+        let p_allow_w = ((trip_c - margin_c - ref_c) / rth).max(0.0);
+        let model_pct = p_allow_w * 100.0 / max_p_w;
+
+        // Measurement-based guard: once the core itself is inside the margin,
+        // ramp linearly down to the minimum at the trip point.
+        let guard_pct = if margin_c > 0.0 && core_c > trip_c - margin_c {
+            let over = (core_c - (trip_c - margin_c)) / margin_c;
+            100.0 - over * (100.0 - cfg.min_derate_pct as f32)
+        } else {
+            100.0
+        };
+
+        let candidate = quantize_derate(model_pct.min(guard_pct), cfg);
+        if candidate < self.derate_pct {
+            self.derate_pct = candidate;
+            self.last_raise_ms = Some(status.uptime_ms);
+        } else if candidate > self.derate_pct {
+            let due = match self.last_raise_ms {
+                Some(last) => status.uptime_ms.wrapping_sub(last) >= cfg.recover_step_ms,
+                None => true,
+            };
+            if due {
+                self.derate_pct = self
+                    .derate_pct
+                    .saturating_add(cfg.step_pct.max(1))
+                    .min(candidate);
+                self.last_raise_ms = Some(status.uptime_ms);
+            }
+        }
+
+        let estimate = ThermalEstimate {
+            derate_pct: self.derate_pct,
+            time_to_trip_ms: predict_time_to_trip_ms(core_c, steady_c, trip_c, cfg.tau_s as f32),
+            steady_core_mc: (steady_c * 1000.0) as i32,
+        };
+
+        DERATE_PCT.store(estimate.derate_pct, Ordering::Relaxed);
+        estimate
+    }
+}
+
+fn quantize_derate(pct: f32, cfg: &ThermalModelConfig) -> u8 {
+    let step = cfg.step_pct.max(1) as u32;
+    let pct = if pct.is_nan() { 0.0 } else { pct };
+    // Round to the nearest percent first so 49.999 does not drop a whole step.
+    let clamped = (pct.clamp(cfg.min_derate_pct as f32, 100.0) + 0.5) as u32;
+    let quantized = (clamped / step) * step;
+    (quantized.max(cfg.min_derate_pct as u32)).min(100) as u8
+}
+
+/// First-order prediction `T(t) = T_ss + (T0 - T_ss)·e^(-t/τ)`, solved for
+/// `T(t) = trip` by forward simulation.
+fn predict_time_to_trip_ms(core_c: f32, steady_c: f32, trip_c: f32, tau_s: f32) -> Option<u32> {
+    if core_c >= trip_c {
+        return Some(0);
+    }
+    if steady_c <= trip_c || tau_s <= 0.0 {
+        return None;
+    }
+    let steps = TIME_TO_TRIP_STEPS_PER_TAU * TIME_TO_TRIP_HORIZON_TAUS;
+    let decay = 1.0 - 1.0 / TIME_TO_TRIP_STEPS_PER_TAU as f32;
+    let step_ms = tau_s * 1000.0 / TIME_TO_TRIP_STEPS_PER_TAU as f32;
+    let mut gap = core_c - steady_c;
+    let target_gap = trip_c - steady_c;
+    for k in 1..=steps {
+        gap *= decay;
+        if gap >= target_gap {
+            return Some((k as f32 * step_ms) as u32);
+        }
+    }
+    None
+}
+
+/// Latest published derate (100 = no derating).
+pub fn derate_pct() -> u8 {
+    DERATE_PCT.load(Ordering::Relaxed)
+}
+
+/// Saved model parameters.
+pub fn config() -> ThermalModelConfig {
+    CONFIG.lock(|cell| cell.get())
+}
+
+pub fn set_config(config: ThermalModelConfig) {
+    CONFIG.lock(|cell| cell.set(config));
+}
+
+/// Sink trip temperature the analog side currently enforces (m°C).
+pub fn sink_trip_mc() -> i32 {
+    SINK_TRIP_MC.load(Ordering::Relaxed)
+}
+
+pub fn set_sink_trip_mc(trip_mc: i32) {
+    SINK_TRIP_MC.store(trip_mc, Ordering::Relaxed);
+}
+
+/// Effective LimitProfile: the static v0 limits with the enforced sink trip
+/// and the current thermal derate applied.
+pub fn limit_profile() -> LimitProfile {
+    LimitProfile {
+        temp_trip_mc: sink_trip_mc(),
+        thermal_derate_pct: derate_pct(),
+        ..crate::LIMIT_PROFILE_DEFAULT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(core_c: i32, exhaust_c: i32, p_w: u32, uptime_ms: u32) -> FastStatus {
+        FastStatus {
+            uptime_ms,
+            sink_core_temp_mc: core_c * 1000,
+            sink_exhaust_temp_mc: exhaust_c * 1000,
+            calc_p_mw: p_w * 1000,
+            ..FastStatus::default()
+        }
+    }
+
+    #[test]
+    fn cool_sink_is_not_derated() {
+        let mut model = ThermalModel::new(THERMAL_MODEL_DEFAULT);
+        let est = model.update(&status(35, 30, 50, 0), &crate::LIMIT_PROFILE_DEFAULT);
+        assert_eq!(est.derate_pct, 100);
+        assert_eq!(est.time_to_trip_ms, None);
+    }
+
+    #[test]
+    fn hot_ambient_derates_power_budget() {
+        let mut model = ThermalModel::new(THERMAL_MODEL_DEFAULT);
+        // (100 - 10 - 60) / 0.3 = 100 W of a 200 W budget.
+        let est = model.update(&status(60, 60, 0, 0), &crate::LIMIT_PROFILE_DEFAULT);
+        assert_eq!(est.derate_pct, 50);
+    }
+
+    #[test]
+    fn time_to_trip_tracks_first_order_model() {
+        let mut model = ThermalModel::new(THERMAL_MODEL_DEFAULT);
+        // T_ss = 40 + 300 W · 0.3 °C/W = 130 °C; from 60 °C the trip at 100 °C
+        // is reached after ln(70 / 30) · 90 s ≈ 76 s.
+        let est = model.update(&status(60, 40, 300, 0), &crate::LIMIT_PROFILE_DEFAULT);
+        let ttt = est.time_to_trip_ms.unwrap();
+        assert!((74_000..=78_000).contains(&ttt), "ttt={ttt}");
+        assert!((129_990..=130_010).contains(&est.steady_core_mc));
+    }
+
+    #[test]
+    fn configured_model_and_trip_change_the_budget() {
+        let mut model = ThermalModel::new(THERMAL_MODEL_DEFAULT);
+        model.set_config(ThermalModelConfig {
+            rth_mc_per_w: 150,
+            ..THERMAL_MODEL_DEFAULT
+        });
+        // (90 - 10 - 60) / 0.15 ≈ 133 W of a 200 W budget.
+        let limits = LimitProfile {
+            temp_trip_mc: 90_000,
+            ..crate::LIMIT_PROFILE_DEFAULT
+        };
+        let est = model.update(&status(60, 60, 0, 0), &limits);
+        assert_eq!(est.derate_pct, 65);
+    }
+
+    #[test]
+    fn config_validation_names_the_field() {
+        assert_eq!(THERMAL_MODEL_DEFAULT.validate(), Ok(()));
+        let cfg = ThermalModelConfig {
+            tau_s: 0,
+            ..THERMAL_MODEL_DEFAULT
+        };
+        assert_eq!(cfg.validate(), Err("tau_s"));
+    }
+
+    #[test]
+    fn recovery_is_rate_limited() {
+        let mut model = ThermalModel::new(THERMAL_MODEL_DEFAULT);
+        assert_eq!(
+            model
+                .update(&status(60, 60, 0, 0), &crate::LIMIT_PROFILE_DEFAULT)
+                .derate_pct,
+            50
+        );
+        let est = model.update(&status(30, 30, 0, 100), &crate::LIMIT_PROFILE_DEFAULT);
+        assert_eq!(est.derate_pct, 50);
+        let est = model.update(&status(30, 30, 0, 2_100), &crate::LIMIT_PROFILE_DEFAULT);
+        assert_eq!(est.derate_pct, 55);
+    }
+}
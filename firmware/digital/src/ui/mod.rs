@@ -12,8 +12,10 @@ use embedded_graphics::pixelcolor::{
 use heapless::{String, Vec};
 use lcd_async::raw_framebuf::RawFrameBuf;
 use loadlynx_protocol::{
-    CalKind, FAULT_MCU_OVER_TEMP, FAULT_OVERCURRENT, FAULT_OVERVOLTAGE, FAULT_SINK_OVER_TEMP,
-    LoadMode,
+    CHANNEL_WARN_CH1_STRAY, CHANNEL_WARN_CH2_STRAY, CHANNEL_WARN_IMBALANCE, CalKind,
+    FAULT_MCU_OVER_TEMP, FAULT_OVERCURRENT, FAULT_OVERVOLTAGE, FAULT_SINK_OVER_TEMP, LoadMode,
+    SENSE_WARN_HIGH_LEAD_DROP, SENSE_WARN_OSCILLATION, SENSE_WARN_REMOTE_OPEN,
+    SENSE_WARN_REMOTE_REVERSED,
 };
 
 use crate::control::AdjustDigit;
@@ -1267,6 +1269,20 @@ pub fn hit_test_dashboard_load_button(x: i32, y: i32) -> bool {
     dx.saturating_mul(dx) + dy.saturating_mul(dy) <= radius.saturating_mul(radius)
 }
 
+/// Top telemetry line; tapping it while it shows a pairing code picks the
+/// role the paired token gets.
+pub fn hit_test_dashboard_run_line(x: i32, y: i32) -> bool {
+    (198..LOGICAL_WIDTH).contains(&x)
+        && (TELEMETRY_TOP..TELEMETRY_TOP + TELEMETRY_LINE_HEIGHT).contains(&y)
+}
+
+/// Bottom-right "reason" line; tapping it while it shows a fault/latch issues
+/// ClearFaults.
+pub fn hit_test_dashboard_reason_line(x: i32, y: i32) -> bool {
+    let top = TELEMETRY_TOP + ((TELEMETRY_LINE_COUNT - 1) as i32) * TELEMETRY_LINE_HEIGHT;
+    (198..LOGICAL_WIDTH).contains(&x) && (top..top + TELEMETRY_LINE_HEIGHT).contains(&y)
+}
+
 pub fn hit_test_dashboard_pd_button(x: i32, y: i32) -> bool {
     (PD_BUTTON_LEFT..PD_BUTTON_RIGHT).contains(&x) && (LOAD_ROW_TOP..PD_BUTTON_BOTTOM).contains(&y)
 }
@@ -1818,8 +1834,26 @@ pub struct UiSnapshot {
     pub sink_exhaust_temp: f32,
     pub mcu_temp: f32,
     pub energy_wh: f32,
+    pub charge_ah: f32,
+    /// Analog reports charge/energy counters; the RUN line then rotates
+    /// through `run_line_page` (0 = RUN, 1 = Ah, 2 = Wh).
+    pub counters_live: bool,
+    pub run_line_page: u8,
+    /// HTTP API pairing code; replaces the RUN line while pairing is open.
+    pub pairing_code: Option<u32>,
+    /// Role picked for the pairing by tapping that line ("READ"/"CTRL"/
+    /// "ADMN"); `None` shows "TAP" until someone at the device picks one.
+    pub pairing_role: Option<&'static str>,
+    /// Digital-side thermal derate (100 = none) and predicted time-to-trip.
+    pub thermal_derate_pct: u8,
+    pub thermal_time_to_trip_s: Option<u32>,
     pub remote_active: bool,
     pub fault_flags: u32,
+    /// Analog sense-path diagnostics (`SENSE_WARN_*`) and cable estimate.
+    pub sense_warnings: u32,
+    pub lead_resistance_mohm: Option<u32>,
+    /// Analog CH1/CH2 sharing health (`CHANNEL_WARN_*`).
+    pub channel_warnings: u32,
     pub analog_state: AnalogState,
     pub wifi_status: WifiUiStatus,
     // Control overlay (active preset + mode + output + UV latch), driven by the
@@ -1856,6 +1890,12 @@ pub struct UiSnapshot {
     pub status_lines: [String<20>; 5],
 }
 
+// Longer time-to-trip predictions are not actionable on the dashboard.
+const THERMAL_ETA_DISPLAY_MAX_S: u32 = 1_000;
+/// Dwell time of each RUN / Ah / Wh page on status line #1.
+pub const RUN_LINE_PAGE_MS: u32 = 3_000;
+pub const RUN_LINE_PAGE_COUNT: u8 = 3;
+
 fn fault_flags_abbrev(flags: u32) -> &'static str {
     if flags & FAULT_OVERVOLTAGE != 0 {
         "OVP"
@@ -1888,8 +1928,18 @@ impl UiSnapshot {
             sink_exhaust_temp: 0.0,
             mcu_temp: 0.0,
             energy_wh: 0.0,
+            charge_ah: 0.0,
+            counters_live: false,
+            run_line_page: 0,
+            pairing_code: None,
+            pairing_role: None,
+            thermal_derate_pct: 100,
+            thermal_time_to_trip_s: None,
             remote_active: false,
             fault_flags: 0,
+            sense_warnings: 0,
+            lead_resistance_mohm: None,
+            channel_warnings: 0,
             analog_state: AnalogState::Offline,
             wifi_status: WifiUiStatus::Disabled,
             calibration_mode: CalibrationUiMode::Off,
@@ -1943,8 +1993,18 @@ impl UiSnapshot {
             sink_exhaust_temp: 38.1,
             mcu_temp: 35.0,
             energy_wh: 125.4,
+            charge_ah: 5.128,
+            counters_live: false,
+            run_line_page: 0,
+            pairing_code: None,
+            pairing_role: None,
+            thermal_derate_pct: 100,
+            thermal_time_to_trip_s: None,
             remote_active: true,
             fault_flags: 0,
+            sense_warnings: 0,
+            lead_resistance_mohm: Some(12),
+            channel_warnings: 0,
             analog_state: AnalogState::Ready,
             wifi_status: WifiUiStatus::Disabled,
             calibration_mode: CalibrationUiMode::Off,
@@ -2062,8 +2122,35 @@ impl UiSnapshot {
     // SINK = NTC near exhaust/side wall (Tag2 / TS1 / R39, `sink_exhaust_temp_mc`)
     fn compute_status_lines(&self) -> [String<20>; 5] {
         let mut run = String::<20>::new();
-        let _ = run.push_str("RUN ");
-        let _ = run.push_str(self.run_time.as_str());
+        let page = if self.counters_live {
+            self.run_line_page % RUN_LINE_PAGE_COUNT
+        } else {
+            0
+        };
+        match (self.pairing_code, page) {
+            (Some(code), _) => {
+                let _ = write!(
+                    run,
+                    "PAIR {:06} {}",
+                    code,
+                    self.pairing_role.unwrap_or("TAP")
+                );
+            }
+            (None, 1) => {
+                let _ = run.push_str("CHG ");
+                append_counter_value(&mut run, self.charge_ah);
+                let _ = run.push_str("Ah");
+            }
+            (None, 2) => {
+                let _ = run.push_str("NRG ");
+                append_counter_value(&mut run, self.energy_wh);
+                let _ = run.push_str("Wh");
+            }
+            _ => {
+                let _ = run.push_str("RUN ");
+                let _ = run.push_str(self.run_time.as_str());
+            }
+        }
 
         let mut core = String::<20>::new();
         let _ = core.push_str("CORE ");
@@ -2129,7 +2216,7 @@ impl UiSnapshot {
                     let _ = ctl.push_str("MEAS");
                 }
                 AnalogState::Ready => {
-                    let _ = ctl.push_str("RDY");
+                    self.push_thermal_or_ready(&mut ctl);
                 }
                 AnalogState::Faulted => {
                     let _ = ctl.push_str("FLT");
@@ -2140,11 +2227,104 @@ impl UiSnapshot {
         [run, core, exhaust, mcu, ctl]
     }
 
+    // Ready-state reason line: thermal derate ("DRT80%") and/or predicted
+    // time-to-trip ("T45s") take precedence over sense-wiring warnings, which
+    // take precedence over the plain "RDY".
+    fn push_thermal_or_ready(&self, ctl: &mut String<20>) {
+        let derating = self.thermal_derate_pct < 100;
+        let eta_s = self
+            .thermal_time_to_trip_s
+            .filter(|s| *s < THERMAL_ETA_DISPLAY_MAX_S);
+        if !derating && eta_s.is_none() {
+            if !self.push_sense_warning(ctl) && !self.push_channel_warning(ctl) {
+                let _ = ctl.push_str("RDY");
+            }
+            return;
+        }
+        if derating {
+            let _ = ctl.push_str("DRT");
+            append_u32(ctl, self.thermal_derate_pct as u32);
+            let _ = ctl.push('%');
+        }
+        if let Some(eta_s) = eta_s {
+            if derating {
+                let _ = ctl.push(' ');
+            }
+            let _ = ctl.push('T');
+            append_u32(ctl, eta_s);
+            let _ = ctl.push('s');
+        }
+    }
+
+    // "SNS REV" / "SNS OPEN" / "SNS OSC", or "LEAD 300mR" with the cable
+    // estimate for an excessive local-vs-remote drop.
+    fn push_sense_warning(&self, ctl: &mut String<20>) -> bool {
+        let warnings = self.sense_warnings;
+        if warnings & SENSE_WARN_REMOTE_REVERSED != 0 {
+            let _ = ctl.push_str("SNS REV");
+        } else if warnings & SENSE_WARN_REMOTE_OPEN != 0 {
+            let _ = ctl.push_str("SNS OPEN");
+        } else if warnings & SENSE_WARN_OSCILLATION != 0 {
+            let _ = ctl.push_str("SNS OSC");
+        } else if warnings & SENSE_WARN_HIGH_LEAD_DROP != 0 {
+            let _ = ctl.push_str("LEAD");
+            if let Some(mohm) = self.lead_resistance_mohm {
+                let _ = ctl.push(' ');
+                append_u32(ctl, mohm);
+                let _ = ctl.push_str("mR");
+            }
+        } else {
+            return false;
+        }
+        true
+    }
+
+    // "CH1 STRAY" / "CH2 STRAY" (idle channel conducts) before "CH IMBAL".
+    fn push_channel_warning(&self, ctl: &mut String<20>) -> bool {
+        let warnings = self.channel_warnings;
+        if warnings & CHANNEL_WARN_CH1_STRAY != 0 {
+            let _ = ctl.push_str("CH1 STRAY");
+        } else if warnings & CHANNEL_WARN_CH2_STRAY != 0 {
+            let _ = ctl.push_str("CH2 STRAY");
+        } else if warnings & CHANNEL_WARN_IMBALANCE != 0 {
+            let _ = ctl.push_str("CH IMBAL");
+        } else {
+            return false;
+        }
+        true
+    }
+
     pub fn status_lines(&self) -> &[String<20>; 5] {
         &self.status_lines
     }
 }
 
+// Four significant digits ("1.234", "12.34", "123.4"), whole units from
+// 1000 up; the counters never go negative.
+fn append_counter_value<const N: usize>(buf: &mut String<N>, value: f32) {
+    let v = if value.is_finite() {
+        value.max(0.0)
+    } else {
+        0.0
+    };
+    let digits: u8 = if v < 10.0 {
+        3
+    } else if v < 100.0 {
+        2
+    } else if v < 1_000.0 {
+        1
+    } else {
+        0
+    };
+    let scale = 10u32.pow(digits as u32);
+    let scaled = (v.min(999_999.0) * scale as f32 + 0.5) as u32;
+    append_u32(buf, scaled / scale);
+    if digits > 0 {
+        let _ = buf.push('.');
+        append_frac(buf, scaled % scale, digits);
+    }
+}
+
 fn append_temp_1dp<const N: usize>(buf: &mut String<N>, value: f32) {
     // 简单 1 位小数格式化（不做宽度对齐），与 format_value 使用同样的缩放策略。
     let mut v = value;
@@ -2252,4 +2432,96 @@ mod tests {
         assert_eq!(snapshot.ch1_current_text.as_str(), "--.--A");
         assert_eq!(snapshot.status_lines()[4].as_str(), "MEAS");
     }
+
+    #[test]
+    fn ready_status_line_reports_thermal_derate_and_time_to_trip() {
+        let mut snapshot = UiSnapshot::demo();
+        snapshot.thermal_derate_pct = 80;
+        snapshot.thermal_time_to_trip_s = Some(45);
+        snapshot.update_strings();
+        assert_eq!(snapshot.status_lines()[4].as_str(), "DRT80% T45s");
+
+        snapshot.thermal_time_to_trip_s = Some(THERMAL_ETA_DISPLAY_MAX_S);
+        snapshot.update_strings();
+        assert_eq!(snapshot.status_lines()[4].as_str(), "DRT80%");
+
+        snapshot.thermal_derate_pct = 100;
+        snapshot.update_strings();
+        assert_eq!(snapshot.status_lines()[4].as_str(), "RDY");
+    }
+
+    #[test]
+    fn ready_status_line_reports_sense_warnings_after_thermal() {
+        let mut snapshot = UiSnapshot::demo();
+        snapshot.sense_warnings = SENSE_WARN_HIGH_LEAD_DROP;
+        snapshot.lead_resistance_mohm = Some(300);
+        snapshot.update_strings();
+        assert_eq!(snapshot.status_lines()[4].as_str(), "LEAD 300mR");
+
+        snapshot.sense_warnings |= SENSE_WARN_REMOTE_OPEN;
+        snapshot.update_strings();
+        assert_eq!(snapshot.status_lines()[4].as_str(), "SNS OPEN");
+
+        snapshot.thermal_derate_pct = 80;
+        snapshot.update_strings();
+        assert_eq!(snapshot.status_lines()[4].as_str(), "DRT80%");
+    }
+
+    #[test]
+    fn run_line_rotates_through_counters() {
+        let mut snapshot = UiSnapshot::demo();
+        snapshot.run_line_page = 1;
+        snapshot.update_strings();
+        // Analog firmware without counters keeps the plain RUN line.
+        assert_eq!(snapshot.status_lines()[0].as_str(), "RUN 01:32:10");
+
+        snapshot.counters_live = true;
+        snapshot.update_strings();
+        assert_eq!(snapshot.status_lines()[0].as_str(), "CHG 5.128Ah");
+
+        snapshot.run_line_page = 2;
+        snapshot.update_strings();
+        assert_eq!(snapshot.status_lines()[0].as_str(), "NRG 125.4Wh");
+
+        snapshot.energy_wh = 12_345.6;
+        snapshot.update_strings();
+        assert_eq!(snapshot.status_lines()[0].as_str(), "NRG 12346Wh");
+
+        snapshot.run_line_page = 3;
+        snapshot.update_strings();
+        assert_eq!(snapshot.status_lines()[0].as_str(), "RUN 01:32:10");
+    }
+
+    #[test]
+    fn pairing_code_replaces_run_line() {
+        let mut snapshot = UiSnapshot::demo();
+        snapshot.counters_live = true;
+        snapshot.pairing_code = Some(42_017);
+        snapshot.update_strings();
+        assert_eq!(snapshot.status_lines()[0].as_str(), "PAIR 042017 TAP");
+
+        snapshot.pairing_role = Some("ADMN");
+        snapshot.update_strings();
+        assert_eq!(snapshot.status_lines()[0].as_str(), "PAIR 042017 ADMN");
+
+        snapshot.pairing_code = None;
+        snapshot.update_strings();
+        assert_eq!(snapshot.status_lines()[0].as_str(), "RUN 01:32:10");
+    }
+
+    #[test]
+    fn ready_status_line_reports_channel_warnings_after_sense() {
+        let mut snapshot = UiSnapshot::demo();
+        snapshot.channel_warnings = CHANNEL_WARN_IMBALANCE;
+        snapshot.update_strings();
+        assert_eq!(snapshot.status_lines()[4].as_str(), "CH IMBAL");
+
+        snapshot.channel_warnings |= CHANNEL_WARN_CH2_STRAY;
+        snapshot.update_strings();
+        assert_eq!(snapshot.status_lines()[4].as_str(), "CH2 STRAY");
+
+        snapshot.sense_warnings = SENSE_WARN_REMOTE_OPEN;
+        snapshot.update_strings();
+        assert_eq!(snapshot.status_lines()[4].as_str(), "SNS OPEN");
+    }
 }
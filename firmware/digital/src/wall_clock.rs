@@ -0,0 +1,85 @@
+//! Wall-clock offset on top of the uptime clock.
+//!
+//! Everything else on the device timestamps with [`crate::timestamp_ms`]; this
+//! module keeps `unix_ms - uptime_ms` once a source has provided real time,
+//! either the SNTP client (`sntp.rs`) or the host over USB/HTTP
+//! (`POST /api/v1/time`, USB `set_time`). The offset is lost on reset.
+
+#![cfg_attr(not(feature = "net_http"), allow(dead_code))]
+
+use core::cell::Cell;
+use core::fmt::Write;
+
+use embassy_sync::blocking_mutex::{Mutex, raw::CriticalSectionRawMutex};
+use loadlynx_sntp::{RFC3339_LEN, format_rfc3339};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum WallClockSource {
+    Sntp,
+    Host,
+}
+
+impl WallClockSource {
+    pub const fn name(self) -> &'static str {
+        match self {
+            WallClockSource::Sntp => "sntp",
+            WallClockSource::Host => "host",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct WallClock {
+    /// `unix_ms - uptime_ms`.
+    pub offset_ms: i64,
+    pub source: WallClockSource,
+    /// Uptime at which the offset was last set.
+    pub synced_at_ms: u64,
+}
+
+// No 64-bit atomics on Xtensa; a critical section guards the offset instead.
+static WALL_CLOCK: Mutex<CriticalSectionRawMutex, Cell<Option<WallClock>>> =
+    Mutex::new(Cell::new(None));
+
+pub fn get() -> Option<WallClock> {
+    WALL_CLOCK.lock(|clock| clock.get())
+}
+
+/// Record that uptime + `offset_ms` is Unix time, as measured by `source`.
+pub fn set_offset(offset_ms: i64, source: WallClockSource) {
+    let clock = WallClock {
+        offset_ms,
+        source,
+        synced_at_ms: crate::timestamp_ms(),
+    };
+    WALL_CLOCK.lock(|cell| cell.set(Some(clock)));
+}
+
+/// Record that it is `unix_ms` right now.
+pub fn set_unix_ms(unix_ms: u64, source: WallClockSource) {
+    set_offset(unix_ms as i64 - crate::timestamp_ms() as i64, source);
+}
+
+/// Unix milliseconds for an uptime stamp, once the wall clock is set.
+pub fn unix_ms_at(uptime_ms: u64) -> Option<u64> {
+    let clock = get()?;
+    u64::try_from(uptime_ms as i64 + clock.offset_ms).ok()
+}
+
+pub fn unix_ms_now() -> Option<u64> {
+    unix_ms_at(crate::timestamp_ms())
+}
+
+/// Write the current time as a JSON value: an RFC3339 string, or `null`
+/// while the wall clock is unset.
+pub fn write_json_wall_time<W: Write>(out: &mut W) {
+    match unix_ms_now() {
+        Some(unix_ms) => {
+            let mut text = [0u8; RFC3339_LEN];
+            let _ = write!(out, "\"{}\"", format_rfc3339(unix_ms, &mut text));
+        }
+        None => {
+            let _ = out.write_str("null");
+        }
+    }
+}
@@ -0,0 +1,544 @@
+//! WebSocket control/telemetry channel (`GET /api/v1/ws`).
+//!
+//! One connection multiplexes what the web console otherwise does with the
+//! status SSE stream plus one HTTP request per action:
+//!
+//! - `status` frames pushed every `status_interval_ms` (same JSON as
+//!   `GET /api/v1/status`);
+//! - `event` frames when the control view, PD view or fault flags change;
+//! - `response` frames answering client requests `{"id":N,"op":"..."}`, which
+//!   run through the same handlers as the HTTP endpoints.
+//!
+//! Backpressure: requests are handled one at a time (the TCP receive window
+//! throttles a fast client), and status pushes are skipped while the socket
+//! send buffer is more than half full. Skipped frames are reported in the
+//! next status frame's `dropped` field. See `docs/interfaces/network-http-api.md`.
+//!
+//! The upgrade request is authenticated like the SSE stream (header or
+//! `?access_token=`), so a `read` token can watch; the `*.set` and
+//! `presets.apply` ops need `control`, as their HTTP routes do.
+
+use core::fmt::Write as _;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use alloc::{string::String, vec};
+use defmt::*;
+use embassy_futures::select::{Either, select};
+use embassy_net::tcp::TcpSocket;
+use embassy_time::{Duration, Instant, Timer};
+use loadlynx_websocket as websocket;
+
+use crate::{
+    CONTROL_REV, CalibrationMutex, ControlMutex, EepromMutex, TelemetryMutex, auth, net, wall_clock,
+};
+
+pub const WS_PROTOCOL: &str = "loadlynx.ws.v1";
+/// Largest client message (frame header included); matches the HTTP request cap.
+const MAX_MESSAGE_BYTES: usize = 1024;
+const STATUS_INTERVAL_MS_DEFAULT: u64 = 200;
+const STATUS_INTERVAL_MS_RANGE: (u64, u64) = (100, 5_000);
+/// Control/PD/fault views are compared at this cadence to emit events.
+const EVENT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+const SOCKET_TIMEOUT: Duration = Duration::from_secs(30);
+const KEEP_ALIVE: Duration = Duration::from_secs(10);
+
+/// Allow a single WebSocket session so it never starves the HTTP worker pool;
+/// additional clients get `503` and fall back to SSE + HTTP.
+static WS_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+pub(crate) struct WsContext {
+    pub telemetry: &'static TelemetryMutex,
+    pub calibration: &'static CalibrationMutex,
+    pub eeprom: &'static EepromMutex,
+    pub control: &'static ControlMutex,
+    /// Role of the token the upgrade request presented.
+    pub role: auth::Role,
+}
+
+/// Complete the upgrade for `GET /api/v1/ws` and serve the session until the
+/// client goes away.
+pub(crate) async fn handle_websocket(
+    socket: &mut TcpSocket<'_>,
+    client_key: &str,
+    cors_origin: Option<&str>,
+    ctx: WsContext,
+) -> Result<(), embassy_net::tcp::Error> {
+    if WS_ACTIVE
+        .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+        .is_err()
+    {
+        let mut body = String::new();
+        net::write_error_body(
+            &mut body,
+            "UNAVAILABLE",
+            "WebSocket session already active",
+            true,
+            None,
+        );
+        net::write_http_response(
+            socket,
+            "HTTP/1.1",
+            "503 Service Unavailable",
+            &body,
+            cors_origin,
+        )
+        .await?;
+        return Ok(());
+    }
+
+    struct ClearWsActive;
+    impl Drop for ClearWsActive {
+        fn drop(&mut self) {
+            WS_ACTIVE.store(false, Ordering::Release);
+        }
+    }
+    let _guard = ClearWsActive;
+
+    let accept = websocket::accept_key(client_key);
+    let mut head = String::new();
+    head.push_str(
+        "HTTP/1.1 101 Switching Protocols\r\n\
+         Upgrade: websocket\r\n\
+         Connection: Upgrade\r\n\
+         Sec-WebSocket-Accept: ",
+    );
+    head.push_str(core::str::from_utf8(&accept).unwrap_or(""));
+    head.push_str("\r\n\r\n");
+    net::socket_write_all(socket, head.as_bytes()).await?;
+    socket.flush().await?;
+
+    socket.set_timeout(Some(SOCKET_TIMEOUT));
+    socket.set_keep_alive(Some(KEEP_ALIVE));
+    info!("WebSocket session opened");
+    let result = Session::new(ctx).run(socket).await;
+    info!("WebSocket session closed");
+    result
+}
+
+struct Session {
+    ctx: WsContext,
+    status_enabled: bool,
+    status_interval: Duration,
+    dropped_status: u32,
+    last_control_rev: u32,
+    last_fault_flags: Option<u32>,
+    last_pd: String,
+    out: String,
+    body: String,
+}
+
+enum Flow {
+    Continue,
+    Close(u16),
+}
+
+impl Session {
+    fn new(ctx: WsContext) -> Self {
+        Self {
+            ctx,
+            status_enabled: true,
+            status_interval: Duration::from_millis(STATUS_INTERVAL_MS_DEFAULT),
+            dropped_status: 0,
+            last_control_rev: CONTROL_REV.load(Ordering::Relaxed),
+            last_fault_flags: None,
+            last_pd: String::new(),
+            out: String::new(),
+            body: String::new(),
+        }
+    }
+
+    async fn run(&mut self, socket: &mut TcpSocket<'_>) -> Result<(), embassy_net::tcp::Error> {
+        self.out.clear();
+        let _ = write!(
+            self.out,
+            "{{\"type\":\"hello\",\"protocol\":\"{}\",\"max_message_bytes\":{},\"status_interval_ms\":{}}}",
+            WS_PROTOCOL,
+            MAX_MESSAGE_BYTES,
+            self.status_interval.as_millis()
+        );
+        send_text(socket, &self.out).await?;
+        // Seed the change trackers so the first poll does not report events
+        // the client already gets from its initial `*.get` requests.
+        let _ =
+            net::render_pd_view_json(&mut self.last_pd, self.ctx.control, self.ctx.telemetry).await;
+
+        let mut rx = vec![0u8; MAX_MESSAGE_BYTES];
+        let mut rx_len = 0usize;
+        let mut next_status = Instant::now();
+        let mut next_poll = Instant::now() + EVENT_POLL_INTERVAL;
+
+        loop {
+            let now = Instant::now();
+            if now >= next_status {
+                next_status = now + self.status_interval;
+                if self.status_enabled {
+                    self.push_status(socket).await?;
+                }
+            }
+            if now >= next_poll {
+                next_poll = now + EVENT_POLL_INTERVAL;
+                self.push_events(socket).await?;
+            }
+
+            let wake_at = next_status.min(next_poll);
+            let n = match select(socket.read(&mut rx[rx_len..]), Timer::at(wake_at)).await {
+                Either::First(read) => read?,
+                Either::Second(()) => continue,
+            };
+            if n == 0 {
+                return Ok(());
+            }
+            rx_len += n;
+
+            loop {
+                let header = match websocket::decode_header(&rx[..rx_len]) {
+                    Ok(Some(header)) => header,
+                    Ok(None) => break,
+                    Err(_) => {
+                        return close(socket, websocket::CLOSE_PROTOCOL_ERROR).await;
+                    }
+                };
+                let frame_len = header.header_len as u64 + header.payload_len;
+                if frame_len > MAX_MESSAGE_BYTES as u64 {
+                    return close(socket, websocket::CLOSE_MESSAGE_TOO_BIG).await;
+                }
+                let frame_len = frame_len as usize;
+                if rx_len < frame_len {
+                    break;
+                }
+                let payload = &mut rx[header.header_len..frame_len];
+                websocket::unmask(payload, header.mask, 0);
+                let flow = match header.opcode {
+                    websocket::OPCODE_TEXT if header.fin => match core::str::from_utf8(payload) {
+                        Ok(text) => {
+                            self.handle_message(socket, text).await?;
+                            Flow::Continue
+                        }
+                        Err(_) => Flow::Close(websocket::CLOSE_UNSUPPORTED_DATA),
+                    },
+                    websocket::OPCODE_PING => {
+                        send_frame(socket, websocket::OPCODE_PONG, payload).await?;
+                        Flow::Continue
+                    }
+                    websocket::OPCODE_PONG => Flow::Continue,
+                    websocket::OPCODE_CLOSE => Flow::Close(websocket::CLOSE_NORMAL),
+                    // Binary and fragmented messages are not part of the protocol.
+                    _ => Flow::Close(websocket::CLOSE_UNSUPPORTED_DATA),
+                };
+                if let Flow::Close(code) = flow {
+                    return close(socket, code).await;
+                }
+                rx.copy_within(frame_len..rx_len, 0);
+                rx_len -= frame_len;
+            }
+        }
+    }
+
+    async fn push_status(
+        &mut self,
+        socket: &mut TcpSocket<'_>,
+    ) -> Result<(), embassy_net::tcp::Error> {
+        if socket.send_queue() > socket.send_capacity() / 2 {
+            self.dropped_status = self.dropped_status.saturating_add(1);
+            return Ok(());
+        }
+        self.body.clear();
+        if net::render_status_json_sse(&mut self.body, self.ctx.telemetry)
+            .await
+            .is_err()
+        {
+            return Ok(());
+        }
+        self.out.clear();
+        let _ = write!(
+            self.out,
+            "{{\"type\":\"status\",\"dropped\":{},\"data\":",
+            self.dropped_status
+        );
+        self.out.push_str(&self.body);
+        self.out.push('}');
+        self.dropped_status = 0;
+        send_text(socket, &self.out).await
+    }
+
+    async fn push_events(
+        &mut self,
+        socket: &mut TcpSocket<'_>,
+    ) -> Result<(), embassy_net::tcp::Error> {
+        let control_rev = CONTROL_REV.load(Ordering::Relaxed);
+        if control_rev != self.last_control_rev {
+            self.last_control_rev = control_rev;
+            self.body.clear();
+            if net::render_control_view_json(
+                &mut self.body,
+                self.ctx.control,
+                self.ctx.calibration,
+                self.ctx.telemetry,
+            )
+            .await
+            .is_ok()
+            {
+                self.send_event(socket, "control").await?;
+            }
+        }
+
+        let fault_flags = {
+            self.ctx
+                .telemetry
+                .lock()
+                .await
+                .last_status
+                .map(|status| status.fault_flags)
+        };
+        if fault_flags.is_some() && fault_flags != self.last_fault_flags {
+            self.last_fault_flags = fault_flags;
+            self.body.clear();
+            let _ = write!(
+                self.body,
+                "{{\"fault_flags\":{}}}",
+                fault_flags.unwrap_or(0)
+            );
+            self.send_event(socket, "faults").await?;
+        }
+
+        self.body.clear();
+        if net::render_pd_view_json(&mut self.body, self.ctx.control, self.ctx.telemetry)
+            .await
+            .is_ok()
+            && self.body != self.last_pd
+        {
+            self.last_pd.clear();
+            self.last_pd.push_str(&self.body);
+            self.send_event(socket, "pd").await?;
+        }
+        Ok(())
+    }
+
+    /// Send `self.body` as an event frame.
+    async fn send_event(
+        &mut self,
+        socket: &mut TcpSocket<'_>,
+        event: &str,
+    ) -> Result<(), embassy_net::tcp::Error> {
+        self.out.clear();
+        let _ = write!(
+            self.out,
+            "{{\"type\":\"event\",\"event\":\"{}\",\"wall_time\":",
+            event
+        );
+        wall_clock::write_json_wall_time(&mut self.out);
+        self.out.push_str(",\"data\":");
+        self.out.push_str(&self.body);
+        self.out.push('}');
+        send_text(socket, &self.out).await
+    }
+
+    async fn handle_message(
+        &mut self,
+        socket: &mut TcpSocket<'_>,
+        text: &str,
+    ) -> Result<(), embassy_net::tcp::Error> {
+        let (envelope, data) = split_data_field(text);
+        let id = net::parse_json_i64_optional(&envelope, "\"id\"")
+            .ok()
+            .flatten();
+        let result = match net::parse_json_str(&envelope, "\"op\"") {
+            Ok(op) => self.execute(op, data.unwrap_or("{}")).await,
+            Err(_) => {
+                net::write_error_body(&mut self.body, "INVALID_REQUEST", "missing op", false, None);
+                Err("400 Bad Request")
+            }
+        };
+        let status = match result {
+            Ok(()) => "200 OK",
+            Err(status) => status,
+        };
+        let code = status
+            .split(' ')
+            .next()
+            .and_then(|code| code.parse::<u16>().ok())
+            .unwrap_or(500);
+        if self.body.is_empty() {
+            self.body.push_str("{}");
+        }
+
+        self.out.clear();
+        self.out.push_str("{\"type\":\"response\",\"id\":");
+        match id {
+            Some(id) => {
+                let _ = write!(self.out, "{}", id);
+            }
+            None => self.out.push_str("null"),
+        }
+        let _ = write!(
+            self.out,
+            ",\"ok\":{},\"status\":{},\"data\":",
+            result.is_ok(),
+            code
+        );
+        self.out.push_str(&self.body);
+        self.out.push('}');
+        send_text(socket, &self.out).await
+    }
+
+    /// Run one request op; `self.body` receives the response or error body.
+    async fn execute(&mut self, op: &str, data: &str) -> Result<(), &'static str> {
+        let ctx = &self.ctx;
+        let body = &mut self.body;
+        body.clear();
+        let writes = matches!(
+            op,
+            "cc.set" | "control.set" | "pd.set" | "presets.set" | "presets.apply"
+        );
+        if writes && ctx.role < auth::Role::Control {
+            net::write_error_body(
+                body,
+                "FORBIDDEN",
+                "API token lacks the permission for this op",
+                false,
+                Some("{\"required\":\"control\"}"),
+            );
+            return Err("403 Forbidden");
+        }
+        match op {
+            "ping" => {
+                body.push_str("{\"ok\":true}");
+                Ok(())
+            }
+            "subscribe" => {
+                if let Some(enabled) = net::parse_json_bool_value(data, "\"status\"") {
+                    self.status_enabled = enabled;
+                }
+                match net::parse_json_i64_optional(data, "\"status_interval_ms\"") {
+                    Ok(None) => {}
+                    Ok(Some(ms))
+                        if (STATUS_INTERVAL_MS_RANGE.0 as i64
+                            ..=STATUS_INTERVAL_MS_RANGE.1 as i64)
+                            .contains(&ms) =>
+                    {
+                        self.status_interval = Duration::from_millis(ms as u64);
+                    }
+                    _ => {
+                        net::write_error_body(
+                            body,
+                            "INVALID_REQUEST",
+                            "status_interval_ms must be 100..5000",
+                            false,
+                            None,
+                        );
+                        return Err("400 Bad Request");
+                    }
+                }
+                let _ = write!(
+                    body,
+                    "{{\"status\":{},\"status_interval_ms\":{}}}",
+                    self.status_enabled,
+                    self.status_interval.as_millis()
+                );
+                Ok(())
+            }
+            "status.get" => net::render_status_json(body, ctx.telemetry).await,
+            "cc.get" => {
+                net::render_cc_view_json(body, ctx.control, ctx.calibration, ctx.telemetry).await
+            }
+            "cc.set" => {
+                net::handle_cc_update(data, body, ctx.control, ctx.calibration, ctx.telemetry).await
+            }
+            "control.get" => {
+                net::render_control_view_json(body, ctx.control, ctx.calibration, ctx.telemetry)
+                    .await
+            }
+            "control.set" => {
+                net::handle_control_update(data, body, ctx.control, ctx.calibration, ctx.telemetry)
+                    .await
+            }
+            "pd.get" => net::render_pd_view_json(body, ctx.control, ctx.telemetry).await,
+            "pd.set" => {
+                net::handle_pd_update(data, body, ctx.control, ctx.telemetry, ctx.eeprom).await
+            }
+            "presets.get" => net::render_presets_json(body, ctx.control).await,
+            "presets.set" => net::handle_presets_update(data, body, ctx.control, ctx.eeprom).await,
+            "presets.apply" => {
+                net::handle_presets_apply(data, body, ctx.control, ctx.calibration, ctx.telemetry)
+                    .await
+            }
+            _ => {
+                net::write_error_body(
+                    body,
+                    "UNSUPPORTED_OPERATION",
+                    "unknown WebSocket op",
+                    false,
+                    None,
+                );
+                Err("404 Not Found")
+            }
+        }
+    }
+}
+
+/// Split a request into its envelope (with the `"data"` object removed) and
+/// the raw `data` object, so envelope keys never collide with handler keys.
+fn split_data_field(text: &str) -> (String, Option<&str>) {
+    let Some(key) = text.find("\"data\"") else {
+        return (String::from(text), None);
+    };
+    let Some(colon) = text[key..].find(':') else {
+        return (String::from(text), None);
+    };
+    let value_start = key + colon + 1;
+    let rest = &text[value_start..];
+    let trimmed = rest.trim_start();
+    if !trimmed.starts_with('{') {
+        return (String::from(text), None);
+    }
+    let start = value_start + (rest.len() - trimmed.len());
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (idx, ch) in text[start..].char_indices() {
+        if in_string {
+            match ch {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match ch {
+            '"' => in_string = true,
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    let end = start + idx + 1;
+                    let mut envelope = String::from(&text[..key]);
+                    envelope.push_str(&text[end..]);
+                    return (envelope, Some(&text[start..end]));
+                }
+            }
+            _ => {}
+        }
+    }
+    (String::from(text), None)
+}
+
+async fn send_frame(
+    socket: &mut TcpSocket<'_>,
+    opcode: u8,
+    payload: &[u8],
+) -> Result<(), embassy_net::tcp::Error> {
+    let mut header = [0u8; websocket::MAX_HEADER_LEN];
+    let len = websocket::encode_header(&mut header, opcode, payload.len()).unwrap_or(0);
+    net::socket_write_all(socket, &header[..len]).await?;
+    net::socket_write_all(socket, payload).await
+}
+
+async fn send_text(socket: &mut TcpSocket<'_>, text: &str) -> Result<(), embassy_net::tcp::Error> {
+    send_frame(socket, websocket::OPCODE_TEXT, text.as_bytes()).await
+}
+
+async fn close(socket: &mut TcpSocket<'_>, code: u16) -> Result<(), embassy_net::tcp::Error> {
+    send_frame(socket, websocket::OPCODE_CLOSE, &code.to_be_bytes()).await?;
+    socket.flush().await
+}
@@ -0,0 +1,402 @@
+//! Calibration curve quality analysis.
+//!
+//! A piecewise-linear curve always passes through its own points, so the
+//! residuals reported here are leave-one-out: each interior point is predicted
+//! from its neighbours and compared with the meter reading. That is the
+//! interpolation error the firmware makes between calibration points and is
+//! the basis of the accuracy estimate. Residuals against the final curve are
+//! also reported; they are non-zero only for source points that were replaced
+//! by deduplication.
+//!
+//! Shared by the CLI and devd; `no_std`, integer-only.
+
+use heapless::Vec;
+use loadlynx_protocol::ProtectionConfig;
+
+use crate::{CalPoint, CurveKind, MAX_POINTS_V3, normalize_points};
+
+pub const MAX_SEGMENTS: usize = MAX_POINTS_V3 - 1;
+pub const MAX_WARNINGS: usize = 16;
+
+/// Points should cover at least this share of the nominal range at each end;
+/// beyond it the firmware extrapolates the end segment.
+pub const EXTRAPOLATION_MARGIN_PERMILLE: i32 = 100;
+/// Segment gain deviating from the end-to-end gain by more than this.
+pub const GAIN_OUTLIER_PPM: i64 = 50_000;
+/// Leave-one-out residual larger than this share of the nominal range.
+pub const LARGE_RESIDUAL_PERMILLE: i32 = 10;
+/// Consecutive points closer than this share of the nominal range.
+pub const SMALL_STEP_PERMILLE: i32 = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SegmentFit {
+    pub raw_lo: i16,
+    pub raw_hi: i16,
+    pub meas_lo: i32,
+    pub meas_hi: i32,
+    /// Physical units (mA or mV) per raw millivolt, scaled by 1000.
+    pub gain_milli_per_mv: i64,
+    /// Value of the segment's line at raw = 0, in physical units.
+    pub offset: i32,
+    /// Deviation of this segment's gain from the end-to-end gain.
+    pub gain_deviation_ppm: i64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PointResidual {
+    pub raw_100uv: i16,
+    pub meas_physical: i32,
+    /// Source point minus the final curve.
+    pub residual: i32,
+    /// Point minus the prediction from its neighbours; `None` for endpoints.
+    pub loo_residual: Option<i32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Warning {
+    TooFewPoints { count: u8 },
+    NotMonotonic { index: u8 },
+    DuplicateRaw { raw_100uv: i16 },
+    ExtrapolatesBelow { meas_min: i32, nominal_min: i32 },
+    ExtrapolatesAbove { meas_max: i32, nominal_max: i32 },
+    SmallStep { index: u8, step: i32 },
+    GainOutlier { segment: u8, deviation_ppm: i64 },
+    LargeResidual { index: u8, residual: i32 },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CurveReport {
+    pub kind: CurveKind,
+    /// Points as supplied (before sort/dedup).
+    pub source_points: u8,
+    /// Points the firmware will use.
+    pub curve_points: u8,
+    pub nominal_min: i32,
+    pub nominal_max: i32,
+    pub segments: Vec<SegmentFit, MAX_SEGMENTS>,
+    pub residuals: Vec<PointResidual, MAX_POINTS_V3>,
+    /// Smallest meas step between consecutive curve points (monotonicity margin).
+    pub min_meas_step: Option<i32>,
+    /// Smallest raw step between consecutive curve points.
+    pub min_raw_step: Option<i32>,
+    pub monotonic: bool,
+    /// Worst leave-one-out residual, in physical units; `None` below 3 points.
+    pub est_accuracy_abs: Option<i32>,
+    /// `est_accuracy_abs` relative to the nominal range.
+    pub est_accuracy_ppm: Option<i64>,
+    pub warnings: Vec<Warning, MAX_WARNINGS>,
+}
+
+/// Nominal operating range used for coverage and relative figures: up to the
+/// default per-channel OC trip for current curves, the default OV trip for
+/// voltage curves.
+pub const fn nominal_range(kind: CurveKind) -> (i32, i32) {
+    match kind {
+        CurveKind::CurrentCh1 | CurveKind::CurrentCh2 => {
+            (0, ProtectionConfig::DEFAULT.oc_limit_ch_ma)
+        }
+        CurveKind::VLocal | CurveKind::VRemote => (0, ProtectionConfig::DEFAULT.ov_limit_mv),
+    }
+}
+
+/// Evaluate the piecewise-linear curve the same way the analog firmware does
+/// (end segments extrapolate; a single point scales through the origin).
+pub fn evaluate(points: &[CalPoint], raw: i16) -> Option<i32> {
+    match points {
+        [] => None,
+        [p] => {
+            if p.raw_100uv == 0 {
+                Some(p.meas_physical)
+            } else {
+                Some(clamp_i32(
+                    raw as i64 * p.meas_physical as i64 / p.raw_100uv as i64,
+                ))
+            }
+        }
+        _ => {
+            let last = points.len() - 1;
+            let idx = if raw <= points[0].raw_100uv {
+                0
+            } else if raw >= points[last].raw_100uv {
+                last - 1
+            } else {
+                points
+                    .windows(2)
+                    .position(|w| raw >= w[0].raw_100uv && raw <= w[1].raw_100uv)?
+            };
+            interpolate(points[idx], points[idx + 1], raw)
+        }
+    }
+}
+
+fn interpolate(a: CalPoint, b: CalPoint, raw: i16) -> Option<i32> {
+    let den = b.raw_100uv as i64 - a.raw_100uv as i64;
+    if den == 0 {
+        return None;
+    }
+    let num = (b.meas_physical as i64 - a.meas_physical as i64) * (raw as i64 - a.raw_100uv as i64);
+    Some(clamp_i32(a.meas_physical as i64 + num / den))
+}
+
+fn clamp_i32(v: i64) -> i32 {
+    v.clamp(i32::MIN as i64, i32::MAX as i64) as i32
+}
+
+fn gain_milli_per_mv(a: CalPoint, b: CalPoint) -> Option<i64> {
+    let d_raw = b.raw_100uv as i64 - a.raw_100uv as i64;
+    if d_raw == 0 {
+        return None;
+    }
+    // raw is 100 µV/LSB: units per mV = d_meas * 10 / d_raw.
+    Some((b.meas_physical as i64 - a.meas_physical as i64) * 10_000 / d_raw)
+}
+
+pub fn analyze_curve(kind: CurveKind, source: &[CalPoint]) -> CurveReport {
+    let (nominal_min, nominal_max) = nominal_range(kind);
+    let span = (nominal_max - nominal_min).max(1);
+
+    let mut warnings = Vec::<Warning, MAX_WARNINGS>::new();
+    let mut warn = |w: Warning| {
+        let _ = warnings.push(w);
+    };
+
+    let mut bounded = Vec::<CalPoint, MAX_POINTS_V3>::new();
+    for p in source.iter().take(MAX_POINTS_V3) {
+        let _ = bounded.push(*p);
+    }
+    let curve = normalize_points(bounded);
+
+    // Duplicates that normalization collapsed.
+    for (idx, p) in source.iter().enumerate() {
+        let same = |q: &CalPoint| q.raw_100uv == p.raw_100uv;
+        if !source[..idx].iter().any(same) && source[idx + 1..].iter().any(same) {
+            warn(Warning::DuplicateRaw {
+                raw_100uv: p.raw_100uv,
+            });
+        }
+    }
+
+    if curve.len() < 2 {
+        warn(Warning::TooFewPoints {
+            count: curve.len() as u8,
+        });
+    }
+
+    let mut monotonic = true;
+    let mut min_meas_step = None::<i32>;
+    let mut min_raw_step = None::<i32>;
+    for (i, w) in curve.windows(2).enumerate() {
+        let meas_step = w[1].meas_physical.saturating_sub(w[0].meas_physical);
+        let raw_step = w[1].raw_100uv as i32 - w[0].raw_100uv as i32;
+        if meas_step <= 0 {
+            monotonic = false;
+            warn(Warning::NotMonotonic { index: i as u8 + 1 });
+        } else if (meas_step as i64) * 1000 < span as i64 * SMALL_STEP_PERMILLE as i64 {
+            warn(Warning::SmallStep {
+                index: i as u8 + 1,
+                step: meas_step,
+            });
+        }
+        min_meas_step = Some(min_meas_step.map_or(meas_step, |m| m.min(meas_step)));
+        min_raw_step = Some(min_raw_step.map_or(raw_step, |m| m.min(raw_step)));
+    }
+
+    if let (Some(first), Some(last)) = (curve.first(), curve.last()) {
+        let margin = (span as i64 * EXTRAPOLATION_MARGIN_PERMILLE as i64 / 1000) as i32;
+        if first.meas_physical > nominal_min + margin {
+            warn(Warning::ExtrapolatesBelow {
+                meas_min: first.meas_physical,
+                nominal_min,
+            });
+        }
+        if last.meas_physical < nominal_max - margin {
+            warn(Warning::ExtrapolatesAbove {
+                meas_max: last.meas_physical,
+                nominal_max,
+            });
+        }
+    }
+
+    let overall_gain = match (curve.first(), curve.last()) {
+        (Some(a), Some(b)) if curve.len() >= 2 => gain_milli_per_mv(*a, *b),
+        _ => None,
+    };
+    let mut segments = Vec::<SegmentFit, MAX_SEGMENTS>::new();
+    for (i, w) in curve.windows(2).enumerate() {
+        let Some(gain) = gain_milli_per_mv(w[0], w[1]) else {
+            continue;
+        };
+        let offset = evaluate(&curve[i..i + 2], 0).unwrap_or(0);
+        let deviation_ppm = match overall_gain {
+            Some(g) if g != 0 => (gain - g) * 1_000_000 / g,
+            _ => 0,
+        };
+        if deviation_ppm.abs() > GAIN_OUTLIER_PPM {
+            warn(Warning::GainOutlier {
+                segment: i as u8,
+                deviation_ppm,
+            });
+        }
+        let _ = segments.push(SegmentFit {
+            raw_lo: w[0].raw_100uv,
+            raw_hi: w[1].raw_100uv,
+            meas_lo: w[0].meas_physical,
+            meas_hi: w[1].meas_physical,
+            gain_milli_per_mv: gain,
+            offset,
+            gain_deviation_ppm: deviation_ppm,
+        });
+    }
+
+    let mut residuals = Vec::<PointResidual, MAX_POINTS_V3>::new();
+    let mut est_accuracy_abs = None::<i32>;
+    for p in source.iter().take(MAX_POINTS_V3) {
+        let residual = evaluate(&curve, p.raw_100uv)
+            .map(|v| p.meas_physical.saturating_sub(v))
+            .unwrap_or(0);
+        let idx = curve.iter().position(|c| c == p);
+        let loo_residual = match idx {
+            Some(i) if i > 0 && i + 1 < curve.len() => {
+                interpolate(curve[i - 1], curve[i + 1], p.raw_100uv)
+                    .map(|v| p.meas_physical.saturating_sub(v))
+            }
+            _ => None,
+        };
+        if let (Some(loo), Some(i)) = (loo_residual, idx) {
+            est_accuracy_abs = Some(est_accuracy_abs.map_or(loo.abs(), |m| m.max(loo.abs())));
+            if (loo.unsigned_abs() as i64) * 1000 > span as i64 * LARGE_RESIDUAL_PERMILLE as i64 {
+                warn(Warning::LargeResidual {
+                    index: i as u8,
+                    residual: loo,
+                });
+            }
+        }
+        let _ = residuals.push(PointResidual {
+            raw_100uv: p.raw_100uv,
+            meas_physical: p.meas_physical,
+            residual,
+            loo_residual,
+        });
+    }
+
+    CurveReport {
+        kind,
+        source_points: source.len().min(u8::MAX as usize) as u8,
+        curve_points: curve.len() as u8,
+        nominal_min,
+        nominal_max,
+        segments,
+        residuals,
+        min_meas_step,
+        min_raw_step,
+        monotonic,
+        est_accuracy_abs,
+        est_accuracy_ppm: est_accuracy_abs.map(|a| a as i64 * 1_000_000 / span as i64),
+        warnings,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn p(raw: i16, meas: i32) -> CalPoint {
+        CalPoint {
+            raw_100uv: raw,
+            raw_dac_code: 0,
+            meas_physical: meas,
+        }
+    }
+
+    #[test]
+    fn linear_curve_has_zero_residuals_and_uniform_gain() {
+        let pts = [
+            p(0, 0),
+            p(10_000, 2_000),
+            p(20_000, 4_000),
+            p(27_500, 5_500),
+        ];
+        let report = analyze_curve(CurveKind::CurrentCh1, &pts);
+        assert!(report.monotonic);
+        assert_eq!(report.curve_points, 4);
+        assert_eq!(report.segments.len(), 3);
+        // 2 mA per mV.
+        assert!(report.segments.iter().all(|s| s.gain_milli_per_mv == 2_000));
+        assert!(report.segments.iter().all(|s| s.offset == 0));
+        assert_eq!(report.est_accuracy_abs, Some(0));
+        assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+    }
+
+    #[test]
+    fn bent_curve_reports_loo_residual_and_outlier() {
+        let pts = [
+            p(0, 0),
+            p(10_000, 2_300),
+            p(20_000, 4_000),
+            p(27_500, 5_500),
+        ];
+        let report = analyze_curve(CurveKind::CurrentCh2, &pts);
+        // Neighbours predict 2000 mA at raw 10000.
+        assert_eq!(report.residuals[1].loo_residual, Some(300));
+        assert_eq!(report.residuals[0].loo_residual, None);
+        assert_eq!(report.est_accuracy_abs, Some(300));
+        assert_eq!(report.est_accuracy_ppm, Some(300 * 1_000_000 / 5_500));
+        assert!(
+            report
+                .warnings
+                .iter()
+                .any(|w| matches!(w, Warning::LargeResidual { index: 1, .. }))
+        );
+        assert!(
+            report
+                .warnings
+                .iter()
+                .any(|w| matches!(w, Warning::GainOutlier { segment: 0, .. }))
+        );
+    }
+
+    #[test]
+    fn narrow_coverage_warns_about_extrapolation() {
+        let pts = [p(5_000, 6_000), p(10_000, 12_000)];
+        let report = analyze_curve(CurveKind::VLocal, &pts);
+        assert!(report.warnings.contains(&Warning::ExtrapolatesBelow {
+            meas_min: 6_000,
+            nominal_min: 0
+        }));
+        assert!(report.warnings.contains(&Warning::ExtrapolatesAbove {
+            meas_max: 12_000,
+            nominal_max: ProtectionConfig::DEFAULT.ov_limit_mv
+        }));
+        assert_eq!(report.est_accuracy_abs, None);
+    }
+
+    #[test]
+    fn duplicates_and_non_monotonic_points_are_flagged() {
+        let pts = [p(0, 0), p(1_000, 900), p(1_000, 1_000), p(2_000, 800)];
+        let report = analyze_curve(CurveKind::CurrentCh1, &pts);
+        assert_eq!(report.source_points, 4);
+        assert_eq!(report.curve_points, 3);
+        assert!(!report.monotonic);
+        assert!(
+            report
+                .warnings
+                .contains(&Warning::DuplicateRaw { raw_100uv: 1_000 })
+        );
+        assert!(
+            report
+                .warnings
+                .contains(&Warning::NotMonotonic { index: 2 })
+        );
+        // The replaced duplicate deviates from the final curve.
+        assert_eq!(report.residuals[1].residual, -100);
+    }
+
+    #[test]
+    fn evaluate_matches_firmware_extrapolation() {
+        let pts = [p(1_000, 1_000), p(2_000, 2_000)];
+        assert_eq!(evaluate(&pts, 0), Some(0));
+        assert_eq!(evaluate(&pts, 3_000), Some(3_000));
+        assert_eq!(evaluate(&[p(1_000, 2_000)], 1_500), Some(3_000));
+        assert_eq!(evaluate(&[], 1_500), None);
+    }
+}
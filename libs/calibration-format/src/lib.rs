@@ -6,10 +6,15 @@ use loadlynx_protocol::{CalWrite, crc16_ccitt_false};
 #[cfg(test)]
 extern crate std;
 
+pub mod analysis;
+pub mod slots;
+pub mod verify;
+
 pub const CAL_FMT_VERSION_V1: u8 = 1;
 pub const CAL_FMT_VERSION_V2: u8 = 2;
 pub const CAL_FMT_VERSION_V3: u8 = 3;
-pub const CAL_FMT_VERSION_LATEST: u8 = CAL_FMT_VERSION_V3;
+pub const CAL_FMT_VERSION_V4: u8 = 4;
+pub const CAL_FMT_VERSION_LATEST: u8 = CAL_FMT_VERSION_V4;
 pub const CAL_FMT_VERSION: u8 = CAL_FMT_VERSION_LATEST;
 
 // v4.2 -> 42 (see docs/dev-notes/user-calibration.md examples).
@@ -21,6 +26,9 @@ pub const DIGITAL_HW_REV: u8 = 42;
 pub const MAX_POINTS_V1: usize = 5;
 pub const MAX_POINTS_V2: usize = 7;
 pub const MAX_POINTS_V3: usize = 24;
+// v4 keeps the v3 point layout and adds one temperature-compensation record
+// per curve after the points (still inside the 1 KiB profile).
+pub const MAX_POINTS_V4: usize = MAX_POINTS_V3;
 
 // M24C64 (64 Kbit = 8 KiB) external EEPROM.
 pub const EEPROM_I2C_ADDR_7BIT: u8 = 0x50;
@@ -29,7 +37,8 @@ pub const EEPROM_I2C_ADDR_7BIT: u8 = 0x50;
 pub const EEPROM_PROFILE_BASE_ADDR: u16 = 0x0000;
 pub const EEPROM_PROFILE_LEN_V1V2: usize = 256;
 pub const EEPROM_PROFILE_LEN_V3: usize = 1024;
-pub const EEPROM_PROFILE_LEN_MAX: usize = EEPROM_PROFILE_LEN_V3;
+pub const EEPROM_PROFILE_LEN_V4: usize = EEPROM_PROFILE_LEN_V3;
+pub const EEPROM_PROFILE_LEN_MAX: usize = EEPROM_PROFILE_LEN_V4;
 pub const EEPROM_PROFILE_CRC32_LEN: usize = 4;
 pub const EEPROM_PROFILE_LEN: usize = EEPROM_PROFILE_LEN_MAX;
 
@@ -43,7 +52,17 @@ pub const CALWRITE_HEADER_LEN: usize = 8;
 pub const CALWRITE_POINTS_REGION_LEN: usize = 24;
 pub const CALWRITE_POINTS_PER_CHUNK: usize = 3;
 pub const CALWRITE_POINT_LEN: usize = 8;
-pub const CALWRITE_MAX_CHUNKS: usize = 8;
+// 24 points = 8 point chunks, plus the v4 temperature-compensation chunk.
+pub const CALWRITE_MAX_CHUNKS: usize = 9;
+
+// v4 temperature-compensation record (EEPROM and CalWrite points region):
+// sensor u8, 3 reserved, t_ref_mc i32, gain_ppm_per_c i32, offset_micro_per_c i32.
+pub const TEMP_COMP_RECORD_LEN: usize = 16;
+
+/// Bounds for [`TempComp`] fields; keeps the integer correction well-defined.
+pub const TEMP_COMP_T_REF_MC_RANGE: core::ops::RangeInclusive<i32> = -40_000..=150_000;
+pub const TEMP_COMP_GAIN_PPM_PER_C_MAX: i32 = 5_000;
+pub const TEMP_COMP_OFFSET_MICRO_PER_C_MAX: i32 = 1_000_000;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ProfileSource {
@@ -63,6 +82,130 @@ impl CurveKind {
     pub const fn as_u8(self) -> u8 {
         self as u8
     }
+
+    pub const fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(CurveKind::VLocal),
+            1 => Some(CurveKind::VRemote),
+            2 => Some(CurveKind::CurrentCh1),
+            3 => Some(CurveKind::CurrentCh2),
+            _ => None,
+        }
+    }
+}
+
+/// Temperature that drives a curve's compensation (both are reported in
+/// `FastStatus`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum TempSensor {
+    /// `sink_core_temp_mc`: NTC next to the MOSFETs / shunts.
+    #[default]
+    SinkCore = 0,
+    /// `mcu_temp_mc`: STM32 die temperature (ADC / op-amp reference drift).
+    Mcu = 1,
+}
+
+impl TempSensor {
+    pub const fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(TempSensor::SinkCore),
+            1 => Some(TempSensor::Mcu),
+            _ => None,
+        }
+    }
+
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            TempSensor::SinkCore => "sink_core",
+            TempSensor::Mcu => "mcu",
+        }
+    }
+}
+
+/// First-order temperature drift of one calibrated curve (fmt v4).
+///
+/// The curve's reading at temperature `T` is modelled as
+/// `true * (1 + gain * dT) + offset * dT` with `dT = T - t_ref`; the analog
+/// side inverts this. `offset_micro_per_c` is in µA/°C for current curves and
+/// µV/°C for voltage curves. All-zero coefficients ([`TempComp::NONE`]) disable
+/// compensation, which is what v1–v3 profiles migrate to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct TempComp {
+    pub sensor: TempSensor,
+    pub t_ref_mc: i32,
+    pub gain_ppm_per_c: i32,
+    pub offset_micro_per_c: i32,
+}
+
+impl TempComp {
+    pub const NONE: Self = Self {
+        sensor: TempSensor::SinkCore,
+        t_ref_mc: 25_000,
+        gain_ppm_per_c: 0,
+        offset_micro_per_c: 0,
+    };
+
+    pub const fn is_none(&self) -> bool {
+        self.gain_ppm_per_c == 0 && self.offset_micro_per_c == 0
+    }
+
+    pub fn is_valid(&self) -> bool {
+        TEMP_COMP_T_REF_MC_RANGE.contains(&self.t_ref_mc)
+            && self.gain_ppm_per_c.unsigned_abs() <= TEMP_COMP_GAIN_PPM_PER_C_MAX as u32
+            && self.offset_micro_per_c.unsigned_abs() <= TEMP_COMP_OFFSET_MICRO_PER_C_MAX as u32
+    }
+
+    pub fn write_record(&self, out: &mut [u8]) {
+        out[0] = self.sensor as u8;
+        out[1..4].fill(0);
+        out[4..8].copy_from_slice(&self.t_ref_mc.to_le_bytes());
+        out[8..12].copy_from_slice(&self.gain_ppm_per_c.to_le_bytes());
+        out[12..16].copy_from_slice(&self.offset_micro_per_c.to_le_bytes());
+    }
+
+    /// Map a curve reading to the temperature-corrected physical value, as
+    /// the analog firmware does. `temp_mc` is the reading of [`TempComp::sensor`].
+    pub fn correct(&self, reading: i32, temp_mc: i32) -> i32 {
+        if self.is_none() {
+            return reading;
+        }
+        let dt_mc = temp_mc as i64 - self.t_ref_mc as i64;
+        let offset = self.offset_micro_per_c as i64 * dt_mc / 1_000_000;
+        let den = 1_000_000_000 + self.gain_ppm_per_c as i64 * dt_mc;
+        if den <= 0 {
+            return reading;
+        }
+        let out = (reading as i64 - offset) * 1_000_000_000 / den;
+        out.clamp(i32::MIN as i64, i32::MAX as i64) as i32
+    }
+
+    /// Inverse of [`TempComp::correct`]: the curve reading that corresponds
+    /// to the physical `value` at `temp_mc`.
+    pub fn uncorrect(&self, value: i32, temp_mc: i32) -> i32 {
+        if self.is_none() {
+            return value;
+        }
+        let dt_mc = temp_mc as i64 - self.t_ref_mc as i64;
+        let offset = self.offset_micro_per_c as i64 * dt_mc / 1_000_000;
+        let scaled =
+            value as i64 * (1_000_000_000 + self.gain_ppm_per_c as i64 * dt_mc) / 1_000_000_000;
+        (scaled + offset).clamp(i32::MIN as i64, i32::MAX as i64) as i32
+    }
+
+    /// Parse a record written by [`TempComp::write_record`]; `None` if the
+    /// sensor is unknown or a field is out of range.
+    pub fn read_record(bytes: &[u8]) -> Option<Self> {
+        let word = |off: usize| {
+            i32::from_le_bytes([bytes[off], bytes[off + 1], bytes[off + 2], bytes[off + 3]])
+        };
+        let tc = Self {
+            sensor: TempSensor::from_u8(bytes[0])?,
+            t_ref_mc: word(4),
+            gain_ppm_per_c: word(8),
+            offset_micro_per_c: word(12),
+        };
+        tc.is_valid().then_some(tc)
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
@@ -81,6 +224,8 @@ pub struct ActiveProfile {
     pub current_ch2: Vec<CalPoint, MAX_POINTS_V3>,
     pub v_local: Vec<CalPoint, MAX_POINTS_V3>,
     pub v_remote: Vec<CalPoint, MAX_POINTS_V3>,
+    /// Per-curve temperature compensation, indexed by [`CurveKind::as_u8`].
+    pub temp_comp: [TempComp; 4],
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -90,6 +235,7 @@ pub enum ProfileLoadError {
     HwRevMismatch { stored: u8, expected: u8 },
     InvalidCounts,
     CrcMismatch { stored: u32, computed: u32 },
+    InvalidTempComp,
 }
 
 impl ActiveProfile {
@@ -139,7 +285,32 @@ impl ActiveProfile {
             current_ch2,
             v_local,
             v_remote,
+            temp_comp: [TempComp::NONE; 4],
+        }
+    }
+
+    /// Upgrade a profile loaded from an older format to
+    /// [`CAL_FMT_VERSION_LATEST`] in RAM. v1–v3 point tables fit the v4 layout
+    /// unchanged and carry no temperature data, so compensation stays disabled
+    /// until the user supplies coefficients. Returns `true` if the version
+    /// changed (the EEPROM copy is rewritten on the next commit).
+    pub fn migrate_to_latest(&mut self) -> bool {
+        if self.fmt_version == CAL_FMT_VERSION_LATEST {
+            return false;
         }
+        if self.fmt_version < CAL_FMT_VERSION_V4 {
+            self.temp_comp = [TempComp::NONE; 4];
+        }
+        self.fmt_version = CAL_FMT_VERSION_LATEST;
+        true
+    }
+
+    pub fn temp_comp_for(&self, kind: CurveKind) -> &TempComp {
+        &self.temp_comp[kind.as_u8() as usize]
+    }
+
+    pub fn temp_comp_for_mut(&mut self, kind: CurveKind) -> &mut TempComp {
+        &mut self.temp_comp[kind.as_u8() as usize]
     }
 
     pub fn points_for(&self, kind: CurveKind) -> &[CalPoint] {
@@ -195,21 +366,28 @@ pub fn meas_is_strictly_increasing(points: &[CalPoint]) -> bool {
     true
 }
 
+/// Split one curve into CalWrite chunks.
+///
+/// fmt v4 appends one extra chunk (the last index) whose points region holds
+/// the curve's [`TempComp`] record; earlier versions ignore `temp_comp`.
 pub fn encode_calwrite_chunks(
     fmt_version: u8,
     hw_rev: u8,
     kind: CurveKind,
     points: &[CalPoint],
+    temp_comp: &TempComp,
 ) -> Vec<CalWrite, CALWRITE_MAX_CHUNKS> {
     let max_points = match fmt_version {
         CAL_FMT_VERSION_V1 => MAX_POINTS_V1,
         CAL_FMT_VERSION_V2 => MAX_POINTS_V2,
         CAL_FMT_VERSION_V3 => MAX_POINTS_V3,
-        _ => MAX_POINTS_V3,
+        _ => MAX_POINTS_V4,
     };
 
     let total_points = points.len().min(max_points);
-    let total_chunks = total_points.div_ceil(CALWRITE_POINTS_PER_CHUNK).max(1);
+    let point_chunks = total_points.div_ceil(CALWRITE_POINTS_PER_CHUNK).max(1);
+    let has_temp_comp = fmt_version >= CAL_FMT_VERSION_V4;
+    let total_chunks = point_chunks + usize::from(has_temp_comp);
 
     let mut chunks = Vec::<CalWrite, CALWRITE_MAX_CHUNKS>::new();
     for chunk_index in 0..total_chunks {
@@ -223,15 +401,21 @@ pub fn encode_calwrite_chunks(
         payload[6] = 0;
         payload[7] = 0;
 
-        let base_point = chunk_index * CALWRITE_POINTS_PER_CHUNK;
-        for i in 0..CALWRITE_POINTS_PER_CHUNK {
-            let point_index = base_point + i;
-            let dst = CALWRITE_HEADER_LEN + i * CALWRITE_POINT_LEN;
-            if point_index < total_points {
-                let p = points[point_index];
-                payload[dst..dst + 2].copy_from_slice(&p.raw_100uv.to_le_bytes());
-                payload[dst + 2..dst + 4].copy_from_slice(&p.raw_dac_code.to_le_bytes());
-                payload[dst + 4..dst + 8].copy_from_slice(&p.meas_physical.to_le_bytes());
+        if chunk_index == point_chunks {
+            temp_comp.write_record(
+                &mut payload[CALWRITE_HEADER_LEN..CALWRITE_HEADER_LEN + TEMP_COMP_RECORD_LEN],
+            );
+        } else {
+            let base_point = chunk_index * CALWRITE_POINTS_PER_CHUNK;
+            for i in 0..CALWRITE_POINTS_PER_CHUNK {
+                let point_index = base_point + i;
+                let dst = CALWRITE_HEADER_LEN + i * CALWRITE_POINT_LEN;
+                if point_index < total_points {
+                    let p = points[point_index];
+                    payload[dst..dst + 2].copy_from_slice(&p.raw_100uv.to_le_bytes());
+                    payload[dst + 2..dst + 4].copy_from_slice(&p.raw_dac_code.to_le_bytes());
+                    payload[dst + 4..dst + 8].copy_from_slice(&p.meas_physical.to_le_bytes());
+                }
             }
         }
 
@@ -259,6 +443,8 @@ pub fn serialize_profile(profile: &ActiveProfile) -> [u8; EEPROM_PROFILE_LEN] {
     // fmt_version=1: 8..168 points (4 curves x 5 points x 8B)
     // fmt_version=2: 8..232 points (4 curves x 7 points x 8B)
     // 252..256: crc32 (u32 LE) over 0..252
+    // fmt_version=3: 8..776 points (4 curves x 24 points x 8B), crc32 at 1020..1024
+    // fmt_version=4: v3 layout + 776..840 temp comp (4 x 16B, CurveKind order)
     const OFF_FMT: usize = 0;
     const OFF_HW_REV: usize = 1;
     const OFF_COUNTS: usize = 2;
@@ -268,6 +454,7 @@ pub fn serialize_profile(profile: &ActiveProfile) -> [u8; EEPROM_PROFILE_LEN] {
         CAL_FMT_VERSION_V1 => (EEPROM_PROFILE_LEN_V1V2, MAX_POINTS_V1),
         CAL_FMT_VERSION_V2 => (EEPROM_PROFILE_LEN_V1V2, MAX_POINTS_V2),
         CAL_FMT_VERSION_V3 => (EEPROM_PROFILE_LEN_V3, MAX_POINTS_V3),
+        CAL_FMT_VERSION_V4 => (EEPROM_PROFILE_LEN_V4, MAX_POINTS_V4),
         _ => (EEPROM_PROFILE_LEN_V1V2, MAX_POINTS_V2),
     };
     let crc_offset = profile_len - EEPROM_PROFILE_CRC32_LEN;
@@ -302,6 +489,14 @@ pub fn serialize_profile(profile: &ActiveProfile) -> [u8; EEPROM_PROFILE_LEN] {
     write_curve(2, profile.v_local.as_slice());
     write_curve(3, profile.v_remote.as_slice());
 
+    if profile.fmt_version >= CAL_FMT_VERSION_V4 {
+        let base = temp_comp_offset(points_per_curve);
+        for (idx, tc) in profile.temp_comp.iter().enumerate() {
+            let dst = base + idx * TEMP_COMP_RECORD_LEN;
+            tc.write_record(&mut out[dst..dst + TEMP_COMP_RECORD_LEN]);
+        }
+    }
+
     let crc = crc32_ieee(&out[..crc_offset]);
     out[crc_offset..crc_offset + EEPROM_PROFILE_CRC32_LEN].copy_from_slice(&crc.to_le_bytes());
     out
@@ -321,6 +516,7 @@ pub fn deserialize_profile(
         CAL_FMT_VERSION_V1 => (EEPROM_PROFILE_LEN_V1V2, MAX_POINTS_V1),
         CAL_FMT_VERSION_V2 => (EEPROM_PROFILE_LEN_V1V2, MAX_POINTS_V2),
         CAL_FMT_VERSION_V3 => (EEPROM_PROFILE_LEN_V3, MAX_POINTS_V3),
+        CAL_FMT_VERSION_V4 => (EEPROM_PROFILE_LEN_V4, MAX_POINTS_V4),
         _ => return Err(ProfileLoadError::UnsupportedFmtVersion(fmt_version)),
     };
     let crc_offset = profile_len - EEPROM_PROFILE_CRC32_LEN;
@@ -379,6 +575,16 @@ pub fn deserialize_profile(
     let v_local = read_curve(2, counts[2] as usize);
     let v_remote = read_curve(3, counts[3] as usize);
 
+    let mut temp_comp = [TempComp::NONE; 4];
+    if fmt_version >= CAL_FMT_VERSION_V4 {
+        let base = temp_comp_offset(points_per_curve);
+        for (idx, tc) in temp_comp.iter_mut().enumerate() {
+            let src = base + idx * TEMP_COMP_RECORD_LEN;
+            *tc = TempComp::read_record(&bytes[src..src + TEMP_COMP_RECORD_LEN])
+                .ok_or(ProfileLoadError::InvalidTempComp)?;
+        }
+    }
+
     Ok(ActiveProfile {
         source: ProfileSource::UserCalibrated,
         fmt_version,
@@ -387,9 +593,14 @@ pub fn deserialize_profile(
         current_ch2,
         v_local,
         v_remote,
+        temp_comp,
     })
 }
 
+const fn temp_comp_offset(points_per_curve: usize) -> usize {
+    8 + 4 * points_per_curve * CALWRITE_POINT_LEN
+}
+
 pub fn crc32_ieee(bytes: &[u8]) -> u32 {
     let mut crc = 0xFFFF_FFFFu32;
     for &b in bytes {
@@ -447,70 +658,77 @@ mod tests {
 
         assert_eq!(
             encode_calwrite_chunks(
-                CAL_FMT_VERSION_LATEST,
+                CAL_FMT_VERSION_V3,
                 DIGITAL_HW_REV,
                 CurveKind::VLocal,
-                &pts1
+                &pts1,
+                &TempComp::NONE,
             )
             .len(),
             1
         );
         assert_eq!(
             encode_calwrite_chunks(
-                CAL_FMT_VERSION_LATEST,
+                CAL_FMT_VERSION_V3,
                 DIGITAL_HW_REV,
                 CurveKind::VLocal,
-                &pts2
+                &pts2,
+                &TempComp::NONE,
             )
             .len(),
             1
         );
         assert_eq!(
             encode_calwrite_chunks(
-                CAL_FMT_VERSION_LATEST,
+                CAL_FMT_VERSION_V3,
                 DIGITAL_HW_REV,
                 CurveKind::VLocal,
-                &pts3
+                &pts3,
+                &TempComp::NONE,
             )
             .len(),
             1
         );
         assert_eq!(
             encode_calwrite_chunks(
-                CAL_FMT_VERSION_LATEST,
+                CAL_FMT_VERSION_V3,
                 DIGITAL_HW_REV,
                 CurveKind::VLocal,
-                &pts4
+                &pts4,
+                &TempComp::NONE,
             )
             .len(),
             2
         );
         assert_eq!(
             encode_calwrite_chunks(
-                CAL_FMT_VERSION_LATEST,
+                CAL_FMT_VERSION_V3,
                 DIGITAL_HW_REV,
                 CurveKind::VLocal,
-                &pts5
+                &pts5,
+                &TempComp::NONE,
             )
             .len(),
             2
         );
         assert_eq!(
             encode_calwrite_chunks(
-                CAL_FMT_VERSION_LATEST,
+                CAL_FMT_VERSION_V3,
                 DIGITAL_HW_REV,
                 CurveKind::VLocal,
-                &pts6
+                &pts6,
+                &TempComp::NONE,
             )
             .len(),
             2
         );
         assert_eq!(
             encode_calwrite_chunks(
-                CAL_FMT_VERSION_LATEST,
+                CAL_FMT_VERSION_V3,
                 DIGITAL_HW_REV,
                 CurveKind::VLocal,
-                &pts7
+                &pts7,
+                &TempComp::NONE,
             )
             .len(),
             3
@@ -520,12 +738,18 @@ mod tests {
     #[test]
     fn calwrite_payload_layout_and_crc16_match() {
         let pts = [p(-123, 0x1234, -0x1020_3040)];
-        let chunks = encode_calwrite_chunks(CAL_FMT_VERSION, 7, CurveKind::CurrentCh2, &pts);
+        let chunks = encode_calwrite_chunks(
+            CAL_FMT_VERSION_V3,
+            7,
+            CurveKind::CurrentCh2,
+            &pts,
+            &TempComp::NONE,
+        );
         assert_eq!(chunks.len(), 1);
         let c = chunks[0];
 
         // Header bytes.
-        assert_eq!(c.payload[0], CAL_FMT_VERSION);
+        assert_eq!(c.payload[0], CAL_FMT_VERSION_V3);
         assert_eq!(c.payload[1], 7);
         assert_eq!(c.payload[2], CurveKind::CurrentCh2.as_u8());
         assert_eq!(c.payload[3], 0); // chunk_index
@@ -570,6 +794,123 @@ mod tests {
         assert!(matches!(err, ProfileLoadError::CrcMismatch { .. }));
     }
 
+    #[test]
+    fn v4_calwrite_appends_temp_comp_chunk() {
+        let pts = [p(1, 2, 3), p(4, 5, 6), p(7, 8, 9), p(10, 11, 12)];
+        let tc = TempComp {
+            sensor: TempSensor::Mcu,
+            t_ref_mc: 23_500,
+            gain_ppm_per_c: -45,
+            offset_micro_per_c: 1_200,
+        };
+        let chunks = encode_calwrite_chunks(
+            CAL_FMT_VERSION_V4,
+            DIGITAL_HW_REV,
+            CurveKind::CurrentCh1,
+            &pts,
+            &tc,
+        );
+        assert_eq!(chunks.len(), 3);
+        for (idx, c) in chunks.iter().enumerate() {
+            assert_eq!(c.payload[3] as usize, idx);
+            assert_eq!(c.payload[4], 3); // total_chunks includes temp comp
+            assert_eq!(c.payload[5], 4); // total_points excludes it
+        }
+        let last = &chunks[2].payload;
+        let record = &last[CALWRITE_HEADER_LEN..CALWRITE_HEADER_LEN + TEMP_COMP_RECORD_LEN];
+        assert_eq!(TempComp::read_record(record), Some(tc));
+    }
+
+    #[test]
+    fn v4_eeprom_roundtrips_temp_comp() {
+        let mut prof = ActiveProfile::factory_default(DIGITAL_HW_REV);
+        prof.source = ProfileSource::UserCalibrated;
+        *prof.temp_comp_for_mut(CurveKind::VRemote) = TempComp {
+            sensor: TempSensor::SinkCore,
+            t_ref_mc: 30_000,
+            gain_ppm_per_c: 12,
+            offset_micro_per_c: -80,
+        };
+        let bytes = serialize_profile(&prof);
+        let decoded = deserialize_profile(&bytes, DIGITAL_HW_REV).unwrap();
+        assert_eq!(decoded.temp_comp, prof.temp_comp);
+
+        // Unknown sensor byte is rejected even with a valid CRC.
+        let mut bad = bytes;
+        bad[temp_comp_offset(MAX_POINTS_V4)] = 7;
+        let crc = crc32_ieee(&bad[..EEPROM_PROFILE_LEN_V4 - EEPROM_PROFILE_CRC32_LEN]);
+        bad[EEPROM_PROFILE_LEN_V4 - EEPROM_PROFILE_CRC32_LEN..].copy_from_slice(&crc.to_le_bytes());
+        assert_eq!(
+            deserialize_profile(&bad, DIGITAL_HW_REV).unwrap_err(),
+            ProfileLoadError::InvalidTempComp
+        );
+    }
+
+    #[test]
+    fn v3_profile_migrates_without_temp_comp() {
+        let mut prof = ActiveProfile::factory_default(DIGITAL_HW_REV);
+        prof.source = ProfileSource::UserCalibrated;
+        prof.fmt_version = CAL_FMT_VERSION_V3;
+        let bytes = serialize_profile(&prof);
+        assert!(
+            bytes[temp_comp_offset(MAX_POINTS_V3)..EEPROM_PROFILE_LEN_V3 - 4]
+                .iter()
+                .all(|b| *b == 0)
+        );
+
+        let mut decoded = deserialize_profile(&bytes, DIGITAL_HW_REV).unwrap();
+        assert_eq!(decoded.fmt_version, CAL_FMT_VERSION_V3);
+        assert_eq!(decoded.temp_comp, [TempComp::NONE; 4]);
+        assert!(decoded.migrate_to_latest());
+        assert_eq!(decoded.fmt_version, CAL_FMT_VERSION_LATEST);
+        assert!(!decoded.migrate_to_latest());
+        assert_eq!(decoded.current_ch1, prof.current_ch1);
+    }
+
+    #[test]
+    fn temp_comp_bounds() {
+        assert!(TempComp::NONE.is_valid());
+        assert!(TempComp::NONE.is_none());
+        let too_hot = TempComp {
+            t_ref_mc: 200_000,
+            ..TempComp::NONE
+        };
+        assert!(!too_hot.is_valid());
+        let huge_gain = TempComp {
+            gain_ppm_per_c: TEMP_COMP_GAIN_PPM_PER_C_MAX + 1,
+            ..TempComp::NONE
+        };
+        assert!(!huge_gain.is_valid());
+    }
+
+    #[test]
+    fn temp_comp_correct_round_trips() {
+        // +1000 ppm/°C and +100 µA/°C referenced to 25 °C.
+        let tc = TempComp {
+            sensor: TempSensor::SinkCore,
+            t_ref_mc: 25_000,
+            gain_ppm_per_c: 1_000,
+            offset_micro_per_c: 100,
+        };
+        assert_eq!(tc.correct(5_000, 25_000), 5_000);
+        // At 35 °C: reading = true * 1.01 + 1 mA -> true = (5051 - 1) / 1.01 = 5000.
+        assert_eq!(tc.correct(5_051, 35_000), 5_000);
+        assert_eq!(tc.uncorrect(5_000, 35_000), 5_051);
+
+        // 20 °C above t_ref with -50 µV/°C: reading is 1 mV low.
+        let tc = TempComp {
+            sensor: TempSensor::Mcu,
+            t_ref_mc: 25_000,
+            gain_ppm_per_c: 0,
+            offset_micro_per_c: -50,
+        };
+        assert_eq!(tc.correct(12_000, 45_000), 12_001);
+        assert_eq!(tc.uncorrect(12_001, 45_000), 12_000);
+
+        assert_eq!(TempComp::NONE.correct(1_234, 80_000), 1_234);
+        assert_eq!(TempComp::NONE.uncorrect(1_234, 80_000), 1_234);
+    }
+
     #[test]
     fn meas_monotonic_validation() {
         let ok = [p(10, 0, 100), p(20, 0, 200), p(30, 0, 300)];
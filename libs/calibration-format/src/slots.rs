@@ -0,0 +1,204 @@
+//! Named calibration profile slots.
+//!
+//! Each slot holds one full profile blob (`EEPROM_PROFILE_LEN`). Slot 0 is
+//! the legacy single-profile location at `EEPROM_PROFILE_BASE_ADDR`, so an
+//! EEPROM written by older firmware reads back as "slot 0 active" once the
+//! (missing) directory falls back to [`SlotDirectory::legacy`].
+//!
+//! Directory layout (`EEPROM_SLOT_DIR_LEN` bytes, little-endian):
+//! - 0..4: magic `LLCS`
+//! - 4: directory version (1)
+//! - 5: active slot index
+//! - 6..8: reserved
+//! - 8..: `CAL_SLOT_COUNT` entries of `SLOT_ENTRY_LEN` bytes:
+//!   name (16 B, ASCII, zero-padded), flags u8 (bit0 = used), 3 reserved,
+//!   CRC32 of the slot's profile blob (u32)
+//! - last 4 bytes: CRC32 over everything before it
+
+use heapless::String;
+
+use crate::{EEPROM_PROFILE_BASE_ADDR, EEPROM_PROFILE_LEN, crc32_ieee};
+
+pub const CAL_SLOT_COUNT: usize = 4;
+pub const CAL_SLOT_NAME_MAX: usize = 16;
+
+// Directory sits after the protection blob (ends at 0x0620); extra slots use
+// the upper half of the M24C64, leaving 0x0780..0x1000 and 0x1C00.. free.
+pub const EEPROM_SLOT_DIR_ADDR: u16 = 0x0700;
+pub const EEPROM_SLOT_DIR_LEN: usize = 128;
+pub const EEPROM_SLOT_ADDRS: [u16; CAL_SLOT_COUNT] =
+    [EEPROM_PROFILE_BASE_ADDR, 0x1000, 0x1400, 0x1800];
+
+const SLOT_DIR_MAGIC: &[u8; 4] = b"LLCS";
+const SLOT_DIR_VERSION: u8 = 1;
+const SLOT_DIR_HEADER_LEN: usize = 8;
+const SLOT_ENTRY_LEN: usize = 24;
+const SLOT_FLAG_USED: u8 = 0x01;
+const SLOT_DIR_CRC_OFFSET: usize = EEPROM_SLOT_DIR_LEN - 4;
+
+const _: () = assert!(SLOT_DIR_HEADER_LEN + CAL_SLOT_COUNT * SLOT_ENTRY_LEN <= SLOT_DIR_CRC_OFFSET);
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SlotEntry {
+    pub name: String<CAL_SLOT_NAME_MAX>,
+    /// A profile was committed to this slot.
+    pub used: bool,
+    /// CRC32 of the slot's full profile blob when `used`.
+    pub crc32: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SlotDirectory {
+    pub active: u8,
+    pub slots: [SlotEntry; CAL_SLOT_COUNT],
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SlotNameError {
+    Empty,
+    TooLong,
+    InvalidChar,
+}
+
+/// Slot names are 1..=16 bytes of `[A-Za-z0-9 _.-]`, so they can be embedded
+/// in JSON and on the display without escaping.
+pub fn validate_slot_name(name: &str) -> Result<String<CAL_SLOT_NAME_MAX>, SlotNameError> {
+    if name.is_empty() {
+        return Err(SlotNameError::Empty);
+    }
+    if !name
+        .bytes()
+        .all(|b| b.is_ascii_alphanumeric() || matches!(b, b' ' | b'_' | b'.' | b'-'))
+    {
+        return Err(SlotNameError::InvalidChar);
+    }
+    let mut out = String::new();
+    out.push_str(name).map_err(|_| SlotNameError::TooLong)?;
+    Ok(out)
+}
+
+pub fn profile_blob_crc32(blob: &[u8; EEPROM_PROFILE_LEN]) -> u32 {
+    crc32_ieee(blob)
+}
+
+impl SlotDirectory {
+    /// Directory assumed for EEPROMs without one: slot 0 active and named
+    /// `default`, holding whatever the legacy profile location contains.
+    pub fn legacy(slot0_crc32: Option<u32>) -> Self {
+        let mut slots: [SlotEntry; CAL_SLOT_COUNT] = Default::default();
+        slots[0].name = validate_slot_name("default").unwrap_or_default();
+        if let Some(crc) = slot0_crc32 {
+            slots[0].used = true;
+            slots[0].crc32 = crc;
+        }
+        Self { active: 0, slots }
+    }
+
+    pub fn active_slot(&self) -> usize {
+        self.active as usize
+    }
+
+    pub fn encode(&self) -> [u8; EEPROM_SLOT_DIR_LEN] {
+        let mut out = [0u8; EEPROM_SLOT_DIR_LEN];
+        out[..4].copy_from_slice(SLOT_DIR_MAGIC);
+        out[4] = SLOT_DIR_VERSION;
+        out[5] = self.active;
+        for (idx, entry) in self.slots.iter().enumerate() {
+            let base = SLOT_DIR_HEADER_LEN + idx * SLOT_ENTRY_LEN;
+            let name = entry.name.as_bytes();
+            out[base..base + name.len()].copy_from_slice(name);
+            out[base + 16] = if entry.used { SLOT_FLAG_USED } else { 0 };
+            out[base + 20..base + 24].copy_from_slice(&entry.crc32.to_le_bytes());
+        }
+        let crc = crc32_ieee(&out[..SLOT_DIR_CRC_OFFSET]);
+        out[SLOT_DIR_CRC_OFFSET..].copy_from_slice(&crc.to_le_bytes());
+        out
+    }
+
+    /// `None` for a blank, corrupted or unknown-version directory.
+    pub fn decode(bytes: &[u8; EEPROM_SLOT_DIR_LEN]) -> Option<Self> {
+        if &bytes[..4] != SLOT_DIR_MAGIC || bytes[4] != SLOT_DIR_VERSION {
+            return None;
+        }
+        let stored = u32::from_le_bytes(bytes[SLOT_DIR_CRC_OFFSET..].try_into().ok()?);
+        if crc32_ieee(&bytes[..SLOT_DIR_CRC_OFFSET]) != stored {
+            return None;
+        }
+        let active = bytes[5];
+        if active as usize >= CAL_SLOT_COUNT {
+            return None;
+        }
+        let mut slots: [SlotEntry; CAL_SLOT_COUNT] = Default::default();
+        for (idx, entry) in slots.iter_mut().enumerate() {
+            let base = SLOT_DIR_HEADER_LEN + idx * SLOT_ENTRY_LEN;
+            let raw_name = &bytes[base..base + CAL_SLOT_NAME_MAX];
+            let len = raw_name
+                .iter()
+                .position(|b| *b == 0)
+                .unwrap_or(CAL_SLOT_NAME_MAX);
+            if len > 0 {
+                let name = core::str::from_utf8(&raw_name[..len]).ok()?;
+                entry.name = validate_slot_name(name).ok()?;
+            }
+            entry.used = bytes[base + 16] & SLOT_FLAG_USED != 0;
+            entry.crc32 = u32::from_le_bytes(bytes[base + 20..base + 24].try_into().ok()?);
+        }
+        Some(Self { active, slots })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn directory_roundtrips_and_rejects_corruption() {
+        let mut dir = SlotDirectory::legacy(Some(0x1234_5678));
+        dir.active = 2;
+        dir.slots[2].name = validate_slot_name("shunt-50A").unwrap();
+        dir.slots[2].used = true;
+        dir.slots[2].crc32 = 0xDEAD_BEEF;
+
+        let bytes = dir.encode();
+        assert_eq!(SlotDirectory::decode(&bytes), Some(dir.clone()));
+
+        let mut bad = bytes;
+        bad[SLOT_DIR_HEADER_LEN + 2 * SLOT_ENTRY_LEN] ^= 0x01;
+        assert_eq!(SlotDirectory::decode(&bad), None);
+        assert_eq!(SlotDirectory::decode(&[0xFF; EEPROM_SLOT_DIR_LEN]), None);
+    }
+
+    #[test]
+    fn legacy_directory_uses_slot_zero() {
+        let dir = SlotDirectory::legacy(None);
+        assert_eq!(dir.active_slot(), 0);
+        assert_eq!(dir.slots[0].name.as_str(), "default");
+        assert!(!dir.slots[0].used);
+        assert!(dir.slots[1..].iter().all(|s| s.name.is_empty() && !s.used));
+    }
+
+    #[test]
+    fn slot_names_are_restricted() {
+        assert!(validate_slot_name("front end B").is_ok());
+        assert_eq!(validate_slot_name(""), Err(SlotNameError::Empty));
+        assert_eq!(
+            validate_slot_name("0123456789abcdefg"),
+            Err(SlotNameError::TooLong)
+        );
+        assert_eq!(validate_slot_name("a\"b"), Err(SlotNameError::InvalidChar));
+    }
+
+    #[test]
+    fn slot_regions_do_not_overlap() {
+        for (idx, addr) in EEPROM_SLOT_ADDRS.iter().enumerate() {
+            let end = *addr as usize + EEPROM_PROFILE_LEN;
+            assert!(end <= 8192);
+            let dir =
+                EEPROM_SLOT_DIR_ADDR as usize..EEPROM_SLOT_DIR_ADDR as usize + EEPROM_SLOT_DIR_LEN;
+            assert!(end <= dir.start || *addr as usize >= dir.end);
+            for other in &EEPROM_SLOT_ADDRS[idx + 1..] {
+                assert!(end <= *other as usize);
+            }
+        }
+    }
+}
@@ -0,0 +1,256 @@
+//! Calibration verification ("cal check").
+//!
+//! A check point pairs a raw reading captured with the reference meter
+//! attached (same inputs as calibration) with the meter value. The active
+//! profile's output for that raw reading is compared against the reference;
+//! nothing in the profile is changed.
+//!
+//! The latest result per curve is kept in EEPROM, one
+//! `EEPROM_VERIFY_RECORD_LEN` record per [`CurveKind`] starting at
+//! `EEPROM_VERIFY_BASE_ADDR` (little-endian):
+//! - 0..4: magic `LLCV`
+//! - 4: record version (1)
+//! - 5: curve kind, 6: slot index, 7: point count
+//! - 8..16: host timestamp, Unix ms (0 = unknown)
+//! - 16..20: CRC32 of the verified slot's profile blob (0 = factory default)
+//! - 20..24: reserved
+//! - 24..: points of `VERIFY_POINT_LEN` bytes: raw_100uv i16, 2 reserved,
+//!   reference i32, reading i32, tolerance i32
+//! - last 4 bytes: CRC32 over everything before it
+
+use heapless::Vec;
+
+use crate::{ActiveProfile, CurveKind, TempSensor, analysis, crc32_ieee};
+
+pub const MAX_VERIFY_POINTS: usize = 12;
+
+// Sits after the slot directory (0x0700..0x0780).
+pub const EEPROM_VERIFY_BASE_ADDR: u16 = 0x0780;
+pub const EEPROM_VERIFY_RECORD_LEN: usize = 256;
+pub const EEPROM_VERIFY_LEN: usize = EEPROM_VERIFY_RECORD_LEN * 4;
+
+/// Default acceptance band: `abs + |reference| * ppm / 1e6`, in the curve's
+/// unit (mA or mV).
+pub const DEFAULT_TOLERANCE_ABS: i32 = 5;
+pub const DEFAULT_TOLERANCE_PPM: i32 = 2_000;
+
+const VERIFY_MAGIC: &[u8; 4] = b"LLCV";
+const VERIFY_VERSION: u8 = 1;
+const VERIFY_HEADER_LEN: usize = 24;
+const VERIFY_POINT_LEN: usize = 16;
+const VERIFY_CRC_OFFSET: usize = EEPROM_VERIFY_RECORD_LEN - 4;
+
+const _: () =
+    assert!(VERIFY_HEADER_LEN + MAX_VERIFY_POINTS * VERIFY_POINT_LEN <= VERIFY_CRC_OFFSET);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VerifyPoint {
+    pub raw_100uv: i16,
+    /// Reference meter value (mA or mV).
+    pub reference: i32,
+    /// Active profile output for `raw_100uv`.
+    pub reading: i32,
+    pub tolerance: i32,
+}
+
+impl VerifyPoint {
+    pub fn error(&self) -> i32 {
+        self.reading.saturating_sub(self.reference)
+    }
+
+    pub fn passed(&self) -> bool {
+        self.error().unsigned_abs() <= self.tolerance.max(0) as u32
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VerifyRecord {
+    pub kind: CurveKind,
+    pub ts_unix_ms: u64,
+    pub slot: u8,
+    pub profile_crc32: u32,
+    pub points: Vec<VerifyPoint, MAX_VERIFY_POINTS>,
+}
+
+pub fn tolerance_for(reference: i32, abs: i32, ppm: i32) -> i32 {
+    let rel = reference.unsigned_abs() as i64 * ppm.max(0) as i64 / 1_000_000;
+    (abs.max(0) as i64 + rel).min(i32::MAX as i64) as i32
+}
+
+/// Output of the active profile for `raw`, including temperature
+/// compensation at the given sensor temperatures.
+pub fn profile_reading(
+    profile: &ActiveProfile,
+    kind: CurveKind,
+    raw: i16,
+    sink_core_mc: i32,
+    mcu_mc: i32,
+) -> Option<i32> {
+    let reading = analysis::evaluate(profile.points_for(kind), raw)?;
+    let tc = profile.temp_comp_for(kind);
+    let temp_mc = match tc.sensor {
+        TempSensor::SinkCore => sink_core_mc,
+        TempSensor::Mcu => mcu_mc,
+    };
+    Some(tc.correct(reading, temp_mc))
+}
+
+impl VerifyRecord {
+    pub fn passed(&self) -> bool {
+        !self.points.is_empty() && self.points.iter().all(VerifyPoint::passed)
+    }
+
+    /// Error with the largest magnitude.
+    pub fn worst_error(&self) -> Option<i32> {
+        self.points
+            .iter()
+            .map(VerifyPoint::error)
+            .max_by_key(|err| err.unsigned_abs())
+    }
+
+    pub fn encode(&self) -> [u8; EEPROM_VERIFY_RECORD_LEN] {
+        let mut out = [0u8; EEPROM_VERIFY_RECORD_LEN];
+        out[..4].copy_from_slice(VERIFY_MAGIC);
+        out[4] = VERIFY_VERSION;
+        out[5] = self.kind.as_u8();
+        out[6] = self.slot;
+        out[7] = self.points.len() as u8;
+        out[8..16].copy_from_slice(&self.ts_unix_ms.to_le_bytes());
+        out[16..20].copy_from_slice(&self.profile_crc32.to_le_bytes());
+        for (idx, p) in self.points.iter().enumerate() {
+            let base = VERIFY_HEADER_LEN + idx * VERIFY_POINT_LEN;
+            out[base..base + 2].copy_from_slice(&p.raw_100uv.to_le_bytes());
+            out[base + 4..base + 8].copy_from_slice(&p.reference.to_le_bytes());
+            out[base + 8..base + 12].copy_from_slice(&p.reading.to_le_bytes());
+            out[base + 12..base + 16].copy_from_slice(&p.tolerance.to_le_bytes());
+        }
+        let crc = crc32_ieee(&out[..VERIFY_CRC_OFFSET]);
+        out[VERIFY_CRC_OFFSET..].copy_from_slice(&crc.to_le_bytes());
+        out
+    }
+
+    /// `None` for a blank, corrupted or unknown-version record.
+    pub fn decode(bytes: &[u8; EEPROM_VERIFY_RECORD_LEN]) -> Option<Self> {
+        if &bytes[..4] != VERIFY_MAGIC || bytes[4] != VERIFY_VERSION {
+            return None;
+        }
+        let word = |off: usize| {
+            u32::from_le_bytes([bytes[off], bytes[off + 1], bytes[off + 2], bytes[off + 3]])
+        };
+        if crc32_ieee(&bytes[..VERIFY_CRC_OFFSET]) != word(VERIFY_CRC_OFFSET) {
+            return None;
+        }
+        let kind = CurveKind::from_u8(bytes[5])?;
+        let count = bytes[7] as usize;
+        if count > MAX_VERIFY_POINTS {
+            return None;
+        }
+        let mut points = Vec::new();
+        for idx in 0..count {
+            let base = VERIFY_HEADER_LEN + idx * VERIFY_POINT_LEN;
+            let _ = points.push(VerifyPoint {
+                raw_100uv: i16::from_le_bytes([bytes[base], bytes[base + 1]]),
+                reference: word(base + 4) as i32,
+                reading: word(base + 8) as i32,
+                tolerance: word(base + 12) as i32,
+            });
+        }
+        Some(Self {
+            kind,
+            ts_unix_ms: u64::from_le_bytes(bytes[8..16].try_into().ok()?),
+            slot: bytes[6],
+            profile_crc32: word(16),
+            points,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DIGITAL_HW_REV, TempComp};
+
+    #[test]
+    fn profile_reading_follows_curve_and_temp_comp() {
+        let mut profile = ActiveProfile::factory_default(DIGITAL_HW_REV);
+        // Factory current curve: 25000 raw -> 5000 mA.
+        assert_eq!(
+            profile_reading(&profile, CurveKind::CurrentCh1, 12_500, 60_000, 40_000),
+            Some(2_500)
+        );
+
+        *profile.temp_comp_for_mut(CurveKind::CurrentCh1) = TempComp {
+            gain_ppm_per_c: 1_000,
+            ..TempComp::NONE
+        };
+        // +10 °C at 1000 ppm/°C reads 1 % high; the correction divides it out.
+        assert_eq!(
+            profile_reading(&profile, CurveKind::CurrentCh1, 12_500, 35_000, 0),
+            Some(2_475)
+        );
+    }
+
+    #[test]
+    fn points_pass_within_tolerance() {
+        let tol = tolerance_for(-10_000, DEFAULT_TOLERANCE_ABS, DEFAULT_TOLERANCE_PPM);
+        assert_eq!(tol, 25);
+        let point = VerifyPoint {
+            raw_100uv: 0,
+            reference: 10_000,
+            reading: 10_025,
+            tolerance: tol,
+        };
+        assert!(point.passed());
+        assert!(
+            !VerifyPoint {
+                reading: 9_974,
+                ..point
+            }
+            .passed()
+        );
+    }
+
+    #[test]
+    fn record_roundtrips_and_rejects_corruption() {
+        let mut points = Vec::new();
+        for (raw, reference, reading) in [(100, 1_000, 1_003), (2_000, 20_000, 19_950)] {
+            points
+                .push(VerifyPoint {
+                    raw_100uv: raw,
+                    reference,
+                    reading,
+                    tolerance: 45,
+                })
+                .unwrap();
+        }
+        let record = VerifyRecord {
+            kind: CurveKind::VRemote,
+            ts_unix_ms: 1_760_000_000_000,
+            slot: 2,
+            profile_crc32: 0xCAFE_F00D,
+            points,
+        };
+        assert!(!record.passed());
+        assert_eq!(record.worst_error(), Some(-50));
+
+        let bytes = record.encode();
+        assert_eq!(VerifyRecord::decode(&bytes), Some(record));
+
+        let mut bad = bytes;
+        bad[VERIFY_HEADER_LEN + 4] ^= 0x01;
+        assert_eq!(VerifyRecord::decode(&bad), None);
+        assert_eq!(
+            VerifyRecord::decode(&[0xFF; EEPROM_VERIFY_RECORD_LEN]),
+            None
+        );
+    }
+
+    #[test]
+    fn verify_region_fits_before_extra_slots() {
+        let end = EEPROM_VERIFY_BASE_ADDR as usize + EEPROM_VERIFY_LEN;
+        let dir_end =
+            crate::slots::EEPROM_SLOT_DIR_ADDR as usize + crate::slots::EEPROM_SLOT_DIR_LEN;
+        assert!(EEPROM_VERIFY_BASE_ADDR as usize >= dir_end);
+        assert!(end <= crate::slots::EEPROM_SLOT_ADDRS[1] as usize);
+    }
+}
@@ -0,0 +1,517 @@
+//! Minimal MQTT 3.1.1 packet codec for the digital firmware's telemetry publisher.
+
+#![no_std]
+
+#[cfg(test)]
+extern crate std;
+
+/// MQTT 3.1.1 protocol level (§3.1.2.2).
+const PROTOCOL_LEVEL: u8 = 4;
+/// Largest "Remaining Length" representable in four bytes (§2.2.3).
+const MAX_REMAINING_LEN: usize = 268_435_455;
+
+const PACKET_CONNECT: u8 = 1;
+const PACKET_CONNACK: u8 = 2;
+const PACKET_PUBLISH: u8 = 3;
+const PACKET_PUBACK: u8 = 4;
+const PACKET_SUBSCRIBE: u8 = 8;
+const PACKET_SUBACK: u8 = 9;
+const PACKET_PINGREQ: u8 = 12;
+const PACKET_PINGRESP: u8 = 13;
+const PACKET_DISCONNECT: u8 = 14;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The output buffer cannot hold the packet.
+    BufferTooSmall,
+    /// A string or payload exceeds the protocol's length fields.
+    TooLong,
+    /// The received bytes are not a well-formed packet.
+    Malformed,
+}
+
+/// Last-will message published by the broker when the connection drops.
+#[derive(Clone, Copy, Debug)]
+pub struct Will<'a> {
+    pub topic: &'a str,
+    pub payload: &'a [u8],
+    pub retain: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Connect<'a> {
+    pub client_id: &'a str,
+    pub keep_alive_s: u16,
+    pub username: Option<&'a str>,
+    pub password: Option<&'a str>,
+    pub will: Option<Will<'a>>,
+}
+
+/// Packets the client expects from a broker.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Packet<'a> {
+    /// `return_code` 0 means accepted (§3.2.2.3).
+    ConnAck {
+        session_present: bool,
+        return_code: u8,
+    },
+    /// `packet_id` is present for QoS 1/2 deliveries and must be acknowledged.
+    Publish {
+        topic: &'a str,
+        payload: &'a [u8],
+        qos: u8,
+        retain: bool,
+        packet_id: Option<u16>,
+    },
+    PubAck {
+        packet_id: u16,
+    },
+    /// `granted` is the first return code (0x80 = failure).
+    SubAck {
+        packet_id: u16,
+        granted: u8,
+    },
+    PingResp,
+    /// Any other well-formed packet, identified by its type nibble.
+    Other(u8),
+}
+
+/// Human-readable CONNACK return code.
+pub const fn connack_reason(return_code: u8) -> &'static str {
+    match return_code {
+        0 => "accepted",
+        1 => "unacceptable protocol version",
+        2 => "identifier rejected",
+        3 => "server unavailable",
+        4 => "bad user name or password",
+        5 => "not authorized",
+        _ => "unknown return code",
+    }
+}
+
+struct Writer<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> Writer<'a> {
+    fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf, len: 0 }
+    }
+
+    fn byte(&mut self, value: u8) -> Result<(), Error> {
+        let slot = self.buf.get_mut(self.len).ok_or(Error::BufferTooSmall)?;
+        *slot = value;
+        self.len += 1;
+        Ok(())
+    }
+
+    fn bytes(&mut self, data: &[u8]) -> Result<(), Error> {
+        let end = self.len + data.len();
+        self.buf
+            .get_mut(self.len..end)
+            .ok_or(Error::BufferTooSmall)?
+            .copy_from_slice(data);
+        self.len = end;
+        Ok(())
+    }
+
+    fn u16(&mut self, value: u16) -> Result<(), Error> {
+        self.bytes(&value.to_be_bytes())
+    }
+
+    /// Length-prefixed binary data / UTF-8 string (§1.5.3).
+    fn prefixed(&mut self, data: &[u8]) -> Result<(), Error> {
+        let len = u16::try_from(data.len()).map_err(|_| Error::TooLong)?;
+        self.u16(len)?;
+        self.bytes(data)
+    }
+
+    fn fixed_header(&mut self, first: u8, remaining: usize) -> Result<(), Error> {
+        if remaining > MAX_REMAINING_LEN {
+            return Err(Error::TooLong);
+        }
+        self.byte(first)?;
+        let mut value = remaining;
+        loop {
+            let mut digit = (value % 128) as u8;
+            value /= 128;
+            if value > 0 {
+                digit |= 0x80;
+            }
+            self.byte(digit)?;
+            if value == 0 {
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn prefixed_len(data: &[u8]) -> usize {
+    2 + data.len()
+}
+
+pub fn encode_connect(out: &mut [u8], connect: &Connect<'_>) -> Result<usize, Error> {
+    // A password without a user name is not allowed in 3.1.1 (§3.1.2.9).
+    let password = connect.username.and(connect.password);
+    let mut flags = 0x02; // clean session
+    let mut remaining = 10 + prefixed_len(connect.client_id.as_bytes());
+    if let Some(will) = connect.will {
+        flags |= 0x04;
+        if will.retain {
+            flags |= 0x20;
+        }
+        remaining += prefixed_len(will.topic.as_bytes()) + prefixed_len(will.payload);
+    }
+    if let Some(username) = connect.username {
+        flags |= 0x80;
+        remaining += prefixed_len(username.as_bytes());
+    }
+    if let Some(password) = password {
+        flags |= 0x40;
+        remaining += prefixed_len(password.as_bytes());
+    }
+
+    let mut w = Writer::new(out);
+    w.fixed_header(PACKET_CONNECT << 4, remaining)?;
+    w.prefixed(b"MQTT")?;
+    w.byte(PROTOCOL_LEVEL)?;
+    w.byte(flags)?;
+    w.u16(connect.keep_alive_s)?;
+    w.prefixed(connect.client_id.as_bytes())?;
+    if let Some(will) = connect.will {
+        w.prefixed(will.topic.as_bytes())?;
+        w.prefixed(will.payload)?;
+    }
+    if let Some(username) = connect.username {
+        w.prefixed(username.as_bytes())?;
+    }
+    if let Some(password) = password {
+        w.prefixed(password.as_bytes())?;
+    }
+    Ok(w.len)
+}
+
+/// QoS 0 PUBLISH.
+pub fn encode_publish(
+    out: &mut [u8],
+    topic: &str,
+    payload: &[u8],
+    retain: bool,
+) -> Result<usize, Error> {
+    let remaining = prefixed_len(topic.as_bytes()) + payload.len();
+    let mut w = Writer::new(out);
+    w.fixed_header(PACKET_PUBLISH << 4 | retain as u8, remaining)?;
+    w.prefixed(topic.as_bytes())?;
+    w.bytes(payload)?;
+    Ok(w.len)
+}
+
+/// SUBSCRIBE to one filter at the given maximum QoS.
+pub fn encode_subscribe(
+    out: &mut [u8],
+    packet_id: u16,
+    filter: &str,
+    qos: u8,
+) -> Result<usize, Error> {
+    let remaining = 2 + prefixed_len(filter.as_bytes()) + 1;
+    let mut w = Writer::new(out);
+    w.fixed_header(PACKET_SUBSCRIBE << 4 | 0x02, remaining)?;
+    w.u16(packet_id)?;
+    w.prefixed(filter.as_bytes())?;
+    w.byte(qos.min(2))?;
+    Ok(w.len)
+}
+
+pub fn encode_puback(out: &mut [u8], packet_id: u16) -> Result<usize, Error> {
+    let mut w = Writer::new(out);
+    w.fixed_header(PACKET_PUBACK << 4, 2)?;
+    w.u16(packet_id)?;
+    Ok(w.len)
+}
+
+pub fn encode_pingreq(out: &mut [u8]) -> Result<usize, Error> {
+    let mut w = Writer::new(out);
+    w.fixed_header(PACKET_PINGREQ << 4, 0)?;
+    Ok(w.len)
+}
+
+pub fn encode_disconnect(out: &mut [u8]) -> Result<usize, Error> {
+    let mut w = Writer::new(out);
+    w.fixed_header(PACKET_DISCONNECT << 4, 0)?;
+    Ok(w.len)
+}
+
+/// Decode the fixed header: `(first byte, remaining length, header length)`,
+/// or `None` if more bytes are needed.
+fn decode_fixed_header(buf: &[u8]) -> Result<Option<(u8, usize, usize)>, Error> {
+    let Some(&first) = buf.first() else {
+        return Ok(None);
+    };
+    let mut remaining = 0usize;
+    let mut multiplier = 1usize;
+    for idx in 1..=4 {
+        let Some(&digit) = buf.get(idx) else {
+            return Ok(None);
+        };
+        remaining += (digit & 0x7F) as usize * multiplier;
+        if digit & 0x80 == 0 {
+            return Ok(Some((first, remaining, idx + 1)));
+        }
+        multiplier *= 128;
+    }
+    Err(Error::Malformed)
+}
+
+/// Total size of the first packet in `buf` once its fixed header is complete.
+/// Lets the caller drop packets that do not fit its receive buffer.
+pub fn packet_len(buf: &[u8]) -> Result<Option<usize>, Error> {
+    Ok(decode_fixed_header(buf)?.map(|(_, remaining, header)| header + remaining))
+}
+
+/// Decode the first packet in `buf`. Returns the packet and the number of
+/// bytes it occupied, or `Ok(None)` when the packet is still incomplete.
+pub fn decode(buf: &[u8]) -> Result<Option<(Packet<'_>, usize)>, Error> {
+    let Some((first, remaining, header)) = decode_fixed_header(buf)? else {
+        return Ok(None);
+    };
+    let total = header + remaining;
+    if buf.len() < total {
+        return Ok(None);
+    }
+    let body = &buf[header..total];
+    let packet = match first >> 4 {
+        PACKET_CONNACK => match body {
+            [ack_flags, return_code] => Packet::ConnAck {
+                session_present: ack_flags & 0x01 != 0,
+                return_code: *return_code,
+            },
+            _ => return Err(Error::Malformed),
+        },
+        PACKET_PUBLISH => {
+            let qos = (first >> 1) & 0x03;
+            if qos == 3 {
+                return Err(Error::Malformed);
+            }
+            let (topic, mut rest) = split_prefixed(body)?;
+            let topic = core::str::from_utf8(topic).map_err(|_| Error::Malformed)?;
+            let packet_id = if qos > 0 {
+                let (id, tail) = split_u16(rest)?;
+                rest = tail;
+                Some(id)
+            } else {
+                None
+            };
+            Packet::Publish {
+                topic,
+                payload: rest,
+                qos,
+                retain: first & 0x01 != 0,
+                packet_id,
+            }
+        }
+        PACKET_PUBACK => Packet::PubAck {
+            packet_id: split_u16(body)?.0,
+        },
+        PACKET_SUBACK => {
+            let (packet_id, codes) = split_u16(body)?;
+            Packet::SubAck {
+                packet_id,
+                granted: *codes.first().ok_or(Error::Malformed)?,
+            }
+        }
+        PACKET_PINGRESP => Packet::PingResp,
+        other => Packet::Other(other),
+    };
+    Ok(Some((packet, total)))
+}
+
+fn split_u16(buf: &[u8]) -> Result<(u16, &[u8]), Error> {
+    match buf {
+        [hi, lo, rest @ ..] => Ok((u16::from_be_bytes([*hi, *lo]), rest)),
+        _ => Err(Error::Malformed),
+    }
+}
+
+fn split_prefixed(buf: &[u8]) -> Result<(&[u8], &[u8]), Error> {
+    let (len, rest) = split_u16(buf)?;
+    let len = len as usize;
+    if rest.len() < len {
+        return Err(Error::Malformed);
+    }
+    Ok(rest.split_at(len))
+}
+
+/// Whether `topic` matches the subscription `filter` (`+` = one level,
+/// trailing `#` = any number of levels, including none).
+pub fn topic_matches(filter: &str, topic: &str) -> bool {
+    let mut filter_levels = filter.split('/');
+    let mut topic_levels = topic.split('/');
+    loop {
+        match (filter_levels.next(), topic_levels.next()) {
+            (Some("#"), _) => return filter_levels.next().is_none(),
+            (Some("+"), Some(_)) => {}
+            (Some(f), Some(t)) if f == t => {}
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+/// Whether `topic` is usable as a publish topic or prefix: non-empty, no
+/// wildcards, no NUL (§4.7.3).
+pub fn is_valid_topic(topic: &str) -> bool {
+    !topic.is_empty() && topic.len() <= u16::MAX as usize && !topic.contains(['+', '#', '\0'])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connect_encodes_credentials_and_will() {
+        let mut buf = [0u8; 128];
+        let len = encode_connect(
+            &mut buf,
+            &Connect {
+                client_id: "ll",
+                keep_alive_s: 30,
+                username: Some("u"),
+                password: Some("p"),
+                will: Some(Will {
+                    topic: "t",
+                    payload: b"off",
+                    retain: true,
+                }),
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            &buf[..len],
+            &[
+                0x10, 28, 0, 4, b'M', b'Q', b'T', b'T', 4, 0xE6, 0, 30, 0, 2, b'l', b'l', 0, 1,
+                b't', 0, 3, b'o', b'f', b'f', 0, 1, b'u', 0, 1, b'p',
+            ]
+        );
+    }
+
+    #[test]
+    fn connect_drops_password_without_username() {
+        let mut buf = [0u8; 64];
+        let len = encode_connect(
+            &mut buf,
+            &Connect {
+                client_id: "c",
+                keep_alive_s: 60,
+                username: None,
+                password: Some("secret"),
+                will: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(buf[9], 0x02);
+        assert_eq!(len, 2 + 10 + 3);
+    }
+
+    #[test]
+    fn publish_round_trips_with_multibyte_length() {
+        let payload = [b'x'; 200];
+        let mut buf = [0u8; 256];
+        let len = encode_publish(&mut buf, "a/b", &payload, true).unwrap();
+        assert_eq!(&buf[..3], &[0x31, 205, 1]);
+        assert_eq!(packet_len(&buf[..2]).unwrap(), None);
+        assert_eq!(packet_len(&buf[..3]).unwrap(), Some(len));
+        let (packet, used) = decode(&buf[..len]).unwrap().unwrap();
+        assert_eq!(used, len);
+        assert_eq!(
+            packet,
+            Packet::Publish {
+                topic: "a/b",
+                payload: &payload,
+                qos: 0,
+                retain: true,
+                packet_id: None,
+            }
+        );
+        assert_eq!(decode(&buf[..len - 1]).unwrap(), None);
+    }
+
+    #[test]
+    fn decodes_broker_packets() {
+        assert_eq!(
+            decode(&[0x20, 2, 0, 5]).unwrap(),
+            Some((
+                Packet::ConnAck {
+                    session_present: false,
+                    return_code: 5,
+                },
+                4
+            ))
+        );
+        assert_eq!(
+            decode(&[0x90, 3, 0, 7, 0]).unwrap(),
+            Some((
+                Packet::SubAck {
+                    packet_id: 7,
+                    granted: 0,
+                },
+                5
+            ))
+        );
+        assert_eq!(decode(&[0xD0, 0]).unwrap(), Some((Packet::PingResp, 2)));
+        let qos1 = [0x32, 8, 0, 3, b'c', b'/', b'x', 0, 9, b'1'];
+        assert_eq!(
+            decode(&qos1).unwrap(),
+            Some((
+                Packet::Publish {
+                    topic: "c/x",
+                    payload: b"1",
+                    qos: 1,
+                    retain: false,
+                    packet_id: Some(9),
+                },
+                10
+            ))
+        );
+        assert_eq!(decode(&[0x20, 1, 0]), Err(Error::Malformed));
+        assert_eq!(
+            decode(&[0x30, 0xFF, 0xFF, 0xFF, 0xFF]),
+            Err(Error::Malformed)
+        );
+    }
+
+    #[test]
+    fn small_packets_encode() {
+        let mut buf = [0u8; 32];
+        let len = encode_subscribe(&mut buf, 1, "d/cmd/+", 0).unwrap();
+        assert_eq!(
+            &buf[..len],
+            &[
+                0x82, 12, 0, 1, 0, 7, b'd', b'/', b'c', b'm', b'd', b'/', b'+', 0
+            ]
+        );
+        assert_eq!(encode_puback(&mut buf, 9).unwrap(), 4);
+        assert_eq!(&buf[..4], &[0x40, 2, 0, 9]);
+        assert_eq!(encode_pingreq(&mut buf).unwrap(), 2);
+        assert_eq!(&buf[..2], &[0xC0, 0]);
+        assert_eq!(encode_disconnect(&mut buf).unwrap(), 2);
+        assert_eq!(&buf[..2], &[0xE0, 0]);
+        assert_eq!(
+            encode_publish(&mut buf[..4], "topic", b"", false),
+            Err(Error::BufferTooSmall)
+        );
+    }
+
+    #[test]
+    fn topic_filters_follow_wildcard_rules() {
+        assert!(topic_matches("a/cmd/+", "a/cmd/cc"));
+        assert!(!topic_matches("a/cmd/+", "a/cmd/preset/apply"));
+        assert!(topic_matches("a/#", "a"));
+        assert!(topic_matches("a/#", "a/b/c"));
+        assert!(!topic_matches("a/b", "a/b/c"));
+        assert!(topic_matches("+/+", "/x"));
+        assert!(is_valid_topic("loadlynx/lab-1"));
+        assert!(!is_valid_topic("loadlynx/#"));
+        assert!(!is_valid_topic(""));
+    }
+}
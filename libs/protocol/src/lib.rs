@@ -1,5 +1,7 @@
 #![no_std]
 
+use core::ops::RangeInclusive;
+
 use heapless::Vec;
 use minicbor::decode::Error as CborDecodeError;
 use minicbor::encode::{
@@ -8,6 +10,8 @@ use minicbor::encode::{
 };
 use minicbor::{Decode, Decoder, Encode, Encoder};
 
+pub mod link_safe;
+
 pub const PROTOCOL_VERSION: u8 = 1;
 pub const HEADER_LEN: usize = 6;
 pub const CRC_LEN: usize = 2;
@@ -45,6 +49,27 @@ pub const MSG_CAL_MODE: u8 = 0x25;
 /// analog-side state without power-cycling.
 pub const MSG_SOFT_RESET: u8 = 0x26;
 pub const MSG_PD_SINK_REQUEST: u8 = 0x27;
+/// Runtime protection thresholds: S3 (digital) → G431 (analog), ACK required.
+///
+/// The analog side validates the payload with [`ProtectionConfig::validate`];
+/// out-of-range configs are NACKed and the previous thresholds stay active.
+pub const MSG_PROTECTION_CONFIG: u8 = 0x28;
+/// Explicit fault clear/acknowledge: S3 (digital) → G431 (analog), ACK required.
+///
+/// Clears the latched faults selected by [`ClearFaults::mask`]; a fault whose
+/// condition is still present re-latches on the next control-loop tick.
+pub const MSG_CLEAR_FAULTS: u8 = 0x29;
+/// Charge/energy counter reset: S3 (digital) → G431 (analog), ACK required.
+///
+/// Zeroes the `charge_uah` / `energy_uwh` / `counter_*_ms` totals reported in
+/// FastStatus; the new totals appear in the next status frame.
+pub const MSG_RESET_COUNTERS: u8 = 0x2A;
+/// Bootloader entry: S3 (digital) → G431 (analog), ACK required.
+///
+/// The analog side ACKs, forces the output off and resets into the STM32
+/// system bootloader (AN3155) on the same UART. Ignored unless
+/// [`EnterBootloader::magic`] equals [`ENTER_BOOTLOADER_MAGIC`].
+pub const MSG_ENTER_BOOTLOADER: u8 = 0x2B;
 /// Calibration write message: S3 (digital) → G431 (analog).
 pub const MSG_CAL_WRITE: u8 = 0x30;
 /// Reserved for future calibration readback support.
@@ -77,16 +102,112 @@ pub const STATE_FLAG_ENABLED: u32 = 1 << 2;
 pub const STATE_FLAG_UV_LATCHED: u32 = 1 << 3;
 pub const STATE_FLAG_POWER_LIMITED: u32 = 1 << 4;
 pub const STATE_FLAG_CURRENT_LIMITED: u32 = 1 << 5;
+/// Output current clamped by the analog-side MOSFET SOA table (V_DS / sink temperature).
+pub const STATE_FLAG_SOA_LIMITED: u32 = 1 << 6;
+/// Output forced off because no valid control frame arrived within
+/// [`ProtectionConfig::link_loss_timeout_ms`] (or the analog side rebooted via
+/// its link watchdog). Stays set until the next output-enable rising edge.
+pub const STATE_FLAG_LINK_SAFED: u32 = 1 << 7;
+
+/// `FastStatus.sense_warnings` bits: sense-path diagnostics from the analog
+/// board. Advisory only; none of them changes the output or latches.
+///
+/// Remote sense reads below zero while the local terminals see the DUT.
+pub const SENSE_WARN_REMOTE_REVERSED: u32 = 1 << 0;
+/// Remote sense dropped out while the DUT is still connected (a lead came off).
+pub const SENSE_WARN_REMOTE_OPEN: u32 = 1 << 1;
+/// Local-vs-remote drop or the estimated cable resistance is too high.
+pub const SENSE_WARN_HIGH_LEAD_DROP: u32 = 1 << 2;
+/// Regulated voltage ripple suggests the loop oscillates through the leads.
+pub const SENSE_WARN_OSCILLATION: u32 = 1 << 3;
+/// All sense warning bits currently defined above.
+pub const SENSE_WARN_ALL: u32 = SENSE_WARN_REMOTE_REVERSED
+    | SENSE_WARN_REMOTE_OPEN
+    | SENSE_WARN_HIGH_LEAD_DROP
+    | SENSE_WARN_OSCILLATION;
+
+/// `FastStatus.channel_warnings` bits: CH1/CH2 current-sharing health.
+/// Advisory only, like the sense warnings.
+///
+/// Both channels carry the same target but their measured currents differ.
+pub const CHANNEL_WARN_IMBALANCE: u32 = 1 << 0;
+/// CH1 conducts although its target is zero (shorted FET, DAC offset).
+pub const CHANNEL_WARN_CH1_STRAY: u32 = 1 << 1;
+/// CH2 conducts although its target is zero (shorted FET, DAC offset).
+pub const CHANNEL_WARN_CH2_STRAY: u32 = 1 << 2;
+/// All channel warning bits currently defined above.
+pub const CHANNEL_WARN_ALL: u32 =
+    CHANNEL_WARN_IMBALANCE | CHANNEL_WARN_CH1_STRAY | CHANNEL_WARN_CH2_STRAY;
+
+/// [`ProtectionConfig::share_policy`] values: how the analog side splits the
+/// total current target between CH1 and CH2.
+///
+/// CH1 only below `i_share_threshold_ma`, both channels from there on
+/// (factory behaviour).
+pub const SHARE_POLICY_AUTO: u8 = 0;
+/// Both channels always share the target equally.
+pub const SHARE_POLICY_DUAL: u8 = 1;
+/// CH1 only, whatever the target (debugging one FET); the per-channel limits
+/// then cap the total current.
+pub const SHARE_POLICY_CH1_ONLY: u8 = 2;
+/// CH2 only, whatever the target (debugging one FET).
+pub const SHARE_POLICY_CH2_ONLY: u8 = 3;
+
+/// Wire/JSON name of a `SHARE_POLICY_*` value.
+pub const fn share_policy_name(policy: u8) -> Option<&'static str> {
+    match policy {
+        SHARE_POLICY_AUTO => Some("auto"),
+        SHARE_POLICY_DUAL => Some("dual"),
+        SHARE_POLICY_CH1_ONLY => Some("ch1_only"),
+        SHARE_POLICY_CH2_ONLY => Some("ch2_only"),
+        _ => None,
+    }
+}
+
+/// Inverse of [`share_policy_name`].
+pub fn share_policy_from_name(name: &str) -> Option<u8> {
+    [
+        SHARE_POLICY_AUTO,
+        SHARE_POLICY_DUAL,
+        SHARE_POLICY_CH1_ONLY,
+        SHARE_POLICY_CH2_ONLY,
+    ]
+    .into_iter()
+    .find(|&policy| share_policy_name(policy) == Some(name))
+}
 
 /// Fault bitmask definitions shared between analog and digital firmware.
 ///
 /// These bits live in `FastStatus.fault_flags` and represent latched protection
-/// conditions detected on the analog board. Once set, they remain asserted
-/// until cleared by a SoftReset handshake.
+/// conditions detected on the analog board. Whether a bit stays asserted after
+/// its condition disappears is governed by the fault policy
+/// ([`ProtectionConfig::fault_latch_mask`]): latched faults remain set until a
+/// ClearFaults request or SoftReset handshake, auto-recover faults clear on their own.
 pub const FAULT_OVERCURRENT: u32 = 1 << 0;
 pub const FAULT_OVERVOLTAGE: u32 = 1 << 1;
 pub const FAULT_MCU_OVER_TEMP: u32 = 1 << 2;
 pub const FAULT_SINK_OVER_TEMP: u32 = 1 << 3;
+/// All fault bits currently defined above.
+pub const FAULT_ALL: u32 =
+    FAULT_OVERCURRENT | FAULT_OVERVOLTAGE | FAULT_MCU_OVER_TEMP | FAULT_SINK_OVER_TEMP;
+
+/// Fault-policy / ClearFaults selector for the undervoltage latch.
+///
+/// Not a `fault_flags` bit: the UV latch is reported via [`STATE_FLAG_UV_LATCHED`],
+/// but it participates in the same latch policy and clear workflow as the faults.
+pub const FAULT_POLICY_UV_LATCH: u32 = 1 << 31;
+/// Every selector accepted in fault-policy and ClearFaults masks.
+pub const FAULT_POLICY_ALL: u32 = FAULT_ALL | FAULT_POLICY_UV_LATCH;
+
+/// [`ResetCounters::mask`] selectors.
+pub const COUNTER_CHARGE: u8 = 1 << 0; // charge_uah
+pub const COUNTER_ENERGY: u8 = 1 << 1; // energy_uwh
+pub const COUNTER_TIME: u8 = 1 << 2; // counter_elapsed_ms / counter_on_ms
+pub const COUNTER_ALL: u8 = COUNTER_CHARGE | COUNTER_ENERGY | COUNTER_TIME;
+
+/// [`EnterBootloader::magic`]: ASCII "BOOT", so a corrupted frame that still
+/// passes the CRC cannot drop the analog board into its bootloader.
+pub const ENTER_BOOTLOADER_MAGIC: u32 = 0x424F_4F54;
 
 pub const SLIP_END: u8 = 0xC0;
 pub const SLIP_ESC: u8 = 0xDB;
@@ -154,6 +275,45 @@ pub struct FastStatus {
     /// Optional raw DAC code used by the control loop.
     #[n(20)]
     pub raw_dac_code: Option<u16>,
+    /// Optional `SENSE_WARN_*` bitmask; omitted while no warning is active.
+    #[n(21)]
+    pub sense_warnings: Option<u32>,
+    /// Optional estimated force-lead (cable) resistance in mΩ, from the
+    /// local-vs-remote voltage drop over the load current. Present only while
+    /// remote sense is active and enough current has flowed to measure it.
+    #[n(22)]
+    pub lead_resistance_mohm: Option<u32>,
+    /// Optional per-channel DAC codes driven by the control loop. Present
+    /// while calibration is off (`raw_dac_code` covers calibration).
+    #[n(23)]
+    pub dac_code_ch1: Option<u16>,
+    #[n(24)]
+    pub dac_code_ch2: Option<u16>,
+    /// Optional estimated FET junction temperatures (m°C): heatsink core NTC
+    /// plus each channel's dissipation over the junction-to-sink resistance.
+    #[n(25)]
+    pub fet_temp_ch1_mc: Option<i32>,
+    #[n(26)]
+    pub fet_temp_ch2_mc: Option<i32>,
+    /// Optional active `SHARE_POLICY_*`; omitted by firmware predating it.
+    #[n(27)]
+    pub share_policy: Option<u8>,
+    /// Optional `CHANNEL_WARN_*` bitmask; omitted while no warning is active.
+    #[n(28)]
+    pub channel_warnings: Option<u32>,
+    /// Optional charge (µAh) and energy (µWh) through the load since the last
+    /// `ResetCounters` / analog boot, integrated at control-loop rate while
+    /// the output is enabled.
+    #[n(29)]
+    pub charge_uah: Option<u64>,
+    #[n(30)]
+    pub energy_uwh: Option<u64>,
+    /// Optional time since the counters were reset (ms), and the part of it
+    /// with the output enabled.
+    #[n(31)]
+    pub counter_elapsed_ms: Option<u32>,
+    #[n(32)]
+    pub counter_on_ms: Option<u32>,
 }
 
 /// Stable load mode contract carried in control frames and surfaced via telemetry.
@@ -286,6 +446,165 @@ pub struct LimitProfile {
     pub thermal_derate_pct: u8,
 }
 
+/// Hardware-safe bounds (inclusive) for [`ProtectionConfig`] fields.
+pub const PROTECTION_OC_LIMIT_CH_MA_RANGE: RangeInclusive<i32> = 100..=5_500;
+pub const PROTECTION_OV_LIMIT_MV_RANGE: RangeInclusive<i32> = 1_000..=55_000;
+pub const PROTECTION_MCU_TEMP_LIMIT_MC_RANGE: RangeInclusive<i32> = 40_000..=110_000;
+pub const PROTECTION_SINK_TEMP_LIMIT_MC_RANGE: RangeInclusive<i32> = 40_000..=100_000;
+pub const PROTECTION_I_SHARE_THRESHOLD_MA_RANGE: RangeInclusive<i32> = 0..=5_000;
+pub const PROTECTION_LINK_LOSS_TIMEOUT_MS_RANGE: RangeInclusive<i32> = 500..=10_000;
+
+/// Analog-side protection thresholds (previously compile-time constants).
+///
+/// - oc_limit_ch_ma: per-channel overcurrent fault threshold (mA)
+/// - ov_limit_mv: overvoltage fault threshold on the local sense (mV)
+/// - mcu_temp_limit_mc: MCU over-temperature fault threshold (m°C)
+/// - sink_temp_limit_mc: heatsink core over-temperature fault threshold (m°C)
+/// - i_share_threshold_ma: total current at which CH2 starts sharing the load
+///   under [`SHARE_POLICY_AUTO`] (mA)
+/// - fault_latch_mask: fault policy; selectors from [`FAULT_POLICY_ALL`] set here
+///   latch until cleared, the others auto-recover once their condition is gone
+/// - link_loss_timeout_ms: silence on the UART link after which the analog side
+///   ramps the output to zero and latches [`STATE_FLAG_LINK_SAFED`] (ms)
+/// - share_policy: CH1/CH2 split, one of the `SHARE_POLICY_*` values
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, Encode, Decode, PartialEq, Eq)]
+#[cbor(map)]
+pub struct ProtectionConfig {
+    #[n(0)]
+    pub oc_limit_ch_ma: i32,
+    #[n(1)]
+    pub ov_limit_mv: i32,
+    #[n(2)]
+    pub mcu_temp_limit_mc: i32,
+    #[n(3)]
+    pub sink_temp_limit_mc: i32,
+    #[n(4)]
+    pub i_share_threshold_ma: i32,
+    #[n(5)]
+    pub fault_latch_mask: u32,
+    #[n(6)]
+    pub link_loss_timeout_ms: i32,
+    #[n(7)]
+    pub share_policy: u8,
+}
+
+/// Field identifiers used to report [`ProtectionConfig::validate`] failures.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProtectionField {
+    OcLimitChMa,
+    OvLimitMv,
+    McuTempLimitMc,
+    SinkTempLimitMc,
+    IShareThresholdMa,
+    FaultLatchMask,
+    LinkLossTimeoutMs,
+    SharePolicy,
+}
+
+impl ProtectionField {
+    /// Wire/JSON name of the field.
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            ProtectionField::OcLimitChMa => "oc_limit_ch_ma",
+            ProtectionField::OvLimitMv => "ov_limit_mv",
+            ProtectionField::McuTempLimitMc => "mcu_temp_limit_mc",
+            ProtectionField::SinkTempLimitMc => "sink_temp_limit_mc",
+            ProtectionField::IShareThresholdMa => "i_share_threshold_ma",
+            ProtectionField::FaultLatchMask => "fault_latch_mask",
+            ProtectionField::LinkLossTimeoutMs => "link_loss_timeout_ms",
+            ProtectionField::SharePolicy => "share_policy",
+        }
+    }
+}
+
+impl ProtectionConfig {
+    /// Factory thresholds; identical to the historical analog constants.
+    ///
+    /// Every fault and the UV latch default to latch-until-cleared, matching the
+    /// behaviour before the fault policy became configurable.
+    pub const DEFAULT: Self = Self {
+        oc_limit_ch_ma: 5_500,
+        ov_limit_mv: 55_000,
+        mcu_temp_limit_mc: 110_000,
+        sink_temp_limit_mc: 100_000,
+        i_share_threshold_ma: 2_000,
+        fault_latch_mask: FAULT_POLICY_ALL,
+        link_loss_timeout_ms: 1_000,
+        share_policy: SHARE_POLICY_AUTO,
+    };
+
+    /// Check every field against its hardware-safe range, reporting the first
+    /// offending field.
+    pub fn validate(&self) -> Result<(), ProtectionField> {
+        if !PROTECTION_OC_LIMIT_CH_MA_RANGE.contains(&self.oc_limit_ch_ma) {
+            return Err(ProtectionField::OcLimitChMa);
+        }
+        if !PROTECTION_OV_LIMIT_MV_RANGE.contains(&self.ov_limit_mv) {
+            return Err(ProtectionField::OvLimitMv);
+        }
+        if !PROTECTION_MCU_TEMP_LIMIT_MC_RANGE.contains(&self.mcu_temp_limit_mc) {
+            return Err(ProtectionField::McuTempLimitMc);
+        }
+        if !PROTECTION_SINK_TEMP_LIMIT_MC_RANGE.contains(&self.sink_temp_limit_mc) {
+            return Err(ProtectionField::SinkTempLimitMc);
+        }
+        if !PROTECTION_I_SHARE_THRESHOLD_MA_RANGE.contains(&self.i_share_threshold_ma) {
+            return Err(ProtectionField::IShareThresholdMa);
+        }
+        if self.fault_latch_mask & !FAULT_POLICY_ALL != 0 {
+            return Err(ProtectionField::FaultLatchMask);
+        }
+        if !PROTECTION_LINK_LOSS_TIMEOUT_MS_RANGE.contains(&self.link_loss_timeout_ms) {
+            return Err(ProtectionField::LinkLossTimeoutMs);
+        }
+        if share_policy_name(self.share_policy).is_none() {
+            return Err(ProtectionField::SharePolicy);
+        }
+        Ok(())
+    }
+}
+
+impl Default for ProtectionConfig {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Explicit fault clear request (`MSG_CLEAR_FAULTS`).
+///
+/// `mask` selects what to clear using the `FAULT_*` bits plus
+/// [`FAULT_POLICY_UV_LATCH`]; unknown bits are ignored by the analog side.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, Encode, Decode, PartialEq, Eq)]
+#[cbor(map)]
+pub struct ClearFaults {
+    #[n(0)]
+    pub mask: u32,
+}
+
+/// Charge/energy counter reset request (`MSG_RESET_COUNTERS`).
+///
+/// `mask` selects the `COUNTER_*` totals to zero; unknown bits are ignored by
+/// the analog side.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, Encode, Decode, PartialEq, Eq)]
+#[cbor(map)]
+pub struct ResetCounters {
+    #[n(0)]
+    pub mask: u8,
+}
+
+/// System bootloader entry request (`MSG_ENTER_BOOTLOADER`).
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, Encode, Decode, PartialEq, Eq)]
+#[cbor(map)]
+pub struct EnterBootloader {
+    #[n(0)]
+    pub magic: u32,
+}
+
 /// Reason codes for a soft-reset request initiated by the digital side.
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
@@ -1403,6 +1722,174 @@ pub fn encode_pd_sink_request_frame(
     Ok(frame_len_without_crc + CRC_LEN)
 }
 
+/// Encode a PROTECTION_CONFIG control frame from the digital side (ACK required).
+pub fn encode_protection_config_frame(
+    seq: u8,
+    cfg: &ProtectionConfig,
+    out: &mut [u8],
+) -> Result<usize, Error> {
+    if out.len() < HEADER_LEN + CRC_LEN {
+        return Err(Error::BufferTooSmall);
+    }
+
+    out[0] = PROTOCOL_VERSION;
+    out[1] = FLAG_ACK_REQ;
+    out[2] = seq;
+    out[3] = MSG_PROTECTION_CONFIG;
+
+    let payload_len = {
+        let payload_slice = &mut out[HEADER_LEN..];
+        let mut cursor = Cursor::new(payload_slice);
+        let mut encoder = minicbor::Encoder::new(&mut cursor);
+        encoder.encode(cfg).map_err(map_encode_err)?;
+        cursor.position()
+    };
+    if payload_len > u16::MAX as usize {
+        return Err(Error::PayloadTooLarge);
+    }
+
+    let len_bytes = (payload_len as u16).to_le_bytes();
+    out[4] = len_bytes[0];
+    out[5] = len_bytes[1];
+
+    let frame_len_without_crc = HEADER_LEN + payload_len;
+    if frame_len_without_crc + CRC_LEN > out.len() {
+        return Err(Error::BufferTooSmall);
+    }
+
+    let crc = crc16_ccitt_false(&out[..frame_len_without_crc]);
+    let crc_bytes = crc.to_le_bytes();
+    out[frame_len_without_crc] = crc_bytes[0];
+    out[frame_len_without_crc + 1] = crc_bytes[1];
+    Ok(frame_len_without_crc + CRC_LEN)
+}
+
+/// Encode a CLEAR_FAULTS control frame from the digital side (ACK required).
+pub fn encode_clear_faults_frame(
+    seq: u8,
+    req: &ClearFaults,
+    out: &mut [u8],
+) -> Result<usize, Error> {
+    if out.len() < HEADER_LEN + CRC_LEN {
+        return Err(Error::BufferTooSmall);
+    }
+
+    out[0] = PROTOCOL_VERSION;
+    out[1] = FLAG_ACK_REQ;
+    out[2] = seq;
+    out[3] = MSG_CLEAR_FAULTS;
+
+    let payload_len = {
+        let payload_slice = &mut out[HEADER_LEN..];
+        let mut cursor = Cursor::new(payload_slice);
+        let mut encoder = minicbor::Encoder::new(&mut cursor);
+        encoder.encode(req).map_err(map_encode_err)?;
+        cursor.position()
+    };
+    if payload_len > u16::MAX as usize {
+        return Err(Error::PayloadTooLarge);
+    }
+
+    let len_bytes = (payload_len as u16).to_le_bytes();
+    out[4] = len_bytes[0];
+    out[5] = len_bytes[1];
+
+    let frame_len_without_crc = HEADER_LEN + payload_len;
+    if frame_len_without_crc + CRC_LEN > out.len() {
+        return Err(Error::BufferTooSmall);
+    }
+
+    let crc = crc16_ccitt_false(&out[..frame_len_without_crc]);
+    let crc_bytes = crc.to_le_bytes();
+    out[frame_len_without_crc] = crc_bytes[0];
+    out[frame_len_without_crc + 1] = crc_bytes[1];
+    Ok(frame_len_without_crc + CRC_LEN)
+}
+
+/// Encode a RESET_COUNTERS control frame from the digital side (ACK required).
+pub fn encode_reset_counters_frame(
+    seq: u8,
+    req: &ResetCounters,
+    out: &mut [u8],
+) -> Result<usize, Error> {
+    if out.len() < HEADER_LEN + CRC_LEN {
+        return Err(Error::BufferTooSmall);
+    }
+
+    out[0] = PROTOCOL_VERSION;
+    out[1] = FLAG_ACK_REQ;
+    out[2] = seq;
+    out[3] = MSG_RESET_COUNTERS;
+
+    let payload_len = {
+        let payload_slice = &mut out[HEADER_LEN..];
+        let mut cursor = Cursor::new(payload_slice);
+        let mut encoder = minicbor::Encoder::new(&mut cursor);
+        encoder.encode(req).map_err(map_encode_err)?;
+        cursor.position()
+    };
+    if payload_len > u16::MAX as usize {
+        return Err(Error::PayloadTooLarge);
+    }
+
+    let len_bytes = (payload_len as u16).to_le_bytes();
+    out[4] = len_bytes[0];
+    out[5] = len_bytes[1];
+
+    let frame_len_without_crc = HEADER_LEN + payload_len;
+    if frame_len_without_crc + CRC_LEN > out.len() {
+        return Err(Error::BufferTooSmall);
+    }
+
+    let crc = crc16_ccitt_false(&out[..frame_len_without_crc]);
+    let crc_bytes = crc.to_le_bytes();
+    out[frame_len_without_crc] = crc_bytes[0];
+    out[frame_len_without_crc + 1] = crc_bytes[1];
+    Ok(frame_len_without_crc + CRC_LEN)
+}
+
+/// Encode an ENTER_BOOTLOADER control frame from the digital side (ACK required).
+pub fn encode_enter_bootloader_frame(
+    seq: u8,
+    req: &EnterBootloader,
+    out: &mut [u8],
+) -> Result<usize, Error> {
+    if out.len() < HEADER_LEN + CRC_LEN {
+        return Err(Error::BufferTooSmall);
+    }
+
+    out[0] = PROTOCOL_VERSION;
+    out[1] = FLAG_ACK_REQ;
+    out[2] = seq;
+    out[3] = MSG_ENTER_BOOTLOADER;
+
+    let payload_len = {
+        let payload_slice = &mut out[HEADER_LEN..];
+        let mut cursor = Cursor::new(payload_slice);
+        let mut encoder = minicbor::Encoder::new(&mut cursor);
+        encoder.encode(req).map_err(map_encode_err)?;
+        cursor.position()
+    };
+    if payload_len > u16::MAX as usize {
+        return Err(Error::PayloadTooLarge);
+    }
+
+    let len_bytes = (payload_len as u16).to_le_bytes();
+    out[4] = len_bytes[0];
+    out[5] = len_bytes[1];
+
+    let frame_len_without_crc = HEADER_LEN + payload_len;
+    if frame_len_without_crc + CRC_LEN > out.len() {
+        return Err(Error::BufferTooSmall);
+    }
+
+    let crc = crc16_ccitt_false(&out[..frame_len_without_crc]);
+    let crc_bytes = crc.to_le_bytes();
+    out[frame_len_without_crc] = crc_bytes[0];
+    out[frame_len_without_crc + 1] = crc_bytes[1];
+    Ok(frame_len_without_crc + CRC_LEN)
+}
+
 pub fn decode_fast_status_frame(frame: &[u8]) -> Result<(FrameHeader, FastStatus), Error> {
     let (header, payload) = decode_frame(frame)?;
     if header.msg != MSG_FAST_STATUS {
@@ -1537,6 +2024,50 @@ pub fn decode_pd_sink_request_frame(frame: &[u8]) -> Result<(FrameHeader, PdSink
     Ok((header, req))
 }
 
+pub fn decode_protection_config_frame(
+    frame: &[u8],
+) -> Result<(FrameHeader, ProtectionConfig), Error> {
+    let (header, payload) = decode_frame(frame)?;
+    if header.msg != MSG_PROTECTION_CONFIG {
+        return Err(Error::UnsupportedMessage(header.msg));
+    }
+    let mut decoder = minicbor::Decoder::new(payload);
+    let cfg: ProtectionConfig = decoder.decode().map_err(map_decode_err)?;
+    Ok((header, cfg))
+}
+
+pub fn decode_clear_faults_frame(frame: &[u8]) -> Result<(FrameHeader, ClearFaults), Error> {
+    let (header, payload) = decode_frame(frame)?;
+    if header.msg != MSG_CLEAR_FAULTS {
+        return Err(Error::UnsupportedMessage(header.msg));
+    }
+    let mut decoder = minicbor::Decoder::new(payload);
+    let req: ClearFaults = decoder.decode().map_err(map_decode_err)?;
+    Ok((header, req))
+}
+
+pub fn decode_reset_counters_frame(frame: &[u8]) -> Result<(FrameHeader, ResetCounters), Error> {
+    let (header, payload) = decode_frame(frame)?;
+    if header.msg != MSG_RESET_COUNTERS {
+        return Err(Error::UnsupportedMessage(header.msg));
+    }
+    let mut decoder = minicbor::Decoder::new(payload);
+    let req: ResetCounters = decoder.decode().map_err(map_decode_err)?;
+    Ok((header, req))
+}
+
+pub fn decode_enter_bootloader_frame(
+    frame: &[u8],
+) -> Result<(FrameHeader, EnterBootloader), Error> {
+    let (header, payload) = decode_frame(frame)?;
+    if header.msg != MSG_ENTER_BOOTLOADER {
+        return Err(Error::UnsupportedMessage(header.msg));
+    }
+    let mut decoder = minicbor::Decoder::new(payload);
+    let req: EnterBootloader = decoder.decode().map_err(map_decode_err)?;
+    Ok((header, req))
+}
+
 pub fn decode_frame(buf: &[u8]) -> Result<(FrameHeader, &[u8]), Error> {
     if buf.len() < HEADER_LEN + CRC_LEN {
         return Err(Error::LengthMismatch);
@@ -1942,6 +2473,109 @@ mod tests {
         assert_eq!(decoded, req);
     }
 
+    #[test]
+    fn protection_config_roundtrip_and_validation() {
+        let cfg = ProtectionConfig {
+            oc_limit_ch_ma: 3_000,
+            ov_limit_mv: 30_000,
+            mcu_temp_limit_mc: 90_000,
+            sink_temp_limit_mc: 80_000,
+            i_share_threshold_ma: 1_500,
+            fault_latch_mask: FAULT_OVERCURRENT | FAULT_POLICY_UV_LATCH,
+            link_loss_timeout_ms: 2_500,
+            share_policy: SHARE_POLICY_CH2_ONLY,
+        };
+        assert_eq!(cfg.validate(), Ok(()));
+        assert_eq!(ProtectionConfig::default().validate(), Ok(()));
+
+        let mut raw = [0u8; 64];
+        let len = encode_protection_config_frame(11, &cfg, &mut raw).unwrap();
+        let (hdr, decoded) = decode_protection_config_frame(&raw[..len]).unwrap();
+        assert_eq!(hdr.msg, MSG_PROTECTION_CONFIG);
+        assert_eq!(hdr.seq, 11);
+        assert_eq!(hdr.flags & FLAG_ACK_REQ, FLAG_ACK_REQ);
+        assert_eq!(decoded, cfg);
+
+        let too_hot = ProtectionConfig {
+            sink_temp_limit_mc: 120_000,
+            ..cfg
+        };
+        assert_eq!(too_hot.validate(), Err(ProtectionField::SinkTempLimitMc));
+        let no_oc = ProtectionConfig {
+            oc_limit_ch_ma: 0,
+            ..cfg
+        };
+        assert_eq!(no_oc.validate(), Err(ProtectionField::OcLimitChMa));
+        let bad_policy = ProtectionConfig {
+            fault_latch_mask: 1 << 8,
+            ..cfg
+        };
+        assert_eq!(bad_policy.validate(), Err(ProtectionField::FaultLatchMask));
+        let deaf = ProtectionConfig {
+            link_loss_timeout_ms: 60_000,
+            ..cfg
+        };
+        assert_eq!(deaf.validate(), Err(ProtectionField::LinkLossTimeoutMs));
+        let unknown_share = ProtectionConfig {
+            share_policy: 4,
+            ..cfg
+        };
+        assert_eq!(unknown_share.validate(), Err(ProtectionField::SharePolicy));
+        for policy in [
+            SHARE_POLICY_AUTO,
+            SHARE_POLICY_DUAL,
+            SHARE_POLICY_CH1_ONLY,
+            SHARE_POLICY_CH2_ONLY,
+        ] {
+            let name = share_policy_name(policy).unwrap();
+            assert_eq!(share_policy_from_name(name), Some(policy));
+        }
+        assert_eq!(share_policy_from_name("triple"), None);
+    }
+
+    #[test]
+    fn clear_faults_roundtrip() {
+        let req = ClearFaults {
+            mask: FAULT_OVERVOLTAGE | FAULT_POLICY_UV_LATCH,
+        };
+        let mut raw = [0u8; 32];
+        let len = encode_clear_faults_frame(5, &req, &mut raw).unwrap();
+        let (hdr, decoded) = decode_clear_faults_frame(&raw[..len]).unwrap();
+        assert_eq!(hdr.msg, MSG_CLEAR_FAULTS);
+        assert_eq!(hdr.seq, 5);
+        assert_eq!(hdr.flags & FLAG_ACK_REQ, FLAG_ACK_REQ);
+        assert_eq!(decoded, req);
+        assert!(decode_protection_config_frame(&raw[..len]).is_err());
+    }
+
+    #[test]
+    fn reset_counters_roundtrip() {
+        let mut raw = [0u8; 16];
+        let req = ResetCounters { mask: COUNTER_ALL };
+        let len = encode_reset_counters_frame(9, &req, &mut raw).unwrap();
+        let (hdr, decoded) = decode_reset_counters_frame(&raw[..len]).unwrap();
+        assert_eq!(hdr.msg, MSG_RESET_COUNTERS);
+        assert_eq!(hdr.seq, 9);
+        assert_eq!(hdr.flags & FLAG_ACK_REQ, FLAG_ACK_REQ);
+        assert_eq!(decoded, req);
+        assert!(decode_clear_faults_frame(&raw[..len]).is_err());
+    }
+
+    #[test]
+    fn enter_bootloader_roundtrip() {
+        let mut raw = [0u8; 24];
+        let req = EnterBootloader {
+            magic: ENTER_BOOTLOADER_MAGIC,
+        };
+        let len = encode_enter_bootloader_frame(3, &req, &mut raw).unwrap();
+        let (hdr, decoded) = decode_enter_bootloader_frame(&raw[..len]).unwrap();
+        assert_eq!(hdr.msg, MSG_ENTER_BOOTLOADER);
+        assert_eq!(hdr.seq, 3);
+        assert_eq!(hdr.flags & FLAG_ACK_REQ, FLAG_ACK_REQ);
+        assert_eq!(decoded, req);
+        assert!(decode_reset_counters_frame(&raw[..len]).is_err());
+    }
+
     #[test]
     fn pd_status_roundtrip_and_lists() {
         let mut fixed_pdos = FixedPdoList::new();
@@ -2091,6 +2725,18 @@ mod tests {
             raw_v_rmt_100uv: None,
             raw_cur_100uv: Some(789),
             raw_dac_code: None,
+            sense_warnings: Some(SENSE_WARN_REMOTE_OPEN),
+            lead_resistance_mohm: None,
+            dac_code_ch1: None,
+            dac_code_ch2: None,
+            fet_temp_ch1_mc: None,
+            fet_temp_ch2_mc: None,
+            share_policy: Some(SHARE_POLICY_DUAL),
+            channel_warnings: Some(CHANNEL_WARN_IMBALANCE),
+            charge_uah: Some(12_345_678),
+            energy_uwh: Some(u32::MAX as u64 + 1),
+            counter_elapsed_ms: Some(3_600_000),
+            counter_on_ms: None,
         };
 
         let mut raw = [0u8; 192];
@@ -2101,6 +2747,16 @@ mod tests {
         assert_eq!(decoded.raw_v_rmt_100uv, None);
         assert_eq!(decoded.raw_cur_100uv, status.raw_cur_100uv);
         assert_eq!(decoded.raw_dac_code, None);
+        assert_eq!(decoded.sense_warnings, Some(SENSE_WARN_REMOTE_OPEN));
+        assert_eq!(decoded.lead_resistance_mohm, None);
+        assert_eq!(decoded.dac_code_ch1, None);
+        assert_eq!(decoded.fet_temp_ch2_mc, None);
+        assert_eq!(decoded.share_policy, Some(SHARE_POLICY_DUAL));
+        assert_eq!(decoded.channel_warnings, Some(CHANNEL_WARN_IMBALANCE));
+        assert_eq!(decoded.charge_uah, Some(12_345_678));
+        assert_eq!(decoded.energy_uwh, Some(u32::MAX as u64 + 1));
+        assert_eq!(decoded.counter_elapsed_ms, Some(3_600_000));
+        assert_eq!(decoded.counter_on_ms, None);
     }
 
     #[test]
@@ -0,0 +1,301 @@
+//! Link-loss safe state for the analog board's load output.
+//!
+//! The analog board only sinks current on behalf of the digital board. If the
+//! ESP32 hangs or the UART is unplugged mid-test, no further SetPoint/SetMode
+//! frames arrive and the last setpoint would otherwise stay applied forever.
+//! [`LinkSafing`] watches the age of the last valid control frame and, once it
+//! exceeds the configured window
+//! ([`crate::ProtectionConfig::link_loss_timeout_ms`]), ramps the output to
+//! zero over [`RAMP_MS`] and latches the safed state.
+//!
+//! The latch is reported via [`crate::STATE_FLAG_LINK_SAFED`] once the link is
+//! back and only clears on the next `SetMode` output-enable rising edge, so a
+//! recovered link never silently resumes a load. ClearFaults, SoftReset and
+//! the replayed `SetMode` after reconnect leave it set.
+//!
+//! Pure state machine, no timers or atomics: the analog firmware feeds it
+//! timestamps from its 10 kHz control loop.
+
+use crate::STATE_FLAG_LINK_SAFED;
+
+/// Duration of the linear ramp from the current setpoint down to zero.
+pub const RAMP_MS: u32 = 200;
+
+/// Full-scale output factor returned by [`LinkSafing::tick`].
+pub const SCALE_FULL_PERMILLE: u32 = 1_000;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum State {
+    /// Link healthy (or never established yet); output follows the setpoint.
+    Normal,
+    /// Link silent for longer than the window; output ramping down.
+    Ramping { since_ms: u32 },
+    /// Output forced to zero until [`LinkSafing::rearm`].
+    Safed,
+}
+
+/// Why the output was safed; reported once the link comes back.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Reason {
+    /// No valid frame within the configured window.
+    LinkTimeout,
+    /// The MCU came back from an IWDG reset (link silent long enough that
+    /// the watchdog was no longer refreshed).
+    WatchdogReset,
+}
+
+/// Edge events for logging.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// Window expired; ramp started. Carries the silence duration in ms.
+    RampStarted { silent_ms: u32 },
+    /// Ramp finished; output is now held at zero.
+    Safed,
+    /// A valid frame arrived while safed; the output stays off until re-armed.
+    LinkRestored { reason: Reason },
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct LinkSafing {
+    state: State,
+    reason: Reason,
+    /// Whether the link was seen alive since the last transition to `Safed`;
+    /// drives the one-shot [`Event::LinkRestored`].
+    restored: bool,
+}
+
+impl LinkSafing {
+    pub const fn new() -> Self {
+        Self {
+            state: State::Normal,
+            reason: Reason::LinkTimeout,
+            restored: false,
+        }
+    }
+
+    /// Start latched, e.g. after an IWDG reset: the previous session lost its
+    /// link, so the output must not come back on by itself.
+    pub const fn new_safed(reason: Reason) -> Self {
+        Self {
+            state: State::Safed,
+            reason,
+            restored: false,
+        }
+    }
+
+    pub const fn state(&self) -> State {
+        self.state
+    }
+
+    pub const fn is_safed(&self) -> bool {
+        matches!(self.state, State::Safed)
+    }
+
+    /// `FastStatus.state_flags` contribution.
+    pub const fn state_flags(&self) -> u32 {
+        if self.is_safed() {
+            STATE_FLAG_LINK_SAFED
+        } else {
+            0
+        }
+    }
+
+    /// Advance the state machine.
+    ///
+    /// `last_rx_ms` is the timestamp of the last valid control frame, `None`
+    /// until the link has been good once (boot without a digital board must
+    /// not latch). Returns the output scale in permille (1000 = unchanged,
+    /// 0 = off) and an optional edge event.
+    pub fn tick(
+        &mut self,
+        now_ms: u32,
+        last_rx_ms: Option<u32>,
+        window_ms: u32,
+    ) -> (u32, Option<Event>) {
+        let silent_ms = last_rx_ms.map(|t| now_ms.wrapping_sub(t));
+        let link_alive = matches!(silent_ms, Some(age) if age <= window_ms);
+
+        match self.state {
+            State::Normal => match silent_ms {
+                Some(age) if age > window_ms => {
+                    self.state = State::Ramping { since_ms: now_ms };
+                    self.reason = Reason::LinkTimeout;
+                    (
+                        SCALE_FULL_PERMILLE,
+                        Some(Event::RampStarted { silent_ms: age }),
+                    )
+                }
+                _ => (SCALE_FULL_PERMILLE, None),
+            },
+            State::Ramping { since_ms } => {
+                // A frame during the ramp does not cancel it: the link was
+                // already gone for a full window, treat it as lost.
+                let elapsed = now_ms.wrapping_sub(since_ms);
+                if elapsed >= RAMP_MS {
+                    self.state = State::Safed;
+                    self.restored = link_alive;
+                    (0, Some(Event::Safed))
+                } else {
+                    (
+                        SCALE_FULL_PERMILLE - elapsed * SCALE_FULL_PERMILLE / RAMP_MS,
+                        None,
+                    )
+                }
+            }
+            State::Safed => {
+                if link_alive && !self.restored {
+                    self.restored = true;
+                    (
+                        0,
+                        Some(Event::LinkRestored {
+                            reason: self.reason,
+                        }),
+                    )
+                } else {
+                    if !link_alive {
+                        self.restored = false;
+                    }
+                    (0, None)
+                }
+            }
+        }
+    }
+
+    /// Leave the safed state; the analog firmware calls this only on a
+    /// `SetMode` output-enable rising edge.
+    ///
+    /// Safe to call while the link is still silent: the next `tick` simply
+    /// starts a new ramp, so callers do not need to check the link themselves.
+    pub fn rearm(&mut self) {
+        self.state = State::Normal;
+        self.restored = false;
+    }
+}
+
+impl Default for LinkSafing {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Apply a [`LinkSafing::tick`] scale to a current/power target.
+pub const fn scale_target(target: i32, scale_permille: u32) -> i32 {
+    (target as i64 * scale_permille as i64 / SCALE_FULL_PERMILLE as i64) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PROTECTION_LINK_LOSS_TIMEOUT_MS_RANGE, ProtectionConfig};
+
+    const WINDOW: u32 = ProtectionConfig::DEFAULT.link_loss_timeout_ms as u32;
+
+    #[test]
+    fn default_window_is_in_range() {
+        assert!(PROTECTION_LINK_LOSS_TIMEOUT_MS_RANGE.contains(&(WINDOW as i32)));
+    }
+
+    #[test]
+    fn never_linked_does_not_latch() {
+        let mut s = LinkSafing::new();
+        assert_eq!(s.tick(60_000, None, WINDOW), (1_000, None));
+        assert_eq!(s.state(), State::Normal);
+        assert_eq!(s.state_flags(), 0);
+    }
+
+    #[test]
+    fn silence_ramps_then_latches() {
+        let mut s = LinkSafing::new();
+        let last = 1_000;
+        assert_eq!(s.tick(last + WINDOW, Some(last), WINDOW), (1_000, None));
+
+        let t0 = last + WINDOW + 1;
+        assert_eq!(
+            s.tick(t0, Some(last), WINDOW),
+            (
+                1_000,
+                Some(Event::RampStarted {
+                    silent_ms: WINDOW + 1
+                })
+            )
+        );
+        assert_eq!(s.tick(t0 + RAMP_MS / 2, Some(last), WINDOW), (500, None));
+        assert_eq!(s.state_flags(), 0);
+        assert_eq!(
+            s.tick(t0 + RAMP_MS, Some(last), WINDOW),
+            (0, Some(Event::Safed))
+        );
+        assert!(s.is_safed());
+        assert_eq!(s.state_flags(), STATE_FLAG_LINK_SAFED);
+        assert_eq!(s.tick(t0 + 10_000, Some(last), WINDOW), (0, None));
+    }
+
+    #[test]
+    fn recovery_reports_once_and_stays_off_until_rearm() {
+        let mut s = LinkSafing::new();
+        s.tick(5_000, Some(0), WINDOW);
+        s.tick(5_000 + RAMP_MS, Some(0), WINDOW);
+        assert!(s.is_safed());
+
+        let rx = 9_000;
+        assert_eq!(
+            s.tick(rx, Some(rx), WINDOW),
+            (
+                0,
+                Some(Event::LinkRestored {
+                    reason: Reason::LinkTimeout
+                })
+            )
+        );
+        assert_eq!(s.tick(rx + 10, Some(rx + 10), WINDOW), (0, None));
+        assert!(s.is_safed());
+
+        s.rearm();
+        assert_eq!(s.tick(rx + 20, Some(rx + 20), WINDOW), (1_000, None));
+        assert_eq!(s.state_flags(), 0);
+    }
+
+    #[test]
+    fn rearm_while_silent_ramps_again() {
+        let mut s = LinkSafing::new();
+        s.tick(5_000, Some(0), WINDOW);
+        s.tick(5_000 + RAMP_MS, Some(0), WINDOW);
+        s.rearm();
+        let (scale, ev) = s.tick(5_500, Some(0), WINDOW);
+        assert_eq!(scale, 1_000);
+        assert!(matches!(ev, Some(Event::RampStarted { .. })));
+    }
+
+    #[test]
+    fn watchdog_reset_boots_safed() {
+        let mut s = LinkSafing::new_safed(Reason::WatchdogReset);
+        assert_eq!(s.tick(10, None, WINDOW), (0, None));
+        assert_eq!(
+            s.tick(2_000, Some(1_990), WINDOW),
+            (
+                0,
+                Some(Event::LinkRestored {
+                    reason: Reason::WatchdogReset
+                })
+            )
+        );
+    }
+
+    #[test]
+    fn timestamps_wrap() {
+        let mut s = LinkSafing::new();
+        let last = u32::MAX - 100;
+        assert_eq!(s.tick(50, Some(last), WINDOW), (1_000, None));
+        assert!(matches!(
+            s.tick(last.wrapping_add(WINDOW + 1), Some(last), WINDOW).1,
+            Some(Event::RampStarted { .. })
+        ));
+    }
+
+    #[test]
+    fn scale_target_rounds_toward_zero() {
+        assert_eq!(scale_target(5_000, 1_000), 5_000);
+        assert_eq!(scale_target(5_000, 333), 1_665);
+        assert_eq!(scale_target(5_000, 0), 0);
+    }
+}
@@ -0,0 +1,521 @@
+//! DHCP, captive DNS and form helpers for the digital firmware's SoftAP setup portal.
+
+#![no_std]
+
+#[cfg(test)]
+extern crate std;
+
+pub const DHCP_SERVER_PORT: u16 = 67;
+pub const DHCP_CLIENT_PORT: u16 = 68;
+pub const DNS_PORT: u16 = 53;
+
+/// Fixed BOOTP header length before the magic cookie.
+const BOOTP_HEADER_LEN: usize = 236;
+const DHCP_MAGIC_COOKIE: [u8; 4] = [99, 130, 83, 99];
+const DHCP_OPTIONS_OFFSET: usize = BOOTP_HEADER_LEN + DHCP_MAGIC_COOKIE.len();
+/// Reply size produced by [`encode_dhcp_reply`] (header, cookie, options).
+pub const DHCP_REPLY_LEN: usize = DHCP_OPTIONS_OFFSET + 34;
+
+const OPTION_PAD: u8 = 0;
+const OPTION_SUBNET_MASK: u8 = 1;
+const OPTION_ROUTER: u8 = 3;
+const OPTION_DNS_SERVER: u8 = 6;
+const OPTION_REQUESTED_IP: u8 = 50;
+const OPTION_LEASE_TIME: u8 = 51;
+const OPTION_MESSAGE_TYPE: u8 = 53;
+const OPTION_SERVER_ID: u8 = 54;
+const OPTION_END: u8 = 255;
+
+const DNS_HEADER_LEN: usize = 12;
+const DNS_TYPE_A: u16 = 1;
+const DNS_TYPE_ANY: u16 = 255;
+const DNS_CLASS_IN: u16 = 1;
+/// TTL of redirect answers; short so clients re-resolve after provisioning.
+const DNS_ANSWER_TTL_S: u32 = 60;
+/// Compressed name pointer + type + class + TTL + rdlength + IPv4.
+const DNS_ANSWER_LEN: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The output buffer cannot hold the decoded value.
+    BufferTooSmall,
+    /// Invalid percent escape or the decoded bytes are not UTF-8.
+    Malformed,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DhcpMessageType {
+    Discover,
+    Offer,
+    Request,
+    Decline,
+    Ack,
+    Nak,
+    Release,
+    Inform,
+}
+
+impl DhcpMessageType {
+    fn from_code(code: u8) -> Option<Self> {
+        Some(match code {
+            1 => Self::Discover,
+            2 => Self::Offer,
+            3 => Self::Request,
+            4 => Self::Decline,
+            5 => Self::Ack,
+            6 => Self::Nak,
+            7 => Self::Release,
+            8 => Self::Inform,
+            _ => return None,
+        })
+    }
+
+    const fn code(self) -> u8 {
+        match self {
+            Self::Discover => 1,
+            Self::Offer => 2,
+            Self::Request => 3,
+            Self::Decline => 4,
+            Self::Ack => 5,
+            Self::Nak => 6,
+            Self::Release => 7,
+            Self::Inform => 8,
+        }
+    }
+}
+
+/// Client message fields the server needs to build a reply.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DhcpRequest {
+    pub message_type: DhcpMessageType,
+    pub xid: [u8; 4],
+    pub flags: [u8; 2],
+    pub client_mac: [u8; 6],
+    /// Option 50, falling back to `ciaddr` when the client is renewing.
+    pub requested_ip: Option<[u8; 4]>,
+    /// Option 54; `None` when the client did not pick a server.
+    pub server_id: Option<[u8; 4]>,
+}
+
+/// Parse a client `BOOTREQUEST` carrying a DHCP message type option.
+///
+/// Returns `None` for replies, non-Ethernet hardware and anything without
+/// the magic cookie or option 53.
+pub fn parse_dhcp_request(buf: &[u8]) -> Option<DhcpRequest> {
+    if buf.len() < DHCP_OPTIONS_OFFSET || buf[0] != 1 || buf[1] != 1 || buf[2] != 6 {
+        return None;
+    }
+    if buf[BOOTP_HEADER_LEN..DHCP_OPTIONS_OFFSET] != DHCP_MAGIC_COOKIE {
+        return None;
+    }
+
+    let mut message_type = None;
+    let mut requested_ip = None;
+    let mut server_id = None;
+    let mut idx = DHCP_OPTIONS_OFFSET;
+    while idx < buf.len() {
+        let code = buf[idx];
+        if code == OPTION_PAD {
+            idx += 1;
+            continue;
+        }
+        if code == OPTION_END {
+            break;
+        }
+        let len = *buf.get(idx + 1)? as usize;
+        let value = buf.get(idx + 2..idx + 2 + len)?;
+        match code {
+            OPTION_MESSAGE_TYPE if len == 1 => {
+                message_type = DhcpMessageType::from_code(value[0]);
+            }
+            OPTION_REQUESTED_IP if len == 4 => requested_ip = Some(ipv4(value)),
+            OPTION_SERVER_ID if len == 4 => server_id = Some(ipv4(value)),
+            _ => {}
+        }
+        idx += 2 + len;
+    }
+
+    let ciaddr = ipv4(&buf[12..16]);
+    if requested_ip.is_none() && ciaddr != [0; 4] {
+        requested_ip = Some(ciaddr);
+    }
+    let mut client_mac = [0u8; 6];
+    client_mac.copy_from_slice(&buf[28..34]);
+
+    Some(DhcpRequest {
+        message_type: message_type?,
+        xid: [buf[4], buf[5], buf[6], buf[7]],
+        flags: [buf[10], buf[11]],
+        client_mac,
+        requested_ip,
+        server_id,
+    })
+}
+
+fn ipv4(bytes: &[u8]) -> [u8; 4] {
+    [bytes[0], bytes[1], bytes[2], bytes[3]]
+}
+
+/// Network parameters announced in every reply. The server doubles as the
+/// router and DNS server so captive-portal probes reach the setup page.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DhcpServerConfig {
+    pub server_ip: [u8; 4],
+    pub netmask: [u8; 4],
+    pub lease_time_s: u32,
+}
+
+/// Write an `OFFER`, `ACK` or `NAK` for `request` into `out`.
+///
+/// `your_ip` is ignored for `NAK`. Returns the reply length
+/// ([`DHCP_REPLY_LEN`]); the caller broadcasts it to
+/// [`DHCP_CLIENT_PORT`] because the client has no address yet.
+pub fn encode_dhcp_reply(
+    out: &mut [u8],
+    request: &DhcpRequest,
+    message_type: DhcpMessageType,
+    your_ip: [u8; 4],
+    config: &DhcpServerConfig,
+) -> Result<usize, Error> {
+    if out.len() < DHCP_REPLY_LEN {
+        return Err(Error::BufferTooSmall);
+    }
+    let out = &mut out[..DHCP_REPLY_LEN];
+    out.fill(0);
+    out[0] = 2; // BOOTREPLY
+    out[1] = 1; // Ethernet
+    out[2] = 6;
+    out[4..8].copy_from_slice(&request.xid);
+    out[10..12].copy_from_slice(&request.flags);
+    if message_type != DhcpMessageType::Nak {
+        out[16..20].copy_from_slice(&your_ip);
+        out[20..24].copy_from_slice(&config.server_ip);
+    }
+    out[28..34].copy_from_slice(&request.client_mac);
+    out[BOOTP_HEADER_LEN..DHCP_OPTIONS_OFFSET].copy_from_slice(&DHCP_MAGIC_COOKIE);
+
+    let mut idx = DHCP_OPTIONS_OFFSET;
+    let mut put = |code: u8, value: &[u8]| {
+        out[idx] = code;
+        out[idx + 1] = value.len() as u8;
+        out[idx + 2..idx + 2 + value.len()].copy_from_slice(value);
+        idx += 2 + value.len();
+    };
+    put(OPTION_MESSAGE_TYPE, &[message_type.code()]);
+    put(OPTION_SERVER_ID, &config.server_ip);
+    if message_type != DhcpMessageType::Nak {
+        put(OPTION_LEASE_TIME, &config.lease_time_s.to_be_bytes());
+        put(OPTION_SUBNET_MASK, &config.netmask);
+        put(OPTION_ROUTER, &config.server_ip);
+        put(OPTION_DNS_SERVER, &config.server_ip);
+    }
+    out[idx] = OPTION_END;
+    Ok(DHCP_REPLY_LEN)
+}
+
+/// Fixed pool of `N` addresses directly after the server address, keyed by
+/// client MAC. When the pool is full the oldest assignment is reused.
+#[derive(Clone, Debug)]
+pub struct Leases<const N: usize> {
+    server_ip: [u8; 4],
+    clients: [Option<[u8; 6]>; N],
+    next: usize,
+}
+
+impl<const N: usize> Leases<N> {
+    pub const fn new(server_ip: [u8; 4]) -> Self {
+        Self {
+            server_ip,
+            clients: [None; N],
+            next: 0,
+        }
+    }
+
+    /// Address already assigned to `mac`, if any.
+    pub fn lookup(&self, mac: [u8; 6]) -> Option<[u8; 4]> {
+        self.clients
+            .iter()
+            .position(|client| *client == Some(mac))
+            .map(|slot| self.address(slot))
+    }
+
+    /// Address for `mac`, assigning a slot on first use.
+    pub fn assign(&mut self, mac: [u8; 6]) -> [u8; 4] {
+        if let Some(ip) = self.lookup(mac) {
+            return ip;
+        }
+        let slot = match self.clients.iter().position(Option::is_none) {
+            Some(slot) => slot,
+            None => {
+                let slot = self.next;
+                self.next = (self.next + 1) % N;
+                slot
+            }
+        };
+        self.clients[slot] = Some(mac);
+        self.address(slot)
+    }
+
+    pub fn release(&mut self, mac: [u8; 6]) {
+        for client in self.clients.iter_mut() {
+            if *client == Some(mac) {
+                *client = None;
+            }
+        }
+    }
+
+    fn address(&self, slot: usize) -> [u8; 4] {
+        let mut ip = self.server_ip;
+        ip[3] = ip[3].wrapping_add(1 + slot as u8);
+        ip
+    }
+}
+
+/// Answer a standard query with `ip` for any `A`/`ANY` question.
+///
+/// Other question types get an empty `NOERROR` answer so clients fall back
+/// to IPv4. Returns `None` for responses, non-query opcodes, anything other
+/// than exactly one question, or when `out` is too small.
+pub fn encode_dns_redirect(query: &[u8], out: &mut [u8], ip: [u8; 4]) -> Option<usize> {
+    if query.len() < DNS_HEADER_LEN {
+        return None;
+    }
+    let flags = u16::from_be_bytes([query[2], query[3]]);
+    let is_response = flags & 0x8000 != 0;
+    let opcode = (flags >> 11) & 0x0F;
+    let qdcount = u16::from_be_bytes([query[4], query[5]]);
+    if is_response || opcode != 0 || qdcount != 1 {
+        return None;
+    }
+
+    let mut idx = DNS_HEADER_LEN;
+    loop {
+        let len = *query.get(idx)? as usize;
+        idx += 1;
+        if len == 0 {
+            break;
+        }
+        // Compression pointers never appear in a query's only question.
+        if len & 0xC0 != 0 {
+            return None;
+        }
+        idx += len;
+    }
+    let question_end = idx + 4;
+    let question = query.get(DNS_HEADER_LEN..question_end)?;
+    let qtype = u16::from_be_bytes([query[idx], query[idx + 1]]);
+    let qclass = u16::from_be_bytes([query[idx + 2], query[idx + 3]]);
+    let answer = qclass == DNS_CLASS_IN && (qtype == DNS_TYPE_A || qtype == DNS_TYPE_ANY);
+
+    let total = question_end + if answer { DNS_ANSWER_LEN } else { 0 };
+    if out.len() < total {
+        return None;
+    }
+    out[0..2].copy_from_slice(&query[0..2]);
+    // QR + AA, keep the client's RD bit, NOERROR.
+    let reply_flags = 0x8400 | (flags & 0x0100);
+    out[2..4].copy_from_slice(&reply_flags.to_be_bytes());
+    out[4..6].copy_from_slice(&1u16.to_be_bytes());
+    out[6..8].copy_from_slice(&u16::from(answer).to_be_bytes());
+    out[8..12].fill(0);
+    out[DNS_HEADER_LEN..question_end].copy_from_slice(question);
+    if answer {
+        let rr = &mut out[question_end..total];
+        rr[0..2].copy_from_slice(&[0xC0, DNS_HEADER_LEN as u8]);
+        rr[2..4].copy_from_slice(&DNS_TYPE_A.to_be_bytes());
+        rr[4..6].copy_from_slice(&DNS_CLASS_IN.to_be_bytes());
+        rr[6..10].copy_from_slice(&DNS_ANSWER_TTL_S.to_be_bytes());
+        rr[10..12].copy_from_slice(&4u16.to_be_bytes());
+        rr[12..16].copy_from_slice(&ip);
+    }
+    Some(total)
+}
+
+/// Decode the value of `key` from a urlencoded form body into `out`.
+///
+/// `+` becomes a space and `%XX` escapes are decoded. Returns `Ok(None)`
+/// when the key is absent.
+pub fn form_value<'a>(body: &str, key: &str, out: &'a mut [u8]) -> Result<Option<&'a str>, Error> {
+    let Some(raw) = body.split('&').find_map(|pair| {
+        let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+        (name == key).then_some(value)
+    }) else {
+        return Ok(None);
+    };
+
+    let bytes = raw.as_bytes();
+    let mut len = 0;
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let byte = match bytes[idx] {
+            b'+' => b' ',
+            b'%' => {
+                let hi = bytes.get(idx + 1).and_then(|b| hex_value(*b));
+                let lo = bytes.get(idx + 2).and_then(|b| hex_value(*b));
+                let (Some(hi), Some(lo)) = (hi, lo) else {
+                    return Err(Error::Malformed);
+                };
+                idx += 2;
+                (hi << 4) | lo
+            }
+            other => other,
+        };
+        *out.get_mut(len).ok_or(Error::BufferTooSmall)? = byte;
+        len += 1;
+        idx += 1;
+    }
+    core::str::from_utf8(&out[..len])
+        .map(Some)
+        .map_err(|_| Error::Malformed)
+}
+
+fn hex_value(byte: u8) -> Option<u8> {
+    match byte {
+        b'0'..=b'9' => Some(byte - b'0'),
+        b'a'..=b'f' => Some(byte - b'a' + 10),
+        b'A'..=b'F' => Some(byte - b'A' + 10),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SERVER: [u8; 4] = [192, 168, 4, 1];
+    const MAC: [u8; 6] = [0x02, 0x11, 0x22, 0x33, 0x44, 0x55];
+
+    fn client_message(message_type: u8, requested: Option<[u8; 4]>) -> std::vec::Vec<u8> {
+        let mut buf = std::vec![0u8; DHCP_OPTIONS_OFFSET];
+        buf[0] = 1;
+        buf[1] = 1;
+        buf[2] = 6;
+        buf[4..8].copy_from_slice(&[0xDE, 0xAD, 0xBE, 0xEF]);
+        buf[10] = 0x80;
+        buf[28..34].copy_from_slice(&MAC);
+        buf[BOOTP_HEADER_LEN..DHCP_OPTIONS_OFFSET].copy_from_slice(&DHCP_MAGIC_COOKIE);
+        buf.extend_from_slice(&[OPTION_PAD, OPTION_MESSAGE_TYPE, 1, message_type]);
+        if let Some(ip) = requested {
+            buf.extend_from_slice(&[OPTION_REQUESTED_IP, 4]);
+            buf.extend_from_slice(&ip);
+        }
+        buf.extend_from_slice(&[12, 3, b'p', b'h', b'n', OPTION_END]);
+        buf
+    }
+
+    #[test]
+    fn dhcp_discover_gets_offer_with_portal_options() {
+        let request = parse_dhcp_request(&client_message(1, None)).unwrap();
+        assert_eq!(request.message_type, DhcpMessageType::Discover);
+        assert_eq!(request.client_mac, MAC);
+        assert_eq!(request.requested_ip, None);
+
+        let mut leases = Leases::<4>::new(SERVER);
+        let ip = leases.assign(request.client_mac);
+        assert_eq!(ip, [192, 168, 4, 2]);
+
+        let config = DhcpServerConfig {
+            server_ip: SERVER,
+            netmask: [255, 255, 255, 0],
+            lease_time_s: 3600,
+        };
+        let mut out = [0u8; 320];
+        let len =
+            encode_dhcp_reply(&mut out, &request, DhcpMessageType::Offer, ip, &config).unwrap();
+        assert_eq!(len, DHCP_REPLY_LEN);
+        assert_eq!(out[0], 2);
+        assert_eq!(&out[4..8], &[0xDE, 0xAD, 0xBE, 0xEF]);
+        assert_eq!(&out[10..12], &[0x80, 0]);
+        assert_eq!(&out[16..20], &ip);
+        assert_eq!(&out[28..34], &MAC);
+        assert_eq!(
+            &out[DHCP_OPTIONS_OFFSET..len],
+            &[
+                53, 1, 2, 54, 4, 192, 168, 4, 1, 51, 4, 0, 0, 14, 16, 1, 4, 255, 255, 255, 0, 3, 4,
+                192, 168, 4, 1, 6, 4, 192, 168, 4, 1, 255
+            ]
+        );
+
+        let request = parse_dhcp_request(&client_message(3, Some(ip))).unwrap();
+        assert_eq!(request.message_type, DhcpMessageType::Request);
+        assert_eq!(request.requested_ip, Some(ip));
+        assert_eq!(leases.lookup(request.client_mac), Some(ip));
+    }
+
+    #[test]
+    fn dhcp_rejects_non_requests() {
+        let mut reply = client_message(1, None);
+        reply[0] = 2;
+        assert_eq!(parse_dhcp_request(&reply), None);
+        let mut no_cookie = client_message(1, None);
+        no_cookie[BOOTP_HEADER_LEN] = 0;
+        assert_eq!(parse_dhcp_request(&no_cookie), None);
+        let truncated = client_message(1, None);
+        assert_eq!(
+            parse_dhcp_request(&truncated[..DHCP_OPTIONS_OFFSET + 2]),
+            None
+        );
+    }
+
+    #[test]
+    fn leases_reuse_oldest_slot_when_full() {
+        let mut leases = Leases::<2>::new(SERVER);
+        assert_eq!(leases.assign([1; 6]), [192, 168, 4, 2]);
+        assert_eq!(leases.assign([2; 6]), [192, 168, 4, 3]);
+        assert_eq!(leases.assign([1; 6]), [192, 168, 4, 2]);
+        assert_eq!(leases.assign([3; 6]), [192, 168, 4, 2]);
+        assert_eq!(leases.lookup([1; 6]), None);
+        leases.release([2; 6]);
+        assert_eq!(leases.assign([4; 6]), [192, 168, 4, 3]);
+    }
+
+    #[test]
+    fn dns_answers_a_queries_with_portal_address() {
+        let mut query = std::vec![0x12, 0x34, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0];
+        query.extend_from_slice(b"\x07example\x03com\x00");
+        query.extend_from_slice(&[0, 1, 0, 1]);
+        let mut out = [0u8; 128];
+        let len = encode_dns_redirect(&query, &mut out, SERVER).unwrap();
+        assert_eq!(len, query.len() + DNS_ANSWER_LEN);
+        assert_eq!(&out[..4], &[0x12, 0x34, 0x85, 0x00]);
+        assert_eq!(&out[4..8], &[0, 1, 0, 1]);
+        assert_eq!(&out[12..query.len()], &query[12..]);
+        assert_eq!(
+            &out[query.len()..len],
+            &[0xC0, 12, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4, 192, 168, 4, 1]
+        );
+
+        let aaaa_len = query.len();
+        query[aaaa_len - 3] = 28;
+        let len = encode_dns_redirect(&query, &mut out, SERVER).unwrap();
+        assert_eq!(len, query.len());
+        assert_eq!(&out[6..8], &[0, 0]);
+
+        query[2] = 0x81;
+        assert_eq!(encode_dns_redirect(&query, &mut out, SERVER), None);
+        assert_eq!(encode_dns_redirect(&query[..8], &mut out, SERVER), None);
+    }
+
+    #[test]
+    fn form_values_are_url_decoded() {
+        let body = "ssid=Lab+Wi%2DFi%20%E2%9C%93&psk=p%26ss%3Dword&empty=&flag";
+        let mut buf = [0u8; 64];
+        assert_eq!(form_value(body, "ssid", &mut buf), Ok(Some("Lab Wi-Fi ✓")));
+        assert_eq!(form_value(body, "psk", &mut buf), Ok(Some("p&ss=word")));
+        assert_eq!(form_value(body, "empty", &mut buf), Ok(Some("")));
+        assert_eq!(form_value(body, "flag", &mut buf), Ok(Some("")));
+        assert_eq!(form_value(body, "missing", &mut buf), Ok(None));
+        assert_eq!(
+            form_value("ssid=%zz", "ssid", &mut buf),
+            Err(Error::Malformed)
+        );
+        assert_eq!(
+            form_value("ssid=%FF", "ssid", &mut buf),
+            Err(Error::Malformed)
+        );
+        assert_eq!(
+            form_value("ssid=toolong", "ssid", &mut buf[..3]),
+            Err(Error::BufferTooSmall)
+        );
+    }
+}
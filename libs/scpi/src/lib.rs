@@ -0,0 +1,941 @@
+//! SCPI-99 command parser and error queue for the digital firmware's TCP port 5025.
+
+#![no_std]
+
+#[cfg(test)]
+extern crate std;
+
+use core::fmt::{self, Write};
+
+/// Longest accepted header path (`SOUR:CURR:LEV:IMM:AMPL` + one spare).
+const MAX_NODES: usize = 6;
+/// Longest accepted `SYSTem:AUTHenticate` token.
+pub const MAX_TOKEN_LEN: usize = 64;
+/// Mantissa digits kept before a number is treated as out of range.
+const MAX_MANTISSA_DIGITS: u32 = 18;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Quantity {
+    Voltage,
+    Current,
+    Power,
+}
+
+impl Quantity {
+    /// Base unit suffix (`V` / `A` / `W`).
+    pub const fn unit(self) -> &'static str {
+        match self {
+            Quantity::Voltage => "V",
+            Quantity::Current => "A",
+            Quantity::Power => "W",
+        }
+    }
+}
+
+/// Regulation mode selected by `[SOURce:]FUNCtion`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Function {
+    Cc,
+    Cv,
+    Cp,
+}
+
+impl Function {
+    /// `FUNCtion?` response token.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Function::Cc => "CC",
+            Function::Cv => "CV",
+            Function::Cp => "CP",
+        }
+    }
+}
+
+/// Setpoint argument: milli-units of the quantity (mA / mV / mW) or one of
+/// the SCPI keywords, resolved by the firmware against the active limits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Level {
+    Milli(i64),
+    Min,
+    Max,
+    Default,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command<'a> {
+    /// `*IDN?`
+    Identify,
+    /// `*RST`
+    Reset,
+    /// `*CLS`
+    Clear,
+    /// `*OPC`
+    OperationComplete,
+    /// `*OPC?`
+    OperationCompleteQuery,
+    /// `*RCL <n>`: recall (activate) preset `n`.
+    Recall(u8),
+    /// `SYSTem:ERRor[:NEXT]?`
+    ErrorQuery,
+    /// `SYSTem:VERSion?`
+    VersionQuery,
+    /// `SYSTem:AUTHenticate <token>`, quoted or bare.
+    Authenticate(&'a str),
+    /// `SYSTem:AUTHenticate?`: role granted to this connection.
+    AuthQuery,
+    /// `{MEASure|FETCh}[:SCALar]:{VOLTage|CURRent|POWer}[:DC]?`
+    Measure(Quantity),
+    /// `[SOURce:]{CURRent|VOLTage|POWer}[:LEVel][:IMMediate][:AMPLitude] <level>`
+    SetLevel(Quantity, Level),
+    /// Query form of [`Command::SetLevel`].
+    LevelQuery(Quantity),
+    /// `[SOURce:]FUNCtion[:MODE] CC|CV|CP`
+    SetFunction(Function),
+    FunctionQuery,
+    /// `INPut[:STATe] ON|OFF|1|0`
+    SetInput(bool),
+    InputQuery,
+}
+
+/// SCPI-99 / IEEE 488.2 error numbers used by this instrument.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    Command,
+    Syntax,
+    DataType,
+    ParameterNotAllowed,
+    MissingParameter,
+    UndefinedHeader,
+    InvalidSuffix,
+    Execution,
+    CommandProtected,
+    SettingsConflict,
+    DataOutOfRange,
+    DataStale,
+    QueueOverflow,
+}
+
+impl Error {
+    pub const fn code(self) -> i16 {
+        match self {
+            Error::Command => -100,
+            Error::Syntax => -102,
+            Error::DataType => -104,
+            Error::ParameterNotAllowed => -108,
+            Error::MissingParameter => -109,
+            Error::UndefinedHeader => -113,
+            Error::InvalidSuffix => -131,
+            Error::Execution => -200,
+            Error::CommandProtected => -203,
+            Error::SettingsConflict => -221,
+            Error::DataOutOfRange => -222,
+            Error::DataStale => -230,
+            Error::QueueOverflow => -350,
+        }
+    }
+
+    pub const fn message(self) -> &'static str {
+        match self {
+            Error::Command => "Command error",
+            Error::Syntax => "Syntax error",
+            Error::DataType => "Data type error",
+            Error::ParameterNotAllowed => "Parameter not allowed",
+            Error::MissingParameter => "Missing parameter",
+            Error::UndefinedHeader => "Undefined header",
+            Error::InvalidSuffix => "Invalid suffix",
+            Error::Execution => "Execution error",
+            Error::CommandProtected => "Command protected",
+            Error::SettingsConflict => "Settings conflict",
+            Error::DataOutOfRange => "Data out of range",
+            Error::DataStale => "Data corrupt or stale",
+            Error::QueueOverflow => "Queue overflow",
+        }
+    }
+}
+
+// ---- Header table ----------------------------------------------------------
+
+#[derive(Clone, Copy)]
+struct Node {
+    keyword: &'static str,
+    optional: bool,
+}
+
+const fn req(keyword: &'static str) -> Node {
+    Node {
+        keyword,
+        optional: false,
+    }
+}
+
+const fn opt(keyword: &'static str) -> Node {
+    Node {
+        keyword,
+        optional: true,
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Header {
+    Measure(Quantity),
+    Level(Quantity),
+    Function,
+    Input,
+    SystemError,
+    SystemVersion,
+    SystemAuth,
+}
+
+const HEADERS: &[(&[Node], Header)] = &[
+    (
+        &[req("MEASure"), opt("SCALar"), req("VOLTage"), opt("DC")],
+        Header::Measure(Quantity::Voltage),
+    ),
+    (
+        &[req("MEASure"), opt("SCALar"), req("CURRent"), opt("DC")],
+        Header::Measure(Quantity::Current),
+    ),
+    (
+        &[req("MEASure"), opt("SCALar"), req("POWer"), opt("DC")],
+        Header::Measure(Quantity::Power),
+    ),
+    (
+        &[req("FETCh"), opt("SCALar"), req("VOLTage"), opt("DC")],
+        Header::Measure(Quantity::Voltage),
+    ),
+    (
+        &[req("FETCh"), opt("SCALar"), req("CURRent"), opt("DC")],
+        Header::Measure(Quantity::Current),
+    ),
+    (
+        &[req("FETCh"), opt("SCALar"), req("POWer"), opt("DC")],
+        Header::Measure(Quantity::Power),
+    ),
+    (
+        &[
+            opt("SOURce"),
+            req("CURRent"),
+            opt("LEVel"),
+            opt("IMMediate"),
+            opt("AMPLitude"),
+        ],
+        Header::Level(Quantity::Current),
+    ),
+    (
+        &[
+            opt("SOURce"),
+            req("VOLTage"),
+            opt("LEVel"),
+            opt("IMMediate"),
+            opt("AMPLitude"),
+        ],
+        Header::Level(Quantity::Voltage),
+    ),
+    (
+        &[
+            opt("SOURce"),
+            req("POWer"),
+            opt("LEVel"),
+            opt("IMMediate"),
+            opt("AMPLitude"),
+        ],
+        Header::Level(Quantity::Power),
+    ),
+    (
+        &[opt("SOURce"), req("FUNCtion"), opt("MODE")],
+        Header::Function,
+    ),
+    (&[req("INPut"), opt("STATe")], Header::Input),
+    (
+        &[req("SYSTem"), req("ERRor"), opt("NEXT")],
+        Header::SystemError,
+    ),
+    (&[req("SYSTem"), req("VERSion")], Header::SystemVersion),
+    (&[req("SYSTem"), req("AUTHenticate")], Header::SystemAuth),
+];
+
+/// `CURRent` matches `CURR` and `CURRENT` in any case.
+fn keyword_matches(keyword: &str, input: &str) -> bool {
+    let short_len = keyword
+        .bytes()
+        .take_while(|b| !b.is_ascii_lowercase())
+        .count();
+    input.eq_ignore_ascii_case(keyword) || input.eq_ignore_ascii_case(&keyword[..short_len])
+}
+
+fn nodes_match(pattern: &[Node], input: &[&str]) -> bool {
+    let Some((node, rest)) = pattern.split_first() else {
+        return input.is_empty();
+    };
+    if let Some((first, tail)) = input.split_first()
+        && keyword_matches(node.keyword, first)
+        && nodes_match(rest, tail)
+    {
+        return true;
+    }
+    node.optional && nodes_match(rest, input)
+}
+
+fn lookup_header(nodes: &[&str]) -> Option<Header> {
+    HEADERS
+        .iter()
+        .find(|(pattern, _)| nodes_match(pattern, nodes))
+        .map(|(_, header)| *header)
+}
+
+// ---- Program message splitting ----------------------------------------------
+
+/// Iterator over the commands of one program message; see [`parse_program`].
+pub struct Program<'a> {
+    rest: Option<&'a str>,
+    path: [&'a str; MAX_NODES],
+    path_len: usize,
+}
+
+/// Parse one program message (a line without its terminator). Each message
+/// unit yields a command or the error to queue for it; empty units are
+/// skipped.
+pub fn parse_program(line: &str) -> Program<'_> {
+    Program {
+        rest: Some(line),
+        path: [""; MAX_NODES],
+        path_len: 0,
+    }
+}
+
+impl<'a> Iterator for Program<'a> {
+    type Item = Result<Command<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let rest = self.rest?;
+            let (unit, tail) = match rest.split_once(';') {
+                Some((unit, tail)) => (unit, Some(tail)),
+                None => (rest, None),
+            };
+            self.rest = tail;
+            let unit = unit.trim();
+            if !unit.is_empty() {
+                return Some(self.parse_unit(unit));
+            }
+        }
+    }
+}
+
+impl<'a> Program<'a> {
+    fn parse_unit(&mut self, unit: &'a str) -> Result<Command<'a>, Error> {
+        let (header, params) = match unit.find(|c: char| c.is_ascii_whitespace()) {
+            Some(idx) => (&unit[..idx], unit[idx..].trim()),
+            None => (unit, ""),
+        };
+        if header.starts_with('*') {
+            return parse_common(header, params);
+        }
+
+        let (header, query) = match header.strip_suffix('?') {
+            Some(h) => (h, true),
+            None => (header, false),
+        };
+        let (header, absolute) = match header.strip_prefix(':') {
+            Some(h) => (h, true),
+            None => (header, false),
+        };
+
+        let mut nodes = [""; MAX_NODES];
+        let mut len = 0;
+        if !absolute {
+            nodes[..self.path_len].copy_from_slice(&self.path[..self.path_len]);
+            len = self.path_len;
+        }
+        for node in header.split(':') {
+            if node.is_empty() || !node.bytes().all(|b| b.is_ascii_alphanumeric()) {
+                return Err(Error::Syntax);
+            }
+            if len == MAX_NODES {
+                return Err(Error::UndefinedHeader);
+            }
+            nodes[len] = node;
+            len += 1;
+        }
+        // The next relative unit starts from this header minus its leaf.
+        self.path = nodes;
+        self.path_len = len - 1;
+
+        let header = lookup_header(&nodes[..len]).ok_or(Error::UndefinedHeader)?;
+        parse_params(header, query, params)
+    }
+}
+
+fn parse_common(header: &str, params: &str) -> Result<Command<'static>, Error> {
+    let command = match () {
+        _ if header.eq_ignore_ascii_case("*IDN?") => Command::Identify,
+        _ if header.eq_ignore_ascii_case("*RST") => Command::Reset,
+        _ if header.eq_ignore_ascii_case("*CLS") => Command::Clear,
+        _ if header.eq_ignore_ascii_case("*OPC") => Command::OperationComplete,
+        _ if header.eq_ignore_ascii_case("*OPC?") => Command::OperationCompleteQuery,
+        _ if header.eq_ignore_ascii_case("*RCL") => {
+            let value = parse_unitless(require_param(params)?)?;
+            let slot = u8::try_from(value).map_err(|_| Error::DataOutOfRange)?;
+            return Ok(Command::Recall(slot));
+        }
+        _ => return Err(Error::UndefinedHeader),
+    };
+    if params.is_empty() {
+        Ok(command)
+    } else {
+        Err(Error::ParameterNotAllowed)
+    }
+}
+
+fn parse_params(header: Header, query: bool, params: &str) -> Result<Command<'_>, Error> {
+    if query {
+        if !params.is_empty() {
+            return Err(Error::ParameterNotAllowed);
+        }
+        return Ok(match header {
+            Header::Measure(q) => Command::Measure(q),
+            Header::Level(q) => Command::LevelQuery(q),
+            Header::Function => Command::FunctionQuery,
+            Header::Input => Command::InputQuery,
+            Header::SystemError => Command::ErrorQuery,
+            Header::SystemVersion => Command::VersionQuery,
+            Header::SystemAuth => Command::AuthQuery,
+        });
+    }
+
+    match header {
+        Header::Level(q) => Ok(Command::SetLevel(
+            q,
+            parse_level(require_param(params)?, q)?,
+        )),
+        Header::Function => Ok(Command::SetFunction(parse_function(require_param(
+            params,
+        )?)?)),
+        Header::Input => Ok(Command::SetInput(parse_bool(require_param(params)?)?)),
+        Header::SystemAuth => Ok(Command::Authenticate(parse_token(require_param(params)?)?)),
+        // Query-only headers.
+        Header::Measure(_) | Header::SystemError | Header::SystemVersion => {
+            Err(Error::UndefinedHeader)
+        }
+    }
+}
+
+/// Exactly one parameter.
+fn require_param(params: &str) -> Result<&str, Error> {
+    if params.is_empty() {
+        return Err(Error::MissingParameter);
+    }
+    if params.contains(',') {
+        return Err(Error::ParameterNotAllowed);
+    }
+    Ok(params)
+}
+
+// ---- Parameters ---------------------------------------------------------------
+
+fn parse_level(param: &str, quantity: Quantity) -> Result<Level, Error> {
+    if keyword_matches("MINimum", param) {
+        return Ok(Level::Min);
+    }
+    if keyword_matches("MAXimum", param) {
+        return Ok(Level::Max);
+    }
+    if keyword_matches("DEFault", param) {
+        return Ok(Level::Default);
+    }
+
+    let (mantissa, exp10, suffix) = parse_decimal(param)?;
+    let unit = quantity.unit();
+    let suffix_exp10 = if suffix.is_empty() || suffix.eq_ignore_ascii_case(unit) {
+        0
+    } else if suffix.len() == unit.len() + 1 && suffix[1..].eq_ignore_ascii_case(unit) {
+        match suffix.as_bytes()[0].to_ascii_uppercase() {
+            b'K' => 3,
+            b'M' => -3,
+            b'U' => -6,
+            _ => return Err(Error::InvalidSuffix),
+        }
+    } else {
+        return Err(Error::InvalidSuffix);
+    };
+    Ok(Level::Milli(scale(mantissa, exp10 + suffix_exp10 + 3)?))
+}
+
+fn parse_function(param: &str) -> Result<Function, Error> {
+    let param = param.trim_matches('"');
+    if param.eq_ignore_ascii_case("CC") || keyword_matches("CURRent", param) {
+        Ok(Function::Cc)
+    } else if param.eq_ignore_ascii_case("CV") || keyword_matches("VOLTage", param) {
+        Ok(Function::Cv)
+    } else if param.eq_ignore_ascii_case("CP") || keyword_matches("POWer", param) {
+        Ok(Function::Cp)
+    } else {
+        Err(Error::DataType)
+    }
+}
+
+/// Bare or double-quoted run of ASCII letters and digits.
+fn parse_token(param: &str) -> Result<&str, Error> {
+    let token = match param.strip_prefix('"') {
+        Some(quoted) => quoted.strip_suffix('"').ok_or(Error::Syntax)?,
+        None => param,
+    };
+    if token.is_empty()
+        || token.len() > MAX_TOKEN_LEN
+        || !token.bytes().all(|b| b.is_ascii_alphanumeric())
+    {
+        return Err(Error::DataType);
+    }
+    Ok(token)
+}
+
+/// `ON|OFF` or a number, which is rounded and true when non-zero.
+fn parse_bool(param: &str) -> Result<bool, Error> {
+    if param.eq_ignore_ascii_case("ON") {
+        return Ok(true);
+    }
+    if param.eq_ignore_ascii_case("OFF") {
+        return Ok(false);
+    }
+    Ok(parse_unitless(param)? != 0)
+}
+
+/// Number without suffix, rounded to an integer.
+fn parse_unitless(param: &str) -> Result<i64, Error> {
+    let (mantissa, exp10, suffix) = parse_decimal(param)?;
+    if !suffix.is_empty() {
+        return Err(Error::InvalidSuffix);
+    }
+    scale(mantissa, exp10)
+}
+
+/// `[+-]digits[.digits][E[+-]digits]` followed by an optional suffix; the
+/// value is `mantissa × 10^exp10`.
+fn parse_decimal(param: &str) -> Result<(i64, i32, &str), Error> {
+    let bytes = param.as_bytes();
+    let mut idx = 0;
+    let negative = match bytes.first() {
+        Some(b'-') => {
+            idx += 1;
+            true
+        }
+        Some(b'+') => {
+            idx += 1;
+            false
+        }
+        _ => false,
+    };
+
+    let mut mantissa: i64 = 0;
+    let mut digits = 0u32;
+    let mut exp10: i32 = 0;
+    let mut seen_point = false;
+    let mut seen_digit = false;
+    while idx < bytes.len() {
+        match bytes[idx] {
+            b'0'..=b'9' => {
+                let digit = (bytes[idx] - b'0') as i64;
+                seen_digit = true;
+                if digits < MAX_MANTISSA_DIGITS {
+                    mantissa = mantissa * 10 + digit;
+                    if mantissa != 0 {
+                        digits += 1;
+                    }
+                    if seen_point {
+                        exp10 -= 1;
+                    }
+                } else if !seen_point {
+                    // Beyond i64 precision: keep magnitude, drop digits.
+                    exp10 += 1;
+                }
+                idx += 1;
+            }
+            b'.' if !seen_point => {
+                seen_point = true;
+                idx += 1;
+            }
+            _ => break,
+        }
+    }
+    if !seen_digit {
+        return Err(Error::DataType);
+    }
+
+    // Exponent only when digits follow, so `5MA` keeps its suffix.
+    if idx < bytes.len() && bytes[idx].eq_ignore_ascii_case(&b'E') {
+        let mut exp_idx = idx + 1;
+        let exp_negative = match bytes.get(exp_idx) {
+            Some(b'-') => {
+                exp_idx += 1;
+                true
+            }
+            Some(b'+') => {
+                exp_idx += 1;
+                false
+            }
+            _ => false,
+        };
+        let start = exp_idx;
+        let mut exponent: i32 = 0;
+        while let Some(b) = bytes.get(exp_idx).filter(|b| b.is_ascii_digit()) {
+            exponent = (exponent * 10 + (b - b'0') as i32).min(1_000);
+            exp_idx += 1;
+        }
+        if exp_idx > start {
+            exp10 += if exp_negative { -exponent } else { exponent };
+            idx = exp_idx;
+        }
+    }
+
+    let suffix = param[idx..].trim_start();
+    if !suffix.bytes().all(|b| b.is_ascii_alphabetic()) {
+        return Err(Error::Syntax);
+    }
+    Ok((if negative { -mantissa } else { mantissa }, exp10, suffix))
+}
+
+/// `mantissa × 10^exp10` rounded half away from zero.
+fn scale(mantissa: i64, exp10: i32) -> Result<i64, Error> {
+    if mantissa == 0 {
+        return Ok(0);
+    }
+    if exp10 >= 0 {
+        let factor = 10i64
+            .checked_pow(exp10 as u32)
+            .ok_or(Error::DataOutOfRange)?;
+        return mantissa.checked_mul(factor).ok_or(Error::DataOutOfRange);
+    }
+    let Some(divisor) = 10i64.checked_pow(exp10.unsigned_abs()) else {
+        return Ok(0);
+    };
+    let half = divisor / 2;
+    Ok(if mantissa < 0 {
+        -((-mantissa + half) / divisor)
+    } else {
+        (mantissa + half) / divisor
+    })
+}
+
+// ---- Error queue ----------------------------------------------------------------
+
+pub const ERROR_QUEUE_LEN: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ErrorEntry {
+    pub error: Error,
+    /// Device-dependent detail appended after `;` in the error string.
+    pub detail: Option<&'static str>,
+}
+
+/// `SYSTem:ERRor?` FIFO. When full, the newest entry is replaced by
+/// `-350 Queue overflow` and further errors are dropped.
+#[derive(Clone, Debug)]
+pub struct ErrorQueue {
+    entries: [Option<ErrorEntry>; ERROR_QUEUE_LEN],
+    len: usize,
+}
+
+impl Default for ErrorQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ErrorQueue {
+    pub const fn new() -> Self {
+        Self {
+            entries: [None; ERROR_QUEUE_LEN],
+            len: 0,
+        }
+    }
+
+    pub fn push(&mut self, error: Error) {
+        self.push_entry(ErrorEntry {
+            error,
+            detail: None,
+        });
+    }
+
+    pub fn push_detail(&mut self, error: Error, detail: &'static str) {
+        self.push_entry(ErrorEntry {
+            error,
+            detail: Some(detail),
+        });
+    }
+
+    fn push_entry(&mut self, entry: ErrorEntry) {
+        if self.len < ERROR_QUEUE_LEN {
+            self.entries[self.len] = Some(entry);
+            self.len += 1;
+        } else {
+            self.entries[ERROR_QUEUE_LEN - 1] = Some(ErrorEntry {
+                error: Error::QueueOverflow,
+                detail: None,
+            });
+        }
+    }
+
+    /// Oldest entry, or `None` for `0,"No error"`.
+    pub fn pop(&mut self) -> Option<ErrorEntry> {
+        if self.len == 0 {
+            return None;
+        }
+        let entry = self.entries[0];
+        self.entries.copy_within(1..self.len, 0);
+        self.len -= 1;
+        self.entries[self.len] = None;
+        entry
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+// ---- Response formatting ----------------------------------------------------------
+
+/// `-113,"Undefined header"` (with `;detail`), or `0,"No error"` for `None`.
+pub fn write_error<W: Write>(out: &mut W, entry: Option<ErrorEntry>) -> fmt::Result {
+    let Some(entry) = entry else {
+        return out.write_str("0,\"No error\"");
+    };
+    write!(out, "{},\"{}", entry.error.code(), entry.error.message())?;
+    if let Some(detail) = entry.detail {
+        write!(out, ";{}", detail)?;
+    }
+    out.write_char('"')
+}
+
+/// Milli-units as a decimal with three fractional digits (`1500` → `1.500`).
+pub fn write_milli<W: Write>(out: &mut W, milli: i64) -> fmt::Result {
+    if milli < 0 {
+        out.write_char('-')?;
+    }
+    let abs = milli.unsigned_abs();
+    write!(out, "{}.{:03}", abs / 1_000, abs % 1_000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::string::String;
+    use std::vec::Vec;
+
+    fn parse(line: &str) -> Vec<Result<Command<'_>, Error>> {
+        parse_program(line).collect()
+    }
+
+    #[test]
+    fn common_commands_and_short_long_forms() {
+        assert_eq!(
+            parse("*idn?;*RST;*cls;*OPC;*OPC?"),
+            [
+                Ok(Command::Identify),
+                Ok(Command::Reset),
+                Ok(Command::Clear),
+                Ok(Command::OperationComplete),
+                Ok(Command::OperationCompleteQuery),
+            ]
+        );
+        assert_eq!(parse("*RCL 3"), [Ok(Command::Recall(3))]);
+        assert_eq!(parse("*RCL"), [Err(Error::MissingParameter)]);
+        assert_eq!(parse("*RCL 300"), [Err(Error::DataOutOfRange)]);
+        assert_eq!(parse("*IDN? 1"), [Err(Error::ParameterNotAllowed)]);
+        assert_eq!(parse("*TRG"), [Err(Error::UndefinedHeader)]);
+
+        for line in [
+            "MEAS:VOLT?",
+            "measure:voltage?",
+            ":MEAS:SCAL:VOLT:DC?",
+            "FETC:VOLT?",
+        ] {
+            assert_eq!(
+                parse(line),
+                [Ok(Command::Measure(Quantity::Voltage))],
+                "{line}"
+            );
+        }
+        assert_eq!(parse("MEAS:VOLTA?"), [Err(Error::UndefinedHeader)]);
+        assert_eq!(parse("MEAS:VOLT"), [Err(Error::UndefinedHeader)]);
+        assert_eq!(parse("SYST:ERR?"), [Ok(Command::ErrorQuery)]);
+        assert_eq!(parse("SYSTEM:ERROR:NEXT?"), [Ok(Command::ErrorQuery)]);
+        assert_eq!(parse("SYST:VERS?"), [Ok(Command::VersionQuery)]);
+    }
+
+    #[test]
+    fn levels_take_suffixes_and_keywords() {
+        let current = |level| Ok(Command::SetLevel(Quantity::Current, level));
+        assert_eq!(parse("CURR 1.5"), [current(Level::Milli(1_500))]);
+        assert_eq!(
+            parse("SOUR:CURR:LEV:IMM 250mA"),
+            [current(Level::Milli(250))]
+        );
+        assert_eq!(parse("CURR 2.5E-1 A"), [current(Level::Milli(250))]);
+        assert_eq!(parse("CURR 5MA"), [current(Level::Milli(5))]);
+        assert_eq!(parse("CURR 1500uA"), [current(Level::Milli(2))]);
+        assert_eq!(parse("CURR MAX"), [current(Level::Max)]);
+        assert_eq!(parse("CURR minimum"), [current(Level::Min)]);
+        assert_eq!(parse("CURR DEF"), [current(Level::Default)]);
+        assert_eq!(parse("CURR 1V"), [Err(Error::InvalidSuffix)]);
+        assert_eq!(parse("CURR"), [Err(Error::MissingParameter)]);
+        assert_eq!(parse("CURR 1,2"), [Err(Error::ParameterNotAllowed)]);
+        assert_eq!(parse("CURR abc"), [Err(Error::DataType)]);
+        assert_eq!(parse("CURR 1e30"), [Err(Error::DataOutOfRange)]);
+
+        assert_eq!(
+            parse("VOLT 12.3456"),
+            [Ok(Command::SetLevel(
+                Quantity::Voltage,
+                Level::Milli(12_346)
+            ))]
+        );
+        assert_eq!(
+            parse("POW 0.1KW"),
+            [Ok(Command::SetLevel(
+                Quantity::Power,
+                Level::Milli(100_000)
+            ))]
+        );
+        assert_eq!(
+            parse("VOLT -0.0005"),
+            [Ok(Command::SetLevel(Quantity::Voltage, Level::Milli(-1)))]
+        );
+        assert_eq!(parse("CURR?"), [Ok(Command::LevelQuery(Quantity::Current))]);
+        assert_eq!(parse("CURR? 1"), [Err(Error::ParameterNotAllowed)]);
+    }
+
+    #[test]
+    fn function_and_input_parameters() {
+        assert_eq!(parse("FUNC CV"), [Ok(Command::SetFunction(Function::Cv))]);
+        assert_eq!(
+            parse("SOUR:FUNC:MODE curr"),
+            [Ok(Command::SetFunction(Function::Cc))]
+        );
+        assert_eq!(
+            parse("FUNC \"POW\""),
+            [Ok(Command::SetFunction(Function::Cp))]
+        );
+        assert_eq!(parse("FUNC CR"), [Err(Error::DataType)]);
+        assert_eq!(parse("FUNC?"), [Ok(Command::FunctionQuery)]);
+
+        assert_eq!(parse("INP ON"), [Ok(Command::SetInput(true))]);
+        assert_eq!(parse("INPUT:STATE off"), [Ok(Command::SetInput(false))]);
+        assert_eq!(parse("INP 1"), [Ok(Command::SetInput(true))]);
+        assert_eq!(parse("INP 0.4"), [Ok(Command::SetInput(false))]);
+        assert_eq!(parse("INP 1V"), [Err(Error::InvalidSuffix)]);
+        assert_eq!(parse("INP?"), [Ok(Command::InputQuery)]);
+    }
+
+    #[test]
+    fn authenticate_takes_one_token() {
+        let token = "00112233445566778899aabbccddeeff";
+        assert_eq!(
+            parse("SYST:AUTH 00112233445566778899aabbccddeeff"),
+            [Ok(Command::Authenticate(token))]
+        );
+        assert_eq!(
+            parse("system:authenticate \"00112233445566778899aabbccddeeff\";:INP ON"),
+            [
+                Ok(Command::Authenticate(token)),
+                Ok(Command::SetInput(true))
+            ]
+        );
+        assert_eq!(parse("SYST:AUTH?"), [Ok(Command::AuthQuery)]);
+        assert_eq!(parse("SYST:AUTH"), [Err(Error::MissingParameter)]);
+        assert_eq!(parse("SYST:AUTH \"abc"), [Err(Error::Syntax)]);
+        assert_eq!(parse("SYST:AUTH ab-cd"), [Err(Error::DataType)]);
+        assert_eq!(parse("SYST:AUTH a,b"), [Err(Error::ParameterNotAllowed)]);
+    }
+
+    #[test]
+    fn compound_units_follow_the_header_path() {
+        assert_eq!(
+            parse("MEAS:VOLT?;CURR?;:MEAS:POW?"),
+            [
+                Ok(Command::Measure(Quantity::Voltage)),
+                Ok(Command::Measure(Quantity::Current)),
+                Ok(Command::Measure(Quantity::Power)),
+            ]
+        );
+        assert_eq!(
+            parse("SOUR:CURR 1;VOLT 5;*OPC?;POW 10"),
+            [
+                Ok(Command::SetLevel(Quantity::Current, Level::Milli(1_000))),
+                Ok(Command::SetLevel(Quantity::Voltage, Level::Milli(5_000))),
+                Ok(Command::OperationCompleteQuery),
+                Ok(Command::SetLevel(Quantity::Power, Level::Milli(10_000))),
+            ]
+        );
+        // MEAS:VOLT? leaves the path at MEAS, so INP? is MEAS:INP?.
+        assert_eq!(
+            parse("MEAS:VOLT?;INP?;:INP?"),
+            [
+                Ok(Command::Measure(Quantity::Voltage)),
+                Err(Error::UndefinedHeader),
+                Ok(Command::InputQuery),
+            ]
+        );
+        assert_eq!(parse("  ;; "), []);
+        assert_eq!(parse("MEAS::VOLT?"), [Err(Error::Syntax)]);
+    }
+
+    #[test]
+    fn error_queue_is_fifo_with_overflow_marker() {
+        let mut queue = ErrorQueue::new();
+        assert_eq!(queue.pop(), None);
+        queue.push(Error::UndefinedHeader);
+        queue.push_detail(Error::SettingsConflict, "link down");
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue.pop().map(|e| e.error), Some(Error::UndefinedHeader));
+        assert_eq!(
+            queue.pop(),
+            Some(ErrorEntry {
+                error: Error::SettingsConflict,
+                detail: Some("link down"),
+            })
+        );
+        assert!(queue.is_empty());
+
+        for _ in 0..ERROR_QUEUE_LEN + 3 {
+            queue.push(Error::Syntax);
+        }
+        assert_eq!(queue.len(), ERROR_QUEUE_LEN);
+        let errors: Vec<Error> = core::iter::from_fn(|| queue.pop().map(|e| e.error)).collect();
+        assert_eq!(errors[ERROR_QUEUE_LEN - 2], Error::Syntax);
+        assert_eq!(errors[ERROR_QUEUE_LEN - 1], Error::QueueOverflow);
+    }
+
+    #[test]
+    fn response_formatting() {
+        let mut out = String::new();
+        write_error(&mut out, None).unwrap();
+        out.push('|');
+        write_error(
+            &mut out,
+            Some(ErrorEntry {
+                error: Error::SettingsConflict,
+                detail: Some("analog faulted"),
+            }),
+        )
+        .unwrap();
+        assert_eq!(
+            out,
+            "0,\"No error\"|-221,\"Settings conflict;analog faulted\""
+        );
+
+        out.clear();
+        for milli in [1_500, 0, -5, 12_345_678] {
+            write_milli(&mut out, milli).unwrap();
+            out.push(' ');
+        }
+        assert_eq!(out, "1.500 0.000 -0.005 12345.678 ");
+    }
+}
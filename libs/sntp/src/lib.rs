@@ -0,0 +1,272 @@
+//! SNTP (RFC 4330) packet helpers and RFC3339 formatting.
+
+#![no_std]
+
+#[cfg(test)]
+extern crate std;
+
+pub const NTP_PORT: u16 = 123;
+pub const PACKET_LEN: usize = 48;
+/// Length of [`format_rfc3339`] output.
+pub const RFC3339_LEN: usize = 24;
+/// 2024-01-01T00:00:00Z; anything earlier is treated as a bogus clock.
+pub const PLAUSIBLE_UNIX_MS_MIN: u64 = 1_704_067_200_000;
+
+/// Seconds between the NTP epoch (1900) and the Unix epoch (1970).
+const NTP_UNIX_OFFSET_S: u64 = 2_208_988_800;
+const VERSION: u8 = 4;
+const MODE_CLIENT: u8 = 3;
+const MODE_SERVER: u8 = 4;
+const LEAP_UNSYNCHRONIZED: u8 = 3;
+const REFERENCE_OFFSET: usize = 16;
+const ORIGIN_OFFSET: usize = 24;
+const RECEIVE_OFFSET: usize = 32;
+const TRANSMIT_OFFSET: usize = 40;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// Too short, or not a server reply to a client request.
+    Malformed,
+    /// Leap indicator "alarm" or stratum 0 (kiss-o'-death).
+    Unsynchronized,
+    /// The reply does not echo our transmit timestamp.
+    OriginMismatch,
+}
+
+/// Server timestamps from a validated reply, as Unix milliseconds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Reply {
+    pub stratum: u8,
+    pub receive_unix_ms: u64,
+    pub transmit_unix_ms: u64,
+}
+
+/// One request/reply exchange reduced to the RFC 4330 offset and delay.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sample {
+    /// Add to the local clock to get Unix milliseconds.
+    pub offset_ms: i64,
+    pub delay_ms: i64,
+}
+
+impl Sample {
+    /// `sent_local_ms` / `received_local_ms` are the caller's clock (e.g.
+    /// uptime) when the request left and the reply arrived.
+    pub fn new(sent_local_ms: u64, reply: &Reply, received_local_ms: u64) -> Self {
+        let t1 = sent_local_ms as i64;
+        let t2 = reply.receive_unix_ms as i64;
+        let t3 = reply.transmit_unix_ms as i64;
+        let t4 = received_local_ms as i64;
+        Self {
+            offset_ms: ((t2 - t1) + (t3 - t4)) / 2,
+            delay_ms: ((t4 - t1) - (t3 - t2)).max(0),
+        }
+    }
+}
+
+/// 64-bit NTP timestamp (seconds since 1900 in the high half).
+pub fn unix_ms_to_ntp(unix_ms: u64) -> u64 {
+    let secs = (unix_ms / 1000 + NTP_UNIX_OFFSET_S) as u32;
+    // Round the fraction up so `ntp_to_unix_ms` truncates back to the same ms.
+    let frac = (((unix_ms % 1000) << 32).div_ceil(1000)) as u32;
+    ((secs as u64) << 32) | frac as u64
+}
+
+/// Inverse of [`unix_ms_to_ntp`]. Seconds with the top bit clear are read as
+/// NTP era 1 (2036..2104), per RFC 4330 §3.
+pub fn ntp_to_unix_ms(ntp: u64) -> u64 {
+    let secs = ntp >> 32;
+    let secs = if secs & 0x8000_0000 != 0 {
+        secs
+    } else {
+        secs + (1 << 32)
+    };
+    let frac_ms = ((ntp & 0xFFFF_FFFF) * 1000) >> 32;
+    (secs - NTP_UNIX_OFFSET_S) * 1000 + frac_ms
+}
+
+/// Client request carrying `transmit` as its transmit timestamp; the server
+/// echoes it back as the origin, which [`parse_reply`] checks. Any unique
+/// value works, so callers without a wall clock may pass a random nonce.
+pub fn encode_request(out: &mut [u8; PACKET_LEN], transmit: u64) {
+    out.fill(0);
+    out[0] = (VERSION << 3) | MODE_CLIENT;
+    out[TRANSMIT_OFFSET..TRANSMIT_OFFSET + 8].copy_from_slice(&transmit.to_be_bytes());
+}
+
+pub fn parse_reply(reply: &[u8], transmit: u64) -> Result<Reply, Error> {
+    if reply.len() < PACKET_LEN {
+        return Err(Error::Malformed);
+    }
+    let leap = reply[0] >> 6;
+    let version = (reply[0] >> 3) & 0x07;
+    let mode = reply[0] & 0x07;
+    if mode != MODE_SERVER || !(1..=VERSION).contains(&version) {
+        return Err(Error::Malformed);
+    }
+    let stratum = reply[1];
+    if leap == LEAP_UNSYNCHRONIZED || stratum == 0 || stratum > 15 {
+        return Err(Error::Unsynchronized);
+    }
+    if read_u64(reply, ORIGIN_OFFSET) != transmit {
+        return Err(Error::OriginMismatch);
+    }
+    let receive = read_u64(reply, RECEIVE_OFFSET);
+    let transmit_ts = read_u64(reply, TRANSMIT_OFFSET);
+    if transmit_ts == 0 {
+        return Err(Error::Malformed);
+    }
+    Ok(Reply {
+        stratum,
+        receive_unix_ms: ntp_to_unix_ms(receive),
+        transmit_unix_ms: ntp_to_unix_ms(transmit_ts),
+    })
+}
+
+/// Stratum-1 server reply (reference id `LOCL`) to a client `request`.
+pub fn encode_reply(
+    request: &[u8],
+    out: &mut [u8; PACKET_LEN],
+    receive_unix_ms: u64,
+    transmit_unix_ms: u64,
+) -> Result<(), Error> {
+    if request.len() < PACKET_LEN || request[0] & 0x07 != MODE_CLIENT {
+        return Err(Error::Malformed);
+    }
+    let version = ((request[0] >> 3) & 0x07).clamp(1, VERSION);
+    out.fill(0);
+    out[0] = (version << 3) | MODE_SERVER;
+    out[1] = 1;
+    // Poll interval 2^6 s, precision 2^-10 s (~1 ms).
+    out[2] = 6;
+    out[3] = (-10i8) as u8;
+    out[12..16].copy_from_slice(b"LOCL");
+    out[REFERENCE_OFFSET..REFERENCE_OFFSET + 8]
+        .copy_from_slice(&unix_ms_to_ntp(receive_unix_ms).to_be_bytes());
+    out[ORIGIN_OFFSET..ORIGIN_OFFSET + 8]
+        .copy_from_slice(&request[TRANSMIT_OFFSET..TRANSMIT_OFFSET + 8]);
+    out[RECEIVE_OFFSET..RECEIVE_OFFSET + 8]
+        .copy_from_slice(&unix_ms_to_ntp(receive_unix_ms).to_be_bytes());
+    out[TRANSMIT_OFFSET..TRANSMIT_OFFSET + 8]
+        .copy_from_slice(&unix_ms_to_ntp(transmit_unix_ms).to_be_bytes());
+    Ok(())
+}
+
+/// Render `unix_ms` as UTC `YYYY-MM-DDTHH:MM:SS.mmmZ`.
+pub fn format_rfc3339(unix_ms: u64, out: &mut [u8; RFC3339_LEN]) -> &str {
+    let days = unix_ms / 86_400_000;
+    let ms_of_day = unix_ms % 86_400_000;
+    let (year, month, day) = civil_from_days(days);
+    let fields: [(usize, u64, usize); 7] = [
+        (0, year % 10_000, 4),
+        (5, month, 2),
+        (8, day, 2),
+        (11, ms_of_day / 3_600_000, 2),
+        (14, ms_of_day / 60_000 % 60, 2),
+        (17, ms_of_day / 1000 % 60, 2),
+        (20, ms_of_day % 1000, 3),
+    ];
+    out.copy_from_slice(b"0000-00-00T00:00:00.000Z");
+    for (offset, mut value, width) in fields {
+        for idx in (offset..offset + width).rev() {
+            out[idx] = b'0' + (value % 10) as u8;
+            value /= 10;
+        }
+    }
+    // Only ASCII digits and separators were written.
+    core::str::from_utf8(out).unwrap_or("")
+}
+
+/// Days since 1970-01-01 to (year, month, day); Howard Hinnant's
+/// `civil_from_days` restricted to non-negative input.
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+fn read_u64(buf: &[u8], offset: usize) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&buf[offset..offset + 8]);
+    u64::from_be_bytes(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2026-10-18T09:30:15.250Z
+    const NOW_MS: u64 = 1_792_315_815_250;
+
+    #[test]
+    fn request_reply_exchange_yields_offset_and_delay() {
+        let mut request = [0u8; PACKET_LEN];
+        encode_request(&mut request, 0x1234_5678_9abc_def0);
+        assert_eq!(request[0], 0x23);
+
+        // Device uptime 10_000 ms when sending; 40 ms round trip, server
+        // spends 2 ms between receive and transmit.
+        let mut reply = [0u8; PACKET_LEN];
+        encode_reply(&request, &mut reply, NOW_MS, NOW_MS + 2).unwrap();
+        let parsed = parse_reply(&reply, 0x1234_5678_9abc_def0).unwrap();
+        assert_eq!(parsed.stratum, 1);
+        assert_eq!(parsed.receive_unix_ms, NOW_MS);
+
+        let sample = Sample::new(9_981, &parsed, 10_021);
+        assert_eq!(sample.delay_ms, 38);
+        assert_eq!(sample.offset_ms, NOW_MS as i64 - 10_000);
+    }
+
+    #[test]
+    fn parse_reply_rejects_bad_packets() {
+        let mut request = [0u8; PACKET_LEN];
+        encode_request(&mut request, 7);
+        let mut reply = [0u8; PACKET_LEN];
+        encode_reply(&request, &mut reply, NOW_MS, NOW_MS).unwrap();
+
+        assert_eq!(parse_reply(&reply, 8), Err(Error::OriginMismatch));
+        assert_eq!(parse_reply(&reply[..40], 7), Err(Error::Malformed));
+        assert_eq!(parse_reply(&request, 7), Err(Error::Malformed));
+
+        let mut kiss = reply;
+        kiss[1] = 0;
+        assert_eq!(parse_reply(&kiss, 7), Err(Error::Unsynchronized));
+        let mut alarm = reply;
+        alarm[0] |= 0xC0;
+        assert_eq!(parse_reply(&alarm, 7), Err(Error::Unsynchronized));
+        assert_eq!(
+            encode_reply(&reply, &mut [0u8; PACKET_LEN], NOW_MS, NOW_MS),
+            Err(Error::Malformed)
+        );
+    }
+
+    #[test]
+    fn ntp_timestamps_round_trip_across_the_2036_era_boundary() {
+        for unix_ms in [NOW_MS, 2_085_978_496_000 - 1, 2_085_978_496_000 + 1_500] {
+            assert_eq!(ntp_to_unix_ms(unix_ms_to_ntp(unix_ms)), unix_ms);
+        }
+    }
+
+    #[test]
+    fn rfc3339_formats_utc_with_milliseconds() {
+        let mut out = [0u8; RFC3339_LEN];
+        assert_eq!(format_rfc3339(0, &mut out), "1970-01-01T00:00:00.000Z");
+        assert_eq!(format_rfc3339(NOW_MS, &mut out), "2026-10-18T09:30:15.250Z");
+        // Leap day and end of a leap year.
+        assert_eq!(
+            format_rfc3339(1_709_208_000_007, &mut out),
+            "2024-02-29T12:00:00.007Z"
+        );
+        assert_eq!(
+            format_rfc3339(1_735_689_599_999, &mut out),
+            "2024-12-31T23:59:59.999Z"
+        );
+    }
+}
@@ -0,0 +1,199 @@
+//! STM32 USART bootloader (AN3155) framing for flashing the analog STM32G431.
+
+#![no_std]
+
+#[cfg(test)]
+extern crate std;
+
+/// Autobaud byte sent once after the bootloader starts; the ROM runs 8E1
+/// (even parity) and detects the baud rate from it.
+pub const SYNC: u8 = 0x7F;
+pub const ACK: u8 = 0x79;
+pub const NACK: u8 = 0x1F;
+
+pub const CMD_GET_ID: u8 = 0x02;
+pub const CMD_READ_MEMORY: u8 = 0x11;
+pub const CMD_GO: u8 = 0x21;
+pub const CMD_WRITE_MEMORY: u8 = 0x31;
+pub const CMD_EXTENDED_ERASE: u8 = 0x44;
+
+/// Largest read/write block per command.
+pub const MAX_BLOCK_LEN: usize = 256;
+/// G4 flash programs double words; write lengths must be a multiple of this.
+pub const WRITE_ALIGN: usize = 8;
+/// Largest frame [`write_data`] produces: length byte, data, checksum.
+pub const MAX_FRAME_LEN: usize = MAX_BLOCK_LEN + 2;
+
+pub const G431_PRODUCT_ID: u16 = 0x468;
+pub const G431_FLASH_BASE: u32 = 0x0800_0000;
+pub const G431_FLASH_LEN: u32 = 128 * 1024;
+pub const G431_PAGE_LEN: u32 = 2 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The bootloader answered NACK.
+    Nack,
+    /// Neither ACK nor NACK.
+    Unexpected(u8),
+    /// Block empty, longer than [`MAX_BLOCK_LEN`], unaligned, or the output
+    /// buffer is too small.
+    BadLength,
+    /// Image empty or larger than the flash.
+    BadImageSize,
+    /// GET_ID reply too short or with an unexpected length byte.
+    Malformed,
+}
+
+/// Command byte followed by its complement.
+pub const fn command(cmd: u8) -> [u8; 2] {
+    [cmd, !cmd]
+}
+
+/// Big-endian address plus XOR checksum (read, write and go commands).
+pub const fn address(addr: u32) -> [u8; 5] {
+    let b = addr.to_be_bytes();
+    [b[0], b[1], b[2], b[3], b[0] ^ b[1] ^ b[2] ^ b[3]]
+}
+
+/// Byte count for READ_MEMORY (`len - 1` and its complement).
+pub fn read_length(len: usize) -> Result<[u8; 2], Error> {
+    if len == 0 || len > MAX_BLOCK_LEN {
+        return Err(Error::BadLength);
+    }
+    let n = (len - 1) as u8;
+    Ok([n, !n])
+}
+
+/// WRITE_MEMORY data frame: `len - 1`, the data and an XOR checksum.
+///
+/// `data` must be 1..=256 bytes and a multiple of [`WRITE_ALIGN`]; pad a
+/// short last block with `0xFF` (see [`padded_len`]).
+pub fn write_data(data: &[u8], out: &mut [u8]) -> Result<usize, Error> {
+    let len = data.len();
+    if len == 0 || len > MAX_BLOCK_LEN || !len.is_multiple_of(WRITE_ALIGN) || out.len() < len + 2 {
+        return Err(Error::BadLength);
+    }
+    let n = (len - 1) as u8;
+    out[0] = n;
+    out[1..=len].copy_from_slice(data);
+    out[len + 1] = data.iter().fold(n, |acc, byte| acc ^ byte);
+    Ok(len + 2)
+}
+
+/// EXTENDED_ERASE frame for `count` consecutive pages starting at `first`.
+pub fn erase_pages(first: u16, count: u16, out: &mut [u8]) -> Result<usize, Error> {
+    let frame_len = 2 + 2 * count as usize + 1;
+    if count == 0 || out.len() < frame_len {
+        return Err(Error::BadLength);
+    }
+    out[..2].copy_from_slice(&(count - 1).to_be_bytes());
+    for i in 0..count {
+        let at = 2 + 2 * i as usize;
+        out[at..at + 2].copy_from_slice(&(first + i).to_be_bytes());
+    }
+    out[frame_len - 1] = out[..frame_len - 1].iter().fold(0, |acc, byte| acc ^ byte);
+    Ok(frame_len)
+}
+
+/// Pages an image of `image_len` bytes occupies from the start of flash.
+pub fn page_span(image_len: u32) -> Result<u16, Error> {
+    if image_len == 0 || image_len > G431_FLASH_LEN {
+        return Err(Error::BadImageSize);
+    }
+    Ok(image_len.div_ceil(G431_PAGE_LEN) as u16)
+}
+
+/// `len` rounded up to the programming granularity.
+pub const fn padded_len(len: usize) -> usize {
+    len.next_multiple_of(WRITE_ALIGN)
+}
+
+pub fn check_reply(byte: u8) -> Result<(), Error> {
+    match byte {
+        ACK => Ok(()),
+        NACK => Err(Error::Nack),
+        other => Err(Error::Unexpected(other)),
+    }
+}
+
+/// Product ID from the GET_ID payload (`N = 1`, PID high, PID low), i.e. the
+/// bytes between the two ACKs.
+pub fn parse_product_id(reply: &[u8]) -> Result<u16, Error> {
+    match reply {
+        [1, hi, lo, ..] => Ok(u16::from_be_bytes([*hi, *lo])),
+        _ => Err(Error::Malformed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn command_and_address_checksums() {
+        assert_eq!(command(CMD_WRITE_MEMORY), [0x31, 0xCE]);
+        assert_eq!(command(CMD_EXTENDED_ERASE), [0x44, 0xBB]);
+        assert_eq!(address(G431_FLASH_BASE), [0x08, 0x00, 0x00, 0x00, 0x08]);
+        assert_eq!(address(0x0800_1F00), [0x08, 0x00, 0x1F, 0x00, 0x17]);
+    }
+
+    #[test]
+    fn read_length_bounds() {
+        assert_eq!(read_length(256), Ok([0xFF, 0x00]));
+        assert_eq!(read_length(1), Ok([0x00, 0xFF]));
+        assert_eq!(read_length(0), Err(Error::BadLength));
+        assert_eq!(read_length(257), Err(Error::BadLength));
+    }
+
+    #[test]
+    fn write_data_frames_and_rejects_unaligned_blocks() {
+        let mut out = [0u8; MAX_FRAME_LEN];
+        let data = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08];
+        let len = write_data(&data, &mut out).unwrap();
+        assert_eq!(len, 10);
+        assert_eq!(out[0], 7);
+        assert_eq!(&out[1..9], &data);
+        // 0x07 ^ 0x01 ^ 0x02 ^ ... ^ 0x08
+        assert_eq!(out[9], 0x0F);
+
+        assert_eq!(write_data(&[0u8; 256], &mut out), Ok(258));
+        assert_eq!(write_data(&[0u8; 12], &mut out), Err(Error::BadLength));
+        assert_eq!(write_data(&[], &mut out), Err(Error::BadLength));
+        assert_eq!(write_data(&[0u8; 264], &mut out), Err(Error::BadLength));
+        assert_eq!(
+            write_data(&[0u8; 16], &mut [0u8; 17]),
+            Err(Error::BadLength)
+        );
+    }
+
+    #[test]
+    fn erase_pages_lists_each_page() {
+        let mut out = [0u8; 16];
+        let len = erase_pages(0, 3, &mut out).unwrap();
+        assert_eq!(&out[..len], &[0x00, 0x02, 0, 0, 0, 1, 0, 2, 0x01]);
+        assert_eq!(erase_pages(0, 0, &mut out), Err(Error::BadLength));
+        assert_eq!(erase_pages(0, 8, &mut out), Err(Error::BadLength));
+    }
+
+    #[test]
+    fn page_span_covers_image() {
+        assert_eq!(page_span(1), Ok(1));
+        assert_eq!(page_span(2048), Ok(1));
+        assert_eq!(page_span(2049), Ok(2));
+        assert_eq!(page_span(G431_FLASH_LEN), Ok(64));
+        assert_eq!(page_span(0), Err(Error::BadImageSize));
+        assert_eq!(page_span(G431_FLASH_LEN + 1), Err(Error::BadImageSize));
+        assert_eq!(padded_len(13), 16);
+        assert_eq!(padded_len(16), 16);
+    }
+
+    #[test]
+    fn replies_and_product_id() {
+        assert_eq!(check_reply(ACK), Ok(()));
+        assert_eq!(check_reply(NACK), Err(Error::Nack));
+        assert_eq!(check_reply(0x00), Err(Error::Unexpected(0x00)));
+        assert_eq!(parse_product_id(&[1, 0x04, 0x68]), Ok(G431_PRODUCT_ID));
+        assert_eq!(parse_product_id(&[2, 0x04, 0x68]), Err(Error::Malformed));
+        assert_eq!(parse_product_id(&[1, 0x04]), Err(Error::Malformed));
+    }
+}
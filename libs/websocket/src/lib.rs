@@ -0,0 +1,368 @@
+//! Minimal RFC 6455 server-side WebSocket codec for the digital firmware's `/api/v1/ws`.
+
+#![no_std]
+
+#[cfg(test)]
+extern crate std;
+
+/// GUID appended to the client key before hashing (§1.3).
+const ACCEPT_GUID: &[u8] = b"258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Length of a `Sec-WebSocket-Accept` value (base64 of a 20-byte digest).
+pub const ACCEPT_KEY_LEN: usize = 28;
+/// Largest frame header: 2 bytes + 8-byte extended length + 4-byte mask.
+pub const MAX_HEADER_LEN: usize = 14;
+
+pub const OPCODE_CONTINUATION: u8 = 0x0;
+pub const OPCODE_TEXT: u8 = 0x1;
+pub const OPCODE_BINARY: u8 = 0x2;
+pub const OPCODE_CLOSE: u8 = 0x8;
+pub const OPCODE_PING: u8 = 0x9;
+pub const OPCODE_PONG: u8 = 0xA;
+
+/// Close status codes (§7.4.1).
+pub const CLOSE_NORMAL: u16 = 1000;
+pub const CLOSE_GOING_AWAY: u16 = 1001;
+pub const CLOSE_PROTOCOL_ERROR: u16 = 1002;
+pub const CLOSE_UNSUPPORTED_DATA: u16 = 1003;
+pub const CLOSE_MESSAGE_TOO_BIG: u16 = 1009;
+pub const CLOSE_TRY_AGAIN_LATER: u16 = 1013;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The output buffer cannot hold the header.
+    BufferTooSmall,
+    /// Reserved bits set, an unknown opcode or an oversized control frame.
+    Malformed,
+    /// Client frames must be masked (§5.1).
+    Unmasked,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FrameHeader {
+    pub fin: bool,
+    pub opcode: u8,
+    /// Client masking key.
+    pub mask: [u8; 4],
+    pub payload_len: u64,
+    /// Bytes consumed by the header itself.
+    pub header_len: usize,
+}
+
+impl FrameHeader {
+    pub const fn is_control(&self) -> bool {
+        self.opcode & 0x8 != 0
+    }
+}
+
+/// Parse the frame header at the start of `buf`.
+///
+/// Returns `Ok(None)` while more bytes are needed. Client frames without a
+/// mask are rejected with [`Error::Unmasked`].
+pub fn decode_header(buf: &[u8]) -> Result<Option<FrameHeader>, Error> {
+    if buf.len() < 2 {
+        return Ok(None);
+    }
+    let b0 = buf[0];
+    let b1 = buf[1];
+    if b0 & 0x70 != 0 {
+        return Err(Error::Malformed);
+    }
+    let opcode = b0 & 0x0F;
+    if !matches!(
+        opcode,
+        OPCODE_CONTINUATION
+            | OPCODE_TEXT
+            | OPCODE_BINARY
+            | OPCODE_CLOSE
+            | OPCODE_PING
+            | OPCODE_PONG
+    ) {
+        return Err(Error::Malformed);
+    }
+    let fin = b0 & 0x80 != 0;
+    let masked = b1 & 0x80 != 0;
+    let (payload_len, mut offset) = match b1 & 0x7F {
+        126 => {
+            if buf.len() < 4 {
+                return Ok(None);
+            }
+            (u16::from_be_bytes([buf[2], buf[3]]) as u64, 4)
+        }
+        127 => {
+            if buf.len() < 10 {
+                return Ok(None);
+            }
+            let mut raw = [0u8; 8];
+            raw.copy_from_slice(&buf[2..10]);
+            let len = u64::from_be_bytes(raw);
+            if len >> 63 != 0 {
+                return Err(Error::Malformed);
+            }
+            (len, 10)
+        }
+        len => (len as u64, 2),
+    };
+    // Control frames are never fragmented and carry at most 125 bytes (§5.5).
+    if opcode & 0x8 != 0 && (!fin || payload_len > 125) {
+        return Err(Error::Malformed);
+    }
+    if !masked {
+        return Err(Error::Unmasked);
+    }
+    if buf.len() < offset + 4 {
+        return Ok(None);
+    }
+    let mask = [
+        buf[offset],
+        buf[offset + 1],
+        buf[offset + 2],
+        buf[offset + 3],
+    ];
+    offset += 4;
+    Ok(Some(FrameHeader {
+        fin,
+        opcode,
+        mask,
+        payload_len,
+        header_len: offset,
+    }))
+}
+
+/// XOR `payload` with the masking key. `offset` is the position of
+/// `payload[0]` within the frame payload, so large payloads can be unmasked
+/// in chunks.
+pub fn unmask(payload: &mut [u8], mask: [u8; 4], offset: usize) {
+    for (idx, byte) in payload.iter_mut().enumerate() {
+        *byte ^= mask[(offset + idx) % 4];
+    }
+}
+
+/// Write an unmasked, final server frame header for `payload_len` bytes.
+pub fn encode_header(out: &mut [u8], opcode: u8, payload_len: usize) -> Result<usize, Error> {
+    let needed = match payload_len {
+        0..=125 => 2,
+        126..=0xFFFF => 4,
+        _ => 10,
+    };
+    if out.len() < needed {
+        return Err(Error::BufferTooSmall);
+    }
+    out[0] = 0x80 | (opcode & 0x0F);
+    match needed {
+        2 => out[1] = payload_len as u8,
+        4 => {
+            out[1] = 126;
+            out[2..4].copy_from_slice(&(payload_len as u16).to_be_bytes());
+        }
+        _ => {
+            out[1] = 127;
+            out[2..10].copy_from_slice(&(payload_len as u64).to_be_bytes());
+        }
+    }
+    Ok(needed)
+}
+
+/// Compute the `Sec-WebSocket-Accept` value for a `Sec-WebSocket-Key`.
+pub fn accept_key(client_key: &str) -> [u8; ACCEPT_KEY_LEN] {
+    let mut sha = Sha1::new();
+    sha.update(client_key.trim().as_bytes());
+    sha.update(ACCEPT_GUID);
+    let digest = sha.finish();
+    let mut out = [0u8; ACCEPT_KEY_LEN];
+    base64_encode(&digest, &mut out);
+    out
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(input: &[u8], out: &mut [u8]) {
+    for (chunk, dst) in input.chunks(3).zip(out.chunks_mut(4)) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        dst[0] = BASE64_ALPHABET[(n >> 18) as usize & 0x3F];
+        dst[1] = BASE64_ALPHABET[(n >> 12) as usize & 0x3F];
+        dst[2] = if chunk.len() > 1 {
+            BASE64_ALPHABET[(n >> 6) as usize & 0x3F]
+        } else {
+            b'='
+        };
+        dst[3] = if chunk.len() > 2 {
+            BASE64_ALPHABET[n as usize & 0x3F]
+        } else {
+            b'='
+        };
+    }
+}
+
+/// Streaming SHA-1 (FIPS 180-4); only used for the opening handshake.
+struct Sha1 {
+    state: [u32; 5],
+    block: [u8; 64],
+    block_len: usize,
+    total_len: u64,
+}
+
+impl Sha1 {
+    const fn new() -> Self {
+        Self {
+            state: [
+                0x6745_2301,
+                0xEFCD_AB89,
+                0x98BA_DCFE,
+                0x1032_5476,
+                0xC3D2_E1F0,
+            ],
+            block: [0; 64],
+            block_len: 0,
+            total_len: 0,
+        }
+    }
+
+    fn update(&mut self, mut data: &[u8]) {
+        self.total_len += data.len() as u64;
+        while !data.is_empty() {
+            let take = (64 - self.block_len).min(data.len());
+            self.block[self.block_len..self.block_len + take].copy_from_slice(&data[..take]);
+            self.block_len += take;
+            data = &data[take..];
+            if self.block_len == 64 {
+                let block = self.block;
+                self.compress(&block);
+                self.block_len = 0;
+            }
+        }
+    }
+
+    fn finish(mut self) -> [u8; 20] {
+        let bit_len = self.total_len * 8;
+        self.update(&[0x80]);
+        while self.block_len != 56 {
+            self.update(&[0]);
+        }
+        self.update(&bit_len.to_be_bytes());
+        let mut out = [0u8; 20];
+        for (dst, word) in out.chunks_mut(4).zip(self.state) {
+            dst.copy_from_slice(&word.to_be_bytes());
+        }
+        out
+    }
+
+    fn compress(&mut self, block: &[u8; 64]) {
+        let mut w = [0u32; 80];
+        for (idx, word) in block.chunks(4).enumerate() {
+            w[idx] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for idx in 16..80 {
+            w[idx] = (w[idx - 3] ^ w[idx - 8] ^ w[idx - 14] ^ w[idx - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = self.state;
+        for (idx, word) in w.iter().enumerate() {
+            let (f, k) = match idx {
+                0..=19 => ((b & c) | (!b & d), 0x5A82_7999),
+                20..=39 => (b ^ c ^ d, 0x6ED9_EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1B_BCDC),
+                _ => (b ^ c ^ d, 0xCA62_C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (state, value) in self.state.iter_mut().zip([a, b, c, d, e]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accept_key_matches_rfc_example() {
+        // RFC 6455 §1.3.
+        let key = accept_key("dGhlIHNhbXBsZSBub25jZQ==");
+        assert_eq!(&key, b"s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+    }
+
+    #[test]
+    fn sha1_handles_multi_block_input() {
+        let mut sha = Sha1::new();
+        sha.update(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq");
+        assert_eq!(
+            sha.finish(),
+            [
+                0x84, 0x98, 0x3E, 0x44, 0x1C, 0x3B, 0xD2, 0x6E, 0xBA, 0xAE, 0x4A, 0xA1, 0xF9, 0x51,
+                0x29, 0xE5, 0xE5, 0x46, 0x70, 0xF1
+            ]
+        );
+    }
+
+    #[test]
+    fn decodes_masked_text_frame() {
+        // RFC 6455 §5.7: a single-frame masked text message containing "Hello".
+        let mut frame = [
+            0x81, 0x85, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x51, 0x58,
+        ];
+        assert_eq!(decode_header(&frame[..5]), Ok(None));
+        let header = decode_header(&frame).unwrap().unwrap();
+        assert!(header.fin);
+        assert_eq!(header.opcode, OPCODE_TEXT);
+        assert_eq!(header.payload_len, 5);
+        assert_eq!(header.header_len, 6);
+        let payload = &mut frame[header.header_len..];
+        unmask(&mut payload[..2], header.mask, 0);
+        unmask(&mut payload[2..], header.mask, 2);
+        assert_eq!(payload, b"Hello");
+    }
+
+    #[test]
+    fn rejects_unmasked_and_oversized_control_frames() {
+        assert_eq!(
+            decode_header(&[0x81, 0x05, b'H', b'e']),
+            Err(Error::Unmasked)
+        );
+        assert_eq!(
+            decode_header(&[0x89, 0xFE, 0x00, 0x7E]),
+            Err(Error::Malformed)
+        );
+        assert_eq!(
+            decode_header(&[0x09, 0x80, 0, 0, 0, 0]),
+            Err(Error::Malformed)
+        );
+        assert_eq!(decode_header(&[0xC1, 0x80]), Err(Error::Malformed));
+    }
+
+    #[test]
+    fn extended_lengths_round_trip() {
+        let mut out = [0u8; MAX_HEADER_LEN];
+        for len in [0usize, 125, 126, 65_535, 65_536] {
+            let n = encode_header(&mut out, OPCODE_BINARY, len).unwrap();
+            // Server frames are unmasked; set the mask bit so the client-side
+            // decoder accepts them.
+            out[1] |= 0x80;
+            out[n..n + 4].copy_from_slice(&[1, 2, 3, 4]);
+            let header = decode_header(&out[..n + 4]).unwrap().unwrap();
+            assert_eq!(header.payload_len, len as u64);
+            assert_eq!(header.header_len, n + 4);
+            assert_eq!(header.opcode, OPCODE_BINARY);
+        }
+        assert_eq!(
+            encode_header(&mut out[..3], OPCODE_TEXT, 200),
+            Err(Error::BufferTooSmall)
+        );
+    }
+}
@@ -3,8 +3,9 @@ use clap::{ArgAction, CommandFactory, Parser, Subcommand, ValueEnum};
 use clap_complete::{Shell, generate};
 use dialoguer::{Confirm, Input, Select, theme::ColorfulTheme};
 use loadlynx_devd::{
-    FLASH_CONFIRMATION_TEXT, HOST_TOOLS_VERSION, IpcRequest, TargetKind, default_ipc_endpoint,
-    ipc_request, list_digital_usb_port_candidates, write_default_digital_usb_port,
+    FLASH_CONFIRMATION_TEXT, HOST_TOOLS_VERSION, IpcRequest, TargetKind, calibration_report,
+    default_ipc_endpoint, ipc_request, list_digital_usb_port_candidates,
+    write_default_digital_usb_port,
 };
 use reqwest::{Client, Url};
 use serde::{Deserialize, Serialize};
@@ -21,17 +22,33 @@ use std::{
 };
 use tokio::process::Command as TokioCommand;
 
+#[path = "loadlynx/analog_bridge.rs"]
+mod analog_bridge;
+#[path = "loadlynx/auth.rs"]
+mod auth;
 #[path = "loadlynx/backup.rs"]
 mod backup;
+#[path = "loadlynx/calibrate.rs"]
+mod calibrate;
+#[path = "loadlynx/certificate.rs"]
+mod certificate;
 #[path = "loadlynx/hardware.rs"]
 mod hardware;
+#[path = "loadlynx/measure.rs"]
+mod measure;
 #[path = "loadlynx/mode_first.rs"]
 mod mode_first;
+#[path = "loadlynx/ntp.rs"]
+mod ntp;
+#[path = "loadlynx/ota.rs"]
+mod ota;
 #[path = "loadlynx/render.rs"]
 mod render;
 #[path = "loadlynx/transport.rs"]
 mod transport;
 
+use analog_bridge::{AnalogFlashRequest, handle_flash_analog_bridge};
+use auth::{AuthPairRequest, handle_auth_pair};
 #[cfg(test)]
 use backup::{
     BackupSelection, backup_unknown_section_warnings, calibration_curve_write_body,
@@ -39,6 +56,23 @@ use backup::{
     write_backup_file,
 };
 use backup::{handle_backup_export, handle_backup_import};
+use calibrate::{
+    AutoCalKind, AutoCalibrateArgs, VerifyArgs, handle_calibrate_auto, handle_calibration_verify,
+};
+#[cfg(test)]
+use calibrate::{
+    MeterEndpoint, ScpiMeter, current_points_json, parse_meter_endpoint, parse_scpi_number,
+    validated_points, verify_bodies_from_file, verify_body_from_capture,
+};
+use certificate::{
+    CertificateDetails, CertificateIssueArgs, ReferenceMeter, handle_certificate_issue,
+    handle_certificate_verify,
+};
+#[cfg(test)]
+use certificate::{
+    build_certificate, canonical_json, certificate_from_text, certificate_html,
+    offline_certificate_checks, profile_sha256,
+};
 #[cfg(test)]
 use hardware::{
     HardwareRegistry, SavedHardware, SavedHttpTransport, SavedTransports, SavedUsbTransport,
@@ -52,9 +86,14 @@ use hardware::{
     resolve_saved_hardware_selection, resolve_saved_hardware_selection_with_transport,
     resolve_usb_target,
 };
+#[cfg(test)]
+use measure::ir_measure_run_ms;
+use measure::{IrMeasureArgs, handle_measure_ir};
 use mode_first::{ModeFirstCommand, handle_mode_first_command};
 #[cfg(test)]
 use mode_first::{handle_mode_first_command_for_selector, validate_mode_first_targets};
+use ntp::{handle_time_serve_ntp, host_unix_ms};
+use ota::{LanFlashRequest, handle_flash_digital_lan};
 #[cfg(test)]
 use render::{classify_cli_error_code, render_human_payload};
 use render::{print_cli_error, print_cli_payload};
@@ -65,8 +104,8 @@ use transport::{
     ensure_one_api_selector, ensure_one_status_selector, freeze_api_selector,
     post_usb_operation_with_optional_lease, release_cli_lease, request_api_value,
     request_devd_usb_value, request_http_value, resolve_output_enable,
-    resolve_scanned_usb_device_for_saved_hardware, run_monitor, saved_usb_device_needs_relookup,
-    spawn_cli_lease_heartbeat,
+    resolve_scanned_usb_device_for_saved_hardware, run_monitor, run_scpi_bridge,
+    saved_usb_device_needs_relookup, spawn_cli_lease_heartbeat,
 };
 
 #[derive(Debug, Clone)]
@@ -193,6 +232,16 @@ enum Command {
         target: BoardTarget,
         #[arg(long)]
         device: Option<String>,
+        /// `lan` updates over Wi-Fi instead of USB (digital: OTA; analog: via the
+        /// digital board). `bridge` flashes the analog board through the digital
+        /// board's UART bootloader bridge over USB, without an SWD probe.
+        #[arg(long, value_enum, default_value_t = FlashTransport::Usb)]
+        transport: FlashTransport,
+        #[arg(long, hide = true)]
+        url: Option<String>,
+        /// Device OTA token for `--transport lan` (else LOADLYNX_OTA_TOKEN).
+        #[arg(long)]
+        ota_token: Option<String>,
         #[arg(long)]
         artifact: Option<String>,
         #[arg(long = "manifest-path", hide = true)]
@@ -273,6 +322,47 @@ enum Command {
         #[command(subcommand)]
         command: PresetCommand,
     },
+    Protection {
+        #[command(subcommand)]
+        command: ProtectionCommand,
+    },
+    Faults {
+        #[command(subcommand)]
+        command: FaultsCommand,
+    },
+    Counters {
+        #[command(subcommand)]
+        command: CountersCommand,
+    },
+    Measure {
+        #[command(subcommand)]
+        command: MeasureCommand,
+    },
+    /// Firmware slots and the OTA upload token.
+    Firmware {
+        #[command(subcommand)]
+        command: FirmwareCommand,
+    },
+    /// Tokens for the device HTTP API: pair, list and revoke.
+    Auth {
+        #[command(subcommand)]
+        command: AuthCommand,
+    },
+    /// Device wall clock: SNTP status, host sync and a local NTP stand-in.
+    Time {
+        #[command(subcommand)]
+        command: TimeCommand,
+    },
+    /// Local SCPI endpoints for USB-attached devices.
+    Scpi {
+        #[command(subcommand)]
+        command: ScpiCommand,
+    },
+    /// Thermal derating model parameters.
+    Thermal {
+        #[command(subcommand)]
+        command: ThermalCommand,
+    },
     #[command(hide = true)]
     Discover {
         #[arg(long)]
@@ -302,7 +392,7 @@ enum Command {
         #[arg(long, default_value_t = 500)]
         status_interval_ms: u64,
     },
-    #[command(hide = true)]
+    #[command(hide = true, visible_alias = "calibrate")]
     Calibration {
         #[command(subcommand)]
         command: CalibrationCommand,
@@ -404,6 +494,47 @@ enum WifiCommand {
         #[arg(long)]
         allow_insecure_lan_wifi: bool,
     },
+    /// List stored networks in priority order (no PSKs).
+    List {
+        #[arg(long, hide = true)]
+        url: Option<String>,
+        #[arg(long)]
+        device: Option<String>,
+    },
+    /// Store a network; without --priority it is appended as the last fallback.
+    Add {
+        #[arg(long, hide = true)]
+        url: Option<String>,
+        #[arg(long)]
+        device: Option<String>,
+        #[arg(long)]
+        ssid: String,
+        #[arg(long)]
+        psk: String,
+        #[arg(long)]
+        priority: Option<u8>,
+        #[arg(long)]
+        allow_insecure_lan_wifi: bool,
+    },
+    Remove {
+        #[arg(long, hide = true)]
+        url: Option<String>,
+        #[arg(long)]
+        device: Option<String>,
+        #[arg(long)]
+        ssid: String,
+    },
+    /// Reorder a stored network; priority 0 is tried first.
+    Move {
+        #[arg(long, hide = true)]
+        url: Option<String>,
+        #[arg(long)]
+        device: Option<String>,
+        #[arg(long)]
+        ssid: String,
+        #[arg(long)]
+        priority: u8,
+    },
 }
 
 #[derive(Debug, Subcommand)]
@@ -426,6 +557,331 @@ enum ControlCommand {
     },
 }
 
+#[derive(Debug, Subcommand)]
+enum ProtectionCommand {
+    Show {
+        #[arg(long, hide = true)]
+        url: Option<String>,
+        #[arg(long)]
+        device: Option<String>,
+    },
+    Set {
+        #[arg(long, hide = true)]
+        url: Option<String>,
+        #[arg(long)]
+        device: Option<String>,
+        #[arg(long = "oc-limit-ch-ma")]
+        oc_limit_ch_ma: Option<i32>,
+        #[arg(long = "ov-limit-mv")]
+        ov_limit_mv: Option<i32>,
+        #[arg(long = "mcu-temp-limit-mc")]
+        mcu_temp_limit_mc: Option<i32>,
+        #[arg(long = "sink-temp-limit-mc")]
+        sink_temp_limit_mc: Option<i32>,
+        #[arg(long = "i-share-threshold-ma")]
+        i_share_threshold_ma: Option<i32>,
+        /// How the target is split between CH1 and CH2.
+        #[arg(long = "share-policy", value_enum)]
+        share_policy: Option<SharePolicy>,
+        /// UART silence after which the analog board ramps the output off.
+        #[arg(long = "link-loss-timeout-ms")]
+        link_loss_timeout_ms: Option<i32>,
+        /// Fault kinds that latch until cleared; every other kind auto-recovers.
+        /// Pass `--latch` without values to make every kind auto-recover.
+        #[arg(long, value_enum, value_delimiter = ',', num_args = 0..)]
+        latch: Option<Vec<FaultKind>>,
+        /// Start from the firmware defaults instead of the saved values.
+        #[arg(long)]
+        reset: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum FaultsCommand {
+    /// Clear latched faults / UV latch and silence alarms.
+    Clear {
+        #[arg(long, hide = true)]
+        url: Option<String>,
+        #[arg(long)]
+        device: Option<String>,
+        /// Fault kinds to clear (default: all).
+        #[arg(long, value_enum, value_delimiter = ',')]
+        fault: Vec<FaultKind>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum CountersCommand {
+    /// Reset the charge (Ah) / energy (Wh) counters and their timers.
+    Reset {
+        #[arg(long, hide = true)]
+        url: Option<String>,
+        #[arg(long)]
+        device: Option<String>,
+        /// Counters to reset (default: all).
+        #[arg(long, value_enum, value_delimiter = ',')]
+        counter: Vec<CounterKind>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ScpiCommand {
+    /// Serve SCPI on 127.0.0.1 (VISA `TCPIP::127.0.0.1::<port>::SOCKET`) over
+    /// the USB link until interrupted.
+    Bridge {
+        #[arg(long)]
+        device: Option<String>,
+        /// Loopback TCP port (0 picks a free port).
+        #[arg(long, default_value_t = 5025)]
+        port: u16,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum FirmwareCommand {
+    /// Running OTA slot, confirmation state and the last upload.
+    Status {
+        #[arg(long, hide = true)]
+        url: Option<String>,
+        #[arg(long)]
+        device: Option<String>,
+    },
+    /// Print the token `flash digital --transport lan` needs (USB only).
+    OtaToken {
+        #[arg(long)]
+        device: Option<String>,
+        /// Replace the token, invalidating copies held elsewhere.
+        #[arg(long)]
+        rotate: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum AuthCommand {
+    /// Get a token and save it with the device's HTTP transport. Over USB the
+    /// device issues it directly; over the LAN it shows a pairing code.
+    Pair {
+        #[arg(long, hide = true)]
+        url: Option<String>,
+        #[arg(long)]
+        device: Option<String>,
+        /// Six-digit code shown on the device (LAN only).
+        #[arg(long)]
+        code: Option<String>,
+        /// Role of the issued token (USB only; over the LAN it is picked by
+        /// tapping the pairing code on the device).
+        #[arg(long, value_enum, default_value_t = AuthRole::Control)]
+        role: AuthRole,
+        /// Name the token is listed and revoked under.
+        #[arg(long, default_value = "cli")]
+        label: String,
+    },
+    /// Labels and roles of the issued tokens.
+    Tokens {
+        #[arg(long, hide = true)]
+        url: Option<String>,
+        #[arg(long)]
+        device: Option<String>,
+    },
+    /// Revoke the token issued under a label.
+    Revoke {
+        #[arg(long, hide = true)]
+        url: Option<String>,
+        #[arg(long)]
+        device: Option<String>,
+        #[arg(long)]
+        label: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum TimeCommand {
+    Show {
+        #[arg(long, hide = true)]
+        url: Option<String>,
+        #[arg(long)]
+        device: Option<String>,
+    },
+    /// Set the device wall clock from this host (e.g. when it has no Wi-Fi).
+    Sync {
+        #[arg(long, hide = true)]
+        url: Option<String>,
+        #[arg(long)]
+        device: Option<String>,
+    },
+    /// Set the SNTP server as `host[:port]`; an empty value restores the default.
+    Server {
+        #[arg(long, hide = true)]
+        url: Option<String>,
+        #[arg(long)]
+        device: Option<String>,
+        server: String,
+    },
+    /// Answer SNTP requests with this host's clock (stand-in for tests and
+    /// benches without internet access).
+    ServeNtp {
+        #[arg(long, default_value = "0.0.0.0:123")]
+        bind: String,
+        /// Exit after answering this many requests.
+        #[arg(long)]
+        count: Option<usize>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum MeasureCommand {
+    /// Measure the DUT's internal DC resistance (ΔV/ΔI over CC steps).
+    Ir {
+        #[arg(long, hide = true)]
+        url: Option<String>,
+        #[arg(long)]
+        device: Option<String>,
+        /// Low current level (mA).
+        #[arg(long)]
+        low_ma: Option<u32>,
+        /// High current level (mA).
+        #[arg(long)]
+        high_ma: Option<u32>,
+        /// Number of low/high cycles to average.
+        #[arg(long)]
+        cycles: Option<u8>,
+        /// Wait after each current step before sampling (ms).
+        #[arg(long)]
+        settle_ms: Option<u32>,
+        /// Averaging window per level (ms).
+        #[arg(long)]
+        sample_ms: Option<u32>,
+        /// Return right after starting instead of waiting for the result.
+        #[arg(long)]
+        no_wait: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum FaultKind {
+    Overcurrent,
+    Overvoltage,
+    McuOverTemp,
+    SinkOverTemp,
+    UvLatch,
+}
+
+impl FaultKind {
+    /// Fault-policy selector bit (matches the firmware `FAULT_*` /
+    /// `FAULT_POLICY_UV_LATCH` constants).
+    fn bit(self) -> u32 {
+        match self {
+            FaultKind::Overcurrent => 1 << 0,
+            FaultKind::Overvoltage => 1 << 1,
+            FaultKind::McuOverTemp => 1 << 2,
+            FaultKind::SinkOverTemp => 1 << 3,
+            FaultKind::UvLatch => 1 << 31,
+        }
+    }
+}
+
+fn fault_kinds_mask(kinds: &[FaultKind]) -> u32 {
+    kinds.iter().fold(0, |mask, kind| mask | kind.bit())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum CounterKind {
+    Charge,
+    Energy,
+    /// Elapsed and output-on timers.
+    Time,
+}
+
+impl CounterKind {
+    /// Counter selector bit (matches the firmware `COUNTER_*` constants).
+    fn bit(self) -> u8 {
+        match self {
+            CounterKind::Charge => 1 << 0,
+            CounterKind::Energy => 1 << 1,
+            CounterKind::Time => 1 << 2,
+        }
+    }
+}
+
+/// CH1/CH2 current sharing policy (firmware `share_policy` names).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum SharePolicy {
+    /// CH1 alone below the share threshold, both channels above it.
+    Auto,
+    /// Both channels at every target.
+    Dual,
+    /// CH1 carries everything (single-FET debugging).
+    Ch1Only,
+    /// CH2 carries everything (single-FET debugging).
+    Ch2Only,
+}
+
+impl SharePolicy {
+    fn as_str(self) -> &'static str {
+        match self {
+            SharePolicy::Auto => "auto",
+            SharePolicy::Dual => "dual",
+            SharePolicy::Ch1Only => "ch1_only",
+            SharePolicy::Ch2Only => "ch2_only",
+        }
+    }
+}
+
+/// Device HTTP API role; each one includes the ones before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum AuthRole {
+    /// GET endpoints and the telemetry streams.
+    Read,
+    /// Setpoints, output, presets and other bench operation.
+    Control,
+    /// Wi-Fi, MQTT, time, calibration and token management.
+    Admin,
+}
+
+impl AuthRole {
+    fn as_str(self) -> &'static str {
+        match self {
+            AuthRole::Read => "read",
+            AuthRole::Control => "control",
+            AuthRole::Admin => "admin",
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+enum ThermalCommand {
+    Show {
+        #[arg(long, hide = true)]
+        url: Option<String>,
+        #[arg(long)]
+        device: Option<String>,
+    },
+    Set {
+        #[arg(long, hide = true)]
+        url: Option<String>,
+        #[arg(long)]
+        device: Option<String>,
+        /// Heatsink thermal resistance, core NTC to exhaust NTC (m°C/W).
+        #[arg(long = "rth-mc-per-w")]
+        rth_mc_per_w: Option<u32>,
+        /// First-order heatsink time constant (s).
+        #[arg(long = "tau-s")]
+        tau_s: Option<u32>,
+        /// Derating starts this far below the sink trip (m°C).
+        #[arg(long = "margin-mc")]
+        margin_mc: Option<u32>,
+        #[arg(long = "min-derate-pct")]
+        min_derate_pct: Option<u8>,
+        #[arg(long = "step-pct")]
+        step_pct: Option<u8>,
+        #[arg(long = "recover-step-ms")]
+        recover_step_ms: Option<u32>,
+        /// Start from the built-in model instead of the saved values.
+        #[arg(long)]
+        reset: bool,
+    },
+}
+
 #[derive(Debug, Subcommand)]
 enum PresetCommand {
     List {
@@ -489,6 +945,162 @@ enum CalibrationCommand {
         device: Option<String>,
         kind: String,
     },
+    /// Named profile slots stored in the device EEPROM.
+    Slots {
+        #[command(subcommand)]
+        command: CalibrationSlotsCommand,
+    },
+    /// Analyze curve quality (segment fits, residuals, coverage). Reads a
+    /// profile, backup or apply body from `--file`, else the device profile.
+    Report {
+        #[arg(long, hide = true)]
+        url: Option<String>,
+        #[arg(long)]
+        device: Option<String>,
+        #[arg(long)]
+        file: Option<PathBuf>,
+    },
+    /// Step the load through setpoints and capture points against a SCPI
+    /// reference meter, then apply (or commit) the resulting curve.
+    Auto {
+        #[arg(long, hide = true)]
+        url: Option<String>,
+        #[arg(long)]
+        device: Option<String>,
+        #[arg(long, value_enum)]
+        kind: AutoCalKind,
+        /// Reference meter: `tcp:HOST:PORT` or `serial:PATH[@BAUD]`.
+        #[arg(long)]
+        meter: String,
+        /// Current setpoints in mA, or nominal source voltages in mV
+        /// (prompted one by one) for `--kind voltage`.
+        #[arg(long, value_delimiter = ',', required = true)]
+        setpoints: Vec<u32>,
+        #[arg(long, default_value_t = 1500)]
+        settle_ms: u64,
+        /// Readings per point; the median is used.
+        #[arg(long, default_value_t = 5)]
+        samples: usize,
+        /// Persist to EEPROM instead of a RAM-only apply.
+        #[arg(long)]
+        commit: bool,
+        /// Capture and print the points without writing them.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Check the active profile against reference readings without changing
+    /// it. Checks come from `--file` (`{kind, points: [[raw_100uv, reference],
+    /// ...]}` or an array of those), or are captured with `--meter` as in
+    /// `auto`. Results are stored on the device; `--last` prints them.
+    Verify {
+        #[arg(long, hide = true)]
+        url: Option<String>,
+        #[arg(long)]
+        device: Option<String>,
+        #[arg(long, conflicts_with_all = ["kind", "meter"])]
+        file: Option<PathBuf>,
+        #[arg(long, value_enum, requires = "meter")]
+        kind: Option<AutoCalKind>,
+        /// Reference meter: `tcp:HOST:PORT` or `serial:PATH[@BAUD]`.
+        #[arg(long, requires = "kind")]
+        meter: Option<String>,
+        #[arg(long, value_delimiter = ',')]
+        setpoints: Vec<u32>,
+        #[arg(long, default_value_t = 1500)]
+        settle_ms: u64,
+        #[arg(long, default_value_t = 5)]
+        samples: usize,
+        /// Acceptance band `abs + |reference| * ppm / 1e6` (device default 5 + 2000 ppm).
+        #[arg(long)]
+        tolerance_abs: Option<i32>,
+        #[arg(long)]
+        tolerance_ppm: Option<i32>,
+        /// Print the latest stored result per curve instead of running a check.
+        #[arg(long, conflicts_with_all = ["file", "kind", "meter"])]
+        last: bool,
+    },
+    /// Calibration certificates for lab traceability.
+    Certificate {
+        #[command(subcommand)]
+        command: CalibrationCertificateCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum CalibrationCertificateCommand {
+    /// Write a certificate (JSON with a SHA-256 digest, optionally HTML) from
+    /// the device identity, active profile and stored verification results.
+    Issue {
+        #[arg(long, hide = true)]
+        url: Option<String>,
+        #[arg(long)]
+        device: Option<String>,
+        #[arg(long)]
+        out: PathBuf,
+        /// Also write a self-contained HTML rendering.
+        #[arg(long)]
+        html: Option<PathBuf>,
+        #[arg(long)]
+        meter_model: String,
+        #[arg(long)]
+        meter_serial: String,
+        /// Calibration due date of the reference meter.
+        #[arg(long)]
+        meter_cal_due: Option<String>,
+        /// Certificate number of the reference meter's own calibration.
+        #[arg(long)]
+        meter_cert: Option<String>,
+        #[arg(long)]
+        operator: Option<String>,
+        #[arg(long)]
+        notes: Option<String>,
+    },
+    /// Check a certificate (JSON or HTML) against its digest and, unless
+    /// `--offline`, against the live device identity and profile.
+    Verify {
+        #[arg(long, hide = true)]
+        url: Option<String>,
+        #[arg(long)]
+        device: Option<String>,
+        #[arg(long)]
+        file: PathBuf,
+        #[arg(long)]
+        offline: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum CalibrationSlotsCommand {
+    List {
+        #[arg(long, hide = true)]
+        url: Option<String>,
+        #[arg(long)]
+        device: Option<String>,
+    },
+    /// Load the slot's profile and send it to the analog board.
+    Activate {
+        #[arg(long, hide = true)]
+        url: Option<String>,
+        #[arg(long)]
+        device: Option<String>,
+        slot: u8,
+    },
+    Rename {
+        #[arg(long, hide = true)]
+        url: Option<String>,
+        #[arg(long)]
+        device: Option<String>,
+        slot: u8,
+        name: String,
+    },
+    /// Erase an inactive slot.
+    Delete {
+        #[arg(long, hide = true)]
+        url: Option<String>,
+        #[arg(long)]
+        device: Option<String>,
+        slot: u8,
+    },
 }
 
 #[derive(Debug, Subcommand)]
@@ -548,6 +1160,13 @@ enum BoardTarget {
     Analog,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum FlashTransport {
+    Usb,
+    Lan,
+    Bridge,
+}
+
 #[derive(Debug, Clone, ValueEnum)]
 enum MonitorFormat {
     Human,
@@ -809,6 +1428,19 @@ fn ipc_request_for_devd_call(
             params.insert("lease_id".to_string(), json!(lease_id));
             "serial.lease.release"
         }
+        ("GET", ["api", "v1", "devices", id, "scpi"]) => {
+            params.insert("device_id".to_string(), json!(id));
+            "devices.scpi.get"
+        }
+        ("POST", ["api", "v1", "devices", id, "scpi"]) => {
+            params.insert("device_id".to_string(), json!(id));
+            set_body(&mut params, body.as_ref());
+            "devices.scpi.start"
+        }
+        ("DELETE", ["api", "v1", "devices", id, "scpi"]) => {
+            params.insert("device_id".to_string(), json!(id));
+            "devices.scpi.stop"
+        }
         ("GET", ["api", "v1", "identity"]) => "compat.identity",
         ("GET", ["api", "v1", "status"]) => {
             coerce_bool_query_param(&mut params, "fresh")?;
@@ -826,17 +1458,83 @@ fn ipc_request_for_devd_call(
             set_body(&mut params, body.as_ref());
             "compat.cc"
         }
-        ("GET", ["api", "v1", "wifi"]) => "compat.wifi.get",
-        ("POST", ["api", "v1", "wifi"]) | ("PUT", ["api", "v1", "wifi"]) => {
+        ("GET", ["api", "v1", "wifi"]) => "compat.wifi.get",
+        ("POST", ["api", "v1", "wifi"]) | ("PUT", ["api", "v1", "wifi"]) => {
+            set_body(&mut params, body.as_ref());
+            "compat.wifi.post"
+        }
+        ("DELETE", ["api", "v1", "wifi"]) => "compat.wifi.delete",
+        ("GET", ["api", "v1", "wifi", "credentials"]) => "compat.wifi.credentials",
+        ("GET", ["api", "v1", "wifi", "networks"]) => "compat.wifi.networks.get",
+        ("POST", ["api", "v1", "wifi", "networks", "add"]) => {
+            set_body(&mut params, body.as_ref());
+            "compat.wifi.networks.add"
+        }
+        ("POST", ["api", "v1", "wifi", "networks", "remove"]) => {
+            set_body(&mut params, body.as_ref());
+            "compat.wifi.networks.remove"
+        }
+        ("POST", ["api", "v1", "wifi", "networks", "move"]) => {
+            set_body(&mut params, body.as_ref());
+            "compat.wifi.networks.move"
+        }
+        ("GET", ["api", "v1", "firmware"]) => "compat.firmware.get",
+        ("GET", ["api", "v1", "firmware", "ota-token"]) => "compat.firmware.ota_token.get",
+        ("POST", ["api", "v1", "firmware", "ota-token", "rotate"]) => {
+            "compat.firmware.ota_token.rotate"
+        }
+        ("GET", ["api", "v1", "auth", "tokens"]) => "compat.auth.tokens.get",
+        ("POST", ["api", "v1", "auth", "tokens"]) => {
+            set_body(&mut params, body.as_ref());
+            "compat.auth.tokens.create"
+        }
+        ("POST", ["api", "v1", "auth", "tokens", "revoke"]) => {
+            set_body(&mut params, body.as_ref());
+            "compat.auth.tokens.revoke"
+        }
+        ("GET", ["api", "v1", "analog", "firmware"]) => "compat.analog_firmware.get",
+        ("POST", ["api", "v1", "analog", "firmware", "begin"]) => {
+            set_body(&mut params, body.as_ref());
+            "compat.analog_firmware.begin"
+        }
+        ("POST", ["api", "v1", "analog", "firmware", "write"]) => {
+            set_body(&mut params, body.as_ref());
+            "compat.analog_firmware.write"
+        }
+        ("POST", ["api", "v1", "analog", "firmware", "finish"]) => "compat.analog_firmware.finish",
+        ("GET", ["api", "v1", "time"]) => "compat.time.get",
+        ("POST", ["api", "v1", "time"]) => {
+            set_body(&mut params, body.as_ref());
+            "compat.time.set"
+        }
+        ("GET", ["api", "v1", "control"]) => "compat.control.get",
+        ("POST", ["api", "v1", "control"]) | ("PUT", ["api", "v1", "control"]) => {
+            set_body(&mut params, body.as_ref());
+            "compat.control.post"
+        }
+        ("GET", ["api", "v1", "protection"]) => "compat.protection.get",
+        ("POST", ["api", "v1", "protection"]) | ("PUT", ["api", "v1", "protection"]) => {
+            set_body(&mut params, body.as_ref());
+            "compat.protection.post"
+        }
+        ("POST", ["api", "v1", "faults", "clear"]) => {
+            set_body(&mut params, body.as_ref());
+            "compat.faults.clear"
+        }
+        ("POST", ["api", "v1", "counters", "reset"]) => {
+            set_body(&mut params, body.as_ref());
+            "compat.counters.reset"
+        }
+        ("GET", ["api", "v1", "measure", "ir"]) => "compat.measure.ir.get",
+        ("POST", ["api", "v1", "measure", "ir"]) => {
             set_body(&mut params, body.as_ref());
-            "compat.wifi.post"
+            "compat.measure.ir.start"
         }
-        ("DELETE", ["api", "v1", "wifi"]) => "compat.wifi.delete",
-        ("GET", ["api", "v1", "wifi", "credentials"]) => "compat.wifi.credentials",
-        ("GET", ["api", "v1", "control"]) => "compat.control.get",
-        ("POST", ["api", "v1", "control"]) | ("PUT", ["api", "v1", "control"]) => {
+        ("POST", ["api", "v1", "measure", "ir", "cancel"]) => "compat.measure.ir.cancel",
+        ("GET", ["api", "v1", "thermal"]) => "compat.thermal.get",
+        ("POST", ["api", "v1", "thermal"]) | ("PUT", ["api", "v1", "thermal"]) => {
             set_body(&mut params, body.as_ref());
-            "compat.control.post"
+            "compat.thermal.post"
         }
         ("GET", ["api", "v1", "presets"]) => "compat.presets.get",
         ("POST", ["api", "v1", "presets"]) | ("PUT", ["api", "v1", "presets"]) => {
@@ -848,6 +1546,7 @@ fn ipc_request_for_devd_call(
             "compat.presets.apply"
         }
         ("GET", ["api", "v1", "calibration", "profile"]) => "compat.calibration.profile",
+        ("GET", ["api", "v1", "calibration", "report"]) => "compat.calibration.report",
         ("POST", ["api", "v1", "calibration", "apply"]) => {
             set_body(&mut params, body.as_ref());
             "compat.calibration.apply"
@@ -864,6 +1563,24 @@ fn ipc_request_for_devd_call(
             set_body(&mut params, body.as_ref());
             "compat.calibration.mode"
         }
+        ("GET", ["api", "v1", "calibration", "verify"]) => "compat.calibration.verify.results",
+        ("POST", ["api", "v1", "calibration", "verify"]) => {
+            set_body(&mut params, body.as_ref());
+            "compat.calibration.verify"
+        }
+        ("GET", ["api", "v1", "calibration", "slots"]) => "compat.calibration.slots",
+        ("POST", ["api", "v1", "calibration", "slots", "activate"]) => {
+            set_body(&mut params, body.as_ref());
+            "compat.calibration.slots.activate"
+        }
+        ("POST", ["api", "v1", "calibration", "slots", "rename"]) => {
+            set_body(&mut params, body.as_ref());
+            "compat.calibration.slots.rename"
+        }
+        ("POST", ["api", "v1", "calibration", "slots", "delete"]) => {
+            set_body(&mut params, body.as_ref());
+            "compat.calibration.slots.delete"
+        }
         ("POST", ["api", "v1", "soft-reset"]) => {
             set_body(&mut params, body.as_ref());
             "compat.soft_reset"
@@ -1098,6 +1815,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
             Command::Flash {
                 target,
                 device,
+                transport,
+                url,
+                ota_token,
                 artifact,
                 manifest_path,
                 dry_run,
@@ -1105,8 +1825,49 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
                 expected_identity_device_id,
                 acknowledge_non_project_firmware,
             } => {
+                if transport == FlashTransport::Bridge && matches!(target, BoardTarget::Digital) {
+                    return Err("--transport bridge only updates the analog board".into());
+                }
+                if transport != FlashTransport::Lan && url.is_some() {
+                    return Err("--url applies only to --transport lan".into());
+                }
                 let confirmation_text = resolve_flash_confirmation_text(&target, dry_run, confirm)?;
                 match target {
+                    BoardTarget::Digital if transport == FlashTransport::Lan => {
+                        handle_flash_digital_lan(
+                            &client,
+                            &devd,
+                            allow_interactive,
+                            LanFlashRequest {
+                                selector: ApiSelector { url, device },
+                                manifest_path,
+                                artifact,
+                                ota_token,
+                                dry_run,
+                                confirmation: confirmation_text,
+                                acknowledge_non_project_firmware,
+                            },
+                        )
+                        .await?
+                    }
+                    BoardTarget::Analog if transport != FlashTransport::Usb => {
+                        handle_flash_analog_bridge(
+                            &client,
+                            &devd,
+                            allow_interactive,
+                            AnalogFlashRequest {
+                                selector: ApiSelector { url, device },
+                                transport,
+                                manifest_path,
+                                artifact,
+                                ota_token,
+                                dry_run,
+                                confirmation: confirmation_text,
+                                acknowledge_non_project_firmware,
+                            },
+                        )
+                        .await?
+                    }
                     BoardTarget::Digital => {
                         let resolved = resolve_usb_target(device, &devd, allow_interactive)?;
                         let resolved = ResolvedUsbHardware {
@@ -1226,6 +1987,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
                     BoardTarget::Analog => reject_unsupported_analog_monitor()?,
                 }
             }
+            Command::Scpi {
+                command: ScpiCommand::Bridge { device, port },
+            } => {
+                let resolved = resolve_usb_target(device, &devd, allow_interactive)?;
+                run_scpi_bridge(&client, resolved, port).await?
+            }
             Command::Cc {
                 target_i_ma,
                 url,
@@ -1409,6 +2176,74 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
                     )
                     .await?
                 }
+                WifiCommand::List { url, device } => {
+                    request_api_value(
+                        &client,
+                        &devd,
+                        ApiSelector { url, device },
+                        allow_interactive,
+                        reqwest::Method::GET,
+                        "/api/v1/wifi/networks",
+                        None,
+                        false,
+                    )
+                    .await?
+                }
+                WifiCommand::Add {
+                    url,
+                    device,
+                    ssid,
+                    psk,
+                    priority,
+                    allow_insecure_lan_wifi,
+                } => {
+                    let mut body = json!({"ssid": ssid, "psk": psk});
+                    if let Some(priority) = priority {
+                        body["priority"] = json!(priority);
+                    }
+                    request_api_value(
+                        &client,
+                        &devd,
+                        ApiSelector { url, device },
+                        allow_interactive,
+                        reqwest::Method::POST,
+                        "/api/v1/wifi/networks/add",
+                        Some(body),
+                        allow_insecure_lan_wifi,
+                    )
+                    .await?
+                }
+                WifiCommand::Remove { url, device, ssid } => {
+                    request_api_value(
+                        &client,
+                        &devd,
+                        ApiSelector { url, device },
+                        allow_interactive,
+                        reqwest::Method::POST,
+                        "/api/v1/wifi/networks/remove",
+                        Some(json!({"ssid": ssid})),
+                        false,
+                    )
+                    .await?
+                }
+                WifiCommand::Move {
+                    url,
+                    device,
+                    ssid,
+                    priority,
+                } => {
+                    request_api_value(
+                        &client,
+                        &devd,
+                        ApiSelector { url, device },
+                        allow_interactive,
+                        reqwest::Method::POST,
+                        "/api/v1/wifi/networks/move",
+                        Some(json!({"ssid": ssid, "priority": priority})),
+                        false,
+                    )
+                    .await?
+                }
             },
             Command::Control { command } => match command {
                 ControlCommand::Get { url, device } => {
@@ -1437,8 +2272,288 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
                         ApiSelector { url, device },
                         allow_interactive,
                         reqwest::Method::POST,
-                        "/api/v1/control",
-                        Some(json!({"output_enabled": output_enabled})),
+                        "/api/v1/control",
+                        Some(json!({"output_enabled": output_enabled})),
+                        false,
+                    )
+                    .await?
+                }
+            },
+            Command::Protection { command } => match command {
+                ProtectionCommand::Show { url, device } => {
+                    request_api_value(
+                        &client,
+                        &devd,
+                        ApiSelector { url, device },
+                        allow_interactive,
+                        reqwest::Method::GET,
+                        "/api/v1/protection",
+                        None,
+                        false,
+                    )
+                    .await?
+                }
+                ProtectionCommand::Set {
+                    url,
+                    device,
+                    oc_limit_ch_ma,
+                    ov_limit_mv,
+                    mcu_temp_limit_mc,
+                    sink_temp_limit_mc,
+                    i_share_threshold_ma,
+                    share_policy,
+                    link_loss_timeout_ms,
+                    latch,
+                    reset,
+                } => {
+                    let body = ProtectionUpdate {
+                        oc_limit_ch_ma,
+                        ov_limit_mv,
+                        mcu_temp_limit_mc,
+                        sink_temp_limit_mc,
+                        i_share_threshold_ma,
+                        share_policy,
+                        link_loss_timeout_ms,
+                        fault_latch_mask: latch.as_deref().map(fault_kinds_mask),
+                        reset,
+                    }
+                    .body()?;
+                    request_api_value(
+                        &client,
+                        &devd,
+                        ApiSelector { url, device },
+                        allow_interactive,
+                        reqwest::Method::POST,
+                        "/api/v1/protection",
+                        Some(body),
+                        false,
+                    )
+                    .await?
+                }
+            },
+            Command::Faults { command } => match command {
+                FaultsCommand::Clear { url, device, fault } => {
+                    request_api_value(
+                        &client,
+                        &devd,
+                        ApiSelector { url, device },
+                        allow_interactive,
+                        reqwest::Method::POST,
+                        "/api/v1/faults/clear",
+                        Some(faults_clear_body(&fault)),
+                        false,
+                    )
+                    .await?
+                }
+            },
+            Command::Counters { command } => match command {
+                CountersCommand::Reset {
+                    url,
+                    device,
+                    counter,
+                } => {
+                    request_api_value(
+                        &client,
+                        &devd,
+                        ApiSelector { url, device },
+                        allow_interactive,
+                        reqwest::Method::POST,
+                        "/api/v1/counters/reset",
+                        Some(counters_reset_body(&counter)),
+                        false,
+                    )
+                    .await?
+                }
+            },
+            Command::Measure { command } => match command {
+                MeasureCommand::Ir {
+                    url,
+                    device,
+                    low_ma,
+                    high_ma,
+                    cycles,
+                    settle_ms,
+                    sample_ms,
+                    no_wait,
+                } => {
+                    handle_measure_ir(
+                        &client,
+                        &devd,
+                        ApiSelector { url, device },
+                        allow_interactive,
+                        IrMeasureArgs {
+                            low_ma,
+                            high_ma,
+                            cycles,
+                            settle_ms,
+                            sample_ms,
+                            wait: !no_wait,
+                        },
+                    )
+                    .await?
+                }
+            },
+            Command::Firmware { command } => match command {
+                FirmwareCommand::Status { url, device } => {
+                    request_api_value(
+                        &client,
+                        &devd,
+                        ApiSelector { url, device },
+                        allow_interactive,
+                        reqwest::Method::GET,
+                        "/api/v1/firmware",
+                        None,
+                        false,
+                    )
+                    .await?
+                }
+                FirmwareCommand::OtaToken { device, rotate } => {
+                    let resolved = resolve_usb_target(device, &devd, allow_interactive)?;
+                    let (method, path) = if rotate {
+                        (reqwest::Method::POST, "/api/v1/firmware/ota-token/rotate")
+                    } else {
+                        (reqwest::Method::GET, "/api/v1/firmware/ota-token")
+                    };
+                    request_devd_usb_value(&client, &resolved, method, path, None).await?
+                }
+            },
+            Command::Auth { command } => match command {
+                AuthCommand::Pair {
+                    url,
+                    device,
+                    code,
+                    role,
+                    label,
+                } => {
+                    handle_auth_pair(
+                        &client,
+                        &devd,
+                        allow_interactive,
+                        AuthPairRequest {
+                            selector: ApiSelector { url, device },
+                            code,
+                            role,
+                            label,
+                        },
+                    )
+                    .await?
+                }
+                AuthCommand::Tokens { url, device } => {
+                    request_api_value(
+                        &client,
+                        &devd,
+                        ApiSelector { url, device },
+                        allow_interactive,
+                        reqwest::Method::GET,
+                        "/api/v1/auth/tokens",
+                        None,
+                        false,
+                    )
+                    .await?
+                }
+                AuthCommand::Revoke { url, device, label } => {
+                    request_api_value(
+                        &client,
+                        &devd,
+                        ApiSelector { url, device },
+                        allow_interactive,
+                        reqwest::Method::POST,
+                        "/api/v1/auth/tokens/revoke",
+                        Some(json!({ "label": label })),
+                        false,
+                    )
+                    .await?
+                }
+            },
+            Command::Time { command } => match command {
+                TimeCommand::Show { url, device } => {
+                    request_api_value(
+                        &client,
+                        &devd,
+                        ApiSelector { url, device },
+                        allow_interactive,
+                        reqwest::Method::GET,
+                        "/api/v1/time",
+                        None,
+                        false,
+                    )
+                    .await?
+                }
+                TimeCommand::Sync { url, device } => {
+                    request_api_value(
+                        &client,
+                        &devd,
+                        ApiSelector { url, device },
+                        allow_interactive,
+                        reqwest::Method::POST,
+                        "/api/v1/time",
+                        Some(json!({"unix_ms": host_unix_ms()})),
+                        false,
+                    )
+                    .await?
+                }
+                TimeCommand::Server {
+                    url,
+                    device,
+                    server,
+                } => {
+                    request_api_value(
+                        &client,
+                        &devd,
+                        ApiSelector { url, device },
+                        allow_interactive,
+                        reqwest::Method::POST,
+                        "/api/v1/time",
+                        Some(json!({"ntp_server": server})),
+                        false,
+                    )
+                    .await?
+                }
+                TimeCommand::ServeNtp { bind, count } => handle_time_serve_ntp(&bind, count).await?,
+            },
+            Command::Thermal { command } => match command {
+                ThermalCommand::Show { url, device } => {
+                    request_api_value(
+                        &client,
+                        &devd,
+                        ApiSelector { url, device },
+                        allow_interactive,
+                        reqwest::Method::GET,
+                        "/api/v1/thermal",
+                        None,
+                        false,
+                    )
+                    .await?
+                }
+                ThermalCommand::Set {
+                    url,
+                    device,
+                    rth_mc_per_w,
+                    tau_s,
+                    margin_mc,
+                    min_derate_pct,
+                    step_pct,
+                    recover_step_ms,
+                    reset,
+                } => {
+                    let body = ThermalUpdate {
+                        rth_mc_per_w,
+                        tau_s,
+                        margin_mc,
+                        min_derate_pct,
+                        step_pct,
+                        recover_step_ms,
+                        reset,
+                    }
+                    .body()?;
+                    request_api_value(
+                        &client,
+                        &devd,
+                        ApiSelector { url, device },
+                        allow_interactive,
+                        reqwest::Method::POST,
+                        "/api/v1/thermal",
+                        Some(body),
                         false,
                     )
                     .await?
@@ -1555,6 +2670,203 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
                     )
                     .await?
                 }
+                CalibrationCommand::Slots { command } => {
+                    let (selector, method, path, body) = match command {
+                        CalibrationSlotsCommand::List { url, device } => (
+                            ApiSelector { url, device },
+                            reqwest::Method::GET,
+                            "/api/v1/calibration/slots",
+                            None,
+                        ),
+                        CalibrationSlotsCommand::Activate { url, device, slot } => (
+                            ApiSelector { url, device },
+                            reqwest::Method::POST,
+                            "/api/v1/calibration/slots/activate",
+                            Some(json!({"slot": slot})),
+                        ),
+                        CalibrationSlotsCommand::Rename {
+                            url,
+                            device,
+                            slot,
+                            name,
+                        } => (
+                            ApiSelector { url, device },
+                            reqwest::Method::POST,
+                            "/api/v1/calibration/slots/rename",
+                            Some(json!({"slot": slot, "name": name})),
+                        ),
+                        CalibrationSlotsCommand::Delete { url, device, slot } => (
+                            ApiSelector { url, device },
+                            reqwest::Method::POST,
+                            "/api/v1/calibration/slots/delete",
+                            Some(json!({"slot": slot})),
+                        ),
+                    };
+                    request_api_value(
+                        &client,
+                        &devd,
+                        selector,
+                        allow_interactive,
+                        method,
+                        path,
+                        body,
+                        false,
+                    )
+                    .await?
+                }
+                CalibrationCommand::Report { url, device, file } => {
+                    let document = match file {
+                        Some(file) => read_json_file(&file)?,
+                        None => {
+                            request_api_value(
+                                &client,
+                                &devd,
+                                ApiSelector { url, device },
+                                allow_interactive,
+                                reqwest::Method::GET,
+                                "/api/v1/calibration/profile",
+                                None,
+                                false,
+                            )
+                            .await?
+                        }
+                    };
+                    calibration_report(&document)?
+                }
+                CalibrationCommand::Verify {
+                    url,
+                    device,
+                    last: true,
+                    ..
+                } => {
+                    request_api_value(
+                        &client,
+                        &devd,
+                        ApiSelector { url, device },
+                        allow_interactive,
+                        reqwest::Method::GET,
+                        "/api/v1/calibration/verify",
+                        None,
+                        false,
+                    )
+                    .await?
+                }
+                CalibrationCommand::Verify {
+                    url,
+                    device,
+                    file,
+                    kind,
+                    meter,
+                    setpoints,
+                    settle_ms,
+                    samples,
+                    tolerance_abs,
+                    tolerance_ppm,
+                    last: false,
+                } => {
+                    let capture = kind.zip(meter).map(|(kind, meter)| AutoCalibrateArgs {
+                        kind,
+                        meter,
+                        setpoints,
+                        settle_ms,
+                        samples,
+                        commit: false,
+                        dry_run: true,
+                    });
+                    handle_calibration_verify(
+                        &client,
+                        &devd,
+                        ApiSelector { url, device },
+                        allow_interactive,
+                        VerifyArgs {
+                            capture,
+                            file,
+                            tolerance_abs,
+                            tolerance_ppm,
+                        },
+                    )
+                    .await?
+                }
+                CalibrationCommand::Certificate { command } => match command {
+                    CalibrationCertificateCommand::Issue {
+                        url,
+                        device,
+                        out,
+                        html,
+                        meter_model,
+                        meter_serial,
+                        meter_cal_due,
+                        meter_cert,
+                        operator,
+                        notes,
+                    } => {
+                        handle_certificate_issue(
+                            &client,
+                            &devd,
+                            ApiSelector { url, device },
+                            allow_interactive,
+                            CertificateIssueArgs {
+                                out,
+                                html,
+                                details: CertificateDetails {
+                                    meter: ReferenceMeter {
+                                        model: meter_model,
+                                        serial: meter_serial,
+                                        cal_due: meter_cal_due,
+                                        certificate: meter_cert,
+                                    },
+                                    operator,
+                                    notes,
+                                },
+                            },
+                        )
+                        .await?
+                    }
+                    CalibrationCertificateCommand::Verify {
+                        url,
+                        device,
+                        file,
+                        offline,
+                    } => {
+                        handle_certificate_verify(
+                            &client,
+                            &devd,
+                            ApiSelector { url, device },
+                            allow_interactive,
+                            &file,
+                            offline,
+                        )
+                        .await?
+                    }
+                },
+                CalibrationCommand::Auto {
+                    url,
+                    device,
+                    kind,
+                    meter,
+                    setpoints,
+                    settle_ms,
+                    samples,
+                    commit,
+                    dry_run,
+                } => {
+                    handle_calibrate_auto(
+                        &client,
+                        &devd,
+                        ApiSelector { url, device },
+                        allow_interactive,
+                        AutoCalibrateArgs {
+                            kind,
+                            meter,
+                            setpoints,
+                            settle_ms,
+                            samples,
+                            commit,
+                            dry_run,
+                        },
+                    )
+                    .await?
+                }
             },
             Command::SoftReset {
                 url,
@@ -1830,6 +3142,15 @@ fn initial_devd_endpoints(command: &Command, default_devd: &str) -> Vec<String>
                 .into_iter()
                 .collect()
         }
+        Command::Flash {
+            target: BoardTarget::Digital,
+            transport: FlashTransport::Lan,
+            url,
+            device,
+            ..
+        } => selector_devd_endpoint(url.as_ref(), device.as_ref(), default_devd)
+            .into_iter()
+            .collect(),
         Command::Flash { target, device, .. } | Command::Reset { target, device, .. } => {
             match target {
                 BoardTarget::Digital => usb_target_devd_endpoint(device.as_ref(), default_devd)
@@ -1841,6 +3162,9 @@ fn initial_devd_endpoints(command: &Command, default_devd: &str) -> Vec<String>
             }
         }
         Command::Monitor { device, .. }
+        | Command::Scpi {
+            command: ScpiCommand::Bridge { device, .. },
+        }
         | Command::Pd {
             command: PdCommand::Set { device, .. },
         } => usb_target_devd_endpoint(device.as_ref(), default_devd)
@@ -1856,7 +3180,11 @@ fn initial_devd_endpoints(command: &Command, default_devd: &str) -> Vec<String>
         Command::Wifi { command } => match command {
             WifiCommand::Show { url, device }
             | WifiCommand::Set { url, device, .. }
-            | WifiCommand::Clear { url, device, .. } => {
+            | WifiCommand::Clear { url, device, .. }
+            | WifiCommand::List { url, device }
+            | WifiCommand::Add { url, device, .. }
+            | WifiCommand::Remove { url, device, .. }
+            | WifiCommand::Move { url, device, .. } => {
                 selector_devd_endpoint(url.as_ref(), device.as_ref(), default_devd)
                     .into_iter()
                     .collect()
@@ -1869,6 +3197,73 @@ fn initial_devd_endpoints(command: &Command, default_devd: &str) -> Vec<String>
                     .collect()
             }
         },
+        Command::Protection { command } => match command {
+            ProtectionCommand::Show { url, device }
+            | ProtectionCommand::Set { url, device, .. } => {
+                selector_devd_endpoint(url.as_ref(), device.as_ref(), default_devd)
+                    .into_iter()
+                    .collect()
+            }
+        },
+        Command::Faults { command } => match command {
+            FaultsCommand::Clear { url, device, .. } => {
+                selector_devd_endpoint(url.as_ref(), device.as_ref(), default_devd)
+                    .into_iter()
+                    .collect()
+            }
+        },
+        Command::Counters { command } => match command {
+            CountersCommand::Reset { url, device, .. } => {
+                selector_devd_endpoint(url.as_ref(), device.as_ref(), default_devd)
+                    .into_iter()
+                    .collect()
+            }
+        },
+        Command::Measure { command } => match command {
+            MeasureCommand::Ir { url, device, .. } => {
+                selector_devd_endpoint(url.as_ref(), device.as_ref(), default_devd)
+                    .into_iter()
+                    .collect()
+            }
+        },
+        Command::Firmware { command } => match command {
+            FirmwareCommand::Status { url, device } => {
+                selector_devd_endpoint(url.as_ref(), device.as_ref(), default_devd)
+                    .into_iter()
+                    .collect()
+            }
+            FirmwareCommand::OtaToken { device, .. } => {
+                usb_target_devd_endpoint(device.as_ref(), default_devd)
+                    .into_iter()
+                    .collect()
+            }
+        },
+        Command::Auth { command } => match command {
+            AuthCommand::Pair { url, device, .. }
+            | AuthCommand::Tokens { url, device }
+            | AuthCommand::Revoke { url, device, .. } => {
+                selector_devd_endpoint(url.as_ref(), device.as_ref(), default_devd)
+                    .into_iter()
+                    .collect()
+            }
+        },
+        Command::Time { command } => match command {
+            TimeCommand::Show { url, device }
+            | TimeCommand::Sync { url, device }
+            | TimeCommand::Server { url, device, .. } => {
+                selector_devd_endpoint(url.as_ref(), device.as_ref(), default_devd)
+                    .into_iter()
+                    .collect()
+            }
+            TimeCommand::ServeNtp { .. } => Vec::new(),
+        },
+        Command::Thermal { command } => match command {
+            ThermalCommand::Show { url, device } | ThermalCommand::Set { url, device, .. } => {
+                selector_devd_endpoint(url.as_ref(), device.as_ref(), default_devd)
+                    .into_iter()
+                    .collect()
+            }
+        },
         Command::Preset { command } => match command {
             PresetCommand::List { url, device }
             | PresetCommand::Set { url, device, .. }
@@ -1883,11 +3278,39 @@ fn initial_devd_endpoints(command: &Command, default_devd: &str) -> Vec<String>
             | CalibrationCommand::Mode { url, device, .. }
             | CalibrationCommand::Apply { url, device, .. }
             | CalibrationCommand::Commit { url, device, .. }
-            | CalibrationCommand::Reset { url, device, .. } => {
+            | CalibrationCommand::Reset { url, device, .. }
+            | CalibrationCommand::Report {
+                url,
+                device,
+                file: None,
+            }
+            | CalibrationCommand::Slots {
+                command:
+                    CalibrationSlotsCommand::List { url, device }
+                    | CalibrationSlotsCommand::Activate { url, device, .. }
+                    | CalibrationSlotsCommand::Rename { url, device, .. }
+                    | CalibrationSlotsCommand::Delete { url, device, .. },
+            }
+            | CalibrationCommand::Verify { url, device, .. }
+            | CalibrationCommand::Certificate {
+                command:
+                    CalibrationCertificateCommand::Issue { url, device, .. }
+                    | CalibrationCertificateCommand::Verify {
+                        url,
+                        device,
+                        offline: false,
+                        ..
+                    },
+            }
+            | CalibrationCommand::Auto { url, device, .. } => {
                 selector_devd_endpoint(url.as_ref(), device.as_ref(), default_devd)
                     .into_iter()
                     .collect()
             }
+            CalibrationCommand::Report { file: Some(_), .. }
+            | CalibrationCommand::Certificate {
+                command: CalibrationCertificateCommand::Verify { offline: true, .. },
+            } => Vec::new(),
         },
         Command::SoftReset { url, device, .. }
         | Command::Diagnostics {
@@ -1954,6 +3377,109 @@ fn usb_target_devd_endpoint(device: Option<&String>, default_devd: &str) -> Opti
     })
 }
 
+/// Partial `POST /api/v1/protection` update; absent fields keep their saved value.
+#[derive(Debug, Default)]
+struct ProtectionUpdate {
+    oc_limit_ch_ma: Option<i32>,
+    ov_limit_mv: Option<i32>,
+    mcu_temp_limit_mc: Option<i32>,
+    sink_temp_limit_mc: Option<i32>,
+    i_share_threshold_ma: Option<i32>,
+    share_policy: Option<SharePolicy>,
+    link_loss_timeout_ms: Option<i32>,
+    fault_latch_mask: Option<u32>,
+    reset: bool,
+}
+
+impl ProtectionUpdate {
+    fn body(&self) -> Result<Value, Box<dyn std::error::Error + Send + Sync>> {
+        let mut body = Map::new();
+        if self.reset {
+            body.insert("reset".to_string(), json!(true));
+        }
+        for (key, value) in [
+            ("oc_limit_ch_ma", self.oc_limit_ch_ma),
+            ("ov_limit_mv", self.ov_limit_mv),
+            ("mcu_temp_limit_mc", self.mcu_temp_limit_mc),
+            ("sink_temp_limit_mc", self.sink_temp_limit_mc),
+            ("i_share_threshold_ma", self.i_share_threshold_ma),
+            ("link_loss_timeout_ms", self.link_loss_timeout_ms),
+        ] {
+            if let Some(value) = value {
+                body.insert(key.to_string(), json!(value));
+            }
+        }
+        if let Some(mask) = self.fault_latch_mask {
+            body.insert("fault_latch_mask".to_string(), json!(mask));
+        }
+        if let Some(policy) = self.share_policy {
+            body.insert("share_policy".to_string(), json!(policy.as_str()));
+        }
+        if body.is_empty() {
+            return Err(
+                "protection set requires at least one threshold, --share-policy, --latch or --reset"
+                    .into(),
+            );
+        }
+        Ok(Value::Object(body))
+    }
+}
+
+/// Empty `fault` list clears everything (the firmware default mask).
+fn faults_clear_body(fault: &[FaultKind]) -> Value {
+    if fault.is_empty() {
+        json!({})
+    } else {
+        json!({ "mask": fault_kinds_mask(fault) })
+    }
+}
+
+/// Empty `counter` list resets everything (the firmware default mask).
+fn counters_reset_body(counter: &[CounterKind]) -> Value {
+    if counter.is_empty() {
+        json!({})
+    } else {
+        json!({ "mask": counter.iter().fold(0, |mask, kind| mask | kind.bit()) })
+    }
+}
+
+/// Partial `POST /api/v1/thermal` update; absent fields keep their saved value.
+#[derive(Debug, Default)]
+struct ThermalUpdate {
+    rth_mc_per_w: Option<u32>,
+    tau_s: Option<u32>,
+    margin_mc: Option<u32>,
+    min_derate_pct: Option<u8>,
+    step_pct: Option<u8>,
+    recover_step_ms: Option<u32>,
+    reset: bool,
+}
+
+impl ThermalUpdate {
+    fn body(&self) -> Result<Value, Box<dyn std::error::Error + Send + Sync>> {
+        let mut body = Map::new();
+        if self.reset {
+            body.insert("reset".to_string(), json!(true));
+        }
+        for (key, value) in [
+            ("rth_mc_per_w", self.rth_mc_per_w),
+            ("tau_s", self.tau_s),
+            ("margin_mc", self.margin_mc),
+            ("min_derate_pct", self.min_derate_pct.map(u32::from)),
+            ("step_pct", self.step_pct.map(u32::from)),
+            ("recover_step_ms", self.recover_step_ms),
+        ] {
+            if let Some(value) = value {
+                body.insert(key.to_string(), json!(value));
+            }
+        }
+        if body.is_empty() {
+            return Err("thermal set requires at least one parameter or --reset".into());
+        }
+        Ok(Value::Object(body))
+    }
+}
+
 fn analog_target_devd_endpoint(device: Option<&String>, default_devd: &str) -> String {
     usb_target_devd_endpoint(device, default_devd).unwrap_or_else(|| default_devd.to_string())
 }
@@ -2549,6 +4075,38 @@ mod tests {
         assert_eq!(mode, 0o600);
     }
 
+    #[test]
+    fn wifi_move_parses_ssid_and_priority() {
+        let cli = Cli::try_parse_from([
+            "loadlynx",
+            "wifi",
+            "move",
+            "--device",
+            "bench-1",
+            "--ssid",
+            "OfficeNet",
+            "--priority",
+            "0",
+        ])
+        .expect("wifi move parse");
+        match cli.command {
+            Command::Wifi {
+                command:
+                    WifiCommand::Move {
+                        device,
+                        ssid,
+                        priority,
+                        ..
+                    },
+            } => {
+                assert_eq!(device.as_deref(), Some("bench-1"));
+                assert_eq!(ssid, "OfficeNet");
+                assert_eq!(priority, 0);
+            }
+            _ => panic!("expected wifi move command"),
+        }
+    }
+
     #[test]
     fn backup_import_parses_lan_wifi_write_opt_in() {
         let cli = Cli::try_parse_from([
@@ -2841,6 +4399,188 @@ mod tests {
         );
     }
 
+    #[test]
+    fn ipc_request_for_devd_call_maps_scpi_bridge_start() {
+        let request = ipc_request_for_devd_call(
+            reqwest::Method::POST,
+            "/api/v1/devices/loadlynx-a1b2c3/scpi?lease_id=lease-1",
+            Some(json!({"port": 5025})),
+        )
+        .expect("SCPI bridge IPC request");
+
+        assert_eq!(request.op, "devices.scpi.start");
+        assert_eq!(request.params["device_id"], "loadlynx-a1b2c3");
+        assert_eq!(request.params["lease_id"], "lease-1");
+        assert_eq!(request.params["body"], json!({"port": 5025}));
+    }
+
+    #[test]
+    fn flash_digital_lan_parses_transport_and_token() {
+        let cli = Cli::try_parse_from([
+            "loadlynx",
+            "flash",
+            "digital",
+            "--transport",
+            "lan",
+            "--device",
+            "bench-1",
+            "--manifest-path",
+            "catalog.json",
+            "--ota-token",
+            "abcd",
+        ])
+        .expect("flash --transport lan parse");
+        match cli.command {
+            Command::Flash {
+                transport,
+                device,
+                ota_token,
+                dry_run,
+                ..
+            } => {
+                assert_eq!(transport, FlashTransport::Lan);
+                assert_eq!(device.as_deref(), Some("bench-1"));
+                assert_eq!(ota_token.as_deref(), Some("abcd"));
+                assert!(dry_run);
+            }
+            _ => panic!("expected flash command"),
+        }
+    }
+
+    #[test]
+    fn flash_analog_bridge_parses_and_maps_write_step() {
+        let cli = Cli::try_parse_from([
+            "loadlynx",
+            "flash",
+            "analog",
+            "--transport",
+            "bridge",
+            "--manifest-path",
+            "catalog.json",
+        ])
+        .expect("flash analog --transport bridge parse");
+        match cli.command {
+            Command::Flash {
+                target, transport, ..
+            } => {
+                assert!(matches!(target, BoardTarget::Analog));
+                assert_eq!(transport, FlashTransport::Bridge);
+            }
+            _ => panic!("expected flash command"),
+        }
+
+        let request = ipc_request_for_devd_call(
+            reqwest::Method::POST,
+            "/api/v1/analog/firmware/write?device_id=loadlynx-a1b2c3&lease_id=lease-1",
+            Some(json!({"offset": 1024, "data": "00ff"})),
+        )
+        .expect("analog flash write IPC request");
+        assert_eq!(request.op, "compat.analog_firmware.write");
+        assert_eq!(request.params["lease_id"], "lease-1");
+        assert_eq!(
+            request.params["body"],
+            json!({"offset": 1024, "data": "00ff"})
+        );
+        assert_eq!(analog_bridge::hex_encode(&[0x00, 0xab, 0x7f]), "00ab7f");
+    }
+
+    #[test]
+    fn firmware_ota_token_parses_rotate() {
+        let cli = Cli::try_parse_from(["loadlynx", "firmware", "ota-token", "--rotate"])
+            .expect("firmware ota-token parse");
+        match cli.command {
+            Command::Firmware {
+                command: FirmwareCommand::OtaToken { device, rotate },
+            } => {
+                assert_eq!(device, None);
+                assert!(rotate);
+            }
+            _ => panic!("expected firmware ota-token command"),
+        }
+    }
+
+    #[test]
+    fn auth_pair_parses_and_maps_usb_token_routes() {
+        let cli = Cli::try_parse_from([
+            "loadlynx", "auth", "pair", "--code", "042017", "--role", "admin", "--label", "lab",
+        ])
+        .expect("auth pair parse");
+        match cli.command {
+            Command::Auth {
+                command:
+                    AuthCommand::Pair {
+                        code, role, label, ..
+                    },
+            } => {
+                assert_eq!(code.as_deref(), Some("042017"));
+                assert_eq!(role, AuthRole::Admin);
+                assert_eq!(label, "lab");
+            }
+            _ => panic!("expected auth pair command"),
+        }
+        let cli = Cli::try_parse_from(["loadlynx", "auth", "pair"]).expect("auth pair defaults");
+        match cli.command {
+            Command::Auth {
+                command: AuthCommand::Pair { role, label, .. },
+            } => {
+                assert_eq!(role, AuthRole::Control);
+                assert_eq!(label, "cli");
+            }
+            _ => panic!("expected auth pair command"),
+        }
+        assert!(Cli::try_parse_from(["loadlynx", "auth", "revoke"]).is_err());
+
+        let request = ipc_request_for_devd_call(
+            reqwest::Method::POST,
+            "/api/v1/auth/tokens/revoke?device_id=loadlynx-a1b2c3&lease_id=lease-1",
+            Some(json!({"label": "lab"})),
+        )
+        .expect("auth revoke IPC request");
+        assert_eq!(request.op, "compat.auth.tokens.revoke");
+        assert_eq!(request.params["body"], json!({"label": "lab"}));
+        let request = ipc_request_for_devd_call(reqwest::Method::GET, "/api/v1/auth/tokens", None)
+            .expect("auth tokens IPC request");
+        assert_eq!(request.op, "compat.auth.tokens.get");
+    }
+
+    #[test]
+    fn time_serve_ntp_parses_bind_and_count() {
+        let cli = Cli::try_parse_from([
+            "loadlynx",
+            "time",
+            "serve-ntp",
+            "--bind",
+            "127.0.0.1:1123",
+            "--count",
+            "2",
+        ])
+        .expect("time serve-ntp parse");
+        match cli.command {
+            Command::Time {
+                command: TimeCommand::ServeNtp { bind, count },
+            } => {
+                assert_eq!(bind, "127.0.0.1:1123");
+                assert_eq!(count, Some(2));
+            }
+            _ => panic!("expected time serve-ntp command"),
+        }
+    }
+
+    #[test]
+    fn scpi_bridge_defaults_to_port_5025() {
+        let cli =
+            Cli::try_parse_from(["loadlynx", "scpi", "bridge", "--device", "digital-1"]).unwrap();
+        match cli.command {
+            Command::Scpi {
+                command: ScpiCommand::Bridge { device, port },
+            } => {
+                assert_eq!(device.as_deref(), Some("digital-1"));
+                assert_eq!(port, 5025);
+            }
+            _ => panic!("expected scpi bridge command"),
+        }
+    }
+
     #[test]
     fn ipc_request_for_devd_call_coerces_status_flags_to_bool() {
         let request = ipc_request_for_devd_call(
@@ -3407,6 +5147,7 @@ mod tests {
             None,
             Some(SavedHttpTransport {
                 url: "http://loadlynx.local".to_string(),
+                token: None,
             }),
             10,
         );
@@ -3441,6 +5182,34 @@ mod tests {
         assert!(reloaded.hardware[0].transports.http.is_some());
     }
 
+    #[test]
+    fn rebinding_http_transport_keeps_paired_token() {
+        let mut registry = HardwareRegistry::default();
+        let bind = |registry: &mut HardwareRegistry, url: &str, token: Option<&str>| {
+            upsert_hardware_transport(
+                registry,
+                "loadlynx-a1b2c3".to_string(),
+                None,
+                None,
+                SavedTransport::Http,
+                None,
+                Some(SavedHttpTransport {
+                    url: url.to_string(),
+                    token: token.map(str::to_string),
+                }),
+                10,
+            )
+        };
+        bind(&mut registry, "http://loadlynx.local", Some("00112233"));
+        let rebound = bind(&mut registry, "http://loadlynx.local", None);
+        assert_eq!(
+            rebound.transports.http.unwrap().token.as_deref(),
+            Some("00112233")
+        );
+        let moved = bind(&mut registry, "http://192.168.1.50", None);
+        assert_eq!(moved.transports.http.unwrap().token, None);
+    }
+
     #[test]
     fn legacy_hardware_registry_migrates_to_v2_entities() {
         let legacy = json!({
@@ -3527,6 +5296,7 @@ mod tests {
                 usb: None,
                 http: Some(SavedHttpTransport {
                     url: "http://loadlynx-http.local".to_string(),
+                    token: None,
                 }),
             },
             last_seen_unix_seconds: None,
@@ -3722,6 +5492,177 @@ mod tests {
         }
     }
 
+    #[test]
+    fn protection_set_builds_partial_body() {
+        let cli = Cli::try_parse_from([
+            "loadlynx",
+            "protection",
+            "set",
+            "--oc-limit-ch-ma",
+            "3000",
+            "--sink-temp-limit-mc",
+            "80000",
+            "--link-loss-timeout-ms",
+            "2500",
+            "--share-policy",
+            "ch2-only",
+        ])
+        .unwrap();
+        let Command::Protection {
+            command:
+                ProtectionCommand::Set {
+                    oc_limit_ch_ma,
+                    ov_limit_mv,
+                    mcu_temp_limit_mc,
+                    sink_temp_limit_mc,
+                    i_share_threshold_ma,
+                    share_policy,
+                    link_loss_timeout_ms,
+                    latch,
+                    reset,
+                    ..
+                },
+        } = cli.command
+        else {
+            panic!("expected protection set command");
+        };
+        let body = ProtectionUpdate {
+            oc_limit_ch_ma,
+            ov_limit_mv,
+            mcu_temp_limit_mc,
+            sink_temp_limit_mc,
+            i_share_threshold_ma,
+            share_policy,
+            link_loss_timeout_ms,
+            fault_latch_mask: latch.as_deref().map(fault_kinds_mask),
+            reset,
+        }
+        .body()
+        .unwrap();
+        assert_eq!(
+            body,
+            json!({
+                "oc_limit_ch_ma": 3000,
+                "sink_temp_limit_mc": 80000,
+                "link_loss_timeout_ms": 2500,
+                "share_policy": "ch2_only"
+            })
+        );
+        assert!(ProtectionUpdate::default().body().is_err());
+
+        let cli = Cli::try_parse_from([
+            "loadlynx",
+            "protection",
+            "set",
+            "--latch",
+            "overcurrent,uv-latch",
+        ])
+        .unwrap();
+        let Command::Protection {
+            command: ProtectionCommand::Set { latch, .. },
+        } = cli.command
+        else {
+            panic!("expected protection set command");
+        };
+        assert_eq!(latch.as_deref().map(fault_kinds_mask), Some(0x8000_0001));
+    }
+
+    #[test]
+    fn faults_clear_builds_mask_body() {
+        let cli = Cli::try_parse_from(["loadlynx", "faults", "clear"]).unwrap();
+        let Command::Faults {
+            command: FaultsCommand::Clear { fault, .. },
+        } = cli.command
+        else {
+            panic!("expected faults clear command");
+        };
+        assert_eq!(faults_clear_body(&fault), json!({}));
+
+        let cli = Cli::try_parse_from([
+            "loadlynx",
+            "faults",
+            "clear",
+            "--fault",
+            "overvoltage",
+            "--fault",
+            "uv-latch",
+        ])
+        .unwrap();
+        let Command::Faults {
+            command: FaultsCommand::Clear { fault, .. },
+        } = cli.command
+        else {
+            panic!("expected faults clear command");
+        };
+        assert_eq!(faults_clear_body(&fault), json!({"mask": 0x8000_0002u32}));
+    }
+
+    #[test]
+    fn counters_reset_builds_mask_body() {
+        let cli = Cli::try_parse_from(["loadlynx", "counters", "reset"]).unwrap();
+        let Command::Counters {
+            command: CountersCommand::Reset { counter, .. },
+        } = cli.command
+        else {
+            panic!("expected counters reset command");
+        };
+        assert_eq!(counters_reset_body(&counter), json!({}));
+
+        let cli =
+            Cli::try_parse_from(["loadlynx", "counters", "reset", "--counter", "energy,time"])
+                .unwrap();
+        let Command::Counters {
+            command: CountersCommand::Reset { counter, .. },
+        } = cli.command
+        else {
+            panic!("expected counters reset command");
+        };
+        assert_eq!(counters_reset_body(&counter), json!({"mask": 6}));
+    }
+
+    #[test]
+    fn thermal_set_builds_partial_body() {
+        let cli = Cli::try_parse_from([
+            "loadlynx",
+            "thermal",
+            "set",
+            "--rth-mc-per-w",
+            "450",
+            "--tau-s",
+            "120",
+        ])
+        .unwrap();
+        let Command::Thermal {
+            command:
+                ThermalCommand::Set {
+                    rth_mc_per_w,
+                    tau_s,
+                    margin_mc,
+                    min_derate_pct,
+                    step_pct,
+                    recover_step_ms,
+                    reset,
+                    ..
+                },
+        } = cli.command
+        else {
+            panic!("expected thermal set command");
+        };
+        let body = ThermalUpdate {
+            rth_mc_per_w,
+            tau_s,
+            margin_mc,
+            min_derate_pct,
+            step_pct,
+            recover_step_ms,
+            reset,
+        }
+        .body()
+        .unwrap();
+        assert_eq!(body, json!({ "rth_mc_per_w": 450, "tau_s": 120 }));
+        assert!(ThermalUpdate::default().body().is_err());
+    }
+
     #[test]
     fn mode_first_commands_parse_and_validate_targets() {
         assert!(resolve_output_enable(true, false).unwrap());
@@ -4059,6 +6000,7 @@ mod tests {
                     usb: None,
                     http: Some(SavedHttpTransport {
                         url: "http://new.local".to_string(),
+                        token: None,
                     }),
                 },
                 last_seen_unix_seconds: Some(30),
@@ -4088,6 +6030,7 @@ mod tests {
             None,
             Some(SavedHttpTransport {
                 url: "http://loadlynx.local".to_string(),
+                token: None,
             }),
             10,
         );
@@ -4270,4 +6213,413 @@ mod tests {
         assert_eq!(seq, 0);
         assert_eq!(fetches, 0);
     }
+
+    #[test]
+    fn calibrate_auto_parses_meter_endpoints() {
+        assert_eq!(
+            parse_meter_endpoint("tcp:192.168.1.50:5025").unwrap(),
+            MeterEndpoint::Tcp("192.168.1.50:5025".into())
+        );
+        assert_eq!(
+            parse_meter_endpoint("serial:/dev/ttyUSB0@115200").unwrap(),
+            MeterEndpoint::Serial {
+                path: "/dev/ttyUSB0".into(),
+                baud: 115_200
+            }
+        );
+        assert_eq!(
+            parse_meter_endpoint("serial:/dev/ttyUSB0").unwrap(),
+            MeterEndpoint::Serial {
+                path: "/dev/ttyUSB0".into(),
+                baud: 9600
+            }
+        );
+        assert!(parse_meter_endpoint("tcp:meter").is_err());
+        assert!(parse_meter_endpoint("gpib:22").is_err());
+    }
+
+    #[test]
+    fn calibrate_auto_parses_scpi_numbers() {
+        assert_eq!(parse_scpi_number("+1.234500E+01\r"), Some(12.345));
+        assert_eq!(parse_scpi_number("-2.5E-03,+0"), Some(-0.0025));
+        assert_eq!(parse_scpi_number("0.5 A"), Some(0.5));
+        assert_eq!(parse_scpi_number("9.9E+37").map(|v| v > 1e30), Some(true));
+        assert_eq!(parse_scpi_number("OVLD"), None);
+    }
+
+    #[test]
+    fn calibrate_auto_validates_captured_points() {
+        let p = |raw, dac, meas| loadlynx_calibration_format::CalPoint {
+            raw_100uv: raw,
+            raw_dac_code: dac,
+            meas_physical: meas,
+        };
+        let points = validated_points(&[p(5000, 700, 1000), p(500, 70, 100)]).unwrap();
+        assert_eq!(
+            current_points_json(&points),
+            json!([[500, 70, 100], [5000, 700, 1000]])
+        );
+        assert!(validated_points(&[p(500, 70, 1000), p(5000, 700, 100)]).is_err());
+        assert!(validated_points(&[]).is_err());
+    }
+
+    #[tokio::test]
+    async fn calibrate_auto_queries_scpi_stand_in() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = std::io::BufReader::new(stream.try_clone().unwrap());
+            let mut writer = stream;
+            for _ in 0..2 {
+                let mut line = String::new();
+                std::io::BufRead::read_line(&mut reader, &mut line).unwrap();
+                let reply = match line.trim() {
+                    "MEAS:VOLT?" => "+1.200000E+01\n",
+                    "MEAS:CURR?" => "+1.500500E+00\n",
+                    _ => "ERR\n",
+                };
+                writer.write_all(reply.as_bytes()).unwrap();
+            }
+        });
+
+        let meter = ScpiMeter::connect(&MeterEndpoint::Tcp(addr.to_string()))
+            .await
+            .unwrap();
+        assert_eq!(meter.measure_mv().await.unwrap(), 12_000);
+        assert_eq!(meter.measure_ma().await.unwrap(), 1_501);
+        server.join().unwrap();
+    }
+
+    /// Point `LOADLYNX_HOME` at `home` with `id` saved as a USB device; returns
+    /// the previous value for the caller to restore. Hold TEST_ENV_LOCK.
+    fn use_home_with_usb_device(home: &Path, id: &str) -> Option<std::ffi::OsString> {
+        let previous_home = env::var_os("LOADLYNX_HOME");
+        // Tests serialize environment mutation through TEST_ENV_LOCK.
+        unsafe { env::set_var("LOADLYNX_HOME", home) };
+        write_hardware_registry(
+            &home.join("devices.json"),
+            &HardwareRegistry {
+                default_hardware_id: Some(id.to_string()),
+                hardware: vec![SavedHardware {
+                    id: id.to_string(),
+                    name: None,
+                    identity: None,
+                    last_transport: Some(SavedTransport::Usb),
+                    transports: SavedTransports {
+                        usb: Some(SavedUsbTransport {
+                            device: "digital-1".to_string(),
+                            port_path: Some("mock://esp32s3".to_string()),
+                            devd: None,
+                        }),
+                        http: None,
+                    },
+                    last_seen_unix_seconds: None,
+                }],
+                ..HardwareRegistry::default()
+            },
+        )
+        .unwrap();
+        previous_home
+    }
+
+    fn restore_home(previous_home: Option<std::ffi::OsString>) {
+        match previous_home {
+            Some(value) => unsafe { env::set_var("LOADLYNX_HOME", value) },
+            None => unsafe { env::remove_var("LOADLYNX_HOME") },
+        }
+    }
+
+    #[test]
+    fn calibration_report_from_file_skips_devd() {
+        let _guard = TEST_ENV_LOCK.lock().unwrap();
+        let temp = tempfile::tempdir().unwrap();
+        let previous_home = use_home_with_usb_device(temp.path(), "loadlynx-a1b2c3");
+
+        let cli = Cli::try_parse_from([
+            "loadlynx",
+            "--ipc",
+            "/tmp/loadlynx.sock",
+            "calibrate",
+            "report",
+            "--file",
+            "backup.json",
+        ])
+        .expect("calibrate report parse");
+        assert!(initial_devd_endpoints(&cli.command, &cli.ipc).is_empty());
+
+        let cli = Cli::try_parse_from([
+            "loadlynx",
+            "--ipc",
+            "/tmp/loadlynx.sock",
+            "calibration",
+            "report",
+        ])
+        .expect("calibration report parse");
+        assert_eq!(
+            initial_devd_endpoints(&cli.command, &cli.ipc),
+            vec!["/tmp/loadlynx.sock"]
+        );
+
+        restore_home(previous_home);
+    }
+
+    #[test]
+    fn calibration_slots_commands_map_to_ipc_ops() {
+        let _guard = TEST_ENV_LOCK.lock().unwrap();
+        let temp = tempfile::tempdir().unwrap();
+        let previous_home = use_home_with_usb_device(temp.path(), "loadlynx-a1b2c3");
+
+        let cli = Cli::try_parse_from([
+            "loadlynx",
+            "--ipc",
+            "/tmp/loadlynx.sock",
+            "calibration",
+            "slots",
+            "rename",
+            "2",
+            "shunt-50A",
+            "--device",
+            "loadlynx-a1b2c3",
+        ])
+        .expect("calibration slots rename parse");
+        assert!(matches!(
+            cli.command,
+            Command::Calibration {
+                command: CalibrationCommand::Slots {
+                    command: CalibrationSlotsCommand::Rename { slot: 2, ref name, .. }
+                }
+            } if name == "shunt-50A"
+        ));
+        assert_eq!(
+            initial_devd_endpoints(&cli.command, &cli.ipc),
+            vec!["/tmp/loadlynx.sock"]
+        );
+        restore_home(previous_home);
+
+        let request = ipc_request_for_devd_call(
+            reqwest::Method::POST,
+            "/api/v1/calibration/slots/activate",
+            Some(json!({"slot": 1})),
+        )
+        .expect("slot activate IPC request");
+        assert_eq!(request.op, "compat.calibration.slots.activate");
+        assert_eq!(request.params["body"], json!({"slot": 1}));
+        let request =
+            ipc_request_for_devd_call(reqwest::Method::GET, "/api/v1/calibration/slots", None)
+                .expect("slot list IPC request");
+        assert_eq!(request.op, "compat.calibration.slots");
+    }
+
+    #[test]
+    fn calibration_verify_builds_check_bodies() {
+        let body = verify_body_from_capture(
+            "current_ch1",
+            &json!([[1000, 210, 200], [12000, 2500, 2400]]),
+        );
+        assert_eq!(
+            body,
+            json!({"kind": "current_ch1", "points": [[1000, 200], [12000, 2400]]})
+        );
+
+        let bodies = verify_bodies_from_file(json!([
+            {"kind": "v_local", "points": [[2500, 5000]]},
+            {"kind": "v_remote", "points": [[2500, 5000, 20]]},
+        ]))
+        .expect("verify file with two checks");
+        assert_eq!(bodies.len(), 2);
+        assert!(verify_bodies_from_file(json!({"points": []})).is_err());
+
+        let cli = Cli::try_parse_from([
+            "loadlynx",
+            "--ipc",
+            "/tmp/loadlynx.sock",
+            "calibration",
+            "verify",
+            "--last",
+        ])
+        .expect("calibration verify --last parse");
+        assert!(matches!(
+            cli.command,
+            Command::Calibration {
+                command: CalibrationCommand::Verify { last: true, .. }
+            }
+        ));
+        assert!(
+            Cli::try_parse_from(["loadlynx", "calibration", "verify", "--kind", "current-ch1",])
+                .is_err()
+        );
+
+        let request = ipc_request_for_devd_call(
+            reqwest::Method::POST,
+            "/api/v1/calibration/verify",
+            Some(json!({"kind": "v_local", "points": [[2500, 5000]]})),
+        )
+        .expect("verify IPC request");
+        assert_eq!(request.op, "compat.calibration.verify");
+        let request =
+            ipc_request_for_devd_call(reqwest::Method::GET, "/api/v1/calibration/verify", None)
+                .expect("verify results IPC request");
+        assert_eq!(request.op, "compat.calibration.verify.results");
+    }
+
+    #[test]
+    fn calibration_certificate_digest_detects_tampering() {
+        assert_eq!(
+            canonical_json(&json!({"b": [1, {"d": 2, "c": "x"}], "a": null})),
+            r#"{"a":null,"b":[1,{"c":"x","d":2}]}"#
+        );
+
+        let identity = json!({
+            "device_id": "loadlynx-a1b2c3",
+            "digital_fw_version": "1.4.0",
+            "analog_fw_version": "1.4.0",
+        });
+        let profile = json!({
+            "active": {"source": "user-calibrated", "fmt_version": 4, "hw_rev": 1},
+            "persistence": {"status": "ok"},
+            "current_ch1_points": [{"raw_100uv": 100, "raw_dac_code": 40, "meas_ma": 200}],
+            "current_ch2_points": [],
+            "v_local_points": [{"raw_100uv": 2500, "meas_mv": 5000}],
+            "v_remote_points": [],
+            "temp_comp": {},
+        });
+        let verification = json!({"results": {
+            "current_ch1": {
+                "kind": "current_ch1", "ts_unix_ms": 1_760_000_000_000_u64, "current": true,
+                "passed": true, "points": [{"raw_100uv": 100, "reference": 200, "reading": 201,
+                "error": 1, "tolerance": 5, "passed": true}]
+            },
+            "v_local": null,
+        }});
+        let details = CertificateDetails {
+            meter: ReferenceMeter {
+                model: "Keysight 34465A".to_string(),
+                serial: "MY5900</script>".to_string(),
+                cal_due: Some("2027-03-01".to_string()),
+                certificate: None,
+            },
+            operator: Some("lab".to_string()),
+            notes: None,
+        };
+        let issued_at = chrono::DateTime::from_timestamp(1_760_000_100, 0).expect("timestamp");
+        let certificate = build_certificate(
+            &identity,
+            &profile,
+            Some(&verification),
+            None,
+            &details,
+            issued_at,
+        )
+        .expect("certificate");
+        assert_eq!(
+            certificate["certificate_id"],
+            "loadlynx-a1b2c3-20251009T085500Z"
+        );
+        assert_eq!(certificate["verification"]["passed"], true);
+        assert!(
+            certificate["verification"]["results"]
+                .get("v_local")
+                .is_none()
+        );
+        assert!(
+            offline_certificate_checks(&certificate)
+                .iter()
+                .all(|check| check["ok"] == true)
+        );
+
+        // The persistence state is not part of the profile hash.
+        let mut reloaded = profile.clone();
+        reloaded["persistence"] = json!({"status": "dirty"});
+        assert_eq!(profile_sha256(&reloaded), certificate["profile_sha256"]);
+        let mut recalibrated = profile.clone();
+        recalibrated["v_local_points"][0]["meas_mv"] = json!(5001);
+        assert_ne!(profile_sha256(&recalibrated), certificate["profile_sha256"]);
+
+        // The HTML rendering escapes user text and embeds the same document.
+        let html = certificate_html(&certificate);
+        assert!(html.contains("MY5900&lt;/script&gt;"));
+        assert_eq!(
+            certificate_from_text(&html).expect("embedded certificate"),
+            certificate
+        );
+
+        let mut tampered = certificate.clone();
+        tampered["reference_meter"]["serial"] = json!("OTHER");
+        assert_eq!(offline_certificate_checks(&tampered)[0]["ok"], false);
+        let mut tampered = certificate.clone();
+        tampered["profile"]["v_local_points"][0]["meas_mv"] = json!(5001);
+        assert!(
+            offline_certificate_checks(&tampered)
+                .iter()
+                .all(|check| check["ok"] == false)
+        );
+
+        let cli = Cli::try_parse_from([
+            "loadlynx",
+            "calibration",
+            "certificate",
+            "verify",
+            "--file",
+            "cert.json",
+            "--offline",
+        ])
+        .expect("certificate verify parse");
+        assert!(initial_devd_endpoints(&cli.command, &cli.ipc).is_empty());
+        assert!(
+            Cli::try_parse_from([
+                "loadlynx",
+                "calibration",
+                "certificate",
+                "issue",
+                "--out",
+                "cert.json",
+            ])
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn measure_ir_body_and_timeout_follow_firmware_config() {
+        let cli = Cli::try_parse_from([
+            "loadlynx",
+            "measure",
+            "ir",
+            "--high-ma",
+            "2000",
+            "--cycles",
+            "3",
+            "--no-wait",
+        ])
+        .expect("measure ir parse");
+        let Command::Measure {
+            command:
+                MeasureCommand::Ir {
+                    low_ma,
+                    high_ma,
+                    cycles,
+                    settle_ms,
+                    sample_ms,
+                    no_wait,
+                    ..
+                },
+        } = cli.command
+        else {
+            panic!("expected measure ir command");
+        };
+        let args = IrMeasureArgs {
+            low_ma,
+            high_ma,
+            cycles,
+            settle_ms,
+            sample_ms,
+            wait: !no_wait,
+        };
+        assert!(!args.wait);
+        assert_eq!(args.body(), json!({ "high_ma": 2000, "cycles": 3 }));
+
+        let config = json!({ "cycles": 5, "settle_ms": 300, "sample_ms": 200 });
+        assert_eq!(ir_measure_run_ms(Some(&config)), 5_000);
+        assert_eq!(ir_measure_run_ms(None), 0);
+    }
 }
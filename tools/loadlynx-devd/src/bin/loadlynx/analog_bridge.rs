@@ -0,0 +1,276 @@
+use super::*;
+use loadlynx_devd::{load_bridge_flash_artifact, upload_analog_image};
+use std::fmt::Write as _;
+use std::time::{Duration, Instant};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Image bytes per `analog_flash_write`; the firmware accepts at most 1 KiB.
+const WRITE_CHUNK_LEN: usize = 1024;
+/// `Go` to the analog side answering HELLO again.
+const LINK_TIMEOUT: Duration = Duration::from_secs(15);
+
+pub(crate) struct AnalogFlashRequest {
+    pub(crate) selector: ApiSelector,
+    pub(crate) transport: FlashTransport,
+    pub(crate) manifest_path: Option<String>,
+    pub(crate) artifact: Option<String>,
+    pub(crate) ota_token: Option<String>,
+    pub(crate) dry_run: bool,
+    pub(crate) confirmation: Option<String>,
+    pub(crate) acknowledge_non_project_firmware: bool,
+}
+
+/// Where the digital board that bridges to the analog ROM bootloader is
+/// reached: devd's USB compat routes, or the device's own HTTP API.
+enum BridgeRoute {
+    Usb {
+        resolved: ResolvedUsbHardware,
+        lease: CliLease,
+        lease_device: String,
+    },
+    Lan {
+        url: String,
+    },
+}
+
+impl BridgeRoute {
+    async fn request(
+        &self,
+        client: &Client,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<Value>,
+    ) -> Result<Value, BoxError> {
+        match self {
+            BridgeRoute::Usb {
+                resolved,
+                lease,
+                lease_device,
+            } => {
+                let mut url = Url::parse("http://loadlynx.local")?.join(path)?;
+                url.query_pairs_mut()
+                    .append_pair("device_id", lease_device)
+                    .append_pair("lease_id", &lease.lease_id);
+                let path = format!("{}?{}", url.path(), url.query().unwrap_or_default());
+                request_devd_value(&resolved.devd, method, &path, body).await
+            }
+            BridgeRoute::Lan { url } => request_http_value(client, url, method, path, body).await,
+        }
+    }
+}
+
+/// `loadlynx flash analog --transport bridge|lan`: write the catalog's raw
+/// analog `image` through the digital board, which resets the STM32 into its
+/// ROM bootloader and programs it over the board-to-board UART. No SWD probe
+/// is needed. `bridge` drives the steps over USB via devd; `lan` uploads the
+/// whole image to `POST /api/v1/analog/firmware` with the OTA token.
+pub(crate) async fn handle_flash_analog_bridge(
+    client: &Client,
+    devd: &str,
+    allow_interactive: bool,
+    request: AnalogFlashRequest,
+) -> Result<Value, BoxError> {
+    let transport = if request.transport == FlashTransport::Lan {
+        "lan"
+    } else {
+        "bridge"
+    };
+    let manifest_path = request.manifest_path.ok_or_else(|| {
+        format!(
+            "--transport {transport} requires --manifest-path (firmware catalog or artifact manifest)"
+        )
+    })?;
+    let (artifact, file) = load_bridge_flash_artifact(
+        &manifest_path,
+        request.artifact.as_deref(),
+        request.acknowledge_non_project_firmware,
+    )?;
+    let route = if request.transport == FlashTransport::Lan {
+        let Some(url) = freeze_api_selector(request.selector, devd, allow_interactive)?.url else {
+            return Err(
+                "--transport lan needs a device with a saved HTTP transport, or --url".into(),
+            );
+        };
+        BridgeRoute::Lan { url }
+    } else {
+        let resolved = resolve_usb_target(request.selector.device, devd, allow_interactive)?;
+        let (lease, lease_device) = create_cli_lease_for_resolved_usb(client, &resolved).await?;
+        BridgeRoute::Usb {
+            resolved,
+            lease,
+            lease_device,
+        }
+    };
+    let heartbeat = match &route {
+        BridgeRoute::Usb {
+            resolved, lease, ..
+        } => Some(spawn_cli_lease_heartbeat(
+            client.clone(),
+            resolved.devd.clone(),
+            lease.clone(),
+        )),
+        BridgeRoute::Lan { .. } => None,
+    };
+
+    let result = flash_over_route(
+        client,
+        &route,
+        transport,
+        &artifact.artifact_id,
+        &file,
+        request.ota_token,
+        request.dry_run,
+        request.confirmation,
+    )
+    .await;
+
+    if let BridgeRoute::Usb {
+        resolved, lease, ..
+    } = &route
+    {
+        let _ = release_cli_lease(client, &resolved.devd, &lease.lease_id).await;
+    }
+    if let Some(heartbeat) = heartbeat {
+        heartbeat.abort();
+    }
+    result
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn flash_over_route(
+    client: &Client,
+    route: &BridgeRoute,
+    transport: &str,
+    artifact_id: &str,
+    file: &loadlynx_devd::ArtifactFile,
+    ota_token: Option<String>,
+    dry_run: bool,
+    confirmation: Option<String>,
+) -> Result<Value, BoxError> {
+    let before = route
+        .request(
+            client,
+            reqwest::Method::GET,
+            "/api/v1/analog/firmware",
+            None,
+        )
+        .await?;
+    let plan = json!({
+        "transport": transport,
+        "artifact_id": artifact_id,
+        "image": {
+            "path": file.path,
+            "size": file.size,
+            "sha256": file.sha256,
+            "flash_address": file.flash_address,
+        },
+        "analog_firmware": before,
+    });
+    if dry_run {
+        return Ok(json!({"ok": true, "dry_run": true, "action": "flash", "plan": plan}));
+    }
+    let confirmed = confirmation
+        .as_deref()
+        .map(str::trim)
+        .is_some_and(|value| value.eq_ignore_ascii_case(FLASH_CONFIRMATION_TEXT));
+    if !confirmed {
+        return Err(
+            format!("type `{FLASH_CONFIRMATION_TEXT}` to confirm real analog flash").into(),
+        );
+    }
+
+    let flashed = match route {
+        BridgeRoute::Lan { url } => {
+            let token = ota_token
+                .or_else(|| env::var("LOADLYNX_OTA_TOKEN").ok())
+                .ok_or(
+                    "LAN flash needs the device OTA token: read it over USB with `loadlynx firmware ota-token`, then pass --ota-token or set LOADLYNX_OTA_TOKEN",
+                )?;
+            upload_analog_image(client, url, &token, file).await?
+        }
+        BridgeRoute::Usb { .. } => {
+            let image = fs::read(&file.path).map_err(|error| format!("{}: {error}", file.path))?;
+            write_image_steps(client, route, &image, &file.sha256).await?
+        }
+    };
+    let status = wait_for_analog_link(client, route).await?;
+    Ok(json!({
+        "ok": true,
+        "dry_run": false,
+        "action": "flash",
+        "plan": plan,
+        "analog_firmware": flashed,
+        "post_flash_status": status,
+    }))
+}
+
+async fn write_image_steps(
+    client: &Client,
+    route: &BridgeRoute,
+    image: &[u8],
+    sha256: &str,
+) -> Result<Value, BoxError> {
+    route
+        .request(
+            client,
+            reqwest::Method::POST,
+            "/api/v1/analog/firmware/begin",
+            Some(json!({"size": image.len(), "sha256": sha256})),
+        )
+        .await?;
+    for (index, chunk) in image.chunks(WRITE_CHUNK_LEN).enumerate() {
+        route
+            .request(
+                client,
+                reqwest::Method::POST,
+                "/api/v1/analog/firmware/write",
+                Some(json!({
+                    "offset": index * WRITE_CHUNK_LEN,
+                    "data": hex_encode(chunk),
+                })),
+            )
+            .await?;
+    }
+    route
+        .request(
+            client,
+            reqwest::Method::POST,
+            "/api/v1/analog/firmware/finish",
+            None,
+        )
+        .await
+}
+
+/// The analog image restarts after `Go`; wait until the digital board sees
+/// its link again.
+async fn wait_for_analog_link(client: &Client, route: &BridgeRoute) -> Result<Value, BoxError> {
+    let deadline = Instant::now() + LINK_TIMEOUT;
+    loop {
+        let status = route
+            .request(client, reqwest::Method::GET, "/api/v1/status", None)
+            .await;
+        match status {
+            Ok(status) if status.get("link_up").and_then(Value::as_bool) == Some(true) => {
+                return Ok(status);
+            }
+            _ if Instant::now() < deadline => {
+                tokio::time::sleep(Duration::from_millis(500)).await;
+            }
+            Ok(_) => return Err("analog link did not come back after the update".into()),
+            Err(error) => {
+                return Err(
+                    format!("analog link did not come back after the update: {error}").into(),
+                );
+            }
+        }
+    }
+}
+
+pub(crate) fn hex_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        let _ = write!(out, "{byte:02x}");
+    }
+    out
+}
@@ -0,0 +1,165 @@
+use super::hardware::{hardware_registry_path, read_hardware_registry, write_hardware_registry};
+use super::*;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+pub(crate) struct AuthPairRequest {
+    pub(crate) selector: ApiSelector,
+    pub(crate) code: Option<String>,
+    pub(crate) role: AuthRole,
+    pub(crate) label: String,
+}
+
+/// `loadlynx auth pair`: obtain a device HTTP API token and remember it with
+/// the saved HTTP transport so later LAN commands send it automatically.
+///
+/// Over USB the device issues the token directly (the cable is the proof of
+/// presence). Over the LAN the device opens a pairing window and shows a
+/// six-digit code on its screen, which the user types back here; the role is
+/// whatever was picked by tapping that code on the device, not `--role`.
+pub(crate) async fn handle_auth_pair(
+    client: &Client,
+    devd: &str,
+    allow_interactive: bool,
+    request: AuthPairRequest,
+) -> Result<Value, BoxError> {
+    ensure_one_api_selector(
+        request.selector.url.as_ref(),
+        request.selector.device.as_ref(),
+    )?;
+    let lan_body = json!({"label": request.label});
+    let (hardware_id, url, issued) = match request.selector.url {
+        Some(url) => {
+            let Some(issued) =
+                pair_over_lan(client, &url, allow_interactive, request.code, lan_body).await?
+            else {
+                return Ok(pairing_started_payload(&url));
+            };
+            (None, Some(url), issued)
+        }
+        None => match resolve_saved_hardware_selection(
+            request.selector.device,
+            devd,
+            allow_interactive,
+        )? {
+            ResolvedHardware::Usb(resolved) => {
+                let issued = request_devd_usb_value(
+                    client,
+                    &resolved,
+                    reqwest::Method::POST,
+                    "/api/v1/auth/tokens",
+                    Some(json!({"role": request.role.as_str(), "label": request.label})),
+                )
+                .await?;
+                (Some(resolved.hardware_id), None, issued)
+            }
+            ResolvedHardware::Http { hardware_id, url } => {
+                let Some(issued) =
+                    pair_over_lan(client, &url, allow_interactive, request.code, lan_body).await?
+                else {
+                    return Ok(pairing_started_payload(&url));
+                };
+                (Some(hardware_id), Some(url), issued)
+            }
+        },
+    };
+    let token = issued
+        .get("token")
+        .and_then(Value::as_str)
+        .ok_or("device reply has no token")?;
+    let saved_to = save_http_token(hardware_id.as_deref(), url.as_deref(), token)?;
+    Ok(json!({
+        "ok": true,
+        "role": issued.get("role").cloned().unwrap_or(Value::Null),
+        "label": issued.get("label").cloned().unwrap_or(Value::Null),
+        "token": token,
+        "saved_device_id": saved_to,
+    }))
+}
+
+/// Without `--code` this opens the pairing window first. A terminal gets a
+/// prompt for the code; a script gets `None` and re-runs with `--code`.
+async fn pair_over_lan(
+    client: &Client,
+    url: &str,
+    allow_interactive: bool,
+    code: Option<String>,
+    mut body: Value,
+) -> Result<Option<Value>, BoxError> {
+    let code = match code {
+        Some(code) => code,
+        None => {
+            let started = request_http_value(
+                client,
+                url,
+                reqwest::Method::POST,
+                "/api/v1/auth/pair/start",
+                None,
+            )
+            .await?;
+            if !allow_interactive {
+                return Ok(None);
+            }
+            let expires_in_s = started
+                .get("expires_in_s")
+                .and_then(Value::as_u64)
+                .unwrap_or_default();
+            eprintln!(
+                "The device now shows `PAIR nnnnnn TAP` (valid for {expires_in_s} s). \
+                 Tap it on the device until it shows the role to grant."
+            );
+            Input::with_theme(&ColorfulTheme::default())
+                .with_prompt("Pairing code")
+                .allow_empty(false)
+                .interact_text()?
+        }
+    };
+    body["code"] = Value::String(code.trim().to_string());
+    let issued = request_http_value(
+        client,
+        url,
+        reqwest::Method::POST,
+        "/api/v1/auth/pair",
+        Some(body),
+    )
+    .await?;
+    Ok(Some(issued))
+}
+
+fn pairing_started_payload(url: &str) -> Value {
+    json!({
+        "ok": true,
+        "pairing": true,
+        "url": url,
+        "next": "tap the code on the device to pick the role, then re-run `loadlynx auth pair --code <code>`",
+    })
+}
+
+/// Store `token` on the saved device's HTTP transport (by id, else by URL).
+/// Returns the device id it was saved under, if any.
+fn save_http_token(
+    hardware_id: Option<&str>,
+    url: Option<&str>,
+    token: &str,
+) -> Result<Option<String>, BoxError> {
+    let path = hardware_registry_path()?;
+    let mut registry = read_hardware_registry(&path)?;
+    let url = url.map(|url| url.trim_end_matches('/'));
+    let Some(hardware) = registry.hardware.iter_mut().find(|hardware| {
+        hardware.transports.http.as_ref().is_some_and(|http| {
+            hardware_id.is_some_and(|id| id == hardware.id)
+                || url.is_some_and(|url| url == http.url.trim_end_matches('/'))
+        })
+    }) else {
+        return Ok(None);
+    };
+    let http = hardware
+        .transports
+        .http
+        .as_mut()
+        .expect("filtered on a saved HTTP transport");
+    http.token = Some(token.to_string());
+    let id = hardware.id.clone();
+    write_hardware_registry(&path, &registry)?;
+    Ok(Some(id))
+}
@@ -128,20 +128,40 @@ pub(crate) async fn handle_backup_export(
     }
 
     if selection.calibration {
-        sections.insert(
-            "calibration".to_string(),
-            request_api_value(
-                client,
-                default_devd,
-                selector.clone(),
-                allow_interactive,
-                reqwest::Method::GET,
-                "/api/v1/calibration/profile",
-                None,
-                false,
-            )
-            .await?,
-        );
+        let mut calibration = request_api_value(
+            client,
+            default_devd,
+            selector.clone(),
+            allow_interactive,
+            reqwest::Method::GET,
+            "/api/v1/calibration/profile",
+            None,
+            false,
+        )
+        .await?;
+        // Informational only; restore reads the curve point fields.
+        if let Ok(report) = calibration_report(&calibration)
+            && let Some(object) = calibration.as_object_mut()
+        {
+            object.insert("report".to_string(), report);
+        }
+        // Verification history; older firmware has no verify endpoint.
+        if let Ok(verification) = request_api_value(
+            client,
+            default_devd,
+            selector.clone(),
+            allow_interactive,
+            reqwest::Method::GET,
+            "/api/v1/calibration/verify",
+            None,
+            false,
+        )
+        .await
+            && let Some(object) = calibration.as_object_mut()
+        {
+            object.insert("verification".to_string(), verification);
+        }
+        sections.insert("calibration".to_string(), calibration);
     }
 
     let mut settings = serde_json::Map::new();
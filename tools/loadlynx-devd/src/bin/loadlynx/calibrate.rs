@@ -0,0 +1,551 @@
+use super::*;
+use loadlynx_calibration_format::{self as calfmt, CalPoint};
+use std::{
+    net::TcpStream,
+    sync::{Arc, Mutex, PoisonError},
+    time::Duration,
+};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+const METER_TIMEOUT: Duration = Duration::from_secs(5);
+const SAMPLE_INTERVAL_MS: u64 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum AutoCalKind {
+    Voltage,
+    CurrentCh1,
+    CurrentCh2,
+}
+
+impl AutoCalKind {
+    fn mode_kind(self) -> &'static str {
+        match self {
+            AutoCalKind::Voltage => "voltage",
+            AutoCalKind::CurrentCh1 => "current_ch1",
+            AutoCalKind::CurrentCh2 => "current_ch2",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum MeterEndpoint {
+    Tcp(String),
+    Serial { path: String, baud: u32 },
+}
+
+/// Parse `tcp:HOST:PORT` or `serial:PATH[@BAUD]` (default 9600 baud).
+pub(crate) fn parse_meter_endpoint(spec: &str) -> Result<MeterEndpoint, BoxError> {
+    if let Some(addr) = spec.strip_prefix("tcp:") {
+        if addr
+            .rsplit_once(':')
+            .is_none_or(|(host, port)| host.is_empty() || port.parse::<u16>().is_err())
+        {
+            return Err(format!("meter address must be tcp:HOST:PORT, got {spec}").into());
+        }
+        return Ok(MeterEndpoint::Tcp(addr.to_string()));
+    }
+    if let Some(rest) = spec.strip_prefix("serial:") {
+        let (path, baud) = match rest.rsplit_once('@') {
+            Some((path, baud)) => (
+                path,
+                baud.parse::<u32>()
+                    .map_err(|_| format!("invalid meter baud rate: {baud}"))?,
+            ),
+            None => (rest, 9600),
+        };
+        if path.is_empty() {
+            return Err("meter serial path must not be empty".into());
+        }
+        return Ok(MeterEndpoint::Serial {
+            path: path.to_string(),
+            baud,
+        });
+    }
+    Err(format!("meter must be tcp:HOST:PORT or serial:PATH[@BAUD], got {spec}").into())
+}
+
+trait MeterIo: Read + Write + Send {}
+impl<T: Read + Write + Send> MeterIo for T {}
+
+/// Minimal line-based SCPI client for the reference DMM. The meter speaks
+/// over blocking std/serialport I/O, so every exchange runs on tokio's
+/// blocking pool instead of stalling the runtime.
+pub(crate) struct ScpiMeter {
+    io: Arc<Mutex<Box<dyn MeterIo>>>,
+}
+
+impl ScpiMeter {
+    pub(crate) async fn connect(endpoint: &MeterEndpoint) -> Result<Self, BoxError> {
+        let endpoint = endpoint.clone();
+        let io = tokio::task::spawn_blocking(move || -> Result<Box<dyn MeterIo>, BoxError> {
+            let io: Box<dyn MeterIo> = match endpoint {
+                MeterEndpoint::Tcp(addr) => {
+                    let stream = TcpStream::connect(addr)?;
+                    stream.set_read_timeout(Some(METER_TIMEOUT))?;
+                    stream.set_write_timeout(Some(METER_TIMEOUT))?;
+                    Box::new(stream)
+                }
+                MeterEndpoint::Serial { path, baud } => {
+                    Box::new(serialport::new(path, baud).timeout(METER_TIMEOUT).open()?)
+                }
+            };
+            Ok(io)
+        })
+        .await??;
+        Ok(Self {
+            io: Arc::new(Mutex::new(io)),
+        })
+    }
+
+    async fn query(&self, command: &'static str) -> Result<f64, BoxError> {
+        let io = Arc::clone(&self.io);
+        tokio::task::spawn_blocking(move || {
+            let mut io = io.lock().unwrap_or_else(PoisonError::into_inner);
+            query_blocking(&mut **io, command)
+        })
+        .await?
+    }
+
+    /// DC voltage in millivolts.
+    pub(crate) async fn measure_mv(&self) -> Result<i32, BoxError> {
+        Ok(si_to_milli(self.query("MEAS:VOLT?").await?))
+    }
+
+    /// DC current in milliamps.
+    pub(crate) async fn measure_ma(&self) -> Result<i32, BoxError> {
+        Ok(si_to_milli(self.query("MEAS:CURR?").await?))
+    }
+}
+
+fn query_blocking(io: &mut dyn MeterIo, command: &str) -> Result<f64, BoxError> {
+    io.write_all(command.as_bytes())?;
+    io.write_all(b"\n")?;
+    io.flush()?;
+
+    let mut line = Vec::new();
+    let mut byte = [0u8; 1];
+    loop {
+        if io.read(&mut byte)? == 0 {
+            return Err(format!("meter closed the connection during {command}").into());
+        }
+        match byte[0] {
+            b'\n' => break,
+            b => line.push(b),
+        }
+        if line.len() > 256 {
+            return Err(format!("meter response to {command} is too long").into());
+        }
+    }
+    parse_scpi_number(&String::from_utf8_lossy(&line))
+        .ok_or_else(|| format!("meter returned a non-numeric reply to {command}").into())
+}
+
+pub(crate) fn parse_scpi_number(reply: &str) -> Option<f64> {
+    // Some meters append units or several comma-separated values.
+    let first = reply.trim().split(',').next()?.trim();
+    let value = first
+        .trim_end_matches(|c: char| c.is_ascii_alphabetic() && c != 'E' && c != 'e')
+        .trim_end()
+        .parse::<f64>()
+        .ok()?;
+    value.is_finite().then_some(value)
+}
+
+fn si_to_milli(value: f64) -> i32 {
+    (value * 1000.0)
+        .round()
+        .clamp(i32::MIN as f64, i32::MAX as f64) as i32
+}
+
+fn median_i32(values: &mut [i32]) -> i32 {
+    values.sort_unstable();
+    values[(values.len() - 1) / 2]
+}
+
+/// Sort/dedup the captured samples and enforce the same rules as the firmware
+/// before anything is written to the device.
+pub(crate) fn validated_points(points: &[CalPoint]) -> Result<Vec<CalPoint>, BoxError> {
+    let mut bounded = heapless::Vec::<CalPoint, { calfmt::MAX_POINTS_V3 }>::new();
+    for point in points {
+        bounded
+            .push(*point)
+            .map_err(|_| format!("at most {} points are supported", calfmt::MAX_POINTS_V3))?;
+    }
+    let normalized = calfmt::normalize_points(bounded);
+    if normalized.is_empty() {
+        return Err("no calibration points were captured".into());
+    }
+    if !calfmt::meas_is_strictly_increasing(&normalized) {
+        return Err(
+            "meter readings are not strictly increasing with raw ADC values; check wiring and setpoints"
+                .into(),
+        );
+    }
+    Ok(normalized.to_vec())
+}
+
+pub(crate) fn current_points_json(points: &[CalPoint]) -> Value {
+    Value::Array(
+        points
+            .iter()
+            .map(|p| json!([p.raw_100uv, p.raw_dac_code, p.meas_physical]))
+            .collect(),
+    )
+}
+
+pub(crate) fn voltage_points_json(points: &[CalPoint]) -> Value {
+    Value::Array(
+        points
+            .iter()
+            .map(|p| json!([p.raw_100uv, p.meas_physical]))
+            .collect(),
+    )
+}
+
+#[derive(Debug, Default)]
+struct VoltageCapture {
+    local: Vec<CalPoint>,
+    remote: Vec<CalPoint>,
+}
+
+pub(crate) struct AutoCalibrateArgs {
+    pub(crate) kind: AutoCalKind,
+    pub(crate) meter: String,
+    pub(crate) setpoints: Vec<u32>,
+    pub(crate) settle_ms: u64,
+    pub(crate) samples: usize,
+    pub(crate) commit: bool,
+    pub(crate) dry_run: bool,
+}
+
+pub(crate) async fn handle_calibrate_auto(
+    client: &Client,
+    default_devd: &str,
+    selector: ApiSelector,
+    allow_interactive: bool,
+    args: AutoCalibrateArgs,
+) -> Result<Value, BoxError> {
+    check_capture_args(&args)?;
+    let selector = freeze_api_selector(selector, default_devd, allow_interactive)?;
+    let api = |method: reqwest::Method, path: &'static str, body: Option<Value>| {
+        request_api_value(
+            client,
+            default_devd,
+            selector.clone(),
+            allow_interactive,
+            method,
+            path,
+            body,
+            false,
+        )
+    };
+
+    let writes = capture_with_meter(&api, &args).await?;
+    let path = if args.commit {
+        "/api/v1/calibration/commit"
+    } else {
+        "/api/v1/calibration/apply"
+    };
+    let mut results = Vec::new();
+    for (kind, points) in &writes {
+        if !args.dry_run {
+            api(
+                reqwest::Method::POST,
+                path,
+                Some(json!({"kind": kind, "points": points})),
+            )
+            .await?;
+        }
+        results.push(json!({"kind": kind, "points": points}));
+    }
+
+    Ok(json!({
+        "ok": true,
+        "dry_run": args.dry_run,
+        "action": if args.dry_run { "none" } else if args.commit { "commit" } else { "apply" },
+        "curves": results,
+    }))
+}
+
+fn check_capture_args(args: &AutoCalibrateArgs) -> Result<(), BoxError> {
+    if args.setpoints.is_empty() {
+        return Err("at least one --setpoints value is required".into());
+    }
+    if args.samples == 0 {
+        return Err("--samples must be at least 1".into());
+    }
+    if args.kind == AutoCalKind::Voltage && !io::stdin().is_terminal() {
+        return Err(
+            "voltage auto-calibration prompts for each source voltage and needs an interactive terminal"
+                .into(),
+        );
+    }
+    Ok(())
+}
+
+/// Capture points against the reference meter with the device in calibration
+/// mode; returns compact point arrays per curve, as written by apply/commit.
+async fn capture_with_meter<F, Fut>(
+    api: &F,
+    args: &AutoCalibrateArgs,
+) -> Result<Vec<(&'static str, Value)>, BoxError>
+where
+    F: Fn(reqwest::Method, &'static str, Option<Value>) -> Fut,
+    Fut: Future<Output = Result<Value, BoxError>>,
+{
+    let endpoint = parse_meter_endpoint(&args.meter)?;
+    let meter = ScpiMeter::connect(&endpoint).await?;
+
+    api(
+        reqwest::Method::POST,
+        "/api/v1/calibration/mode",
+        Some(json!({"kind": args.kind.mode_kind()})),
+    )
+    .await?;
+
+    let capture = capture_points(api, &meter, args).await;
+
+    // Always leave the load idle and out of calibration mode, even when a
+    // step failed half-way.
+    if args.kind != AutoCalKind::Voltage {
+        let _ = api(
+            reqwest::Method::POST,
+            "/api/v1/cc",
+            Some(json!({"enable": false})),
+        )
+        .await;
+    }
+    let _ = api(
+        reqwest::Method::POST,
+        "/api/v1/calibration/mode",
+        Some(json!({"kind": "off"})),
+    )
+    .await;
+
+    capture
+}
+
+pub(crate) struct VerifyArgs {
+    pub(crate) capture: Option<AutoCalibrateArgs>,
+    pub(crate) file: Option<PathBuf>,
+    pub(crate) tolerance_abs: Option<i32>,
+    pub(crate) tolerance_ppm: Option<i32>,
+}
+
+/// Turn captured compact points (`[raw, meas]` or `[raw, dac, meas]`) into a
+/// verify body: `{kind, points: [[raw, reference], ...]}`.
+pub(crate) fn verify_body_from_capture(kind: &str, points: &Value) -> Value {
+    let checks = points
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|point| {
+            let items = point.as_array()?;
+            Some(json!([items.first()?, items.last()?]))
+        })
+        .collect::<Vec<_>>();
+    json!({"kind": kind, "points": checks})
+}
+
+/// Verify bodies from a file: a single `{kind, points}` object or an array.
+pub(crate) fn verify_bodies_from_file(document: Value) -> Result<Vec<Value>, BoxError> {
+    let bodies = match document {
+        Value::Array(items) => items,
+        other => vec![other],
+    };
+    if bodies.is_empty() {
+        return Err("verify file contains no checks".into());
+    }
+    for body in &bodies {
+        if body.get("kind").and_then(Value::as_str).is_none()
+            || body.get("points").and_then(Value::as_array).is_none()
+        {
+            return Err("each verify check needs a string kind and a points array".into());
+        }
+    }
+    Ok(bodies)
+}
+
+pub(crate) async fn handle_calibration_verify(
+    client: &Client,
+    default_devd: &str,
+    selector: ApiSelector,
+    allow_interactive: bool,
+    args: VerifyArgs,
+) -> Result<Value, BoxError> {
+    let mut bodies = match (&args.file, &args.capture) {
+        (Some(file), _) => verify_bodies_from_file(read_json_file(file)?)?,
+        (None, Some(capture)) => {
+            check_capture_args(capture)?;
+            Vec::new()
+        }
+        (None, None) => return Err("either --file or --kind with --meter is required".into()),
+    };
+
+    let selector = freeze_api_selector(selector, default_devd, allow_interactive)?;
+    let api = |method: reqwest::Method, path: &'static str, body: Option<Value>| {
+        request_api_value(
+            client,
+            default_devd,
+            selector.clone(),
+            allow_interactive,
+            method,
+            path,
+            body,
+            false,
+        )
+    };
+
+    if args.file.is_none()
+        && let Some(capture) = &args.capture
+    {
+        for (kind, points) in capture_with_meter(&api, capture).await? {
+            bodies.push(verify_body_from_capture(kind, &points));
+        }
+    }
+
+    let ts_unix_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    let mut results = Vec::new();
+    for mut body in bodies {
+        if let Some(object) = body.as_object_mut() {
+            object
+                .entry("ts_unix_ms")
+                .or_insert_with(|| json!(ts_unix_ms));
+            if let Some(abs) = args.tolerance_abs {
+                object.insert("tolerance_abs".to_string(), json!(abs));
+            }
+            if let Some(ppm) = args.tolerance_ppm {
+                object.insert("tolerance_ppm".to_string(), json!(ppm));
+            }
+        }
+        results.push(
+            api(
+                reqwest::Method::POST,
+                "/api/v1/calibration/verify",
+                Some(body),
+            )
+            .await?,
+        );
+    }
+
+    let passed = results
+        .iter()
+        .all(|result| result.get("passed").and_then(Value::as_bool) == Some(true));
+    Ok(json!({"ok": true, "passed": passed, "results": results}))
+}
+
+async fn capture_points<F, Fut>(
+    api: &F,
+    meter: &ScpiMeter,
+    args: &AutoCalibrateArgs,
+) -> Result<Vec<(&'static str, Value)>, BoxError>
+where
+    F: Fn(reqwest::Method, &'static str, Option<Value>) -> Fut,
+    Fut: Future<Output = Result<Value, BoxError>>,
+{
+    match args.kind {
+        AutoCalKind::CurrentCh1 | AutoCalKind::CurrentCh2 => {
+            let mut points = Vec::new();
+            for &target_i_ma in &args.setpoints {
+                api(
+                    reqwest::Method::POST,
+                    "/api/v1/cc",
+                    Some(json!({"enable": true, "target_i_ma": target_i_ma})),
+                )
+                .await?;
+                tokio::time::sleep(Duration::from_millis(args.settle_ms)).await;
+
+                let mut raws = Vec::with_capacity(args.samples);
+                let mut dacs = Vec::with_capacity(args.samples);
+                let mut meas = Vec::with_capacity(args.samples);
+                for _ in 0..args.samples {
+                    let status = api(reqwest::Method::GET, "/api/v1/status", None).await?;
+                    raws.push(status_raw(&status, "raw_cur_100uv")?);
+                    dacs.push(status_raw(&status, "raw_dac_code")?);
+                    meas.push(meter.measure_ma().await?);
+                    tokio::time::sleep(Duration::from_millis(SAMPLE_INTERVAL_MS)).await;
+                }
+                points.push(CalPoint {
+                    raw_100uv: median_i32(&mut raws).clamp(i16::MIN as i32, i16::MAX as i32) as i16,
+                    raw_dac_code: median_i32(&mut dacs).clamp(0, u16::MAX as i32) as u16,
+                    meas_physical: median_i32(&mut meas),
+                });
+            }
+            let kind = args.kind.mode_kind();
+            Ok(vec![(
+                kind,
+                current_points_json(&validated_points(&points)?),
+            )])
+        }
+        AutoCalKind::Voltage => {
+            let mut capture = VoltageCapture::default();
+            for &nominal_mv in &args.setpoints {
+                eprint!(
+                    "Set the source to ~{:.3} V and press Enter to capture...",
+                    nominal_mv as f64 / 1000.0
+                );
+                io::stderr().flush()?;
+                let mut line = String::new();
+                io::stdin().read_line(&mut line)?;
+                tokio::time::sleep(Duration::from_millis(args.settle_ms)).await;
+
+                let mut local = Vec::with_capacity(args.samples);
+                let mut remote = Vec::with_capacity(args.samples);
+                let mut meas = Vec::with_capacity(args.samples);
+                for _ in 0..args.samples {
+                    let status = api(reqwest::Method::GET, "/api/v1/status", None).await?;
+                    local.push(status_raw(&status, "raw_v_nr_100uv")?);
+                    if let Ok(raw) = status_raw(&status, "raw_v_rmt_100uv") {
+                        remote.push(raw);
+                    }
+                    meas.push(meter.measure_mv().await?);
+                    tokio::time::sleep(Duration::from_millis(SAMPLE_INTERVAL_MS)).await;
+                }
+                let meas_mv = median_i32(&mut meas);
+                capture.local.push(CalPoint {
+                    raw_100uv: median_i32(&mut local).clamp(i16::MIN as i32, i16::MAX as i32)
+                        as i16,
+                    raw_dac_code: 0,
+                    meas_physical: meas_mv,
+                });
+                // Remote sense is only calibrated when it reported on every sample.
+                if remote.len() == args.samples {
+                    capture.remote.push(CalPoint {
+                        raw_100uv: median_i32(&mut remote).clamp(i16::MIN as i32, i16::MAX as i32)
+                            as i16,
+                        raw_dac_code: 0,
+                        meas_physical: meas_mv,
+                    });
+                }
+            }
+
+            let mut writes = vec![(
+                "v_local",
+                voltage_points_json(&validated_points(&capture.local)?),
+            )];
+            if capture.remote.len() == capture.local.len() {
+                writes.push((
+                    "v_remote",
+                    voltage_points_json(&validated_points(&capture.remote)?),
+                ));
+            }
+            Ok(writes)
+        }
+    }
+}
+
+fn status_raw(status: &Value, field: &str) -> Result<i32, BoxError> {
+    status
+        .get("status")
+        .unwrap_or(status)
+        .get(field)
+        .and_then(Value::as_i64)
+        .map(|v| v as i32)
+        .ok_or_else(|| {
+            format!("device status has no {field}; is the analog board in calibration mode?").into()
+        })
+}
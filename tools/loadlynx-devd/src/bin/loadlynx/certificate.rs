@@ -0,0 +1,580 @@
+use super::hardware::stable_hardware_id_from_identity;
+use super::*;
+use sha2::{Digest, Sha256};
+use std::fmt::Write as _;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+pub(crate) const CERTIFICATE_KIND: &str = "loadlynx.calibration_certificate";
+const CERTIFICATE_SCHEMA_VERSION: u64 = 1;
+
+/// Profile fields covered by `profile_sha256`. Persistence state is left out
+/// so a later RAM-only apply/reset round trip does not change the hash.
+const PROFILE_FIELDS: [&str; 6] = [
+    "active",
+    "current_ch1_points",
+    "current_ch2_points",
+    "v_local_points",
+    "v_remote_points",
+    "temp_comp",
+];
+
+const CURVE_TABLES: [(&str, &str, &str); 4] = [
+    ("current_ch1_points", "Current CH1", "meas_ma"),
+    ("current_ch2_points", "Current CH2", "meas_ma"),
+    ("v_local_points", "Voltage (local)", "meas_mv"),
+    ("v_remote_points", "Voltage (remote sense)", "meas_mv"),
+];
+
+pub(crate) struct ReferenceMeter {
+    pub(crate) model: String,
+    pub(crate) serial: String,
+    pub(crate) cal_due: Option<String>,
+    pub(crate) certificate: Option<String>,
+}
+
+/// Details supplied by the person issuing the certificate.
+pub(crate) struct CertificateDetails {
+    pub(crate) meter: ReferenceMeter,
+    pub(crate) operator: Option<String>,
+    pub(crate) notes: Option<String>,
+}
+
+pub(crate) struct CertificateIssueArgs {
+    pub(crate) out: PathBuf,
+    pub(crate) html: Option<PathBuf>,
+    pub(crate) details: CertificateDetails,
+}
+
+/// Serialize with object keys sorted at every level and no whitespace, so the
+/// digest does not depend on field order or pretty-printing.
+pub(crate) fn canonical_json(value: &Value) -> String {
+    fn write_value(out: &mut String, value: &Value) {
+        match value {
+            Value::Object(map) => {
+                let mut keys = map.keys().collect::<Vec<_>>();
+                keys.sort();
+                out.push('{');
+                for (idx, key) in keys.into_iter().enumerate() {
+                    if idx != 0 {
+                        out.push(',');
+                    }
+                    out.push_str(&Value::String(key.clone()).to_string());
+                    out.push(':');
+                    write_value(out, &map[key]);
+                }
+                out.push('}');
+            }
+            Value::Array(items) => {
+                out.push('[');
+                for (idx, item) in items.iter().enumerate() {
+                    if idx != 0 {
+                        out.push(',');
+                    }
+                    write_value(out, item);
+                }
+                out.push(']');
+            }
+            other => out.push_str(&other.to_string()),
+        }
+    }
+
+    let mut out = String::new();
+    write_value(&mut out, value);
+    out
+}
+
+fn sha256_hex(value: &Value) -> String {
+    format!("{:x}", Sha256::digest(canonical_json(value).as_bytes()))
+}
+
+/// SHA-256 over the calibration-relevant part of a profile response.
+pub(crate) fn profile_sha256(profile: &Value) -> String {
+    sha256_hex(&profile_fields(profile))
+}
+
+fn profile_fields(profile: &Value) -> Value {
+    Value::Object(
+        PROFILE_FIELDS
+            .iter()
+            .filter_map(|field| Some(((*field).to_string(), profile.get(*field)?.clone())))
+            .collect(),
+    )
+}
+
+/// SHA-256 over the whole certificate except its `digest` field.
+pub(crate) fn certificate_digest(certificate: &Value) -> String {
+    let mut body = certificate.clone();
+    if let Some(object) = body.as_object_mut() {
+        object.remove("digest");
+    }
+    sha256_hex(&body)
+}
+
+fn rfc3339_from_unix_ms(ts_unix_ms: u64) -> Option<String> {
+    if ts_unix_ms == 0 {
+        return None;
+    }
+    chrono::DateTime::from_timestamp_millis(ts_unix_ms as i64).map(|ts| ts.to_rfc3339())
+}
+
+/// Stored verification results (`GET /api/v1/calibration/verify`) reduced to
+/// the curves that were checked, with an overall verdict.
+pub(crate) fn certificate_verification(results: &Value) -> Value {
+    let mut curves = Map::new();
+    let mut passed = true;
+    let mut stale = false;
+    for (kind, record) in results
+        .get("results")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flatten()
+    {
+        if record.is_null() {
+            continue;
+        }
+        let mut record = record.clone();
+        passed &= record.get("passed").and_then(Value::as_bool) == Some(true);
+        stale |= record.get("current").and_then(Value::as_bool) == Some(false);
+        if let Some(object) = record.as_object_mut()
+            && let Some(verified_at) = object
+                .get("ts_unix_ms")
+                .and_then(Value::as_u64)
+                .and_then(rfc3339_from_unix_ms)
+        {
+            object.insert("verified_at".to_string(), json!(verified_at));
+        }
+        curves.insert(kind.clone(), record);
+    }
+    json!({
+        "passed": passed && !curves.is_empty(),
+        "stale": stale,
+        "results": curves,
+    })
+}
+
+pub(crate) fn build_certificate(
+    identity: &Value,
+    profile: &Value,
+    verification: Option<&Value>,
+    slots: Option<&Value>,
+    details: &CertificateDetails,
+    issued_at: chrono::DateTime<Utc>,
+) -> Result<Value, BoxError> {
+    let device_id = stable_hardware_id_from_identity(identity)?;
+    let active_slot = slots.and_then(|slots| {
+        let active = slots.get("active")?.as_u64()?;
+        slots
+            .get("slots")?
+            .as_array()?
+            .iter()
+            .find(|slot| slot.get("slot").and_then(Value::as_u64) == Some(active))
+            .cloned()
+    });
+
+    let mut certificate = json!({
+        "kind": CERTIFICATE_KIND,
+        "schema_version": CERTIFICATE_SCHEMA_VERSION,
+        "certificate_id": format!("{device_id}-{}", issued_at.format("%Y%m%dT%H%M%SZ")),
+        "issued_at": issued_at.to_rfc3339(),
+        "device": {
+            "device_id": device_id,
+            "digital_fw_version": identity.get("digital_fw_version").cloned().unwrap_or(Value::Null),
+            "analog_fw_version": identity.get("analog_fw_version").cloned().unwrap_or(Value::Null),
+            "firmware": identity.get("firmware").cloned().unwrap_or(Value::Null),
+            "calibration_slot": active_slot.unwrap_or(Value::Null),
+        },
+        "reference_meter": {
+            "model": details.meter.model,
+            "serial": details.meter.serial,
+            "cal_due": details.meter.cal_due,
+            "certificate": details.meter.certificate,
+        },
+        "operator": details.operator,
+        "notes": details.notes,
+        "profile": profile_fields(profile),
+        "profile_sha256": profile_sha256(profile),
+        "verification": verification.map(certificate_verification).unwrap_or(Value::Null),
+    });
+    let digest = certificate_digest(&certificate);
+    if let Some(object) = certificate.as_object_mut() {
+        object.insert(
+            "digest".to_string(),
+            json!({"algorithm": "sha256", "value": digest}),
+        );
+    }
+    Ok(certificate)
+}
+
+fn html_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+fn display_value(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => "—".to_string(),
+        Some(Value::String(text)) => html_escape(text),
+        Some(other) => html_escape(&other.to_string()),
+    }
+}
+
+fn push_row(out: &mut String, label: &str, value: Option<&Value>) {
+    let _ = write!(
+        out,
+        "<tr><th>{}</th><td>{}</td></tr>",
+        html_escape(label),
+        display_value(value)
+    );
+}
+
+/// Self-contained HTML rendering: inline styles, no external assets, and the
+/// JSON document embedded so the page alone is enough to re-verify.
+pub(crate) fn certificate_html(certificate: &Value) -> String {
+    let get = |path: &str| certificate.pointer(path);
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html lang=\"en\"><head><meta charset=\"utf-8\">");
+    let _ = write!(
+        out,
+        "<title>Calibration certificate {}</title>",
+        display_value(get("/certificate_id"))
+    );
+    out.push_str(
+        "<style>body{font-family:system-ui,sans-serif;margin:2rem;color:#111}\
+         h1{font-size:1.4rem}h2{font-size:1.1rem;margin-top:1.5rem}\
+         table{border-collapse:collapse;margin:.5rem 0}\
+         th,td{border:1px solid #bbb;padding:.25rem .6rem;text-align:left;font-variant-numeric:tabular-nums}\
+         th{background:#f2f2f2}.pass{color:#17692c;font-weight:600}.fail{color:#a4161a;font-weight:600}\
+         code{word-break:break-all}</style></head><body>",
+    );
+    out.push_str("<h1>LoadLynx calibration certificate</h1><table>");
+    push_row(&mut out, "Certificate", get("/certificate_id"));
+    push_row(&mut out, "Issued", get("/issued_at"));
+    push_row(&mut out, "Operator", get("/operator"));
+    push_row(&mut out, "Notes", get("/notes"));
+    out.push_str("</table>");
+
+    out.push_str("<h2>Device</h2><table>");
+    push_row(&mut out, "Device ID", get("/device/device_id"));
+    push_row(
+        &mut out,
+        "Digital firmware",
+        get("/device/digital_fw_version"),
+    );
+    push_row(
+        &mut out,
+        "Analog firmware",
+        get("/device/analog_fw_version"),
+    );
+    push_row(
+        &mut out,
+        "Calibration slot",
+        get("/device/calibration_slot/name"),
+    );
+    push_row(&mut out, "Profile source", get("/profile/active/source"));
+    push_row(
+        &mut out,
+        "Profile format",
+        get("/profile/active/fmt_version"),
+    );
+    push_row(&mut out, "Hardware revision", get("/profile/active/hw_rev"));
+    out.push_str("</table>");
+
+    out.push_str("<h2>Reference meter</h2><table>");
+    push_row(&mut out, "Model", get("/reference_meter/model"));
+    push_row(&mut out, "Serial", get("/reference_meter/serial"));
+    push_row(&mut out, "Calibration due", get("/reference_meter/cal_due"));
+    push_row(
+        &mut out,
+        "Meter certificate",
+        get("/reference_meter/certificate"),
+    );
+    out.push_str("</table>");
+
+    out.push_str("<h2>Calibration curves</h2>");
+    for (field, title, meas_key) in CURVE_TABLES {
+        let points = get(&format!("/profile/{field}"))
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let unit = if meas_key == "meas_ma" { "mA" } else { "mV" };
+        let _ = write!(
+            out,
+            "<h3>{}</h3><table><tr><th>raw (100 µV)</th><th>measured ({unit})</th></tr>",
+            html_escape(title)
+        );
+        for point in points {
+            let _ = write!(
+                out,
+                "<tr><td>{}</td><td>{}</td></tr>",
+                display_value(point.get("raw_100uv")),
+                display_value(point.get(meas_key))
+            );
+        }
+        out.push_str("</table>");
+    }
+
+    out.push_str("<h2>Verification</h2>");
+    match get("/verification") {
+        Some(verification) if !verification.is_null() => {
+            let passed = verification.get("passed").and_then(Value::as_bool) == Some(true);
+            let _ = write!(
+                out,
+                "<p class=\"{}\">{}</p>",
+                if passed { "pass" } else { "fail" },
+                if passed { "PASSED" } else { "NOT PASSED" }
+            );
+            for (kind, record) in verification
+                .get("results")
+                .and_then(Value::as_object)
+                .into_iter()
+                .flatten()
+            {
+                let _ = write!(
+                    out,
+                    "<h3>{}</h3><p>Verified {}</p><table><tr><th>raw (100 µV)</th><th>reference</th><th>reading</th><th>error</th><th>tolerance</th><th>result</th></tr>",
+                    html_escape(kind),
+                    display_value(record.get("verified_at"))
+                );
+                for point in record
+                    .get("points")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                {
+                    let ok = point.get("passed").and_then(Value::as_bool) == Some(true);
+                    let _ = write!(
+                        out,
+                        "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td class=\"{}\">{}</td></tr>",
+                        display_value(point.get("raw_100uv")),
+                        display_value(point.get("reference")),
+                        display_value(point.get("reading")),
+                        display_value(point.get("error")),
+                        display_value(point.get("tolerance")),
+                        if ok { "pass" } else { "fail" },
+                        if ok { "pass" } else { "fail" }
+                    );
+                }
+                out.push_str("</table>");
+            }
+        }
+        _ => out.push_str("<p>No verification results were stored on the device.</p>"),
+    }
+
+    out.push_str("<h2>Integrity</h2><table>");
+    push_row(&mut out, "Profile SHA-256", get("/profile_sha256"));
+    push_row(&mut out, "Certificate SHA-256", get("/digest/value"));
+    out.push_str("</table>");
+
+    // `</` would end the script element early; `<\/` is the same JSON string.
+    let embedded = serde_json::to_string(certificate)
+        .unwrap_or_default()
+        .replace("</", "<\\/");
+    let _ = writeln!(
+        out,
+        "<script type=\"application/json\" id=\"loadlynx-certificate\">{embedded}</script></body></html>"
+    );
+    out
+}
+
+/// Accept the JSON document or an HTML rendering with it embedded.
+pub(crate) fn certificate_from_text(text: &str) -> Result<Value, BoxError> {
+    const MARKER: &str = "id=\"loadlynx-certificate\">";
+    let json_text = match text.find(MARKER) {
+        Some(start) => {
+            let rest = &text[start + MARKER.len()..];
+            let end = rest
+                .find("</script>")
+                .ok_or("certificate HTML is missing the end of the embedded JSON")?;
+            &rest[..end]
+        }
+        None => text,
+    };
+    let certificate: Value = serde_json::from_str(json_text)?;
+    if certificate.get("kind").and_then(Value::as_str) != Some(CERTIFICATE_KIND) {
+        return Err(format!("not a {CERTIFICATE_KIND} document").into());
+    }
+    if certificate.get("schema_version").and_then(Value::as_u64) != Some(CERTIFICATE_SCHEMA_VERSION)
+    {
+        return Err("unsupported calibration certificate schema_version".into());
+    }
+    Ok(certificate)
+}
+
+/// Checks that need only the document itself.
+pub(crate) fn offline_certificate_checks(certificate: &Value) -> Vec<Value> {
+    let stored_digest = certificate
+        .pointer("/digest/value")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let stored_profile = certificate
+        .get("profile_sha256")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let profile = certificate.get("profile").cloned().unwrap_or(Value::Null);
+    vec![
+        json!({
+            "check": "digest",
+            "ok": certificate.pointer("/digest/algorithm").and_then(Value::as_str) == Some("sha256")
+                && certificate_digest(certificate) == stored_digest,
+        }),
+        json!({
+            "check": "profile_sha256",
+            "ok": profile_sha256(&profile) == stored_profile,
+        }),
+    ]
+}
+
+pub(crate) async fn handle_certificate_issue(
+    client: &Client,
+    default_devd: &str,
+    selector: ApiSelector,
+    allow_interactive: bool,
+    args: CertificateIssueArgs,
+) -> Result<Value, BoxError> {
+    let selector = freeze_api_selector(selector, default_devd, allow_interactive)?;
+    let api = |path: &'static str| {
+        request_api_value(
+            client,
+            default_devd,
+            selector.clone(),
+            allow_interactive,
+            reqwest::Method::GET,
+            path,
+            None,
+            false,
+        )
+    };
+
+    let identity = api("/api/v1/identity").await?;
+    let profile = api("/api/v1/calibration/profile").await?;
+    let mut warnings = Vec::<Value>::new();
+    let verification = match api("/api/v1/calibration/verify").await {
+        Ok(value) => Some(value),
+        Err(err) => {
+            warnings.push(json!(format!("verification results unavailable: {err}")));
+            None
+        }
+    };
+    let slots = match api("/api/v1/calibration/slots").await {
+        Ok(value) => Some(value),
+        Err(err) => {
+            warnings.push(json!(format!("calibration slots unavailable: {err}")));
+            None
+        }
+    };
+
+    let certificate = build_certificate(
+        &identity,
+        &profile,
+        verification.as_ref(),
+        slots.as_ref(),
+        &args.details,
+        Utc::now(),
+    )?;
+    if certificate
+        .pointer("/verification/passed")
+        .and_then(Value::as_bool)
+        != Some(true)
+    {
+        warnings.push(json!("certificate has no passing verification results"));
+    }
+    if certificate
+        .pointer("/verification/stale")
+        .and_then(Value::as_bool)
+        == Some(true)
+    {
+        warnings.push(json!(
+            "some verification results were recorded against a different profile"
+        ));
+    }
+
+    let mut bytes = serde_json::to_vec_pretty(&certificate)?;
+    bytes.push(b'\n');
+    fs::write(&args.out, bytes)?;
+    if let Some(html) = &args.html {
+        fs::write(html, certificate_html(&certificate))?;
+    }
+
+    Ok(json!({
+        "ok": true,
+        "certificate_id": certificate["certificate_id"],
+        "device_id": certificate["device"]["device_id"],
+        "digest": certificate["digest"]["value"],
+        "file": args.out.display().to_string(),
+        "html": args.html.as_ref().map(|path| path.display().to_string()),
+        "warnings": warnings,
+    }))
+}
+
+pub(crate) async fn handle_certificate_verify(
+    client: &Client,
+    default_devd: &str,
+    selector: ApiSelector,
+    allow_interactive: bool,
+    file: &Path,
+    offline: bool,
+) -> Result<Value, BoxError> {
+    let certificate = certificate_from_text(&fs::read_to_string(file)?)?;
+    let mut checks = offline_certificate_checks(&certificate);
+
+    if !offline {
+        let selector = freeze_api_selector(selector, default_devd, allow_interactive)?;
+        let api = |path: &'static str| {
+            request_api_value(
+                client,
+                default_devd,
+                selector.clone(),
+                allow_interactive,
+                reqwest::Method::GET,
+                path,
+                None,
+                false,
+            )
+        };
+        let identity = api("/api/v1/identity").await?;
+        let live_device = stable_hardware_id_from_identity(&identity)?;
+        checks.push(json!({
+            "check": "device_id",
+            "ok": certificate.pointer("/device/device_id").and_then(Value::as_str)
+                == Some(live_device.as_str()),
+            "live": live_device,
+        }));
+        let live_profile = profile_sha256(&api("/api/v1/calibration/profile").await?);
+        checks.push(json!({
+            "check": "live_profile",
+            "ok": certificate.get("profile_sha256").and_then(Value::as_str)
+                == Some(live_profile.as_str()),
+            "live": live_profile,
+        }));
+    }
+
+    let failed = checks
+        .iter()
+        .filter(|check| check.get("ok").and_then(Value::as_bool) != Some(true))
+        .filter_map(|check| check.get("check").and_then(Value::as_str))
+        .collect::<Vec<_>>();
+    if !failed.is_empty() {
+        return Err(format!(
+            "calibration certificate verification failed: {}",
+            failed.join(", ")
+        )
+        .into());
+    }
+    Ok(json!({
+        "ok": true,
+        "certificate_id": certificate["certificate_id"],
+        "offline": offline,
+        "checks": checks,
+    }))
+}
@@ -66,6 +66,9 @@ pub(crate) struct SavedUsbTransport {
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub(crate) struct SavedHttpTransport {
     pub(crate) url: String,
+    /// Bearer token issued by the device's `/api/v1/auth` pairing flow.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) token: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -363,6 +366,7 @@ async fn bind_http_device(
         None,
         Some(SavedHttpTransport {
             url: url.to_string(),
+            token: None,
         }),
         now,
     ))
@@ -777,7 +781,21 @@ pub(crate) fn upsert_hardware_transport(
         }
         match transport {
             SavedTransport::Usb => existing.transports.usb = usb,
-            SavedTransport::Http => existing.transports.http = http,
+            SavedTransport::Http => {
+                // Re-binding the same URL must not drop a token paired earlier.
+                let previous_token = existing
+                    .transports
+                    .http
+                    .as_ref()
+                    .filter(|previous| http.as_ref().is_some_and(|next| next.url == previous.url))
+                    .and_then(|previous| previous.token.clone());
+                existing.transports.http = http.map(|mut next| {
+                    if next.token.is_none() {
+                        next.token = previous_token;
+                    }
+                    next
+                });
+            }
         }
         existing.last_transport = Some(transport);
         existing.last_seen_unix_seconds = Some(now);
@@ -999,7 +1017,7 @@ pub(crate) fn migrate_legacy_hardware_registry(
                     None,
                     SavedTransport::Http,
                     None,
-                    Some(SavedHttpTransport { url }),
+                    Some(SavedHttpTransport { url, token: None }),
                     now,
                 );
             }
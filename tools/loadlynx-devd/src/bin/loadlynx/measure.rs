@@ -0,0 +1,130 @@
+use super::*;
+use std::time::{Duration, Instant};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+const IR_POLL_INTERVAL_MS: u64 = 250;
+/// Added to the firmware's nominal run time before giving up on a run.
+const IR_TIMEOUT_SLACK_MS: u64 = 5_000;
+
+/// `loadlynx measure ir` options; unset fields fall back to firmware defaults.
+pub(crate) struct IrMeasureArgs {
+    pub(crate) low_ma: Option<u32>,
+    pub(crate) high_ma: Option<u32>,
+    pub(crate) cycles: Option<u8>,
+    pub(crate) settle_ms: Option<u32>,
+    pub(crate) sample_ms: Option<u32>,
+    pub(crate) wait: bool,
+}
+
+impl IrMeasureArgs {
+    pub(crate) fn body(&self) -> Value {
+        let mut body = Map::new();
+        let fields = [
+            ("low_ma", self.low_ma),
+            ("high_ma", self.high_ma),
+            ("cycles", self.cycles.map(u32::from)),
+            ("settle_ms", self.settle_ms),
+            ("sample_ms", self.sample_ms),
+        ];
+        for (key, value) in fields {
+            if let Some(value) = value {
+                body.insert(key.to_string(), json!(value));
+            }
+        }
+        Value::Object(body)
+    }
+}
+
+/// Nominal run time of the config echoed by the firmware: every cycle settles
+/// and samples once at each of the two current levels.
+pub(crate) fn ir_measure_run_ms(config: Option<&Value>) -> u64 {
+    let field = |key: &str| {
+        config
+            .and_then(|config| config.get(key))
+            .and_then(Value::as_u64)
+            .unwrap_or(0)
+    };
+    field("cycles") * 2 * (field("settle_ms") + field("sample_ms"))
+}
+
+pub(crate) async fn handle_measure_ir(
+    client: &Client,
+    default_devd: &str,
+    selector: ApiSelector,
+    allow_interactive: bool,
+    args: IrMeasureArgs,
+) -> Result<Value, BoxError> {
+    let selector = freeze_api_selector(selector, default_devd, allow_interactive)?;
+    let started = request_api_value(
+        client,
+        default_devd,
+        selector.clone(),
+        allow_interactive,
+        reqwest::Method::POST,
+        "/api/v1/measure/ir",
+        Some(args.body()),
+        false,
+    )
+    .await?;
+    if !args.wait {
+        return Ok(started);
+    }
+
+    let deadline = Instant::now()
+        + Duration::from_millis(ir_measure_run_ms(started.get("config")) + IR_TIMEOUT_SLACK_MS);
+    let mut reported_cycle = None;
+    loop {
+        tokio::time::sleep(Duration::from_millis(IR_POLL_INTERVAL_MS)).await;
+        let view = request_api_value(
+            client,
+            default_devd,
+            selector.clone(),
+            allow_interactive,
+            reqwest::Method::GET,
+            "/api/v1/measure/ir",
+            None,
+            false,
+        )
+        .await?;
+        match view.get("state").and_then(Value::as_str) {
+            Some("done") => return Ok(view),
+            Some("failed") => {
+                let code = view
+                    .pointer("/error/code")
+                    .and_then(Value::as_str)
+                    .unwrap_or("UNKNOWN");
+                let message = view
+                    .pointer("/error/message")
+                    .and_then(Value::as_str)
+                    .unwrap_or("no details");
+                return Err(format!("IR measurement failed ({code}): {message}").into());
+            }
+            Some("running") if Instant::now() < deadline => {
+                if let (Some(cycle), Some(cycles)) = (
+                    view.get("cycle").and_then(Value::as_u64),
+                    view.pointer("/config/cycles").and_then(Value::as_u64),
+                ) && reported_cycle != Some(cycle)
+                {
+                    reported_cycle = Some(cycle);
+                    eprintln!("measuring: cycle {}/{}", cycle + 1, cycles);
+                }
+            }
+            Some("running") => {
+                let _ = request_api_value(
+                    client,
+                    default_devd,
+                    selector.clone(),
+                    allow_interactive,
+                    reqwest::Method::POST,
+                    "/api/v1/measure/ir/cancel",
+                    Some(json!({})),
+                    false,
+                )
+                .await;
+                return Err("IR measurement timed out; cancel requested".into());
+            }
+            _ => return Err("IR measurement is no longer running (cancelled?)".into()),
+        }
+    }
+}
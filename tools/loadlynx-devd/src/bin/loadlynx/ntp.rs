@@ -0,0 +1,37 @@
+use super::*;
+use loadlynx_sntp::{self as sntp, PACKET_LEN};
+use tokio::net::UdpSocket;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+pub(crate) fn host_unix_ms() -> u64 {
+    Utc::now().timestamp_millis().max(0) as u64
+}
+
+/// `loadlynx time serve-ntp`: a minimal stratum-1 SNTP server backed by this
+/// host's clock, for pointing a device at (`loadlynx time server <ip>:<port>`)
+/// on benches without internet access.
+pub(crate) async fn handle_time_serve_ntp(
+    bind: &str,
+    count: Option<usize>,
+) -> Result<Value, BoxError> {
+    let socket = UdpSocket::bind(bind).await?;
+    let local = socket.local_addr()?;
+    eprintln!("serving SNTP on {local}");
+
+    let mut request = [0u8; 512];
+    let mut reply = [0u8; PACKET_LEN];
+    let mut answered = 0usize;
+    while count.is_none_or(|count| answered < count) {
+        let (len, peer) = socket.recv_from(&mut request).await?;
+        let received_ms = host_unix_ms();
+        if sntp::encode_reply(&request[..len], &mut reply, received_ms, host_unix_ms()).is_err() {
+            eprintln!("ignored non-SNTP datagram from {peer}");
+            continue;
+        }
+        socket.send_to(&reply, peer).await?;
+        answered += 1;
+        eprintln!("answered {peer}");
+    }
+    Ok(json!({"bind": local.to_string(), "answered": answered}))
+}
@@ -0,0 +1,116 @@
+use super::*;
+use loadlynx_devd::{load_lan_flash_artifact, upload_ota_image};
+use std::time::{Duration, Instant};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+const OTA_TOKEN_ENV: &str = "LOADLYNX_OTA_TOKEN";
+/// Upload reply to first answer from the new image: reboot plus Wi-Fi join.
+const REBOOT_TIMEOUT: Duration = Duration::from_secs(60);
+
+pub(crate) struct LanFlashRequest {
+    pub(crate) selector: ApiSelector,
+    pub(crate) manifest_path: Option<String>,
+    pub(crate) artifact: Option<String>,
+    pub(crate) ota_token: Option<String>,
+    pub(crate) dry_run: bool,
+    pub(crate) confirmation: Option<String>,
+    pub(crate) acknowledge_non_project_firmware: bool,
+}
+
+/// `loadlynx flash digital --transport lan`: push the catalog's `ota_image`
+/// through the device's `POST /api/v1/firmware` and wait for it to come back
+/// on the new slot. No devd or USB cable is involved, so the artifact comes
+/// from `--manifest-path` rather than devd's artifact store.
+pub(crate) async fn handle_flash_digital_lan(
+    client: &Client,
+    devd: &str,
+    allow_interactive: bool,
+    request: LanFlashRequest,
+) -> Result<Value, BoxError> {
+    let manifest_path = request.manifest_path.ok_or(
+        "--transport lan requires --manifest-path (firmware catalog or artifact manifest)",
+    )?;
+    let (artifact, file) = load_lan_flash_artifact(
+        &manifest_path,
+        request.artifact.as_deref(),
+        request.acknowledge_non_project_firmware,
+    )?;
+    let Some(url) = freeze_api_selector(request.selector, devd, allow_interactive)?.url else {
+        return Err("--transport lan needs a device with a saved HTTP transport, or --url".into());
+    };
+    let before =
+        request_http_value(client, &url, reqwest::Method::GET, "/api/v1/firmware", None).await?;
+    let plan = json!({
+        "transport": "lan",
+        "url": url,
+        "artifact_id": artifact.artifact_id,
+        "ota_image": {"path": file.path, "size": file.size, "sha256": file.sha256},
+        "firmware": before,
+    });
+    if request.dry_run {
+        return Ok(json!({"ok": true, "dry_run": true, "action": "flash", "plan": plan}));
+    }
+    let confirmed = request
+        .confirmation
+        .as_deref()
+        .map(str::trim)
+        .is_some_and(|value| value.eq_ignore_ascii_case(FLASH_CONFIRMATION_TEXT));
+    if !confirmed {
+        return Err(
+            format!("type `{FLASH_CONFIRMATION_TEXT}` to confirm real digital flash").into(),
+        );
+    }
+    let token = request
+        .ota_token
+        .or_else(|| env::var(OTA_TOKEN_ENV).ok())
+        .ok_or(
+            "LAN flash needs the device OTA token: read it over USB with `loadlynx firmware ota-token`, then pass --ota-token or set LOADLYNX_OTA_TOKEN",
+        )?;
+
+    let upload = upload_ota_image(client, &url, &token, &file).await?;
+    let slot = upload.get("slot").and_then(Value::as_str);
+    let after = wait_for_new_image(client, &url, slot).await?;
+    Ok(json!({
+        "ok": true,
+        "dry_run": false,
+        "action": "flash",
+        "plan": plan,
+        "upload": upload,
+        "post_flash_firmware": after,
+    }))
+}
+
+async fn wait_for_new_image(
+    client: &Client,
+    url: &str,
+    slot: Option<&str>,
+) -> Result<Value, BoxError> {
+    // The device replies before it resets; don't mistake the old image for
+    // the new one.
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    let deadline = Instant::now() + REBOOT_TIMEOUT;
+    loop {
+        match request_http_value(client, url, reqwest::Method::GET, "/api/v1/firmware", None).await
+        {
+            Ok(firmware) => {
+                let running = firmware.get("running_slot").and_then(Value::as_str);
+                if slot.is_some() && running != slot {
+                    return Err(format!(
+                        "device came back on {} instead of {}",
+                        running.unwrap_or("<unknown>"),
+                        slot.unwrap_or("<unknown>")
+                    )
+                    .into());
+                }
+                return Ok(firmware);
+            }
+            Err(_) if Instant::now() < deadline => {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+            Err(error) => {
+                return Err(format!("device did not come back after the update: {error}").into());
+            }
+        }
+    }
+}
@@ -1,5 +1,8 @@
+use super::hardware::{HardwareRegistry, hardware_registry_path, read_hardware_registry};
 use super::*;
 
+const API_TOKEN_ENV: &str = "LOADLYNX_API_TOKEN";
+
 #[derive(Debug, Clone)]
 pub(crate) struct ApiSelector {
     pub(crate) url: Option<String>,
@@ -18,7 +21,8 @@ pub(crate) async fn request_api_value(
     allow_insecure_lan_wifi: bool,
 ) -> Result<Value, Box<dyn std::error::Error + Send + Sync>> {
     ensure_one_api_selector(selector.url.as_ref(), selector.device.as_ref())?;
-    let is_wifi_write = path == "/api/v1/wifi"
+    // Writes that carry (or drop) a PSK; reordering and removal do not.
+    let is_wifi_write = matches!(path, "/api/v1/wifi" | "/api/v1/wifi/networks/add")
         && (method == reqwest::Method::POST || method == reqwest::Method::DELETE);
     if let Some(url) = selector.url {
         if is_wifi_write && !allow_insecure_lan_wifi {
@@ -76,6 +80,9 @@ pub(crate) async fn request_http_value(
     body: Option<Value>,
 ) -> Result<Value, Box<dyn std::error::Error + Send + Sync>> {
     let mut request = client.request(method, api_url(base, path)?);
+    if let Some(token) = http_api_token(base) {
+        request = request.bearer_auth(token);
+    }
     if let Some(body) = body {
         request = request.json(&body);
     }
@@ -89,6 +96,29 @@ pub(crate) async fn request_http_value(
     }
 }
 
+/// Token for the device API at `base`: `LOADLYNX_API_TOKEN` wins, otherwise
+/// the token saved with the matching HTTP transport by `loadlynx auth pair`.
+pub(crate) fn http_api_token(base: &str) -> Option<String> {
+    if let Some(token) = env::var(API_TOKEN_ENV)
+        .ok()
+        .filter(|token| !token.is_empty())
+    {
+        return Some(token);
+    }
+    let registry = read_hardware_registry(&hardware_registry_path().ok()?).ok()?;
+    saved_http_token(&registry, base)
+}
+
+pub(crate) fn saved_http_token(registry: &HardwareRegistry, base: &str) -> Option<String> {
+    let base = base.trim_end_matches('/');
+    registry
+        .hardware
+        .iter()
+        .filter_map(|hardware| hardware.transports.http.as_ref())
+        .find(|http| http.url.trim_end_matches('/') == base)
+        .and_then(|http| http.token.clone())
+}
+
 pub(crate) async fn request_devd_usb_value(
     client: &Client,
     resolved: &ResolvedUsbHardware,
@@ -538,6 +568,47 @@ pub(crate) async fn run_monitor(
     }
 }
 
+/// Keep a devd SCPI endpoint open for the resolved USB device until the
+/// process is interrupted. The bridge is owned by the CLI lease, so devd tears
+/// it down once heartbeats stop.
+pub(crate) async fn run_scpi_bridge(
+    client: &Client,
+    resolved: ResolvedUsbHardware,
+    port: u16,
+) -> Result<Value, Box<dyn std::error::Error + Send + Sync>> {
+    let (lease, lease_device) = create_cli_lease_for_resolved_usb(client, &resolved).await?;
+    let heartbeat = spawn_cli_lease_heartbeat(client.clone(), resolved.devd.clone(), lease.clone());
+    let started = request_devd_value(
+        &resolved.devd,
+        reqwest::Method::POST,
+        &format!(
+            "/api/v1/devices/{}/scpi?lease_id={}",
+            lease_device, lease.lease_id
+        ),
+        Some(json!({"port": port})),
+    )
+    .await;
+    let started = match started {
+        Ok(started) => started,
+        Err(error) => {
+            heartbeat.abort();
+            release_cli_lease(client, &resolved.devd, &lease.lease_id).await?;
+            return Err(error);
+        }
+    };
+    let bridge = started.get("bridge").unwrap_or(&started);
+    println!(
+        "SCPI bridge for {lease_device} listening on {} ({}); Ctrl-C to stop",
+        bridge.get("address").and_then(Value::as_str).unwrap_or("-"),
+        bridge
+            .get("visa_resource")
+            .and_then(Value::as_str)
+            .unwrap_or("-")
+    );
+    let _ = heartbeat.await;
+    Err("USB lease heartbeat stopped; SCPI bridge closed".into())
+}
+
 fn print_session_delta(
     session: &Value,
     seen: &mut HashSet<String>,
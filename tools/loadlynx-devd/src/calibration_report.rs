@@ -0,0 +1,229 @@
+use loadlynx_calibration_format::analysis::{self, CurveReport, Warning};
+use loadlynx_calibration_format::{CalPoint, CurveKind, MAX_POINTS_V3};
+use serde_json::{Map, Value, json};
+
+const CURVES: [(&str, &str, CurveKind); 4] = [
+    ("current_ch1", "current_ch1_points", CurveKind::CurrentCh1),
+    ("current_ch2", "current_ch2_points", CurveKind::CurrentCh2),
+    ("v_local", "v_local_points", CurveKind::VLocal),
+    ("v_remote", "v_remote_points", CurveKind::VRemote),
+];
+
+/// Build a calibration quality report from a calibration document.
+///
+/// Accepts a profile (`GET /api/v1/calibration/profile`), a backup envelope
+/// (`sections.calibration`) or an apply/commit body (`{kind, points}`).
+/// Points may be objects or compact arrays, as in backups.
+pub fn calibration_report(document: &Value) -> Result<Value, String> {
+    let profile = document
+        .pointer("/sections/calibration")
+        .unwrap_or(document);
+    let mut curves = Map::new();
+    if let Some(kind) = profile.get("kind").and_then(Value::as_str) {
+        let (name, _, curve_kind) = CURVES
+            .iter()
+            .copied()
+            .find(|(name, _, _)| *name == kind)
+            .ok_or_else(|| format!("unknown calibration kind: {kind}"))?;
+        let points = profile
+            .get("points")
+            .ok_or("calibration body missing points")?;
+        curves.insert(name.to_string(), curve_report(curve_kind, points)?);
+    } else {
+        for (name, field, curve_kind) in CURVES {
+            let Some(points) = profile.get(field) else {
+                continue;
+            };
+            if points.as_array().is_none_or(Vec::is_empty) {
+                continue;
+            }
+            curves.insert(name.to_string(), curve_report(curve_kind, points)?);
+        }
+    }
+    if curves.is_empty() {
+        return Err("no calibration points found (factory defaults or unrecognized file)".into());
+    }
+
+    let warning_count = curves
+        .values()
+        .filter_map(|curve| curve.get("warnings").and_then(Value::as_array))
+        .map(Vec::len)
+        .sum::<usize>();
+    let worst_accuracy_ppm = curves
+        .values()
+        .filter_map(|curve| curve.get("est_accuracy_ppm").and_then(Value::as_i64))
+        .max();
+    let mut report = json!({
+        "curves": curves,
+        "summary": {
+            "warnings": warning_count,
+            "worst_est_accuracy_ppm": worst_accuracy_ppm,
+        },
+    });
+    if let Some(active) = profile.get("active") {
+        report["active"] = active.clone();
+    }
+    Ok(report)
+}
+
+fn curve_report(kind: CurveKind, points: &Value) -> Result<Value, String> {
+    let array = points
+        .as_array()
+        .ok_or("calibration curve points must be an array")?;
+    if array.len() > MAX_POINTS_V3 {
+        return Err(format!(
+            "calibration curve has {} points (max {MAX_POINTS_V3})",
+            array.len()
+        ));
+    }
+    let points = array
+        .iter()
+        .map(|point| parse_point(kind, point))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(curve_report_json(&analysis::analyze_curve(kind, &points)))
+}
+
+fn is_current(kind: CurveKind) -> bool {
+    matches!(kind, CurveKind::CurrentCh1 | CurveKind::CurrentCh2)
+}
+
+fn parse_point(kind: CurveKind, point: &Value) -> Result<CalPoint, String> {
+    let (raw, dac, meas) = if let Some(items) = point.as_array() {
+        let item = |idx: usize| items.get(idx).and_then(Value::as_i64);
+        if is_current(kind) {
+            (item(0), item(1), item(2))
+        } else {
+            (item(0), Some(0), item(1))
+        }
+    } else {
+        let object = point
+            .as_object()
+            .ok_or("calibration point must be an object or compact array")?;
+        let field = |names: &[&str]| names.iter().find_map(|n| object.get(*n)?.as_i64());
+        let raw = field(&["raw_100uv", "raw"]);
+        if is_current(kind) {
+            (
+                raw,
+                field(&["raw_dac_code", "dac_code"]).or(Some(0)),
+                field(&["meas_ma", "ma"]),
+            )
+        } else {
+            (raw, Some(0), field(&["meas_mv", "mv"]))
+        }
+    };
+    let raw = raw
+        .and_then(|v| i16::try_from(v).ok())
+        .ok_or("calibration point raw_100uv missing or out of range")?;
+    let dac = dac
+        .and_then(|v| u16::try_from(v).ok())
+        .ok_or("calibration point raw_dac_code out of range")?;
+    let meas = meas
+        .and_then(|v| i32::try_from(v).ok())
+        .ok_or("calibration point measured value missing or out of range")?;
+    Ok(CalPoint {
+        raw_100uv: raw,
+        raw_dac_code: dac,
+        meas_physical: meas,
+    })
+}
+
+fn curve_report_json(report: &CurveReport) -> Value {
+    let unit = if is_current(report.kind) { "mA" } else { "mV" };
+    let segments = report
+        .segments
+        .iter()
+        .map(|s| {
+            json!({
+                "raw_lo": s.raw_lo,
+                "raw_hi": s.raw_hi,
+                "meas_lo": s.meas_lo,
+                "meas_hi": s.meas_hi,
+                "gain_per_mv": s.gain_milli_per_mv as f64 / 1000.0,
+                "offset": s.offset,
+                "gain_deviation_ppm": s.gain_deviation_ppm,
+            })
+        })
+        .collect::<Vec<_>>();
+    let residuals = report
+        .residuals
+        .iter()
+        .map(|r| {
+            json!({
+                "raw_100uv": r.raw_100uv,
+                "meas": r.meas_physical,
+                "residual": r.residual,
+                "loo_residual": r.loo_residual,
+            })
+        })
+        .collect::<Vec<_>>();
+    let warnings = report
+        .warnings
+        .iter()
+        .map(|w| warning_json(w, unit))
+        .collect::<Vec<_>>();
+    json!({
+        "unit": unit,
+        "source_points": report.source_points,
+        "curve_points": report.curve_points,
+        "nominal_range": [report.nominal_min, report.nominal_max],
+        "monotonic": report.monotonic,
+        "min_meas_step": report.min_meas_step,
+        "min_raw_step": report.min_raw_step,
+        "est_accuracy_abs": report.est_accuracy_abs,
+        "est_accuracy_ppm": report.est_accuracy_ppm,
+        "segments": segments,
+        "residuals": residuals,
+        "warnings": warnings,
+    })
+}
+
+fn warning_json(warning: &Warning, unit: &str) -> Value {
+    let (code, message) = match *warning {
+        Warning::TooFewPoints { count } => (
+            "too_few_points",
+            format!("only {count} usable point(s); at least 2 are needed for a curve"),
+        ),
+        Warning::NotMonotonic { index } => (
+            "not_monotonic",
+            format!("point {index} does not increase over the previous point"),
+        ),
+        Warning::DuplicateRaw { raw_100uv } => (
+            "duplicate_raw",
+            format!("raw {raw_100uv} appears more than once; the last point is used"),
+        ),
+        Warning::ExtrapolatesBelow {
+            meas_min,
+            nominal_min,
+        } => (
+            "extrapolates_below",
+            format!(
+                "lowest point {meas_min} {unit}; below it down to {nominal_min} {unit} is extrapolated"
+            ),
+        ),
+        Warning::ExtrapolatesAbove {
+            meas_max,
+            nominal_max,
+        } => (
+            "extrapolates_above",
+            format!(
+                "highest point {meas_max} {unit}; above it up to {nominal_max} {unit} is extrapolated"
+            ),
+        ),
+        Warning::SmallStep { index, step } => (
+            "small_step",
+            format!("point {index} is only {step} {unit} above the previous point"),
+        ),
+        Warning::GainOutlier {
+            segment,
+            deviation_ppm,
+        } => (
+            "gain_outlier",
+            format!("segment {segment} gain deviates {deviation_ppm} ppm from the end-to-end gain"),
+        ),
+        Warning::LargeResidual { index, residual } => (
+            "large_residual",
+            format!("point {index} is {residual} {unit} off the line through its neighbours"),
+        ),
+    };
+    json!({"code": code, "message": message})
+}
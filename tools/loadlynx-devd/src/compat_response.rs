@@ -141,7 +141,7 @@ pub(crate) fn expand_compact_calibration_profile(data: Value) -> Result<Value, H
         .and_then(Value::as_str)
         .unwrap_or("unknown");
 
-    Ok(json!({
+    let mut profile = json!({
         "active": {
             "source": source,
             "fmt_version": fmt_version,
@@ -152,7 +152,12 @@ pub(crate) fn expand_compact_calibration_profile(data: Value) -> Result<Value, H
         "current_ch2_points": expand_compact_current_curve(compact, "c2")?,
         "v_local_points": expand_compact_voltage_curve(compact, "vl")?,
         "v_remote_points": expand_compact_voltage_curve(compact, "vr")?,
-    }))
+    });
+    // Firmware before calibration fmt v4 does not send `tc`.
+    if compact.contains_key("tc") {
+        profile["temp_comp"] = expand_compact_temp_comp(compact)?;
+    }
+    Ok(profile)
 }
 
 pub(crate) fn serial_response_data(response: Value, operation: &str) -> Result<Value, HttpError> {
@@ -216,6 +221,36 @@ fn expand_compact_current_curve(
     Ok(Value::Array(points))
 }
 
+fn expand_compact_temp_comp(compact: &serde_json::Map<String, Value>) -> Result<Value, HttpError> {
+    let entries = compact_array(compact, "tc")?;
+    let kinds = ["current_ch1", "current_ch2", "v_local", "v_remote"];
+    if entries.len() != kinds.len() {
+        return Err(compact_calibration_error("temp comp must have 4 entries"));
+    }
+    let mut out = serde_json::Map::new();
+    for (kind, entry) in kinds.iter().zip(entries) {
+        let tuple = entry
+            .as_array()
+            .filter(|t| t.len() == 4)
+            .ok_or_else(|| compact_calibration_error("temp comp tuple must have 4 items"))?;
+        let sensor = match compact_u64(&tuple[0], "sensor")? {
+            0 => "sink_core",
+            1 => "mcu",
+            _ => return Err(compact_calibration_error("temp comp sensor is unknown")),
+        };
+        out.insert(
+            (*kind).to_string(),
+            json!({
+                "sensor": sensor,
+                "t_ref_mc": compact_i64(&tuple[1], "t_ref_mc")?,
+                "gain_ppm_per_c": compact_i64(&tuple[2], "gain_ppm_per_c")?,
+                "offset_micro_per_c": compact_i64(&tuple[3], "offset_micro_per_c")?,
+            }),
+        );
+    }
+    Ok(Value::Object(out))
+}
+
 fn expand_compact_voltage_curve(
     compact: &serde_json::Map<String, Value>,
     key: &str,
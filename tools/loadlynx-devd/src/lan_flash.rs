@@ -0,0 +1,167 @@
+use std::time::Duration;
+
+use serde_json::Value;
+
+use crate::{
+    ArtifactFile, FirmwareArtifact, HttpError, TargetKind, is_loadlynx_project_artifact,
+    read_manifest, selected_analog_bridge_image_file, selected_ota_image_file,
+    verify_artifact_files,
+};
+
+/// Flash erases make the device slow to drain the body; allow well beyond a
+/// normal API call.
+const UPLOAD_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Load the digital artifact for a LAN (OTA) flash from a manifest or catalog.
+///
+/// Applies the same gates as a USB flash: the artifact must target the digital
+/// board, non-project firmware needs an explicit acknowledgement, and every
+/// file must match its catalog SHA-256. Returns the artifact together with its
+/// `ota_image` file, whose digest the device re-checks after writing.
+pub fn load_lan_flash_artifact(
+    manifest_path: &str,
+    artifact_id: Option<&str>,
+    acknowledge_non_project_firmware: bool,
+) -> Result<(FirmwareArtifact, ArtifactFile), String> {
+    let artifact = load_catalog_artifact(
+        manifest_path,
+        artifact_id,
+        TargetKind::DigitalEsp32s3,
+        acknowledge_non_project_firmware,
+    )?;
+    let file = selected_ota_image_file(&artifact)
+        .map_err(describe)?
+        .clone();
+    Ok((artifact, file))
+}
+
+/// Load the analog artifact for a flash through the digital board's UART
+/// bootloader bridge, with the same gates as [`load_lan_flash_artifact`].
+/// Returns the raw `image` file for 0x0800_0000, which the digital firmware
+/// streams into the STM32 ROM bootloader and checks against its SHA-256.
+pub fn load_bridge_flash_artifact(
+    manifest_path: &str,
+    artifact_id: Option<&str>,
+    acknowledge_non_project_firmware: bool,
+) -> Result<(FirmwareArtifact, ArtifactFile), String> {
+    let artifact = load_catalog_artifact(
+        manifest_path,
+        artifact_id,
+        TargetKind::AnalogStm32g431,
+        acknowledge_non_project_firmware,
+    )?;
+    let file = selected_analog_bridge_image_file(&artifact)
+        .map_err(describe)?
+        .clone();
+    Ok((artifact, file))
+}
+
+fn load_catalog_artifact(
+    manifest_path: &str,
+    artifact_id: Option<&str>,
+    target: TargetKind,
+    acknowledge_non_project_firmware: bool,
+) -> Result<FirmwareArtifact, String> {
+    let (target_name, board) = match target {
+        TargetKind::AnalogStm32g431 => ("analog_stm32g431", "analog"),
+        _ => ("digital_esp32s3", "digital"),
+    };
+    let artifacts = read_manifest(manifest_path).map_err(describe)?;
+    let artifact = match artifact_id {
+        Some(artifact_id) => artifacts
+            .into_iter()
+            .find(|artifact| artifact.artifact_id == artifact_id)
+            .ok_or_else(|| format!("artifact {artifact_id} is not in {manifest_path}"))?,
+        None => {
+            let mut matching = artifacts
+                .into_iter()
+                .filter(|artifact| artifact.target == target);
+            match (matching.next(), matching.next()) {
+                (Some(artifact), None) => artifact,
+                (None, _) => {
+                    return Err(format!(
+                        "{manifest_path} contains no {target_name} artifact"
+                    ));
+                }
+                (Some(_), Some(_)) => {
+                    return Err(format!(
+                        "{manifest_path} contains several {board} artifacts; pass --artifact"
+                    ));
+                }
+            }
+        }
+    };
+    if artifact.target != target {
+        return Err(format!(
+            "artifact {} does not target the {board} board",
+            artifact.artifact_id
+        ));
+    }
+    if !is_loadlynx_project_artifact(&artifact) && !acknowledge_non_project_firmware {
+        return Err(
+            "non-project or unknown firmware requires --acknowledge-non-project-firmware".into(),
+        );
+    }
+    verify_artifact_files(&artifact).map_err(describe)?;
+    Ok(artifact)
+}
+
+/// Upload `file` to a device's `POST /api/v1/firmware` and return its reply.
+///
+/// The device streams the body into its inactive OTA slot, verifies it against
+/// `X-LoadLynx-Image-SHA256` and reboots into it after replying.
+pub async fn upload_ota_image(
+    client: &reqwest::Client,
+    base_url: &str,
+    token: &str,
+    file: &ArtifactFile,
+) -> Result<Value, String> {
+    upload_image(client, base_url, "/api/v1/firmware", token, file).await
+}
+
+/// Upload `file` to a device's `POST /api/v1/analog/firmware`, which writes it
+/// to the analog STM32 through the UART bootloader bridge and replies once the
+/// new analog image has been started.
+pub async fn upload_analog_image(
+    client: &reqwest::Client,
+    base_url: &str,
+    token: &str,
+    file: &ArtifactFile,
+) -> Result<Value, String> {
+    upload_image(client, base_url, "/api/v1/analog/firmware", token, file).await
+}
+
+async fn upload_image(
+    client: &reqwest::Client,
+    base_url: &str,
+    path: &str,
+    token: &str,
+    file: &ArtifactFile,
+) -> Result<Value, String> {
+    let bytes = std::fs::read(&file.path).map_err(|error| format!("{}: {error}", file.path))?;
+    let url = format!("{}{path}", base_url.trim_end_matches('/'));
+    let response = client
+        .post(url)
+        .timeout(UPLOAD_TIMEOUT)
+        .bearer_auth(token)
+        .header("X-LoadLynx-Image-SHA256", &file.sha256)
+        .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
+        .body(bytes)
+        .send()
+        .await
+        .map_err(|error| error.to_string())?;
+    let status = response.status();
+    let value = response
+        .json::<Value>()
+        .await
+        .map_err(|error| error.to_string())?;
+    if status.is_success() {
+        Ok(value)
+    } else {
+        Err(format!("HTTP {status}: {value}"))
+    }
+}
+
+fn describe(error: HttpError) -> String {
+    format!("{}: {}", error.0.code, error.0.message)
+}
@@ -35,9 +35,17 @@ use tower_http::{
     services::ServeDir,
 };
 
+mod calibration_report;
 mod compat_response;
+mod lan_flash;
+mod scpi_bridge;
 mod serial_response;
 
+pub use calibration_report::calibration_report;
+pub use lan_flash::{
+    load_bridge_flash_artifact, load_lan_flash_artifact, upload_analog_image, upload_ota_image,
+};
+
 use compat_response::{
     expand_compact_calibration_profile, identity_data_from_serial_response,
     merge_presets_from_data, pd_post_response_data, pd_response_data, presets_data_from_map,
@@ -65,6 +73,8 @@ const DEFAULT_PROBE_RS: &str = "probe-rs";
 const ANALOG_PROBE_CHIP: &str = "STM32G431CB";
 const ANALOG_PROBE_PROTOCOL: &str = "swd";
 const ANALOG_PROBE_SPEED_KHZ: u32 = 4_000;
+/// Start of the STM32G431 flash; the analog bridge image is written from here.
+const ANALOG_FLASH_BASE: u64 = 0x0800_0000;
 pub const WEB_LEASE_HEARTBEAT_INTERVAL_MS: u64 = 2_000;
 pub const WEB_LEASE_TTL_MS: u64 = 60_000;
 const EVENT_LIMIT: usize = 1_000;
@@ -79,6 +89,9 @@ const SERIAL_WIFI_WRITE_PROTOCOL_TIMEOUT_MS: u64 = 160;
 const SERIAL_WIFI_STATUS_PROTOCOL_TIMEOUT_MS: u64 = 90;
 const SERIAL_IMMEDIATE_PROTOCOL_TIMEOUT_MS: u64 = 500;
 const SERIAL_WIFI_WAIT_PROTOCOL_TIMEOUT_MS: u64 = 35_000;
+/// Analog update steps: entering the STM32 bootloader plus a full-flash erase,
+/// or 1 KiB written and read back through the UART bridge.
+const SERIAL_ANALOG_FLASH_PROTOCOL_TIMEOUT_MS: u64 = 10_000;
 const SERIAL_STATUS_CACHE_MAX_AGE_MS: i64 = 1_500;
 const SERIAL_BACKGROUND_STATUS_REFRESH_MS: u64 = 200;
 const SERIAL_WORKER_TICK_MS: u64 = 20;
@@ -181,6 +194,7 @@ struct DevdState {
     artifacts: HashMap<String, FirmwareArtifact>,
     leases: HashMap<String, WebLease>,
     events: VecDeque<DevdEvent>,
+    scpi_bridges: HashMap<String, scpi_bridge::ScpiBridge>,
 }
 
 #[derive(Default)]
@@ -847,6 +861,23 @@ async fn dispatch_ipc_operation_result(
                 .await?
                 .0)
         }
+        "devices.scpi.get" => {
+            let id = required_string(&params, "device_id")?;
+            Ok(scpi_bridge_get(State(state), Path(id)).await?.0)
+        }
+        "devices.scpi.start" => {
+            let id = required_string(&params, "device_id")?;
+            let (query, body) = compat_query_and_body(params)?;
+            Ok(
+                scpi_bridge_start(State(state), Path(id), Query(query), body.to_string())
+                    .await?
+                    .0,
+            )
+        }
+        "devices.scpi.stop" => {
+            let id = required_string(&params, "device_id")?;
+            Ok(scpi_bridge_stop(State(state), Path(id)).await?.0)
+        }
         "serial.lease.create" => {
             let input: LeaseRequest = serde_json::from_value(params)
                 .map_err(|error| HttpError::bad_request("ipc_invalid_params", error.to_string()))?;
@@ -915,6 +946,100 @@ async fn dispatch_ipc_operation_result(
             let query = compat_query_from_params(params)?;
             Ok(compat_wifi_delete(State(state), Query(query)).await?.0)
         }
+        "compat.wifi.networks.get" => {
+            let query = compat_query_from_params(params)?;
+            Ok(compat_wifi_networks_get(State(state), Query(query))
+                .await?
+                .0)
+        }
+        "compat.wifi.networks.add" => {
+            let (query, body) = compat_query_and_body(params)?;
+            Ok(
+                compat_wifi_networks_add(State(state), Query(query), body.to_string())
+                    .await?
+                    .0,
+            )
+        }
+        "compat.wifi.networks.remove" => {
+            let (query, body) = compat_query_and_body(params)?;
+            Ok(
+                compat_wifi_networks_remove(State(state), Query(query), body.to_string())
+                    .await?
+                    .0,
+            )
+        }
+        "compat.wifi.networks.move" => {
+            let (query, body) = compat_query_and_body(params)?;
+            Ok(
+                compat_wifi_networks_move(State(state), Query(query), body.to_string())
+                    .await?
+                    .0,
+            )
+        }
+        "compat.time.get" => {
+            let query = compat_query_from_params(params)?;
+            Ok(compat_time_get(State(state), Query(query)).await?.0)
+        }
+        "compat.time.set" => {
+            let (query, body) = compat_query_and_body(params)?;
+            Ok(
+                compat_time_set(State(state), Query(query), body.to_string())
+                    .await?
+                    .0,
+            )
+        }
+        "compat.firmware.get" => {
+            let query = compat_query_from_params(params)?;
+            Ok(compat_firmware_get(State(state), Query(query)).await?.0)
+        }
+        "compat.firmware.ota_token.get" => {
+            let query = compat_query_from_params(params)?;
+            Ok(compat_ota_token_get(State(state), Query(query)).await?.0)
+        }
+        "compat.firmware.ota_token.rotate" => {
+            let query = compat_query_from_params(params)?;
+            Ok(compat_ota_token_rotate(State(state), Query(query)).await?.0)
+        }
+        "compat.analog_firmware.get" => {
+            let query = compat_query_from_params(params)?;
+            Ok(compat_analog_firmware_get(State(state), Query(query))
+                .await?
+                .0)
+        }
+        "compat.analog_firmware.begin" => {
+            let (query, body) = compat_query_and_body(params)?;
+            compat_analog_flash_step(&state, &query, "analog_flash_begin", Some(body.to_string()))
+                .await
+        }
+        "compat.analog_firmware.write" => {
+            let (query, body) = compat_query_and_body(params)?;
+            compat_analog_flash_step(&state, &query, "analog_flash_write", Some(body.to_string()))
+                .await
+        }
+        "compat.analog_firmware.finish" => {
+            let query = compat_query_from_params(params)?;
+            compat_analog_flash_step(&state, &query, "analog_flash_finish", None).await
+        }
+        "compat.auth.tokens.get" => {
+            let query = compat_query_from_params(params)?;
+            Ok(compat_auth_tokens_get(State(state), Query(query)).await?.0)
+        }
+        "compat.auth.tokens.create" => {
+            let (query, body) = compat_query_and_body(params)?;
+            Ok(
+                compat_auth_tokens_create(State(state), Query(query), body.to_string())
+                    .await?
+                    .0,
+            )
+        }
+        "compat.auth.tokens.revoke" => {
+            let (query, body) = compat_query_and_body(params)?;
+            Ok(
+                compat_auth_tokens_revoke(State(state), Query(query), body.to_string())
+                    .await?
+                    .0,
+            )
+        }
         "compat.control.get" => {
             let query = compat_query_from_params(params)?;
             Ok(compat_control_get(State(state), Query(query)).await?.0)
@@ -953,6 +1078,12 @@ async fn dispatch_ipc_operation_result(
                 .await?
                 .0)
         }
+        "compat.calibration.report" => {
+            let query = compat_query_from_params(params)?;
+            Ok(compat_calibration_report(State(state), Query(query))
+                .await?
+                .0)
+        }
         "compat.calibration.apply" => {
             let (query, body) = compat_query_and_body(params)?;
             Ok(
@@ -985,6 +1116,76 @@ async fn dispatch_ipc_operation_result(
                     .0,
             )
         }
+        "compat.calibration.verify" => {
+            let (query, body) = compat_query_and_body(params)?;
+            Ok(
+                compat_calibration_verify(State(state), Query(query), body.to_string())
+                    .await?
+                    .0,
+            )
+        }
+        "compat.calibration.verify.results" => {
+            let query = compat_query_from_params(params)?;
+            Ok(
+                compat_calibration_verify_results(State(state), Query(query))
+                    .await?
+                    .0,
+            )
+        }
+        "compat.calibration.slots" => {
+            let query = compat_query_from_params(params)?;
+            Ok(compat_calibration_slots(State(state), Query(query))
+                .await?
+                .0)
+        }
+        "compat.calibration.slots.activate" => {
+            let (query, body) = compat_query_and_body(params)?;
+            Ok(
+                compat_calibration_slot_activate(State(state), Query(query), body.to_string())
+                    .await?
+                    .0,
+            )
+        }
+        "compat.calibration.slots.rename" => {
+            let (query, body) = compat_query_and_body(params)?;
+            Ok(
+                compat_calibration_slot_rename(State(state), Query(query), body.to_string())
+                    .await?
+                    .0,
+            )
+        }
+        "compat.calibration.slots.delete" => {
+            let (query, body) = compat_query_and_body(params)?;
+            Ok(
+                compat_calibration_slot_delete(State(state), Query(query), body.to_string())
+                    .await?
+                    .0,
+            )
+        }
+        "compat.protection.get" => {
+            let query = compat_query_from_params(params)?;
+            Ok(compat_protection_get(State(state), Query(query)).await?.0)
+        }
+        "compat.protection.post" => {
+            let (query, body) = compat_query_and_body(params)?;
+            Ok(
+                compat_protection_post(State(state), Query(query), body.to_string())
+                    .await?
+                    .0,
+            )
+        }
+        "compat.thermal.get" => {
+            let query = compat_query_from_params(params)?;
+            Ok(compat_thermal_get(State(state), Query(query)).await?.0)
+        }
+        "compat.thermal.post" => {
+            let (query, body) = compat_query_and_body(params)?;
+            Ok(
+                compat_thermal_post(State(state), Query(query), body.to_string())
+                    .await?
+                    .0,
+            )
+        }
         "compat.soft_reset" => {
             let (query, body) = compat_query_and_body(params)?;
             Ok(
@@ -993,6 +1194,40 @@ async fn dispatch_ipc_operation_result(
                     .0,
             )
         }
+        "compat.faults.clear" => {
+            let (query, body) = compat_query_and_body(params)?;
+            Ok(
+                compat_faults_clear(State(state), Query(query), body.to_string())
+                    .await?
+                    .0,
+            )
+        }
+        "compat.counters.reset" => {
+            let (query, body) = compat_query_and_body(params)?;
+            Ok(
+                compat_counters_reset(State(state), Query(query), body.to_string())
+                    .await?
+                    .0,
+            )
+        }
+        "compat.measure.ir.get" => {
+            let query = compat_query_from_params(params)?;
+            Ok(compat_ir_measure_get(State(state), Query(query)).await?.0)
+        }
+        "compat.measure.ir.start" => {
+            let (query, body) = compat_query_and_body(params)?;
+            Ok(
+                compat_ir_measure_start(State(state), Query(query), body.to_string())
+                    .await?
+                    .0,
+            )
+        }
+        "compat.measure.ir.cancel" => {
+            let query = compat_query_from_params(params)?;
+            Ok(compat_ir_measure_cancel(State(state), Query(query))
+                .await?
+                .0)
+        }
         "compat.diagnostics.export" => {
             let query: SessionQuery = serde_json::from_value(params)
                 .map_err(|error| HttpError::bad_request("ipc_invalid_params", error.to_string()))?;
@@ -1102,6 +1337,12 @@ fn router(state: AppState, web_root: Option<PathBuf>, allow_dev_cors: bool) -> R
         .route("/api/v1/devices/{id}/reset", post(reset_device))
         .route("/api/v1/devices/{id}/monitor/start", post(monitor_start))
         .route("/api/v1/devices/{id}/monitor/stop", post(monitor_stop))
+        .route(
+            "/api/v1/devices/{id}/scpi",
+            get(scpi_bridge_get)
+                .post(scpi_bridge_start)
+                .delete(scpi_bridge_stop),
+        )
         .route("/api/v1/serial/lease", post(create_lease))
         .route(
             "/api/v1/serial/lease/{lease_id}",
@@ -1119,6 +1360,44 @@ fn router(state: AppState, web_root: Option<PathBuf>, allow_dev_cors: bool) -> R
                 .delete(compat_wifi_delete),
         )
         .route("/api/v1/wifi/credentials", get(compat_wifi_credentials_get))
+        .route("/api/v1/wifi/networks", get(compat_wifi_networks_get))
+        .route("/api/v1/wifi/networks/add", post(compat_wifi_networks_add))
+        .route(
+            "/api/v1/wifi/networks/remove",
+            post(compat_wifi_networks_remove),
+        )
+        .route(
+            "/api/v1/wifi/networks/move",
+            post(compat_wifi_networks_move),
+        )
+        .route("/api/v1/time", get(compat_time_get).post(compat_time_set))
+        .route("/api/v1/firmware", get(compat_firmware_get))
+        .route("/api/v1/firmware/ota-token", get(compat_ota_token_get))
+        .route(
+            "/api/v1/firmware/ota-token/rotate",
+            post(compat_ota_token_rotate),
+        )
+        .route("/api/v1/analog/firmware", get(compat_analog_firmware_get))
+        .route(
+            "/api/v1/analog/firmware/begin",
+            post(compat_analog_firmware_begin),
+        )
+        .route(
+            "/api/v1/analog/firmware/write",
+            post(compat_analog_firmware_write),
+        )
+        .route(
+            "/api/v1/analog/firmware/finish",
+            post(compat_analog_firmware_finish),
+        )
+        .route(
+            "/api/v1/auth/tokens",
+            get(compat_auth_tokens_get).post(compat_auth_tokens_create),
+        )
+        .route(
+            "/api/v1/auth/tokens/revoke",
+            post(compat_auth_tokens_revoke),
+        )
         .route("/api/v1/cc", post(compat_cc))
         .route("/api/v1/pd", get(compat_pd_get).post(compat_pd_post))
         .route(
@@ -1138,6 +1417,7 @@ fn router(state: AppState, web_root: Option<PathBuf>, allow_dev_cors: bool) -> R
             "/api/v1/calibration/profile",
             get(compat_calibration_profile),
         )
+        .route("/api/v1/calibration/report", get(compat_calibration_report))
         .route("/api/v1/calibration/apply", post(compat_calibration_apply))
         .route(
             "/api/v1/calibration/commit",
@@ -1145,6 +1425,42 @@ fn router(state: AppState, web_root: Option<PathBuf>, allow_dev_cors: bool) -> R
         )
         .route("/api/v1/calibration/reset", post(compat_calibration_reset))
         .route("/api/v1/calibration/mode", post(compat_calibration_mode))
+        .route(
+            "/api/v1/calibration/verify",
+            get(compat_calibration_verify_results).post(compat_calibration_verify),
+        )
+        .route("/api/v1/calibration/slots", get(compat_calibration_slots))
+        .route(
+            "/api/v1/calibration/slots/activate",
+            post(compat_calibration_slot_activate),
+        )
+        .route(
+            "/api/v1/calibration/slots/rename",
+            post(compat_calibration_slot_rename),
+        )
+        .route(
+            "/api/v1/calibration/slots/delete",
+            post(compat_calibration_slot_delete),
+        )
+        .route(
+            "/api/v1/protection",
+            get(compat_protection_get)
+                .post(compat_protection_post)
+                .put(compat_protection_post),
+        )
+        .route("/api/v1/faults/clear", post(compat_faults_clear))
+        .route("/api/v1/counters/reset", post(compat_counters_reset))
+        .route(
+            "/api/v1/measure/ir",
+            get(compat_ir_measure_get).post(compat_ir_measure_start),
+        )
+        .route("/api/v1/measure/ir/cancel", post(compat_ir_measure_cancel))
+        .route(
+            "/api/v1/thermal",
+            get(compat_thermal_get)
+                .post(compat_thermal_post)
+                .put(compat_thermal_post),
+        )
         .route("/api/v1/soft-reset", post(compat_soft_reset))
         .route("/api/v1/diagnostics", get(compat_diagnostics_export))
         .route("/api/v1/diagnostics/export", get(compat_diagnostics_export))
@@ -1757,6 +2073,51 @@ async fn monitor_stop(
     Ok(Json(json!({"ok": true, "monitor": "stopped"})))
 }
 
+#[derive(Debug, Default, Deserialize)]
+struct ScpiBridgeRequest {
+    port: Option<u16>,
+}
+
+async fn scpi_bridge_get(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Value>, HttpError> {
+    let bridge = scpi_bridge::describe(&state, &id);
+    Ok(Json(json!({"device_id": id, "bridge": bridge})))
+}
+
+async fn scpi_bridge_start(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<CompatQuery>,
+    body: String,
+) -> Result<Json<Value>, HttpError> {
+    // An empty body (or `null` over IPC) selects an ephemeral port.
+    let input: ScpiBridgeRequest = if body.trim().is_empty() {
+        ScpiBridgeRequest::default()
+    } else {
+        serde_json::from_str::<Option<ScpiBridgeRequest>>(&body)
+            .map_err(|error| HttpError::bad_request("invalid_request", error.to_string()))?
+            .unwrap_or_default()
+    };
+    let bridge = scpi_bridge::start(
+        &state,
+        &id,
+        query.lease_id.as_deref(),
+        input.port.unwrap_or(0),
+    )
+    .await?;
+    Ok(Json(json!({"device_id": id, "bridge": bridge})))
+}
+
+async fn scpi_bridge_stop(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Value>, HttpError> {
+    let stopped = scpi_bridge::stop(&state, &id);
+    Ok(Json(json!({"device_id": id, "stopped": stopped})))
+}
+
 async fn device_session(
     State(state): State<AppState>,
     Path(id): Path<String>,
@@ -2618,41 +2979,316 @@ async fn compat_wifi_credentials_get(
     Ok(Json(data))
 }
 
-async fn recover_wifi_status_after_write_gap(
-    state: &AppState,
-    query: &CompatQuery,
-) -> Result<Value, HttpError> {
-    let (_, data) = compat_usb_json_request_with_retry(
-        state,
-        query,
-        "get_wifi_status",
+async fn compat_wifi_networks_get(
+    State(state): State<AppState>,
+    Query(query): Query<CompatQuery>,
+) -> Result<Json<Value>, HttpError> {
+    let (_, data) = compat_usb_json_request(
+        &state,
+        &query,
+        "get_wifi_networks",
         None,
-        "USB WiFi write recovery status completed",
-        "USB WiFi write recovery status",
-        2,
+        "USB WiFi networks completed",
+        "USB WiFi networks",
     )
     .await?;
-    Ok(data)
-}
-
-fn wifi_status_confirms_set(wifi: &Value, ssid: &str) -> bool {
-    wifi.get("source").and_then(Value::as_str) == Some("user")
-        && wifi.get("ssid").and_then(Value::as_str) == Some(ssid)
-        && wifi.get("state").and_then(Value::as_str) != Some("error")
+    Ok(Json(data))
 }
 
-fn wifi_status_confirms_clear(wifi: &Value) -> bool {
-    wifi.get("source")
-        .and_then(Value::as_str)
-        .is_some_and(|source| source != "user")
+async fn compat_wifi_networks_add(
+    State(state): State<AppState>,
+    Query(query): Query<CompatQuery>,
+    body: String,
+) -> Result<Json<Value>, HttpError> {
+    let input = parse_compat_json_body(&body)?;
+    let (_, data) = compat_usb_json_request(
+        &state,
+        &query,
+        "add_wifi_network",
+        Some(input),
+        "USB WiFi network add completed",
+        "USB WiFi network add",
+    )
+    .await?;
+    Ok(Json(data))
 }
 
-async fn compat_wifi_post(
+async fn compat_wifi_networks_remove(
     State(state): State<AppState>,
     Query(query): Query<CompatQuery>,
-    Json(input): Json<WifiSetRequest>,
+    body: String,
 ) -> Result<Json<Value>, HttpError> {
-    let _wait_requested = input.wait;
+    let input = parse_compat_json_body(&body)?;
+    let (_, data) = compat_usb_json_request(
+        &state,
+        &query,
+        "remove_wifi_network",
+        Some(input),
+        "USB WiFi network remove completed",
+        "USB WiFi network remove",
+    )
+    .await?;
+    Ok(Json(data))
+}
+
+async fn compat_wifi_networks_move(
+    State(state): State<AppState>,
+    Query(query): Query<CompatQuery>,
+    body: String,
+) -> Result<Json<Value>, HttpError> {
+    let input = parse_compat_json_body(&body)?;
+    let (_, data) = compat_usb_json_request(
+        &state,
+        &query,
+        "move_wifi_network",
+        Some(input),
+        "USB WiFi network move completed",
+        "USB WiFi network move",
+    )
+    .await?;
+    Ok(Json(data))
+}
+
+async fn compat_time_get(
+    State(state): State<AppState>,
+    Query(query): Query<CompatQuery>,
+) -> Result<Json<Value>, HttpError> {
+    let (_, data) = compat_usb_json_request(
+        &state,
+        &query,
+        "get_time",
+        None,
+        "USB time completed",
+        "USB time",
+    )
+    .await?;
+    Ok(Json(data))
+}
+
+async fn compat_firmware_get(
+    State(state): State<AppState>,
+    Query(query): Query<CompatQuery>,
+) -> Result<Json<Value>, HttpError> {
+    let (_, data) = compat_usb_json_request(
+        &state,
+        &query,
+        "get_firmware",
+        None,
+        "USB firmware status completed",
+        "USB firmware status",
+    )
+    .await?;
+    Ok(Json(data))
+}
+
+async fn compat_ota_token_get(
+    State(state): State<AppState>,
+    Query(query): Query<CompatQuery>,
+) -> Result<Json<Value>, HttpError> {
+    let (_, data) = compat_usb_json_request(
+        &state,
+        &query,
+        "get_ota_token",
+        None,
+        "USB OTA token completed",
+        "USB OTA token",
+    )
+    .await?;
+    Ok(Json(data))
+}
+
+async fn compat_ota_token_rotate(
+    State(state): State<AppState>,
+    Query(query): Query<CompatQuery>,
+) -> Result<Json<Value>, HttpError> {
+    let (_, data) = compat_usb_json_request(
+        &state,
+        &query,
+        "rotate_ota_token",
+        None,
+        "USB OTA token rotation completed",
+        "USB OTA token rotation",
+    )
+    .await?;
+    Ok(Json(data))
+}
+
+async fn compat_auth_tokens_get(
+    State(state): State<AppState>,
+    Query(query): Query<CompatQuery>,
+) -> Result<Json<Value>, HttpError> {
+    let (_, data) = compat_usb_json_request(
+        &state,
+        &query,
+        "get_api_tokens",
+        None,
+        "USB API tokens completed",
+        "USB API tokens",
+    )
+    .await?;
+    Ok(Json(data))
+}
+
+/// Issue an HTTP API token over USB, which the device trusts without the
+/// on-screen pairing code.
+async fn compat_auth_tokens_create(
+    State(state): State<AppState>,
+    Query(query): Query<CompatQuery>,
+    body: String,
+) -> Result<Json<Value>, HttpError> {
+    let input = parse_compat_json_body(&body)?;
+    let (_, data) = compat_usb_json_request(
+        &state,
+        &query,
+        "create_api_token",
+        Some(input),
+        "USB API token create completed",
+        "USB API token create",
+    )
+    .await?;
+    Ok(Json(data))
+}
+
+async fn compat_auth_tokens_revoke(
+    State(state): State<AppState>,
+    Query(query): Query<CompatQuery>,
+    body: String,
+) -> Result<Json<Value>, HttpError> {
+    let input = parse_compat_json_body(&body)?;
+    let (_, data) = compat_usb_json_request(
+        &state,
+        &query,
+        "revoke_api_token",
+        Some(input),
+        "USB API token revoke completed",
+        "USB API token revoke",
+    )
+    .await?;
+    Ok(Json(data))
+}
+
+async fn compat_analog_firmware_get(
+    State(state): State<AppState>,
+    Query(query): Query<CompatQuery>,
+) -> Result<Json<Value>, HttpError> {
+    let (_, data) = compat_usb_json_request(
+        &state,
+        &query,
+        "get_analog_firmware",
+        None,
+        "USB analog firmware status completed",
+        "USB analog firmware status",
+    )
+    .await?;
+    Ok(Json(data))
+}
+
+async fn compat_analog_firmware_begin(
+    State(state): State<AppState>,
+    Query(query): Query<CompatQuery>,
+    body: String,
+) -> Result<Json<Value>, HttpError> {
+    compat_analog_flash_step(&state, &query, "analog_flash_begin", Some(body))
+        .await
+        .map(Json)
+}
+
+async fn compat_analog_firmware_write(
+    State(state): State<AppState>,
+    Query(query): Query<CompatQuery>,
+    body: String,
+) -> Result<Json<Value>, HttpError> {
+    compat_analog_flash_step(&state, &query, "analog_flash_write", Some(body))
+        .await
+        .map(Json)
+}
+
+async fn compat_analog_firmware_finish(
+    State(state): State<AppState>,
+    Query(query): Query<CompatQuery>,
+) -> Result<Json<Value>, HttpError> {
+    compat_analog_flash_step(&state, &query, "analog_flash_finish", None)
+        .await
+        .map(Json)
+}
+
+/// One step of an analog STM32 update through the digital board's UART
+/// bootloader bridge (`analog_flash_begin` / `_write` / `_finish`).
+async fn compat_analog_flash_step(
+    state: &AppState,
+    query: &CompatQuery,
+    op: &str,
+    body: Option<String>,
+) -> Result<Value, HttpError> {
+    let input = match body {
+        Some(body) => Some(parse_compat_json_body(&body)?),
+        None => None,
+    };
+    let (_, data) = compat_usb_json_request(
+        state,
+        query,
+        op,
+        input,
+        "USB analog flash step completed",
+        "USB analog flash step",
+    )
+    .await?;
+    Ok(data)
+}
+
+async fn compat_time_set(
+    State(state): State<AppState>,
+    Query(query): Query<CompatQuery>,
+    body: String,
+) -> Result<Json<Value>, HttpError> {
+    let input = parse_compat_json_body(&body)?;
+    let (_, data) = compat_usb_json_request(
+        &state,
+        &query,
+        "set_time",
+        Some(input),
+        "USB time set completed",
+        "USB time set",
+    )
+    .await?;
+    Ok(Json(data))
+}
+
+async fn recover_wifi_status_after_write_gap(
+    state: &AppState,
+    query: &CompatQuery,
+) -> Result<Value, HttpError> {
+    let (_, data) = compat_usb_json_request_with_retry(
+        state,
+        query,
+        "get_wifi_status",
+        None,
+        "USB WiFi write recovery status completed",
+        "USB WiFi write recovery status",
+        2,
+    )
+    .await?;
+    Ok(data)
+}
+
+fn wifi_status_confirms_set(wifi: &Value, ssid: &str) -> bool {
+    wifi.get("source").and_then(Value::as_str) == Some("user")
+        && wifi.get("ssid").and_then(Value::as_str) == Some(ssid)
+        && wifi.get("state").and_then(Value::as_str) != Some("error")
+}
+
+fn wifi_status_confirms_clear(wifi: &Value) -> bool {
+    wifi.get("source")
+        .and_then(Value::as_str)
+        .is_some_and(|source| source != "user")
+}
+
+async fn compat_wifi_post(
+    State(state): State<AppState>,
+    Query(query): Query<CompatQuery>,
+    Json(input): Json<WifiSetRequest>,
+) -> Result<Json<Value>, HttpError> {
+    let _wait_requested = input.wait;
     let ssid = input.ssid.clone();
     let extra = json!({
         "ssid": input.ssid,
@@ -2913,25 +3549,295 @@ async fn compat_presets_get(
     }))
 }
 
-async fn compat_presets_post(
+async fn compat_presets_post(
+    State(state): State<AppState>,
+    Query(query): Query<CompatQuery>,
+    body: String,
+) -> Result<Json<Value>, HttpError> {
+    let input = parse_compat_json_body(&body)?;
+    let (_, data) = compat_usb_json_request(
+        &state,
+        &query,
+        "set_preset",
+        Some(input),
+        "USB preset SET completed",
+        "USB preset SET",
+    )
+    .await?;
+    Ok(Json(data))
+}
+
+async fn compat_presets_apply(
+    State(state): State<AppState>,
+    Query(query): Query<CompatQuery>,
+    body: String,
+) -> Result<Json<Value>, HttpError> {
+    let input = parse_compat_json_body(&body)?;
+    let (_, data) = compat_usb_json_request(
+        &state,
+        &query,
+        "apply_preset",
+        Some(input),
+        "USB preset APPLY completed",
+        "USB preset APPLY",
+    )
+    .await?;
+    Ok(Json(data))
+}
+
+async fn compat_calibration_profile(
+    State(state): State<AppState>,
+    Query(query): Query<CompatQuery>,
+) -> Result<Json<Value>, HttpError> {
+    let (_, data) = compat_usb_json_request(
+        &state,
+        &query,
+        "get_calibration_profile",
+        None,
+        "USB calibration profile completed",
+        "USB calibration profile",
+    )
+    .await?;
+    Ok(Json(expand_compact_calibration_profile(data)?))
+}
+
+async fn compat_calibration_report(
+    State(state): State<AppState>,
+    Query(query): Query<CompatQuery>,
+) -> Result<Json<Value>, HttpError> {
+    let Json(profile) = compat_calibration_profile(State(state), Query(query)).await?;
+    calibration_report(&profile)
+        .map(Json)
+        .map_err(|message| HttpError::conflict("calibration_report_unavailable", message))
+}
+
+async fn compat_calibration_apply(
+    State(state): State<AppState>,
+    Query(query): Query<CompatQuery>,
+    body: String,
+) -> Result<Json<Value>, HttpError> {
+    let input = parse_compat_json_body(&body)?;
+    let (_, data) = compat_usb_json_request(
+        &state,
+        &query,
+        "calibration_apply",
+        Some(input),
+        "USB calibration apply completed",
+        "USB calibration apply",
+    )
+    .await?;
+    Ok(Json(data))
+}
+
+async fn compat_calibration_commit(
+    State(state): State<AppState>,
+    Query(query): Query<CompatQuery>,
+    body: String,
+) -> Result<Json<Value>, HttpError> {
+    let input = parse_compat_json_body(&body)?;
+    let (_, data) = compat_usb_json_request(
+        &state,
+        &query,
+        "calibration_commit",
+        Some(input),
+        "USB calibration commit completed",
+        "USB calibration commit",
+    )
+    .await?;
+    Ok(Json(data))
+}
+
+async fn compat_calibration_reset(
+    State(state): State<AppState>,
+    Query(query): Query<CompatQuery>,
+    body: String,
+) -> Result<Json<Value>, HttpError> {
+    let input = parse_compat_json_body(&body)?;
+    let (_, data) = compat_usb_json_request(
+        &state,
+        &query,
+        "calibration_reset",
+        Some(input),
+        "USB calibration reset completed",
+        "USB calibration reset",
+    )
+    .await?;
+    Ok(Json(data))
+}
+
+async fn compat_calibration_mode(
+    State(state): State<AppState>,
+    Query(query): Query<CompatQuery>,
+    body: String,
+) -> Result<Json<Value>, HttpError> {
+    let input = parse_compat_json_body(&body)?;
+    let (_, data) = compat_usb_json_request(
+        &state,
+        &query,
+        "calibration_mode",
+        Some(input),
+        "USB calibration mode completed",
+        "USB calibration mode",
+    )
+    .await?;
+    Ok(Json(data))
+}
+
+async fn compat_calibration_verify(
+    State(state): State<AppState>,
+    Query(query): Query<CompatQuery>,
+    body: String,
+) -> Result<Json<Value>, HttpError> {
+    let input = parse_compat_json_body(&body)?;
+    let (_, data) = compat_usb_json_request(
+        &state,
+        &query,
+        "calibration_verify",
+        Some(input),
+        "USB calibration verify completed",
+        "USB calibration verify",
+    )
+    .await?;
+    Ok(Json(data))
+}
+
+async fn compat_calibration_verify_results(
+    State(state): State<AppState>,
+    Query(query): Query<CompatQuery>,
+) -> Result<Json<Value>, HttpError> {
+    let (_, data) = compat_usb_json_request(
+        &state,
+        &query,
+        "get_calibration_verify",
+        None,
+        "USB calibration verify results completed",
+        "USB calibration verify results",
+    )
+    .await?;
+    Ok(Json(data))
+}
+
+async fn compat_calibration_slots(
+    State(state): State<AppState>,
+    Query(query): Query<CompatQuery>,
+) -> Result<Json<Value>, HttpError> {
+    let (_, data) = compat_usb_json_request(
+        &state,
+        &query,
+        "get_calibration_slots",
+        None,
+        "USB calibration slots completed",
+        "USB calibration slots",
+    )
+    .await?;
+    Ok(Json(data))
+}
+
+async fn compat_calibration_slot_activate(
+    State(state): State<AppState>,
+    Query(query): Query<CompatQuery>,
+    body: String,
+) -> Result<Json<Value>, HttpError> {
+    let input = parse_compat_json_body(&body)?;
+    let (_, data) = compat_usb_json_request(
+        &state,
+        &query,
+        "calibration_slot_activate",
+        Some(input),
+        "USB calibration slot activate completed",
+        "USB calibration slot activate",
+    )
+    .await?;
+    Ok(Json(data))
+}
+
+async fn compat_calibration_slot_rename(
+    State(state): State<AppState>,
+    Query(query): Query<CompatQuery>,
+    body: String,
+) -> Result<Json<Value>, HttpError> {
+    let input = parse_compat_json_body(&body)?;
+    let (_, data) = compat_usb_json_request(
+        &state,
+        &query,
+        "calibration_slot_rename",
+        Some(input),
+        "USB calibration slot rename completed",
+        "USB calibration slot rename",
+    )
+    .await?;
+    Ok(Json(data))
+}
+
+async fn compat_calibration_slot_delete(
+    State(state): State<AppState>,
+    Query(query): Query<CompatQuery>,
+    body: String,
+) -> Result<Json<Value>, HttpError> {
+    let input = parse_compat_json_body(&body)?;
+    let (_, data) = compat_usb_json_request(
+        &state,
+        &query,
+        "calibration_slot_delete",
+        Some(input),
+        "USB calibration slot delete completed",
+        "USB calibration slot delete",
+    )
+    .await?;
+    Ok(Json(data))
+}
+
+async fn compat_protection_get(
+    State(state): State<AppState>,
+    Query(query): Query<CompatQuery>,
+) -> Result<Json<Value>, HttpError> {
+    let (_, data) = compat_usb_json_request(
+        &state,
+        &query,
+        "get_protection",
+        None,
+        "USB protection GET completed",
+        "USB protection GET",
+    )
+    .await?;
+    Ok(Json(data))
+}
+
+async fn compat_protection_post(
+    State(state): State<AppState>,
+    Query(query): Query<CompatQuery>,
+    body: String,
+) -> Result<Json<Value>, HttpError> {
+    let input = parse_compat_json_body(&body)?;
+    let (_, data) = compat_usb_json_request(
+        &state,
+        &query,
+        "set_protection",
+        Some(input),
+        "USB protection SET completed",
+        "USB protection SET",
+    )
+    .await?;
+    Ok(Json(data))
+}
+
+async fn compat_thermal_get(
     State(state): State<AppState>,
     Query(query): Query<CompatQuery>,
-    body: String,
 ) -> Result<Json<Value>, HttpError> {
-    let input = parse_compat_json_body(&body)?;
     let (_, data) = compat_usb_json_request(
         &state,
         &query,
-        "set_preset",
-        Some(input),
-        "USB preset SET completed",
-        "USB preset SET",
+        "get_thermal",
+        None,
+        "USB thermal GET completed",
+        "USB thermal GET",
     )
     .await?;
     Ok(Json(data))
 }
 
-async fn compat_presets_apply(
+async fn compat_thermal_post(
     State(state): State<AppState>,
     Query(query): Query<CompatQuery>,
     body: String,
@@ -2940,32 +3846,34 @@ async fn compat_presets_apply(
     let (_, data) = compat_usb_json_request(
         &state,
         &query,
-        "apply_preset",
+        "set_thermal",
         Some(input),
-        "USB preset APPLY completed",
-        "USB preset APPLY",
+        "USB thermal SET completed",
+        "USB thermal SET",
     )
     .await?;
     Ok(Json(data))
 }
 
-async fn compat_calibration_profile(
+async fn compat_soft_reset(
     State(state): State<AppState>,
     Query(query): Query<CompatQuery>,
+    body: String,
 ) -> Result<Json<Value>, HttpError> {
+    let input = parse_compat_json_body(&body)?;
     let (_, data) = compat_usb_json_request(
         &state,
         &query,
-        "get_calibration_profile",
-        None,
-        "USB calibration profile completed",
-        "USB calibration profile",
+        "soft_reset",
+        Some(input),
+        "USB soft reset completed",
+        "USB soft reset",
     )
     .await?;
-    Ok(Json(expand_compact_calibration_profile(data)?))
+    Ok(Json(data))
 }
 
-async fn compat_calibration_apply(
+async fn compat_faults_clear(
     State(state): State<AppState>,
     Query(query): Query<CompatQuery>,
     body: String,
@@ -2974,16 +3882,16 @@ async fn compat_calibration_apply(
     let (_, data) = compat_usb_json_request(
         &state,
         &query,
-        "calibration_apply",
+        "clear_faults",
         Some(input),
-        "USB calibration apply completed",
-        "USB calibration apply",
+        "USB clear faults completed",
+        "USB clear faults",
     )
     .await?;
     Ok(Json(data))
 }
 
-async fn compat_calibration_commit(
+async fn compat_counters_reset(
     State(state): State<AppState>,
     Query(query): Query<CompatQuery>,
     body: String,
@@ -2992,34 +3900,32 @@ async fn compat_calibration_commit(
     let (_, data) = compat_usb_json_request(
         &state,
         &query,
-        "calibration_commit",
+        "reset_counters",
         Some(input),
-        "USB calibration commit completed",
-        "USB calibration commit",
+        "USB reset counters completed",
+        "USB reset counters",
     )
     .await?;
     Ok(Json(data))
 }
 
-async fn compat_calibration_reset(
+async fn compat_ir_measure_get(
     State(state): State<AppState>,
     Query(query): Query<CompatQuery>,
-    body: String,
 ) -> Result<Json<Value>, HttpError> {
-    let input = parse_compat_json_body(&body)?;
     let (_, data) = compat_usb_json_request(
         &state,
         &query,
-        "calibration_reset",
-        Some(input),
-        "USB calibration reset completed",
-        "USB calibration reset",
+        "get_ir_measure",
+        None,
+        "USB IR measure GET completed",
+        "USB IR measure GET",
     )
     .await?;
     Ok(Json(data))
 }
 
-async fn compat_calibration_mode(
+async fn compat_ir_measure_start(
     State(state): State<AppState>,
     Query(query): Query<CompatQuery>,
     body: String,
@@ -3028,28 +3934,26 @@ async fn compat_calibration_mode(
     let (_, data) = compat_usb_json_request(
         &state,
         &query,
-        "calibration_mode",
+        "start_ir_measure",
         Some(input),
-        "USB calibration mode completed",
-        "USB calibration mode",
+        "USB IR measure start completed",
+        "USB IR measure start",
     )
     .await?;
     Ok(Json(data))
 }
 
-async fn compat_soft_reset(
+async fn compat_ir_measure_cancel(
     State(state): State<AppState>,
     Query(query): Query<CompatQuery>,
-    body: String,
 ) -> Result<Json<Value>, HttpError> {
-    let input = parse_compat_json_body(&body)?;
     let (_, data) = compat_usb_json_request(
         &state,
         &query,
-        "soft_reset",
-        Some(input),
-        "USB soft reset completed",
-        "USB soft reset",
+        "cancel_ir_measure",
+        None,
+        "USB IR measure cancel completed",
+        "USB IR measure cancel",
     )
     .await?;
     Ok(Json(data))
@@ -3522,6 +4426,7 @@ fn release_lease_inner(state: &AppState, lease_id: &str, reason: &str) -> bool {
     let Some(lease) = lease else {
         return false;
     };
+    scpi_bridge::stop_for_lease(state, lease_id);
     let port_path = lease
         .port_path
         .clone()
@@ -3758,6 +4663,8 @@ struct EspflashOperation {
     command: &'static str,
     file_path: String,
     flash_address: Option<u64>,
+    /// Set for ELF flashes so the app lands in the OTA layout.
+    partition_table: Option<String>,
 }
 
 fn selected_espflash_operation(
@@ -3768,6 +4675,11 @@ fn selected_espflash_operation(
             command: "flash",
             file_path: file.path.clone(),
             flash_address: None,
+            partition_table: artifact
+                .files
+                .iter()
+                .find(|file| file.kind == "partition_table")
+                .map(|file| file.path.clone()),
         });
     }
     if let Some(file) = artifact.files.iter().find(|file| file.kind == "image") {
@@ -3781,6 +4693,7 @@ fn selected_espflash_operation(
             command: "write-bin",
             file_path: file.path.clone(),
             flash_address: Some(flash_address),
+            partition_table: None,
         });
     }
     Err(HttpError::bad_request(
@@ -3789,6 +4702,38 @@ fn selected_espflash_operation(
     ))
 }
 
+/// The bare app image that `POST /api/v1/firmware` accepts; the merged
+/// `image` kind also carries the bootloader and cannot go into an OTA slot.
+fn selected_ota_image_file(artifact: &FirmwareArtifact) -> Result<&ArtifactFile, HttpError> {
+    artifact
+        .files
+        .iter()
+        .find(|file| file.kind == "ota_image")
+        .ok_or_else(|| {
+            HttpError::bad_request(
+                "artifact_ota_image_missing",
+                "LAN flash requires an artifact file with kind=ota_image",
+            )
+        })
+}
+
+/// The raw analog image that the digital board's UART bootloader bridge writes
+/// from the start of the STM32 flash.
+fn selected_analog_bridge_image_file(
+    artifact: &FirmwareArtifact,
+) -> Result<&ArtifactFile, HttpError> {
+    artifact
+        .files
+        .iter()
+        .find(|file| file.kind == "image" && file.flash_address == Some(ANALOG_FLASH_BASE))
+        .ok_or_else(|| {
+            HttpError::bad_request(
+                "artifact_analog_image_missing",
+                "bridge flash requires an artifact file with kind=image at flash_address 0x08000000",
+            )
+        })
+}
+
 fn selected_analog_elf_file(artifact: &FirmwareArtifact) -> Result<String, HttpError> {
     artifact
         .files
@@ -3881,6 +4826,7 @@ async fn run_espflash_digital(
                     "command": operation.command,
                     "file": operation.file_path,
                     "flash_address": operation.flash_address,
+                    "partition_table": operation.partition_table,
                 }),
             );
         }
@@ -3895,6 +4841,15 @@ async fn run_espflash_digital(
         .arg("--port")
         .arg(&port_path)
         .arg("--non-interactive");
+    if let Some(partition_table) = &operation.partition_table {
+        // A fresh table plus an erased otadata boots the app just written to
+        // ota_0, whichever slot the last OTA update selected.
+        command
+            .arg("--partition-table")
+            .arg(partition_table)
+            .arg("--erase-parts")
+            .arg("otadata");
+    }
     if let Some(flash_address) = operation.flash_address {
         command.arg(format!("0x{flash_address:x}"));
     }
@@ -4403,6 +5358,7 @@ fn cached_status_bundle(device: &DeviceRecord) -> Option<Value> {
             "link_up",
             "hello_seen",
             "analog_state",
+            "thermal",
             "fault_flags_decoded",
         ] {
             if let Some(value) = meta.get(key) {
@@ -4437,6 +5393,7 @@ fn status_meta_from_status_bundle(value: &Value) -> Value {
         "link_up",
         "hello_seen",
         "analog_state",
+        "thermal",
         "fault_flags_decoded",
     ] {
         if let Some(field) = value.get(key).cloned() {
@@ -4553,11 +5510,27 @@ fn serial_request_is_wifi_status(op: &str) -> bool {
     matches!(op, "get_wifi_status")
 }
 
+fn serial_request_is_analog_flash(op: &str) -> bool {
+    matches!(
+        op,
+        "analog_flash_begin" | "analog_flash_write" | "analog_flash_finish"
+    )
+}
+
 fn serial_request_is_immediate_response(op: &str) -> bool {
     matches!(
         op,
         "get_wifi_status"
             | "get_wifi_credentials"
+            | "get_wifi_networks"
+            | "get_time"
+            | "get_firmware"
+            | "get_ota_token"
+            | "rotate_ota_token"
+            | "get_analog_firmware"
+            | "get_api_tokens"
+            | "create_api_token"
+            | "revoke_api_token"
             | "get_status"
             | "get_identity"
             | "get_pd"
@@ -4566,7 +5539,17 @@ fn serial_request_is_immediate_response(op: &str) -> bool {
             | "set_control"
             | "apply_preset"
             | "set_pd_policy"
+            | "get_protection"
+            | "set_protection"
+            | "get_thermal"
+            | "set_thermal"
             | "soft_reset"
+            | "clear_faults"
+            | "reset_counters"
+            | "get_ir_measure"
+            | "start_ir_measure"
+            | "cancel_ir_measure"
+            | "scpi"
     )
 }
 
@@ -4577,6 +5560,8 @@ fn serial_protocol_timeout_ms(op: &str, extra: Option<&Value>) -> u64 {
         SERIAL_WIFI_WRITE_PROTOCOL_TIMEOUT_MS
     } else if serial_request_is_wifi_status(op) {
         SERIAL_WIFI_STATUS_PROTOCOL_TIMEOUT_MS
+    } else if serial_request_is_analog_flash(op) {
+        SERIAL_ANALOG_FLASH_PROTOCOL_TIMEOUT_MS
     } else if serial_request_is_immediate_response(op) {
         SERIAL_IMMEDIATE_PROTOCOL_TIMEOUT_MS
     } else {
@@ -4755,8 +5740,87 @@ fn mock_serial_probe(
             "status": {"enable": false, "v_local_mv": 0, "i_local_ma": 0, "fault_flags": 0},
             "link_up": true,
             "hello_seen": true,
+            "wall_time": null,
             "analog_state": "ready"
         }),
+        "get_time" | "set_time" => {
+            let unix_ms = extra
+                .as_ref()
+                .and_then(|v| v.get("unix_ms"))
+                .and_then(Value::as_i64)
+                .unwrap_or_else(|| Utc::now().timestamp_millis());
+            json!({
+                "synced": true,
+                "source": "host",
+                "unix_ms": unix_ms,
+                "last_sync_uptime_ms": 0,
+                "wall_time": chrono::DateTime::<Utc>::from_timestamp_millis(unix_ms)
+                    .map(|dt| dt.to_rfc3339_opts(chrono::SecondsFormat::Millis, true)),
+                "uptime_ms": 0,
+                "ntp": {
+                    "server": extra
+                        .as_ref()
+                        .and_then(|v| v.get("ntp_server"))
+                        .and_then(Value::as_str)
+                        .filter(|server| !server.is_empty())
+                        .unwrap_or("pool.ntp.org:123"),
+                    "custom": false,
+                    "state": "waiting_for_network",
+                    "last_error": null
+                }
+            })
+        }
+        "get_firmware" => json!({
+            "version": "mock",
+            "running_slot": "ota_0",
+            "image_state": "valid",
+            "boot_attempts": 0,
+            "max_unconfirmed_boots": 3,
+            "rolled_back": false,
+            "token_set": true,
+            "slot_capacity": 1966080,
+            "upload": {"state": "idle", "received": 0, "size": 0, "last_error": null}
+        }),
+        "get_ota_token" | "rotate_ota_token" => json!({"token": "0".repeat(64)}),
+        "get_api_tokens" | "revoke_api_token" => json!({
+            "tokens": [{"label": "usb", "role": "control"}],
+            "max_tokens": 4
+        }),
+        "create_api_token" => {
+            let field = |key: &str| {
+                extra
+                    .as_ref()
+                    .and_then(|value| value.get(key))
+                    .and_then(Value::as_str)
+                    .map(str::to_string)
+            };
+            json!({
+                "token": "0".repeat(32),
+                "role": field("role").unwrap_or_else(|| "control".to_string()),
+                "label": field("label").unwrap_or_else(|| "usb".to_string()),
+            })
+        }
+        "get_analog_firmware" => json!({
+            "state": "idle",
+            "received": 0,
+            "size": 0,
+            "max_size": 131072,
+            "last_error": null
+        }),
+        "analog_flash_begin" | "analog_flash_write" | "analog_flash_finish" => {
+            let size = extra
+                .as_ref()
+                .and_then(|value| value.get("size"))
+                .and_then(Value::as_u64)
+                .unwrap_or(0);
+            json!({
+                "state": if op == "analog_flash_finish" { "done" } else { "writing" },
+                "received": 0,
+                "size": size,
+                "max_size": 131072,
+                "last_error": null
+            })
+        }
         "get_pd" | "set_pd_policy" => json!({
             "attached": false,
             "contract": null,
@@ -4768,11 +5832,13 @@ fn mock_serial_probe(
         "get_wifi_credentials" => json!({
             "ssid": "LoadLynx-Test",
             "psk": "mock-loadlynx-psk",
-            "source": "user"
+            "source": "user",
+            "networks": [{"ssid": "LoadLynx-Test", "psk": "mock-loadlynx-psk"}]
         }),
         "clear_wifi_config" => json!({
             "ssid": "",
             "source": "none",
+            "network_count": 0,
             "state": "idle",
             "ip": null,
             "last_error": null
@@ -4780,10 +5846,18 @@ fn mock_serial_probe(
         "get_wifi_status" | "set_wifi_config" => json!({
             "ssid": extra.as_ref().and_then(|v| v.get("ssid")).and_then(Value::as_str).unwrap_or("LoadLynx-Test"),
             "source": "user",
+            "network_count": 1,
             "state": "connected",
             "ip": "192.0.2.10",
             "last_error": null
         }),
+        "get_wifi_networks" | "add_wifi_network" | "remove_wifi_network" | "move_wifi_network" => {
+            json!({
+                "max_networks": 6,
+                "active_ssid": "LoadLynx-Test",
+                "networks": [{"ssid": "LoadLynx-Test", "priority": 0, "active": true}]
+            })
+        }
         "get_control" | "set_control" | "apply_preset" => json!({
             "active_preset_id": extra.as_ref().and_then(|v| v.get("preset_id")).and_then(Value::as_u64).unwrap_or(1),
             "output_enabled": extra.as_ref().and_then(|v| v.get("output_enabled")).and_then(Value::as_bool).unwrap_or(false),
@@ -4795,19 +5869,177 @@ fn mock_serial_probe(
         }),
         "set_preset" => extra.clone().unwrap_or_else(|| mock_preset(1)),
         "get_calibration_profile" => json!({
-            "active": {"source": "factory-default", "fmt_version": 3, "hw_rev": 1},
+            "active": {"source": "factory-default", "fmt_version": 4, "hw_rev": 1},
             "current_ch1_points": [],
             "current_ch2_points": [],
             "v_local_points": [],
             "v_remote_points": []
         }),
-        "calibration_apply" | "calibration_commit" | "calibration_reset" | "calibration_mode" => {
-            json!({"ok": true})
+        "calibration_apply"
+        | "calibration_commit"
+        | "calibration_reset"
+        | "calibration_mode"
+        | "calibration_slot_activate"
+        | "calibration_slot_rename"
+        | "calibration_slot_delete" => json!({"ok": true}),
+        "get_calibration_verify" => json!({
+            "results": {"current_ch1": null, "current_ch2": null, "v_local": null, "v_remote": null}
+        }),
+        "calibration_verify" => {
+            // Factory-default mock reads back exactly the reference value.
+            let input = extra.clone().unwrap_or_else(|| json!({}));
+            let points = input
+                .get("points")
+                .and_then(Value::as_array)
+                .cloned()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|point| {
+                    let raw = point.get(0).cloned().unwrap_or(Value::Null);
+                    let reference = point.get(1).and_then(Value::as_i64).unwrap_or(0);
+                    let tolerance = point
+                        .get(2)
+                        .and_then(Value::as_i64)
+                        .unwrap_or(5 + reference.abs() * 2_000 / 1_000_000);
+                    json!({
+                        "raw_100uv": raw,
+                        "reference": reference,
+                        "reading": reference,
+                        "error": 0,
+                        "tolerance": tolerance,
+                        "passed": true,
+                    })
+                })
+                .collect::<Vec<_>>();
+            json!({
+                "kind": input.get("kind").cloned().unwrap_or(Value::Null),
+                "ts_unix_ms": input.get("ts_unix_ms").cloned().unwrap_or(Value::Null),
+                "slot": 0,
+                "profile_crc32": 0,
+                "current": true,
+                "passed": !points.is_empty(),
+                "worst_error": if points.is_empty() { Value::Null } else { json!(0) },
+                "points": points,
+                "stored": true,
+            })
+        }
+        "get_calibration_slots" => json!({
+            "active": 0,
+            "slots": (0..4).map(|slot| json!({
+                "slot": slot,
+                "name": if slot == 0 { "default" } else { "" },
+                "used": false,
+                "active": slot == 0,
+            })).collect::<Vec<_>>()
+        }),
+        "get_protection" | "set_protection" => {
+            let field = |key: &str, default: i64| {
+                extra
+                    .as_ref()
+                    .and_then(|v| v.get(key))
+                    .and_then(Value::as_i64)
+                    .unwrap_or(default)
+            };
+            json!({
+                "oc_limit_ch_ma": field("oc_limit_ch_ma", 5_500),
+                "ov_limit_mv": field("ov_limit_mv", 55_000),
+                "mcu_temp_limit_mc": field("mcu_temp_limit_mc", 110_000),
+                "sink_temp_limit_mc": field("sink_temp_limit_mc", 100_000),
+                "i_share_threshold_ma": field("i_share_threshold_ma", 2_000),
+                "fault_latch_mask": field("fault_latch_mask", 0x8000_000f),
+                "link_loss_timeout_ms": field("link_loss_timeout_ms", 1_000),
+                "share_policy": extra
+                    .as_ref()
+                    .and_then(|v| v.get("share_policy"))
+                    .and_then(Value::as_str)
+                    .unwrap_or("auto"),
+                "sync": "applied"
+            })
+        }
+        "start_ir_measure" => {
+            let field = |key: &str, default: i64| {
+                extra
+                    .as_ref()
+                    .and_then(|v| v.get(key))
+                    .and_then(Value::as_i64)
+                    .unwrap_or(default)
+            };
+            json!({
+                "state": "running",
+                "config": {
+                    "low_ma": field("low_ma", 100),
+                    "high_ma": field("high_ma", 1_000),
+                    "cycles": field("cycles", 5),
+                    "settle_ms": field("settle_ms", 300),
+                    "sample_ms": field("sample_ms", 200),
+                },
+                "cycle": 0,
+                "result": null,
+                "error": null
+            })
+        }
+        // The mock run completes instantly with a 4-wire 50 mΩ DUT.
+        "get_ir_measure" => json!({
+            "state": "done",
+            "config": {"low_ma": 100, "high_ma": 1_000, "cycles": 5, "settle_ms": 300, "sample_ms": 200},
+            "cycle": null,
+            "result": {
+                "r_mohm": 50,
+                "r_uohm": 50_000,
+                "r_min_uohm": 49_000,
+                "r_max_uohm": 51_000,
+                "cycles": 5,
+                "v_low_mv": 12_000,
+                "v_high_mv": 11_955,
+                "i_low_ma": 100,
+                "i_high_ma": 1_000,
+                "sense": "remote"
+            },
+            "error": null
+        }),
+        "cancel_ir_measure" => json!({
+            "state": "idle",
+            "config": null,
+            "cycle": null,
+            "result": null,
+            "error": null
+        }),
+        "clear_faults" => json!({
+            "accepted": true,
+            "mask": extra.as_ref().and_then(|v| v.get("mask")).and_then(Value::as_u64).unwrap_or(0x8000_000f)
+        }),
+        "reset_counters" => json!({
+            "accepted": true,
+            "mask": extra.as_ref().and_then(|v| v.get("mask")).and_then(Value::as_u64).unwrap_or(0x07)
+        }),
+        "get_thermal" | "set_thermal" => {
+            let field = |key: &str, default: i64| {
+                extra
+                    .as_ref()
+                    .and_then(|v| v.get(key))
+                    .and_then(Value::as_i64)
+                    .unwrap_or(default)
+            };
+            json!({
+                "rth_mc_per_w": field("rth_mc_per_w", 300),
+                "tau_s": field("tau_s", 90),
+                "margin_mc": field("margin_mc", 10_000),
+                "min_derate_pct": field("min_derate_pct", 20),
+                "step_pct": field("step_pct", 5),
+                "recover_step_ms": field("recover_step_ms", 2_000),
+                "temp_trip_mc": 100_000,
+                "derate_pct": 100
+            })
         }
         "soft_reset" => json!({
             "accepted": true,
             "reason": extra.as_ref().and_then(|v| v.get("reason")).and_then(Value::as_str).unwrap_or("manual")
         }),
+        "scpi" => json!({
+            "response": mock_scpi_response(
+                extra.as_ref().and_then(|v| v.get("line")).and_then(Value::as_str).unwrap_or("")
+            )
+        }),
         "get_diagnostics" => json!({
             "schema_version": 1,
             "events": [],
@@ -4831,6 +6063,22 @@ fn mock_serial_probe(
     }
 }
 
+/// Canned answers for the queries in one SCPI program message; commands
+/// produce no output, like the firmware.
+fn mock_scpi_response(line: &str) -> String {
+    line.split(';')
+        .map(str::trim)
+        .filter(|command| command.ends_with('?'))
+        .map(|query| match query.to_ascii_uppercase().as_str() {
+            "*IDN?" => "LoadLynx,LoadLynx,mock-loadlynx-devd,mock".to_string(),
+            "*OPC?" => "1".to_string(),
+            query if query.contains("ERR") => "0,\"No error\"".to_string(),
+            _ => "0.000".to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
 fn mock_preset(preset_id: u8) -> Value {
     json!({
         "preset_id": preset_id.clamp(1, 5),
@@ -5997,6 +7245,91 @@ mod tests {
         assert_eq!(expanded["current_ch1_points"][1]["raw_dac_code"], 4095);
         assert_eq!(expanded["current_ch2_points"][0]["meas_ma"], 3);
         assert_eq!(expanded["v_remote_points"][0]["meas_mv"], 120);
+        assert!(expanded.get("temp_comp").is_none());
+    }
+
+    #[test]
+    fn expands_compact_usb_calibration_temp_comp() {
+        let expanded = expand_compact_calibration_profile(json!({
+            "compact": "cal_profile_v1",
+            "a": ["user-calibrated", 4, 42],
+            "s": "commit-verified",
+            "c1": [[0, 0, 0]],
+            "c2": [[0, 0, 0]],
+            "vl": [[0, 0]],
+            "vr": [[0, 0]],
+            "tc": [[0, 25000, -40, 150], [0, 25000, 0, 0], [1, 30000, 12, 0], [0, 25000, 0, 0]]
+        }))
+        .unwrap();
+
+        assert_eq!(expanded["active"]["fmt_version"], 4);
+        assert_eq!(expanded["temp_comp"]["current_ch1"]["gain_ppm_per_c"], -40);
+        assert_eq!(
+            expanded["temp_comp"]["current_ch1"]["offset_micro_per_c"],
+            150
+        );
+        assert_eq!(expanded["temp_comp"]["v_local"]["sensor"], "mcu");
+        assert_eq!(expanded["temp_comp"]["v_local"]["t_ref_mc"], 30000);
+
+        let err = expand_compact_calibration_profile(json!({
+            "compact": "cal_profile_v1",
+            "a": ["user-calibrated", 4, 42],
+            "c1": [], "c2": [], "vl": [], "vr": [],
+            "tc": [[5, 25000, 0, 0], [0, 25000, 0, 0], [0, 25000, 0, 0], [0, 25000, 0, 0]]
+        }))
+        .unwrap_err();
+        assert_eq!(err.0.code, "serial_response_invalid");
+    }
+
+    #[test]
+    fn calibration_report_reads_profiles_backups_and_apply_bodies() {
+        let profile = json!({
+            "active": {"source": "user-calibrated", "fmt_version": 4, "hw_rev": 42},
+            "current_ch1_points": [
+                {"raw_100uv": 0, "raw_dac_code": 0, "meas_ma": 0},
+                {"raw_100uv": 10000, "raw_dac_code": 1400, "meas_ma": 2300},
+                {"raw_100uv": 20000, "raw_dac_code": 2800, "meas_ma": 4000},
+                {"raw_100uv": 27500, "raw_dac_code": 3850, "meas_ma": 5500}
+            ],
+            "current_ch2_points": [],
+            "v_local_points": [[0, 0], [12500, 27500], [25000, 55000]],
+            "v_remote_points": []
+        });
+        let report = calibration_report(&profile).unwrap();
+        let ch1 = &report["curves"]["current_ch1"];
+        assert_eq!(ch1["unit"], "mA");
+        assert_eq!(ch1["residuals"][1]["loo_residual"], 300);
+        assert_eq!(ch1["est_accuracy_abs"], 300);
+        assert!(
+            ch1["warnings"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .any(|w| w["code"] == "large_residual")
+        );
+        assert_eq!(report["curves"]["v_local"]["est_accuracy_abs"], 0);
+        assert_eq!(
+            report["curves"]["v_local"]["segments"][0]["gain_per_mv"],
+            22.0
+        );
+        assert!(report["curves"].get("current_ch2").is_none());
+        assert_eq!(report["active"]["fmt_version"], 4);
+
+        let backup = json!({"sections": {"calibration": profile}});
+        assert_eq!(calibration_report(&backup).unwrap(), report);
+
+        let body = json!({"kind": "v_remote", "points": [[5000, 6000], [10000, 12000]]});
+        let report = calibration_report(&body).unwrap();
+        let codes = report["curves"]["v_remote"]["warnings"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|w| w["code"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(codes, ["extrapolates_below", "extrapolates_above"]);
+
+        assert!(calibration_report(&json!({"current_ch1_points": []})).is_err());
+        assert!(calibration_report(&json!({"kind": "bogus", "points": []})).is_err());
     }
 
     #[test]
@@ -6154,6 +7487,78 @@ mod tests {
         assert_eq!(op.command, "flash");
         assert_eq!(op.file_path, "digital");
         assert_eq!(op.flash_address, None);
+        assert_eq!(op.partition_table, None);
+
+        artifact.files.push(ArtifactFile {
+            kind: "partition_table".into(),
+            path: "partitions.csv".into(),
+            sha256: "unused".into(),
+            size: 1,
+            flash_address: None,
+        });
+        let op = selected_espflash_operation(&artifact).unwrap();
+        assert_eq!(op.partition_table.as_deref(), Some("partitions.csv"));
+    }
+
+    #[test]
+    fn lan_flash_requires_bare_ota_image() {
+        let mut artifact = test_artifact("a", TargetKind::DigitalEsp32s3);
+        artifact.files = vec![ArtifactFile {
+            kind: "image".into(),
+            path: "merged.bin".into(),
+            sha256: "unused".into(),
+            size: 1,
+            flash_address: Some(0),
+        }];
+        let err = selected_ota_image_file(&artifact).unwrap_err();
+        assert_eq!(err.0.code, "artifact_ota_image_missing");
+
+        artifact.files.push(ArtifactFile {
+            kind: "ota_image".into(),
+            path: "app.ota.bin".into(),
+            sha256: "unused".into(),
+            size: 1,
+            flash_address: None,
+        });
+        assert_eq!(
+            selected_ota_image_file(&artifact).unwrap().path,
+            "app.ota.bin"
+        );
+    }
+
+    #[test]
+    fn analog_bridge_flash_requires_image_at_flash_base() {
+        let mut artifact = test_artifact("a", TargetKind::AnalogStm32g431);
+        artifact.files = vec![
+            ArtifactFile {
+                kind: "elf".into(),
+                path: "analog.elf".into(),
+                sha256: "unused".into(),
+                size: 1,
+                flash_address: None,
+            },
+            ArtifactFile {
+                kind: "image".into(),
+                path: "other.bin".into(),
+                sha256: "unused".into(),
+                size: 1,
+                flash_address: Some(0x0800_4000),
+            },
+        ];
+        let err = selected_analog_bridge_image_file(&artifact).unwrap_err();
+        assert_eq!(err.0.code, "artifact_analog_image_missing");
+
+        artifact.files.push(ArtifactFile {
+            kind: "image".into(),
+            path: "analog.bin".into(),
+            sha256: "unused".into(),
+            size: 1,
+            flash_address: Some(0x0800_0000),
+        });
+        assert_eq!(
+            selected_analog_bridge_image_file(&artifact).unwrap().path,
+            "analog.bin"
+        );
     }
 
     #[test]
@@ -7436,6 +8841,54 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn scpi_bridge_forwards_lines_and_stops_with_lease() {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+        let state = AppState::new(PathBuf::from("."));
+        state.inner.lock().expect("state lock").leases.insert(
+            "lease-1".to_string(),
+            WebLease {
+                lease_id: "lease-1".to_string(),
+                device_id: "mock-loadlynx-devd".to_string(),
+                identity_device_id: None,
+                bind_probe: false,
+                legacy_preflash_only: false,
+                port_path: Some("mock://esp32s3".to_string()),
+                expires_at: Instant::now() + Duration::from_secs(30),
+            },
+        );
+
+        let err = scpi_bridge::start(&state, "mock-loadlynx-devd", None, 0)
+            .await
+            .unwrap_err();
+        assert_eq!(err.0.code, "web_session_required");
+
+        let bridge = scpi_bridge::start(&state, "mock-loadlynx-devd", Some("lease-1"), 0)
+            .await
+            .unwrap();
+        let address = bridge["address"].as_str().unwrap().to_string();
+        assert!(
+            bridge["visa_resource"]
+                .as_str()
+                .unwrap()
+                .starts_with("TCPIP0::127.0.0.1::")
+        );
+
+        let stream = tokio::net::TcpStream::connect(&address).await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        writer
+            .write_all(b"FUNC CC\n*IDN?;MEAS:VOLT?\n")
+            .await
+            .unwrap();
+        let reply = lines.next_line().await.unwrap().unwrap();
+        assert_eq!(reply, "LoadLynx,LoadLynx,mock-loadlynx-devd,mock;0.000");
+
+        assert!(release_lease_inner(&state, "lease-1", "released"));
+        assert!(scpi_bridge::describe(&state, "mock-loadlynx-devd").is_none());
+    }
+
     #[tokio::test]
     async fn bind_probe_lease_is_restricted_to_identity_binding() {
         let state = AppState::new(PathBuf::from("."));
@@ -0,0 +1,237 @@
+//! Virtual SCPI TCP endpoints for USB-attached devices.
+//!
+//! Each endpoint is a loopback listener owned by one device and the USB lease
+//! that started it. Received lines are forwarded as the firmware `scpi` JSONL
+//! op through the serial owner, so SCPI traffic interleaves with status
+//! polling instead of switching the CDC link into text mode. The endpoint is
+//! torn down when its lease is released or expires.
+
+use serde_json::{Value, json};
+use std::net::{Ipv4Addr, SocketAddr};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+
+use crate::{
+    AppState, CompatQuery, HttpError, compat_usb_json_request, emit, select_serial_port_for_compat,
+};
+
+#[derive(Debug)]
+pub(crate) struct ScpiBridge {
+    lease_id: String,
+    address: SocketAddr,
+    accept_task: JoinHandle<()>,
+}
+
+impl Drop for ScpiBridge {
+    fn drop(&mut self) {
+        self.accept_task.abort();
+    }
+}
+
+impl ScpiBridge {
+    fn json(&self, device_id: &str) -> Value {
+        json!({
+            "device_id": device_id,
+            "lease_id": self.lease_id,
+            "address": self.address.to_string(),
+            "visa_resource": format!(
+                "TCPIP0::{}::{}::SOCKET",
+                self.address.ip(),
+                self.address.port()
+            ),
+        })
+    }
+}
+
+pub(crate) fn describe(state: &AppState, device_id: &str) -> Option<Value> {
+    let guard = state.inner.lock().expect("state lock");
+    guard
+        .scpi_bridges
+        .get(device_id)
+        .map(|bridge| bridge.json(device_id))
+}
+
+/// Start (or return the running) endpoint for `device_id`. `port` 0 picks an
+/// ephemeral loopback port.
+pub(crate) async fn start(
+    state: &AppState,
+    device_id: &str,
+    lease_id: Option<&str>,
+    port: u16,
+) -> Result<Value, HttpError> {
+    let lease_id = lease_id.ok_or_else(|| {
+        HttpError::bad_request(
+            "web_session_required",
+            "SCPI bridge requires the lease_id of an active USB lease",
+        )
+    })?;
+    {
+        let guard = state.inner.lock().expect("state lock");
+        select_serial_port_for_compat(&guard, &bridge_query(device_id, lease_id), "SCPI bridge")?;
+        if let Some(bridge) = guard.scpi_bridges.get(device_id)
+            && bridge.lease_id == lease_id
+            && (port == 0 || bridge.address.port() == port)
+        {
+            return Ok(bridge.json(device_id));
+        }
+    }
+    // A different lease or port replaces the previous endpoint.
+    stop(state, device_id);
+
+    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, port)))
+        .await
+        .map_err(|error| {
+            HttpError::conflict(
+                "scpi_bind_failed",
+                format!("cannot listen on 127.0.0.1:{port}: {error}"),
+            )
+        })?;
+    let address = listener.local_addr().map_err(|error| {
+        HttpError::conflict("scpi_bind_failed", format!("listener address: {error}"))
+    })?;
+    let accept_task = tokio::spawn(accept_loop(
+        state.clone(),
+        device_id.to_string(),
+        lease_id.to_string(),
+        listener,
+    ));
+    let bridge = ScpiBridge {
+        lease_id: lease_id.to_string(),
+        address,
+        accept_task,
+    };
+    let value = bridge.json(device_id);
+    state
+        .inner
+        .lock()
+        .expect("state lock")
+        .scpi_bridges
+        .insert(device_id.to_string(), bridge);
+    emit(
+        state,
+        Some(device_id.to_string()),
+        "scpi_bridge",
+        &format!("SCPI bridge listening on {address}"),
+        value.clone(),
+    );
+    Ok(value)
+}
+
+pub(crate) fn stop(state: &AppState, device_id: &str) -> bool {
+    let removed = state
+        .inner
+        .lock()
+        .expect("state lock")
+        .scpi_bridges
+        .remove(device_id);
+    let Some(bridge) = removed else {
+        return false;
+    };
+    emit(
+        state,
+        Some(device_id.to_string()),
+        "scpi_bridge",
+        "SCPI bridge stopped",
+        json!({"address": bridge.address.to_string()}),
+    );
+    true
+}
+
+pub(crate) fn stop_for_lease(state: &AppState, lease_id: &str) {
+    let devices = {
+        let guard = state.inner.lock().expect("state lock");
+        guard
+            .scpi_bridges
+            .iter()
+            .filter(|(_, bridge)| bridge.lease_id == lease_id)
+            .map(|(device_id, _)| device_id.clone())
+            .collect::<Vec<_>>()
+    };
+    for device_id in devices {
+        stop(state, &device_id);
+    }
+}
+
+fn bridge_query(device_id: &str, lease_id: &str) -> CompatQuery {
+    CompatQuery {
+        device_id: Some(device_id.to_string()),
+        lease_id: Some(lease_id.to_string()),
+        fresh: false,
+        cache: false,
+    }
+}
+
+fn bridge_is_current(state: &AppState, device_id: &str, lease_id: &str) -> bool {
+    let guard = state.inner.lock().expect("state lock");
+    guard
+        .scpi_bridges
+        .get(device_id)
+        .is_some_and(|bridge| bridge.lease_id == lease_id)
+}
+
+async fn accept_loop(state: AppState, device_id: String, lease_id: String, listener: TcpListener) {
+    loop {
+        let Ok((stream, peer)) = listener.accept().await else {
+            continue;
+        };
+        tracing::info!("SCPI bridge for {device_id}: client {peer} connected");
+        tokio::spawn(serve_client(
+            state.clone(),
+            device_id.clone(),
+            lease_id.clone(),
+            stream,
+        ));
+    }
+}
+
+async fn serve_client(state: AppState, device_id: String, lease_id: String, stream: TcpStream) {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    let query = bridge_query(&device_id, &lease_id);
+    while let Ok(Some(line)) = lines.next_line().await {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if !bridge_is_current(&state, &device_id, &lease_id) {
+            break;
+        }
+        let result = compat_usb_json_request(
+            &state,
+            &query,
+            "scpi",
+            Some(json!({"line": line})),
+            "USB SCPI request completed",
+            "USB SCPI",
+        )
+        .await;
+        match result {
+            Ok((_, data)) => {
+                let response = data.get("response").and_then(Value::as_str).unwrap_or("");
+                if !response.is_empty()
+                    && writer
+                        .write_all(format!("{response}\n").as_bytes())
+                        .await
+                        .is_err()
+                {
+                    break;
+                }
+            }
+            Err(HttpError(error, _)) => {
+                // Close instead of leaving the client waiting for a reply
+                // that will never come.
+                emit(
+                    &state,
+                    Some(device_id.clone()),
+                    "scpi_bridge",
+                    &format!("SCPI request failed: {}", error.message),
+                    json!({"code": error.code}),
+                );
+                break;
+            }
+        }
+    }
+}
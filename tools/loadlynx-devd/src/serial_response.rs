@@ -284,7 +284,7 @@ fn wifi_state_appended_to_request_id(text: &str) -> Option<String> {
     };
     let end = tail.find('"')?;
     let request_text = &tail[..end];
-    for state in ["connected", "connecting", "idle", "error"] {
+    for state in ["connected", "connecting", "provisioning", "idle", "error"] {
         if request_text.ends_with(state) {
             return Some(state.to_string());
         }
@@ -294,7 +294,7 @@ fn wifi_state_appended_to_request_id(text: &str) -> Option<String> {
 
 fn wifi_state_after_colon_quote(text: &str) -> Option<String> {
     let mut best: Option<(usize, &str)> = None;
-    for state in ["connected", "connecting", "idle", "error"] {
+    for state in ["connected", "connecting", "provisioning", "idle", "error"] {
         let marker = format!(":\"{state}\"");
         if let Some(index) = text.find(&marker)
             && best.is_none_or(|(best_index, _)| index < best_index)
@@ -306,7 +306,10 @@ fn wifi_state_after_colon_quote(text: &str) -> Option<String> {
 }
 
 fn is_wifi_state_name(value: &str) -> bool {
-    matches!(value, "idle" | "connecting" | "connected" | "error")
+    matches!(
+        value,
+        "idle" | "connecting" | "connected" | "provisioning" | "error"
+    )
 }
 
 fn expected_op_for_request_id(request_id: &str) -> Option<&'static str> {
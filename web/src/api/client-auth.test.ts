@@ -0,0 +1,69 @@
+import { afterEach, beforeEach, expect, test, vi } from "vitest";
+
+import { pairApiToken } from "./client-auth.ts";
+import {
+  __testClearDeviceQueues,
+  __testHttpJsonQueued,
+  getApiToken,
+  setApiToken,
+} from "./client-core.ts";
+import { makeDeviceSocketUrl } from "./client-ws.ts";
+
+const TOKEN = "00112233445566778899aabbccddeeff";
+
+beforeEach(() => {
+  const entries = new Map<string, string>();
+  vi.stubGlobal("localStorage", {
+    getItem: (key: string) => entries.get(key) ?? null,
+    setItem: (key: string, value: string) => entries.set(key, value),
+  });
+});
+
+afterEach(() => {
+  vi.unstubAllGlobals();
+  vi.restoreAllMocks();
+  __testClearDeviceQueues();
+});
+
+test("pairing stores the token and later requests send it", async () => {
+  const authorizations: Array<string | undefined> = [];
+  vi.spyOn(globalThis, "fetch").mockImplementation(
+    async (_input: RequestInfo | URL, init?: RequestInit) => {
+      authorizations.push(
+        (init?.headers as Record<string, string> | undefined)?.Authorization,
+      );
+      return new Response(
+        JSON.stringify({ token: TOKEN, role: "control", label: "web" }),
+        { status: 200 },
+      );
+    },
+  );
+
+  const issued = await pairApiToken("http://192.0.2.55", { code: "042017" });
+  expect(issued.role).toBe("control");
+  expect(getApiToken("http://192.0.2.55/")).toBe(TOKEN);
+
+  await __testHttpJsonQueued("http://192.0.2.55", "/api/v1/status");
+  expect(authorizations).toEqual([undefined, `Bearer ${TOKEN}`]);
+});
+
+test("stream URLs carry the token as access_token", () => {
+  setApiToken("http://192.0.2.55", TOKEN);
+  const url = new URL(makeDeviceSocketUrl("http://192.0.2.55"));
+  expect(url.protocol).toBe("ws:");
+  expect(url.searchParams.get("access_token")).toBe(TOKEN);
+
+  setApiToken("http://192.0.2.55", null);
+  expect(makeDeviceSocketUrl("http://192.0.2.55")).not.toContain(
+    "access_token",
+  );
+});
+
+test("devd and mock base URLs never hold a token", () => {
+  const devd =
+    "http://127.0.0.1:24567/api/compat?device_id=ll-001&lease_id=lease-001";
+  setApiToken(devd, TOKEN);
+  setApiToken("mock://demo-1", TOKEN);
+  expect(getApiToken(devd)).toBeNull();
+  expect(getApiToken("mock://demo-1")).toBeNull();
+});
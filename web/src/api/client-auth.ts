@@ -0,0 +1,60 @@
+import { httpJsonQueued, setApiToken } from "./client-core.ts";
+import type {
+  ApiPairingStart,
+  ApiPairRequest,
+  ApiTokenList,
+  AuthStatus,
+  IssuedApiToken,
+} from "./types.ts";
+
+const JSON_HEADERS = {
+  "Content-Type": "application/json; charset=utf-8",
+};
+
+// Role of the token this browser holds for `baseUrl`. Without one the device
+// answers 401 UNAUTHORIZED.
+export async function getAuthStatus(baseUrl: string): Promise<AuthStatus> {
+  return httpJsonQueued<AuthStatus>(baseUrl, "/api/v1/auth");
+}
+
+// Opens the device's pairing window; the six-digit code appears on its screen.
+export async function startApiPairing(
+  baseUrl: string,
+): Promise<ApiPairingStart> {
+  return httpJsonQueued<ApiPairingStart>(baseUrl, "/api/v1/auth/pair/start", {
+    method: "POST",
+  });
+}
+
+// Trades the on-screen code for a token and remembers it for this device.
+export async function pairApiToken(
+  baseUrl: string,
+  request: ApiPairRequest,
+): Promise<IssuedApiToken> {
+  const issued = await httpJsonQueued<IssuedApiToken>(
+    baseUrl,
+    "/api/v1/auth/pair",
+    {
+      method: "POST",
+      body: JSON.stringify(request),
+      headers: JSON_HEADERS,
+    },
+  );
+  setApiToken(baseUrl, issued.token);
+  return issued;
+}
+
+export async function getApiTokens(baseUrl: string): Promise<ApiTokenList> {
+  return httpJsonQueued<ApiTokenList>(baseUrl, "/api/v1/auth/tokens");
+}
+
+export async function revokeApiToken(
+  baseUrl: string,
+  label: string,
+): Promise<ApiTokenList> {
+  return httpJsonQueued<ApiTokenList>(baseUrl, "/api/v1/auth/tokens/revoke", {
+    method: "POST",
+    body: JSON.stringify({ label }),
+    headers: JSON_HEADERS,
+  });
+}
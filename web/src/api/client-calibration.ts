@@ -1,10 +1,16 @@
 import { httpJsonQueued, isMockBaseUrl } from "./client-core.ts";
 import {
   mockGetCalibrationProfile,
+  mockGetCalibrationSlots,
+  mockGetCalibrationVerify,
   mockPostCalibrationApply,
   mockPostCalibrationCommit,
   mockPostCalibrationMode,
   mockPostCalibrationReset,
+  mockPostCalibrationSlotActivate,
+  mockPostCalibrationSlotDelete,
+  mockPostCalibrationSlotRename,
+  mockPostCalibrationVerify,
 } from "./client-mock.ts";
 import type {
   CalibrationApplyRequest,
@@ -15,6 +21,10 @@ import type {
   CalibrationProfile,
   CalibrationProfileWire,
   CalibrationResetRequest,
+  CalibrationSlotsResponse,
+  CalibrationVerifyRequest,
+  CalibrationVerifyResult,
+  CalibrationVerifyResultsResponse,
   CalibrationWriteRequestWire,
 } from "./types.ts";
 
@@ -42,6 +52,7 @@ export function mapCalibrationProfileWireToUi(
       ua: point.meas_ma * 1000,
       dac_code: point.raw_dac_code,
     })),
+    temp_comp: profile.temp_comp,
   };
 }
 
@@ -69,6 +80,7 @@ export function mapCalibrationProfileUiToWire(
       raw_dac_code: point.dac_code,
       meas_ma: Math.floor((point.ua + 500) / 1000),
     })),
+    temp_comp: profile.temp_comp,
   };
 }
 
@@ -175,3 +187,100 @@ export async function postCalibrationMode(
     },
   });
 }
+
+export async function getCalibrationSlots(
+  baseUrl: string,
+): Promise<CalibrationSlotsResponse> {
+  if (isMockBaseUrl(baseUrl)) {
+    return mockGetCalibrationSlots(baseUrl);
+  }
+  return httpJsonQueued<CalibrationSlotsResponse>(
+    baseUrl,
+    "/api/v1/calibration/slots",
+  );
+}
+
+export async function postCalibrationSlotActivate(
+  baseUrl: string,
+  slot: number,
+): Promise<void> {
+  if (isMockBaseUrl(baseUrl)) {
+    return mockPostCalibrationSlotActivate(baseUrl, slot);
+  }
+  return httpJsonQueued<void>(baseUrl, "/api/v1/calibration/slots/activate", {
+    method: "POST",
+    body: JSON.stringify({ slot }),
+    headers: {
+      "Content-Type": "text/plain",
+    },
+  });
+}
+
+export async function postCalibrationSlotRename(
+  baseUrl: string,
+  slot: number,
+  name: string,
+): Promise<void> {
+  if (isMockBaseUrl(baseUrl)) {
+    return mockPostCalibrationSlotRename(baseUrl, slot, name);
+  }
+  return httpJsonQueued<void>(baseUrl, "/api/v1/calibration/slots/rename", {
+    method: "POST",
+    body: JSON.stringify({ slot, name }),
+    headers: {
+      "Content-Type": "text/plain",
+    },
+  });
+}
+
+export async function postCalibrationSlotDelete(
+  baseUrl: string,
+  slot: number,
+): Promise<void> {
+  if (isMockBaseUrl(baseUrl)) {
+    return mockPostCalibrationSlotDelete(baseUrl, slot);
+  }
+  return httpJsonQueued<void>(baseUrl, "/api/v1/calibration/slots/delete", {
+    method: "POST",
+    body: JSON.stringify({ slot }),
+    headers: {
+      "Content-Type": "text/plain",
+    },
+  });
+}
+
+export async function getCalibrationVerify(
+  baseUrl: string,
+): Promise<CalibrationVerifyResultsResponse> {
+  if (isMockBaseUrl(baseUrl)) {
+    return mockGetCalibrationVerify(baseUrl);
+  }
+  return httpJsonQueued<CalibrationVerifyResultsResponse>(
+    baseUrl,
+    "/api/v1/calibration/verify",
+  );
+}
+
+export async function postCalibrationVerify(
+  baseUrl: string,
+  payload: CalibrationVerifyRequest,
+): Promise<CalibrationVerifyResult> {
+  const body: CalibrationVerifyRequest = {
+    ts_unix_ms: Date.now(),
+    ...payload,
+  };
+  if (isMockBaseUrl(baseUrl)) {
+    return mockPostCalibrationVerify(baseUrl, body);
+  }
+  return httpJsonQueued<CalibrationVerifyResult>(
+    baseUrl,
+    "/api/v1/calibration/verify",
+    {
+      method: "POST",
+      body: JSON.stringify(body),
+      headers: {
+        "Content-Type": "text/plain",
+      },
+    },
+  );
+}
@@ -48,6 +48,69 @@ export function isDevdCompatBaseUrl(baseUrl: string): boolean {
   }
 }
 
+// Device HTTP API tokens (see "Authentication" in
+// docs/interfaces/network-http-api.md), keyed by device origin. devd compat
+// URLs need none: devd reaches the device over USB, which the device trusts.
+const API_TOKEN_STORAGE_KEY = "loadlynx.apiTokens";
+
+function apiTokenOrigin(baseUrl: string): string | null {
+  if (!baseUrl || isMockBaseUrl(baseUrl) || isDevdCompatBaseUrl(baseUrl)) {
+    return null;
+  }
+  try {
+    return new URL(baseUrl).origin;
+  } catch {
+    return null;
+  }
+}
+
+function readApiTokens(): Record<string, string> {
+  try {
+    const raw = globalThis.localStorage?.getItem(API_TOKEN_STORAGE_KEY);
+    const parsed = raw ? (JSON.parse(raw) as unknown) : null;
+    return parsed && typeof parsed === "object"
+      ? (parsed as Record<string, string>)
+      : {};
+  } catch {
+    return {};
+  }
+}
+
+export function getApiToken(baseUrl: string): string | null {
+  const origin = apiTokenOrigin(baseUrl);
+  if (!origin) {
+    return null;
+  }
+  const token = readApiTokens()[origin];
+  return typeof token === "string" && token.length > 0 ? token : null;
+}
+
+export function setApiToken(baseUrl: string, token: string | null): void {
+  const origin = apiTokenOrigin(baseUrl);
+  const storage = globalThis.localStorage;
+  if (!origin || !storage) {
+    return;
+  }
+  const tokens = readApiTokens();
+  if (token) {
+    tokens[origin] = token;
+  } else {
+    delete tokens[origin];
+  }
+  storage.setItem(API_TOKEN_STORAGE_KEY, JSON.stringify(tokens));
+}
+
+// EventSource and WebSocket cannot send an Authorization header, so the
+// device also accepts the token as `?access_token=` on those two upgrades
+// (`/api/v1/status` SSE and `/api/v1/ws`) only.
+export function withApiToken(url: URL, baseUrl: string): URL {
+  const token = getApiToken(baseUrl);
+  if (token) {
+    url.searchParams.set("access_token", token);
+  }
+  return url;
+}
+
 export function supportsBackupWifiCredentials(baseUrl: string): boolean {
   return Boolean(baseUrl);
 }
@@ -96,7 +159,7 @@ interface ErrorEnvelope {
   };
 }
 
-function mapHttpError(status: number, data: unknown): HttpApiError {
+export function mapHttpError(status: number, data: unknown): HttpApiError {
   const envelope = (data ?? {}) as ErrorEnvelope;
   const inner = envelope.error ?? {};
 
@@ -199,6 +262,11 @@ async function httpJson<T>(
 
   headers.Connection ||= "close";
 
+  const token = getApiToken(baseUrl);
+  if (token) {
+    headers.Authorization ||= `Bearer ${token}`;
+  }
+
   const hasBody = init?.body !== undefined && init.body !== null;
   if (hasBody || method.toUpperCase() !== "GET") {
     headers["Content-Type"] ||= "application/json";
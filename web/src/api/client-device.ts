@@ -5,19 +5,24 @@ import {
   isStorybookRuntime,
   makeApiUrl,
   TAB_ID,
+  withApiToken,
 } from "./client-core.ts";
 import {
   type DevdIdentityPayload,
   type DevdStatusPayload,
   mockApplyPreset,
+  mockCancelIrMeasure,
   mockDebugSetUvLatched,
   mockGetCc,
   mockGetControl,
   mockGetIdentity,
+  mockGetIrMeasure,
   mockGetPd,
   mockGetPresets,
   mockGetStatus,
+  mockResetCounters,
   mockSoftReset,
+  mockStartIrMeasure,
   mockUpdateCc,
   mockUpdateControl,
   mockUpdatePd,
@@ -25,6 +30,11 @@ import {
   normalizeDevdIdentity,
   normalizeDevdStatus,
 } from "./client-mock.ts";
+import {
+  getDeviceSocket,
+  getOpenDeviceSocket,
+  supportsDeviceSocket,
+} from "./client-ws.ts";
 import { subscribeMockStatusStream } from "./mock-status-stream.ts";
 import type {
   ApplyPresetRequest,
@@ -32,9 +42,13 @@ import type {
   CcUpdateRequest,
   ControlUpdateRequest,
   ControlView,
+  CounterSelector,
+  CountersResetResponse,
   FastStatusResponse,
   FastStatusView,
   Identity,
+  IrMeasureRequest,
+  IrMeasureView,
   PdUpdateRequest,
   PdView,
   Preset,
@@ -44,14 +58,17 @@ import type {
   SoftResetResponse,
 } from "./types.ts";
 
-function toFastStatusView(payload: FastStatusResponse): FastStatusView {
+export function toFastStatusView(payload: FastStatusResponse): FastStatusView {
   return {
     raw: payload.status,
     link_up: payload.link_up,
     hello_seen: payload.hello_seen,
     analog_state: payload.analog_state,
+    thermal: payload.thermal,
     fault_flags_decoded: payload.fault_flags_decoded,
     state_flags_decoded: payload.state_flags_decoded ?? [],
+    sense_warnings_decoded: payload.sense_warnings_decoded,
+    channel_warnings_decoded: payload.channel_warnings_decoded,
   };
 }
 
@@ -74,6 +91,17 @@ function makeSoftResetRequest(reason: SoftResetReason): SoftResetRequest {
   return { reason };
 }
 
+// Route a one-shot call over the device WebSocket when one is already open,
+// so it does not compete with the firmware's small HTTP worker pool.
+function requestViaDeviceSocket<T>(
+  baseUrl: string,
+  op: string,
+  data?: unknown,
+): Promise<T> | null {
+  const socket = getOpenDeviceSocket(baseUrl);
+  return socket === null ? null : socket.request<T>(op, data);
+}
+
 export async function getIdentity(baseUrl: string): Promise<Identity> {
   if (isMockBaseUrl(baseUrl)) {
     return mockGetIdentity(baseUrl);
@@ -148,7 +176,50 @@ export function subscribeStatusStream(
     );
   }
 
-  const url = makeApiUrl(baseUrl, "/api/v1/status");
+  if (!supportsDeviceSocket(baseUrl)) {
+    return subscribeStatusSse(baseUrl, onMessage, onError);
+  }
+
+  // Prefer the multiplexed WebSocket; fall back to SSE while it is
+  // unavailable (busy session, older firmware, reconnect backoff).
+  const socket = getDeviceSocket(baseUrl);
+  let unsubscribeSse: (() => void) | null = null;
+  const startSse = () => {
+    unsubscribeSse ??= subscribeStatusSse(baseUrl, onMessage, onError);
+  };
+  const stopSse = () => {
+    unsubscribeSse?.();
+    unsubscribeSse = null;
+  };
+  const offStatus = socket.onStatus((payload) => {
+    onMessage(toFastStatusView(payload));
+  });
+  const offState = socket.onStateChange((state) => {
+    if (state === "open") {
+      stopSse();
+    } else if (state === "unavailable") {
+      startSse();
+    }
+  });
+  socket.retain();
+  if (socket.state === "unavailable") {
+    startSse();
+  }
+
+  return () => {
+    offStatus();
+    offState();
+    stopSse();
+    socket.release();
+  };
+}
+
+function subscribeStatusSse(
+  baseUrl: string,
+  onMessage: (view: FastStatusView) => void,
+  onError?: (error: Event | Error) => void,
+): () => void {
+  const url = withApiToken(makeApiUrl(baseUrl, "/api/v1/status"), baseUrl);
   let closed = false;
 
   const isFastStatusView = (value: unknown): value is FastStatusView => {
@@ -323,6 +394,10 @@ export async function getCc(baseUrl: string): Promise<CcControlView> {
   if (isMockBaseUrl(baseUrl)) {
     return mockGetCc(baseUrl);
   }
+  const viaSocket = requestViaDeviceSocket<CcControlView>(baseUrl, "cc.get");
+  if (viaSocket !== null) {
+    return viaSocket;
+  }
   return httpJsonQueued<CcControlView>(baseUrl, "/api/v1/cc");
 }
 
@@ -330,6 +405,10 @@ export async function getPd(baseUrl: string): Promise<PdView> {
   if (isMockBaseUrl(baseUrl)) {
     return mockGetPd(baseUrl);
   }
+  const viaSocket = requestViaDeviceSocket<PdView>(baseUrl, "pd.get");
+  if (viaSocket !== null) {
+    return viaSocket;
+  }
   return httpJsonQueued<PdView>(baseUrl, "/api/v1/pd");
 }
 
@@ -340,6 +419,10 @@ export async function postPd(
   if (isMockBaseUrl(baseUrl)) {
     return mockUpdatePd(baseUrl, payload);
   }
+  const viaSocket = requestViaDeviceSocket<PdView>(baseUrl, "pd.set", payload);
+  if (viaSocket !== null) {
+    return viaSocket;
+  }
   return httpJsonQueued<PdView>(baseUrl, "/api/v1/pd", {
     method: "POST",
     body: JSON.stringify(payload),
@@ -356,6 +439,14 @@ export async function updateCc(
   if (isMockBaseUrl(baseUrl)) {
     return mockUpdateCc(baseUrl, payload);
   }
+  const viaSocket = requestViaDeviceSocket<CcControlView>(
+    baseUrl,
+    "cc.set",
+    payload,
+  );
+  if (viaSocket !== null) {
+    return viaSocket;
+  }
   return httpJsonQueued<CcControlView>(baseUrl, "/api/v1/cc", {
     method: "POST",
     body: JSON.stringify(payload),
@@ -369,6 +460,13 @@ export async function getPresets(baseUrl: string): Promise<Preset[]> {
   if (isMockBaseUrl(baseUrl)) {
     return (await mockGetPresets(baseUrl)).presets;
   }
+  const viaSocket = requestViaDeviceSocket<PresetsResponse>(
+    baseUrl,
+    "presets.get",
+  );
+  if (viaSocket !== null) {
+    return (await viaSocket).presets;
+  }
   const payload = await httpJsonQueued<PresetsResponse>(
     baseUrl,
     "/api/v1/presets",
@@ -383,6 +481,14 @@ export async function updatePreset(
   if (isMockBaseUrl(baseUrl)) {
     return mockUpdatePreset(baseUrl, payload);
   }
+  const viaSocket = requestViaDeviceSocket<Preset>(
+    baseUrl,
+    "presets.set",
+    payload,
+  );
+  if (viaSocket !== null) {
+    return viaSocket;
+  }
   return httpJsonQueued<Preset>(baseUrl, "/api/v1/presets", {
     method: "POST",
     body: JSON.stringify(payload),
@@ -400,6 +506,14 @@ export async function applyPreset(
     return mockApplyPreset(baseUrl, preset_id);
   }
   const payload = makeApplyPresetRequest(preset_id);
+  const viaSocket = requestViaDeviceSocket<ControlView>(
+    baseUrl,
+    "presets.apply",
+    payload,
+  );
+  if (viaSocket !== null) {
+    return viaSocket;
+  }
   return httpJsonQueued<ControlView>(baseUrl, "/api/v1/presets/apply", {
     method: "POST",
     body: JSON.stringify(payload),
@@ -413,6 +527,10 @@ export async function getControl(baseUrl: string): Promise<ControlView> {
   if (isMockBaseUrl(baseUrl)) {
     return mockGetControl(baseUrl);
   }
+  const viaSocket = requestViaDeviceSocket<ControlView>(baseUrl, "control.get");
+  if (viaSocket !== null) {
+    return viaSocket;
+  }
   return httpJsonQueued<ControlView>(baseUrl, "/api/v1/control");
 }
 
@@ -423,6 +541,14 @@ export async function updateControl(
   if (isMockBaseUrl(baseUrl)) {
     return mockUpdateControl(baseUrl, payload);
   }
+  const viaSocket = requestViaDeviceSocket<ControlView>(
+    baseUrl,
+    "control.set",
+    payload,
+  );
+  if (viaSocket !== null) {
+    return viaSocket;
+  }
   return httpJsonQueued<ControlView>(baseUrl, "/api/v1/control", {
     method: "POST",
     body: JSON.stringify(payload),
@@ -460,3 +586,73 @@ export async function postSoftReset(
     },
   });
 }
+
+// `COUNTER_*` selector bits of POST /api/v1/counters/reset.
+const COUNTER_SELECTOR_BITS: Record<CounterSelector, number> = {
+  charge: 1 << 0,
+  energy: 1 << 1,
+  time: 1 << 2,
+};
+
+// `counters` empty = reset charge, energy and timers (the firmware default).
+export async function postCountersReset(
+  baseUrl: string,
+  counters: CounterSelector[] = [],
+): Promise<CountersResetResponse> {
+  if (isMockBaseUrl(baseUrl)) {
+    return mockResetCounters(baseUrl, counters);
+  }
+  const mask = counters.reduce(
+    (acc, counter) => acc | COUNTER_SELECTOR_BITS[counter],
+    0,
+  );
+  return httpJsonQueued<CountersResetResponse>(
+    baseUrl,
+    "/api/v1/counters/reset",
+    {
+      method: "POST",
+      body: JSON.stringify(mask === 0 ? {} : { mask }),
+      headers: {
+        "Content-Type": "application/json; charset=utf-8",
+      },
+    },
+  );
+}
+
+export async function getIrMeasure(baseUrl: string): Promise<IrMeasureView> {
+  if (isMockBaseUrl(baseUrl)) {
+    return mockGetIrMeasure(baseUrl);
+  }
+  return httpJsonQueued<IrMeasureView>(baseUrl, "/api/v1/measure/ir");
+}
+
+export async function startIrMeasure(
+  baseUrl: string,
+  payload: IrMeasureRequest = {},
+): Promise<IrMeasureView> {
+  if (isMockBaseUrl(baseUrl)) {
+    return mockStartIrMeasure(baseUrl, payload);
+  }
+  return httpJsonQueued<IrMeasureView>(baseUrl, "/api/v1/measure/ir", {
+    method: "POST",
+    body: JSON.stringify(payload),
+    headers: {
+      "Content-Type": "text/plain",
+    },
+  });
+}
+
+export async function cancelIrMeasure(
+  baseUrl: string,
+): Promise<IrMeasureView> {
+  if (isMockBaseUrl(baseUrl)) {
+    return mockCancelIrMeasure(baseUrl);
+  }
+  return httpJsonQueued<IrMeasureView>(baseUrl, "/api/v1/measure/ir/cancel", {
+    method: "POST",
+    body: "{}",
+    headers: {
+      "Content-Type": "text/plain",
+    },
+  });
+}
@@ -1,3 +1,4 @@
+import { type Point, piecewiseLinear } from "../calibration/piecewise.ts";
 import { CALIBRATION_MAX_POINTS } from "../calibration/validation.ts";
 import { HttpApiError } from "./client-core.ts";
 import { getOrCreateMockDevice } from "./client-mock-state.ts";
@@ -10,6 +11,11 @@ import type {
   CalibrationProfile,
   CalibrationProfileWire,
   CalibrationResetRequest,
+  CalibrationCurveKind,
+  CalibrationSlotsResponse,
+  CalibrationVerifyRequest,
+  CalibrationVerifyResult,
+  CalibrationVerifyResultsResponse,
 } from "./types.ts";
 
 function mapCalibrationProfileWireToUi(
@@ -351,3 +357,169 @@ export async function mockPostCalibrationMode(
   const state = getOrCreateMockDevice(baseUrl);
   state.calibrationMode = payload.kind;
 }
+
+function mockSlotIndex(slot: number): number {
+  if (!Number.isInteger(slot) || slot < 0 || slot > 3) {
+    mockCalValidationError("slot is out of range");
+  }
+  return slot;
+}
+
+export async function mockGetCalibrationSlots(
+  baseUrl: string,
+): Promise<CalibrationSlotsResponse> {
+  const { calibration } = getOrCreateMockDevice(baseUrl);
+  return {
+    active: calibration.activeSlot,
+    slots: calibration.slots.map((slot, index) => {
+      const active = index === calibration.activeSlot;
+      const eeprom = active ? calibration.eeprom : slot.eeprom;
+      return { slot: index, name: slot.name, used: eeprom !== null, active };
+    }),
+  };
+}
+
+export async function mockPostCalibrationSlotActivate(
+  baseUrl: string,
+  slot: number,
+): Promise<void> {
+  const state = getOrCreateMockDevice(baseUrl);
+  const calibration = state.calibration;
+  const next = mockSlotIndex(slot);
+  if (state.calibrationMode !== "off") {
+    throw new HttpApiError({
+      status: 409,
+      code: "INVALID_STATE",
+      message: "leave calibration mode before switching slots",
+      retryable: false,
+      details: null,
+    });
+  }
+  calibration.slots[calibration.activeSlot].eeprom = calibration.eeprom;
+  calibration.activeSlot = next;
+  calibration.eeprom = calibration.slots[next].eeprom;
+  calibration.ram = structuredClone(calibration.eeprom ?? calibration.factory);
+  calibration.ram.persistence = {
+    status: calibration.eeprom ? "user-profile-loaded" : "factory-default",
+  };
+}
+
+export async function mockPostCalibrationSlotRename(
+  baseUrl: string,
+  slot: number,
+  name: string,
+): Promise<void> {
+  const { calibration } = getOrCreateMockDevice(baseUrl);
+  if (!/^[A-Za-z0-9 _.-]{1,16}$/.test(name)) {
+    mockCalValidationError(
+      "name must be 1..16 characters of A-Z a-z 0-9 space _ . -",
+    );
+  }
+  calibration.slots[mockSlotIndex(slot)].name = name;
+}
+
+export async function mockPostCalibrationSlotDelete(
+  baseUrl: string,
+  slot: number,
+): Promise<void> {
+  const { calibration } = getOrCreateMockDevice(baseUrl);
+  const index = mockSlotIndex(slot);
+  if (index === calibration.activeSlot) {
+    throw new HttpApiError({
+      status: 409,
+      code: "INVALID_STATE",
+      message: "cannot delete the active slot",
+      retryable: false,
+      details: null,
+    });
+  }
+  calibration.slots[index] = { name: "", eeprom: null };
+}
+
+function mockProfileCurve(
+  profile: CalibrationProfileWire,
+  kind: CalibrationCurveKind,
+): Point[] {
+  switch (kind) {
+    case "v_local":
+      return profile.v_local_points.map((p) => ({
+        x: p.raw_100uv,
+        y: p.meas_mv,
+      }));
+    case "v_remote":
+      return profile.v_remote_points.map((p) => ({
+        x: p.raw_100uv,
+        y: p.meas_mv,
+      }));
+    case "current_ch1":
+      return profile.current_ch1_points.map((p) => ({
+        x: p.raw_100uv,
+        y: p.meas_ma,
+      }));
+    case "current_ch2":
+      return profile.current_ch2_points.map((p) => ({
+        x: p.raw_100uv,
+        y: p.meas_ma,
+      }));
+  }
+}
+
+export async function mockGetCalibrationVerify(
+  baseUrl: string,
+): Promise<CalibrationVerifyResultsResponse> {
+  const { calibration } = getOrCreateMockDevice(baseUrl);
+  const current = (result: CalibrationVerifyResult | null) =>
+    result && { ...result, current: result.slot === calibration.activeSlot };
+  return {
+    results: {
+      current_ch1: current(calibration.verifyResults.current_ch1),
+      current_ch2: current(calibration.verifyResults.current_ch2),
+      v_local: current(calibration.verifyResults.v_local),
+      v_remote: current(calibration.verifyResults.v_remote),
+    },
+  };
+}
+
+export async function mockPostCalibrationVerify(
+  baseUrl: string,
+  payload: CalibrationVerifyRequest,
+): Promise<CalibrationVerifyResult> {
+  const { calibration } = getOrCreateMockDevice(baseUrl);
+  if (payload.points.length === 0 || payload.points.length > 12) {
+    mockCalValidationError("points must contain 1..12 items");
+  }
+  const abs = payload.tolerance_abs ?? 5;
+  const ppm = payload.tolerance_ppm ?? 2000;
+  const curve = mockProfileCurve(calibration.ram, payload.kind);
+  const points = payload.points.map(([raw, reference, tol]) => {
+    const reading = Math.trunc(piecewiseLinear(curve, raw));
+    const tolerance =
+      tol ?? abs + Math.floor((Math.abs(reference) * ppm) / 1_000_000);
+    const error = reading - reference;
+    return {
+      raw_100uv: raw,
+      reference,
+      reading,
+      error,
+      tolerance,
+      passed: Math.abs(error) <= tolerance,
+    };
+  });
+  const worst = points.reduce<number | null>(
+    (acc, p) =>
+      acc === null || Math.abs(p.error) > Math.abs(acc) ? p.error : acc,
+    null,
+  );
+  const result: CalibrationVerifyResult = {
+    kind: payload.kind,
+    ts_unix_ms: payload.ts_unix_ms ?? null,
+    slot: calibration.activeSlot,
+    profile_crc32: 0,
+    current: true,
+    passed: points.every((p) => p.passed),
+    worst_error: worst,
+    points,
+  };
+  calibration.verifyResults[payload.kind] = result;
+  return { ...result, stored: true };
+}
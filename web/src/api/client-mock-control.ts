@@ -10,8 +10,13 @@ import type {
   CcUpdateRequest,
   ControlUpdateRequest,
   ControlView,
+  CounterSelector,
+  CountersResetResponse,
   FastStatusView,
   Identity,
+  IrMeasureConfig,
+  IrMeasureRequest,
+  IrMeasureView,
   LoadMode,
   Preset,
   PresetId,
@@ -237,6 +242,14 @@ export async function mockGetStatus(baseUrl: string): Promise<FastStatusView> {
   next.raw.sink_core_temp_mc = electrical.sinkCoreTempMc;
   next.raw.sink_exhaust_temp_mc = electrical.sinkExhaustTempMc;
   next.raw.mcu_temp_mc = electrical.mcuTempMc;
+  // mA·ms / 3600 = µAh (and mW·ms / 3600 = µWh).
+  next.raw.charge_uah =
+    (next.raw.charge_uah ?? 0) + (electrical.iTotalMa * elapsedMs) / 3600;
+  next.raw.energy_uwh =
+    (next.raw.energy_uwh ?? 0) + (electrical.pMainMw * elapsedMs) / 3600;
+  next.raw.counter_elapsed_ms = (next.raw.counter_elapsed_ms ?? 0) + elapsedMs;
+  next.raw.counter_on_ms =
+    (next.raw.counter_on_ms ?? 0) + (state.output_enabled ? elapsedMs : 0);
 
   state.cc = {
     ...state.cc,
@@ -274,6 +287,32 @@ export async function mockGetStatus(baseUrl: string): Promise<FastStatusView> {
     next.state_flags_decoded.push("POWER_LIMITED");
   }
 
+  // Mock wiring is always healthy; only the lead resistance estimate moves.
+  next.sense_warnings_decoded = [];
+  delete next.raw.sense_warnings;
+  if (state.output_enabled && electrical.iTotalMa >= 500) {
+    next.raw.lead_resistance_mohm = Math.round(
+      (Math.abs(electrical.vLocalMv - electrical.vRemoteMv) * 1000) /
+        electrical.iTotalMa,
+    );
+  } else {
+    delete next.raw.lead_resistance_mohm;
+  }
+
+  // Mock channels always share cleanly; FET temps follow the firmware
+  // estimate (sink core + V_local × I_ch × 0.53 °C/W).
+  next.channel_warnings_decoded = [];
+  delete next.raw.channel_warnings;
+  next.raw.share_policy = "auto";
+  next.raw.fet_temp_ch1_mc = Math.round(
+    electrical.sinkCoreTempMc +
+      (electrical.vLocalMv * electrical.iLocalMa * 0.53) / 1000,
+  );
+  next.raw.fet_temp_ch2_mc = Math.round(
+    electrical.sinkCoreTempMc +
+      (electrical.vLocalMv * electrical.iRemoteMa * 0.53) / 1000,
+  );
+
   switch (state.calibrationMode) {
     case "voltage":
       next.raw.cal_kind = 1;
@@ -625,3 +664,178 @@ export async function mockSoftReset(
   }
   return { accepted: true, reason };
 }
+
+const COUNTER_BITS: Record<CounterSelector, number> = {
+  charge: 1 << 0,
+  energy: 1 << 1,
+  time: 1 << 2,
+};
+
+function counterSelectorsMask(counters: CounterSelector[]): number {
+  return counters.reduce((mask, counter) => mask | COUNTER_BITS[counter], 0);
+}
+
+export async function mockResetCounters(
+  baseUrl: string,
+  counters: CounterSelector[],
+): Promise<CountersResetResponse> {
+  const state = getOrCreateMockDevice(baseUrl);
+  const mask = counters.length > 0 ? counterSelectorsMask(counters) : 0x07;
+  const raw = state.status.raw;
+  if (mask & COUNTER_BITS.charge) raw.charge_uah = 0;
+  if (mask & COUNTER_BITS.energy) raw.energy_uwh = 0;
+  if (mask & COUNTER_BITS.time) {
+    raw.counter_elapsed_ms = 0;
+    raw.counter_on_ms = 0;
+  }
+  return { accepted: true, mask };
+}
+
+const IR_MEASURE_DEFAULT: IrMeasureConfig = {
+  low_ma: 100,
+  high_ma: 1_000,
+  cycles: 5,
+  settle_ms: 300,
+  sample_ms: 200,
+};
+
+function mockIrLimitViolation(field: string, maxIMa: number, maxPMw: number) {
+  return new HttpApiError({
+    status: 422,
+    code: "LIMIT_VIOLATION",
+    message: "IR measurement parameters outside the active preset limits",
+    retryable: false,
+    details: { field, max_i_ma: maxIMa, max_p_mw: maxPMw },
+  });
+}
+
+function mockAdvanceIrMeasure(state: MockDeviceState) {
+  const run = state.irMeasure;
+  const config = run.view.config;
+  if (run.view.state !== "running" || !config || run.startedAtMs === null) {
+    return;
+  }
+  const cycleMs = 2 * (config.settle_ms + config.sample_ms);
+  const elapsedMs = Date.now() - run.startedAtMs;
+  if (elapsedMs < cycleMs * config.cycles) {
+    run.view.cycle = Math.floor(elapsedMs / cycleMs);
+    return;
+  }
+
+  const { openCircuitMv, sourceResistanceMilliohm } = state.simulation.profile;
+  // mA * mΩ = µV
+  const vLowMv = Math.round(
+    openCircuitMv - (config.low_ma * sourceResistanceMilliohm) / 1000,
+  );
+  const vHighMv = Math.round(
+    openCircuitMv - (config.high_ma * sourceResistanceMilliohm) / 1000,
+  );
+  const rUohm = sourceResistanceMilliohm * 1000;
+  run.startedAtMs = null;
+  run.view = {
+    state: "done",
+    config,
+    cycle: null,
+    result: {
+      r_mohm: sourceResistanceMilliohm,
+      r_uohm: rUohm,
+      r_min_uohm: Math.round(rUohm * 0.99),
+      r_max_uohm: Math.round(rUohm * 1.01),
+      cycles: config.cycles,
+      v_low_mv: vLowMv,
+      v_high_mv: vHighMv,
+      i_low_ma: config.low_ma,
+      i_high_ma: config.high_ma,
+      sense: "remote",
+    },
+    error: null,
+  };
+}
+
+export async function mockGetIrMeasure(
+  baseUrl: string,
+): Promise<IrMeasureView> {
+  const state = getOrCreateMockDevice(baseUrl);
+  mockAdvanceIrMeasure(state);
+  return structuredClone(state.irMeasure.view);
+}
+
+export async function mockStartIrMeasure(
+  baseUrl: string,
+  payload: IrMeasureRequest,
+): Promise<IrMeasureView> {
+  const state = getOrCreateMockDevice(baseUrl);
+  mockAdvanceIrMeasure(state);
+  if (state.calibrationMode !== "off") {
+    throw new HttpApiError({
+      status: 409,
+      code: "INVALID_STATE",
+      message: "exit calibration mode before measuring internal resistance",
+      retryable: false,
+      details: null,
+    });
+  }
+
+  const config: IrMeasureConfig = { ...IR_MEASURE_DEFAULT, ...payload };
+  const preset = mockGetActivePreset(state);
+  const maxIMa = Math.max(0, preset.max_i_ma_total);
+  if (config.low_ma < 0 || config.low_ma > maxIMa) {
+    throw mockIrLimitViolation("low_ma", maxIMa, preset.max_p_mw);
+  }
+  if (config.high_ma > maxIMa || config.high_ma - config.low_ma < 100) {
+    throw mockIrLimitViolation("high_ma", maxIMa, preset.max_p_mw);
+  }
+    throw mockIrLimitViolation("high_ma", maxIMa, preset.max_p_mw);
+  }
+  if (config.cycles < 1 || config.cycles > 20) {
+    throw mockIrLimitViolation("cycles", maxIMa, preset.max_p_mw);
+  }
+  if (config.settle_ms < 50 || config.settle_ms > 5_000) {
+    throw mockIrLimitViolation("settle_ms", maxIMa, preset.max_p_mw);
+  }
+  if (config.sample_ms < 100 || config.sample_ms > 5_000) {
+    throw mockIrLimitViolation("sample_ms", maxIMa, preset.max_p_mw);
+  }
+  if (
+    preset.max_p_mw > 0 &&
+    (state.cc.v_main_mv * config.high_ma) / 1000 > preset.max_p_mw
+  ) {
+    throw mockIrLimitViolation("high_ma", maxIMa, preset.max_p_mw);
+  }
+
+  mockRequireControlReady(state);
+  if (state.irMeasure.view.state === "running") {
+    throw new HttpApiError({
+      status: 409,
+      code: "CONFLICT",
+      message: "an IR measurement is already running",
+      retryable: true,
+      details: null,
+    });
+  }
+
+  state.irMeasure = {
+    view: { state: "running", config, cycle: 0, result: null, error: null },
+    startedAtMs: Date.now(),
+  };
+  return structuredClone(state.irMeasure.view);
+}
+
+export async function mockCancelIrMeasure(
+  baseUrl: string,
+): Promise<IrMeasureView> {
+  const state = getOrCreateMockDevice(baseUrl);
+  mockAdvanceIrMeasure(state);
+  if (state.irMeasure.view.state === "running") {
+    state.irMeasure = {
+      view: {
+        ...state.irMeasure.view,
+        state: "failed",
+        cycle: null,
+        error: { code: "CANCELLED", message: "cancelled" },
+      },
+      startedAtMs: null,
+    };
+  }
+  return structuredClone(state.irMeasure.view);
+}
@@ -3,7 +3,10 @@ import { exportDiagnostics } from "./client.ts";
 import {
   type DevdStatusPayload,
   getOrCreateMockDevice,
+  mockCancelIrMeasure,
+  mockGetIrMeasure,
   mockGetStatus,
+  mockStartIrMeasure,
   mockUpdateCc,
   mockUpdateControl,
   normalizeDevdIdentity,
@@ -399,3 +402,39 @@ test("mock diagnostics export matches firmware-facing diagnostics contract", asy
     },
   });
 });
+
+test("mock IR measurement validates limits, rejects overlap and cancels", async () => {
+  const baseUrl = "mock://ir-measure";
+  const device = getOrCreateMockDevice(baseUrl);
+  const maxIMa =
+    device.presets.find(
+      (preset) => preset.preset_id === device.active_preset_id,
+    )?.max_i_ma_total ?? 0;
+
+  expect((await mockGetIrMeasure(baseUrl)).state).toBe("idle");
+  await expect(
+    mockStartIrMeasure(baseUrl, { high_ma: maxIMa + 1 }),
+  ).rejects.toMatchObject({
+    status: 422,
+    code: "LIMIT_VIOLATION",
+    details: { field: "high_ma" },
+  });
+
+  const started = await mockStartIrMeasure(baseUrl, {
+    high_ma: 500,
+    cycles: 2,
+  });
+  expect(started).toMatchObject({
+    state: "running",
+    cycle: 0,
+    config: { low_ma: 100, high_ma: 500, cycles: 2 },
+  });
+  await expect(mockStartIrMeasure(baseUrl, {})).rejects.toMatchObject({
+    status: 409,
+    code: "CONFLICT",
+  });
+
+  const cancelled = await mockCancelIrMeasure(baseUrl);
+  expect(cancelled.state).toBe("failed");
+  expect(cancelled.error?.code).toBe("CANCELLED");
+});
@@ -1,10 +1,13 @@
 import type {
+  CalibrationCurveKind,
   CalibrationModeRequest,
   CalibrationProfileWire,
+  CalibrationVerifyResult,
   CcControlView,
   FastStatusJson,
   FastStatusView,
   Identity,
+  IrMeasureView,
   PdView,
   Preset,
   PresetId,
@@ -26,12 +29,23 @@ export interface MockDeviceState {
   wifiPsk: string;
   simulation: MockSimulationState;
   wifiConnectPollsRemaining: number;
+  irMeasure: MockIrMeasureState;
+}
+
+export interface MockIrMeasureState {
+  view: IrMeasureView;
+  // Wall clock of the POST; the mock run advances on read like the status sim.
+  startedAtMs: number | null;
 }
 
 export interface MockCalibrationState {
   factory: CalibrationProfileWire;
   ram: CalibrationProfileWire;
   eeprom: CalibrationProfileWire | null;
+  // `eeprom` mirrors the active slot; inactive slots keep their own copy.
+  activeSlot: number;
+  slots: { name: string; eeprom: CalibrationProfileWire | null }[];
+  verifyResults: Record<CalibrationCurveKind, CalibrationVerifyResult | null>;
 }
 
 export interface MockSimulationProfile {
@@ -80,13 +94,15 @@ export interface DevdStatusPayload {
   analog_state?: FastStatusView["analog_state"];
   fault_flags_decoded?: FastStatusView["fault_flags_decoded"];
   state_flags_decoded?: FastStatusView["state_flags_decoded"];
+  sense_warnings_decoded?: FastStatusView["sense_warnings_decoded"];
+  channel_warnings_decoded?: FastStatusView["channel_warnings_decoded"];
   control?: DevdControlCompatPayload;
 }
 
 function createInitialCalibrationProfileWire(): CalibrationProfileWire {
   const active = {
     source: "factory-default" as const,
-    fmt_version: 3,
+    fmt_version: 4,
     hw_rev: 1,
   };
 
@@ -540,6 +556,7 @@ export function normalizeDevdIdentity(
       presets_supported: payload.capabilities?.presets_supported ?? false,
       preset_count: payload.capabilities?.preset_count,
       api_version: payload.capabilities?.api_version ?? "devd-usb",
+      auth_required: payload.capabilities?.auth_required,
     },
   };
 }
@@ -602,6 +619,22 @@ export function normalizeDevdStatus(
       payload.status.raw_v_rmt_100uv ?? previousRaw?.raw_v_rmt_100uv,
     raw_cur_100uv: payload.status.raw_cur_100uv ?? previousRaw?.raw_cur_100uv,
     raw_dac_code: payload.status.raw_dac_code ?? previousRaw?.raw_dac_code,
+    // Omitted means "clear", so these never carry over from `previous`.
+    sense_warnings: payload.status.sense_warnings,
+    lead_resistance_mohm: payload.status.lead_resistance_mohm,
+    channel_warnings: payload.status.channel_warnings,
+    dac_code_ch1: payload.status.dac_code_ch1 ?? previousRaw?.dac_code_ch1,
+    dac_code_ch2: payload.status.dac_code_ch2 ?? previousRaw?.dac_code_ch2,
+    fet_temp_ch1_mc:
+      payload.status.fet_temp_ch1_mc ?? previousRaw?.fet_temp_ch1_mc,
+    fet_temp_ch2_mc:
+      payload.status.fet_temp_ch2_mc ?? previousRaw?.fet_temp_ch2_mc,
+    share_policy: payload.status.share_policy ?? previousRaw?.share_policy,
+    charge_uah: payload.status.charge_uah ?? previousRaw?.charge_uah,
+    energy_uwh: payload.status.energy_uwh ?? previousRaw?.energy_uwh,
+    counter_elapsed_ms:
+      payload.status.counter_elapsed_ms ?? previousRaw?.counter_elapsed_ms,
+    counter_on_ms: payload.status.counter_on_ms ?? previousRaw?.counter_on_ms,
   } as FastStatusJson;
   return {
     raw,
@@ -612,6 +645,8 @@ export function normalizeDevdStatus(
       payload.fault_flags_decoded ?? previous?.fault_flags_decoded ?? [],
     state_flags_decoded:
       payload.state_flags_decoded ?? previous?.state_flags_decoded ?? [],
+    sense_warnings_decoded: payload.sense_warnings_decoded,
+    channel_warnings_decoded: payload.channel_warnings_decoded,
   };
 }
 
@@ -634,6 +669,19 @@ export function getOrCreateMockDevice(baseUrl: string): MockDeviceState {
     factory: structuredClone(factoryProfile),
     ram: structuredClone(factoryProfile),
     eeprom: null,
+    activeSlot: 0,
+    slots: [
+      { name: "default", eeprom: null },
+      { name: "", eeprom: null },
+      { name: "", eeprom: null },
+      { name: "", eeprom: null },
+    ],
+    verifyResults: {
+      current_ch1: null,
+      current_ch2: null,
+      v_local: null,
+      v_remote: null,
+    },
   };
   const wifi: WifiStatus = {
     ssid: "LoadLynx Lab",
@@ -681,6 +729,16 @@ export function getOrCreateMockDevice(baseUrl: string): MockDeviceState {
       lastWallClockMs: Date.now(),
     },
     wifiConnectPollsRemaining: 0,
+    irMeasure: {
+      view: {
+        state: "idle",
+        config: null,
+        cycle: null,
+        result: null,
+        error: null,
+      },
+      startedAtMs: null,
+    },
   };
 
   if (baseUrl.toLowerCase().includes("calibration-output-applied")) {
@@ -3,18 +3,28 @@ export {
   mockPostCalibrationApply,
   mockPostCalibrationCommit,
   mockPostCalibrationMode,
+  mockGetCalibrationSlots,
+  mockGetCalibrationVerify,
   mockPostCalibrationReset,
+  mockPostCalibrationSlotActivate,
+  mockPostCalibrationSlotDelete,
+  mockPostCalibrationSlotRename,
+  mockPostCalibrationVerify,
 } from "./client-mock-calibration.ts";
 export {
   mockApplyPreset,
+  mockCancelIrMeasure,
   mockDebugSetUvLatched,
   mockGetCc,
   mockGetControl,
   mockGetIdentity,
+  mockGetIrMeasure,
   mockGetPresets,
   mockGetStatus,
   mockRequireControlReady,
+  mockResetCounters,
   mockSoftReset,
+  mockStartIrMeasure,
   mockUpdateCc,
   mockUpdateControl,
   mockUpdatePreset,
@@ -0,0 +1,135 @@
+import { afterEach, expect, test, vi } from "vitest";
+
+import { HttpApiError } from "./client-core.ts";
+import { DeviceSocket, makeDeviceSocketUrl } from "./client-ws.ts";
+
+class FakeWebSocket {
+  static readonly OPEN = 1;
+  static instances: FakeWebSocket[] = [];
+
+  readonly url: string;
+  readyState = 0;
+  sent: string[] = [];
+  private listeners = new Map<string, Array<(event: unknown) => void>>();
+
+  constructor(url: string) {
+    this.url = url;
+    FakeWebSocket.instances.push(this);
+  }
+
+  addEventListener(type: string, listener: (event: unknown) => void) {
+    this.listeners.set(type, [...(this.listeners.get(type) ?? []), listener]);
+  }
+
+  send(data: string) {
+    this.sent.push(data);
+  }
+
+  close() {
+    this.readyState = 3;
+  }
+
+  emit(type: string, event: unknown = {}) {
+    if (type === "open") {
+      this.readyState = FakeWebSocket.OPEN;
+    }
+    for (const listener of this.listeners.get(type) ?? []) {
+      listener(event);
+    }
+  }
+}
+
+afterEach(() => {
+  vi.unstubAllGlobals();
+  FakeWebSocket.instances = [];
+});
+
+test("makeDeviceSocketUrl switches to ws:// and keeps base query params", () => {
+  expect(makeDeviceSocketUrl("http://192.168.1.50/?token=abc")).toBe(
+    "ws://192.168.1.50/api/v1/ws?token=abc",
+  );
+  expect(makeDeviceSocketUrl("https://device.local")).toBe(
+    "wss://device.local/api/v1/ws",
+  );
+});
+
+test("DeviceSocket matches responses by id and pushes status frames", async () => {
+  vi.stubGlobal("WebSocket", FakeWebSocket);
+  const socket = new DeviceSocket("http://192.168.1.50");
+  const statuses: number[] = [];
+  socket.onStatus((payload) => statuses.push(payload.status.uptime_ms));
+
+  socket.retain();
+  const ws = FakeWebSocket.instances[0];
+  expect(ws?.url).toBe("ws://192.168.1.50/api/v1/ws");
+  ws?.emit("open");
+  expect(socket.state).toBe("open");
+
+  const okRequest = socket.request<{ target_i_ma: number }>("cc.set", {
+    target_i_ma: 500,
+  });
+  const failedRequest = socket.request("presets.apply", { preset_id: 9 });
+  expect(ws?.sent.map((raw) => JSON.parse(raw))).toEqual([
+    { id: 1, op: "cc.set", data: { target_i_ma: 500 } },
+    { id: 2, op: "presets.apply", data: { preset_id: 9 } },
+  ]);
+
+  ws?.emit("message", {
+    data: JSON.stringify({
+      type: "response",
+      id: 2,
+      ok: false,
+      status: 400,
+      data: {
+        error: {
+          code: "INVALID_REQUEST",
+          message: "preset_id out of range (1..=5)",
+          retryable: false,
+        },
+      },
+    }),
+  });
+  ws?.emit("message", {
+    data: JSON.stringify({
+      type: "status",
+      dropped: 0,
+      data: { status: { uptime_ms: 42 }, link_up: true },
+    }),
+  });
+  ws?.emit("message", {
+    data: JSON.stringify({
+      type: "response",
+      id: 1,
+      ok: true,
+      status: 200,
+      data: { target_i_ma: 500 },
+    }),
+  });
+
+  await expect(okRequest).resolves.toEqual({ target_i_ma: 500 });
+  const error = await failedRequest.catch((err: unknown) => err);
+  expect(error).toBeInstanceOf(HttpApiError);
+  expect((error as HttpApiError).status).toBe(400);
+  expect((error as HttpApiError).code).toBe("INVALID_REQUEST");
+  expect(statuses).toEqual([42]);
+
+  socket.release();
+  expect(socket.state).toBe("unavailable");
+});
+
+test("DeviceSocket rejects pending requests when the connection drops", async () => {
+  vi.stubGlobal("WebSocket", FakeWebSocket);
+  const socket = new DeviceSocket("http://192.168.1.51");
+  socket.retain();
+  const ws = FakeWebSocket.instances[0];
+  ws?.emit("open");
+
+  const pending = socket.request("control.get");
+  ws?.emit("close");
+
+  await expect(pending).rejects.toMatchObject({ code: "WS_CLOSED" });
+  await expect(socket.request("control.get")).rejects.toMatchObject({
+    code: "WS_NOT_OPEN",
+  });
+  socket.release();
+});
@@ -0,0 +1,284 @@
+import {
+  HttpApiError,
+  isDevdCompatBaseUrl,
+  isMockBaseUrl,
+  isStorybookRuntime,
+  makeApiUrl,
+  mapHttpError,
+  withApiToken,
+} from "./client-core.ts";
+import type { FastStatusResponse } from "./types.ts";
+
+// One multiplexed WebSocket per device (`GET /api/v1/ws`, protocol
+// `loadlynx.ws.v1`) carrying status push, control requests and change events.
+// The firmware accepts a single session; when the upgrade fails the socket is
+// marked unavailable for a while and callers fall back to SSE + HTTP.
+
+export type DeviceSocketEvent = "control" | "pd" | "faults";
+
+export type DeviceSocketState = "connecting" | "open" | "unavailable";
+
+interface PendingRequest {
+  resolve: (value: unknown) => void;
+  reject: (error: HttpApiError) => void;
+  timeout: ReturnType<typeof globalThis.setTimeout>;
+}
+
+type ServerMessage =
+  | { type: "hello"; protocol: string; max_message_bytes: number }
+  | { type: "status"; dropped: number; data: FastStatusResponse }
+  | {
+      type: "event";
+      event: DeviceSocketEvent;
+      wall_time?: string | null;
+      data: unknown;
+    }
+  | {
+      type: "response";
+      id: number | null;
+      ok: boolean;
+      status: number;
+      data: unknown;
+    };
+
+const REQUEST_TIMEOUT_MS = 10_000;
+// Requests the client keeps outstanding; further calls wait for a slot so a
+// burst of UI actions cannot pile up behind the device's sequential handler.
+const MAX_IN_FLIGHT = 4;
+const RETRY_AFTER_UNAVAILABLE_MS = 15_000;
+const PING_INTERVAL_MS = 15_000;
+
+function makeSocketError(code: string, message: string): HttpApiError {
+  return new HttpApiError({ status: 0, code, message, retryable: true });
+}
+
+export function makeDeviceSocketUrl(baseUrl: string): string {
+  const url = withApiToken(makeApiUrl(baseUrl, "/api/v1/ws"), baseUrl);
+  url.protocol = url.protocol === "https:" ? "wss:" : "ws:";
+  return url.toString();
+}
+
+export function supportsDeviceSocket(baseUrl: string): boolean {
+  return (
+    typeof WebSocket !== "undefined" &&
+    Boolean(baseUrl) &&
+    !isMockBaseUrl(baseUrl) &&
+    !isDevdCompatBaseUrl(baseUrl) &&
+    !isStorybookRuntime()
+  );
+}
+
+export class DeviceSocket {
+  readonly baseUrl: string;
+  state: DeviceSocketState = "connecting";
+
+  private socket: WebSocket | null = null;
+  private refCount = 0;
+  private nextId = 1;
+  private pending = new Map<number, PendingRequest>();
+  private waiters: Array<() => void> = [];
+  private statusListeners = new Set<(payload: FastStatusResponse) => void>();
+  private eventListeners = new Set<
+    (event: DeviceSocketEvent, data: unknown) => void
+  >();
+  private stateListeners = new Set<(state: DeviceSocketState) => void>();
+  private unavailableUntil = 0;
+  private pingTimer: ReturnType<typeof globalThis.setInterval> | null = null;
+
+  constructor(baseUrl: string) {
+    this.baseUrl = baseUrl;
+  }
+
+  retain(): void {
+    this.refCount += 1;
+    if (this.socket === null && Date.now() >= this.unavailableUntil) {
+      this.open();
+    }
+  }
+
+  release(): void {
+    this.refCount = Math.max(0, this.refCount - 1);
+    if (this.refCount === 0) {
+      this.shutdown("released");
+    }
+  }
+
+  onStatus(listener: (payload: FastStatusResponse) => void): () => void {
+    this.statusListeners.add(listener);
+    return () => this.statusListeners.delete(listener);
+  }
+
+  onEvent(
+    listener: (event: DeviceSocketEvent, data: unknown) => void,
+  ): () => void {
+    this.eventListeners.add(listener);
+    return () => this.eventListeners.delete(listener);
+  }
+
+  onStateChange(listener: (state: DeviceSocketState) => void): () => void {
+    this.stateListeners.add(listener);
+    return () => this.stateListeners.delete(listener);
+  }
+
+  async request<T>(op: string, data?: unknown): Promise<T> {
+    while (this.pending.size >= MAX_IN_FLIGHT && this.state === "open") {
+      await new Promise<void>((resolve) => this.waiters.push(resolve));
+    }
+    const socket = this.socket;
+    if (this.state !== "open" || socket === null) {
+      throw makeSocketError("WS_NOT_OPEN", "device socket is not open");
+    }
+    const id = this.nextId++;
+    const message = JSON.stringify(
+      data === undefined ? { id, op } : { id, op, data },
+    );
+    return new Promise<T>((resolve, reject) => {
+      const timeout = globalThis.setTimeout(() => {
+        this.settle(id);
+        reject(
+          makeSocketError(
+            "WS_TIMEOUT",
+            `${op} did not complete within ${REQUEST_TIMEOUT_MS}ms`,
+          ),
+        );
+      }, REQUEST_TIMEOUT_MS);
+      this.pending.set(id, {
+        resolve: resolve as (value: unknown) => void,
+        reject,
+        timeout,
+      });
+      socket.send(message);
+    });
+  }
+
+  private open(): void {
+    this.setState("connecting");
+    const socket = new WebSocket(makeDeviceSocketUrl(this.baseUrl));
+    this.socket = socket;
+    socket.addEventListener("open", () => {
+      if (this.socket !== socket) {
+        return;
+      }
+      this.setState("open");
+      this.pingTimer = globalThis.setInterval(() => {
+        void this.request("ping").catch(() => undefined);
+      }, PING_INTERVAL_MS);
+    });
+    socket.addEventListener("message", (event: MessageEvent) => {
+      if (this.socket === socket && typeof event.data === "string") {
+        this.handleMessage(event.data);
+      }
+    });
+    socket.addEventListener("close", () => {
+      if (this.socket !== socket) {
+        return;
+      }
+      // A refused upgrade (busy session, old firmware) and a dropped link
+      // both back off before the next attempt; SSE + HTTP cover the gap.
+      this.unavailableUntil = Date.now() + RETRY_AFTER_UNAVAILABLE_MS;
+      this.shutdown("closed");
+      if (this.refCount > 0) {
+        globalThis.setTimeout(() => {
+          if (this.refCount > 0 && this.socket === null) {
+            this.open();
+          }
+        }, RETRY_AFTER_UNAVAILABLE_MS);
+      }
+    });
+  }
+
+  private shutdown(reason: string): void {
+    const socket = this.socket;
+    this.socket = null;
+    if (this.pingTimer !== null) {
+      globalThis.clearInterval(this.pingTimer);
+      this.pingTimer = null;
+    }
+    for (const id of [...this.pending.keys()]) {
+      this.settle(id)?.reject(
+        makeSocketError("WS_CLOSED", `device socket ${reason}`),
+      );
+    }
+    for (const wake of this.waiters.splice(0)) {
+      wake();
+    }
+    if (socket !== null && socket.readyState <= WebSocket.OPEN) {
+      socket.close(1000);
+    }
+    this.setState("unavailable");
+  }
+
+  private settle(id: number): PendingRequest | undefined {
+    const request = this.pending.get(id);
+    if (request === undefined) {
+      return undefined;
+    }
+    this.pending.delete(id);
+    globalThis.clearTimeout(request.timeout);
+    this.waiters.shift()?.();
+    return request;
+  }
+
+  private setState(state: DeviceSocketState): void {
+    if (this.state === state) {
+      return;
+    }
+    this.state = state;
+    for (const listener of this.stateListeners) {
+      listener(state);
+    }
+  }
+
+  private handleMessage(raw: string): void {
+    let message: ServerMessage;
+    try {
+      message = JSON.parse(raw) as ServerMessage;
+    } catch {
+      return;
+    }
+    switch (message.type) {
+      case "status":
+        for (const listener of this.statusListeners) {
+          listener(message.data);
+        }
+        break;
+      case "event":
+        for (const listener of this.eventListeners) {
+          listener(message.event, message.data);
+        }
+        break;
+      case "response": {
+        if (message.id === null) {
+          break;
+        }
+        const request = this.settle(message.id);
+        if (message.ok) {
+          request?.resolve(message.data);
+        } else {
+          request?.reject(mapHttpError(message.status, message.data));
+        }
+        break;
+      }
+      default:
+        break;
+    }
+  }
+}
+
+const sockets = new Map<string, DeviceSocket>();
+
+// Shared socket for `baseUrl`; callers pair `retain()` with `release()`.
+export function getDeviceSocket(baseUrl: string): DeviceSocket {
+  let socket = sockets.get(baseUrl);
+  if (socket === undefined) {
+    socket = new DeviceSocket(baseUrl);
+    sockets.set(baseUrl, socket);
+  }
+  return socket;
+}
+
+// The shared socket when it is open, so one-shot calls can skip HTTP.
+export function getOpenDeviceSocket(baseUrl: string): DeviceSocket | null {
+  const socket = sockets.get(baseUrl);
+  return socket !== undefined && socket.state === "open" ? socket : null;
+}
@@ -1,3 +1,10 @@
+export {
+  getApiTokens,
+  getAuthStatus,
+  pairApiToken,
+  revokeApiToken,
+  startApiPairing,
+} from "./client-auth.ts";
 export {
   BACKUP_SECTION_KEYS,
   deleteWifiConfig,
@@ -15,6 +22,8 @@ export {
 } from "./client-backup.ts";
 export {
   getCalibrationProfile,
+  getCalibrationSlots,
+  getCalibrationVerify,
   mapCalibrationProfileUiToWire,
   mapCalibrationProfileWireToUi,
   mapCalibrationWriteRequestToWire,
@@ -22,6 +31,10 @@ export {
   postCalibrationCommit,
   postCalibrationMode,
   postCalibrationReset,
+  postCalibrationSlotActivate,
+  postCalibrationSlotDelete,
+  postCalibrationSlotRename,
+  postCalibrationVerify,
 } from "./client-calibration.ts";
 export {
   __testClearDeviceQueues,
@@ -30,25 +43,33 @@ export {
   ENABLE_APP_DEVTOOLS,
   ENABLE_MOCK,
   ENABLE_MOCK_DEVTOOLS,
+  getApiToken,
   HttpApiError,
   isDevdCompatBaseUrl,
   isHttpApiError,
   isMockBaseUrl,
+  setApiToken,
   supportsBackupWifiCredentials,
 } from "./client-core.ts";
 export {
   __debugSetUvLatched,
   applyPreset,
+  cancelIrMeasure,
   getCc,
   getControl,
   getIdentity,
+  getIrMeasure,
   getPd,
   getPresets,
   getStatus,
+  postCountersReset,
   postPd,
   postSoftReset,
+  startIrMeasure,
   subscribeStatusStream,
+  toFastStatusView,
   updateCc,
   updateControl,
   updatePreset,
 } from "./client-device.ts";
+export { getDeviceSocket, supportsDeviceSocket } from "./client-ws.ts";
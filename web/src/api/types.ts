@@ -12,7 +12,15 @@ export interface NetworkInfo {
 export interface WifiStatus {
   ssid: string | null;
   source: "factory" | "user" | "none";
-  state: "idle" | "configured" | "connecting" | "connected" | "error";
+  // Stored user networks; absent on firmware without the network list.
+  network_count?: number;
+  state:
+    | "idle"
+    | "configured"
+    | "connecting"
+    | "connected"
+    | "provisioning"
+    | "error";
   ip: string | null;
   last_error: string | null;
 }
@@ -21,6 +29,30 @@ export interface WifiCredentials {
   ssid: string;
   psk: string;
   source: "factory" | "user";
+  // Every stored network in priority order (top entry is `ssid`/`psk`).
+  networks?: Array<{ ssid: string; psk: string }>;
+}
+
+export interface WifiNetworkList {
+  max_networks: number;
+  active_ssid: string | null;
+  networks: Array<{ ssid: string; priority: number; active: boolean }>;
+}
+
+// `GET /api/v1/time`; wall_time fields are RFC3339 UTC or null until set.
+export interface TimeStatus {
+  synced: boolean;
+  source: "sntp" | "host" | null;
+  unix_ms: number | null;
+  wall_time: string | null;
+  uptime_ms: number;
+  last_sync_uptime_ms: number | null;
+  ntp: {
+    server: string;
+    custom: boolean;
+    state: "waiting_for_network" | "syncing" | "synced" | "error";
+    last_error: string | null;
+  };
 }
 
 export interface WifiSetRequest {
@@ -46,6 +78,8 @@ export interface DiagnosticsExport {
     psk: "<redacted>";
   };
   link_up: boolean;
+  wall_time?: string | null;
+  uptime_ms?: number;
   calibration_persistence: CalibrationPersistence;
   last_status: DiagnosticsLastStatus | null;
 }
@@ -57,6 +91,7 @@ export interface DeviceCapabilities {
   presets_supported?: boolean;
   preset_count?: number; // 固定为 5（见 docs/interfaces/network-http-api.md）
   api_version: string;
+  auth_required?: boolean; // HTTP API needs a paired token (see ApiRole)
 }
 
 export interface FirmwareIdentity {
@@ -120,7 +155,19 @@ export type StateFlag =
   | "ENABLED"
   | "UV_LATCHED"
   | "POWER_LIMITED"
-  | "CURRENT_LIMITED";
+  | "CURRENT_LIMITED"
+  | "SOA_LIMITED"
+  | "LINK_SAFED";
+
+export type SenseWarning =
+  | "REMOTE_REVERSED"
+  | "REMOTE_OPEN"
+  | "HIGH_LEAD_DROP"
+  | "OSCILLATION";
+
+export type ChannelWarning = "IMBALANCE" | "CH1_STRAY" | "CH2_STRAY";
+
+export type SharePolicy = "auto" | "dual" | "ch1_only" | "ch2_only";
 
 export interface FastStatusJson {
   uptime_ms: number;
@@ -145,6 +192,23 @@ export interface FastStatusJson {
   raw_v_rmt_100uv?: number;
   raw_cur_100uv?: number;
   raw_dac_code?: number;
+  // Remote-sense diagnostics (omitted when clear / unknown)
+  sense_warnings?: number;
+  lead_resistance_mohm?: number;
+  // CH1/CH2 sharing (DAC codes omitted during calibration; FET temps are
+  // estimates from the sink core NTC plus per-channel dissipation)
+  dac_code_ch1?: number;
+  dac_code_ch2?: number;
+  fet_temp_ch1_mc?: number;
+  fet_temp_ch2_mc?: number;
+  share_policy?: SharePolicy;
+  channel_warnings?: number;
+  // Charge / energy counters since the last reset (analog boot or
+  // POST /api/v1/counters/reset); omitted by older analog firmware
+  charge_uah?: number;
+  energy_uwh?: number;
+  counter_elapsed_ms?: number;
+  counter_on_ms?: number;
 }
 
 export type CalibrationCurveKind =
@@ -163,6 +227,18 @@ export interface CalibrationPersistence {
   status: string;
 }
 
+// Calibration fmt v4 per-curve temperature compensation (all-zero = disabled).
+export interface CalibrationTempComp {
+  sensor: "sink_core" | "mcu";
+  t_ref_mc: number;
+  gain_ppm_per_c: number;
+  offset_micro_per_c: number;
+}
+
+export type CalibrationTempCompMap = Partial<
+  Record<CalibrationCurveKind, CalibrationTempComp>
+>;
+
 export interface CalibrationPointVoltage {
   raw: number;
   mv: number;
@@ -181,6 +257,7 @@ export interface CalibrationProfile {
   v_remote_points: CalibrationPointVoltage[];
   current_ch1_points: CalibrationPointCurrent[];
   current_ch2_points: CalibrationPointCurrent[];
+  temp_comp?: CalibrationTempCompMap;
 }
 
 export type CalibrationWriteRequest =
@@ -201,6 +278,61 @@ export interface CalibrationModeRequest {
   kind: "off" | "voltage" | "current_ch1" | "current_ch2";
 }
 
+// Named EEPROM profile slots; `active` is the slot commit/reset write to.
+export interface CalibrationSlot {
+  slot: number;
+  name: string;
+  used: boolean;
+  active: boolean;
+}
+
+export interface CalibrationSlotsResponse {
+  active: number;
+  slots: CalibrationSlot[];
+}
+
+// Calibration verification ("cal check"): reference readings are compared
+// against the active profile without changing it.
+export type CalibrationVerifyPointWireCompact =
+  | [raw_100uv: number, reference: number]
+  | [raw_100uv: number, reference: number, tolerance: number];
+
+export interface CalibrationVerifyRequest {
+  kind: CalibrationCurveKind;
+  points: CalibrationVerifyPointWireCompact[];
+  // Default band per point: tolerance_abs + |reference| * tolerance_ppm / 1e6.
+  tolerance_abs?: number;
+  tolerance_ppm?: number;
+  ts_unix_ms?: number;
+}
+
+export interface CalibrationVerifyPoint {
+  raw_100uv: number;
+  reference: number;
+  reading: number;
+  error: number;
+  tolerance: number;
+  passed: boolean;
+}
+
+export interface CalibrationVerifyResult {
+  kind: CalibrationCurveKind;
+  ts_unix_ms: number | null;
+  slot: number;
+  profile_crc32: number;
+  // Taken against the profile that is active now.
+  current: boolean;
+  passed: boolean;
+  worst_error: number | null;
+  points: CalibrationVerifyPoint[];
+  // Only on POST: whether the record reached EEPROM.
+  stored?: boolean;
+}
+
+export interface CalibrationVerifyResultsResponse {
+  results: Record<CalibrationCurveKind, CalibrationVerifyResult | null>;
+}
+
 // Calibration wire protocol types (ESP32-S3 firmware net_http)
 
 export interface CalibrationPointVoltageWire {
@@ -233,6 +365,7 @@ export interface CalibrationProfileWire {
   current_ch2_points: CalibrationPointCurrentWire[];
   v_local_points: CalibrationPointVoltageWire[];
   v_remote_points: CalibrationPointVoltageWire[];
+  temp_comp?: CalibrationTempCompMap;
 }
 
 export type CalibrationWriteRequestWire =
@@ -253,22 +386,36 @@ export type CalibrationWriteRequestWire =
       points: CalibrationPointCurrentWireCompact[];
     };
 
+export interface ThermalView {
+  derate_pct: number;
+  time_to_trip_ms: number | null;
+  steady_core_mc: number | null;
+  temp_trip_mc: number;
+}
+
 export interface FastStatusView {
   raw: FastStatusJson;
   link_up: boolean;
   hello_seen: boolean;
   analog_state: AnalogState;
+  thermal?: ThermalView;
   fault_flags_decoded: FaultFlag[];
   state_flags_decoded: StateFlag[];
+  sense_warnings_decoded?: SenseWarning[];
+  channel_warnings_decoded?: ChannelWarning[];
 }
 
 export interface FastStatusResponse {
   status: FastStatusJson;
   link_up: boolean;
   hello_seen: boolean;
+  wall_time?: string | null;
   analog_state: AnalogState;
+  thermal?: ThermalView;
   fault_flags_decoded: FaultFlag[];
   state_flags_decoded?: StateFlag[];
+  sense_warnings_decoded?: SenseWarning[];
+  channel_warnings_decoded?: ChannelWarning[];
 }
 
 export type CcProtectionMode = "off" | "protect" | "maintain";
@@ -364,6 +511,48 @@ export interface SoftResetResponse {
   reason: SoftResetReason;
 }
 
+export type CounterSelector = "charge" | "energy" | "time";
+
+export interface CountersResetResponse {
+  accepted: boolean;
+  mask: number;
+}
+
+// DUT internal resistance (docs/interfaces/network-http-api.md §3.16)
+
+export interface IrMeasureConfig {
+  low_ma: number;
+  high_ma: number;
+  cycles: number; // 1..=20
+  settle_ms: number;
+  sample_ms: number;
+}
+
+export type IrMeasureRequest = Partial<IrMeasureConfig>;
+
+export interface IrMeasureResult {
+  r_mohm: number;
+  r_uohm: number;
+  r_min_uohm: number;
+  r_max_uohm: number;
+  cycles: number;
+  v_low_mv: number;
+  v_high_mv: number;
+  i_low_ma: number;
+  i_high_ma: number;
+  sense: "remote" | "local";
+}
+
+export type IrMeasureState = "idle" | "running" | "done" | "failed";
+
+export interface IrMeasureView {
+  state: IrMeasureState;
+  config: IrMeasureConfig | null;
+  cycle: number | null; // 0-based, only while running
+  result: IrMeasureResult | null;
+  error: { code: string; message: string } | null;
+}
+
 // USB-PD (docs/interfaces/network-http-api.md §3.5..§3.6)
 
 export interface PdFixedPdo {
@@ -497,3 +686,34 @@ export interface BackupRestoreResult {
   restored: BackupRestoreSectionResult[];
   warnings: string[];
 }
+
+// Device HTTP API auth (`/api/v1/auth*`). Roles nest: admin ⊃ control ⊃ read.
+export type ApiRole = "read" | "control" | "admin";
+
+export interface AuthStatus {
+  role: ApiRole;
+  pairing: boolean;
+}
+
+export interface ApiPairingStart {
+  ok: true;
+  expires_in_s: number;
+}
+
+// The role is not part of the request: it is picked by tapping the pairing
+// code on the device screen.
+export interface ApiPairRequest {
+  code: string;
+  label?: string;
+}
+
+export interface IssuedApiToken {
+  token: string;
+  role: ApiRole;
+  label: string;
+}
+
+export interface ApiTokenList {
+  tokens: Array<{ label: string; role: ApiRole }>;
+  max_tokens: number;
+}
@@ -10,6 +10,9 @@ export const DEVICE_QUERY_PARTS = {
   backupPd: ["backup-pd"],
   calibrationProfile: ["calibration", "profile"],
   calibrationStatusFallback: ["status", "calibration-fallback"],
+  irMeasure: ["measure", "ir"],
+  auth: ["auth"],
+  authTokens: ["auth", "tokens"],
 } as const;
 
 export type DeviceQueryParts =
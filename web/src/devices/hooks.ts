@@ -1,7 +1,9 @@
 import { useMutation, useQuery, useQueryClient } from "@tanstack/react-query";
+import { useEffect, useState } from "react";
 import {
   ENABLE_MOCK,
   getControl,
+  getDeviceSocket,
   getIdentity,
   getPd,
   getPresets,
@@ -10,10 +12,18 @@ import {
   HttpApiError,
   isDevdCompatBaseUrl,
   isMockBaseUrl,
+  supportsDeviceSocket,
+  toFastStatusView,
 } from "../api/client.ts";
-import type { Identity, WifiStatus } from "../api/types.ts";
+import type {
+  ControlView,
+  Identity,
+  PdView,
+  WifiStatus,
+} from "../api/types.ts";
 import { resolveDemoMode } from "../lib/demo-mode.ts";
 import { isUsbSerialUnavailableError } from "../lib/http-error.ts";
+import { setDeviceQueryData } from "./device-query-cache.ts";
 import {
   DEVICE_QUERY_PARTS,
   type DeviceQueryParts,
@@ -121,6 +131,76 @@ export function useDeviceIdentity(device: StoredDevice | null | undefined) {
   return useDeviceIdentityByBaseUrl(device?.id, device?.baseUrl);
 }
 
+/**
+ * Hold the device WebSocket while `enabled` and mirror its pushes into the
+ * query cache: `control` / `pd` events always, status frames when
+ * `options.status` is set. Returns whether the socket is open, so callers can
+ * stop polling the same resources over HTTP.
+ */
+export function useDeviceSocket(
+  deviceId: string | undefined,
+  baseUrl: string | undefined,
+  enabled: boolean,
+  options?: { status?: boolean },
+): boolean {
+  const queryClient = useQueryClient();
+  const [open, setOpen] = useState(false);
+  const mirrorStatus = options?.status ?? false;
+
+  useEffect(() => {
+    if (!enabled || !baseUrl || !supportsDeviceSocket(baseUrl)) {
+      setOpen(false);
+      return undefined;
+    }
+    const socket = getDeviceSocket(baseUrl);
+    const offState = socket.onStateChange((state) => {
+      setOpen(state === "open");
+    });
+    const offEvent = socket.onEvent((event, data) => {
+      if (event === "control") {
+        setDeviceQueryData<ControlView>(
+          queryClient,
+          deviceId,
+          baseUrl,
+          DEVICE_QUERY_PARTS.control,
+          data as ControlView,
+        );
+      } else if (event === "pd") {
+        setDeviceQueryData<PdView>(
+          queryClient,
+          deviceId,
+          baseUrl,
+          DEVICE_QUERY_PARTS.pd,
+          data as PdView,
+        );
+      }
+    });
+    const offStatus = mirrorStatus
+      ? socket.onStatus((payload) => {
+          setDeviceQueryData(
+            queryClient,
+            deviceId,
+            baseUrl,
+            DEVICE_QUERY_PARTS.status,
+            toFastStatusView(payload),
+          );
+        })
+      : () => undefined;
+    socket.retain();
+    setOpen(socket.state === "open");
+
+    return () => {
+      offState();
+      offEvent();
+      offStatus();
+      socket.release();
+      setOpen(false);
+    };
+  }, [enabled, baseUrl, deviceId, mirrorStatus, queryClient]);
+
+  return open;
+}
+
 export function getDevicePdQueryOptions(input: {
   deviceId: string | undefined;
   baseUrl: string | undefined;
@@ -176,6 +176,19 @@ export const resources = {
           contractFallback: "Contract: —",
           savedFallback: "Saved: —",
         },
+        irMeasure: {
+          title: "DUT 内阻",
+          start: "测量",
+          cancel: "取消",
+          hint:
+            "在两档电流间切换，按 ΔV/ΔI 计算被测源内阻；测量期间会临时开启输出。",
+          running: "测量中… 周期 {{cycle}}/{{cycles}}",
+          senseRemote:
+            "四线（远端 sense）· {{lowMa}}→{{highMa}} mA · {{cycles}} 周期",
+          senseLocal:
+            "本地 sense（含引线电阻）· {{lowMa}}→{{highMa}} mA · {{cycles}} 周期",
+          spread: "周期间 {{min}}–{{max}} mΩ",
+        },
         presets: {
           title: "预设",
           slotEditor: "槽位编辑",
@@ -616,6 +629,19 @@ export const resources = {
           contractFallback: "Contract: —",
           savedFallback: "Saved: —",
         },
+        irMeasure: {
+          title: "DUT internal resistance",
+          start: "Measure",
+          cancel: "Cancel",
+          hint:
+            "Steps between two currents and reports ΔV/ΔI; the output is switched on for the run.",
+          running: "Measuring… cycle {{cycle}}/{{cycles}}",
+          senseRemote:
+            "4-wire (remote sense) · {{lowMa}}→{{highMa}} mA · {{cycles}} cycles",
+          senseLocal:
+            "Local sense (includes leads) · {{lowMa}}→{{highMa}} mA · {{cycles}} cycles",
+          spread: "Per-cycle {{min}}–{{max}} mΩ",
+        },
         presets: {
           title: "Presets",
           slotEditor: "Slot editor",
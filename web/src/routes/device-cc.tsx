@@ -16,6 +16,7 @@ import { PageContainer } from "../components/layout/page-container.tsx";
 import { useDeviceContext } from "../layouts/device-layout.tsx";
 import { usePageVisibility } from "../lib/page-visibility.ts";
 import { AdvancedControlsPanel } from "./device-cc/advanced-controls-panel.tsx";
+import { IrMeasurePanel } from "./device-cc/ir-measure-panel.tsx";
 import { useDeviceCcState } from "./device-cc/use-device-cc-state.ts";
 import { PdControlPanel } from "./device-pd-panel.tsx";
 
@@ -595,6 +596,12 @@ export function DeviceCcRoute() {
                   </div>
                 ) : null}
 
+                <IrMeasurePanel
+                  deviceId={deviceId}
+                  baseUrl={baseUrl}
+                  disabled={view.outputToggleDisabled}
+                />
+
                 <div className="sr-only">
                   <div data-testid="control-active-preset">
                     Active preset: {view.control?.active_preset_id ?? "—"}
@@ -0,0 +1,152 @@
+import { useMutation, useQuery, useQueryClient } from "@tanstack/react-query";
+import { useTranslation } from "react-i18next";
+import {
+  cancelIrMeasure,
+  getIrMeasure,
+  type HttpApiError,
+  isHttpApiError,
+  startIrMeasure,
+} from "../../api/client.ts";
+import type { IrMeasureView } from "../../api/types.ts";
+import {
+  DEVICE_QUERY_PARTS,
+  makeDeviceQueryKey,
+} from "../../devices/device-query-key.ts";
+import { requireDeviceBaseUrl } from "../../lib/device-base-url.ts";
+
+const IR_RUNNING_REFETCH_MS = 500;
+
+export interface IrMeasurePanelProps {
+  deviceId: string;
+  baseUrl: string | undefined;
+  disabled: boolean;
+}
+
+function formatMilliohm(uohm: number): string {
+  return (uohm / 1000).toFixed(uohm < 10_000 ? 2 : 1);
+}
+
+export function IrMeasurePanel({
+  deviceId,
+  baseUrl,
+  disabled,
+}: IrMeasurePanelProps) {
+  const { t } = useTranslation();
+  const queryClient = useQueryClient();
+  const queryKey = makeDeviceQueryKey(
+    deviceId,
+    baseUrl,
+    ...DEVICE_QUERY_PARTS.irMeasure,
+  );
+
+  const irQuery = useQuery<IrMeasureView, HttpApiError>({
+    queryKey,
+    queryFn: () => getIrMeasure(requireDeviceBaseUrl(baseUrl)),
+    enabled: Boolean(baseUrl),
+    refetchInterval: (query) =>
+      query.state.data?.state === "running" ? IR_RUNNING_REFETCH_MS : false,
+  });
+
+  const startMutation = useMutation({
+    mutationFn: async () => startIrMeasure(requireDeviceBaseUrl(baseUrl)),
+    onSuccess: (view) => {
+      queryClient.setQueryData(queryKey, view);
+    },
+  });
+
+  const cancelMutation = useMutation({
+    mutationFn: async () => cancelIrMeasure(requireDeviceBaseUrl(baseUrl)),
+    onSuccess: (view) => {
+      queryClient.setQueryData(queryKey, view);
+      void queryClient.invalidateQueries({ queryKey });
+    },
+  });
+
+  const view = irQuery.data ?? null;
+  const running = view?.state === "running";
+  const result = view?.state === "done" ? view.result : null;
+  const startError = startMutation.error;
+
+  let detail: string;
+  if (running && view?.config) {
+    detail = t("dashboard.irMeasure.running", {
+      cycle: (view.cycle ?? 0) + 1,
+      cycles: view.config.cycles,
+    });
+  } else if (result) {
+    detail = t(
+      result.sense === "remote"
+        ? "dashboard.irMeasure.senseRemote"
+        : "dashboard.irMeasure.senseLocal",
+      {
+        lowMa: result.i_low_ma,
+        highMa: result.i_high_ma,
+        cycles: result.cycles,
+      },
+    );
+  } else if (view?.state === "failed" && view.error) {
+    detail = `${view.error.code} — ${view.error.message}`;
+  } else {
+    detail = t("dashboard.irMeasure.hint");
+  }
+
+  return (
+    <section
+      aria-label={t("dashboard.irMeasure.title")}
+      className="instrument-card p-5"
+    >
+      <div className="flex items-start justify-between gap-4">
+        <div>
+          <div className="instrument-label">
+            {t("dashboard.irMeasure.title")}
+          </div>
+          <div
+            className="mt-2 text-2xl font-bold tracking-tight text-slate-50"
+            data-testid="ir-measure-value"
+          >
+            {result ? `${formatMilliohm(result.r_uohm)} mΩ` : "—"}
+          </div>
+          {result ? (
+            <div className="mt-1 text-[11px] text-slate-200/46">
+              {t("dashboard.irMeasure.spread", {
+                min: formatMilliohm(result.r_min_uohm),
+                max: formatMilliohm(result.r_max_uohm),
+              })}
+            </div>
+          ) : null}
+        </div>
+        {running ? (
+          <button
+            type="button"
+            className="ll-button ll-button-sm ll-button-ghost"
+            disabled={cancelMutation.isPending}
+            onClick={() => cancelMutation.mutate()}
+          >
+            {t("dashboard.irMeasure.cancel")}
+          </button>
+        ) : (
+          <button
+            type="button"
+            className="ll-button ll-button-sm ll-button-outline"
+            disabled={disabled || startMutation.isPending || !view}
+            onClick={() => startMutation.mutate()}
+          >
+            {t("dashboard.irMeasure.start")}
+          </button>
+        )}
+      </div>
+
+      <div className="mt-3 text-[12px] text-slate-200/60">{detail}</div>
+
+      {startMutation.isError && startError ? (
+        <div className="mt-3 rounded-2xl border border-red-400/15 bg-red-500/10 px-4 py-3 text-[12px] text-red-200">
+          {isHttpApiError(startError)
+            ? `${startError.code ?? "HTTP_ERROR"} — ${startError.message}`
+            : startError instanceof Error
+              ? startError.message
+              : t("dashboard.errors.unknown")}
+        </div>
+      ) : null}
+    </section>
+  );
+}
@@ -40,6 +40,7 @@ import {
   getDevicePresetsQueryOptions,
   getDeviceStatusQueryOptions,
   useDeviceIdentityByBaseUrl,
+  useDeviceSocket,
 } from "../../devices/hooks.ts";
 import { requireDeviceBaseUrl } from "../../lib/device-base-url.ts";
 import {
@@ -318,6 +319,13 @@ export function useDeviceCcState(
   }, [clearStreamFlushTimer]);
 
   const identityQuery = useDeviceIdentityByBaseUrl(deviceId, baseUrl);
+  // Control and PD change events arrive over the device WebSocket; HTTP
+  // polling only runs while it is unavailable.
+  const deviceSocketOpen = useDeviceSocket(
+    deviceId,
+    baseUrl,
+    identityQuery.isSuccess && isPageVisible,
+  );
 
   const controlQuery = useQuery<ControlView, HttpApiError>(
     getDeviceControlQueryOptions({
@@ -669,7 +677,8 @@ export function useDeviceCcState(
       deviceId,
       baseUrl,
       enabled: Boolean(baseUrl) && identityQuery.isSuccess && !writesInFlight,
-      refetchInterval: isPageVisible ? PD_REFETCH_MS : false,
+      refetchInterval:
+        isPageVisible && !deviceSocketOpen ? PD_REFETCH_MS : false,
       retryDelay: RETRY_DELAY_MS,
     }),
   );
@@ -892,6 +901,7 @@ export function useDeviceCcState(
       : remoteVoltageV;
   const localCurrentA = statusLocalMa != null ? statusLocalMa / 1_000 : null;
   const remoteCurrentA = statusRemoteMa != null ? statusRemoteMa / 1_000 : null;
+  const leadResistanceMohm = status?.raw.lead_resistance_mohm ?? null;
   const totalCurrentA =
     statusLocalMa != null && statusRemoteMa != null
       ? (statusLocalMa + statusRemoteMa) / 1_000
@@ -1099,6 +1109,16 @@ export function useDeviceCcState(
     if (!remoteActive) {
       return { summary: "LINK_DOWN", level: "warn" } as const;
     }
+    const senseWarning = status?.sense_warnings_decoded?.[0];
+    if (senseWarning) {
+      return { summary: `SENSE_${senseWarning}`, level: "warn" } as const;
+    }
+    const channelWarning = status?.channel_warnings_decoded?.[0];
+    if (channelWarning) {
+      const summary =
+        channelWarning === "IMBALANCE" ? "CH_IMBALANCE" : channelWarning;
+      return { summary, level: "warn" } as const;
+    }
     return { summary: "OK", level: "ok" } as const;
   })();
 
@@ -1143,7 +1163,9 @@ export function useDeviceCcState(
       digits: 3,
       detail:
         remoteVoltageV != null
-          ? `Remote ${formatWithUnit(remoteVoltageV, 3, "V")}`
+          ? leadResistanceMohm != null
+            ? `Remote ${formatWithUnit(remoteVoltageV, 3, "V")} · Leads ${leadResistanceMohm} mΩ`
+            : `Remote ${formatWithUnit(remoteVoltageV, 3, "V")}`
           : null,
       emphasized: defaultChartMetric === "voltage",
     },
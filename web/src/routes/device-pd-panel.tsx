@@ -19,6 +19,7 @@ import { DEVICE_QUERY_PARTS } from "../devices/device-query-key.ts";
 import {
   getDevicePdQueryOptions,
   useDeviceIdentityByBaseUrl,
+  useDeviceSocket,
 } from "../devices/hooks.ts";
 import { requireDeviceBaseUrl } from "../lib/device-base-url.ts";
 import {
@@ -82,13 +83,19 @@ export function PdControlPanel(props: {
   const queryClient = useQueryClient();
   const isPageVisible = usePageVisibility();
   const identityQuery = useDeviceIdentityByBaseUrl(deviceId, baseUrl);
+  const deviceSocketOpen = useDeviceSocket(
+    deviceId,
+    baseUrl,
+    identityQuery.isSuccess && isPageVisible,
+  );
 
   const pdQuery = useQuery<PdView, HttpApiError>(
     getDevicePdQueryOptions({
       deviceId,
       baseUrl,
       enabled: Boolean(baseUrl) && identityQuery.isSuccess,
-      refetchInterval: isPageVisible ? PD_REFETCH_MS : false,
+      refetchInterval:
+        isPageVisible && !deviceSocketOpen ? PD_REFETCH_MS : false,
       retryDelay: RETRY_DELAY_MS,
     }),
   );
@@ -59,6 +59,7 @@ import {
   isLinkUnavailableError,
   isUsbSerialUnavailableError,
 } from "../lib/http-error.ts";
+import { ApiAccessPanel } from "./device-settings/api-access-panel.tsx";
 import { parseBackupImportText } from "./device-settings/import-backup.ts";
 
 const BACKUP_SECTION_LABELS: Record<BackupSectionKey, string> = {
@@ -1040,6 +1041,11 @@ export function DeviceSettingsRoute() {
             </div>
           </div>
 
+          {/* API Access */}
+          {baseUrl && identity?.capabilities.auth_required ? (
+            <ApiAccessPanel deviceId={deviceId} baseUrl={baseUrl} />
+          ) : null}
+
           {/* 5. Backup & Restore */}
           <div className="ll-panel bg-base-100 shadow-sm border border-base-200">
             <div className="ll-panel-body p-6">
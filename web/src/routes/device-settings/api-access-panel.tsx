@@ -0,0 +1,221 @@
+import { useMutation, useQuery, useQueryClient } from "@tanstack/react-query";
+import { useState } from "react";
+import {
+  getApiToken,
+  getApiTokens,
+  getAuthStatus,
+  isDevdCompatBaseUrl,
+  isHttpApiError,
+  pairApiToken,
+  revokeApiToken,
+  setApiToken,
+  startApiPairing,
+} from "../../api/client.ts";
+import {
+  DEVICE_QUERY_PARTS,
+  makeDeviceQueryKey,
+} from "../../devices/device-query-key.ts";
+import { formatHttpApiErrorSummary } from "../../lib/http-error.ts";
+
+const PAIRING_CODE_PATTERN = /^\d{6}$/;
+
+function formatError(error: Error | null): string | null {
+  if (!error) {
+    return null;
+  }
+  return isHttpApiError(error)
+    ? formatHttpApiErrorSummary(error)
+    : error.message;
+}
+
+// Pairs this browser with a device whose HTTP API requires tokens
+// (`capabilities.auth_required`) and lists / revokes issued tokens. Over
+// USB/devd the device trusts the cable, so only the token list is shown.
+export function ApiAccessPanel({
+  deviceId,
+  baseUrl,
+}: {
+  deviceId: string;
+  baseUrl: string;
+}) {
+  const queryClient = useQueryClient();
+  const viaDevd = isDevdCompatBaseUrl(baseUrl);
+  const [code, setCode] = useState("");
+  const [pairingOpen, setPairingOpen] = useState(false);
+
+  const authQuery = useQuery({
+    queryKey: makeDeviceQueryKey(deviceId, baseUrl, ...DEVICE_QUERY_PARTS.auth),
+    queryFn: () => getAuthStatus(baseUrl),
+    enabled: !viaDevd,
+    retry: false,
+  });
+  const canManageTokens = viaDevd || authQuery.data?.role === "admin";
+  const tokensQuery = useQuery({
+    queryKey: makeDeviceQueryKey(
+      deviceId,
+      baseUrl,
+      ...DEVICE_QUERY_PARTS.authTokens,
+    ),
+    queryFn: () => getApiTokens(baseUrl),
+    enabled: canManageTokens,
+  });
+
+  const invalidateDevice = () =>
+    queryClient.invalidateQueries({
+      queryKey: ["device", deviceId, baseUrl],
+    });
+
+  const startMutation = useMutation({
+    mutationFn: () => startApiPairing(baseUrl),
+    onSuccess: () => setPairingOpen(true),
+  });
+  const pairMutation = useMutation({
+    mutationFn: () => pairApiToken(baseUrl, { code, label: "web" }),
+    onSuccess: () => {
+      setCode("");
+      setPairingOpen(false);
+      void invalidateDevice();
+    },
+  });
+  const revokeMutation = useMutation({
+    mutationFn: (label: string) => revokeApiToken(baseUrl, label),
+    onSuccess: (tokens) => {
+      queryClient.setQueryData(
+        makeDeviceQueryKey(
+          deviceId,
+          baseUrl,
+          ...DEVICE_QUERY_PARTS.authTokens,
+        ),
+        tokens,
+      );
+    },
+  });
+
+  const forgetToken = () => {
+    setApiToken(baseUrl, null);
+    void invalidateDevice();
+  };
+
+  const hasToken = Boolean(getApiToken(baseUrl));
+  const unauthorized =
+    isHttpApiError(authQuery.error) && authQuery.error.status === 401;
+  const error = formatError(
+    startMutation.error ??
+      pairMutation.error ??
+      revokeMutation.error ??
+      (unauthorized ? null : authQuery.error) ??
+      tokensQuery.error,
+  );
+
+  return (
+    <div className="ll-panel bg-base-100 shadow-sm border border-base-200">
+      <div className="ll-panel-body p-6">
+        <h3 className="ll-panel-title text-sm uppercase tracking-wider text-base-content/50 mb-4 h-auto min-h-0">
+          API Access
+        </h3>
+        <div className="grid gap-3">
+          {viaDevd ? (
+            <p className="text-xs text-base-content/60">
+              USB/devd is trusted by the device and needs no token.
+            </p>
+          ) : (
+            <div className="grid grid-cols-2 gap-3 text-xs">
+              <span className="text-base-content/60">This browser</span>
+              <span data-testid="api-access-role">
+                {authQuery.data
+                  ? `paired (${authQuery.data.role})`
+                  : unauthorized
+                    ? "not paired"
+                    : "..."}
+              </span>
+            </div>
+          )}
+          {error ? (
+            <div className="ll-alert ll-alert-error shadow-sm text-xs">
+              <span>{error}</span>
+            </div>
+          ) : null}
+          {!viaDevd && pairingOpen ? (
+            <div className="grid gap-2">
+              <p className="text-xs text-base-content/60">
+                On the device, tap the PAIR nnnnnn line until it shows the
+                role to grant (READ, CTRL or ADMN), then enter the code.
+              </p>
+              <input
+                className="ll-input ll-input-sm w-full"
+                placeholder="Pairing code"
+                inputMode="numeric"
+                maxLength={6}
+                value={code}
+                onChange={(event) => setCode(event.target.value.trim())}
+              />
+              <button
+                type="button"
+                className="ll-button ll-button-neutral ll-button-sm"
+                disabled={
+                  !PAIRING_CODE_PATTERN.test(code) || pairMutation.isPending
+                }
+                onClick={() => pairMutation.mutate()}
+              >
+                Pair
+              </button>
+            </div>
+          ) : null}
+          {!viaDevd ? (
+            <div className="flex flex-wrap gap-2">
+              <button
+                type="button"
+                className="ll-button ll-button-outline ll-button-sm"
+                disabled={startMutation.isPending}
+                onClick={() => startMutation.mutate()}
+              >
+                {hasToken ? "Pair Again" : "Pair This Browser"}
+              </button>
+              {hasToken ? (
+                <button
+                  type="button"
+                  className="ll-button ll-button-outline ll-button-sm"
+                  onClick={forgetToken}
+                >
+                  Forget Token
+                </button>
+              ) : null}
+            </div>
+          ) : null}
+          {canManageTokens && tokensQuery.data ? (
+            <table className="ll-table ll-table-xs">
+              <tbody>
+                {tokensQuery.data.tokens.map((token) => (
+                  <tr key={token.label}>
+                    <td>
+                      <code className="bg-base-200 px-1 rounded">
+                        {token.label}
+                      </code>
+                    </td>
+                    <td className="text-base-content/60">{token.role}</td>
+                    <td className="text-right">
+                      <button
+                        type="button"
+                        className="ll-button ll-button-ghost ll-button-xs text-error"
+                        disabled={revokeMutation.isPending}
+                        onClick={() => revokeMutation.mutate(token.label)}
+                      >
+                        Revoke
+                      </button>
+                    </td>
+                  </tr>
+                ))}
+                <tr>
+                  <td colSpan={3} className="text-base-content/50">
+                    {tokensQuery.data.tokens.length} of{" "}
+                    {tokensQuery.data.max_tokens} tokens
+                  </td>
+                </tr>
+              </tbody>
+            </table>
+          ) : null}
+        </div>
+      </div>
+    </div>
+  );
+}
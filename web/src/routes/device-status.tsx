@@ -1,8 +1,8 @@
-import { useQuery } from "@tanstack/react-query";
+import { useMutation, useQuery } from "@tanstack/react-query";
 import { Link } from "@tanstack/react-router";
 import { useTranslation } from "react-i18next";
 import type { HttpApiError } from "../api/client.ts";
-import { isHttpApiError } from "../api/client.ts";
+import { isHttpApiError, postCountersReset } from "../api/client.ts";
 import { findVisibleSavedFixedPdo } from "../api/pd-display.ts";
 import type { FastStatusView, PdView } from "../api/types.ts";
 import { PageContainer } from "../components/layout/page-container.tsx";
@@ -10,6 +10,7 @@ import {
   getDevicePdQueryOptions,
   getDeviceStatusQueryOptions,
   useDeviceIdentityByBaseUrl,
+  useDeviceSocket,
 } from "../devices/hooks.ts";
 import { useDeviceContext } from "../layouts/device-layout.tsx";
 import {
@@ -29,13 +30,20 @@ export function DeviceStatusRoute() {
   const { deviceId, device, baseUrl } = useDeviceContext();
   const isPageVisible = usePageVisibility();
   const identityQuery = useDeviceIdentityByBaseUrl(deviceId, baseUrl);
+  const deviceSocketOpen = useDeviceSocket(
+    deviceId,
+    baseUrl,
+    identityQuery.isSuccess && isPageVisible,
+    { status: true },
+  );
 
   const statusQuery = useQuery<FastStatusView, HttpApiError>(
     getDeviceStatusQueryOptions({
       deviceId,
       baseUrl,
       enabled: Boolean(baseUrl) && identityQuery.isSuccess,
-      refetchInterval: isPageVisible ? FAST_STATUS_REFETCH_MS : false,
+      refetchInterval:
+        isPageVisible && !deviceSocketOpen ? FAST_STATUS_REFETCH_MS : false,
     }),
   );
 
@@ -44,7 +52,8 @@ export function DeviceStatusRoute() {
       deviceId,
       baseUrl,
       enabled: Boolean(baseUrl) && identityQuery.isSuccess,
-      refetchInterval: isPageVisible ? PD_REFETCH_MS : false,
+      refetchInterval:
+        isPageVisible && !deviceSocketOpen ? PD_REFETCH_MS : false,
       retryDelay: RETRY_DELAY_MS,
     }),
   );
@@ -113,6 +122,57 @@ export function DeviceStatusRoute() {
   const tempMcu =
     status?.raw.mcu_temp_mc != null ? status.raw.mcu_temp_mc / 1000 : null;
 
+  // CH1/CH2 sharing (FET temperatures are firmware estimates)
+  const channelRows = [
+    {
+      label: "CH1",
+      currentMa: statusLocalMa,
+      dacCode: status?.raw.dac_code_ch1 ?? null,
+      fetTempMc: status?.raw.fet_temp_ch1_mc ?? null,
+    },
+    {
+      label: "CH2",
+      currentMa: statusRemoteMa,
+      dacCode: status?.raw.dac_code_ch2 ?? null,
+      fetTempMc: status?.raw.fet_temp_ch2_mc ?? null,
+    },
+  ];
+  const channelWarnings = status?.channel_warnings_decoded ?? [];
+
+  // Charge / energy counters (analog-side, reset via POST /counters/reset)
+  const hasCounters = status?.raw.charge_uah != null;
+  const chargeAh =
+    status?.raw.charge_uah != null ? status.raw.charge_uah / 1e6 : null;
+  const energyWh =
+    status?.raw.energy_uwh != null ? status.raw.energy_uwh / 1e6 : null;
+  const formatDuration = (ms: number | undefined) => {
+    if (ms == null) return "-";
+    const totalSeconds = Math.floor(ms / 1000);
+    const hh = Math.floor(totalSeconds / 3600);
+    const mm = Math.floor((totalSeconds % 3600) / 60);
+    const ss = totalSeconds % 60;
+    return `${hh}:${String(mm).padStart(2, "0")}:${String(ss).padStart(2, "0")}`;
+  };
+
+  const countersResetMutation = useMutation({
+    mutationFn: async () => {
+      if (!baseUrl) {
+        throw new Error("Device base URL is not available");
+      }
+      return postCountersReset(baseUrl);
+    },
+    onSuccess: () => {
+      void statusQuery.refetch();
+    },
+  });
+  const countersResetError = (() => {
+    const err = countersResetMutation.error;
+    if (!err) return null;
+    return isHttpApiError(err)
+      ? `Counter reset failed: ${formatHttpApiErrorSummary(err)}`
+      : `Counter reset failed: ${err.message}`;
+  })();
+
   const pdSummary = (() => {
     if (pd) {
       const attached = pd.attached;
@@ -423,6 +483,115 @@ export function DeviceStatusRoute() {
         </div>
       </div>
 
+      {/* Channel sharing card */}
+      <div className="ll-panel bg-base-100 shadow-sm border border-base-200">
+        <div className="ll-panel-body p-6">
+          <div className="flex flex-wrap items-center gap-3">
+            <h3 className="ll-panel-title text-sm uppercase tracking-wider text-base-content/50 h-auto min-h-0">
+              Channel Sharing
+            </h3>
+            <div className="ll-badge ll-badge-ghost">
+              Policy: {status?.raw.share_policy ?? "-"}
+            </div>
+            {channelWarnings.length > 0 ? (
+              channelWarnings.map((warning) => (
+                <div key={warning} className="ll-badge ll-badge-warning">
+                  {warning}
+                </div>
+              ))
+            ) : (
+              <div className="ll-badge ll-badge-success">Balanced</div>
+            )}
+          </div>
+
+          <div className="mt-4 grid grid-cols-2 gap-4 text-sm">
+            {channelRows.map((row) => (
+              <div key={row.label} className="bg-base-200/50 p-2 rounded">
+                <div className="text-xs text-base-content/60 mb-1">
+                  {row.label}
+                </div>
+                <div className="font-medium">
+                  {row.currentMa != null
+                    ? `${(row.currentMa / 1000).toFixed(3)} A`
+                    : "-"}
+                </div>
+                <div className="flex justify-between text-xs mt-1">
+                  <span className="text-base-content/60">DAC</span>
+                  <span>{row.dacCode ?? "-"}</span>
+                </div>
+                <div className="flex justify-between text-xs mt-1">
+                  <span className="text-base-content/60">FET (est.)</span>
+                  <span>
+                    {row.fetTempMc != null
+                      ? `${(row.fetTempMc / 1000).toFixed(1)} °C`
+                      : "-"}
+                  </span>
+                </div>
+              </div>
+            ))}
+          </div>
+        </div>
+      </div>
+
+      {/* Charge / energy counters card */}
+      <div className="ll-panel bg-base-100 shadow-sm border border-base-200">
+        <div className="ll-panel-body p-6">
+          <div className="flex flex-wrap items-center gap-3">
+            <h3 className="ll-panel-title text-sm uppercase tracking-wider text-base-content/50 h-auto min-h-0">
+              Counters
+            </h3>
+            {hasCounters ? null : (
+              <div className="ll-badge ll-badge-ghost">UNSUPPORTED</div>
+            )}
+            <div className="ml-auto">
+              <button
+                type="button"
+                className="ll-button ll-button-sm ll-button-outline"
+                disabled={!hasCounters || countersResetMutation.isPending}
+                onClick={() => {
+                  countersResetMutation.reset();
+                  countersResetMutation.mutate();
+                }}
+              >
+                Reset
+              </button>
+            </div>
+          </div>
+
+          <div className="mt-4 grid grid-cols-2 md:grid-cols-4 gap-4 text-sm">
+            <div>
+              <div className="text-xs text-base-content/60">Charge</div>
+              <div className="font-medium">
+                {chargeAh != null ? `${chargeAh.toFixed(4)} Ah` : "-"}
+              </div>
+            </div>
+            <div>
+              <div className="text-xs text-base-content/60">Energy</div>
+              <div className="font-medium">
+                {energyWh != null ? `${energyWh.toFixed(3)} Wh` : "-"}
+              </div>
+            </div>
+            <div>
+              <div className="text-xs text-base-content/60">Since reset</div>
+              <div className="font-medium">
+                {formatDuration(status?.raw.counter_elapsed_ms)}
+              </div>
+            </div>
+            <div>
+              <div className="text-xs text-base-content/60">Output on</div>
+              <div className="font-medium">
+                {formatDuration(status?.raw.counter_on_ms)}
+              </div>
+            </div>
+          </div>
+          {countersResetError ? (
+            <div className="mt-3 ll-alert ll-alert-error shadow-sm text-xs sm:text-sm">
+              <span>{countersResetError}</span>
+            </div>
+          ) : null}
+        </div>
+      </div>
+
       {/* PD summary card with secondary entry */}
       <div className="ll-panel bg-base-100 shadow-sm border border-base-200">
         <div className="ll-panel-body p-6">